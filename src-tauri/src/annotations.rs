@@ -0,0 +1,108 @@
+// --- 擷取檔註記與書籤 ---
+// 檢視擷取檔時可以在特定 frame (依 frame 序號) 加上文字註記或標成書籤，例如標出開始出錯的那個 report。
+// 註記存在擷取檔旁的 <擷取檔>.notes.json，擷取檔本身維持只追加寫入，錄製中也可以註記。
+// post-capture hook 複製 / 移動擷取檔時一併處理註記檔；匯出 pcapng 時註記寫進對應封包的註解。
+//
+// { "version": 1, "notes": [ { "index": 42, "text": "從這裡開始逾時", "bookmark": true, "updated_ms": 0 } ] }
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const FORMAT_VERSION: u32 = 1;
+pub const NOTES_SUFFIX: &str = ".notes.json";
+// 註記文字的長度上限 (位元組)
+pub const MAX_TEXT_LEN: usize = 4096;
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Annotation {
+    // 擷取檔中的 frame 序號
+    pub index: u64,
+    pub text: String,
+    pub bookmark: bool,
+    pub updated_ms: i64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct NotesFile {
+    version: u32,
+    notes: Vec<Annotation>,
+}
+
+pub fn notes_file(capture_file: &Path) -> PathBuf {
+    let mut name = capture_file.as_os_str().to_os_string();
+    name.push(NOTES_SUFFIX);
+    PathBuf::from(name)
+}
+
+// 沒有註記檔時為空 (依 frame 序號排序)
+pub fn load(capture_file: &str) -> Result<Vec<Annotation>, String> {
+    let file = notes_file(Path::new(capture_file));
+    if !file.exists() { return Ok(Vec::new()); }
+    let text = std::fs::read_to_string(&file).map_err(|e| format!("讀取 {} 失敗: {}", file.display(), e))?;
+    let parsed: NotesFile = serde_json::from_str(&text).map_err(|e| format!("註記檔格式錯誤 ({}): {}", file.display(), e))?;
+    Ok(parsed.notes)
+}
+
+// 先寫入暫存檔再改名，寫到一半中斷時不會破壞原本的註記
+fn save(capture_file: &str, notes: &[Annotation]) -> Result<(), String> {
+    let file = notes_file(Path::new(capture_file));
+    if notes.is_empty() {
+        if file.exists() { std::fs::remove_file(&file).map_err(|e| format!("刪除 {} 失敗: {}", file.display(), e))?; }
+        return Ok(());
+    }
+    let text = serde_json::to_string_pretty(&NotesFile { version: FORMAT_VERSION, notes: notes.to_vec() }).map_err(|e| e.to_string())?;
+    let temp = file.with_extension("tmp");
+    std::fs::write(&temp, text).map_err(|e| format!("寫入 {} 失敗: {}", temp.display(), e))?;
+    std::fs::rename(&temp, &file).map_err(|e| format!("寫入 {} 失敗: {}", file.display(), e))
+}
+
+// 已讀取過的註記 (擷取檔 -> 註記)，同時確保同一個註記檔不會同時被兩個指令改寫
+#[derive(Default)]
+pub struct AnnotationStore {
+    files: HashMap<String, Vec<Annotation>>,
+}
+
+impl AnnotationStore {
+    fn notes(&mut self, capture_file: &str) -> Result<&mut Vec<Annotation>, String> {
+        if !self.files.contains_key(capture_file) {
+            let notes = load(capture_file)?;
+            self.files.insert(capture_file.to_string(), notes);
+        }
+        Ok(self.files.get_mut(capture_file).unwrap())
+    }
+
+    // 取代該 frame 原有的註記；text 為空且不是書籤時移除，回傳 None
+    pub fn annotate(&mut self, capture_file: &str, index: u64, text: &str, bookmark: bool) -> Result<Option<Annotation>, String> {
+        let text = text.trim();
+        if text.len() > MAX_TEXT_LEN {
+            return Err(format!("註記不可超過 {} 位元組", MAX_TEXT_LEN));
+        }
+        let notes = self.notes(capture_file)?;
+        let mut updated = notes.clone();
+        updated.retain(|n| n.index != index);
+        let annotation = (!text.is_empty() || bookmark).then(|| Annotation {
+            index,
+            text: text.to_string(),
+            bookmark,
+            updated_ms: chrono::Local::now().timestamp_millis(),
+        });
+        if let Some(annotation) = &annotation {
+            let pos = updated.partition_point(|n| n.index < index);
+            updated.insert(pos, annotation.clone());
+        }
+        save(capture_file, &updated)?;
+        *notes = updated;
+        Ok(annotation)
+    }
+
+    pub fn list(&mut self, capture_file: &str, bookmarks_only: bool) -> Result<Vec<Annotation>, String> {
+        let notes = self.notes(capture_file)?;
+        Ok(notes.iter().filter(|n| n.bookmark || !bookmarks_only).cloned().collect())
+    }
+
+    // 擷取檔被新的錄製取代 (或被移動) 時丟掉快取，下次重新讀檔
+    pub fn forget(&mut self, capture_file: &str) {
+        self.files.remove(capture_file);
+    }
+}
@@ -0,0 +1,154 @@
+// --- 網路介面的存取控制 ---
+// 對外的管理介面 (目前為服務的本機 HTTP) 在處理請求前先確認身分與權限範圍。
+// 可以組合的驗證方式：
+//   api_keys    靜態 API key，以 "Authorization: Bearer <key>" 或 "X-Api-Key: <key>" 帶入，
+//               每把 key 有自己的權限範圍
+//   same_user   連線端必須與服務以同一個 OS 使用者執行 (Linux 由 /proc/net/tcp 查詢連線的擁有者)
+// 都沒有設定時維持原本只限本機連線的行為 (不檢查身分，擁有全部權限)。
+// 尚未支援 mTLS (用戶端憑證)：管理介面只提供明文 HTTP，專案中也沒有 TLS 函式庫，
+// 因此無法以憑證確認連線端身分，API key 也以明文傳送。需要跨主機存取時請放在負責 TLS / mTLS 的反向代理之後，
+// 並只讓服務監聽本機。
+//
+// 權限範圍由小到大，較大的範圍包含較小的：
+//   read      查詢狀態與記錄
+//   control   重新載入、停止、借出與歸還設備
+// 管理介面沒有韌體更新等改寫設備的端點，因此沒有對應的權限範圍。
+//
+// "auth": { "same_user": true,
+//           "api_keys": [ { "name": "dashboard", "key": "...", "scope": "read" },
+//                         { "name": "ci", "key": "...", "scope": "control" } ] }
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Control,
+}
+
+impl Scope {
+    pub fn name(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Control => "control",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ApiKey {
+    pub name: String,
+    pub key: String,
+    pub scope: Scope,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub api_keys: Vec<ApiKey>,
+    pub same_user: bool,
+}
+
+// 請求帶來的身分資訊
+#[derive(Default)]
+pub struct Credentials {
+    pub api_key: Option<String>,
+    // 連線端的 OS 使用者 (取不到時為 None)
+    pub peer_uid: Option<u32>,
+}
+
+// 通過驗證的身分
+pub struct Principal {
+    // 使用的 API key 名稱 (未設定 key 時為 None)
+    pub name: Option<String>,
+    pub scope: Scope,
+}
+
+// 長度不同時直接回傳 false；相同長度時比較時間與內容無關
+fn same_key(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+impl AuthConfig {
+    // 從 API key 中挑一把至少有 scope 權限的 (GUI 呼叫服務時使用)
+    pub fn key_for(&self, scope: Scope) -> Option<&ApiKey> {
+        self.api_keys.iter().filter(|k| k.scope >= scope).min_by_key(|k| k.scope)
+    }
+
+    // 回傳 Err((HTTP 狀態碼, 原因))
+    pub fn authenticate(&self, credentials: &Credentials) -> Result<Principal, (u16, String)> {
+        if self.same_user {
+            let own = current_uid().ok_or((403, "此平台不支援確認連線的使用者".to_string()))?;
+            if credentials.peer_uid != Some(own) {
+                return Err((403, "連線端與服務不是同一個使用者".into()));
+            }
+        }
+        if self.api_keys.is_empty() {
+            return Ok(Principal { name: None, scope: Scope::Control });
+        }
+        let given = credentials.api_key.as_deref().ok_or((401, "需要 API key".to_string()))?;
+        self.api_keys.iter()
+            .find(|k| same_key(&k.key, given))
+            .map(|k| Principal { name: Some(k.name.clone()), scope: k.scope })
+            .ok_or((401, "無效的 API key".into()))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, k) in self.api_keys.iter().enumerate() {
+            if k.key.len() < 16 { return Err(format!("API key {} 太短 (至少 16 個字元)", k.name)); }
+            if self.api_keys[..i].iter().any(|other| other.key == k.key) {
+                return Err(format!("API key {} 與其他 key 重複", k.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Principal {
+    pub fn require(&self, scope: Scope) -> Result<(), (u16, String)> {
+        if self.scope >= scope { return Ok(()); }
+        let name = self.name.as_deref().unwrap_or("-");
+        Err((403, format!("API key {} 沒有 {} 權限", name, scope.name())))
+    }
+}
+
+// --- 連線端的 OS 使用者 ---
+
+#[cfg(target_os = "linux")]
+pub fn current_uid() -> Option<u32> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("Uid:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn current_uid() -> Option<u32> {
+    None
+}
+
+// /proc/net/tcp 的位址格式: 主機位元組順序的 16 進位 IPv4 與連接埠，例如 127.0.0.1:8080 為 0100007F:1F90
+#[cfg(target_os = "linux")]
+fn proc_addr(addr: &std::net::SocketAddr) -> Option<String> {
+    match addr {
+        std::net::SocketAddr::V4(v4) => Some(format!("{:08X}:{:04X}", u32::from_ne_bytes(v4.ip().octets()), v4.port())),
+        std::net::SocketAddr::V6(_) => None,
+    }
+}
+
+// 找出本機連線的另一端 socket 的擁有者
+#[cfg(target_os = "linux")]
+pub fn peer_uid(stream: &std::net::TcpStream) -> Option<u32> {
+    let (peer, local) = (proc_addr(&stream.peer_addr().ok()?)?, proc_addr(&stream.local_addr().ok()?)?);
+    let table = std::fs::read_to_string("/proc/net/tcp").ok()?;
+    table.lines().skip(1).find_map(|line| {
+        // sl local_address rem_address st tx_queue:rx_queue tr:tm->when retrnsmt uid ...
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        (cols.len() > 7 && cols[1] == peer && cols[2] == local).then(|| cols[7].parse().ok()).flatten()
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn peer_uid(_stream: &std::net::TcpStream) -> Option<u32> {
+    None
+}
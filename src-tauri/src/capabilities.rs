@@ -0,0 +1,56 @@
+// --- 每個設備的 UI 能力清單 ---
+// 依協定偵測與 descriptor 內容決定前端要顯示哪些面板。
+
+use crate::descriptor::{ReportDescriptor, ReportKind};
+use crate::protocols::{DeviceIdentity, ProtocolPlugin};
+use crate::{firmware, scale, telephony};
+use serde::Serialize;
+
+const PAGE_SENSOR: u16 = 0x20;
+
+#[derive(Serialize, Clone)]
+pub struct CapabilityManifest {
+    // 自動偵測到的協定插件
+    pub protocol: Option<&'static str>,
+    pub panels: Vec<&'static str>,
+}
+
+fn has_page(desc: Option<&ReportDescriptor>, page: u16) -> bool {
+    desc.is_some_and(|d| d.fields.iter().any(|f| f.usage_page == page))
+}
+
+pub fn compute(
+    identity: &DeviceIdentity,
+    desc: Option<&ReportDescriptor>,
+    plugin: Option<&dyn ProtocolPlugin>,
+) -> CapabilityManifest {
+    // 原始 hex 主控台對所有設備都適用
+    let mut panels = vec!["console"];
+
+    if desc.is_some_and(|d| d.fields.iter().any(|f| f.kind == ReportKind::Feature)) {
+        panels.push("feature_reports");
+    }
+    if has_page(desc, PAGE_SENSOR) || identity.usage_page == PAGE_SENSOR {
+        panels.push("sensors");
+    }
+    if has_page(desc, telephony::PAGE_TELEPHONY) {
+        panels.push("telephony");
+    }
+    if identity.usage_page == scale::PAGE_SCALE {
+        panels.push("scale");
+    }
+    // 設備目前處於可辨識的 bootloader
+    if firmware::detect(identity).is_some() {
+        panels.push("firmware_update");
+    }
+    if let Some(p) = plugin {
+        if p.profile_storage().is_some() { panels.push("profiles"); }
+        if p.headset().is_some() { panels.push("headset"); }
+        if p.lighting().is_some() { panels.push("rgb"); }
+    }
+
+    CapabilityManifest {
+        protocol: plugin.map(|p| p.name()),
+        panels,
+    }
+}
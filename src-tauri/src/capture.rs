@@ -0,0 +1,486 @@
+// --- 流量擷取 (session recording) ---
+// start_capture 之後，所有經過設備 I/O 執行緒送出 (tx) 與收到 (rx) 的 report 連同方向、設備路徑與時間戳記
+// 寫入檔案，長時間的錄製不必放在記憶體中。I/O 執行緒只把 frame 放進佇列，由每個 session 自己的背景執行緒
+// 寫檔，磁碟變慢時不會拖住設備讀寫 (佇列滿時丟棄並計數)。獨佔操作 (韌體更新、CTAPHID 等) 不經過
+// I/O 執行緒，不會被記錄。
+//
+// 檔案為只追加寫入的 JSON Lines，每秒至少 flush 一次；中途當機時已寫入的 frame 仍可讀取 (只是沒有 end 行)：
+//   {"type":"header","version":1,"session":"cap-1","path_filter":null,"filter":"rx and id == 3","started_ms":0,"clock":{...},"app_version":"..."}
+//   {"type":"frame","index":0,"dir":"tx","path":"...","mono_us":1234,"wall_us":0,"data":"0a0102"}
+//   {"type":"end","frames":1,"dropped":0,"stopped_ms":0}
+// mono_us 以 clock.monotonic_epoch 為起點，wall_us 為 Unix 微秒 (UTC)。
+// 常駐的流量記錄 (見 traffic_log.rs) 使用同一個寫檔流程，另外依大小 / 時間輪替成多個擷取檔。
+// 兩者都可帶過濾條件 (見 capture_filter.rs)，不符合的 frame 在放進佇列前就略過。
+
+use crate::capture_filter::CaptureFilter;
+use crate::clock::{CaptureClock, ClockMetadata};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::iter::Peekable;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const FORMAT_VERSION: u32 = 1;
+// 寫檔執行緒來不及時最多暫存的 frame 數
+const QUEUE_LEN: usize = 100_000;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    // 主機 -> 設備
+    Tx,
+    // 設備 -> 主機
+    Rx,
+}
+
+pub fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(text: &str) -> Result<Vec<u8>, String> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() { return Err(format!("無效的十六進位資料: {}", text)); }
+    (0..text.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|_| format!("無效的十六進位資料: {}", text)))
+        .collect()
+}
+
+fn serialize_hex<S: Serializer>(data: &[u8], s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&to_hex(data))
+}
+
+fn deserialize_hex<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+    from_hex(&String::deserialize(d)?).map_err(serde::de::Error::custom)
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Frame {
+    // session 內的序號 (從 0 開始；佇列滿而丟棄的 frame 會留下空號)
+    pub index: u64,
+    pub dir: Direction,
+    pub path: String,
+    pub mono_us: u64,
+    pub wall_us: i64,
+    #[serde(serialize_with = "serialize_hex", deserialize_with = "deserialize_hex")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct CaptureHeader {
+    pub version: u32,
+    pub session: String,
+    pub path_filter: Option<String>,
+    // 過濾條件 (舊版擷取檔沒有此欄位)
+    #[serde(default)]
+    pub filter: Option<String>,
+    pub started_ms: i64,
+    pub clock: ClockMetadata,
+    pub app_version: String,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct CaptureEnd {
+    // 實際寫入的 frame 數
+    pub frames: u64,
+    pub dropped: u64,
+    pub stopped_ms: i64,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Line {
+    Header(CaptureHeader),
+    Frame(Frame),
+    End(CaptureEnd),
+}
+
+// 每一行都以 type 欄位開頭，不解析整行就能判斷種類 (見 capture_history.rs)
+pub const FRAME_LINE_PREFIX: &str = r#"{"type":"frame","#;
+pub const END_LINE_PREFIX: &str = r#"{"type":"end","#;
+
+pub fn parse_frame(line: &str) -> Result<Frame, String> {
+    match serde_json::from_str(line) {
+        Ok(Line::Frame(frame)) => Ok(frame),
+        Ok(_) => Err("不是 frame 行".into()),
+        Err(e) => Err(format!("擷取檔格式錯誤: {}", e)),
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct CaptureInfo {
+    pub id: String,
+    pub file: String,
+    // None 代表記錄所有設備
+    pub path_filter: Option<String>,
+    pub filter: Option<String>,
+    pub started_ms: i64,
+    // 已放進寫檔佇列的 frame 數
+    pub frames: u64,
+    pub dropped: u64,
+    // 不符合過濾條件而略過的 frame 數
+    pub filtered: u64,
+    // 寫檔失敗的原因 (停止時才回報；失敗後不再寫入)
+    pub error: Option<String>,
+}
+
+enum Message {
+    Frame(Frame),
+    Finish { dropped: u64, reply: Sender<Option<String>> },
+}
+
+// --- 寫檔執行緒 ---
+
+// 流量記錄 (traffic log) 的輪替條件：目前的檔案超過 max_size 位元組或開啟超過 max_age 時換新檔，
+// 目錄中只保留最新的 max_files 個記錄檔
+#[derive(Clone)]
+pub struct Rotation {
+    pub dir: PathBuf,
+    pub max_size: u64,
+    pub max_age: Option<Duration>,
+    pub max_files: usize,
+}
+
+pub const TRAFFIC_PREFIX: &str = "traffic-";
+pub const CAPTURE_EXTENSION: &str = "jsonl";
+// 輪替時開新檔失敗後，隔多久再試
+const ROTATE_RETRY: Duration = Duration::from_secs(60);
+
+fn rotated_file(dir: &Path) -> PathBuf {
+    dir.join(format!("{}{}.{}", TRAFFIC_PREFIX, chrono::Local::now().format("%Y%m%d-%H%M%S-%3f"), CAPTURE_EXTENSION))
+}
+
+// 由舊到新 (檔名含時間，依名稱排序即可)
+pub fn traffic_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut files: Vec<PathBuf> = entries.flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            let name = p.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            name.starts_with(TRAFFIC_PREFIX) && p.extension().is_some_and(|e| e == CAPTURE_EXTENSION)
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn prune(dir: &Path, max_files: usize) {
+    let files = traffic_files(dir);
+    for file in &files[..files.len().saturating_sub(max_files)] {
+        let _ = std::fs::remove_file(file);
+        let _ = std::fs::remove_file(crate::annotations::notes_file(file));
+    }
+}
+
+struct Writer {
+    out: BufWriter<File>,
+    // 目前這個檔案的 frame 數與大小
+    written: u64,
+    bytes: u64,
+    opened: Instant,
+    error: Option<String>,
+    last_flush: Instant,
+    // 輪替時新檔沿用的 header
+    header: CaptureHeader,
+    rotation: Option<Rotation>,
+}
+
+impl Writer {
+    fn create(file: &Path, header: CaptureHeader, rotation: Option<Rotation>) -> Result<Self, String> {
+        let handle = OpenOptions::new().append(true).create_new(true).open(file)
+            .map_err(|e| format!("建立擷取檔 {} 失敗: {}", file.display(), e))?;
+        let first = Line::Header(header.clone());
+        let mut writer = Writer {
+            out: BufWriter::new(handle),
+            written: 0,
+            bytes: 0,
+            opened: Instant::now(),
+            error: None,
+            last_flush: Instant::now(),
+            header,
+            rotation,
+        };
+        writer.write_line(&first);
+        writer.flush();
+        match writer.error.take() {
+            Some(e) => Err(e),
+            None => Ok(writer),
+        }
+    }
+
+    fn write_line(&mut self, line: &Line) {
+        if self.error.is_some() { return; }
+        let result = serde_json::to_vec(line).map_err(|e| e.to_string()).and_then(|mut bytes| {
+            bytes.push(b'\n');
+            self.out.write_all(&bytes).map_err(|e| e.to_string())?;
+            self.bytes += bytes.len() as u64;
+            Ok(())
+        });
+        if let Err(e) = result { self.error = Some(format!("寫入擷取檔失敗: {}", e)); }
+    }
+
+    fn flush(&mut self) {
+        if self.error.is_none() {
+            if let Err(e) = self.out.flush() { self.error = Some(format!("寫入擷取檔失敗: {}", e)); }
+        }
+        self.last_flush = Instant::now();
+    }
+
+    fn write_end(&mut self, dropped: u64) {
+        let end = CaptureEnd { frames: self.written, dropped, stopped_ms: chrono::Local::now().timestamp_millis() };
+        self.write_line(&Line::End(end));
+        self.flush();
+    }
+
+    fn rotation_due(&self) -> bool {
+        let Some(rotation) = &self.rotation else { return false };
+        if self.error.is_some() { return self.opened.elapsed() >= ROTATE_RETRY; }
+        self.written > 0 && (self.bytes >= rotation.max_size || rotation.max_age.is_some_and(|age| self.opened.elapsed() >= age))
+    }
+
+    // 結束目前的檔案並換新檔；失敗時維持錯誤狀態，稍後再試
+    fn rotate(&mut self) {
+        let Some(rotation) = self.rotation.clone() else { return };
+        self.write_end(0);
+        let header = CaptureHeader { started_ms: chrono::Local::now().timestamp_millis(), ..self.header.clone() };
+        match Writer::create(&rotated_file(&rotation.dir), header, Some(rotation.clone())) {
+            Ok(next) => {
+                *self = next;
+                prune(&rotation.dir, rotation.max_files);
+            }
+            Err(e) => {
+                self.error = Some(e);
+                self.opened = Instant::now();
+            }
+        }
+    }
+
+    fn run(mut self, rx: Receiver<Message>) {
+        loop {
+            match rx.recv_timeout(FLUSH_INTERVAL) {
+                Ok(Message::Frame(frame)) => {
+                    if self.rotation_due() { self.rotate(); }
+                    self.write_line(&Line::Frame(frame));
+                    if self.error.is_none() { self.written += 1; }
+                    if self.last_flush.elapsed() >= FLUSH_INTERVAL { self.flush(); }
+                }
+                Ok(Message::Finish { dropped, reply }) => {
+                    self.write_end(dropped);
+                    let _ = reply.send(self.error);
+                    return;
+                }
+                Err(RecvTimeoutError::Timeout) => self.flush(),
+                // session 被丟棄 (不應發生)：保留已寫入的內容
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.flush();
+                    return;
+                }
+            }
+        }
+    }
+}
+
+// --- 進行中的擷取 ---
+
+pub struct ActiveCapture {
+    info: CaptureInfo,
+    filter: Option<CaptureFilter>,
+    queue: SyncSender<Message>,
+}
+
+impl ActiveCapture {
+    // 寫入 end 行並等待寫檔執行緒結束
+    pub fn finish(self) -> CaptureInfo {
+        let (reply, done) = mpsc::channel();
+        let mut info = self.info;
+        let finished = self.queue.send(Message::Finish { dropped: info.dropped, reply }).is_ok();
+        info.error = match finished {
+            true => done.recv().unwrap_or(Some("寫檔執行緒異常結束".into())),
+            false => Some("寫檔執行緒異常結束".into()),
+        };
+        info
+    }
+}
+
+#[derive(Default)]
+pub struct CaptureRecorder {
+    next_id: u64,
+    sessions: Vec<ActiveCapture>,
+    // 本次執行中建立過的 session ID -> 擷取檔
+    files: HashMap<String, String>,
+    // 常駐的流量記錄 (與 session 分開，不出現在 list 中)
+    traffic_log: Option<ActiveCapture>,
+}
+
+fn header(info: &CaptureInfo, clock: ClockMetadata) -> CaptureHeader {
+    CaptureHeader {
+        version: FORMAT_VERSION,
+        session: info.id.clone(),
+        path_filter: info.path_filter.clone(),
+        filter: info.filter.clone(),
+        started_ms: info.started_ms,
+        clock,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+    }
+}
+
+fn spawn(info: CaptureInfo, filter: Option<CaptureFilter>, writer: Writer) -> ActiveCapture {
+    let (queue, rx) = mpsc::sync_channel(QUEUE_LEN);
+    thread::spawn(move || writer.run(rx));
+    ActiveCapture { info, filter, queue }
+}
+
+impl CaptureRecorder {
+    pub fn start(&mut self, path_filter: Option<String>, filter: Option<CaptureFilter>, file: &str, clock: ClockMetadata) -> Result<CaptureInfo, String> {
+        if self.sessions.iter().any(|s| s.info.file == file) { return Err(format!("{} 已在擷取中", file)); }
+        self.next_id += 1;
+        let info = CaptureInfo {
+            id: format!("cap-{}", self.next_id),
+            file: file.to_string(),
+            path_filter,
+            filter: filter.as_ref().map(|f| f.text().to_string()),
+            started_ms: chrono::Local::now().timestamp_millis(),
+            frames: 0,
+            dropped: 0,
+            filtered: 0,
+            error: None,
+        };
+        let writer = Writer::create(Path::new(file), header(&info, clock), None)?;
+        self.files.insert(info.id.clone(), info.file.clone());
+        self.sessions.push(spawn(info.clone(), filter, writer));
+        Ok(info)
+    }
+
+    // 開始常駐的流量記錄 (已在記錄時先 take_traffic_log 停止)；檔案放在 rotation.dir
+    pub fn start_traffic_log(&mut self, rotation: Rotation, filter: Option<CaptureFilter>, clock: ClockMetadata) -> Result<(), String> {
+        if self.traffic_log.is_some() { return Err("流量記錄已在執行中".into()); }
+        std::fs::create_dir_all(&rotation.dir).map_err(|e| format!("建立目錄 {} 失敗: {}", rotation.dir.display(), e))?;
+        let file = rotated_file(&rotation.dir);
+        let info = CaptureInfo {
+            id: "traffic".into(),
+            file: file.to_string_lossy().to_string(),
+            path_filter: None,
+            filter: filter.as_ref().map(|f| f.text().to_string()),
+            started_ms: chrono::Local::now().timestamp_millis(),
+            frames: 0,
+            dropped: 0,
+            filtered: 0,
+            error: None,
+        };
+        let writer = Writer::create(&file, header(&info, clock), Some(rotation.clone()))?;
+        prune(&rotation.dir, rotation.max_files);
+        self.traffic_log = Some(spawn(info, filter, writer));
+        Ok(())
+    }
+
+    pub fn take_traffic_log(&mut self) -> Option<ActiveCapture> {
+        self.traffic_log.take()
+    }
+
+    pub fn traffic_log_active(&self) -> bool {
+        self.traffic_log.is_some()
+    }
+
+    // 匯出等操作的 session 參數可以是本次執行中的 session ID，或之前留下的擷取檔路徑
+    pub fn resolve(&self, session: &str) -> Result<String, String> {
+        if let Some(file) = self.files.get(session) { return Ok(file.clone()); }
+        if std::path::Path::new(session).is_file() { return Ok(session.to_string()); }
+        Err(format!("找不到擷取: {}", session))
+    }
+
+    // id 為 None 時取出唯一一個進行中的擷取
+    pub fn remove(&mut self, id: Option<&str>) -> Result<ActiveCapture, String> {
+        let index = match id {
+            Some(id) => self.sessions.iter().position(|s| s.info.id == id).ok_or(format!("找不到進行中的擷取: {}", id))?,
+            None if self.sessions.len() == 1 => 0,
+            None if self.sessions.is_empty() => return Err("沒有進行中的擷取".into()),
+            None => return Err("有多個進行中的擷取，請指定 session_id".into()),
+        };
+        Ok(self.sessions.remove(index))
+    }
+
+    pub fn remove_all(&mut self) -> Vec<ActiveCapture> {
+        std::mem::take(&mut self.sessions)
+    }
+
+    pub fn list(&self) -> Vec<CaptureInfo> {
+        self.sessions.iter().map(|s| s.info.clone()).collect()
+    }
+
+    fn record(&mut self, path: &str, dir: Direction, data: &[u8], mono_us: u64, wall_us: i64) {
+        for session in self.sessions.iter_mut().chain(self.traffic_log.as_mut()) {
+            if session.info.path_filter.as_deref().is_some_and(|p| p != path) { continue; }
+            if session.filter.as_ref().is_some_and(|f| !f.matches(path, dir, data)) {
+                session.info.filtered += 1;
+                continue;
+            }
+            let frame = Frame { index: session.info.frames + session.info.dropped, dir, path: path.to_string(), mono_us, wall_us, data: data.to_vec() };
+            match session.queue.try_send(Message::Frame(frame)) {
+                Ok(()) => session.info.frames += 1,
+                Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => session.info.dropped += 1,
+            }
+        }
+    }
+}
+
+pub struct Captures(pub Mutex<CaptureRecorder>);
+
+// 由設備的 I/O 執行緒呼叫；沒有進行中的擷取時直接返回
+pub fn record(app: &AppHandle, path: &str, dir: Direction, data: &[u8]) {
+    let captures = app.state::<Captures>();
+    let mut recorder = captures.0.lock().unwrap();
+    if recorder.sessions.is_empty() && recorder.traffic_log.is_none() { return; }
+    let mono_us = app.state::<CaptureClock>().mono_us();
+    recorder.record(path, dir, data, mono_us, chrono::Utc::now().timestamp_micros());
+}
+
+
+// --- 讀取擷取檔 ---
+
+// 依序讀出 frame；end 行讀到後存在 end (檔案未正常結束時為 None)
+pub struct CaptureReader {
+    pub header: CaptureHeader,
+    pub end: Option<CaptureEnd>,
+    lines: Peekable<Lines<BufReader<File>>>,
+    line_no: usize,
+}
+
+pub fn open(file: &str) -> Result<CaptureReader, String> {
+    let handle = File::open(file).map_err(|e| format!("開啟擷取檔 {} 失敗: {}", file, e))?;
+    let mut lines = BufReader::new(handle).lines().peekable();
+    let first = lines.next().ok_or("擷取檔是空的")?.map_err(|e| format!("讀取擷取檔失敗: {}", e))?;
+    let header = match serde_json::from_str(&first) {
+        Ok(Line::Header(header)) => header,
+        _ => return Err(format!("{} 不是擷取檔", file)),
+    };
+    if header.version > FORMAT_VERSION { return Err(format!("不支援的擷取檔版本: {}", header.version)); }
+    Ok(CaptureReader { header, end: None, lines, line_no: 1 })
+}
+
+impl Iterator for CaptureReader {
+    type Item = Result<Frame, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(format!("讀取擷取檔失敗: {}", e))),
+            };
+            self.line_no += 1;
+            if line.trim().is_empty() { continue; }
+            match serde_json::from_str(&line) {
+                Ok(Line::Frame(frame)) => return Some(Ok(frame)),
+                Ok(Line::End(end)) => self.end = Some(end),
+                Ok(Line::Header(_)) => return Some(Err(format!("第 {} 行: 重複的 header", self.line_no))),
+                // 當機時最後一行可能只寫了一半，視為檔案結束
+                Err(_) if self.lines.peek().is_none() => return None,
+                Err(e) => return Some(Err(format!("第 {} 行格式錯誤: {}", self.line_no, e))),
+            }
+        }
+    }
+}
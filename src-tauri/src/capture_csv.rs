@@ -0,0 +1,169 @@
+// --- 擷取檔轉 CSV ---
+// 每個 report 一列：序號、時間、方向、設備、Report ID、長度、十六進位內容，之後每個解碼欄位一欄。
+// 解碼器依序取用：options.schema 指定的 schema、設備目前套用的 schema、設備的 report descriptor
+// (rx 以 input report、tx 以 output report 解碼)；設備沒有開啟時只能以 options.schema 解碼，
+// Report ID 欄也只在能判斷時填入。
+// 欄位名稱在第一次讀檔時收集 (依出現順序)，第二次讀檔才逐列寫出，不需要整份放進記憶體。
+// time 為 RFC 3339 本地時間 (含時區偏移)。第一行是 '#' 開頭的時鐘資訊 (擷取檔標頭的 ClockMetadata)，讀取時可略過。
+//
+// # clock source=both monotonic_epoch=2026-03-15T02:20:00.000000+00:00 utc_offset_minutes=480 resolution_ns=100
+// index,time,mono_us,direction,path,report_id,length,data,X,Y,Buttons
+// 0,2026-03-15T10:20:30.123456+08:00,1234,rx,...,1,8,01 00 10 ...,16,-2,1 0 0
+
+use crate::capture::{self, Direction, Frame};
+use crate::descriptor::{FieldValue, ReportDescriptor, ReportKind};
+use crate::schema::ReportSchema;
+use chrono::TimeZone;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct CsvOptions {
+    // false 時只輸出原始資料
+    pub decode: bool,
+    // 以已載入的 schema 解碼所有設備
+    pub schema: Option<String>,
+    // 只輸出這個設備的 report
+    pub path: Option<String>,
+    pub direction: Option<Direction>,
+    // 歐系語系的 Excel 常用 ';'
+    pub delimiter: char,
+    // 加上 UTF-8 BOM，讓 Excel 正確辨識中文欄位名稱
+    pub bom: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self { decode: true, schema: None, path: None, direction: None, delimiter: ',', bom: false }
+    }
+}
+
+// 一個設備的解碼方式 (都沒有時不解碼)
+#[derive(Default)]
+pub struct DeviceDecoder {
+    // 設備沒有開啟時不知道是否使用 Report ID
+    pub uses_report_ids: Option<bool>,
+    pub schema: Option<Arc<ReportSchema>>,
+    pub descriptor: Option<ReportDescriptor>,
+}
+
+impl DeviceDecoder {
+    fn decode(&self, frame: &Frame) -> (Option<u8>, Vec<(String, String)>) {
+        let id_byte = self.uses_report_ids.filter(|ids| *ids).and_then(|_| frame.data.first().copied());
+        if let Some(decoded) = self.schema.as_ref().and_then(|s| s.decode(&frame.data)) {
+            let fields = decoded.values.into_iter().map(|v| (v.name, v.display)).collect();
+            return (decoded.report_id.or(id_byte), fields);
+        }
+        let kind = match frame.dir {
+            Direction::Rx => ReportKind::Input,
+            Direction::Tx => ReportKind::Output,
+        };
+        match self.descriptor.as_ref().and_then(|d| d.decode(kind, &frame.data)) {
+            Some(decoded) => {
+                let fields = decoded.fields.into_iter().map(|(name, value)| (name, field_text(&value))).collect();
+                (self.uses_report_ids.filter(|ids| *ids).map(|_| decoded.report_id), fields)
+            }
+            None => (id_byte, Vec::new()),
+        }
+    }
+}
+
+fn field_text(value: &FieldValue) -> String {
+    match value {
+        FieldValue::Number(n) => n.to_string(),
+        FieldValue::List(values) => values.iter().map(i64::to_string).collect::<Vec<_>>().join(" "),
+        FieldValue::Usages(usages) => usages.join(" "),
+    }
+}
+
+fn quote(text: &str, delimiter: char) -> String {
+    if text.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct CsvExport {
+    pub rows: u64,
+    // 解碼欄位的欄名
+    pub fields: Vec<String>,
+}
+
+pub fn write(capture_file: &str, file: &str, options: &CsvOptions, decoder_for: impl Fn(&str) -> DeviceDecoder) -> Result<CsvExport, String> {
+    if matches!(options.delimiter, '"' | ' ' | '\n' | '\r') || options.delimiter.is_ascii_alphanumeric() {
+        return Err(format!("無效的分隔字元: {:?}", options.delimiter));
+    }
+    let mut decoders: HashMap<String, DeviceDecoder> = HashMap::new();
+    let wanted = |frame: &Frame| {
+        options.path.as_ref().is_none_or(|p| *p == frame.path) && options.direction.is_none_or(|d| d == frame.dir)
+    };
+
+    // 第一次讀檔：收集解碼欄位名稱
+    let mut columns: Vec<String> = Vec::new();
+    if options.decode {
+        for frame in capture::open(capture_file)? {
+            let frame = frame?;
+            if !wanted(&frame) { continue; }
+            let decoder = decoders.entry(frame.path.clone()).or_insert_with(|| decoder_for(&frame.path));
+            for (name, _) in decoder.decode(&frame).1 {
+                if !columns.contains(&name) { columns.push(name); }
+            }
+        }
+    }
+    let column_index: HashMap<&str, usize> = columns.iter().enumerate().map(|(i, c)| (c.as_str(), i)).collect();
+
+    let handle = File::create(file).map_err(|e| format!("建立 {} 失敗: {}", file, e))?;
+    let mut out = BufWriter::new(handle);
+    let io_error = |e: std::io::Error| format!("寫入 {} 失敗: {}", file, e);
+    let sep = options.delimiter.to_string();
+    if options.bom { out.write_all("\u{feff}".as_bytes()).map_err(io_error)?; }
+    writeln!(out, "# {}", capture::open(capture_file)?.header.clock.summary()).map_err(io_error)?;
+    let header: Vec<String> = ["index", "time", "mono_us", "direction", "path", "report_id", "length", "data"].iter()
+        .map(|s| s.to_string())
+        .chain(columns.iter().map(|c| quote(c, options.delimiter)))
+        .collect();
+    writeln!(out, "{}", header.join(&sep)).map_err(io_error)?;
+
+    let mut rows = 0;
+    for frame in capture::open(capture_file)? {
+        let frame = frame?;
+        if !wanted(&frame) { continue; }
+        let (report_id, fields) = match options.decode {
+            true => decoders.entry(frame.path.clone()).or_insert_with(|| decoder_for(&frame.path)).decode(&frame),
+            false => (None, Vec::new()),
+        };
+        let time = chrono::Local.timestamp_micros(frame.wall_us).single()
+            .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Micros, false))
+            .unwrap_or_default();
+        let direction = match frame.dir {
+            Direction::Tx => "tx",
+            Direction::Rx => "rx",
+        };
+        let data = frame.data.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+        let mut row = vec![
+            frame.index.to_string(),
+            time,
+            frame.mono_us.to_string(),
+            direction.to_string(),
+            quote(&frame.path, options.delimiter),
+            report_id.map(|id| id.to_string()).unwrap_or_default(),
+            frame.data.len().to_string(),
+            data,
+        ];
+        let mut values = vec![String::new(); columns.len()];
+        for (name, value) in fields {
+            if let Some(&i) = column_index.get(name.as_str()) { values[i] = quote(&value, options.delimiter); }
+        }
+        row.extend(values);
+        writeln!(out, "{}", row.join(&sep)).map_err(io_error)?;
+        rows += 1;
+    }
+    out.flush().map_err(io_error)?;
+    Ok(CsvExport { rows, fields: columns })
+}
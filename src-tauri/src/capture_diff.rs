@@ -0,0 +1,261 @@
+// --- 比較兩個擷取檔 ---
+// 例如同一套測試流程在新舊韌體上各錄一次，找出設備行為不同的地方。兩種對齊方式：
+//   sequence  依 frame 順序逐一比較 (方向與內容都相同才算相符)；遇到不同時在往後 RESYNC_WINDOW 個 frame 內
+//             找最近的重新對齊點，中間多出來的 frame 記為 added (只在 b) / missing (只在 a)，
+//             方向相同的一對記為 changed
+//   request   只看送往設備的 request 與它的回覆 (同一設備上某個 tx 之後收到的第一個 rx，同 replay.rs)，
+//             依 request 內容配對 (相同內容依出現順序)：回覆不同為 changed、只在 a 為 missing、只在 b 為 added；
+//             設備主動送出的 report 不比較
+// 擷取檔中有多個設備時可用 path_a / path_b 各自指定 (兩次錄製的設備路徑通常不同)；
+// 計數器、時間戳記之類每次都會變的位元組以 ignore_offsets 略過。
+//
+// { "align": "request", "path_a": null, "path_b": null, "direction": null, "ignore_offsets": [7] }
+
+use crate::capture::{self, Direction, Frame};
+use crate::replay;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+// 每個擷取檔最多讀入的 frame 數
+const MAX_FRAMES: usize = 500_000;
+// 最多保留的差異筆數 (超過時只計數)
+const MAX_DIFFERENCES: usize = 1000;
+// sequence 對齊時往後尋找重新對齊點的範圍
+const RESYNC_WINDOW: usize = 32;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffAlign {
+    #[default]
+    Sequence,
+    Request,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct DiffOptions {
+    pub align: DiffAlign,
+    // 只比較這個設備的 frame
+    pub path_a: Option<String>,
+    pub path_b: Option<String>,
+    // 只比較 tx 或 rx (sequence 對齊時)
+    pub direction: Option<Direction>,
+    pub ignore_offsets: Vec<usize>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    // 只在 b
+    Added,
+    // 只在 a
+    Missing,
+    Changed,
+}
+
+#[derive(Serialize, Clone)]
+pub struct FrameDiff {
+    pub kind: DiffKind,
+    // 兩邊擷取檔中的 frame 序號 (request 對齊時為回覆的序號，沒有回覆時為 request 的序號)
+    pub index_a: Option<u64>,
+    pub index_b: Option<u64>,
+    pub dir: Direction,
+    pub data_a: Option<Vec<u8>>,
+    pub data_b: Option<Vec<u8>>,
+    // request 對齊時的 request 內容
+    pub request: Option<Vec<u8>>,
+    // changed 時不同的位元組位置 (不含 ignore_offsets；長度不同時超出較短一方的位置也算)
+    pub offsets: Vec<usize>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DiffResult {
+    pub align: DiffAlign,
+    // 參與比較的 frame 數 (request 對齊時為 request 數)
+    pub total_a: usize,
+    pub total_b: usize,
+    pub matched: usize,
+    pub added: usize,
+    pub missing: usize,
+    pub changed: usize,
+    pub differences: Vec<FrameDiff>,
+}
+
+impl DiffResult {
+    fn new(align: DiffAlign, total_a: usize, total_b: usize) -> Self {
+        Self { align, total_a, total_b, matched: 0, added: 0, missing: 0, changed: 0, differences: Vec::new() }
+    }
+
+    fn record(&mut self, diff: FrameDiff) {
+        match diff.kind {
+            DiffKind::Added => self.added += 1,
+            DiffKind::Missing => self.missing += 1,
+            DiffKind::Changed => self.changed += 1,
+        }
+        if self.differences.len() < MAX_DIFFERENCES { self.differences.push(diff); }
+    }
+}
+
+fn changed_offsets(a: &[u8], b: &[u8], ignore: &[usize]) -> Vec<usize> {
+    (0..a.len().max(b.len()))
+        .filter(|i| !ignore.contains(i) && a.get(*i) != b.get(*i))
+        .collect()
+}
+
+fn load(file: &str, path: Option<&str>, direction: Option<Direction>) -> Result<Vec<Frame>, String> {
+    let mut frames = Vec::new();
+    for frame in capture::open(file)? {
+        let frame = frame?;
+        if path.is_some_and(|p| p != frame.path) || direction.is_some_and(|d| d != frame.dir) { continue; }
+        if frames.len() >= MAX_FRAMES {
+            return Err(format!("{} 的 frame 超過 {} 個，請以 path / direction 縮小比較範圍", file, MAX_FRAMES));
+        }
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+pub fn diff(file_a: &str, file_b: &str, options: &DiffOptions) -> Result<DiffResult, String> {
+    let direction = options.direction.filter(|_| options.align == DiffAlign::Sequence);
+    let a = load(file_a, options.path_a.as_deref(), direction)?;
+    let b = load(file_b, options.path_b.as_deref(), direction)?;
+    Ok(match options.align {
+        DiffAlign::Sequence => diff_sequence(&a, &b, &options.ignore_offsets),
+        DiffAlign::Request => diff_requests(&a, &b, &options.ignore_offsets),
+    })
+}
+
+// --- sequence 對齊 ---
+
+fn frame_diff(kind: DiffKind, a: Option<&Frame>, b: Option<&Frame>, ignore: &[usize]) -> FrameDiff {
+    let offsets = match (a, b) {
+        (Some(a), Some(b)) => changed_offsets(&a.data, &b.data, ignore),
+        _ => Vec::new(),
+    };
+    FrameDiff {
+        kind,
+        index_a: a.map(|f| f.index),
+        index_b: b.map(|f| f.index),
+        dir: a.or(b).map_or(Direction::Tx, |f| f.dir),
+        data_a: a.map(|f| f.data.clone()),
+        data_b: b.map(|f| f.data.clone()),
+        request: None,
+        offsets,
+    }
+}
+
+fn diff_sequence(a: &[Frame], b: &[Frame], ignore: &[usize]) -> DiffResult {
+    let same = |x: &Frame, y: &Frame| x.dir == y.dir && replay::same_response(&x.data, &y.data, ignore);
+    let mut result = DiffResult::new(DiffAlign::Sequence, a.len(), b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if same(&a[i], &b[j]) {
+            result.matched += 1;
+            i += 1;
+            j += 1;
+            continue;
+        }
+        // 最近的重新對齊點：兩邊略過的 frame 總數最少者
+        let resync = (1..=2 * RESYNC_WINDOW)
+            .flat_map(|total| (0..=total).map(move |k| (k, total - k)))
+            .filter(|&(k, l)| k <= RESYNC_WINDOW && l <= RESYNC_WINDOW)
+            .find(|&(k, l)| i + k < a.len() && j + l < b.len() && same(&a[i + k], &b[j + l]));
+        // 找不到時把目前這一對當成不同，兩邊各前進一個
+        let (k, l) = resync.unwrap_or((1, 1));
+        let (skipped_a, skipped_b) = (&a[i..i + k], &b[j..j + l]);
+        for n in 0..k.max(l) {
+            match (skipped_a.get(n), skipped_b.get(n)) {
+                (Some(x), Some(y)) if x.dir == y.dir => result.record(frame_diff(DiffKind::Changed, Some(x), Some(y), ignore)),
+                (x, y) => {
+                    if let Some(x) = x { result.record(frame_diff(DiffKind::Missing, Some(x), None, ignore)); }
+                    if let Some(y) = y { result.record(frame_diff(DiffKind::Added, None, Some(y), ignore)); }
+                }
+            }
+        }
+        i += k;
+        j += l;
+    }
+    for x in &a[i..] { result.record(frame_diff(DiffKind::Missing, Some(x), None, ignore)); }
+    for y in &b[j..] { result.record(frame_diff(DiffKind::Added, None, Some(y), ignore)); }
+    result
+}
+
+// --- request 對齊 ---
+
+struct Request<'a> {
+    tx: &'a Frame,
+    response: Option<&'a Frame>,
+}
+
+fn requests(frames: &[Frame]) -> Vec<Request<'_>> {
+    let mut requests: Vec<Request> = Vec::new();
+    // 每個設備最近一次 tx 在 requests 中的位置
+    let mut last_tx: HashMap<&str, usize> = HashMap::new();
+    for frame in frames {
+        match frame.dir {
+            Direction::Tx => {
+                last_tx.insert(&frame.path, requests.len());
+                requests.push(Request { tx: frame, response: None });
+            }
+            Direction::Rx => {
+                if let Some(request) = last_tx.get(frame.path.as_str()).map(|&r| &mut requests[r]) {
+                    if request.response.is_none() { request.response = Some(frame); }
+                }
+            }
+        }
+    }
+    requests
+}
+
+fn request_diff(kind: DiffKind, a: Option<&Request>, b: Option<&Request>, ignore: &[usize]) -> FrameDiff {
+    // 有回覆時比較回覆，沒有回覆時以 request 本身代表
+    let shown = |r: &Request| r.response.unwrap_or(r.tx).index;
+    let response = |r: &Request| r.response.map(|f| f.data.clone());
+    let offsets = match (a.and_then(|r| r.response), b.and_then(|r| r.response)) {
+        (Some(x), Some(y)) => changed_offsets(&x.data, &y.data, ignore),
+        _ => Vec::new(),
+    };
+    FrameDiff {
+        kind,
+        index_a: a.map(shown),
+        index_b: b.map(shown),
+        dir: if a.or(b).is_some_and(|r| r.response.is_some()) { Direction::Rx } else { Direction::Tx },
+        data_a: a.and_then(response),
+        data_b: b.and_then(response),
+        request: a.or(b).map(|r| r.tx.data.clone()),
+        offsets,
+    }
+}
+
+fn diff_requests(a: &[Frame], b: &[Frame], ignore: &[usize]) -> DiffResult {
+    let (a, b) = (requests(a), requests(b));
+    let mut result = DiffResult::new(DiffAlign::Request, a.len(), b.len());
+    // b 中尚未配對的 request (依內容分組，依出現順序)
+    let mut unmatched: HashMap<&[u8], VecDeque<usize>> = HashMap::new();
+    for (n, request) in b.iter().enumerate() {
+        unmatched.entry(request.tx.data.as_slice()).or_default().push_back(n);
+    }
+    let mut paired = vec![false; b.len()];
+    for request in &a {
+        let Some(n) = unmatched.get_mut(request.tx.data.as_slice()).and_then(VecDeque::pop_front) else {
+            result.record(request_diff(DiffKind::Missing, Some(request), None, ignore));
+            continue;
+        };
+        paired[n] = true;
+        let other = &b[n];
+        let same = match (request.response, other.response) {
+            (Some(x), Some(y)) => replay::same_response(&x.data, &y.data, ignore),
+            (None, None) => true,
+            _ => false,
+        };
+        if same {
+            result.matched += 1;
+        } else {
+            result.record(request_diff(DiffKind::Changed, Some(request), Some(other), ignore));
+        }
+    }
+    for (request, _) in b.iter().zip(&paired).filter(|(_, paired)| !**paired) {
+        result.record(request_diff(DiffKind::Added, None, Some(request), ignore));
+    }
+    result
+}
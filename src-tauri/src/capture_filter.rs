@@ -0,0 +1,264 @@
+// --- 擷取過濾條件 ---
+// 擷取與常駐流量記錄可帶一個過濾運算式，在設備的 I/O 執行緒中逐 frame 判斷，不符合的 frame 不會
+// 複製、進佇列或寫入檔案 (也不佔用 frame 序號)，長時間錄製會大量送出 report 的設備時只留下需要的部分。
+// 運算式在開始擷取時就解析完成，判斷時只比較位元組。
+//
+// 條件 (op 為 == != < <= > >=，數值可為十進位或 0x 開頭的十六進位)：
+//   tx / rx                      方向 (也可寫成 dir == tx)
+//   path == "<設備路徑>"          也支援 != 與 contains，例: path contains "mi_02"
+//   id <op> <值>                 Report ID，即 frame 的第一個位元組 (設備不使用 Report ID 時 tx 為 0x00)
+//   byte[N] <op> <值>            例: "byte[2] == 0x01"
+//   byte[N] & <遮罩> <op> <值>   例: "byte[3] & 0xF0 == 0x20"
+//   len <op> <值>                report 長度
+//   matches <pattern>            格式同 expect 的 pattern，例: "matches 01 ?? A*"、"matches 80/C0"
+// 以 and (&&)、or (||)、not (!) 與括號組合，and 優先於 or：
+//   rx and (id == 0x03 or id == 0x04) and not matches 03 00 00
+//   path contains "mi_01" and byte[1] & 0x80 != 0
+
+use crate::capture::Direction;
+use crate::sequence::Condition;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Dir(Direction),
+    Path { op: PathOp, value: String },
+    Data(Condition),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum PathOp { Eq, Ne, Contains }
+
+impl Expr {
+    fn eval(&self, path: &str, dir: Direction, data: &[u8]) -> bool {
+        match self {
+            Expr::Dir(d) => *d == dir,
+            Expr::Path { op: PathOp::Eq, value } => path == value,
+            Expr::Path { op: PathOp::Ne, value } => path != value,
+            Expr::Path { op: PathOp::Contains, value } => path.contains(value.as_str()),
+            Expr::Data(condition) => condition.eval(data),
+            Expr::Not(inner) => !inner.eval(path, dir, data),
+            Expr::And(a, b) => a.eval(path, dir, data) && b.eval(path, dir, data),
+            Expr::Or(a, b) => a.eval(path, dir, data) || b.eval(path, dir, data),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub struct CaptureFilter {
+    text: String,
+    expr: Expr,
+}
+
+impl CaptureFilter {
+    pub fn matches(&self, path: &str, dir: Direction, data: &[u8]) -> bool {
+        self.expr.eval(path, dir, data)
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl From<CaptureFilter> for String {
+    fn from(filter: CaptureFilter) -> Self {
+        filter.text
+    }
+}
+
+impl TryFrom<String> for CaptureFilter {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        let tokens = tokenize(&text)?;
+        if tokens.is_empty() { return Err("過濾條件是空的".into()); }
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("過濾條件中多餘的內容: {}", token.text()));
+        }
+        Ok(Self { text, expr })
+    }
+}
+
+// --- 解析 ---
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    // 條件的一部分 (引號字串保留引號)
+    Word(String),
+}
+
+impl Token {
+    fn text(&self) -> &str {
+        match self {
+            Token::Open => "(",
+            Token::Close => ")",
+            Token::And => "and",
+            Token::Or => "or",
+            Token::Not => "not",
+            Token::Word(word) => word,
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => { chars.next(); }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '"' => {
+                chars.next();
+                let mut word = String::from('"');
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("過濾條件中的引號沒有結束".into()),
+                    }
+                }
+                word.push('"');
+                tokens.push(Token::Word(word));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '"') { break; }
+                    word.push(c);
+                    chars.next();
+                }
+                match word.as_str() {
+                    "and" | "&&" => tokens.push(Token::And),
+                    "or" | "||" => tokens.push(Token::Or),
+                    "not" | "!" => tokens.push(Token::Not),
+                    // !tx、!matches ... (但不是 != 運算子)
+                    w if w.starts_with('!') && !w.starts_with("!=") => {
+                        tokens.push(Token::Not);
+                        tokens.push(Token::Word(w[1..].to_string()));
+                    }
+                    _ => tokens.push(Token::Word(word)),
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next_is(&self, token: &Token) -> bool {
+        self.tokens.get(self.pos) == Some(token)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.next_is(&Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while self.next_is(&Token::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Not) => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::Open) => {
+                self.pos += 1;
+                let expr = self.or()?;
+                if !self.next_is(&Token::Close) { return Err("過濾條件的括號沒有對應的 ')'".into()); }
+                self.pos += 1;
+                Ok(expr)
+            }
+            _ => self.atom(),
+        }
+    }
+
+    // 連續的 Word 組成一個條件
+    fn atom(&mut self) -> Result<Expr, String> {
+        let mut words = Vec::new();
+        while let Some(Token::Word(word)) = self.tokens.get(self.pos) {
+            words.push(word.as_str());
+            self.pos += 1;
+        }
+        match self.tokens.get(self.pos) {
+            _ if !words.is_empty() => parse_atom(&words.join(" ")),
+            Some(token) => Err(format!("過濾條件中不應出現 {}", token.text())),
+            None => Err("過濾條件不完整".into()),
+        }
+    }
+}
+
+fn parse_direction(text: &str) -> Option<Direction> {
+    match text {
+        "tx" => Some(Direction::Tx),
+        "rx" => Some(Direction::Rx),
+        _ => None,
+    }
+}
+
+fn unquote(text: &str) -> &str {
+    text.strip_prefix('"').and_then(|t| t.strip_suffix('"')).unwrap_or(text)
+}
+
+fn parse_atom(text: &str) -> Result<Expr, String> {
+    if let Some(dir) = parse_direction(text) { return Ok(Expr::Dir(dir)); }
+    if let Some(rest) = text.strip_prefix("dir") {
+        let rest = rest.trim();
+        let (negate, value) = match (rest.strip_prefix("=="), rest.strip_prefix("!=")) {
+            (Some(value), _) => (false, value),
+            (_, Some(value)) => (true, value),
+            _ => return Err(format!("dir 只能以 == 或 != 比較: {}", text)),
+        };
+        let dir = parse_direction(unquote(value.trim())).ok_or(format!("方向必須是 tx 或 rx: {}", text))?;
+        return Ok(if negate { Expr::Not(Box::new(Expr::Dir(dir))) } else { Expr::Dir(dir) });
+    }
+    if let Some(rest) = text.strip_prefix("path") {
+        let rest = rest.trim();
+        let (op, value) = if let Some(value) = rest.strip_prefix("==") {
+            (PathOp::Eq, value)
+        } else if let Some(value) = rest.strip_prefix("!=") {
+            (PathOp::Ne, value)
+        } else if let Some(value) = rest.strip_prefix("contains") {
+            (PathOp::Contains, value)
+        } else {
+            return Err(format!("path 只能以 ==、!= 或 contains 比較: {}", text));
+        };
+        let value = unquote(value.trim());
+        if value.is_empty() { return Err(format!("缺少設備路徑: {}", text)); }
+        return Ok(Expr::Path { op, value: value.to_string() });
+    }
+    if let Some(rest) = text.strip_prefix("id").filter(|r| r.trim_start().starts_with(['=', '!', '<', '>'])) {
+        return Ok(Expr::Data(Condition::try_from(format!("byte[0] {}", rest.trim()))?));
+    }
+    Ok(Expr::Data(Condition::try_from(text.to_string())?))
+}
@@ -0,0 +1,153 @@
+// --- 擷取歷史分頁 ---
+// 前端以虛擬捲動顯示擷取檔 (包含錄製中的擷取與流量記錄檔) 時，依 offset / limit 一次只取一頁，
+// 數百萬個 frame 也不需要整份經過 IPC 傳送。frame 本身留在擷取檔中，後端只為每個檔案保存
+// 每個 frame 行的開始位置 (每個 frame 8 位元組)，取一頁時直接 seek 到該位置讀出。
+// 索引在第一次查詢時建立，之後每次查詢只往後讀新寫入的完整行，錄製中的擷取也能持續往下捲動
+// (寫檔執行緒每秒 flush 一次，最新的 frame 最多延遲一秒出現)。
+// 可帶過濾條件 (見 capture_filter.rs)，總數與 offset 都以符合條件的 frame 計算；每組 (檔案, 條件)
+// 各有一份索引，只保留最近使用的 MAX_INDICES 份。
+//
+// { "total": 1843921, "offset": 1000, "complete": false, "frames": [ { "index": 1000, "dir": "rx", ... } ] }
+
+use crate::capture::{self, Frame};
+use crate::capture_filter::CaptureFilter;
+use crate::paging;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+
+const MAX_INDICES: usize = 8;
+
+#[derive(Serialize, Clone)]
+pub struct CapturePage {
+    // 符合條件的 frame 總數 (目前為止)
+    pub total: usize,
+    pub offset: usize,
+    pub frames: Vec<Frame>,
+    // 擷取檔已寫入結尾 (不會再增加)
+    pub complete: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct CaptureCount {
+    pub total: usize,
+    pub complete: bool,
+}
+
+struct FrameIndex {
+    file: String,
+    filter: Option<CaptureFilter>,
+    // 每個 (符合條件的) frame 行的開始位置
+    offsets: Vec<u64>,
+    // 已建立索引的位元組數 (只包含完整的行)
+    scanned: u64,
+    complete: bool,
+}
+
+impl FrameIndex {
+    fn new(file: &str, filter: Option<CaptureFilter>) -> Self {
+        Self { file: file.to_string(), filter, offsets: Vec::new(), scanned: 0, complete: false }
+    }
+
+    fn filter_text(&self) -> Option<&str> {
+        self.filter.as_ref().map(CaptureFilter::text)
+    }
+
+    // 從上次的位置往後讀；檔案變短代表被取代，重新建立
+    fn refresh(&mut self) -> Result<(), String> {
+        if self.complete { return Ok(()); }
+        let io_error = |e: std::io::Error| format!("讀取擷取檔 {} 失敗: {}", self.file, e);
+        let mut handle = File::open(&self.file).map_err(io_error)?;
+        let len = handle.metadata().map_err(io_error)?.len();
+        if len < self.scanned {
+            self.offsets.clear();
+            self.scanned = 0;
+        }
+        if self.scanned == 0 {
+            // 確認是擷取檔 (header 與版本)
+            capture::open(&self.file)?;
+        }
+        handle.seek(SeekFrom::Start(self.scanned)).map_err(io_error)?;
+        let mut reader = BufReader::new(handle);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line).map_err(io_error)?;
+            // 還沒寫完的最後一行等下次再讀
+            if read == 0 || !line.ends_with('\n') { break; }
+            let start = self.scanned;
+            self.scanned += read as u64;
+            if line.starts_with(capture::END_LINE_PREFIX) {
+                self.complete = true;
+                break;
+            }
+            if !line.starts_with(capture::FRAME_LINE_PREFIX) { continue; }
+            // 無法解析的行不列入索引，總數與分頁都不包含它
+            let Ok(frame) = capture::parse_frame(&line) else { continue };
+            if self.filter.as_ref().is_some_and(|f| !f.matches(&frame.path, frame.dir, &frame.data)) { continue; }
+            self.offsets.push(start);
+        }
+        Ok(())
+    }
+
+    fn page(&self, offset: usize, limit: usize) -> Result<Vec<Frame>, String> {
+        let len = self.offsets.len();
+        let wanted = &self.offsets[offset.min(len)..offset.saturating_add(limit).min(len)];
+        let Some(&first) = wanted.first() else { return Ok(Vec::new()) };
+        let io_error = |e: std::io::Error| format!("讀取擷取檔 {} 失敗: {}", self.file, e);
+        let mut handle = File::open(&self.file).map_err(io_error)?;
+        handle.seek(SeekFrom::Start(first)).map_err(io_error)?;
+        let mut reader = BufReader::new(handle);
+        let mut position = first;
+        let mut frames = Vec::with_capacity(wanted.len());
+        let mut line = String::new();
+        for &start in wanted {
+            // 有過濾條件時中間可能隔著不符合的行
+            if start != position {
+                reader.seek(SeekFrom::Start(start)).map_err(io_error)?;
+                position = start;
+            }
+            line.clear();
+            position += reader.read_line(&mut line).map_err(io_error)? as u64;
+            frames.push(capture::parse_frame(line.trim_end())?);
+        }
+        Ok(frames)
+    }
+}
+
+#[derive(Default)]
+pub struct CaptureHistory {
+    // 最近使用的在最後
+    indices: Vec<FrameIndex>,
+}
+
+impl CaptureHistory {
+    fn index(&mut self, file: &str, filter: Option<CaptureFilter>) -> Result<&mut FrameIndex, String> {
+        let text = filter.as_ref().map(|f| f.text().to_string());
+        let index = match self.indices.iter().position(|i| i.file == file && i.filter_text() == text.as_deref()) {
+            Some(pos) => self.indices.remove(pos),
+            None => FrameIndex::new(file, filter),
+        };
+        if self.indices.len() >= MAX_INDICES { self.indices.remove(0); }
+        self.indices.push(index);
+        let index = self.indices.last_mut().unwrap();
+        index.refresh()?;
+        Ok(index)
+    }
+
+    pub fn page(&mut self, file: &str, filter: Option<CaptureFilter>, offset: usize, limit: usize) -> Result<CapturePage, String> {
+        let limit = limit.clamp(1, paging::MAX_PAGE_SIZE);
+        let index = self.index(file, filter)?;
+        Ok(CapturePage { total: index.offsets.len(), offset, frames: index.page(offset, limit)?, complete: index.complete })
+    }
+
+    pub fn count(&mut self, file: &str, filter: Option<CaptureFilter>) -> Result<CaptureCount, String> {
+        let index = self.index(file, filter)?;
+        Ok(CaptureCount { total: index.offsets.len(), complete: index.complete })
+    }
+
+    // 擷取檔被新的錄製取代時丟掉舊索引
+    pub fn forget(&mut self, file: &str) {
+        self.indices.retain(|i| i.file != file);
+    }
+}
@@ -0,0 +1,105 @@
+// --- Checksum / CRC ---
+// 廠商協定常見的校驗演算法，供分段傳輸、匯出與送出指令時自動附加使用。
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Checksum {
+    None,
+    // 所有位元組 XOR
+    Xor,
+    // 所有位元組相加取低 8 位元
+    Sum8,
+    // poly 0x07, init 0x00
+    Crc8,
+    // CRC-16/CCITT-FALSE (poly 0x1021, init 0xFFFF)
+    Crc16,
+    // CRC-16/XMODEM (poly 0x1021, init 0x0000)
+    Crc16Xmodem,
+    // CRC-16/MODBUS (poly 0x8005 反射, init 0xFFFF)
+    Crc16Modbus,
+    // IEEE 802.3
+    Crc32,
+}
+
+fn crc16_ccitt(data: &[u8], init: u16) -> u16 {
+    data.iter().fold(init, |mut crc, b| {
+        crc ^= (*b as u16) << 8;
+        for _ in 0..8 { crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 }; }
+        crc
+    })
+}
+
+// 接續先前的 CRC-32 結果計算 (分段傳輸時不必重算全部資料)
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    !data.iter().fold(!crc, |mut crc, b| {
+        crc ^= *b as u32;
+        for _ in 0..8 { crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 }; }
+        crc
+    })
+}
+
+impl Checksum {
+    // 位元組數
+    pub fn width(&self) -> usize {
+        match self {
+            Checksum::None => 0,
+            Checksum::Xor | Checksum::Sum8 | Checksum::Crc8 => 1,
+            Checksum::Crc16 | Checksum::Crc16Xmodem | Checksum::Crc16Modbus => 2,
+            Checksum::Crc32 => 4,
+        }
+    }
+
+    pub fn compute(&self, data: &[u8]) -> u32 {
+        match self {
+            Checksum::None => 0,
+            Checksum::Xor => data.iter().fold(0u8, |a, b| a ^ b) as u32,
+            Checksum::Sum8 => data.iter().fold(0u8, |a, b| a.wrapping_add(*b)) as u32,
+            Checksum::Crc8 => data.iter().fold(0u8, |mut crc, b| {
+                crc ^= b;
+                for _ in 0..8 { crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 }; }
+                crc
+            }) as u32,
+            Checksum::Crc16 => crc16_ccitt(data, 0xFFFF) as u32,
+            Checksum::Crc16Xmodem => crc16_ccitt(data, 0x0000) as u32,
+            Checksum::Crc16Modbus => data.iter().fold(0xFFFFu16, |mut crc, b| {
+                crc ^= *b as u16;
+                for _ in 0..8 { crc = if crc & 1 != 0 { (crc >> 1) ^ 0xA001 } else { crc >> 1 }; }
+                crc
+            }) as u32,
+            Checksum::Crc32 => crc32_update(0, data),
+        }
+    }
+
+    // 依演算法寬度輸出位元組
+    pub fn encode(&self, value: u32, big_endian: bool) -> Vec<u8> {
+        let len = self.width();
+        if big_endian { value.to_be_bytes()[4 - len..].to_vec() } else { value.to_le_bytes()[..len].to_vec() }
+    }
+}
+
+// 送出前在資料後面附加 checksum
+// "checksum": { "algorithm": "crc16_modbus", "start": 1 } = 對 data[1..] 計算並以 little-endian 附加
+#[derive(Deserialize, Serialize, Clone)]
+pub struct AppendChecksum {
+    pub algorithm: Checksum,
+    // 計算範圍 data[start..end]；end 未指定代表到資料結尾
+    #[serde(default)]
+    pub start: usize,
+    #[serde(default)]
+    pub end: Option<usize>,
+    #[serde(default)]
+    pub big_endian: bool,
+}
+
+impl AppendChecksum {
+    pub fn apply(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let end = self.end.unwrap_or(data.len());
+        let range = data.get(self.start..end)
+            .ok_or(format!("checksum 範圍 {}..{} 超出資料長度 {}", self.start, end, data.len()))?;
+        let mut out = data.to_vec();
+        out.extend(self.algorithm.encode(self.algorithm.compute(range), self.big_endian));
+        Ok(out)
+    }
+}
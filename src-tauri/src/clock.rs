@@ -0,0 +1,123 @@
+// --- 擷取時間戳 ---
+// 解碼事件附帶的時間戳可選擇 monotonic (程式啟動後經過的時間，不受系統校時影響)、
+// wall clock (UTC)，或兩者都附。匯出檔的標頭以 ClockMetadata 記錄時鐘資訊，
+// 方便與其他儀器的資料對齊。
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampSource {
+    Monotonic,
+    Wall,
+    #[default]
+    Both,
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct Timestamp {
+    // 相對於 monotonic_epoch 的微秒數
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mono_us: Option<u64>,
+    // Unix 微秒 (UTC)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wall_us: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ClockMetadata {
+    pub source: TimestampSource,
+    // monotonic 時間 0 對應的 UTC 時間 (RFC 3339)
+    pub monotonic_epoch: String,
+    // 本機時區與 UTC 的差 (分鐘)
+    pub utc_offset_minutes: i32,
+    // 量測到的 monotonic 時鐘最小刻度
+    pub resolution_ns: u64,
+    // 目前的 UTC 時間，用來檢查 wall clock 與 monotonic 之間的漂移
+    pub now: String,
+    pub now_mono_us: u64,
+}
+
+impl ClockMetadata {
+    // 匯出檔中的一行說明 (pcapng 註解、CSV 開頭)
+    pub fn summary(&self) -> String {
+        let source = match self.source {
+            TimestampSource::Monotonic => "monotonic",
+            TimestampSource::Wall => "wall",
+            TimestampSource::Both => "both",
+        };
+        format!(
+            "clock source={} monotonic_epoch={} utc_offset_minutes={} resolution_ns={}",
+            source, self.monotonic_epoch, self.utc_offset_minutes, self.resolution_ns,
+        )
+    }
+}
+
+pub struct CaptureClock {
+    source: Mutex<TimestampSource>,
+    epoch: Instant,
+    epoch_wall: DateTime<Utc>,
+    resolution: Duration,
+}
+
+impl Default for CaptureClock {
+    fn default() -> Self { Self::new() }
+}
+
+// 連續讀取時鐘直到數值改變，取多次中最小的差距
+fn measure_resolution() -> Duration {
+    (0..16)
+        .map(|_| {
+            let start = Instant::now();
+            loop {
+                let now = Instant::now();
+                if now > start { return now - start; }
+            }
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+impl CaptureClock {
+    pub fn new() -> Self {
+        Self {
+            source: Mutex::new(TimestampSource::default()),
+            epoch: Instant::now(),
+            epoch_wall: Utc::now(),
+            resolution: measure_resolution(),
+        }
+    }
+
+    pub fn set_source(&self, source: TimestampSource) {
+        *self.source.lock().unwrap() = source;
+    }
+
+    // 不受 set_source 影響 (擷取檔固定記錄兩種時間)
+    pub fn mono_us(&self) -> u64 {
+        self.epoch.elapsed().as_micros() as u64
+    }
+
+    pub fn now(&self) -> Timestamp {
+        let source = *self.source.lock().unwrap();
+        let mono = source != TimestampSource::Wall;
+        let wall = source != TimestampSource::Monotonic;
+        Timestamp {
+            mono_us: mono.then(|| self.mono_us()),
+            wall_us: wall.then(|| Utc::now().timestamp_micros()),
+        }
+    }
+
+    pub fn metadata(&self) -> ClockMetadata {
+        ClockMetadata {
+            source: *self.source.lock().unwrap(),
+            monotonic_epoch: self.epoch_wall.to_rfc3339(),
+            utc_offset_minutes: Local::now().offset().local_minus_utc() / 60,
+            resolution_ns: self.resolution.as_nanos() as u64,
+            now: Utc::now().to_rfc3339(),
+            now_mono_us: self.epoch.elapsed().as_micros() as u64,
+        }
+    }
+}
@@ -0,0 +1,307 @@
+// --- 由 report schema 產生 C / Rust 程式碼 ---
+// 每個 report 產生一個以位元組陣列保存的結構與各欄位的存取函式，
+// 位元組順序在存取函式中處理，因此與編譯器的 struct 排列及主機 endian 無關。
+//   C:    <schema>_report_<id>_t + static inline get/set 函式 (單一標頭檔)
+//   Rust: <Schema>Report<Id>([u8; LEN]) + 方法
+// 有 scale / bias 的欄位另外產生換算後的 double / f64 讀取函式。
+
+use crate::regmap::Encoding;
+use crate::schema::{ReportSchema, SchemaField, SchemaReport};
+use serde::Deserialize;
+use std::fmt::Write;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum CodeLanguage {
+    C,
+    Rust,
+}
+
+// C (含 C23) 與 Rust (含保留字) 的關鍵字
+const RESERVED: &[&str] = &[
+    // C
+    "alignas", "alignof", "auto", "bool", "break", "case", "char", "const", "constexpr", "continue", "default",
+    "do", "double", "else", "enum", "extern", "false", "float", "for", "goto", "if", "inline", "int", "long",
+    "nullptr", "register", "restrict", "return", "short", "signed", "sizeof", "static", "static_assert", "struct",
+    "switch", "thread_local", "true", "typedef", "typeof", "typeof_unqual", "union", "unsigned", "void", "volatile",
+    "while",
+    // Rust
+    "abstract", "as", "async", "await", "become", "box", "crate", "dyn", "final", "fn", "gen", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "self", "super", "trait",
+    "try", "type", "unsafe", "unsized", "use", "virtual", "where", "yield",
+];
+
+// 轉成小寫底線分隔的識別字
+fn snake(name: &str) -> String {
+    let mut out = String::new();
+    for c in name.chars() {
+        match c {
+            c if c.is_ascii_alphanumeric() => out.push(c.to_ascii_lowercase()),
+            _ if !out.ends_with('_') => out.push('_'),
+            _ => {}
+        }
+    }
+    let mut out = out.trim_matches('_').to_string();
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) { out.insert(0, 'f'); }
+    if RESERVED.contains(&out.as_str()) { out.push('_'); }
+    out
+}
+
+fn camel(name: &str) -> String {
+    snake(name).split('_')
+        .filter(|w| !w.is_empty())
+        .map(|w| w[..1].to_uppercase() + &w[1..])
+        .collect()
+}
+
+// 產生程式碼所需的欄位資訊
+struct Layout<'a> {
+    field: &'a SchemaField,
+    ident: String,
+    offset: usize,
+    width: usize,
+    // 由低位到高位的位元組位置
+    bytes: Vec<usize>,
+    signed: bool,
+    float: bool,
+    // 位元欄位 (位移, 位元數)
+    bits: Option<(u32, u32)>,
+    scaled: bool,
+}
+
+impl<'a> Layout<'a> {
+    fn new(field: &'a SchemaField) -> Result<Self, String> {
+        let e = field.encoding;
+        let width = e.width();
+        let mut bytes: Vec<usize> = (field.offset..field.offset + width).collect();
+        let big_endian = matches!(e, Encoding::U16be | Encoding::I16be | Encoding::U32be | Encoding::I32be | Encoding::F32be);
+        if big_endian { bytes.reverse(); }
+        let float = e.range().is_none();
+        let bits = field.bit_size.map(|size| (field.bit_offset.unwrap_or(0), size));
+        if float && bits.is_some() {
+            return Err(format!("{}: 浮點數欄位不能指定位元範圍", field.name));
+        }
+        Ok(Self {
+            field,
+            ident: snake(&field.name),
+            offset: field.offset,
+            width,
+            bytes,
+            signed: matches!(e, Encoding::I8 | Encoding::I16le | Encoding::I16be | Encoding::I32le | Encoding::I32be),
+            float,
+            bits,
+            scaled: field.scale != 1.0 || field.bias != 0.0,
+        })
+    }
+
+    // 讀取函式回傳的原始值位元數
+    fn value_bits(&self) -> usize {
+        match self.bits {
+            Some((_, size)) => [8, 16, 32].into_iter().find(|b| *b >= size as usize).unwrap_or(32),
+            None => self.width * 8,
+        }
+    }
+
+    fn mask(&self) -> u64 {
+        self.bits.map_or(0, |(_, size)| (1u64 << size) - 1)
+    }
+
+    fn raw_name(&self) -> String {
+        if self.scaled { format!("{}_raw", self.ident) } else { self.ident.clone() }
+    }
+}
+
+// 不同欄位名稱轉成識別字後可能相同 (例如 "X Axis" 與 "x-axis")，重複的依序加上 _2、_3 ...
+// 有 scale 的欄位另外佔用 <ident>_raw
+fn layouts(report: &SchemaReport) -> Result<Vec<Layout<'_>>, String> {
+    let mut out: Vec<Layout> = report.fields.iter().map(Layout::new).collect::<Result<_, _>>()?;
+    let mut taken: Vec<String> = Vec::new();
+    for l in &mut out {
+        let base = l.ident.clone();
+        let mut n = 1;
+        while taken.contains(&l.ident) || (l.scaled && taken.contains(&l.raw_name())) {
+            n += 1;
+            l.ident = format!("{}_{}", base, n);
+        }
+        taken.push(l.ident.clone());
+        if l.scaled { taken.push(l.raw_name()); }
+    }
+    Ok(out)
+}
+
+fn report_len(report: &SchemaReport) -> usize {
+    let fields = report.fields.iter().map(|f| f.offset + f.encoding.width()).max().unwrap_or(0);
+    fields.max(report.report_id.is_some() as usize)
+}
+
+fn report_suffix(report: &SchemaReport) -> String {
+    report.report_id.map_or(String::new(), |id| id.to_string())
+}
+
+// --- C ---
+
+fn c_type(bits: usize, signed: bool) -> String {
+    format!("{}int{}_t", if signed { "" } else { "u" }, bits)
+}
+
+fn c_report(out: &mut String, schema: &str, report: &SchemaReport) -> Result<(), String> {
+    let prefix = format!("{}_report_{}", snake(schema), report_suffix(report)).trim_end_matches('_').to_string();
+    let upper = prefix.to_uppercase();
+    let len = report_len(report);
+    let ty = format!("{}_t", prefix);
+
+    let _ = writeln!(out, "#define {}_LEN {}", upper, len);
+    if let Some(id) = report.report_id {
+        let _ = writeln!(out, "#define {}_ID 0x{:02X}", upper, id);
+    }
+    let _ = writeln!(out, "\ntypedef struct {{\n    uint8_t data[{}_LEN];\n}} {};\n", upper, ty);
+    if let Some(id) = report.report_id {
+        let _ = writeln!(out, "static inline void {}_init({} *r) {{\n    memset(r->data, 0, sizeof r->data);\n    r->data[0] = 0x{:02X};\n}}\n", prefix, ty, id);
+    }
+
+    for l in layouts(report)? {
+        let container = c_type(l.width * 8, false);
+        let load = l.bytes.iter().enumerate()
+            .map(|(i, b)| if i == 0 { format!("({})r->data[{}]", container, b) } else { format!("((({})r->data[{}]) << {})", container, b, i * 8) })
+            .collect::<Vec<_>>()
+            .join(" | ");
+        let store = |value: &str| -> String {
+            l.bytes.iter().enumerate()
+                .map(|(i, b)| format!("    r->data[{}] = (uint8_t)({} >> {});\n", b, value, i * 8))
+                .collect()
+        };
+        let raw = l.raw_name();
+        let value_ty = if l.float { "float".to_string() } else { c_type(l.value_bits(), l.signed && l.bits.is_none()) };
+
+        // 讀取
+        let _ = writeln!(out, "static inline {} {}_get_{}(const {} *r) {{", value_ty, prefix, raw, ty);
+        let _ = writeln!(out, "    {} u = {};", container, load);
+        match l.bits {
+            Some((shift, _)) => { let _ = writeln!(out, "    return ({})((u >> {}) & 0x{:X}u);", value_ty, shift, l.mask()); }
+            None if l.float => { let _ = writeln!(out, "    float v;\n    memcpy(&v, &u, sizeof v);\n    return v;"); }
+            None => { let _ = writeln!(out, "    return ({})u;", value_ty); }
+        }
+        let _ = writeln!(out, "}}\n");
+
+        // 寫入
+        let _ = writeln!(out, "static inline void {}_set_{}({} *r, {} v) {{", prefix, raw, ty, value_ty);
+        match l.bits {
+            Some((shift, _)) => {
+                let _ = writeln!(out, "    {} u = {};", container, load);
+                let _ = writeln!(out, "    u = (u & ~(({})0x{:X}u << {})) | ((({})v & 0x{:X}u) << {});", container, l.mask(), shift, container, l.mask(), shift);
+            }
+            None if l.float => { let _ = writeln!(out, "    {} u;\n    memcpy(&u, &v, sizeof u);", container); }
+            None => { let _ = writeln!(out, "    {} u = ({})v;", container, container); }
+        }
+        out.push_str(&store("u"));
+        let _ = writeln!(out, "}}\n");
+
+        if l.scaled {
+            let unit = l.field.unit.as_ref().map_or(String::new(), |u| format!(" /* {} */", u));
+            let _ = writeln!(out, "static inline double {}_get_{}(const {} *r) {{", prefix, l.ident, ty);
+            let _ = writeln!(out, "    return (double){}_get_{}(r) * {:?} + {:?};{}\n}}\n", prefix, raw, l.field.scale, l.field.bias, unit);
+        }
+        for (value, label) in &l.field.labels {
+            let _ = writeln!(out, "#define {}_{}_{} {}", upper, l.ident.to_uppercase(), snake(label).to_uppercase(), value);
+        }
+        if !l.field.labels.is_empty() { out.push('\n'); }
+    }
+    Ok(())
+}
+
+fn to_c(schema: &ReportSchema) -> Result<String, String> {
+    let guard = format!("{}_H", snake(&schema.name).to_uppercase());
+    let mut out = format!("// 由 report schema \"{}\" 產生，請勿手動修改\n\n#ifndef {}\n#define {}\n\n", schema.name, guard, guard);
+    out.push_str("#include <stdint.h>\n#include <string.h>\n\n");
+    for report in &schema.reports {
+        c_report(&mut out, &schema.name, report)?;
+    }
+    let _ = writeln!(out, "#endif // {}", guard);
+    Ok(out)
+}
+
+// --- Rust ---
+
+fn rust_type(bits: usize, signed: bool) -> String {
+    format!("{}{}", if signed { "i" } else { "u" }, bits)
+}
+
+fn rust_report(out: &mut String, schema: &str, report: &SchemaReport) -> Result<(), String> {
+    let name = format!("{}Report{}", camel(schema), report_suffix(report));
+    let upper = snake(&name).to_uppercase();
+    let len = report_len(report);
+
+    let _ = writeln!(out, "pub const {}_LEN: usize = {};", upper, len);
+    if let Some(id) = report.report_id {
+        let _ = writeln!(out, "pub const {}_ID: u8 = 0x{:02X};", upper, id);
+    }
+    let _ = writeln!(out, "\n#[derive(Clone, Copy, Debug, PartialEq, Eq)]\npub struct {}(pub [u8; {}_LEN]);\n", name, upper);
+    let _ = writeln!(out, "impl {} {{", name);
+
+    let init = match report.report_id {
+        Some(_) => format!("        let mut data = [0u8; {}_LEN];\n        data[0] = {}_ID;\n        Self(data)", upper, upper),
+        None => format!("        Self([0u8; {}_LEN])", upper),
+    };
+    let _ = writeln!(out, "    pub fn new() -> Self {{\n{}\n    }}\n", init);
+    let check_id = report.report_id.map_or(String::new(), |_| format!(" || data[0] != {}_ID", upper));
+    let _ = writeln!(out, "    pub fn from_bytes(data: &[u8]) -> Option<Self> {{");
+    let _ = writeln!(out, "        if data.len() < {}_LEN{} {{ return None; }}", upper, check_id);
+    let _ = writeln!(out, "        let mut out = [0u8; {}_LEN];\n        out.copy_from_slice(&data[..{}_LEN]);\n        Some(Self(out))\n    }}", upper, upper);
+
+    for l in layouts(report)? {
+        let container = rust_type(l.width * 8, false);
+        let endian = if l.bytes.first() == Some(&l.offset) { "le" } else { "be" };
+        let slice = format!("self.0[{}..{}]", l.offset, l.offset + l.width);
+        let array = (l.offset..l.offset + l.width).map(|i| format!("self.0[{}]", i)).collect::<Vec<_>>().join(", ");
+        let load = format!("{}::from_{}_bytes([{}])", container, endian, array);
+        let raw = l.raw_name();
+        let value_ty = if l.float { "f32".to_string() } else { rust_type(l.value_bits(), l.signed && l.bits.is_none()) };
+
+        for (value, label) in &l.field.labels {
+            let _ = writeln!(out, "\n    pub const {}_{}: {} = {};", l.ident.to_uppercase(), snake(label).to_uppercase(), value_ty, value);
+        }
+
+        // 讀取
+        let _ = writeln!(out, "\n    pub fn {}(&self) -> {} {{", raw, value_ty);
+        match l.bits {
+            Some((shift, _)) => { let _ = writeln!(out, "        (({} >> {}) & 0x{:X}) as {}", load, shift, l.mask(), value_ty); }
+            None if l.float => { let _ = writeln!(out, "        f32::from_bits({})", load); }
+            None => { let _ = writeln!(out, "        {}::from_{}_bytes([{}])", value_ty, endian, array); }
+        }
+        let _ = writeln!(out, "    }}");
+
+        // 寫入
+        let _ = writeln!(out, "\n    pub fn set_{}(&mut self, v: {}) {{", raw, value_ty);
+        match l.bits {
+            Some((shift, _)) => {
+                let _ = writeln!(out, "        let u = ({} & !(0x{:X} << {})) | ((v as {} & 0x{:X}) << {});", load, l.mask(), shift, container, l.mask(), shift);
+                let _ = writeln!(out, "        {}.copy_from_slice(&u.to_{}_bytes());", slice, endian);
+            }
+            None => { let _ = writeln!(out, "        {}.copy_from_slice(&v.to_{}_bytes());", slice, endian); }
+        }
+        let _ = writeln!(out, "    }}");
+
+        if l.scaled {
+            let unit = l.field.unit.as_ref().map_or(String::new(), |u| format!(" // {}", u));
+            let _ = writeln!(out, "\n    pub fn {}(&self) -> f64 {{{}", l.ident, unit);
+            let _ = writeln!(out, "        self.{}() as f64 * {:?} + {:?}\n    }}", raw, l.field.scale, l.field.bias);
+        }
+    }
+    let _ = writeln!(out, "}}\n\nimpl Default for {} {{\n    fn default() -> Self {{ Self::new() }}\n}}\n", name);
+    Ok(())
+}
+
+fn to_rust(schema: &ReportSchema) -> Result<String, String> {
+    let mut out = format!("// 由 report schema \"{}\" 產生，請勿手動修改\n\n", schema.name);
+    for report in &schema.reports {
+        rust_report(&mut out, &schema.name, report)?;
+    }
+    Ok(out)
+}
+
+pub fn generate(schema: &ReportSchema, language: CodeLanguage) -> Result<String, String> {
+    match language {
+        CodeLanguage::C => to_c(schema),
+        CodeLanguage::Rust => to_rust(schema),
+    }
+}
@@ -0,0 +1,62 @@
+// --- 多設備總覽 ---
+// 把多個設備選定的解碼欄位合併成單一 dashboard 事件，以固定頻率送出，
+// 總覽畫面只需訂閱一個事件，不必分別監聽每個設備。
+
+use crate::descriptor::FieldValue;
+use crate::device::ManagedDevice;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+// 送出頻率的下限，避免設定過小灌爆前端
+pub const MIN_INTERVAL_MS: u64 = 50;
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct DashboardEntry {
+    pub path: String,
+    // 顯示用的設備名稱，未設定時使用 path
+    #[serde(default)]
+    pub label: Option<String>,
+    // 要顯示的欄位名稱，空陣列代表全部
+    #[serde(default)]
+    pub fields: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct DashboardConfig {
+    // 0 代表關閉
+    pub interval_ms: u64,
+    pub entries: Vec<DashboardEntry>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DashboardRow {
+    pub path: String,
+    pub label: String,
+    // 設備目前是否有在監聽
+    pub connected: bool,
+    pub fields: BTreeMap<String, FieldValue>,
+    // 距離最後一次更新的時間，尚未收到資料為 None
+    pub age_ms: Option<u64>,
+}
+
+impl DashboardConfig {
+    // 依目前設定產生一次總覽資料，同時確保列出的設備有在記錄最新欄位
+    pub fn rows(&self, devices: &HashMap<String, ManagedDevice>) -> Vec<DashboardRow> {
+        self.entries.iter()
+            .map(|entry| {
+                let label = entry.label.clone().unwrap_or_else(|| entry.path.clone());
+                let Some(m_dev) = devices.get(&entry.path) else {
+                    return DashboardRow { path: entry.path.clone(), label, connected: false, fields: BTreeMap::new(), age_ms: None };
+                };
+                let mut latest = m_dev.latest.lock().unwrap();
+                latest.tracking = true;
+                let fields = latest.fields.iter()
+                    .filter(|(name, _)| entry.fields.is_empty() || entry.fields.contains(name))
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect();
+                let age_ms = latest.updated.map(|t| t.elapsed().as_millis() as u64);
+                DashboardRow { path: entry.path.clone(), label, connected: true, fields, age_ms }
+            })
+            .collect()
+    }
+}
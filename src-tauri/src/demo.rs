@@ -0,0 +1,232 @@
+// --- Demo 模式的模擬設備 ---
+// 不需要硬體即可產生合理的資料流量 (鍵盤、感測器、廠商主控台)，供教學截圖與 UI 開發使用。
+
+use crate::device::{self, ListenOptions, ManagedDevice};
+use crate::hid_io::HidIo;
+use crate::protocols::DeviceIdentity;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+pub const PATH_PREFIX: &str = "demo://";
+
+// pid.codes 的測試用 VID
+const DEMO_VENDOR_ID: u16 = 0x1209;
+
+const KEYBOARD_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0x05, 0x07, 0x19, 0xE0, 0x29, 0xE7, 0x15, 0x00, 0x25, 0x01,
+    0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0x95, 0x01, 0x75, 0x08, 0x81, 0x01, 0x95, 0x05, 0x75, 0x01,
+    0x05, 0x08, 0x19, 0x01, 0x29, 0x05, 0x91, 0x02, 0x95, 0x01, 0x75, 0x03, 0x91, 0x01, 0x95, 0x06,
+    0x75, 0x08, 0x15, 0x00, 0x25, 0x65, 0x05, 0x07, 0x19, 0x00, 0x29, 0x65, 0x81, 0x00, 0xC0,
+];
+
+// 8 bytes 廠商自訂 input: 溫度 / 濕度 / 照度 (u16le) + 序號
+const SENSOR_DESCRIPTOR: &[u8] = &[
+    0x06, 0x00, 0xFF, 0x09, 0x02, 0xA1, 0x01, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x08, 0x95, 0x08,
+    0x09, 0x02, 0x81, 0x02, 0xC0,
+];
+
+// 64 bytes input / output / feature
+const CONSOLE_DESCRIPTOR: &[u8] = &[
+    0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x08, 0x95, 0x40,
+    0x09, 0x01, 0x81, 0x02, 0x95, 0x40, 0x09, 0x01, 0x91, 0x02, 0x95, 0x40, 0x09, 0x01, 0xB1, 0x02,
+    0xC0,
+];
+
+const KEYBOARD_TEXT: &str = "hello from keystone demo ";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Keyboard,
+    Sensor,
+    Console,
+}
+
+pub struct DemoDevice {
+    pub path: &'static str,
+    pub identity: DeviceIdentity,
+    kind: Kind,
+}
+
+pub fn devices() -> Vec<DemoDevice> {
+    let identity = |product_id, usage_page, usage| DeviceIdentity {
+        vendor_id: DEMO_VENDOR_ID,
+        product_id,
+        usage_page,
+        usage,
+        interface_number: 0,
+        release_number: 0x0100,
+    };
+    vec![
+        DemoDevice { path: "demo://keyboard", identity: identity(0x0001, 0x0001, 0x06), kind: Kind::Keyboard },
+        DemoDevice { path: "demo://sensor", identity: identity(0x0002, 0xFF00, 0x02), kind: Kind::Sensor },
+        DemoDevice { path: "demo://console", identity: identity(0x0003, 0xFF00, 0x01), kind: Kind::Console },
+    ]
+}
+
+pub fn is_demo_path(path: &str) -> bool {
+    path.starts_with(PATH_PREFIX)
+}
+
+pub fn open(app: &AppHandle, path: &str, options: ListenOptions) -> Result<ManagedDevice, String> {
+    let dev = devices().into_iter().find(|d| d.path == path).ok_or("找不到模擬設備")?;
+    let io = SimulatedDevice::new(dev.kind);
+    Ok(device::start(app, path, Box::new(io), dev.identity, options))
+}
+
+// --- 模擬設備本體 ---
+
+struct SimState {
+    started: Instant,
+    next_at: Instant,
+    pending: VecDeque<Vec<u8>>,
+    seq: u8,
+    step: usize,
+    rng: u64,
+    feature: Vec<u8>,
+}
+
+struct SimulatedDevice {
+    kind: Kind,
+    state: RefCell<SimState>,
+}
+
+impl SimulatedDevice {
+    fn new(kind: Kind) -> Self {
+        let now = Instant::now();
+        Self {
+            kind,
+            state: RefCell::new(SimState {
+                started: now,
+                next_at: now,
+                pending: VecDeque::new(),
+                seq: 0,
+                step: 0,
+                rng: 0x2545_F491_4F6C_DD1D,
+                feature: vec![0u8; 64],
+            }),
+        }
+    }
+}
+
+impl SimState {
+    // xorshift，只需要看起來像雜訊
+    fn noise(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng % 1000) as f64 / 1000.0 - 0.5
+    }
+
+    // 產生下一筆主動回報，並回傳距離再下一筆的間隔
+    fn generate(&mut self, kind: Kind) -> (Option<Vec<u8>>, Duration) {
+        match kind {
+            Kind::Keyboard => {
+                let chars: Vec<char> = KEYBOARD_TEXT.chars().collect();
+                let index = self.step / 2;
+                let pressing = self.step.is_multiple_of(2);
+                self.step = (self.step + 1) % (chars.len() * 2);
+                let mut report = vec![0u8; 8];
+                if pressing {
+                    report[2] = match chars[index] {
+                        c @ 'a'..='z' => c as u8 - b'a' + 0x04,
+                        _ => 0x2C,
+                    };
+                }
+                // 打完一輪後停頓一下
+                let gap = if self.step == 0 { 2000 } else { 60 };
+                (Some(report), Duration::from_millis(gap))
+            }
+            Kind::Sensor => {
+                let t = self.started.elapsed().as_secs_f64();
+                let temp = 2350.0 + 150.0 * (t / 10.0).sin() + 5.0 * self.noise();
+                let humidity = 4500.0 + 300.0 * (t / 25.0).cos() + 20.0 * self.noise();
+                let light = 320.0 + 40.0 * self.noise();
+                let mut report = Vec::with_capacity(8);
+                report.extend((temp as u16).to_le_bytes());
+                report.extend((humidity as u16).to_le_bytes());
+                report.extend((light as u16).to_le_bytes());
+                report.push(self.seq);
+                report.push(0);
+                self.seq = self.seq.wrapping_add(1);
+                (Some(report), Duration::from_millis(100))
+            }
+            Kind::Console => {
+                // 心跳封包
+                let mut report = vec![0u8; 64];
+                report[0] = 0xAA;
+                report[1] = self.seq;
+                self.seq = self.seq.wrapping_add(1);
+                (Some(report), Duration::from_secs(2))
+            }
+        }
+    }
+}
+
+impl HidIo for SimulatedDevice {
+    fn write(&self, data: &[u8]) -> Result<usize, String> {
+        if self.kind == Kind::Console {
+            // data[0] 為 Report ID，指令碼在 data[1]
+            let cmd = data.get(1).copied().unwrap_or(0);
+            let mut resp = vec![0u8; 64];
+            resp[0] = cmd;
+            match cmd {
+                // 版本查詢
+                0xC0 => resp[2..5].copy_from_slice(&[1, 2, 3]),
+                _ => {
+                    let n = std::cmp::min(data.len().saturating_sub(2), 62);
+                    resp[2..2 + n].copy_from_slice(&data[2..2 + n]);
+                }
+            }
+            self.state.borrow_mut().pending.push_back(resp);
+        }
+        Ok(data.len())
+    }
+
+    fn read_timeout(&self, buf: &mut [u8], timeout_ms: i32) -> Result<usize, String> {
+        let mut state = self.state.borrow_mut();
+        if state.pending.is_empty() {
+            let now = Instant::now();
+            let wait = state.next_at.saturating_duration_since(now);
+            let timeout = Duration::from_millis(timeout_ms.max(0) as u64);
+            if wait > timeout {
+                thread::sleep(timeout);
+                return Ok(0);
+            }
+            thread::sleep(wait);
+            let (report, next) = state.generate(self.kind);
+            state.next_at = Instant::now() + next;
+            if let Some(report) = report { state.pending.push_back(report); }
+        }
+        let Some(report) = state.pending.pop_front() else { return Ok(0) };
+        let n = std::cmp::min(report.len(), buf.len());
+        buf[..n].copy_from_slice(&report[..n]);
+        Ok(n)
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> Result<(), String> {
+        if self.kind != Kind::Console { return Err("此模擬設備沒有 feature report".into()); }
+        let mut state = self.state.borrow_mut();
+        let n = std::cmp::min(data.len().saturating_sub(1), state.feature.len());
+        state.feature[..n].copy_from_slice(&data[1..1 + n]);
+        Ok(())
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, String> {
+        if self.kind != Kind::Console { return Err("此模擬設備沒有 feature report".into()); }
+        let state = self.state.borrow();
+        let n = std::cmp::min(buf.len().saturating_sub(1), state.feature.len());
+        buf[1..1 + n].copy_from_slice(&state.feature[..n]);
+        Ok(n + 1)
+    }
+
+    fn report_descriptor(&self) -> Result<Vec<u8>, String> {
+        Ok(match self.kind {
+            Kind::Keyboard => KEYBOARD_DESCRIPTOR,
+            Kind::Sensor => SENSOR_DESCRIPTOR,
+            Kind::Console => CONSOLE_DESCRIPTOR,
+        }.to_vec())
+    }
+}
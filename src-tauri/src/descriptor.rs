@@ -0,0 +1,645 @@
+// --- HID Report Descriptor 解析 ---
+// 走訪 short item，展開成每個 Input/Output/Feature 欄位的位置與 usage。
+
+use crate::usages;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportKind {
+    Input,
+    Output,
+    Feature,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ReportField {
+    pub kind: ReportKind,
+    pub report_id: u8,
+    // 相對於 report 資料開頭 (不含 Report ID) 的位元位置
+    pub bit_offset: usize,
+    pub bit_size: usize,
+    pub count: usize,
+    pub usage_page: u16,
+    // 每個元素對應的 usage；Array 欄位則是可能出現的 usage 清單
+    pub usages: Vec<u16>,
+    pub logical_min: i32,
+    pub logical_max: i32,
+    // 沒有指定 Physical Min/Max 時與 logical 相同
+    pub physical_min: i32,
+    pub physical_max: i32,
+    pub unit: u32,
+    pub unit_exponent: i32,
+    // Main item 的旗標 (bit0 Constant, bit1 Variable, bit2 Relative ...)
+    pub flags: u32,
+}
+
+impl ReportField {
+    pub fn is_constant(&self) -> bool { self.flags & 0x01 != 0 }
+    pub fn is_variable(&self) -> bool { self.flags & 0x02 != 0 }
+    pub fn is_relative(&self) -> bool { self.flags & 0x04 != 0 }
+
+    // 第 i 個元素對應的 usage；usage 數量少於 count 時，其餘元素沿用最後一個
+    fn element_usage(&self, i: usize) -> Option<u16> {
+        self.usages.get(i).or(self.usages.last()).copied()
+    }
+
+    // 讀取第 i 個元素，logical_min 為負時做符號延伸
+    pub fn element_value(&self, body: &[u8], i: usize) -> Option<i64> {
+        let raw = extract_bits(body, self.bit_offset + i * self.bit_size, self.bit_size)?;
+        Some(if self.logical_min < 0 { sign_extend(raw, self.bit_size) } else { raw as i64 })
+    }
+}
+
+#[derive(Serialize, Clone, Default, Debug)]
+pub struct ReportDescriptor {
+    pub fields: Vec<ReportField>,
+    // 是否使用 Report ID (有出現 Report ID item)
+    pub uses_report_ids: bool,
+}
+
+// Usage Minimum..Maximum 展開的上限
+const MAX_USAGE_RANGE: u32 = 0x10000;
+
+#[derive(Clone, Copy, Default)]
+struct GlobalState {
+    usage_page: u16,
+    logical_min: i32,
+    logical_max: i32,
+    physical_min: Option<i32>,
+    physical_max: Option<i32>,
+    unit: u32,
+    unit_exponent: i32,
+    report_size: usize,
+    report_count: usize,
+    report_id: u8,
+}
+
+#[derive(Default)]
+struct LocalState {
+    usages: Vec<u32>,
+    usage_min: Option<u32>,
+    usage_max: Option<u32>,
+}
+
+fn item_value(data: &[u8]) -> u32 {
+    data.iter().rev().fold(0u32, |acc, b| (acc << 8) | *b as u32)
+}
+
+// 依資料長度做符號延伸
+fn item_signed(data: &[u8]) -> i32 {
+    match data.len() {
+        1 => data[0] as i8 as i32,
+        2 => i16::from_le_bytes([data[0], data[1]]) as i32,
+        4 => i32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+        _ => 0,
+    }
+}
+
+pub fn parse(raw: &[u8]) -> Result<ReportDescriptor, String> {
+    let mut desc = ReportDescriptor::default();
+    let mut global = GlobalState::default();
+    let mut stack: Vec<GlobalState> = Vec::new();
+    let mut local = LocalState::default();
+    // (kind, report_id) -> 下一個欄位的位元位置
+    let mut offsets: Vec<(ReportKind, u8, usize)> = Vec::new();
+
+    let mut i = 0;
+    while i < raw.len() {
+        let prefix = raw[i];
+        // Long item: 直接跳過
+        if prefix == 0xFE {
+            let len = *raw.get(i + 1).ok_or("descriptor 在 long item 中截斷")? as usize;
+            i += 3 + len;
+            continue;
+        }
+
+        let size = match prefix & 0x03 { 3 => 4, n => n as usize };
+        let data = raw.get(i + 1..i + 1 + size).ok_or(format!("descriptor 在 offset {} 截斷", i))?;
+        let tag = prefix & 0xFC;
+        i += 1 + size;
+
+        match tag {
+            // --- Main items ---
+            0x80 | 0x90 | 0xB0 => {
+                let kind = match tag {
+                    0x80 => ReportKind::Input,
+                    0x90 => ReportKind::Output,
+                    _ => ReportKind::Feature,
+                };
+                let slot = match offsets.iter().position(|(k, id, _)| *k == kind && *id == global.report_id) {
+                    Some(p) => p,
+                    None => { offsets.push((kind, global.report_id, 0)); offsets.len() - 1 }
+                };
+                let bit_offset = offsets[slot].2;
+                offsets[slot].2 += global.report_size * global.report_count;
+
+                let mut usages: Vec<u32> = local.usages.clone();
+                if let (Some(min), Some(max)) = (local.usage_min, local.usage_max) {
+                    // 範圍最多涵蓋一整個 usage page，異常的 descriptor 不會展開成巨大的清單
+                    usages.extend(min..=max.min(min.saturating_add(MAX_USAGE_RANGE - 1)));
+                }
+                // 32 位元 usage 的高 16 位元是 usage page
+                let usage_page = usages.first()
+                    .filter(|u| **u > 0xFFFF)
+                    .map(|u| (u >> 16) as u16)
+                    .unwrap_or(global.usage_page);
+
+                desc.fields.push(ReportField {
+                    kind,
+                    report_id: global.report_id,
+                    bit_offset,
+                    bit_size: global.report_size,
+                    count: global.report_count,
+                    usage_page,
+                    usages: usages.iter().map(|u| *u as u16).collect(),
+                    logical_min: global.logical_min,
+                    logical_max: global.logical_max,
+                    physical_min: global.physical_min.unwrap_or(global.logical_min),
+                    physical_max: global.physical_max.unwrap_or(global.logical_max),
+                    unit: global.unit,
+                    unit_exponent: global.unit_exponent,
+                    flags: item_value(data),
+                });
+                local = LocalState::default();
+            }
+            // Collection / End Collection
+            0xA0 | 0xC0 => local = LocalState::default(),
+
+            // --- Global items ---
+            0x04 => global.usage_page = item_value(data) as u16,
+            0x14 => global.logical_min = item_signed(data),
+            0x24 => {
+                // Logical Minimum 非負時，Maximum 視為無號數
+                global.logical_max = if global.logical_min >= 0 { item_value(data) as i32 } else { item_signed(data) };
+            }
+            0x34 => global.physical_min = Some(item_signed(data)),
+            0x44 => {
+                let min = global.physical_min.unwrap_or(0);
+                global.physical_max = Some(if min >= 0 { item_value(data) as i32 } else { item_signed(data) });
+            }
+            // Unit Exponent 為 4 位元有號數 (-8..7)
+            0x54 => global.unit_exponent = sign_extend(item_value(data) & 0x0F, 4) as i32,
+            0x64 => global.unit = item_value(data),
+            0x74 => global.report_size = item_value(data) as usize,
+            0x84 => {
+                global.report_id = item_value(data) as u8;
+                desc.uses_report_ids = true;
+            }
+            0x94 => global.report_count = item_value(data) as usize,
+            0xA4 => stack.push(global),
+            0xB4 => global = stack.pop().ok_or("Pop 沒有對應的 Push")?,
+
+            // --- Local items ---
+            0x08 => local.usages.push(item_value(data)),
+            0x18 => local.usage_min = Some(item_value(data)),
+            0x28 => local.usage_max = Some(item_value(data)),
+
+            _ => {}
+        }
+    }
+    Ok(desc)
+}
+
+// usage 在 report 中的位置
+#[derive(Clone, Copy, Debug)]
+pub enum UsageLocation {
+    // Variable 欄位：固定位元位置
+    Variable { report_id: u8, bit_offset: usize, bit_size: usize },
+    // Array 欄位：任一元素等於 value 代表該 usage 被觸發
+    Array { report_id: u8, bit_offset: usize, bit_size: usize, count: usize, value: u32 },
+}
+
+impl ReportDescriptor {
+    pub fn find_usage(&self, kind: ReportKind, usage_page: u16, usage: u16) -> Option<UsageLocation> {
+        self.fields.iter()
+            .filter(|f| f.kind == kind && f.usage_page == usage_page && !f.is_constant())
+            .find_map(|f| {
+                let index = f.usages.iter().position(|u| *u == usage)?;
+                if f.is_variable() {
+                    let element = std::cmp::min(index, f.count.saturating_sub(1));
+                    Some(UsageLocation::Variable {
+                        report_id: f.report_id,
+                        bit_offset: f.bit_offset + element * f.bit_size,
+                        bit_size: f.bit_size,
+                    })
+                } else {
+                    Some(UsageLocation::Array {
+                        report_id: f.report_id,
+                        bit_offset: f.bit_offset,
+                        bit_size: f.bit_size,
+                        count: f.count,
+                        value: (f.logical_min + index as i32) as u32,
+                    })
+                }
+            })
+    }
+
+    // 去掉 Report ID 後的資料；Report ID 不符時回傳 None
+    pub fn report_body<'a>(&self, report: &'a [u8], report_id: u8) -> Option<&'a [u8]> {
+        if self.uses_report_ids {
+            match report.split_first() {
+                Some((id, body)) if *id == report_id => Some(body),
+                _ => None,
+            }
+        } else {
+            Some(report)
+        }
+    }
+
+    // 讀取 usage 目前的值 (Array 欄位回傳 0/1)
+    pub fn usage_value(&self, loc: &UsageLocation, report: &[u8]) -> Option<u32> {
+        match *loc {
+            UsageLocation::Variable { report_id, bit_offset, bit_size } => {
+                extract_bits(self.report_body(report, report_id)?, bit_offset, bit_size)
+            }
+            UsageLocation::Array { report_id, bit_offset, bit_size, count, value } => {
+                let body = self.report_body(report, report_id)?;
+                let hit = (0..count).any(|i| extract_bits(body, bit_offset + i * bit_size, bit_size) == Some(value));
+                Some(hit as u32)
+            }
+        }
+    }
+
+    // 指定 report 的位元組長度 (不含 Report ID)
+    pub fn report_len(&self, kind: ReportKind, report_id: u8) -> usize {
+        let bits = self.fields.iter()
+            .filter(|f| f.kind == kind && f.report_id == report_id)
+            .map(|f| f.bit_offset + f.bit_size * f.count)
+            .max()
+            .unwrap_or(0);
+        bits.div_ceil(8)
+    }
+}
+
+// --- 欄位層級的解碼 ---
+
+// 解碼後的欄位值
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(untagged)]
+pub enum FieldValue {
+    Number(i64),
+    // 同名的多個元素 (例如 Buttons)
+    List(Vec<i64>),
+    // Array 欄位目前被觸發的 usage 名稱 (例如按下的鍵)
+    Usages(Vec<String>),
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DecodedReport {
+    pub report_id: u8,
+    pub fields: BTreeMap<String, FieldValue>,
+}
+
+fn push_value(fields: &mut BTreeMap<String, FieldValue>, name: String, value: i64) {
+    match fields.get_mut(&name) {
+        None => { fields.insert(name, FieldValue::Number(value)); }
+        Some(FieldValue::List(list)) => list.push(value),
+        Some(entry) => {
+            if let FieldValue::Number(first) = *entry { *entry = FieldValue::List(vec![first, value]); }
+        }
+    }
+}
+
+impl ReportDescriptor {
+    // 依 descriptor 把一筆 report (含 Report ID) 解成具名欄位；沒有對應欄位時回傳 None
+    pub fn decode(&self, kind: ReportKind, report: &[u8]) -> Option<DecodedReport> {
+        let report_id = if self.uses_report_ids { *report.first()? } else { 0 };
+        let body = self.report_body(report, report_id)?;
+        let mut fields = BTreeMap::new();
+
+        for f in self.fields.iter().filter(|f| f.kind == kind && f.report_id == report_id && !f.is_constant()) {
+            if f.is_variable() {
+                for i in 0..f.count {
+                    let (Some(usage), Some(value)) = (f.element_usage(i), f.element_value(body, i)) else { continue };
+                    let name = match f.usage_page {
+                        usages::PAGE_BUTTON => usages::page_name(f.usage_page),
+                        page => usages::usage_name(page, usage),
+                    };
+                    push_value(&mut fields, name, value);
+                }
+            } else {
+                // Array 欄位：超出 logical 範圍的值代表沒有觸發
+                let active: Vec<String> = (0..f.count)
+                    .filter_map(|i| f.element_value(body, i))
+                    .filter(|v| *v >= f.logical_min as i64 && *v <= f.logical_max as i64)
+                    .filter_map(|v| f.usages.get((v - f.logical_min as i64) as usize))
+                    .filter(|u| **u != 0)
+                    .map(|u| usages::usage_name(f.usage_page, *u))
+                    .collect();
+                let entry = fields.entry(usages::page_name(f.usage_page)).or_insert(FieldValue::Usages(Vec::new()));
+                if let FieldValue::Usages(list) = entry { list.extend(active); }
+            }
+        }
+        if fields.is_empty() { return None; }
+        Some(DecodedReport { report_id, fields })
+    }
+}
+
+fn sign_extend(value: u32, bits: usize) -> i64 {
+    if bits == 0 || bits >= 32 { return value as i32 as i64; }
+    let shift = 32 - bits;
+    (((value << shift) as i32) >> shift) as i64
+}
+
+// 取出 report 中任意位元位置的無號值 (little-endian 位元順序)
+pub fn extract_bits(data: &[u8], bit_offset: usize, bit_size: usize) -> Option<u32> {
+    if bit_size == 0 || bit_size > 32 || (bit_offset + bit_size).div_ceil(8) > data.len() { return None; }
+    let mut value = 0u32;
+    for b in 0..bit_size {
+        let pos = bit_offset + b;
+        if data[pos / 8] & (1 << (pos % 8)) != 0 {
+            value |= 1 << b;
+        }
+    }
+    Some(value)
+}
+
+pub fn set_bits(data: &mut [u8], bit_offset: usize, bit_size: usize, value: u32) {
+    for b in 0..bit_size {
+        let pos = bit_offset + b;
+        if pos / 8 >= data.len() { return; }
+        if value & (1 << b) != 0 {
+            data[pos / 8] |= 1 << (pos % 8);
+        } else {
+            data[pos / 8] &= !(1 << (pos % 8));
+        }
+    }
+}
+
+// --- 各 report 的長度 ---
+
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct ReportSize {
+    pub report_id: u8,
+    // 不含 Report ID 的位元組數
+    pub len: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ReportSizes {
+    pub uses_report_ids: bool,
+    pub input: Vec<ReportSize>,
+    pub output: Vec<ReportSize>,
+    pub feature: Vec<ReportSize>,
+    // 是否由 descriptor 推算 (否則為預設的 64 bytes)
+    pub from_descriptor: bool,
+}
+
+impl ReportSizes {
+    // 取不到 descriptor 時沿用傳統的 64 bytes / 無 Report ID
+    pub fn fallback() -> Self {
+        let default = vec![ReportSize { report_id: 0, len: 64 }];
+        Self {
+            uses_report_ids: false,
+            input: default.clone(),
+            output: default.clone(),
+            feature: default,
+            from_descriptor: false,
+        }
+    }
+
+    pub fn from_descriptor(desc: &ReportDescriptor) -> Self {
+        let collect = |kind: ReportKind| {
+            let mut ids: Vec<u8> = desc.fields.iter().filter(|f| f.kind == kind).map(|f| f.report_id).collect();
+            ids.sort_unstable();
+            ids.dedup();
+            ids.into_iter()
+                .map(|id| ReportSize { report_id: id, len: desc.report_len(kind, id) })
+                .collect::<Vec<_>>()
+        };
+        Self {
+            uses_report_ids: desc.uses_report_ids,
+            input: collect(ReportKind::Input),
+            output: collect(ReportKind::Output),
+            feature: collect(ReportKind::Feature),
+            from_descriptor: true,
+        }
+    }
+
+    fn list(&self, kind: ReportKind) -> &[ReportSize] {
+        match kind {
+            ReportKind::Input => &self.input,
+            ReportKind::Output => &self.output,
+            ReportKind::Feature => &self.feature,
+        }
+    }
+
+    pub fn len_of(&self, kind: ReportKind, report_id: u8) -> Option<usize> {
+        self.list(kind).iter().find(|r| r.report_id == report_id).map(|r| r.len)
+    }
+
+    // 讀取 input report 所需的緩衝區大小 (含 Report ID)
+    pub fn input_buffer_len(&self) -> usize {
+        let max = self.input.iter().map(|r| r.len).max().unwrap_or(64);
+        std::cmp::max(max, 1) + self.uses_report_ids as usize
+    }
+
+    // 檢查 payload (不含 Report ID) 是否放得進指定 report，回傳該 report 的長度
+    pub fn check_payload(&self, kind: ReportKind, report_id: u8, payload_len: usize) -> Result<usize, String> {
+        let len = self.len_of(kind, report_id).ok_or_else(|| {
+            let ids: Vec<String> = self.list(kind).iter().map(|r| format!("{:#04x}", r.report_id)).collect();
+            format!("設備沒有 Report ID {:#04x} 的 {:?} report (可用: {})", report_id, kind, ids.join(", "))
+        })?;
+        if payload_len > len {
+            return Err(format!("資料長度 {} 超過 {:?} report {:#04x} 的大小 {}", payload_len, kind, report_id, len));
+        }
+        Ok(len)
+    }
+}
+
+// --- 匯出 ---
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DescriptorFormat {
+    // 原始位元組
+    Binary,
+    // 每行 16 bytes 的十六進位文字
+    Hex,
+    // 反組譯清單 (C 陣列 + 註解，與常見 descriptor 工具的格式相同)
+    Listing,
+}
+
+pub fn to_hex(raw: &[u8]) -> String {
+    raw.chunks(16)
+        .map(|line| line.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ") + "\n")
+        .collect()
+}
+
+const COLLECTION_TYPES: [&str; 7] = [
+    "Physical", "Application", "Logical", "Report", "Named Array", "Usage Switch", "Usage Modifier",
+];
+
+// Main item 旗標：(bit, 0 的名稱, 1 的名稱)
+const MAIN_FLAGS: [(u32, &str, &str); 9] = [
+    (0, "Data", "Const"),
+    (1, "Array", "Var"),
+    (2, "Abs", "Rel"),
+    (3, "No Wrap", "Wrap"),
+    (4, "Linear", "Nonlinear"),
+    (5, "Preferred State", "No Preferred State"),
+    (6, "No Null Position", "Null State"),
+    (7, "Non-volatile", "Volatile"),
+    (8, "Bit Field", "Buffered Bytes"),
+];
+
+fn main_flags(tag: u8, value: u32, size: usize) -> String {
+    MAIN_FLAGS.iter()
+        // 只列出資料有涵蓋的位元；Input 沒有 Volatile 位元
+        .filter(|(bit, ..)| (*bit as usize) < size * 8 && !(tag == 0x80 && *bit == 7))
+        .map(|(bit, off, on)| if value & (1 << bit) != 0 { *on } else { *off })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn unit_text(value: u32) -> String {
+    const SYSTEMS: [&str; 5] = ["None", "SI Linear", "SI Rotation", "English Linear", "English Rotation"];
+    const DIMENSIONS: [&str; 6] = ["Length", "Mass", "Time", "Temperature", "Current", "Luminous Intensity"];
+    if value == 0 { return "None".into(); }
+    let system = SYSTEMS.get((value & 0x0F) as usize).copied().unwrap_or("Vendor");
+    let mut parts = vec![format!("System: {}", system)];
+    for (i, name) in DIMENSIONS.iter().enumerate() {
+        let exponent = sign_extend((value >> (4 * (i + 1))) & 0x0F, 4);
+        if exponent != 0 { parts.push(format!("{}: {}", name, exponent)); }
+    }
+    parts.join(", ")
+}
+
+// 以 "0x05, 0x01,   // Usage Page (Generic Desktop)" 的格式逐項列出
+pub fn decompile(raw: &[u8]) -> Result<String, String> {
+    let mut out = String::new();
+    let mut usage_page = 0u16;
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < raw.len() {
+        let prefix = raw[i];
+        let (len, text) = if prefix == 0xFE {
+            let size = *raw.get(i + 1).ok_or("descriptor 在 long item 中截斷")? as usize;
+            (3 + size, "Long Item".to_string())
+        } else {
+            let size = match prefix & 0x03 { 3 => 4, n => n as usize };
+            let data = raw.get(i + 1..i + 1 + size).ok_or(format!("descriptor 在 offset {} 截斷", i))?;
+            let (value, signed) = (item_value(data), item_signed(data));
+            let tag = prefix & 0xFC;
+            if tag == 0xC0 { depth = depth.saturating_sub(1); }
+            let text = match tag {
+                0x80 => format!("Input ({})", main_flags(tag, value, size)),
+                0x90 => format!("Output ({})", main_flags(tag, value, size)),
+                0xB0 => format!("Feature ({})", main_flags(tag, value, size)),
+                0xA0 => format!("Collection ({})", COLLECTION_TYPES.get(value as usize).copied().unwrap_or("Vendor Defined")),
+                0xC0 => "End Collection".into(),
+                0x04 => {
+                    usage_page = value as u16;
+                    format!("Usage Page ({})", usages::page_name(usage_page))
+                }
+                0x14 => format!("Logical Minimum ({})", signed),
+                0x24 => format!("Logical Maximum ({})", signed),
+                0x34 => format!("Physical Minimum ({})", signed),
+                0x44 => format!("Physical Maximum ({})", signed),
+                0x54 => format!("Unit Exponent ({})", sign_extend(value & 0x0F, 4)),
+                0x64 => format!("Unit ({})", unit_text(value)),
+                0x74 => format!("Report Size ({})", value),
+                0x84 => format!("Report ID ({})", value),
+                0x94 => format!("Report Count ({})", value),
+                0xA4 => "Push".into(),
+                0xB4 => "Pop".into(),
+                0x08 | 0x18 | 0x28 => {
+                    let name = match tag { 0x08 => "Usage", 0x18 => "Usage Minimum", _ => "Usage Maximum" };
+                    // 4 bytes 的 usage 自帶 usage page
+                    let page = if size == 4 { (value >> 16) as u16 } else { usage_page };
+                    let usage = usages::usage_name(page, value as u16);
+                    if tag == 0x08 { format!("{} ({})", name, usage) } else { format!("{} ({:#04x})", name, value & 0xFFFF) }
+                }
+                0x38 => format!("Designator Index ({})", value),
+                0x48 => format!("Designator Minimum ({})", value),
+                0x58 => format!("Designator Maximum ({})", value),
+                0x78 => format!("String Index ({})", value),
+                0x88 => format!("String Minimum ({})", value),
+                0x98 => format!("String Maximum ({})", value),
+                0xA8 => format!("Delimiter ({})", value),
+                _ => format!("Unknown ({:#04x})", prefix),
+            };
+            (1 + size, text)
+        };
+        let item = raw.get(i..i + len).ok_or(format!("descriptor 在 offset {} 截斷", i))?;
+        let bytes: String = item.iter().map(|b| format!("0x{:02X}, ", b)).collect();
+        out.push_str(&format!("{:<30}// {}{}\n", bytes.trim_end(), "  ".repeat(depth), text));
+        if prefix & 0xFC == 0xA0 && prefix != 0xFE { depth += 1; }
+        i += len;
+    }
+    out.push_str(&format!("\n// {} bytes\n", raw.len()));
+    Ok(out)
+}
+
+pub fn export(raw: &[u8], format: DescriptorFormat) -> Result<Vec<u8>, String> {
+    Ok(match format {
+        DescriptorFormat::Binary => raw.to_vec(),
+        DescriptorFormat::Hex => to_hex(raw).into_bytes(),
+        DescriptorFormat::Listing => decompile(raw)?.into_bytes(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 標準的 boot mouse：3 個按鍵 + 5 位元填充 + X/Y (-127..127)
+    const MOUSE: &[u8] = &[
+        0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x09, 0x01, 0xA1, 0x00,
+        0x05, 0x09, 0x19, 0x01, 0x29, 0x03, 0x15, 0x00, 0x25, 0x01, 0x95, 0x03, 0x75, 0x01, 0x81, 0x02,
+        0x95, 0x01, 0x75, 0x05, 0x81, 0x01,
+        0x05, 0x01, 0x09, 0x30, 0x09, 0x31, 0x15, 0x81, 0x25, 0x7F, 0x75, 0x08, 0x95, 0x02, 0x81, 0x06,
+        0xC0, 0xC0,
+    ];
+
+    #[test]
+    fn parses_boot_mouse_layout() {
+        let desc = parse(MOUSE).unwrap();
+        assert!(!desc.uses_report_ids);
+        assert_eq!(desc.fields.len(), 3);
+        let buttons = &desc.fields[0];
+        assert_eq!((buttons.bit_offset, buttons.bit_size, buttons.count), (0, 1, 3));
+        assert_eq!(buttons.usages, vec![1, 2, 3]);
+        assert!(desc.fields[1].is_constant());
+        let axes = &desc.fields[2];
+        assert_eq!((axes.bit_offset, axes.usages.clone()), (8, vec![0x30, 0x31]));
+        assert!(axes.is_relative());
+        assert_eq!(desc.report_len(ReportKind::Input, 0), 3);
+
+        // Y = -2 需要符號延伸
+        let report = [0x05, 0x10, 0xFE];
+        assert_eq!(axes.element_value(&report, 0), Some(16));
+        assert_eq!(axes.element_value(&report, 1), Some(-2));
+        assert_eq!(buttons.element_value(&report, 1), Some(0));
+        assert_eq!(buttons.element_value(&report, 2), Some(1));
+    }
+
+    #[test]
+    fn caps_unbounded_usage_ranges() {
+        // Usage Minimum 0、Usage Maximum 0xFFFFFFFF
+        let raw = [0x19, 0x00, 0x2B, 0xFF, 0xFF, 0xFF, 0xFF, 0x75, 0x08, 0x95, 0x01, 0x81, 0x00];
+        let desc = parse(&raw).unwrap();
+        assert_eq!(desc.fields[0].usages.len(), MAX_USAGE_RANGE as usize);
+        // 範圍起點接近 u32 上限時不能溢位
+        let raw = [0x1B, 0xF0, 0xFF, 0xFF, 0xFF, 0x2B, 0xFF, 0xFF, 0xFF, 0xFF, 0x81, 0x00];
+        assert_eq!(parse(&raw).unwrap().fields[0].usages.len(), 0x10);
+    }
+
+    #[test]
+    fn rejects_truncated_items() {
+        assert!(parse(&[0x05]).is_err());
+        assert!(parse(&[0xB4, 0x00]).is_err());
+    }
+
+    #[test]
+    fn set_and_extract_bits_round_trip() {
+        let mut data = [0u8; 4];
+        set_bits(&mut data, 3, 12, 0xABC);
+        assert_eq!(extract_bits(&data, 3, 12), Some(0xABC));
+        assert_eq!(data[0] & 0x07, 0);
+        assert_eq!(extract_bits(&data, 24, 9), None);
+        assert_eq!(sign_extend(0xFF, 8), -1);
+    }
+}
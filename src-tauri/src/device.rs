@@ -0,0 +1,1322 @@
+// --- 設備 I/O 執行緒 ---
+// 每個開啟的設備只有一條 I/O 執行緒負責讀寫，其他地方透過指令佇列請求操作，
+// 回覆經由一次性的 channel 送回，避免監聽與指令互搶回應。
+
+use crate::capture::{self, Direction};
+use crate::clock::{CaptureClock, Timestamp};
+use crate::descriptor::{self, DecodedReport, FieldValue, ReportDescriptor, ReportKind, ReportSizes};
+use crate::framing::FramingProfile;
+use crate::gamepad::{GamepadDecoder, GamepadState};
+use crate::hid_io::HidIo;
+use crate::incident::{self, Incident, IncidentKind, RecentReports};
+use crate::keyboard::{KeyboardDecoder, KeyboardState};
+use crate::keymap::{KeyLayouts, Keymap};
+use crate::logging::{self, Category, Severity};
+use crate::mouse::{MouseDecoder, MouseState};
+use crate::msr::{MsrCapture, MsrSwipe};
+use crate::power::{PowerLayout, PowerStatus};
+use crate::sensor::{SensorLayout, SensorReading};
+use crate::sequence::BytePattern;
+use crate::sony::{SonyDecoder, SonyState};
+use crate::protocols::{self, DeviceIdentity, HeadsetStatus, ProtocolPlugin};
+use crate::scale::{self, ScaleReading};
+use crate::schema::{CounterTracker, ReportLoss, ReportSchema, SchemaDecoded, SchemaViolation, ViolationCounter};
+use crate::stats::DeviceStats;
+use crate::stream::UdpStream;
+use crate::telephony::{TelephonyLayout, TelephonyState};
+use crate::watch::Watch;
+use hidapi::HidApi;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+
+// 沒有指令時每次讀取的等待時間，越短指令的反應越快
+const READ_POLL_MS: i32 = 10;
+
+// 連續寫入失敗達到此次數即進入錯誤狀態
+const FAULT_THRESHOLD: u32 = 5;
+
+// --- 事件 ---
+
+#[derive(Serialize, Clone)]
+struct HeadsetEvent {
+    path: String,
+    // 本次變化的欄位
+    changed: HeadsetStatus,
+    // 合併後的完整狀態
+    state: HeadsetStatus,
+}
+
+#[derive(Serialize, Clone)]
+struct TelephonyEvent {
+    path: String,
+    state: TelephonyState,
+}
+
+#[derive(Serialize, Clone)]
+struct ScaleEvent {
+    path: String,
+    reading: ScaleReading,
+}
+
+// hid-data 事件：設備使用 Report ID 時 report_id 為第一個位元組，data 不含它
+#[derive(Serialize, Clone)]
+struct HidDataEvent {
+    path: String,
+    report_id: Option<u8>,
+    // 這個監聽收到的第幾筆 report (從 0 開始，dedupe 略過的也計入，跳號代表中間有被略過的)
+    seq: u64,
+    timestamp: Timestamp,
+    data: Vec<u8>,
+}
+
+#[derive(Serialize, Clone)]
+struct FieldsEvent {
+    path: String,
+    #[serde(flatten)]
+    report: DecodedReport,
+    // false 代表只含有變化的欄位
+    full: bool,
+    timestamp: Timestamp,
+}
+
+#[derive(Serialize, Clone)]
+struct KeyboardEvent {
+    path: String,
+    #[serde(flatten)]
+    state: KeyboardState,
+    raw: Vec<u8>,
+}
+
+#[derive(Serialize, Clone)]
+struct MouseEvent {
+    path: String,
+    #[serde(flatten)]
+    state: MouseState,
+}
+
+#[derive(Serialize, Clone)]
+struct WatchEvent {
+    path: String,
+    name: String,
+    expression: String,
+    // 觸發當下的完整欄位
+    report: DecodedReport,
+}
+
+#[derive(Serialize, Clone)]
+struct SchemaEvent {
+    path: String,
+    #[serde(flatten)]
+    decoded: SchemaDecoded,
+    timestamp: Timestamp,
+}
+
+#[derive(Serialize, Clone)]
+struct LossEvent {
+    path: String,
+    #[serde(flatten)]
+    loss: ReportLoss,
+}
+
+// 依 framing profile 拆出的 payload；驗證失敗時 payload 為 None
+#[derive(Serialize, Clone)]
+struct FrameEvent {
+    path: String,
+    payload: Option<Vec<u8>>,
+    error: Option<String>,
+    timestamp: Timestamp,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceErrorCode {
+    // 設備已拔除或失去連線
+    Disconnected,
+    // 沒有存取權限 (被其他程式獨佔、缺少 udev 規則)
+    AccessDenied,
+    // 其他讀取錯誤
+    ReadFailed,
+    // 監聽執行緒發生 panic
+    ListenerPanic,
+}
+
+// 監聽執行緒異常結束 (設備已從開啟清單移除，需重新 start_listening)
+#[derive(Serialize, Clone)]
+pub struct DeviceErrorEvent {
+    pub path: String,
+    pub code: DeviceErrorCode,
+    // hidapi / 作業系統回報的原始錯誤訊息
+    pub os_error: String,
+    // 訊息中帶有的作業系統錯誤代碼 (errno / Win32)
+    pub os_code: Option<i32>,
+    pub timestamp_ms: i64,
+}
+
+// hidapi 的錯誤只有訊息字串，依各平台常見的訊息分類
+fn classify_read_error(error: &str) -> (DeviceErrorCode, Option<i32>) {
+    let os_code = error.split_once("os error ")
+        .and_then(|(_, rest)| rest.split(|c: char| !c.is_ascii_digit() && c != '-').next())
+        .and_then(|code| code.parse().ok());
+    let lower = error.to_lowercase();
+    let code = if ["no such device", "not connected", "disconnected", "device not configured", "device was removed"]
+        .iter().any(|m| lower.contains(m)) || matches!(os_code, Some(19) | Some(1167))
+    {
+        DeviceErrorCode::Disconnected
+    } else if ["permission denied", "access is denied", "access denied"].iter().any(|m| lower.contains(m)) || os_code == Some(13) {
+        DeviceErrorCode::AccessDenied
+    } else {
+        DeviceErrorCode::ReadFailed
+    };
+    (code, os_code)
+}
+
+// 進入錯誤狀態 (fault) 或清除 (None)
+#[derive(Serialize, Clone)]
+pub struct FaultEvent {
+    pub path: String,
+    pub fault: Option<DeviceFault>,
+}
+
+#[derive(Serialize, Clone)]
+struct ViolationEvent {
+    path: String,
+    #[serde(flatten)]
+    violation: SchemaViolation,
+    // 同一種違規累計的次數
+    count: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct PowerEvent {
+    pub path: String,
+    #[serde(flatten)]
+    pub status: PowerStatus,
+    // 定期讀取失敗時的原因
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct SensorEvent {
+    path: String,
+    #[serde(flatten)]
+    reading: SensorReading,
+    timestamp: Timestamp,
+}
+
+#[derive(Serialize, Clone)]
+struct GamepadEvent {
+    path: String,
+    #[serde(flatten)]
+    state: GamepadState,
+}
+
+#[derive(Serialize, Clone)]
+struct SonyEvent {
+    path: String,
+    #[serde(flatten)]
+    state: SonyState,
+}
+
+#[derive(Serialize, Clone)]
+struct PollEvent {
+    path: String,
+    // 第幾次輪詢 (從 0 開始)
+    seq: u64,
+    // 逾時為空
+    response: Vec<u8>,
+    error: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct MsrEvent {
+    path: String,
+    swipe: MsrSwipe,
+}
+
+// 監聽執行緒的選項
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ListenOptions {
+    // 只在內容與上一筆不同時才送出 hid-data
+    pub dedupe: bool,
+    // dedupe 時，重複資料至少每隔多久仍送出一次 (ms)
+    pub keepalive_ms: Option<u64>,
+    // 依 report descriptor 把每筆 input report 解成具名欄位 (hid-fields 事件)
+    pub decode: bool,
+    // decode 時只送出與上一筆相比有變化的欄位
+    pub delta: bool,
+    // delta 模式下，每隔多久仍送出一次完整欄位 (ms)
+    pub snapshot_ms: Option<u64>,
+    // 把鍵盤 report 解成按鍵名稱 (keyboard-state 事件)
+    pub keyboard: bool,
+    // 把滑鼠 report 解成按鍵 / 位移 / 滾輪 (mouse-state 事件)
+    pub mouse: bool,
+    // 把搖桿 / 手把 report 解成軸、方向鍵與按鍵 (gamepad-state 事件，只在變化時送出)
+    pub gamepad: bool,
+    // 把 DualShock 4 / DualSense 的完整 report 解成搖桿、陀螺儀、觸控板等 (sony-state 事件，每筆都送出)
+    pub sony: bool,
+    // 把 HID Sensor report 解成具單位的讀值 (sensor-reading 事件)
+    pub sensors: bool,
+    // 檢查 report 是否符合 schema (schema-violation 事件，同一種違規每秒最多一次)
+    pub validate: bool,
+    // hid-data 沿用舊格式，只送出原始位元組陣列 (尚未改用結構化 payload 的前端用)
+    pub legacy_payload: bool,
+}
+
+// output report 的傳送方式
+#[derive(Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMethod {
+    // interrupt OUT endpoint (hidapi 預設的 write)
+    #[default]
+    Interrupt,
+    // control endpoint 的 SET_REPORT，部分舊型 bootloader 只接受這種方式
+    Control,
+}
+
+fn write_output(dev: &dyn HidIo, report: &[u8], method: OutputMethod) -> Result<usize, String> {
+    match method {
+        OutputMethod::Interrupt => dev.write(report).map_err(|e| format!("寫入失敗: {}", e)),
+        OutputMethod::Control => dev.send_output_report(report).map(|_| report.len()),
+    }
+}
+
+// 指令回覆的辨識方式；不符合的封包照常送往 hid-data 事件
+#[derive(Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseMatch {
+    // 回覆以指定位元組開頭 (有 Report ID 的設備，第一個位元組即為 Report ID)
+    Prefix { bytes: Vec<u8> },
+    // 與送出的 report 同一個 Report ID (設備不使用 Report ID 時任何封包皆符合)
+    SameReportId,
+    // 十六進位 pattern，可用 ?? 或半個位元組的萬用字元 (例: "01 ?? A* ..")，格式見 BytePattern
+    Pattern { pattern: BytePattern },
+}
+
+impl ResponseMatch {
+    fn matches(&self, sent: &[u8], data: &[u8]) -> bool {
+        match self {
+            ResponseMatch::Prefix { bytes } => data.starts_with(bytes),
+            ResponseMatch::SameReportId => match sent.first() {
+                Some(0) | None => true,
+                Some(id) => data.first() == Some(id),
+            },
+            ResponseMatch::Pattern { pattern } => pattern.matches(data),
+        }
+    }
+}
+
+// exchange 寫入前讓匯流排安靜下來：先讀掉緩衝區中的 input report (交給監聽流程)，
+// 再等到連續 quiet_ms 沒有新的 report 才寫入，避免舊資料或設備主動回報被當成回覆
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Quiesce {
+    pub quiet_ms: u64,
+    // 等待安靜的上限，未指定為 quiet_ms 的 10 倍 (至少 1 秒)
+    pub quiesce_timeout_ms: Option<u64>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ExchangeReply {
+    // 逾時為空
+    pub response: Vec<u8>,
+    // 安靜期間讀掉的 report 數
+    pub drained: u32,
+    pub quiet_wait_ms: u64,
+    // 寫入到收到回覆的時間
+    pub latency_us: u64,
+}
+
+// send_hid_command 的等待時間設定；依指令開頭位元組 (呼叫端傳入的 data) 比對，最長的 prefix 優先
+// 沒有符合的規則時使用 default_timeout_ms
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct CommandDeadlines {
+    pub default_timeout_ms: Option<i32>,
+    pub rules: Vec<DeadlineRule>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct DeadlineRule {
+    pub prefix: Vec<u8>,
+    pub timeout_ms: i32,
+    #[serde(default)]
+    pub retries: Option<u32>,
+}
+
+impl CommandDeadlines {
+    // 回傳 (等待時間, 重送次數)
+    pub fn resolve(&self, data: &[u8]) -> (Option<i32>, Option<u32>) {
+        match self.rules.iter().filter(|r| data.starts_with(&r.prefix)).max_by_key(|r| r.prefix.len()) {
+            Some(rule) => (Some(rule.timeout_ms), rule.retries),
+            None => (self.default_timeout_ms, None),
+        }
+    }
+}
+
+// send_hid_command 的錯誤；逾時與其他失敗分開，讓前端可以決定是否重試
+#[derive(Serialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CommandError {
+    // 每次嘗試都在等待時間內沒有收到回覆
+    Timeout { message: String, timeout_ms: i32, attempts: u32, consecutive: u32 },
+    Failed { message: String },
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Failed { message }
+    }
+}
+
+// 設備的錯誤狀態；進入後拒絕所有寫入與獨佔操作並停止輪詢，直到 clear_error 或重新開啟
+#[derive(Serialize, Clone)]
+pub struct DeviceFault {
+    // 最後一次失敗的原因
+    pub error: String,
+    pub failures: u32,
+    // 進入錯誤狀態的時間 (Unix ms)
+    pub since_ms: u64,
+}
+
+// 開啟設備時取得、之後不會變動的資訊
+pub struct DeviceMeta {
+    pub identity: DeviceIdentity,
+    // 開啟時讀取的 report descriptor (部分平台可能取不到)
+    pub descriptor: Option<ReportDescriptor>,
+    // 由 descriptor 推算的各 report 長度，用於驗證寫入與配置讀取緩衝區
+    pub report_sizes: ReportSizes,
+    // 解析 descriptor 時 panic 的訊息 (此時視同取不到 descriptor)
+    pub descriptor_panic: Option<String>,
+}
+
+impl DeviceMeta {
+    // 讀取 report descriptor 並推算各 report 的長度
+    pub fn read(device: &dyn HidIo, identity: DeviceIdentity) -> Self {
+        let mut descriptor_panic = None;
+        let descriptor = device.report_descriptor().ok().and_then(|raw| {
+            match panic::catch_unwind(AssertUnwindSafe(|| descriptor::parse(&raw))) {
+                Ok(parsed) => parsed.ok(),
+                Err(payload) => {
+                    let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+                        .or_else(|| payload.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| "未知的錯誤".to_string());
+                    descriptor_panic = Some(format!("解析 report descriptor 時發生 panic: {}", message));
+                    None
+                }
+            }
+        });
+        let report_sizes = descriptor.as_ref()
+            .map(ReportSizes::from_descriptor)
+            .filter(|s| !s.input.is_empty() || !s.output.is_empty())
+            .unwrap_or_else(ReportSizes::fallback);
+        Self { identity, descriptor, report_sizes, descriptor_panic }
+    }
+}
+
+pub enum DeviceCommand {
+    // 寫入後等待符合 matcher 的 input report 作為回覆 (未指定則取下一筆)，逾時回傳空 Vec
+    Exchange {
+        report: Vec<u8>,
+        method: OutputMethod,
+        timeout_ms: i32,
+        matcher: Option<ResponseMatch>,
+        reply: Sender<Result<Vec<u8>, String>>,
+    },
+    // 先讓匯流排安靜下來再寫入並等待回覆 (見 Quiesce)
+    QuiescedExchange {
+        report: Vec<u8>,
+        method: OutputMethod,
+        timeout_ms: i32,
+        matcher: Option<ResponseMatch>,
+        quiesce: Quiesce,
+        reply: Sender<Result<ExchangeReply, String>>,
+    },
+    Write { report: Vec<u8>, method: OutputMethod, reply: Sender<Result<usize, String>> },
+    // 不寫入，等待設備主動送出符合 matcher 的 input report，逾時回傳空 Vec
+    Expect { matcher: ResponseMatch, timeout_ms: i32, reply: Sender<Result<Vec<u8>, String>> },
+    // 由 I/O 執行緒定期送出 request 並以 hid-poll 事件回報回覆；None 代表停止
+    Poll(Option<PollConfig>),
+    // 暫時把設備借給呼叫端獨佔，直到對方 drop 掉 release 的另一端
+    Lease { granted: Sender<()>, release: Receiver<()> },
+    Stop,
+}
+
+pub struct PollConfig {
+    pub report: Vec<u8>,
+    pub method: OutputMethod,
+    pub interval: Duration,
+    pub timeout_ms: i32,
+    pub matcher: Option<ResponseMatch>,
+}
+
+struct PollState {
+    config: PollConfig,
+    next_at: Instant,
+    seq: u64,
+}
+
+// --- 連線狀態 ---
+// opened -> listening <-> paused -> closed (stop_listening) / disconnected (讀取失敗、監聽執行緒異常結束)
+// 每次轉換發送 device-state 事件，前端不需要從有沒有收到資料推測設備狀態；closed 與 disconnected 為終止狀態，
+// 重新開啟同一路徑會建立新的 ManagedDevice (從 opened 開始)。
+//
+// { "path": "...", "state": "paused", "previous": "listening", "timestamp_ms": 0 }
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionState {
+    // 已開啟，I/O 執行緒尚未開始讀取
+    Opened,
+    Listening,
+    // 使用者暫停：仍處理指令，但不讀取監聽資料
+    Paused,
+    Closed,
+    Disconnected,
+}
+
+impl ConnectionState {
+    fn can_enter(self, next: ConnectionState) -> bool {
+        use ConnectionState::*;
+        matches!(
+            (self, next),
+            (Opened, Listening | Paused | Closed | Disconnected)
+                | (Listening, Paused | Closed | Disconnected)
+                | (Paused, Listening | Closed | Disconnected)
+        )
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct DeviceStateEvent {
+    pub path: String,
+    pub state: ConnectionState,
+    // 開啟時為 None
+    pub previous: Option<ConnectionState>,
+    pub timestamp_ms: i64,
+}
+
+pub struct ConnectionTracker {
+    app: AppHandle,
+    path: String,
+    state: Mutex<ConnectionState>,
+}
+
+impl ConnectionTracker {
+    fn new(app: &AppHandle, path: &str) -> Self {
+        let tracker = Self { app: app.clone(), path: path.to_string(), state: Mutex::new(ConnectionState::Opened) };
+        tracker.emit(ConnectionState::Opened, None);
+        tracker
+    }
+
+    fn emit(&self, state: ConnectionState, previous: Option<ConnectionState>) {
+        let _ = self.app.emit("device-state", DeviceStateEvent {
+            path: self.path.clone(),
+            state,
+            previous,
+            timestamp_ms: chrono::Local::now().timestamp_millis(),
+        });
+    }
+
+    pub fn get(&self) -> ConnectionState {
+        *self.state.lock().unwrap()
+    }
+
+    // 狀態沒有變化或不允許的轉換 (例如已關閉後再暫停) 不發送事件，回傳 false
+    fn transition(&self, next: ConnectionState) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if !state.can_enter(next) { return false; }
+        let previous = std::mem::replace(&mut *state, next);
+        drop(state);
+        self.emit(next, Some(previous));
+        true
+    }
+}
+
+#[derive(Clone)]
+pub struct ManagedDevice {
+    pub meta: Arc<DeviceMeta>,
+    device: Arc<Mutex<Box<dyn HidIo>>>,
+    commands: Sender<DeviceCommand>,
+    // 使用者手動暫停：I/O 執行緒仍處理指令，但不讀取監聽資料
+    pub user_paused: Arc<AtomicBool>,
+    pub stats: Arc<Mutex<DeviceStats>>,
+    pub options: Arc<Mutex<ListenOptions>>,
+    // 磁條卡擷取模式 (未啟用為 None)
+    pub msr: Arc<Mutex<Option<MsrCapture>>>,
+    // 鍵盤文字還原使用的配置 (set_layout 可更換)
+    pub keymap: Arc<Mutex<Arc<Keymap>>>,
+    // 對解碼欄位的監看運算式
+    pub watches: Arc<Mutex<Vec<Watch>>>,
+    pub latest: Arc<Mutex<LatestFields>>,
+    // 使用者指定的 report schema (未指定為 None)
+    pub schema: Arc<Mutex<Option<Arc<ReportSchema>>>>,
+    // 驗證模式的違規統計，換 schema 時清除
+    pub violations: Arc<Mutex<ViolationCounter>>,
+    // 即時 UDP 串流 (未啟用為 None)
+    pub stream: Arc<Mutex<Option<UdpStream>>>,
+    // 廠商協定的封包格式 (未指定為 None)
+    pub framing: Arc<Mutex<Option<Arc<FramingProfile>>>>,
+    pub fault: Arc<Mutex<Option<DeviceFault>>>,
+    pub connection: Arc<ConnectionTracker>,
+}
+
+// 各欄位最新的值 (dashboard 用)；tracking 為 false 時不解碼也不更新
+#[derive(Default)]
+pub struct LatestFields {
+    pub tracking: bool,
+    pub fields: BTreeMap<String, FieldValue>,
+    pub updated: Option<Instant>,
+}
+
+// 獨佔期間持有；drop 時 I/O 執行緒恢復運作
+pub struct DeviceLease {
+    device: Arc<Mutex<Box<dyn HidIo>>>,
+    _release: Sender<()>,
+}
+
+impl DeviceLease {
+    pub fn device(&self) -> Result<MutexGuard<'_, Box<dyn HidIo>>, String> {
+        self.device.lock().map_err(|_| "鎖定設備失敗".to_string())
+    }
+}
+
+const WORKER_GONE: &str = "設備 I/O 執行緒已結束";
+
+impl ManagedDevice {
+    fn send(&self, cmd: DeviceCommand) -> Result<(), String> {
+        self.commands.send(cmd).map_err(|_| WORKER_GONE.to_string())
+    }
+
+    fn check_fault(&self) -> Result<(), String> {
+        match self.fault.lock().unwrap().as_ref() {
+            Some(fault) => Err(format!("設備處於錯誤狀態 ({})，請清除錯誤或重新連接後再試", fault.error)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn exchange(
+        &self,
+        report: Vec<u8>,
+        method: OutputMethod,
+        timeout_ms: i32,
+        matcher: Option<ResponseMatch>,
+    ) -> Result<Vec<u8>, String> {
+        self.check_fault()?;
+        let (reply, rx) = mpsc::channel();
+        self.send(DeviceCommand::Exchange { report, method, timeout_ms, matcher, reply })?;
+        rx.recv().map_err(|_| WORKER_GONE.to_string())?
+    }
+
+    pub fn quiesced_exchange(
+        &self,
+        report: Vec<u8>,
+        method: OutputMethod,
+        timeout_ms: i32,
+        matcher: Option<ResponseMatch>,
+        quiesce: Quiesce,
+    ) -> Result<ExchangeReply, String> {
+        self.check_fault()?;
+        let (reply, rx) = mpsc::channel();
+        self.send(DeviceCommand::QuiescedExchange { report, method, timeout_ms, matcher, quiesce, reply })?;
+        rx.recv().map_err(|_| WORKER_GONE.to_string())?
+    }
+
+    pub fn write(&self, report: Vec<u8>, method: OutputMethod) -> Result<usize, String> {
+        self.check_fault()?;
+        let (reply, rx) = mpsc::channel();
+        self.send(DeviceCommand::Write { report, method, reply })?;
+        rx.recv().map_err(|_| WORKER_GONE.to_string())?
+    }
+
+    // 只讀取不寫入，因此錯誤狀態下仍可使用
+    pub fn expect(&self, matcher: ResponseMatch, timeout_ms: i32) -> Result<Vec<u8>, String> {
+        let (reply, rx) = mpsc::channel();
+        self.send(DeviceCommand::Expect { matcher, timeout_ms, reply })?;
+        rx.recv().map_err(|_| WORKER_GONE.to_string())?
+    }
+
+    // 等到佇列中前面的指令都完成後取得設備的獨佔權
+    pub fn lease(&self) -> Result<DeviceLease, String> {
+        self.check_fault()?;
+        let (granted, granted_rx) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel();
+        self.send(DeviceCommand::Lease { granted, release: release_rx })?;
+        granted_rx.recv().map_err(|_| WORKER_GONE.to_string())?;
+        Ok(DeviceLease { device: self.device.clone(), _release: release })
+    }
+
+    pub fn set_polling(&self, config: Option<PollConfig>) -> Result<(), String> {
+        self.send(DeviceCommand::Poll(config))
+    }
+
+    pub fn stop(&self) {
+        let _ = self.commands.send(DeviceCommand::Stop);
+    }
+
+    pub fn pause(&self) {
+        self.user_paused.store(true, Ordering::SeqCst);
+        self.connection.transition(ConnectionState::Paused);
+    }
+
+    pub fn resume(&self) {
+        self.user_paused.store(false, Ordering::SeqCst);
+        self.connection.transition(ConnectionState::Listening);
+    }
+}
+
+// 管理所有開啟中的設備
+#[derive(Default)]
+pub struct DeviceManager(pub Mutex<HashMap<String, ManagedDevice>>);
+
+impl DeviceManager {
+    pub fn get(&self, path: &str) -> Result<ManagedDevice, String> {
+        self.0.lock().unwrap().get(path).cloned().ok_or("設備未開啟監聽，請先啟動監聽".into())
+    }
+}
+
+// --- 開啟設備並啟動 I/O 執行緒 ---
+
+pub fn open(app: &AppHandle, api: &HidApi, path: &str, options: ListenOptions) -> Result<ManagedDevice, String> {
+    let (device, identity) = open_device(api, path)?;
+    Ok(start(app, path, device, identity, options))
+}
+
+// 只開啟設備而不啟動 I/O 執行緒 (短暫的查詢用，drop 即關閉)
+pub fn open_device(api: &HidApi, path: &str) -> Result<(Box<dyn HidIo>, DeviceIdentity), String> {
+    let device_info = api.device_list()
+        .find(|d| d.path().to_string_lossy() == path)
+        .ok_or("找不到設備")?;
+
+    let identity = DeviceIdentity {
+        vendor_id: device_info.vendor_id(),
+        product_id: device_info.product_id(),
+        usage_page: device_info.usage_page(),
+        usage: device_info.usage(),
+        interface_number: device_info.interface_number(),
+        release_number: device_info.release_number(),
+    };
+    let device = device_info.open_device(api).map_err(|e| e.to_string())?;
+    Ok((Box::new(device), identity))
+}
+
+// 以任意 I/O 後端 (實體或模擬設備) 建立 ManagedDevice 並啟動 I/O 執行緒
+pub fn start(
+    app: &AppHandle,
+    path: &str,
+    device: Box<dyn HidIo>,
+    identity: DeviceIdentity,
+    options: ListenOptions,
+) -> ManagedDevice {
+    let meta = DeviceMeta::read(device.as_ref(), identity);
+    if let Some(error) = &meta.descriptor_panic {
+        incident::capture(app, Incident {
+            kind: IncidentKind::DescriptorPanic,
+            error,
+            path,
+            device: meta.identity,
+            report_sizes: &meta.report_sizes,
+            command: None,
+            recent_reports: None,
+        });
+    }
+    let (commands, rx) = mpsc::channel();
+    let managed = ManagedDevice {
+        meta: Arc::new(meta),
+        device: Arc::new(Mutex::new(device)),
+        commands,
+        user_paused: Arc::new(AtomicBool::new(false)),
+        stats: Arc::new(Mutex::new(DeviceStats::new())),
+        options: Arc::new(Mutex::new(options)),
+        msr: Arc::new(Mutex::new(None)),
+        keymap: Arc::new(Mutex::new(app.state::<KeyLayouts>().0.lock().unwrap().for_device(path))),
+        watches: Arc::new(Mutex::new(Vec::new())),
+        latest: Arc::new(Mutex::new(LatestFields::default())),
+        schema: Arc::new(Mutex::new(None)),
+        violations: Arc::new(Mutex::new(ViolationCounter::default())),
+        stream: Arc::new(Mutex::new(None)),
+        framing: Arc::new(Mutex::new(None)),
+        fault: Arc::new(Mutex::new(None)),
+        connection: Arc::new(ConnectionTracker::new(app, path)),
+    };
+
+    let worker = Worker {
+        pipeline: ReportPipeline::new(app.clone(), path.to_string(), &managed),
+        managed: managed.clone(),
+        rx,
+        poll: None,
+        write_failures: 0,
+    };
+    thread::spawn(move || worker.run());
+    managed
+}
+
+struct Worker {
+    managed: ManagedDevice,
+    rx: Receiver<DeviceCommand>,
+    pipeline: ReportPipeline,
+    poll: Option<PollState>,
+    // 連續寫入失敗次數
+    write_failures: u32,
+}
+
+impl Worker {
+    fn run(mut self) {
+        let failure = match panic::catch_unwind(AssertUnwindSafe(|| self.listen())) {
+            Ok(result) => result.err().map(|e| {
+                let (code, os_code) = classify_read_error(&e);
+                (code, e, os_code)
+            }),
+            Err(payload) => {
+                let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "未知的錯誤".into());
+                Some((DeviceErrorCode::ListenerPanic, message, None))
+            }
+        };
+
+        // 清理狀態 (只移除自己，避免誤刪重新開啟的同一路徑)
+        let app = self.pipeline.app.clone();
+        let path = self.pipeline.path.clone();
+        {
+            let state = app.state::<DeviceManager>();
+            let mut manager = state.0.lock().unwrap();
+            if manager.get(&path).is_some_and(|d| Arc::ptr_eq(&d.meta, &self.managed.meta)) {
+                manager.remove(&path);
+            }
+        }
+
+        let Some((code, os_error, os_code)) = failure else {
+            self.managed.connection.transition(ConnectionState::Closed);
+            return;
+        };
+        self.managed.connection.transition(ConnectionState::Disconnected);
+        logging::log(&app, Severity::Error, Category::Device, Some(&path), format!("監聽已停止 ({:?}): {}", code, os_error));
+        let _ = app.emit("device-error", DeviceErrorEvent {
+            path,
+            code,
+            os_error,
+            os_code,
+            timestamp_ms: chrono::Local::now().timestamp_millis(),
+        });
+    }
+
+    // 正常停止時回傳 Ok，讀取失敗時回傳錯誤訊息
+    fn listen(&mut self) -> Result<(), String> {
+        let read_len = self.managed.meta.report_sizes.input_buffer_len();
+        let mut buf = vec![0u8; read_len];
+        if !self.managed.user_paused.load(Ordering::SeqCst) {
+            self.managed.connection.transition(ConnectionState::Listening);
+        }
+
+        loop {
+            // 暫停時改為等待指令，避免空轉
+            let paused = self.managed.user_paused.load(Ordering::SeqCst);
+            let next = if paused {
+                self.rx.recv_timeout(Duration::from_millis(50))
+                    .map_err(|e| e == RecvTimeoutError::Disconnected)
+            } else {
+                self.rx.try_recv().map_err(|e| e == TryRecvError::Disconnected)
+            };
+            match next {
+                Ok(DeviceCommand::Stop) | Err(true) => return Ok(()),
+                Ok(cmd) => {
+                    self.run_command(cmd, &mut buf);
+                    continue;
+                }
+                Err(false) => {}
+            }
+            if paused {
+                self.pipeline.interrupted = true;
+                continue;
+            }
+            self.run_poll(&mut buf);
+
+            let result = self.managed.device.lock().unwrap().read_timeout(&mut buf, READ_POLL_MS);
+            match result {
+                Ok(0) => {}
+                Ok(n) => self.pipeline.handle(&buf[..n]),
+                Err(e) => {
+                    // 讀取錯誤（可能是拔掉設備）
+                    self.managed.stats.lock().unwrap().record_error();
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    fn run_command(&mut self, cmd: DeviceCommand, buf: &mut [u8]) {
+        match cmd {
+            DeviceCommand::Exchange { report, method, timeout_ms, matcher, reply } => {
+                let _ = reply.send(self.exchange(&report, method, timeout_ms, matcher.as_ref(), buf));
+            }
+            DeviceCommand::QuiescedExchange { report, method, timeout_ms, matcher, quiesce, reply } => {
+                let result = self.quiesce(&quiesce, buf).and_then(|(drained, quiet_wait_ms)| {
+                    let started = Instant::now();
+                    let response = self.exchange(&report, method, timeout_ms, matcher.as_ref(), buf)?;
+                    let latency_us = if response.is_empty() { 0 } else { started.elapsed().as_micros() as u64 };
+                    Ok(ExchangeReply { response, drained, quiet_wait_ms, latency_us })
+                });
+                let _ = reply.send(result);
+            }
+            DeviceCommand::Write { report, method, reply } => {
+                let device = self.managed.device.clone();
+                let dev = device.lock().unwrap();
+                let result = write_output(dev.as_ref(), &report, method);
+                let _ = reply.send(self.track_write(result, &report));
+            }
+            DeviceCommand::Expect { matcher, timeout_ms, reply } => {
+                let device = self.managed.device.clone();
+                let dev = device.lock().unwrap();
+                let _ = reply.send(self.await_reply(dev.as_ref(), &[], timeout_ms, Some(&matcher), buf));
+            }
+            DeviceCommand::Poll(config) => {
+                self.poll = config.map(|config| PollState { config, next_at: Instant::now(), seq: 0 });
+            }
+            DeviceCommand::Lease { granted, release } => {
+                if granted.send(()).is_ok() {
+                    // 對方 drop DeviceLease 後 recv 會回傳 Err
+                    let _ = release.recv();
+                    self.pipeline.interrupted = true;
+                }
+            }
+            DeviceCommand::Stop => {}
+        }
+    }
+
+    // 到時間就送出輪詢 request；以固定間隔排程，落後太多時跳過錯過的輪次而不是連發
+    fn run_poll(&mut self, buf: &mut [u8]) {
+        if self.managed.fault.lock().unwrap().is_some() { return; }
+        let Some(poll) = self.poll.as_mut() else { return };
+        let now = Instant::now();
+        if now < poll.next_at { return; }
+        poll.next_at += poll.config.interval;
+        if poll.next_at < now { poll.next_at = now + poll.config.interval; }
+
+        let seq = poll.seq;
+        poll.seq += 1;
+        let report = poll.config.report.clone();
+        let (method, timeout_ms, matcher) = (poll.config.method, poll.config.timeout_ms, poll.config.matcher.clone());
+
+        let result = self.exchange(&report, method, timeout_ms, matcher.as_ref(), buf);
+        if result.is_err() { self.managed.stats.lock().unwrap().record_error(); }
+        let (response, error) = match result {
+            Ok(response) => (response, None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+        let _ = self.pipeline.app.emit("hid-poll", PollEvent { path: self.pipeline.path.clone(), seq, response, error });
+    }
+
+    // 記錄連續寫入失敗；達到門檻時進入錯誤狀態並發送 device-fault。每次失敗都留下異常快照 (有頻率限制)
+    fn track_write(&mut self, result: Result<usize, String>, report: &[u8]) -> Result<usize, String> {
+        match &result {
+            Ok(_) => {
+                self.write_failures = 0;
+                capture::record(&self.pipeline.app, &self.pipeline.path, Direction::Tx, report);
+            }
+            Err(e) => {
+                self.write_failures += 1;
+                self.capture_incident(IncidentKind::WriteFailed, e, report);
+                if self.write_failures >= FAULT_THRESHOLD {
+                    let since_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+                    let fault = DeviceFault { error: e.clone(), failures: self.write_failures, since_ms };
+                    *self.managed.fault.lock().unwrap() = Some(fault.clone());
+                    self.write_failures = 0;
+                    let message = format!("連續 {} 次寫入失敗，進入錯誤狀態: {}", fault.failures, e);
+                    logging::log(&self.pipeline.app, Severity::Error, Category::Device, Some(&self.pipeline.path), message);
+                    let _ = self.pipeline.app.emit("device-fault", FaultEvent { path: self.pipeline.path.clone(), fault: Some(fault) });
+                    self.capture_incident(IncidentKind::Fault, e, report);
+                }
+            }
+        }
+        result
+    }
+
+    fn capture_incident(&self, kind: IncidentKind, error: &str, command: &[u8]) {
+        let meta = &self.managed.meta;
+        incident::capture(&self.pipeline.app, Incident {
+            kind,
+            error,
+            path: &self.pipeline.path,
+            device: meta.identity,
+            report_sizes: &meta.report_sizes,
+            command: Some(command),
+            recent_reports: Some(&self.pipeline.recent),
+        });
+    }
+
+    // 讀掉 input report 直到連續 quiet_ms 沒有資料；回傳 (讀掉的筆數, 等待時間 ms)
+    fn quiesce(&mut self, quiesce: &Quiesce, buf: &mut [u8]) -> Result<(u32, u64), String> {
+        let device = self.managed.device.clone();
+        let dev = device.lock().unwrap();
+        let started = Instant::now();
+        let quiet = Duration::from_millis(quiesce.quiet_ms);
+        let limit = Duration::from_millis(quiesce.quiesce_timeout_ms.unwrap_or(quiesce.quiet_ms.saturating_mul(10).max(1000)));
+        let mut last = started;
+        let mut drained = 0;
+        loop {
+            // 負值代表無限等待，超過 i32 範圍時以上限代替
+            let wait = i32::try_from(quiet.saturating_sub(last.elapsed()).as_millis()).unwrap_or(i32::MAX);
+            match dev.read_timeout(buf, wait) {
+                Ok(0) => return Ok((drained, started.elapsed().as_millis() as u64)),
+                Ok(n) => {
+                    drained += 1;
+                    self.pipeline.handle(&buf[..n]);
+                    last = Instant::now();
+                    if started.elapsed() >= limit {
+                        return Err(format!("匯流排在 {} ms 內沒有安靜下來 (讀掉 {} 筆 report)", limit.as_millis(), drained));
+                    }
+                }
+                Err(e) => return Err(format!("讀取異常: {}", e)),
+            }
+        }
+    }
+
+    fn exchange(
+        &mut self,
+        report: &[u8],
+        method: OutputMethod,
+        timeout_ms: i32,
+        matcher: Option<&ResponseMatch>,
+        buf: &mut [u8],
+    ) -> Result<Vec<u8>, String> {
+        let device = self.managed.device.clone();
+        let dev = device.lock().unwrap();
+        let written = write_output(dev.as_ref(), report, method);
+        self.track_write(written, report)?;
+        self.await_reply(dev.as_ref(), report, timeout_ms, matcher, buf)
+    }
+
+    // 讀取直到符合 matcher 的 report (未指定則取下一筆)，逾時回傳空 Vec
+    fn await_reply(
+        &mut self,
+        dev: &dyn HidIo,
+        sent: &[u8],
+        timeout_ms: i32,
+        matcher: Option<&ResponseMatch>,
+        buf: &mut [u8],
+    ) -> Result<Vec<u8>, String> {
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms.max(0) as u64);
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now()).as_millis() as i32;
+            match dev.read_timeout(buf, remaining) {
+                Ok(0) => return Ok(Vec::new()),
+                Ok(n) => {
+                    let data = &buf[..n];
+                    match matcher {
+                        Some(m) if !m.matches(sent, data) => {
+                            // 不是這個指令的回覆，交給監聽流程 (handle 會計入統計)
+                            self.pipeline.handle(data);
+                            if remaining == 0 { return Ok(Vec::new()); }
+                        }
+                        _ => {
+                            self.managed.stats.lock().unwrap().record_report(n);
+                            self.pipeline.recent.push(data);
+                            capture::record(&self.pipeline.app, &self.pipeline.path, Direction::Rx, data);
+                            return Ok(data.to_vec());
+                        }
+                    }
+                }
+                Err(e) => return Err(format!("讀取異常: {}", e)),
+            }
+        }
+    }
+}
+
+// --- 監聽資料的處理流程 (hid-data 與各種解碼事件) ---
+
+struct ReportPipeline {
+    app: AppHandle,
+    path: String,
+    stats: Arc<Mutex<DeviceStats>>,
+    options: Arc<Mutex<ListenOptions>>,
+    msr: Arc<Mutex<Option<MsrCapture>>>,
+    keymap: Arc<Mutex<Arc<Keymap>>>,
+    watches: Arc<Mutex<Vec<Watch>>>,
+    latest: Arc<Mutex<LatestFields>>,
+    schema: Arc<Mutex<Option<Arc<ReportSchema>>>>,
+    violations: Arc<Mutex<ViolationCounter>>,
+    stream: Arc<Mutex<Option<UdpStream>>>,
+    framing: Arc<Mutex<Option<Arc<FramingProfile>>>>,
+    uses_report_ids: bool,
+    // 目前序號追蹤所依據的 schema，換 schema 時重新開始
+    counter_schema: Option<Arc<ReportSchema>>,
+    counters: CounterTracker,
+    // 上一筆 report 之後讀取曾中斷 (暫停 / 獨佔操作)
+    interrupted: bool,
+    last_payload: Vec<u8>,
+    last_emit: Instant,
+    // 下一筆 report 的 hid-data 序號
+    next_seq: u64,
+    descriptor: Option<ReportDescriptor>,
+    // 各 Report ID 上一次解碼的欄位與完整送出的時間 (delta 模式用)
+    last_fields: HashMap<u8, (DecodedReport, Instant)>,
+    // 不是鍵盤的設備為 None
+    keyboard: Option<KeyboardDecoder>,
+    mouse: Option<MouseDecoder>,
+    gamepad: Option<GamepadDecoder>,
+    sony: Option<SonyDecoder>,
+    // 自動偵測的協定插件，用於解析耳機按鍵等主動回報
+    plugin: Option<Box<dyn ProtocolPlugin>>,
+    headset_state: HeadsetStatus,
+    telephony: Option<TelephonyLayout>,
+    telephony_state: TelephonyState,
+    power: Option<PowerLayout>,
+    power_state: PowerStatus,
+    sensor: Option<SensorLayout>,
+    is_scale: bool,
+    last_scale: Option<ScaleReading>,
+    // 最近的 report，異常快照用
+    recent: RecentReports,
+}
+
+impl ReportPipeline {
+    fn new(app: AppHandle, path: String, managed: &ManagedDevice) -> Self {
+        let meta = &managed.meta;
+        Self {
+            app,
+            path,
+            stats: managed.stats.clone(),
+            options: managed.options.clone(),
+            msr: managed.msr.clone(),
+            keymap: managed.keymap.clone(),
+            watches: managed.watches.clone(),
+            latest: managed.latest.clone(),
+            schema: managed.schema.clone(),
+            violations: managed.violations.clone(),
+            stream: managed.stream.clone(),
+            framing: managed.framing.clone(),
+            uses_report_ids: meta.report_sizes.uses_report_ids,
+            counter_schema: None,
+            counters: CounterTracker::default(),
+            interrupted: false,
+            last_payload: Vec::new(),
+            last_emit: Instant::now(),
+            next_seq: 0,
+            descriptor: meta.descriptor.clone(),
+            last_fields: HashMap::new(),
+            keyboard: KeyboardDecoder::new(meta.descriptor.as_ref()),
+            mouse: MouseDecoder::new(&meta.identity, meta.descriptor.as_ref()),
+            gamepad: GamepadDecoder::new(&meta.identity, meta.descriptor.as_ref()),
+            sony: SonyDecoder::new(&meta.identity),
+            plugin: protocols::resolve(None, &meta.identity).ok(),
+            headset_state: HeadsetStatus::default(),
+            telephony: meta.descriptor.as_ref().and_then(TelephonyLayout::from_descriptor),
+            telephony_state: TelephonyState::default(),
+            power: meta.descriptor.as_ref().and_then(PowerLayout::from_descriptor),
+            power_state: PowerStatus::default(),
+            sensor: meta.descriptor.as_ref().and_then(SensorLayout::from_descriptor),
+            is_scale: meta.identity.usage_page == scale::PAGE_SCALE,
+            last_scale: None,
+            recent: RecentReports::default(),
+        }
+    }
+
+    // 第一筆或到了 snapshot 時間送出完整欄位，其餘只送有變化的欄位 (沒有變化就不送)
+    fn emit_delta(&mut self, report: DecodedReport, snapshot: Option<Duration>, timestamp: Timestamp) {
+        let (event, snapshot_at) = match self.last_fields.get(&report.report_id) {
+            Some((last, at)) if snapshot.is_none_or(|s| at.elapsed() < s) => {
+                let changed = report.fields.iter()
+                    .filter(|(name, value)| last.fields.get(*name) != Some(*value))
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect();
+                let changed = DecodedReport { report_id: report.report_id, fields: changed };
+                (FieldsEvent { path: self.path.clone(), report: changed, full: false, timestamp }, *at)
+            }
+            _ => (FieldsEvent { path: self.path.clone(), report: report.clone(), full: true, timestamp }, Instant::now()),
+        };
+        if event.full || !event.report.fields.is_empty() {
+            let _ = self.app.emit("hid-fields", event);
+        }
+        self.last_fields.insert(report.report_id, (report, snapshot_at));
+    }
+
+    fn handle(&mut self, data: &[u8]) {
+        let timestamp = self.app.state::<CaptureClock>().now();
+        self.stats.lock().unwrap().record_report(data.len());
+        self.recent.push(data);
+        capture::record(&self.app, &self.path, Direction::Rx, data);
+
+        let opts = self.options.lock().unwrap().clone();
+        let ms = |v: Option<u64>| v.map(Duration::from_millis);
+        let (dedupe, keepalive, decode, delta, snapshot) =
+            (opts.dedupe, ms(opts.keepalive_ms), opts.decode, opts.delta, ms(opts.snapshot_ms));
+        let duplicate = dedupe && self.last_payload == data;
+        let keepalive_due = keepalive.is_some_and(|k| self.last_emit.elapsed() >= k);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        if !duplicate || keepalive_due {
+            if opts.legacy_payload {
+                let _ = self.app.emit("hid-data", data.to_vec());
+            } else {
+                let (report_id, body) = match data.split_first() {
+                    Some((&id, rest)) if self.uses_report_ids => (Some(id), rest),
+                    _ => (None, data),
+                };
+                let event = HidDataEvent { path: self.path.clone(), report_id, seq, timestamp, data: body.to_vec() };
+                let _ = self.app.emit("hid-data", event);
+            }
+            self.last_emit = Instant::now();
+        }
+        if !duplicate {
+            self.last_payload = data.to_vec();
+        }
+
+        let framing = self.framing.lock().unwrap().clone();
+        let frame = framing.as_ref().and_then(|f| f.locate(data, self.uses_report_ids).map(|frame| f.unframe(frame)));
+        if let Some(result) = frame {
+            let (payload, error) = match result {
+                Ok(payload) => (Some(payload), None),
+                Err(e) => (None, Some(e)),
+            };
+            let _ = self.app.emit("hid-frame", FrameEvent { path: self.path.clone(), payload, error, timestamp });
+        }
+
+        let streaming = self.stream.lock().unwrap().is_some();
+        // 要串流的數值通道 (名稱, 值)
+        let mut channels: Vec<(String, f64)> = Vec::new();
+
+        let decoded = {
+            let mut watches = self.watches.lock().unwrap();
+            let mut latest = self.latest.lock().unwrap();
+            let decoded = self.descriptor.as_ref()
+                .filter(|_| decode || streaming || latest.tracking || !watches.is_empty())
+                .and_then(|d| d.decode(ReportKind::Input, data));
+            if let Some(report) = decoded.as_ref().filter(|_| latest.tracking) {
+                latest.fields.extend(report.fields.iter().map(|(k, v)| (k.clone(), v.clone())));
+                latest.updated = Some(Instant::now());
+            }
+            for watch in watches.iter_mut() {
+                let Some(report) = decoded.as_ref().filter(|r| watch.update(r)) else { continue };
+                let _ = self.app.emit("watch-triggered", WatchEvent {
+                    path: self.path.clone(),
+                    name: watch.name.clone(),
+                    expression: watch.expression.clone(),
+                    report: report.clone(),
+                });
+            }
+            decoded
+        };
+        if let Some(report) = decoded.as_ref().filter(|_| streaming) {
+            for (name, value) in &report.fields {
+                match value {
+                    FieldValue::Number(v) => channels.push((name.clone(), *v as f64)),
+                    FieldValue::List(list) => channels.extend(list.iter().enumerate().map(|(i, v)| (format!("{}[{}]", name, i), *v as f64))),
+                    FieldValue::Usages(_) => {}
+                }
+            }
+        }
+        if let Some(report) = decoded.filter(|_| decode) {
+            if delta {
+                self.emit_delta(report, snapshot, timestamp);
+            } else {
+                let _ = self.app.emit("hid-fields", FieldsEvent { path: self.path.clone(), report, full: true, timestamp });
+            }
+        }
+
+        let schema = self.schema.lock().unwrap().clone();
+        if let Some(schema) = schema {
+            if !self.counter_schema.as_ref().is_some_and(|s| Arc::ptr_eq(s, &schema)) {
+                self.counters.reset();
+                self.violations.lock().unwrap().reset();
+                self.counter_schema = Some(schema.clone());
+            }
+            if opts.validate {
+                for violation in schema.check(data) {
+                    let Some(count) = self.violations.lock().unwrap().record(&violation) else { continue };
+                    let _ = self.app.emit("schema-violation", ViolationEvent { path: self.path.clone(), violation, count });
+                }
+            }
+            for loss in self.counters.check(&schema, data, self.interrupted) {
+                self.stats.lock().unwrap().record_loss(loss.cause, loss.missed);
+                let _ = self.app.emit("report-loss", LossEvent { path: self.path.clone(), loss });
+            }
+            if let Some(decoded) = schema.decode(data) {
+                if streaming {
+                    channels.extend(decoded.values.iter().map(|v| (v.name.clone(), v.value)));
+                }
+                let _ = self.app.emit("schema-fields", SchemaEvent { path: self.path.clone(), decoded, timestamp });
+            }
+        }
+        self.interrupted = false;
+
+        if let Some(state) = self.keyboard.as_mut().filter(|_| opts.keyboard).and_then(|k| k.feed(data, &self.keymap.lock().unwrap())) {
+            let _ = self.app.emit("keyboard-state", KeyboardEvent { path: self.path.clone(), state, raw: data.to_vec() });
+        }
+
+        if let Some(state) = self.mouse.as_ref().filter(|_| opts.mouse).and_then(|m| m.feed(data)) {
+            let _ = self.app.emit("mouse-state", MouseEvent { path: self.path.clone(), state });
+        }
+
+        if let Some(state) = self.gamepad.as_mut().filter(|_| opts.gamepad).and_then(|g| g.feed(data)) {
+            let _ = self.app.emit("gamepad-state", GamepadEvent { path: self.path.clone(), state });
+        }
+
+        if let Some(state) = self.sony.as_ref().filter(|_| opts.sony).and_then(|s| s.feed(data)) {
+            self.stats.lock().unwrap().record_battery(state.battery.percent);
+            let _ = self.app.emit("sony-state", SonyEvent { path: self.path.clone(), state });
+        }
+
+        if let Some(reading) = self.sensor.as_ref().filter(|_| opts.sensors || streaming).and_then(|s| s.decode_input(data)) {
+            if streaming {
+                let prefix = format!("sensor{}", reading.report_id);
+                channels.extend(reading.values.iter().map(|v| (format!("{}.{}", prefix, v.name), v.value)));
+            }
+            if opts.sensors {
+                let _ = self.app.emit("sensor-reading", SensorEvent { path: self.path.clone(), reading, timestamp });
+            }
+        }
+        if let Some(stream) = self.stream.lock().unwrap().as_mut() {
+            stream.send(&self.path, &channels, timestamp);
+        }
+
+        let headset = self.plugin.as_ref().and_then(|p| p.headset());
+        if let Some(update) = headset.and_then(|h| h.decode_report(data)) {
+            if let Some(changed) = self.headset_state.merge(&update) {
+                if let Some(percent) = changed.battery { self.stats.lock().unwrap().record_battery(percent); }
+                let _ = self.app.emit("headset-event", HeadsetEvent {
+                    path: self.path.clone(),
+                    changed,
+                    state: self.headset_state.clone(),
+                });
+            }
+        }
+
+        if let Some(update) = self.power.as_ref().and_then(|p| p.decode_input(data)) {
+            if self.power_state.merge(&update) {
+                if let Some(percent) = self.power_state.battery_percent { self.stats.lock().unwrap().record_battery(percent.round() as u8); }
+                let _ = self.app.emit("power-status", PowerEvent {
+                    path: self.path.clone(),
+                    status: self.power_state.clone(),
+                    error: None,
+                });
+            }
+        }
+
+        if let Some(state) = self.telephony.as_ref().and_then(|t| t.decode(data)) {
+            if state != self.telephony_state {
+                self.telephony_state = state.clone();
+                let _ = self.app.emit("telephony-event", TelephonyEvent { path: self.path.clone(), state });
+            }
+        }
+
+        let swipe = self.msr.lock().unwrap().as_mut().and_then(|c| c.feed(data));
+        if let Some(swipe) = swipe {
+            let _ = self.app.emit("msr-swipe", MsrEvent { path: self.path.clone(), swipe });
+        }
+
+        if let Some(reading) = scale::decode(data).filter(|_| self.is_scale) {
+            if self.last_scale.as_ref() != Some(&reading) {
+                self.last_scale = Some(reading.clone());
+                let _ = self.app.emit("scale-data", ScaleEvent { path: self.path.clone(), reading });
+            }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+// --- 數值資料匯出 (MATLAB / NumPy) ---
+// 每個解碼通道輸出成一個 double 陣列，另外附上時間戳向量 "timestamp"。
+//   .mat: MATLAB v5 格式，每個通道為 N x 1 的變數 (load 後直接可用)
+//   .npz: 未壓縮的 zip，每個通道一個 .npy (numpy.load 後以名稱取用)
+// 通道名稱會轉成合法的變數名稱 (英數字與底線、英文字母開頭)。
+// 時鐘資訊 (ClockMetadata，JSON 文字) 在 .mat 中為字元變數 "clock"，在 .npz 中為 clock.json
+// (numpy.load 後取得 bytes)，時間戳才能與其他儀器的資料對齊。
+
+use crate::clock::ClockMetadata;
+use crate::checksum::Checksum;
+use chrono::{Datelike, Local, Timelike};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Mat,
+    Npz,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ChannelData {
+    pub timestamps: Vec<f64>,
+    pub channels: BTreeMap<String, Vec<f64>>,
+}
+
+// MATLAB 變數名稱上限
+const MAX_NAME_LEN: usize = 63;
+const TIMESTAMP_NAME: &str = "timestamp";
+
+fn sanitize(name: &str) -> String {
+    let mut out: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !out.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        out.insert_str(0, "ch_");
+    }
+    out.truncate(MAX_NAME_LEN);
+    out
+}
+
+// 檢查長度並整理成 (變數名稱, 資料)，時間戳放在第一個
+fn arrays(data: &ChannelData) -> Result<Vec<(String, &[f64])>, String> {
+    let n = data.timestamps.len();
+    let mut out: Vec<(String, &[f64])> = vec![(TIMESTAMP_NAME.to_string(), &data.timestamps)];
+    for (name, values) in &data.channels {
+        if values.len() != n {
+            return Err(format!("通道 {} 有 {} 筆資料，與時間戳 {} 筆不符", name, values.len(), n));
+        }
+        let var = sanitize(name);
+        if out.iter().any(|(v, _)| *v == var) {
+            return Err(format!("通道 {} 轉換後的名稱 {} 重複", name, var));
+        }
+        out.push((var, values));
+    }
+    Ok(out)
+}
+
+// --- MATLAB v5 ---
+
+const MI_INT8: u32 = 1;
+const MI_UINT16: u32 = 4;
+const MI_INT32: u32 = 5;
+const MI_UINT32: u32 = 6;
+const MI_DOUBLE: u32 = 9;
+const MI_MATRIX: u32 = 14;
+const MX_CHAR_CLASS: u32 = 4;
+const MX_DOUBLE_CLASS: u32 = 6;
+const CLOCK_NAME: &str = "clock";
+
+// data element: [type u32][size u32][data]，資料補齊到 8 bytes
+fn mat_element(out: &mut Vec<u8>, ty: u32, data: &[u8]) {
+    out.extend(ty.to_le_bytes());
+    out.extend((data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    out.resize(out.len().next_multiple_of(8), 0);
+}
+
+fn mat_array(name: &str, class: u32, dims: [i32; 2], ty: u32, data: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    let flags: Vec<u8> = [class, 0].iter().flat_map(|v| v.to_le_bytes()).collect();
+    mat_element(&mut body, MI_UINT32, &flags);
+    let dims: Vec<u8> = dims.iter().flat_map(|v| v.to_le_bytes()).collect();
+    mat_element(&mut body, MI_INT32, &dims);
+    mat_element(&mut body, MI_INT8, name.as_bytes());
+    mat_element(&mut body, ty, data);
+
+    let mut out = Vec::with_capacity(body.len() + 8);
+    mat_element(&mut out, MI_MATRIX, &body);
+    out
+}
+
+fn mat_variable(name: &str, values: &[f64]) -> Vec<u8> {
+    let real: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    mat_array(name, MX_DOUBLE_CLASS, [values.len() as i32, 1], MI_DOUBLE, &real)
+}
+
+// 1 x N 的字元陣列 (UTF-16)
+fn mat_text(name: &str, text: &str) -> Vec<u8> {
+    let units: Vec<u16> = text.encode_utf16().collect();
+    let data: Vec<u8> = units.iter().flat_map(|u| u.to_le_bytes()).collect();
+    mat_array(name, MX_CHAR_CLASS, [1, units.len() as i32], MI_UINT16, &data)
+}
+
+fn clock_json(clock: &ClockMetadata) -> String {
+    serde_json::to_string(clock).unwrap_or_default()
+}
+
+pub fn to_mat(data: &ChannelData, clock: &ClockMetadata) -> Result<Vec<u8>, String> {
+    let arrays = arrays(data)?;
+    let text = format!("MATLAB 5.0 MAT-file, Platform: {}, Created on: {}",
+        std::env::consts::OS, Local::now().format("%a %b %e %H:%M:%S %Y"));
+    let mut out = text.into_bytes();
+    out.resize(116, b' ');
+    // subsystem data offset (不使用)
+    out.extend([0u8; 8]);
+    out.extend(0x0100u16.to_le_bytes());
+    out.extend(b"IM");
+    for (name, values) in arrays {
+        out.extend(mat_variable(&name, values));
+    }
+    out.extend(mat_text(CLOCK_NAME, &clock_json(clock)));
+    Ok(out)
+}
+
+// --- NumPy .npz ---
+
+fn npy(values: &[f64]) -> Vec<u8> {
+    let mut header = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': ({},), }}", values.len());
+    // magic(6) + version(2) + header_len(2) + header 需對齊 64 bytes，結尾為換行
+    let total = (10 + header.len() + 1).next_multiple_of(64);
+    while 10 + header.len() + 1 < total { header.push(' '); }
+    header.push('\n');
+
+    let mut out = b"\x93NUMPY\x01\x00".to_vec();
+    out.extend((header.len() as u16).to_le_bytes());
+    out.extend(header.into_bytes());
+    out.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+    out
+}
+
+// 不壓縮 (stored) 的 zip
+fn zip(files: &[(String, Vec<u8>)]) -> Result<Vec<u8>, String> {
+    let now = Local::now();
+    let time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+    let date = (((now.year() - 1980).max(0) as u32) << 9 | now.month() << 5 | now.day()) as u16;
+
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+    for (name, data) in files {
+        let offset = u32::try_from(out.len()).map_err(|_| "匯出資料超過 4 GB，zip 格式不支援")?;
+        let size = u32::try_from(data.len()).map_err(|_| "匯出資料超過 4 GB，zip 格式不支援")?;
+        let crc = Checksum::Crc32.compute(data);
+        // version, flags, method, time, date, crc, 壓縮後 / 原始大小, 名稱長度, extra 長度
+        let mut common = Vec::new();
+        common.extend(20u16.to_le_bytes());
+        common.extend(0u16.to_le_bytes());
+        common.extend(0u16.to_le_bytes());
+        common.extend(time.to_le_bytes());
+        common.extend(date.to_le_bytes());
+        common.extend(crc.to_le_bytes());
+        common.extend(size.to_le_bytes());
+        common.extend(size.to_le_bytes());
+        common.extend((name.len() as u16).to_le_bytes());
+        common.extend(0u16.to_le_bytes());
+
+        out.extend(0x0403_4b50u32.to_le_bytes());
+        out.extend(&common);
+        out.extend(name.as_bytes());
+        out.extend(data);
+
+        central.extend(0x0201_4b50u32.to_le_bytes());
+        central.extend(20u16.to_le_bytes());
+        central.extend(&common);
+        // comment 長度, disk, 內部屬性, 外部屬性, local header 位置
+        central.extend(0u16.to_le_bytes());
+        central.extend(0u16.to_le_bytes());
+        central.extend(0u16.to_le_bytes());
+        central.extend(0u32.to_le_bytes());
+        central.extend(offset.to_le_bytes());
+        central.extend(name.as_bytes());
+    }
+    let cd_offset = u32::try_from(out.len()).map_err(|_| "匯出資料超過 4 GB，zip 格式不支援")?;
+    out.extend(&central);
+    out.extend(0x0605_4b50u32.to_le_bytes());
+    out.extend(0u16.to_le_bytes());
+    out.extend(0u16.to_le_bytes());
+    out.extend((files.len() as u16).to_le_bytes());
+    out.extend((files.len() as u16).to_le_bytes());
+    out.extend((central.len() as u32).to_le_bytes());
+    out.extend(cd_offset.to_le_bytes());
+    out.extend(0u16.to_le_bytes());
+    Ok(out)
+}
+
+pub fn to_npz(data: &ChannelData, clock: &ClockMetadata) -> Result<Vec<u8>, String> {
+    let mut files: Vec<(String, Vec<u8>)> = arrays(data)?.into_iter()
+        .map(|(name, values)| (format!("{}.npy", name), npy(values)))
+        .collect();
+    files.push((format!("{}.json", CLOCK_NAME), clock_json(clock).into_bytes()));
+    zip(&files)
+}
+
+pub fn write(file: &str, format: ExportFormat, data: &ChannelData, clock: &ClockMetadata) -> Result<(), String> {
+    let bytes = match format {
+        ExportFormat::Mat => to_mat(data, clock)?,
+        ExportFormat::Npz => to_npz(data, clock)?,
+    };
+    std::fs::write(file, bytes).map_err(|e| format!("寫入 {} 失敗: {}", file, e))
+}
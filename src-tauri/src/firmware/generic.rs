@@ -0,0 +1,125 @@
+// --- 通用 bootloader ---
+// 以設定描述指令格式，適用於自製或簡單的 HID bootloader：
+//   抹除: [erase]
+//   寫入: [write][address][長度 1 byte][資料]
+//   讀取: [read][address][長度 1 byte]，回覆為 [read][資料]
+//   結束: [finish]
+// 設定 ack 時，每個指令都需等到以 ack 開頭的回覆。
+
+use super::{Bootloader, FirmwareImage};
+use crate::protocols::Transport;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+pub const NAME: &str = "generic";
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct GenericConfig {
+    pub block_size: usize,
+    // 未設定代表寫入前不需抹除
+    pub erase: Option<Vec<u8>>,
+    pub erase_timeout_ms: u64,
+    pub write: Vec<u8>,
+    // 未設定代表不支援回讀驗證
+    pub read: Option<Vec<u8>>,
+    // 位址欄位寬度 (1..=4)
+    pub address_bytes: usize,
+    pub big_endian: bool,
+    pub ack: Option<Vec<u8>>,
+    pub finish: Option<Vec<u8>>,
+    pub timeout_ms: u64,
+}
+
+impl Default for GenericConfig {
+    fn default() -> Self {
+        Self {
+            block_size: 32,
+            erase: None,
+            erase_timeout_ms: 10_000,
+            write: vec![0x01],
+            read: None,
+            address_bytes: 4,
+            big_endian: false,
+            ack: None,
+            finish: None,
+            timeout_ms: 1000,
+        }
+    }
+}
+
+pub struct GenericBootloader {
+    config: GenericConfig,
+}
+
+impl GenericBootloader {
+    pub fn new(config: GenericConfig) -> Result<Self, String> {
+        if !(1..=4).contains(&config.address_bytes) { return Err("address_bytes 只能是 1..=4".into()); }
+        if !(1..=0xFF).contains(&config.block_size) { return Err("block_size 只能是 1..=255".into()); }
+        Ok(Self { config })
+    }
+
+    fn command(&self, prefix: &[u8], address: u32, len: usize) -> Vec<u8> {
+        let bytes = if self.config.big_endian { address.to_be_bytes() } else { address.to_le_bytes() };
+        let width = self.config.address_bytes;
+        let mut out = prefix.to_vec();
+        if self.config.big_endian { out.extend(&bytes[4 - width..]) } else { out.extend(&bytes[..width]) }
+        out.push(len as u8);
+        out
+    }
+
+    // 等待以 prefix 開頭的回覆，其他封包略過
+    fn wait_for(&self, t: &dyn Transport, prefix: &[u8], timeout_ms: u64) -> Result<Vec<u8>, String> {
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() { return Err("等待 bootloader 回覆逾時".into()); }
+            let resp = t.read(left.as_millis() as i32)?;
+            if !resp.is_empty() && resp.starts_with(prefix) { return Ok(resp); }
+        }
+    }
+
+    fn send(&self, t: &dyn Transport, data: &[u8], timeout_ms: u64) -> Result<(), String> {
+        t.write(data)?;
+        if let Some(ack) = &self.config.ack {
+            self.wait_for(t, ack, timeout_ms)?;
+        }
+        Ok(())
+    }
+}
+
+impl Bootloader for GenericBootloader {
+    fn name(&self) -> &'static str { NAME }
+
+    fn block_size(&self) -> usize { self.config.block_size }
+
+    fn erase(&mut self, t: &dyn Transport, _image: &FirmwareImage) -> Result<(), String> {
+        match &self.config.erase {
+            Some(cmd) => self.send(t, cmd, self.config.erase_timeout_ms),
+            None => Ok(()),
+        }
+    }
+
+    fn write_block(&mut self, t: &dyn Transport, address: u32, data: &[u8]) -> Result<(), String> {
+        let mut cmd = self.command(&self.config.write, address, data.len());
+        cmd.extend_from_slice(data);
+        self.send(t, &cmd, self.config.timeout_ms)
+    }
+
+    fn supports_read(&self) -> bool { self.config.read.is_some() }
+
+    fn read_block(&mut self, t: &dyn Transport, address: u32, len: usize) -> Result<Vec<u8>, String> {
+        let prefix = self.config.read.clone().ok_or("未設定讀取指令")?;
+        t.write(&self.command(&prefix, address, len))?;
+        let resp = self.wait_for(t, &prefix, self.config.timeout_ms)?;
+        Ok(resp[prefix.len()..].iter().take(len).copied().collect())
+    }
+
+    fn finish(&mut self, t: &dyn Transport) -> Result<(), String> {
+        match &self.config.finish {
+            // 設備可能立即重新啟動，不等待確認
+            Some(cmd) => t.write(cmd).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,206 @@
+// --- UF2 bootloader 的 HID 介面 (HF2) ---
+// 支援 UF2 的 bootloader (SAMD21/51、nRF52 等) 另外提供 HID 介面，以 HF2 協定寫入 flash。
+// 每個 report 第 0 個 byte：bit 6-7 為類型 (0x00 中間封包, 0x40 最後一個封包, 0x80 / 0xC0 為序列輸出)，
+// bit 0-5 為本封包的資料長度 (最多 63)；多個封包組成一則訊息：
+//   指令: [command u32][tag u16][0][0][參數]
+//   回覆: [tag u16][status][status info][資料]，status 0 代表成功
+// 依 page 寫入 (WRITE_FLASH_PAGE)，以 CHKSUM_PAGES 回傳的 CRC16 驗證，最後 RESET_INTO_APP。
+// 每次寫入整個 page (由 page 起點開始)；segment 從 page 中間開始或結束時其餘補 0xFF，
+// 同一個 page 內的多段資料合併後一起寫入，避免後寫的覆蓋先寫的。
+// HID 介面使用 vendor usage page 0xFF97；版本取自 INFO 回傳的 INFO_UF2.TXT 第一行 ("UF2 Bootloader v3.6.0 ...")。
+
+use super::uf2::family_name;
+use super::{word, Bootloader, FirmwareImage};
+use crate::checksum::Checksum;
+use crate::protocols::{DeviceIdentity, Transport};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::{Duration, Instant};
+
+pub const NAME: &str = "hf2";
+const USAGE_PAGE_HF2: u16 = 0xFF97;
+
+const REPORT_LEN: usize = 64;
+const PACKET_INNER: u8 = 0x00;
+const PACKET_FINAL: u8 = 0x40;
+const PACKET_TYPE_MASK: u8 = 0xC0;
+const PACKET_LEN_MASK: u8 = 0x3F;
+
+const CMD_BININFO: u32 = 0x0001;
+const CMD_INFO: u32 = 0x0002;
+const CMD_RESET_INTO_APP: u32 = 0x0003;
+const CMD_RESET_INTO_BOOTLOADER: u32 = 0x0004;
+const CMD_WRITE_FLASH_PAGE: u32 = 0x0006;
+const CMD_CHKSUM_PAGES: u32 = 0x0007;
+
+const MODE_BOOTLOADER: u32 = 0x01;
+
+// 每個指令遞增，回覆的 tag 需相同
+static TAG: AtomicU16 = AtomicU16::new(0);
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct Hf2Config {
+    pub timeout_ms: u64,
+    // 設備沒有回報 family ID 時仍允許寫入帶有 family ID 的映像
+    pub allow_unknown_family: bool,
+}
+
+impl Default for Hf2Config {
+    fn default() -> Self {
+        Self { timeout_ms: 2000, allow_unknown_family: true }
+    }
+}
+
+pub fn matches(id: &DeviceIdentity) -> bool {
+    id.usage_page == USAGE_PAGE_HF2
+}
+
+pub struct Hf2Bootloader {
+    config: Hf2Config,
+    // 由 BININFO 取得
+    page_size: usize,
+    max_message: usize,
+    family_id: Option<u32>,
+    // 已寫入的 page 內容 (page 起點 -> 資料)，合併同一 page 內的區塊並供驗證使用
+    pages: HashMap<u32, Vec<u8>>,
+}
+
+impl Hf2Bootloader {
+    pub fn new(config: Hf2Config) -> Result<Self, String> {
+        Ok(Self { config, page_size: 256, max_message: REPORT_LEN, family_id: None, pages: HashMap::new() })
+    }
+
+    fn send(&self, t: &dyn Transport, cmd: u32, args: &[u8]) -> Result<u16, String> {
+        let tag = TAG.fetch_add(1, Ordering::Relaxed);
+        let mut msg = cmd.to_le_bytes().to_vec();
+        msg.extend(tag.to_le_bytes());
+        msg.extend([0, 0]);
+        msg.extend_from_slice(args);
+        let max = PACKET_LEN_MASK as usize;
+        let count = msg.len().div_ceil(max);
+        for (i, chunk) in msg.chunks(max).enumerate() {
+            let kind = if i + 1 == count { PACKET_FINAL } else { PACKET_INNER };
+            let mut packet = vec![kind | chunk.len() as u8];
+            packet.extend_from_slice(chunk);
+            t.write(&packet)?;
+        }
+        Ok(tag)
+    }
+
+    fn receive(&self, t: &dyn Transport, tag: u16) -> Result<Vec<u8>, String> {
+        let deadline = Instant::now() + Duration::from_millis(self.config.timeout_ms);
+        let mut msg = Vec::new();
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() { return Err("等待 bootloader 回覆逾時".into()); }
+            let packet = t.read(left.as_millis() as i32)?;
+            let Some(&header) = packet.first() else { continue };
+            let len = (header & PACKET_LEN_MASK) as usize;
+            let kind = header & PACKET_TYPE_MASK;
+            // 序列輸出 (stdout / stderr) 不屬於回覆
+            if kind != PACKET_INNER && kind != PACKET_FINAL { continue; }
+            msg.extend_from_slice(&packet[1..(1 + len).min(packet.len())]);
+            if kind != PACKET_FINAL { continue; }
+            if msg.len() < 4 { return Err("回覆長度不足".into()); }
+            // 前一個逾時指令遲到的回覆
+            if u16::from_le_bytes([msg[0], msg[1]]) != tag {
+                msg.clear();
+                continue;
+            }
+            return match msg[2] {
+                0 => Ok(msg[4..].to_vec()),
+                1 => Err("bootloader 不支援此指令".into()),
+                status => Err(format!("bootloader 執行失敗 (status {}, {})", status, msg[3])),
+            };
+        }
+    }
+
+    fn command(&self, t: &dyn Transport, cmd: u32, args: &[u8]) -> Result<Vec<u8>, String> {
+        let tag = self.send(t, cmd, args)?;
+        self.receive(t, tag)
+    }
+
+    // 區塊所在的 page 起點與合併後的 page 內容
+    fn page(&mut self, address: u32, data: &[u8]) -> (u32, &[u8]) {
+        let start = address - address % self.page_size as u32;
+        let offset = (address - start) as usize;
+        let page = self.pages.entry(start).or_insert_with(|| vec![0xFF; self.page_size]);
+        page[offset..offset + data.len()].copy_from_slice(data);
+        (start, page)
+    }
+}
+
+impl Bootloader for Hf2Bootloader {
+    fn name(&self) -> &'static str { NAME }
+
+    fn block_size(&self) -> usize { self.page_size }
+
+    fn enter(&mut self, t: &dyn Transport) -> Result<(), String> {
+        let info = self.command(t, CMD_BININFO, &[])?;
+        let mode = word(&info, 0).ok_or("BININFO 回覆長度不足")?;
+        if mode != MODE_BOOTLOADER {
+            // 設備重新列舉後才會出現 bootloader 介面
+            self.send(t, CMD_RESET_INTO_BOOTLOADER, &[])?;
+            return Err("設備正在執行應用程式，已要求切換至 bootloader，請重新開啟設備後再更新".into());
+        }
+        self.page_size = word(&info, 4).ok_or("BININFO 回覆長度不足")? as usize;
+        self.max_message = word(&info, 12).ok_or("BININFO 回覆長度不足")? as usize;
+        self.family_id = word(&info, 16).filter(|f| *f != 0);
+        self.pages.clear();
+        if self.page_size == 0 || self.page_size + 8 > self.max_message {
+            return Err(format!("page 大小 {} 超過單則訊息上限 {}", self.page_size, self.max_message));
+        }
+        Ok(())
+    }
+
+    // HF2 寫入 page 時自行抹除，這裡只檢查映像是否適用於此設備
+    fn erase(&mut self, _t: &dyn Transport, image: &FirmwareImage) -> Result<(), String> {
+        match (image.family_id, self.family_id) {
+            (Some(file), Some(device)) if file != device => Err(format!(
+                "UF2 family {} 與設備的 {} 不符", family_name(Some(file)), family_name(Some(device)),
+            )),
+            (Some(_), None) if !self.config.allow_unknown_family => Err("設備沒有回報 family ID，無法確認映像是否適用".into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn write_block(&mut self, t: &dyn Transport, address: u32, data: &[u8]) -> Result<(), String> {
+        let (start, page) = self.page(address, data);
+        let mut args = start.to_le_bytes().to_vec();
+        args.extend_from_slice(page);
+        self.command(t, CMD_WRITE_FLASH_PAGE, &args).map(|_| ())
+    }
+
+    fn supports_verify(&self) -> bool { true }
+
+    fn verify_block(&mut self, t: &dyn Transport, address: u32, data: &[u8]) -> Result<(), String> {
+        let (start, page) = self.page(address, data);
+        let expected = Checksum::Crc16Xmodem.compute(page) as u16;
+        let mut args = start.to_le_bytes().to_vec();
+        args.extend(1u32.to_le_bytes());
+        let resp = self.command(t, CMD_CHKSUM_PAGES, &args)?;
+        let crc = resp.get(..2).map(|b| u16::from_le_bytes([b[0], b[1]])).ok_or("CHKSUM_PAGES 回覆長度不足")?;
+        if crc != expected {
+            return Err(format!("驗證失敗: 位址 {:#010x} CRC 不符 (設備 {:04X}, 檔案 {:04X})", start, crc, expected));
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, t: &dyn Transport) -> Result<(), String> {
+        // 設備立即重新啟動，不會回覆
+        self.send(t, CMD_RESET_INTO_APP, &[]).map(|_| ())
+    }
+
+    // 第一行中 v 開頭的版本號；找不到時回傳整行
+    fn firmware_version(&mut self, t: &dyn Transport) -> Result<Option<String>, String> {
+        let info = self.command(t, CMD_INFO, &[])?;
+        let text = String::from_utf8_lossy(&info);
+        let Some(line) = text.lines().map(str::trim).find(|l| !l.is_empty()) else { return Ok(None) };
+        let version = line.split_whitespace()
+            .find(|w| w.strip_prefix(['v', 'V']).is_some_and(|r| r.starts_with(|c: char| c.is_ascii_digit())))
+            .unwrap_or(line);
+        Ok(Some(version.to_string()))
+    }
+}
@@ -0,0 +1,119 @@
+// --- Intel HEX ---
+// 每行一個 record： ":" 長度 位址(2 bytes) 類型 資料 checksum，皆為十六進位
+//   00 資料  01 檔案結束  02 extended segment address (x16)  03 start segment address (CS:IP)
+//   04 extended linear address (高 16 位元)  05 start linear address
+// 連續的資料合併成同一個 segment。
+
+use super::{merge_chunks, Segment};
+
+pub struct HexFile {
+    // 依位址排序，互不重疊
+    pub segments: Vec<Segment>,
+    // 檔案指定的程式進入點 (record 03 / 05)
+    pub entry: Option<u32>,
+}
+
+fn decode_line(line: &str) -> Result<Vec<u8>, String> {
+    let hex = line.strip_prefix(':').ok_or("缺少起始的 ':'")?;
+    // 先確認全為十六進位字元，之後才能以位元組切片
+    if let Some(c) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("無效的十六進位字元 '{}'", c));
+    }
+    if hex.len() % 2 != 0 { return Err("十六進位字元數必須是偶數".into()); }
+    let bytes: Vec<u8> = (0..hex.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect();
+    if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 { return Err("record 長度不符".into()); }
+    if bytes.iter().fold(0u8, |a, b| a.wrapping_add(*b)) != 0 { return Err("checksum 錯誤".into()); }
+    Ok(bytes)
+}
+
+pub fn parse(text: &str) -> Result<HexFile, String> {
+    let mut chunks: Vec<(u32, Vec<u8>)> = Vec::new();
+    let mut base: u32 = 0;
+    let mut entry = None;
+    let mut ended = false;
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() { continue; }
+        let err = |e: String| format!("HEX 第 {} 行: {}", n + 1, e);
+        if ended { return Err(err("檔案結束 record 之後還有資料".into())); }
+        let bytes = decode_line(line).map_err(err)?;
+        let data = &bytes[4..bytes.len() - 1];
+        let offset = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+        match bytes[3] {
+            0x00 => {
+                let address = base.wrapping_add(offset);
+                if address as u64 + data.len() as u64 > u32::MAX as u64 + 1 {
+                    return Err(err("資料超出 32 位元位址範圍".into()));
+                }
+                match chunks.last_mut() {
+                    Some((start, buf)) if *start as u64 + buf.len() as u64 == address as u64 => buf.extend_from_slice(data),
+                    _ => chunks.push((address, data.to_vec())),
+                }
+            }
+            0x01 => ended = true,
+            0x02 | 0x04 if data.len() != 2 => return Err(err("位址 record 長度必須是 2".into())),
+            0x02 => base = (u16::from_be_bytes([data[0], data[1]]) as u32) << 4,
+            0x04 => base = (u16::from_be_bytes([data[0], data[1]]) as u32) << 16,
+            0x03 | 0x05 if data.len() != 4 => return Err(err("進入點 record 長度必須是 4".into())),
+            0x03 => {
+                let cs = u16::from_be_bytes([data[0], data[1]]) as u32;
+                let ip = u16::from_be_bytes([data[2], data[3]]) as u32;
+                entry = Some((cs << 4) + ip);
+            }
+            0x05 => entry = Some(u32::from_be_bytes([data[0], data[1], data[2], data[3]])),
+            t => return Err(err(format!("未知的 record 類型 {:02X}", t))),
+        }
+    }
+    if !ended { return Err("HEX 檔缺少檔案結束 record".into()); }
+    if chunks.is_empty() { return Err("HEX 檔沒有任何資料".into()); }
+    Ok(HexFile { segments: merge_chunks(chunks)?, entry })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 依資料組出一行 record (含 checksum)
+    fn record(kind: u8, offset: u16, data: &[u8]) -> String {
+        let mut bytes = vec![data.len() as u8, (offset >> 8) as u8, offset as u8, kind];
+        bytes.extend_from_slice(data);
+        bytes.push(bytes.iter().fold(0u8, |a, b| a.wrapping_sub(*b)));
+        let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        format!(":{}", hex)
+    }
+
+    #[test]
+    fn parses_records_into_segments() {
+        let text = [
+            record(0x04, 0, &[0x08, 0x00]),
+            record(0x00, 0x0000, &[1, 2, 3, 4]),
+            record(0x00, 0x0004, &[5, 6]),
+            record(0x00, 0x0100, &[7]),
+            record(0x05, 0, &[0x08, 0x00, 0x01, 0x23]),
+            record(0x01, 0, &[]),
+        ].join("\n");
+        let file = parse(&text).unwrap();
+        assert_eq!(file.segments.len(), 2);
+        assert_eq!(file.segments[0].address, 0x0800_0000);
+        assert_eq!(file.segments[0].data, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(file.segments[1].address, 0x0800_0100);
+        assert_eq!(file.segments[1].data, vec![7]);
+        assert_eq!(file.entry, Some(0x0800_0123));
+    }
+
+    #[test]
+    fn rejects_non_hex_without_panicking() {
+        // 多位元組字元不能被當成兩個十六進位字元切開
+        assert!(parse(":0é00000001FF\n").is_err());
+        assert!(parse(":00000001ZZ\n").is_err());
+        assert!(parse(":0000001FF\n").is_err());
+    }
+
+    #[test]
+    fn rejects_bad_checksum_and_missing_eof() {
+        assert!(parse(":0100000001FF\n:00000001FF\n").is_err());
+        assert!(parse(&record(0x00, 0, &[1])).is_err());
+    }
+}
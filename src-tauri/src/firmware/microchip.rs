@@ -0,0 +1,207 @@
+// --- Microchip HID bootloader (AN1388) ---
+// PIC32 / PIC24 常見的 HID bootloader。每個指令包成一個 frame，可跨多個 report：
+//   [SOH 0x01][指令 + 資料][CRC16 LE][EOT 0x04]
+// 資料與 CRC 中的 SOH / EOT / DLE 前面加上 DLE (0x10) 跳脫；CRC 為 CRC-16/CCITT (多項式 0x1021, 初值 0)。
+// 寫入以 Intel HEX record (二進位形式) 傳送，驗證以 READ_CRC 比對設備計算的 CRC。
+
+use super::{Bootloader, FirmwareImage};
+use crate::checksum::Checksum;
+use crate::protocols::{DeviceIdentity, Transport};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+pub const NAME: &str = "microchip";
+
+// Microchip 範例 bootloader 的預設 VID / PID
+const VENDOR_MICROCHIP: u16 = 0x04D8;
+const PRODUCT_BOOTLOADER: u16 = 0x003C;
+
+// frame 依序切成 64 bytes 的 report 送出
+const REPORT_LEN: usize = 64;
+
+const SOH: u8 = 0x01;
+const EOT: u8 = 0x04;
+const DLE: u8 = 0x10;
+
+const READ_BOOT_INFO: u8 = 0x01;
+const ERASE_FLASH: u8 = 0x02;
+const PROGRAM_FLASH: u8 = 0x03;
+const READ_CRC: u8 = 0x04;
+const JUMP_TO_APP: u8 = 0x05;
+
+// Intel HEX record 類型
+const RECORD_DATA: u8 = 0x00;
+const RECORD_EOF: u8 = 0x01;
+const RECORD_EXTENDED_LINEAR: u8 = 0x04;
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct MicrochipConfig {
+    // 每個 HEX record 的資料長度
+    pub block_size: usize,
+    pub timeout_ms: u64,
+    pub erase_timeout_ms: u64,
+    // READ_CRC 使用的位址要加上的偏移 (PIC32 的 HEX 為實體位址，bootloader 以 KSEG0 虛擬位址計算)
+    pub crc_address_offset: u32,
+}
+
+impl Default for MicrochipConfig {
+    fn default() -> Self {
+        Self {
+            block_size: 16,
+            timeout_ms: 1000,
+            erase_timeout_ms: 20_000,
+            crc_address_offset: 0x8000_0000,
+        }
+    }
+}
+
+pub fn matches(id: &DeviceIdentity) -> bool {
+    id.vendor_id == VENDOR_MICROCHIP && id.product_id == PRODUCT_BOOTLOADER
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    Checksum::Crc16Xmodem.compute(data) as u16
+}
+
+fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut out = vec![SOH];
+    let crc = crc16(payload).to_le_bytes();
+    for &b in payload.iter().chain(crc.iter()) {
+        if matches!(b, SOH | EOT | DLE) { out.push(DLE); }
+        out.push(b);
+    }
+    out.push(EOT);
+    out
+}
+
+// 從累積的位元組中取出一個完整 frame 的內容 (不含 CRC)；資料不足時回傳 None
+fn unframe(buf: &[u8]) -> Option<Result<Vec<u8>, String>> {
+    let start = buf.iter().position(|&b| b == SOH)?;
+    let mut out = Vec::new();
+    let mut bytes = buf[start + 1..].iter();
+    while let Some(&b) = bytes.next() {
+        match b {
+            DLE => out.push(*bytes.next()?),
+            EOT => {
+                if out.len() < 3 { return Some(Err("bootloader 回覆長度不足".into())); }
+                let (body, crc) = out.split_at(out.len() - 2);
+                if crc16(body) != u16::from_le_bytes([crc[0], crc[1]]) {
+                    return Some(Err("bootloader 回覆 CRC 錯誤".into()));
+                }
+                return Some(Ok(body.to_vec()));
+            }
+            _ => out.push(b),
+        }
+    }
+    None
+}
+
+// HEX record 的二進位形式：[長度][位址 BE 2][類型][資料][checksum]
+fn record(kind: u8, offset: u16, data: &[u8]) -> Vec<u8> {
+    let mut out = vec![data.len() as u8];
+    out.extend(offset.to_be_bytes());
+    out.push(kind);
+    out.extend_from_slice(data);
+    let sum = out.iter().fold(0u8, |a, b| a.wrapping_add(*b));
+    out.push(sum.wrapping_neg());
+    out
+}
+
+pub struct MicrochipBootloader {
+    config: MicrochipConfig,
+    // 目前 extended linear address (位址高 16 位元)
+    upper: Option<u16>,
+}
+
+impl MicrochipBootloader {
+    pub fn new(config: MicrochipConfig) -> Result<Self, String> {
+        if !(1..=0xFF).contains(&config.block_size) { return Err("block_size 只能是 1..=255".into()); }
+        Ok(Self { config, upper: None })
+    }
+
+    // 送出指令並等待相同指令碼的回覆，回傳指令碼之後的資料
+    fn command(&self, t: &dyn Transport, cmd: u8, data: &[u8], timeout_ms: u64) -> Result<Vec<u8>, String> {
+        let mut payload = vec![cmd];
+        payload.extend_from_slice(data);
+        for chunk in frame(&payload).chunks(REPORT_LEN) {
+            t.write(chunk)?;
+        }
+
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+        let mut buf = Vec::new();
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() { return Err("等待 bootloader 回覆逾時".into()); }
+            buf.extend(t.read(left.as_millis() as i32)?);
+            match unframe(&buf) {
+                None => continue,
+                Some(Ok(resp)) if resp.first() == Some(&cmd) => return Ok(resp[1..].to_vec()),
+                // 其他指令的遲到回覆
+                Some(Ok(_)) => buf.clear(),
+                Some(Err(e)) => return Err(e),
+            }
+        }
+    }
+
+    fn program(&self, t: &dyn Transport, record: &[u8]) -> Result<(), String> {
+        self.command(t, PROGRAM_FLASH, record, self.config.timeout_ms).map(|_| ())
+    }
+}
+
+impl Bootloader for MicrochipBootloader {
+    fn name(&self) -> &'static str { NAME }
+
+    fn block_size(&self) -> usize { self.config.block_size }
+
+    fn enter(&mut self, t: &dyn Transport) -> Result<(), String> {
+        let info = self.command(t, READ_BOOT_INFO, &[], self.config.timeout_ms)?;
+        if info.len() < 2 { return Err("無法讀取 bootloader 版本".into()); }
+        self.upper = None;
+        Ok(())
+    }
+
+    // bootloader 會抹除整個應用程式區
+    fn erase(&mut self, t: &dyn Transport, _image: &FirmwareImage) -> Result<(), String> {
+        self.command(t, ERASE_FLASH, &[], self.config.erase_timeout_ms).map(|_| ())
+    }
+
+    fn write_block(&mut self, t: &dyn Transport, address: u32, data: &[u8]) -> Result<(), String> {
+        let upper = (address >> 16) as u16;
+        if self.upper != Some(upper) {
+            self.program(t, &record(RECORD_EXTENDED_LINEAR, 0, &upper.to_be_bytes()))?;
+            self.upper = Some(upper);
+        }
+        // block_size 不整除 0x10000 時區塊可能跨越 64 KB 邊界，拆成兩個 record
+        let first = (address & 0xFFFF) as usize;
+        let split = data.len().min(0x10000 - first);
+        self.program(t, &record(RECORD_DATA, first as u16, &data[..split]))?;
+        if split < data.len() {
+            let upper = upper.wrapping_add(1);
+            self.program(t, &record(RECORD_EXTENDED_LINEAR, 0, &upper.to_be_bytes()))?;
+            self.upper = Some(upper);
+            self.program(t, &record(RECORD_DATA, 0, &data[split..]))?;
+        }
+        Ok(())
+    }
+
+    fn supports_verify(&self) -> bool { true }
+
+    fn verify_block(&mut self, t: &dyn Transport, address: u32, data: &[u8]) -> Result<(), String> {
+        let mut args = address.wrapping_add(self.config.crc_address_offset).to_le_bytes().to_vec();
+        args.extend((data.len() as u32).to_le_bytes());
+        let resp = self.command(t, READ_CRC, &args, self.config.timeout_ms)?;
+        if resp.len() < 2 { return Err("READ_CRC 回覆長度不足".into()); }
+        let crc = u16::from_le_bytes([resp[0], resp[1]]);
+        if crc != crc16(data) {
+            return Err(format!("驗證失敗: 位址 {:#010x} CRC 不符 (設備 {:04X}, 檔案 {:04X})", address, crc, crc16(data)));
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, t: &dyn Transport) -> Result<(), String> {
+        self.program(t, &record(RECORD_EOF, 0, &[]))?;
+        // 設備立即跳到應用程式，不會回覆
+        t.write(&frame(&[JUMP_TO_APP])).map(|_| ())
+    }
+}
@@ -0,0 +1,414 @@
+// --- 韌體更新 (DFU) ---
+// 把韌體映像透過可替換的 bootloader 協定寫入設備：
+//   enter -> erase -> write -> verify -> finish -> done
+// 任何階段失敗進入 failed，使用者取消進入 cancelled；每次狀態改變與寫入進度都回報 fw-progress。
+
+pub mod generic;
+pub mod hf2;
+pub mod ihex;
+pub mod microchip;
+pub mod nordic;
+pub mod stm32;
+pub mod uf2;
+
+use crate::protocols::{DeviceIdentity, Transport};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// 回覆中 little-endian 的 u32；長度不足時為 None
+pub fn word(data: &[u8], i: usize) -> Option<u32> {
+    data.get(i..i + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// 連續的一段資料
+#[derive(Clone, Debug)]
+pub struct Segment {
+    pub address: u32,
+    pub data: Vec<u8>,
+}
+
+// 排序後合併相鄰的資料，重疊視為錯誤
+pub fn merge_chunks(mut chunks: Vec<(u32, Vec<u8>)>) -> Result<Vec<Segment>, String> {
+    chunks.sort_by_key(|(address, _)| *address);
+    let mut segments: Vec<Segment> = Vec::new();
+    for (address, data) in chunks {
+        if address as u64 + data.len() as u64 > u32::MAX as u64 + 1 {
+            return Err(format!("資料超出 32 位元位址範圍: {:#010x}", address));
+        }
+        match segments.last_mut() {
+            Some(prev) if (prev.address as u64 + prev.data.len() as u64) > address as u64 => {
+                return Err(format!("資料位址重疊: {:#010x}", address));
+            }
+            Some(prev) if prev.address as u64 + prev.data.len() as u64 == address as u64 => prev.data.extend(data),
+            _ => segments.push(Segment { address, data }),
+        }
+    }
+    Ok(segments)
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    #[default]
+    Binary,
+    IntelHex,
+    Uf2,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FirmwareImage {
+    pub format: ImageFormat,
+    // 依位址排序，互不重疊
+    pub segments: Vec<Segment>,
+    // 程式進入點 (僅 HEX 檔可能指定)
+    pub entry: Option<u32>,
+    // 目標晶片系列 (僅 UF2 檔可能指定)
+    pub family_id: Option<u32>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct AddressRange {
+    pub address: u32,
+    pub size: usize,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ImageInfo {
+    pub format: ImageFormat,
+    // 實際資料的位元組數 (不含空隙)
+    pub size: usize,
+    pub start: u32,
+    // 最後一個 byte 的下一個位址
+    pub end: u64,
+    pub entry: Option<u32>,
+    pub family_id: Option<u32>,
+    pub segments: Vec<AddressRange>,
+    // segment 之間沒有資料的區間
+    pub gaps: Vec<AddressRange>,
+}
+
+impl FirmwareImage {
+    // 原始 binary 檔，從 base_address 開始
+    pub fn from_binary(data: Vec<u8>, base_address: u32) -> Result<Self, String> {
+        if data.is_empty() { return Err("韌體檔是空的".into()); }
+        if base_address as u64 + data.len() as u64 > u32::MAX as u64 + 1 {
+            return Err("韌體超出 32 位元位址範圍".into());
+        }
+        Ok(Self { format: ImageFormat::Binary, segments: vec![Segment { address: base_address, data }], ..Default::default() })
+    }
+
+    pub fn from_hex(text: &str) -> Result<Self, String> {
+        let hex = ihex::parse(text)?;
+        Ok(Self { format: ImageFormat::IntelHex, segments: hex.segments, entry: hex.entry, family_id: None })
+    }
+
+    pub fn from_uf2(data: &[u8]) -> Result<Self, String> {
+        let uf2 = uf2::parse(data)?;
+        Ok(Self { format: ImageFormat::Uf2, segments: uf2.segments, entry: None, family_id: uf2.family_id })
+    }
+
+    // 依副檔名或內容判斷：UF2 (magic)、Intel HEX (.hex / .ihex / .ihx 或以 ':' 開頭)，其餘為 binary (base_address 只用於 binary)
+    pub fn load(file: &str, base_address: u32) -> Result<Self, String> {
+        let data = std::fs::read(file).map_err(|e| format!("讀取 {} 失敗: {}", file, e))?;
+        let ext = std::path::Path::new(file).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        if ext == "uf2" || (ext != "bin" && uf2::is_uf2(&data)) {
+            return Self::from_uf2(&data);
+        }
+        let is_hex = matches!(ext.as_str(), "hex" | "ihex" | "ihx")
+            || (ext != "bin" && data.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b':'));
+        if is_hex {
+            let text = String::from_utf8(data).map_err(|_| "HEX 檔不是文字檔".to_string())?;
+            Self::from_hex(&text)
+        } else {
+            Self::from_binary(data, base_address)
+        }
+    }
+
+    pub fn info(&self) -> ImageInfo {
+        let segments: Vec<AddressRange> = self.segments.iter()
+            .map(|s| AddressRange { address: s.address, size: s.data.len() })
+            .collect();
+        let gaps = segments.windows(2)
+            .map(|w| (w[0].address as u64 + w[0].size as u64, w[1].address))
+            .filter(|(end, next)| *end < *next as u64)
+            .map(|(end, next)| AddressRange { address: end as u32, size: (next as u64 - end) as usize })
+            .collect();
+        ImageInfo {
+            format: self.format,
+            size: self.size(),
+            start: segments.first().map_or(0, |s| s.address),
+            end: segments.last().map_or(0, |s| s.address as u64 + s.size as u64),
+            entry: self.entry,
+            family_id: self.family_id,
+            segments,
+            gaps,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.segments.iter().map(|s| s.data.len()).sum()
+    }
+
+    // 切成不超過 block_size、不跨越 block 邊界的區塊
+    pub fn blocks(&self, block_size: usize) -> Vec<(u32, &[u8])> {
+        let mut out = Vec::new();
+        for seg in &self.segments {
+            let mut offset = 0;
+            while offset < seg.data.len() {
+                let address = seg.address + offset as u32;
+                let room = block_size - address as usize % block_size;
+                let len = room.min(seg.data.len() - offset);
+                out.push((address, &seg.data[offset..offset + len]));
+                offset += len;
+            }
+        }
+        out
+    }
+}
+
+// bootloader 協定
+pub trait Bootloader {
+    fn name(&self) -> &'static str;
+    // 單次寫入的最大位元組數；映像依此切塊並對齊 (在 enter 之後取得，可依設備回報決定)
+    fn block_size(&self) -> usize;
+    // 進入 bootloader 並確認設備可以更新 (例: 讀取版本、解鎖)
+    fn enter(&mut self, _t: &dyn Transport) -> Result<(), String> { Ok(()) }
+    fn erase(&mut self, t: &dyn Transport, image: &FirmwareImage) -> Result<(), String>;
+    fn write_block(&mut self, t: &dyn Transport, address: u32, data: &[u8]) -> Result<(), String>;
+    // 不支援回讀的 bootloader 跳過逐塊驗證
+    fn supports_read(&self) -> bool { false }
+    fn read_block(&mut self, _t: &dyn Transport, _address: u32, _len: usize) -> Result<Vec<u8>, String> {
+        Err(format!("{} 不支援讀取", self.name()))
+    }
+    // 預設以回讀比對；只能以 CRC 等方式驗證的 bootloader 可覆寫
+    fn supports_verify(&self) -> bool { self.supports_read() }
+    fn verify_block(&mut self, t: &dyn Transport, address: u32, data: &[u8]) -> Result<(), String> {
+        let read = self.read_block(t, address, data.len())?;
+        if let Some(pos) = read.iter().zip(data.iter()).position(|(a, b)| a != b) {
+            return Err(format!("驗證失敗: 位址 {:#010x} 資料不符", address as usize + pos));
+        }
+        if read.len() < data.len() {
+            return Err(format!("驗證失敗: 位址 {:#010x} 回讀長度不足", address));
+        }
+        Ok(())
+    }
+    // 結束更新 (例: 寫入完成旗標、重新啟動到應用程式)
+    fn finish(&mut self, _t: &dyn Transport) -> Result<(), String> { Ok(()) }
+    // 查詢設備上目前的韌體版本；不支援時回傳 None
+    fn firmware_version(&mut self, _t: &dyn Transport) -> Result<Option<String>, String> { Ok(None) }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum FwPhase {
+    Enter,
+    Erase,
+    Write,
+    Verify,
+    Finish,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl FwPhase {
+    pub fn name(self) -> &'static str {
+        match self {
+            FwPhase::Enter => "enter",
+            FwPhase::Erase => "erase",
+            FwPhase::Write => "write",
+            FwPhase::Verify => "verify",
+            FwPhase::Finish => "finish",
+            FwPhase::Done => "done",
+            FwPhase::Failed => "failed",
+            FwPhase::Cancelled => "cancelled",
+        }
+    }
+
+    // 正常流程的下一個階段
+    fn next(self) -> Option<FwPhase> {
+        match self {
+            FwPhase::Enter => Some(FwPhase::Erase),
+            FwPhase::Erase => Some(FwPhase::Write),
+            FwPhase::Write => Some(FwPhase::Verify),
+            FwPhase::Verify => Some(FwPhase::Finish),
+            FwPhase::Finish => Some(FwPhase::Done),
+            FwPhase::Done | FwPhase::Failed | FwPhase::Cancelled => None,
+        }
+    }
+
+    // 各階段在整體進度中的比例 (起點, 寬度)
+    fn span(self, verify: bool) -> (f64, f64) {
+        let (write, check) = if verify { (0.55, 0.3) } else { (0.85, 0.0) };
+        match self {
+            FwPhase::Enter => (0.0, 0.02),
+            FwPhase::Erase => (0.02, 0.1),
+            FwPhase::Write => (0.12, write),
+            FwPhase::Verify => (0.12 + write, check),
+            FwPhase::Finish => (0.97, 0.03),
+            FwPhase::Done => (1.0, 0.0),
+            FwPhase::Failed | FwPhase::Cancelled => (0.0, 0.0),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct FwProgress {
+    pub phase: FwPhase,
+    // 整體進度 0..=100
+    pub percent: f64,
+    // 目前階段已處理 / 總位元組數
+    pub current: usize,
+    pub total: usize,
+    pub error: Option<String>,
+}
+
+pub type FwProgressFn<'a> = &'a mut dyn FnMut(FwProgress);
+
+struct Run<'a> {
+    phase: FwPhase,
+    verify: bool,
+    cancel: &'a AtomicBool,
+    progress: FwProgressFn<'a>,
+    // 失敗 / 取消時沿用最後的進度
+    percent: f64,
+}
+
+impl Run<'_> {
+    fn report(&mut self, current: usize, total: usize) {
+        let (start, width) = self.phase.span(self.verify);
+        let fraction = if total > 0 { current as f64 / total as f64 } else { 0.0 };
+        self.percent = (start + width * fraction) * 100.0;
+        (self.progress)(FwProgress { phase: self.phase, percent: self.percent, current, total, error: None });
+    }
+
+    fn advance(&mut self, to: FwPhase) -> Result<(), String> {
+        if self.cancel.load(Ordering::Relaxed) { return Err("已取消".into()); }
+        if self.phase.next() != Some(to) {
+            return Err(format!("狀態錯誤: {:?} 之後不能進入 {:?}", self.phase, to));
+        }
+        self.phase = to;
+        self.report(0, 0);
+        Ok(())
+    }
+
+    fn check_cancel(&self) -> Result<(), String> {
+        if self.cancel.load(Ordering::Relaxed) { Err("已取消".into()) } else { Ok(()) }
+    }
+}
+
+// 回傳是否已逐塊驗證
+fn execute(run: &mut Run, loader: &mut dyn Bootloader, t: &dyn Transport, image: &FirmwareImage) -> Result<bool, String> {
+    run.report(0, 0);
+    loader.enter(t)?;
+
+    let block_size = loader.block_size();
+    if block_size == 0 { return Err("block_size 不能為 0".into()); }
+    let blocks = image.blocks(block_size);
+    let total = image.size();
+
+    run.advance(FwPhase::Erase)?;
+    loader.erase(t, image)?;
+
+    run.advance(FwPhase::Write)?;
+    let mut done = 0;
+    for (address, data) in &blocks {
+        run.check_cancel()?;
+        loader.write_block(t, *address, data).map_err(|e| format!("寫入 {:#010x} 失敗: {}", address, e))?;
+        done += data.len();
+        run.report(done, total);
+    }
+
+    run.advance(FwPhase::Verify)?;
+    let verified = run.verify && loader.supports_verify();
+    if verified {
+        let mut done = 0;
+        for (address, data) in &blocks {
+            run.check_cancel()?;
+            loader.verify_block(t, *address, data)?;
+            done += data.len();
+            run.report(done, total);
+        }
+    }
+
+    run.advance(FwPhase::Finish)?;
+    loader.finish(t)?;
+    run.advance(FwPhase::Done)?;
+    Ok(verified)
+}
+
+// 執行整個更新流程；cancel 在區塊之間檢查
+// 成功時回傳是否已驗證寫入的資料 (使用者略過驗證或 bootloader 不支援時為 false)
+pub fn update(
+    loader: &mut dyn Bootloader,
+    t: &dyn Transport,
+    image: &FirmwareImage,
+    verify: bool,
+    cancel: &AtomicBool,
+    progress: FwProgressFn,
+) -> Result<bool, String> {
+    let mut run = Run { phase: FwPhase::Enter, verify, cancel, progress, percent: 0.0 };
+    let result = execute(&mut run, loader, t, image);
+    if let Err(e) = &result {
+        let phase = if cancel.load(Ordering::Relaxed) { FwPhase::Cancelled } else { FwPhase::Failed };
+        (run.progress)(FwProgress { phase, percent: run.percent, current: 0, total: 0, error: Some(e.clone()) });
+    }
+    result
+}
+
+#[derive(Serialize, Clone)]
+pub struct BootloaderInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+pub fn list() -> Vec<BootloaderInfo> {
+    vec![
+        BootloaderInfo { name: generic::NAME, description: "以可設定的指令格式寫入 (抹除 / 寫入 / 讀取 / 結束)" },
+        BootloaderInfo { name: microchip::NAME, description: "Microchip PIC HID bootloader (AN1388)" },
+        BootloaderInfo { name: hf2::NAME, description: "UF2 bootloader 的 HID 介面 (HF2)，檢查 family ID" },
+        BootloaderInfo { name: nordic::NAME, description: "Nordic nRF Secure DFU (HID 傳輸)，需要 init packet" },
+        BootloaderInfo { name: stm32::NAME, description: "STM32 HID bootloader (stm32duino)，依序寫入並重新啟動" },
+    ]
+}
+
+fn parse<T: DeserializeOwned>(name: &str, options: Option<serde_json::Value>) -> Result<T, String> {
+    serde_json::from_value(options.unwrap_or(serde_json::json!({})))
+        .map_err(|e| format!("{} 設定格式錯誤: {}", name, e))
+}
+
+// 依 VID / PID (或 bootloader 介面的 usage page) 判斷設備所在的 bootloader
+pub fn detect(id: &DeviceIdentity) -> Option<&'static str> {
+    if microchip::matches(id) { return Some(microchip::NAME); }
+    if stm32::matches(id) { return Some(stm32::NAME); }
+    if hf2::matches(id) { return Some(hf2::NAME); }
+    if nordic::matches(id) { return Some(nordic::NAME); }
+    None
+}
+
+// 設備處於可查詢版本的 bootloader 時，回傳其回報的韌體版本
+pub fn firmware_version(id: &DeviceIdentity, t: &dyn Transport) -> Result<Option<String>, String> {
+    let mut loader: Box<dyn Bootloader> = match detect(id) {
+        Some(hf2::NAME) => Box::new(hf2::Hf2Bootloader::new(hf2::Hf2Config::default())?),
+        Some(nordic::NAME) => Box::new(nordic::NordicBootloader::probe(nordic::NordicConfig::default())),
+        _ => return Ok(None),
+    };
+    loader.firmware_version(t)
+}
+
+// 依名稱建立 bootloader，未指定時依設備自動選擇；options 為該協定的設定 (JSON)
+pub fn resolve(name: Option<&str>, id: &DeviceIdentity, options: Option<serde_json::Value>) -> Result<Box<dyn Bootloader>, String> {
+    let name = match name {
+        Some(n) => n,
+        None => detect(id).ok_or("無法判斷此設備的 bootloader，請指定協定")?,
+    };
+    match name {
+        generic::NAME => Ok(Box::new(generic::GenericBootloader::new(parse(name, options)?)?)),
+        microchip::NAME => Ok(Box::new(microchip::MicrochipBootloader::new(parse(name, options)?)?)),
+        hf2::NAME => Ok(Box::new(hf2::Hf2Bootloader::new(parse(name, options)?)?)),
+        nordic::NAME => Ok(Box::new(nordic::NordicBootloader::new(parse(name, options)?)?)),
+        stm32::NAME => Ok(Box::new(stm32::Stm32Bootloader::new(parse(name, options)?)?)),
+        _ => Err(format!("未知的 bootloader: {}", name)),
+    }
+}
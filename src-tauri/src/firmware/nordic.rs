@@ -0,0 +1,300 @@
+// --- Nordic nRF Secure DFU (HID 傳輸) ---
+// nRF5 SDK 的 Secure DFU 以物件傳送：先送 init packet (command 物件，含簽章與版本資訊)，
+// 再把映像切成 data 物件，每個物件 CREATE -> WRITE -> CALC_CRC 比對 -> EXECUTE。
+// 指令沿用 serial DFU 的格式：請求 [opcode][參數]，回覆 [0x60][opcode][result][資料]，整數為 little-endian，
+// 以 SLIP 編碼 (結尾 0xC0)。HID 傳輸把 SLIP 資料流切成 report，每個 report 第 0 byte 為本段長度。
+// init packet 取自 nrfutil 產生的 .zip 中的 .dat 檔 (需先解開)，映像為同一個 .zip 中的 .bin 檔。
+// 每個物件執行前已以 CRC 確認，不另外驗證；最後一個物件執行後設備自行檢查簽章並重新啟動。
+// 以 Nordic 的 VID 且為 vendor usage page 的介面視為 DFU 介面；版本以 FW_VERSION 查詢應用程式映像。
+
+use super::{word, Bootloader, FirmwareImage};
+use crate::checksum::crc32_update;
+use crate::protocols::{DeviceIdentity, Transport};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+pub const NAME: &str = "nordic";
+const VENDOR_ID: u16 = 0x1915;
+
+const OP_CREATE: u8 = 0x01;
+const OP_SET_PRN: u8 = 0x02;
+const OP_CALC_CRC: u8 = 0x03;
+const OP_EXECUTE: u8 = 0x04;
+const OP_SELECT: u8 = 0x06;
+const OP_MTU_GET: u8 = 0x07;
+const OP_WRITE: u8 = 0x08;
+const OP_PING: u8 = 0x09;
+const OP_FW_VERSION: u8 = 0x0B;
+const OP_RESPONSE: u8 = 0x60;
+
+const OBJ_COMMAND: u8 = 0x01;
+const OBJ_DATA: u8 = 0x02;
+
+const FW_TYPE_APPLICATION: u8 = 0x01;
+// FW_VERSION 查詢的映像數上限 (SoftDevice、應用程式、bootloader)
+const MAX_IMAGES: u8 = 4;
+
+const RES_SUCCESS: u8 = 0x01;
+const RES_EXT_ERROR: u8 = 0x0B;
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct NordicConfig {
+    // init packet (.dat) 的路徑
+    pub init_packet: Option<String>,
+    pub report_len: usize,
+    // EXECUTE 需等設備寫入 flash，等待時間要比一般指令長
+    pub timeout_ms: u64,
+}
+
+impl Default for NordicConfig {
+    fn default() -> Self {
+        Self { init_packet: None, report_len: 64, timeout_ms: 5000 }
+    }
+}
+
+pub fn matches(id: &DeviceIdentity) -> bool {
+    id.vendor_id == VENDOR_ID && id.usage_page >= 0xFF00
+}
+
+fn result_name(code: u8) -> &'static str {
+    match code {
+        0x00 => "invalid opcode",
+        0x02 => "opcode not supported",
+        0x03 => "invalid parameter",
+        0x04 => "insufficient resources",
+        0x05 => "invalid object",
+        0x07 => "unsupported type",
+        0x08 => "operation not permitted",
+        0x0A => "operation failed",
+        _ => "unknown",
+    }
+}
+
+fn ext_error_name(code: u8) -> &'static str {
+    match code {
+        0x02 => "unknown command",
+        0x03 => "init command invalid",
+        0x04 => "firmware version failure",
+        0x05 => "hardware version failure",
+        0x06 => "SoftDevice version failure",
+        0x07 => "signature missing",
+        0x08 => "wrong hash type",
+        0x09 => "hash failed",
+        0x0A => "wrong signature type",
+        0x0B => "verification failed",
+        0x0C => "insufficient space",
+        _ => "unknown",
+    }
+}
+
+fn slip_encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 2);
+    for &b in data {
+        match b {
+            SLIP_END => out.extend([SLIP_ESC, SLIP_ESC_END]),
+            SLIP_ESC => out.extend([SLIP_ESC, SLIP_ESC_ESC]),
+            b => out.push(b),
+        }
+    }
+    out.push(SLIP_END);
+    out
+}
+
+fn slip_decode(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut bytes = data.iter();
+    while let Some(&b) = bytes.next() {
+        out.push(match b {
+            SLIP_ESC => match bytes.next() {
+                Some(&SLIP_ESC_END) => SLIP_END,
+                Some(&SLIP_ESC_ESC) => SLIP_ESC,
+                _ => return Err("SLIP 跳脫字元錯誤".into()),
+            },
+            b => b,
+        });
+    }
+    Ok(out)
+}
+
+pub struct NordicBootloader {
+    config: NordicConfig,
+    init: Vec<u8>,
+    // 單次 WRITE 的最大資料長度 (由 MTU 推算)
+    max_write: usize,
+    // data 物件的最大長度 (SELECT 回報)
+    object_size: usize,
+    // 已送出的映像長度與其 CRC-32，設備以此確認物件內容
+    offset: u32,
+    crc: u32,
+    // 映像需連續寫入
+    next_address: Option<u32>,
+}
+
+impl NordicBootloader {
+    pub fn new(config: NordicConfig) -> Result<Self, String> {
+        let file = config.init_packet.as_deref().ok_or("需要 init packet (.dat) 的路徑")?;
+        let init = std::fs::read(file).map_err(|e| format!("讀取 init packet {} 失敗: {}", file, e))?;
+        if init.is_empty() { return Err("init packet 是空的".into()); }
+        if config.report_len < 2 { return Err("report_len 至少為 2".into()); }
+        Ok(Self { config, init, max_write: 0, object_size: 4096, offset: 0, crc: 0, next_address: None })
+    }
+
+    // 只查詢資訊 (例如版本)，不需要 init packet，不能用來更新
+    pub fn probe(config: NordicConfig) -> Self {
+        Self { config, init: Vec::new(), max_write: 0, object_size: 4096, offset: 0, crc: 0, next_address: None }
+    }
+
+    fn send(&self, t: &dyn Transport, msg: &[u8]) -> Result<(), String> {
+        for chunk in slip_encode(msg).chunks(self.config.report_len - 1) {
+            let mut report = vec![chunk.len() as u8];
+            report.extend_from_slice(chunk);
+            t.write(&report)?;
+        }
+        Ok(())
+    }
+
+    fn receive(&self, t: &dyn Transport) -> Result<Vec<u8>, String> {
+        let deadline = Instant::now() + Duration::from_millis(self.config.timeout_ms);
+        let mut slip = Vec::new();
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() { return Err("等待 bootloader 回覆逾時".into()); }
+            let report = t.read(left.as_millis() as i32)?;
+            let Some((&len, rest)) = report.split_first() else { continue };
+            for &b in &rest[..(len as usize).min(rest.len())] {
+                if b == SLIP_END {
+                    return slip_decode(&slip);
+                }
+                slip.push(b);
+            }
+        }
+    }
+
+    fn request(&self, t: &dyn Transport, op: u8, args: &[u8]) -> Result<Vec<u8>, String> {
+        let mut msg = vec![op];
+        msg.extend_from_slice(args);
+        self.send(t, &msg)?;
+        let resp = self.receive(t)?;
+        if resp.len() < 3 || resp[0] != OP_RESPONSE || resp[1] != op {
+            return Err(format!("回覆格式錯誤: {:02X?}", resp));
+        }
+        match resp[2] {
+            RES_SUCCESS => Ok(resp[3..].to_vec()),
+            RES_EXT_ERROR => {
+                let ext = resp.get(3).copied().unwrap_or(0);
+                Err(format!("bootloader 拒絕 (extended error {:#04x} {})", ext, ext_error_name(ext)))
+            }
+            code => Err(format!("bootloader 拒絕 ({:#04x} {})", code, result_name(code))),
+        }
+    }
+
+    // 回傳 (物件最大長度, 目前 offset, CRC)
+    fn select(&self, t: &dyn Transport, object: u8) -> Result<(u32, u32, u32), String> {
+        let resp = self.request(t, OP_SELECT, &[object])?;
+        match (word(&resp, 0), word(&resp, 4), word(&resp, 8)) {
+            (Some(max), Some(offset), Some(crc)) => Ok((max, offset, crc)),
+            _ => Err("SELECT 回覆長度不足".into()),
+        }
+    }
+
+    // 建立物件並寫入內容，比對設備計算的 offset / CRC 後執行
+    fn transfer(&self, t: &dyn Transport, object: u8, data: &[u8], offset: u32, crc: u32) -> Result<(), String> {
+        let mut args = vec![object];
+        args.extend((data.len() as u32).to_le_bytes());
+        self.request(t, OP_CREATE, &args)?;
+        for chunk in data.chunks(self.max_write) {
+            let mut msg = vec![OP_WRITE];
+            msg.extend_from_slice(chunk);
+            // PRN 為 0，WRITE 沒有回覆
+            self.send(t, &msg)?;
+        }
+        let resp = self.request(t, OP_CALC_CRC, &[])?;
+        let (Some(device_offset), Some(device_crc)) = (word(&resp, 0), word(&resp, 4)) else {
+            return Err("CALC_CRC 回覆長度不足".into());
+        };
+        if device_offset != offset || device_crc != crc {
+            return Err(format!(
+                "CRC 不符 (設備 offset {} CRC {:08X}, 預期 offset {} CRC {:08X})",
+                device_offset, device_crc, offset, crc,
+            ));
+        }
+        self.request(t, OP_EXECUTE, &[]).map(|_| ())
+    }
+}
+
+impl Bootloader for NordicBootloader {
+    fn name(&self) -> &'static str { NAME }
+
+    fn block_size(&self) -> usize { self.object_size }
+
+    fn enter(&mut self, t: &dyn Transport) -> Result<(), String> {
+        if self.init.is_empty() { return Err("需要 init packet (.dat) 的路徑".into()); }
+        let pong = self.request(t, OP_PING, &[0x01])?;
+        if pong.first() != Some(&0x01) { return Err("PING 回覆不符".into()); }
+        self.request(t, OP_SET_PRN, &0u16.to_le_bytes())?;
+        let mtu = self.request(t, OP_MTU_GET, &[])?;
+        let mtu = mtu.get(..2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize).ok_or("MTU_GET 回覆長度不足")?;
+        // MTU 為 SLIP 編碼後的長度，最壞情況每個位元組都需要跳脫，再扣掉 opcode
+        self.max_write = (mtu.saturating_sub(1) / 2).saturating_sub(1);
+        if self.max_write == 0 { return Err(format!("MTU {} 太小", mtu)); }
+
+        // 每次都重新送 init packet，設備會從頭接收映像
+        let (max, _, _) = self.select(t, OBJ_COMMAND)?;
+        if self.init.len() > max as usize {
+            return Err(format!("init packet {} bytes 超過設備上限 {}", self.init.len(), max));
+        }
+        self.transfer(t, OBJ_COMMAND, &self.init, self.init.len() as u32, crc32_update(0, &self.init))
+            .map_err(|e| format!("init packet 被拒絕: {}", e))?;
+
+        let (max, _, _) = self.select(t, OBJ_DATA)?;
+        if max == 0 { return Err("data 物件大小為 0".into()); }
+        self.object_size = max as usize;
+        Ok(())
+    }
+
+    // 設備在 EXECUTE init packet 時已確認空間並準備好 bank，這裡只檢查映像格式
+    fn erase(&mut self, _t: &dyn Transport, image: &FirmwareImage) -> Result<(), String> {
+        if image.segments.len() != 1 {
+            return Err("Nordic DFU 的映像必須是單一連續的 binary".into());
+        }
+        self.offset = 0;
+        self.crc = 0;
+        self.next_address = None;
+        Ok(())
+    }
+
+    fn write_block(&mut self, t: &dyn Transport, address: u32, data: &[u8]) -> Result<(), String> {
+        if self.next_address.is_some_and(|next| next != address) {
+            return Err(format!("映像不連續: 位址 {:#010x}", address));
+        }
+        let offset = self.offset + data.len() as u32;
+        let crc = crc32_update(self.crc, data);
+        self.transfer(t, OBJ_DATA, data, offset, crc)?;
+        self.offset = offset;
+        self.crc = crc;
+        self.next_address = Some(address + data.len() as u32);
+        Ok(())
+    }
+
+    // 回覆 [類型][版本 u32][位址 u32][長度 u32]；依序查詢映像，取應用程式的版本
+    fn firmware_version(&mut self, t: &dyn Transport) -> Result<Option<String>, String> {
+        for image in 0..MAX_IMAGES {
+            let resp = match self.request(t, OP_FW_VERSION, &[image]) {
+                Ok(resp) => resp,
+                // 超出映像數時設備回覆錯誤
+                Err(_) if image > 0 => break,
+                Err(e) => return Err(e),
+            };
+            if resp.first() == Some(&FW_TYPE_APPLICATION) {
+                return Ok(word(&resp, 1).map(|v| v.to_string()));
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,129 @@
+// --- STM32 HID bootloader (stm32duino) ---
+// 藍色小板 (STM32F103) 常用的 HID bootloader。沒有位址欄位，設備從應用程式起始位址依序寫入：
+//   重設寫入位置: "BTLDCMD" 0x00
+//   資料: 每 1 KB 切成 64 bytes 的 report 連續送出，設備收滿 1 KB 後寫入 flash，
+//         寫入位置到達 page 起點時先抹除該 page (F103 高密度型號為 2 KB page)
+//   重新啟動到應用程式: "BTLDCMD" 0x01
+// 不支援回讀，跳過驗證。
+
+use super::{Bootloader, FirmwareImage};
+use crate::protocols::{DeviceIdentity, Transport};
+use serde::Deserialize;
+use std::time::Duration;
+
+pub const NAME: &str = "stm32";
+
+const VENDOR_ID: u16 = 0x1209;
+const PRODUCT_ID: u16 = 0xBEBA;
+
+const COMMAND: &[u8; 7] = b"BTLDCMD";
+const CMD_RESET_PAGES: u8 = 0x00;
+const CMD_REBOOT_MCU: u8 = 0x01;
+
+// 設備端的接收緩衝區大小
+const TRANSFER_SIZE: usize = 1024;
+const REPORT_LEN: usize = 64;
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct Stm32Config {
+    // 應用程式起始位址 (bootloader 之後)
+    pub app_address: u32,
+    pub page_size: usize,
+    // 每 1 KB 送完後等待設備寫入 flash 的時間
+    pub write_delay_ms: u64,
+    // page 起點需多等待抹除的時間
+    pub erase_delay_ms: u64,
+}
+
+impl Default for Stm32Config {
+    fn default() -> Self {
+        Self {
+            app_address: 0x0800_0800,
+            page_size: 2048,
+            write_delay_ms: 5,
+            erase_delay_ms: 25,
+        }
+    }
+}
+
+pub fn matches(id: &DeviceIdentity) -> bool {
+    id.vendor_id == VENDOR_ID && id.product_id == PRODUCT_ID
+}
+
+fn command(t: &dyn Transport, cmd: u8) -> Result<(), String> {
+    let mut data = COMMAND.to_vec();
+    data.push(cmd);
+    t.write(&data).map(|_| ())
+}
+
+pub struct Stm32Bootloader {
+    config: Stm32Config,
+    // 設備下一個寫入位址
+    next: u32,
+}
+
+impl Stm32Bootloader {
+    pub fn new(config: Stm32Config) -> Result<Self, String> {
+        if config.page_size == 0 || !config.page_size.is_multiple_of(TRANSFER_SIZE) {
+            return Err(format!("page_size 必須是 {} 的倍數", TRANSFER_SIZE));
+        }
+        if !(config.app_address as usize).is_multiple_of(config.page_size) {
+            return Err("app_address 必須對齊 page".into());
+        }
+        Ok(Self { next: config.app_address, config })
+    }
+
+    // 送出 1 KB；不足的部分補 0xFF (flash 抹除後的值)
+    fn transfer(&mut self, t: &dyn Transport, data: &[u8]) -> Result<(), String> {
+        let mut buf = data.to_vec();
+        buf.resize(TRANSFER_SIZE, 0xFF);
+        for chunk in buf.chunks(REPORT_LEN) {
+            t.write(chunk)?;
+        }
+        let mut delay = self.config.write_delay_ms;
+        if ((self.next - self.config.app_address) as usize).is_multiple_of(self.config.page_size) {
+            delay += self.config.erase_delay_ms;
+        }
+        std::thread::sleep(Duration::from_millis(delay));
+        self.next += TRANSFER_SIZE as u32;
+        Ok(())
+    }
+}
+
+impl Bootloader for Stm32Bootloader {
+    fn name(&self) -> &'static str { NAME }
+
+    fn block_size(&self) -> usize { TRANSFER_SIZE }
+
+    fn enter(&mut self, _t: &dyn Transport) -> Result<(), String> {
+        self.next = self.config.app_address;
+        Ok(())
+    }
+
+    // 只能從應用程式起始位址依序寫入；抹除由設備在每個 page 起點自行進行
+    fn erase(&mut self, t: &dyn Transport, image: &FirmwareImage) -> Result<(), String> {
+        let start = image.segments.first().map(|s| s.address).ok_or("韌體檔是空的")?;
+        if start < self.config.app_address {
+            return Err(format!("韌體起始位址 {:#010x} 早於應用程式區 {:#010x}，會覆寫 bootloader", start, self.config.app_address));
+        }
+        command(t, CMD_RESET_PAGES)
+    }
+
+    fn write_block(&mut self, t: &dyn Transport, address: u32, data: &[u8]) -> Result<(), String> {
+        if address < self.next {
+            return Err(format!("位址 {:#010x} 無法依序寫入", address));
+        }
+        // 區段之間的空隙以 0xFF 填滿
+        while self.next + (TRANSFER_SIZE as u32) <= address {
+            self.transfer(t, &[])?;
+        }
+        let mut buf = vec![0xFF; (address - self.next) as usize];
+        buf.extend_from_slice(data);
+        self.transfer(t, &buf)
+    }
+
+    fn finish(&mut self, t: &dyn Transport) -> Result<(), String> {
+        command(t, CMD_REBOOT_MCU)
+    }
+}
@@ -0,0 +1,131 @@
+// --- UF2 ---
+// 由 512 bytes 的 block 組成，每個 block 各自帶有寫入位址：
+//   0   magic 0x0A324655   4  magic 0x9E5D5157   8  flags
+//   12  位址   16  資料長度   20  block 序號   24  block 總數   28  family ID (flags 含 0x2000 時)
+//   32  資料 (最多 476 bytes)   508  magic 0x0AB16F30
+// 序號需從 0 連續遞增到總數 - 1；合併多個 family 的檔案依 family 各自編號。
+
+use super::{merge_chunks, Segment};
+use std::collections::BTreeMap;
+
+const BLOCK_SIZE: usize = 512;
+const MAX_PAYLOAD: usize = 476;
+const MAGIC_START0: u32 = 0x0A32_4655;
+const MAGIC_START1: u32 = 0x9E5D_5157;
+const MAGIC_END: u32 = 0x0AB1_6F30;
+
+const FLAG_NOT_MAIN_FLASH: u32 = 0x0000_0001;
+const FLAG_FILE_CONTAINER: u32 = 0x0000_1000;
+const FLAG_FAMILY_ID: u32 = 0x0000_2000;
+
+pub struct Uf2File {
+    pub segments: Vec<Segment>,
+    pub family_id: Option<u32>,
+}
+
+pub fn is_uf2(data: &[u8]) -> bool {
+    data.len() >= 8 && data[..4] == MAGIC_START0.to_le_bytes() && data[4..8] == MAGIC_START1.to_le_bytes()
+}
+
+// 各 family 的 block 編號進度
+struct Sequence {
+    next: u32,
+    total: u32,
+}
+
+pub fn parse(data: &[u8]) -> Result<Uf2File, String> {
+    if data.is_empty() || !data.len().is_multiple_of(BLOCK_SIZE) {
+        return Err(format!("UF2 檔長度必須是 {} 的倍數", BLOCK_SIZE));
+    }
+    let mut chunks = Vec::new();
+    let mut sequences: BTreeMap<Option<u32>, Sequence> = BTreeMap::new();
+    for (n, block) in data.chunks(BLOCK_SIZE).enumerate() {
+        let word = |i: usize| u32::from_le_bytes([block[i], block[i + 1], block[i + 2], block[i + 3]]);
+        if word(0) != MAGIC_START0 || word(4) != MAGIC_START1 || word(508) != MAGIC_END {
+            return Err(format!("UF2 block {} 的 magic 錯誤", n));
+        }
+        let flags = word(8);
+        // 不是寫入 flash 的資料 (註解、檔案容器) 略過
+        if flags & (FLAG_NOT_MAIN_FLASH | FLAG_FILE_CONTAINER) != 0 { continue; }
+        let (address, size, seq, total) = (word(12), word(16) as usize, word(20), word(24));
+        if size > MAX_PAYLOAD { return Err(format!("UF2 block {} 的資料長度 {} 超過 {}", n, size, MAX_PAYLOAD)); }
+        let family = if flags & FLAG_FAMILY_ID != 0 { Some(word(28)) } else { None };
+
+        let state = sequences.entry(family).or_insert(Sequence { next: 0, total });
+        if total != state.total {
+            return Err(format!("UF2 block {} 的 block 總數 {} 與前面的 {} 不一致", n, total, state.total));
+        }
+        if seq != state.next {
+            return Err(format!("UF2 block {} 的序號為 {}，預期 {}", n, seq, state.next));
+        }
+        state.next += 1;
+        chunks.push((address, block[32..32 + size].to_vec()));
+    }
+    for (family, state) in &sequences {
+        if state.next != state.total {
+            return Err(format!("UF2 family {} 只有 {} / {} 個 block", family_name(*family), state.next, state.total));
+        }
+    }
+    let family_id = match sequences.keys().collect::<Vec<_>>()[..] {
+        [family] => *family,
+        [] => return Err("UF2 檔沒有任何 flash 資料".into()),
+        ref families => {
+            let names: Vec<String> = families.iter().map(|f| family_name(**f)).collect();
+            return Err(format!("UF2 檔包含多個 family ({})，請先拆開", names.join(", ")));
+        }
+    };
+    Ok(Uf2File { segments: merge_chunks(chunks)?, family_id })
+}
+
+pub fn family_name(family: Option<u32>) -> String {
+    family.map_or("(未指定)".into(), |f| format!("{:#010x}", f))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 組出一個 block；family 為 None 時不設 family 旗標
+    fn block(address: u32, data: &[u8], seq: u32, total: u32, family: Option<u32>) -> Vec<u8> {
+        let mut b = vec![0u8; BLOCK_SIZE];
+        let flags = if family.is_some() { FLAG_FAMILY_ID } else { 0 };
+        for (i, v) in [(0, MAGIC_START0), (4, MAGIC_START1), (8, flags), (12, address), (16, data.len() as u32),
+            (20, seq), (24, total), (28, family.unwrap_or(0)), (508, MAGIC_END)] {
+            b[i..i + 4].copy_from_slice(&v.to_le_bytes());
+        }
+        b[32..32 + data.len()].copy_from_slice(data);
+        b
+    }
+
+    #[test]
+    fn merges_consecutive_blocks() {
+        let family = Some(0xE48B_FF56);
+        let data = [block(0x1000_0000, &[1; 256], 0, 2, family), block(0x1000_0100, &[2; 256], 1, 2, family)].concat();
+        assert!(is_uf2(&data));
+        let file = parse(&data).unwrap();
+        assert_eq!(file.family_id, family);
+        assert_eq!(file.segments.len(), 1);
+        assert_eq!(file.segments[0].address, 0x1000_0000);
+        assert_eq!(file.segments[0].data.len(), 512);
+        assert_eq!(file.segments[0].data[256], 2);
+    }
+
+    #[test]
+    fn rejects_gaps_and_oversized_payloads() {
+        // 缺少序號 1
+        let data = [block(0, &[1; 4], 0, 3, None), block(8, &[1; 4], 2, 3, None)].concat();
+        assert!(parse(&data).is_err());
+        // 總數宣告 2 卻只有 1 個 block
+        assert!(parse(&block(0, &[1; 4], 0, 2, None)).is_err());
+        let mut big = block(0, &[], 0, 1, None);
+        big[16..20].copy_from_slice(&(MAX_PAYLOAD as u32 + 1).to_le_bytes());
+        assert!(parse(&big).is_err());
+        assert!(parse(&[0u8; 100]).is_err());
+    }
+
+    #[test]
+    fn rejects_mixed_families() {
+        let data = [block(0, &[1; 4], 0, 1, Some(1)), block(0x100, &[1; 4], 0, 1, Some(2))].concat();
+        assert!(parse(&data).is_err_and(|e| e.contains("多個 family")));
+    }
+}
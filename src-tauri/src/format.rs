@@ -0,0 +1,61 @@
+// --- 數值顯示格式 ---
+// schema 可指定解碼後數值的顯示方式。字串統一在這裡產生，
+// 讓匯出檔、console 與 UI 看到的表示法完全一致。
+//
+// "format": { "style": "fixed", "decimals": 2 }
+// "format": { "style": "hex", "width": 4 }
+// "format": { "style": "binary", "width": 8 }
+
+use crate::regmap::Encoding;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FormatStyle {
+    // 直接輸出換算後的值 (不補零、不固定小數位)
+    #[default]
+    Auto,
+    // 固定小數位數 (換算後的值)
+    Fixed,
+    // 十六進位 / 二進位 (原始整數值，負數以編碼寬度的二補數表示)
+    Hex,
+    Binary,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ValueFormat {
+    pub style: FormatStyle,
+    pub decimals: Option<usize>,
+    // hex / binary 最少位數，不足補零
+    pub width: Option<usize>,
+}
+
+impl ValueFormat {
+    // raw: 未經 scale 的原始值；bits: 編碼寬度；value: 換算後的實際值
+    pub fn apply(&self, raw: f64, bits: u32, value: f64) -> String {
+        let int = raw as i64;
+        let masked = if bits >= 64 { int as u64 } else { (int as u64) & ((1u64 << bits) - 1) };
+        let width = self.width.unwrap_or(0);
+        match self.style {
+            FormatStyle::Auto => value.to_string(),
+            FormatStyle::Fixed => format!("{:.*}", self.decimals.unwrap_or(0), value),
+            FormatStyle::Hex => format!("0x{:0width$X}", masked, width = width),
+            FormatStyle::Binary => format!("0b{:0width$b}", masked, width = width),
+        }
+    }
+}
+
+// 狀態名稱表的載入檢查：值必須在編碼範圍內，名稱不可為空
+pub fn check_labels(name: &str, encoding: Encoding, labels: &BTreeMap<i64, String>) -> Result<(), String> {
+    if labels.is_empty() { return Ok(()); }
+    let (min, max) = encoding.range().ok_or(format!("{}: 浮點數欄位不能定義 labels", name))?;
+    if let Some(v) = labels.keys().find(|v| **v < min || **v > max) {
+        return Err(format!("{}: label 值 {} 超出 {:?} 範圍", name, v, encoding));
+    }
+    if let Some((v, _)) = labels.iter().find(|(_, l)| l.trim().is_empty()) {
+        return Err(format!("{}: 值 {} 的 label 是空字串", name, v));
+    }
+    Ok(())
+}
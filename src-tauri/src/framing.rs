@@ -0,0 +1,153 @@
+// --- 廠商協定的封包格式 (framing profile) ---
+// 以 JSON 描述 header、長度欄位、checksum 與 footer，套用到設備後：
+//   送出時把呼叫端的 payload 包成完整 frame，收到的 report 則驗證並拆出 payload (hid-frame 事件)。
+// frame 的排列： header | payload 前段 | 長度欄位 | payload 其餘 | checksum | footer
+// 長度欄位的 offset 大於 header 長度時，中間的位元組取自 payload 開頭 (例如長度前面的指令碼)。
+//
+// 例: { "header": [170, 85], "length": { "offset": 2, "width": 1 },
+//       "checksum": { "algorithm": "sum8", "start": 2 }, "footer": [13] }
+//     payload [01 02] => AA 55 02 01 02 05 0D
+
+use crate::checksum::AppendChecksum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct LengthField {
+    // 在 frame 中的位置 (不可小於 header 長度)
+    pub offset: usize,
+    // 1、2 或 4 bytes
+    pub width: usize,
+    #[serde(default)]
+    pub big_endian: bool,
+    // 欄位值 = payload 長度 + adjust (例如長度包含 checksum 時為 checksum 的寬度)
+    #[serde(default)]
+    pub adjust: i64,
+}
+
+impl LengthField {
+    fn encode(&self, len: usize) -> Result<Vec<u8>, String> {
+        let value = len as i64 + self.adjust;
+        if value < 0 || value >= 1i64 << (self.width * 8) {
+            return Err(format!("長度 {} 無法放入 {} bytes 的長度欄位", value, self.width));
+        }
+        let value = value as u32;
+        Ok(if self.big_endian { value.to_be_bytes()[4 - self.width..].to_vec() } else { value.to_le_bytes()[..self.width].to_vec() })
+    }
+
+    fn decode(&self, raw: &[u8]) -> Result<usize, String> {
+        let value = raw.iter().enumerate().fold(0i64, |acc, (i, b)| {
+            let shift = if self.big_endian { (raw.len() - 1 - i) * 8 } else { i * 8 };
+            acc | (*b as i64) << shift
+        });
+        usize::try_from(value - self.adjust).map_err(|_| format!("長度欄位 {} 小於 adjust {}", value, self.adjust))
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct FramingProfile {
+    pub header: Vec<u8>,
+    pub length: Option<LengthField>,
+    // 範圍以 frame 的索引計算；end 未指定代表到 checksum 之前
+    pub checksum: Option<AppendChecksum>,
+    pub footer: Vec<u8>,
+    // 只處理這個 Report ID 的 report，送出時也使用此 ID；未指定時依設備是否使用 Report ID
+    pub report_id: Option<u8>,
+}
+
+impl FramingProfile {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(field) = &self.length {
+            if ![1, 2, 4].contains(&field.width) {
+                return Err(format!("長度欄位寬度必須是 1、2 或 4，而不是 {}", field.width));
+            }
+            if field.offset < self.header.len() {
+                return Err(format!("長度欄位 offset {} 與 header ({} bytes) 重疊", field.offset, self.header.len()));
+            }
+        } else if self.footer.is_empty() && self.checksum.is_some() {
+            // 沒有長度與 footer 時無法分辨 checksum 與 report 後面補的零
+            return Err("使用 checksum 時需要長度欄位或 footer".into());
+        }
+        Ok(())
+    }
+
+    fn checksum_width(&self) -> usize {
+        self.checksum.as_ref().map_or(0, |c| c.algorithm.width())
+    }
+
+    // 把 payload 包成完整 frame (不含 Report ID)
+    pub fn frame(&self, payload: &[u8]) -> Result<Vec<u8>, String> {
+        let mut out = self.header.clone();
+        match &self.length {
+            Some(field) => {
+                let split = field.offset - self.header.len();
+                let (before, after) = payload.split_at_checked(split)
+                    .ok_or(format!("payload 長度 {} 不足以填到長度欄位 (offset {})", payload.len(), field.offset))?;
+                out.extend_from_slice(before);
+                out.extend(field.encode(payload.len())?);
+                out.extend_from_slice(after);
+            }
+            None => out.extend_from_slice(payload),
+        }
+        if let Some(checksum) = &self.checksum {
+            out = checksum.apply(&out)?;
+        }
+        out.extend_from_slice(&self.footer);
+        Ok(out)
+    }
+
+    // report 中 frame 的起點；不是此 profile 處理的 Report ID 時回傳 None
+    pub fn locate<'a>(&self, report: &'a [u8], uses_report_ids: bool) -> Option<&'a [u8]> {
+        match self.report_id {
+            Some(id) => report.split_first().filter(|(first, _)| **first == id).map(|(_, rest)| rest),
+            None if uses_report_ids => report.get(1..),
+            None => Some(report),
+        }
+    }
+
+    // 驗證 frame 並取出 payload；frame 後面可以有補齊 report 長度的位元組
+    pub fn unframe(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        if !data.starts_with(&self.header) {
+            return Err("header 不符".into());
+        }
+        let width = self.checksum_width();
+        let (payload, body_end) = match &self.length {
+            Some(field) => {
+                let raw = data.get(field.offset..field.offset + field.width).ok_or("frame 長度不足，沒有長度欄位")?;
+                let len = field.decode(raw)?;
+                let split = field.offset - self.header.len();
+                if len < split {
+                    return Err(format!("長度 {} 小於長度欄位前的 {} bytes", len, split));
+                }
+                let body_end = field.offset + field.width + len - split;
+                let rest = data.get(field.offset + field.width..body_end)
+                    .ok_or(format!("frame 不完整: 長度 {}，report 只有 {} bytes", len, data.len()))?;
+                let mut payload = data[self.header.len()..field.offset].to_vec();
+                payload.extend_from_slice(rest);
+                (payload, body_end)
+            }
+            None => {
+                // 以最後一個 footer 判斷結尾；沒有 footer 時 payload 到 report 結尾
+                let end = if self.footer.is_empty() {
+                    data.len()
+                } else {
+                    data.windows(self.footer.len()).rposition(|w| w == self.footer.as_slice()).ok_or("找不到 footer")?
+                };
+                let body_end = end.checked_sub(width).filter(|e| *e >= self.header.len()).ok_or("frame 長度不足")?;
+                (data[self.header.len()..body_end].to_vec(), body_end)
+            }
+        };
+        if let Some(checksum) = &self.checksum {
+            let expected = checksum.apply(&data[..body_end])?;
+            let actual = data.get(body_end..body_end + width).ok_or("frame 長度不足，沒有 checksum")?;
+            if actual != &expected[body_end..] {
+                return Err(format!("checksum 錯誤 (收到 {:02X?}, 計算為 {:02X?})", actual, &expected[body_end..]));
+            }
+        }
+        let footer_at = body_end + width;
+        if data.get(footer_at..footer_at + self.footer.len()) != Some(self.footer.as_slice()) {
+            return Err("footer 不符".into());
+        }
+        Ok(payload)
+    }
+}
@@ -0,0 +1,141 @@
+// --- 搖桿 / 手把 report 解碼 ---
+// 依 descriptor 取出類比軸 (正規化到 -1.0..1.0)、方向鍵 (Hat Switch) 與按鍵。
+
+use crate::descriptor::{ReportDescriptor, ReportField, ReportKind};
+use crate::protocols::DeviceIdentity;
+use crate::usages::{usage_name, PAGE_BUTTON, PAGE_GENERIC_DESKTOP};
+use serde::Serialize;
+
+const USAGE_JOYSTICK: u16 = 0x04;
+const USAGE_GAMEPAD: u16 = 0x05;
+const USAGE_MULTI_AXIS: u16 = 0x08;
+// X, Y, Z, Rx, Ry, Rz, Slider, Dial, Wheel
+const AXIS_USAGES: std::ops::RangeInclusive<u16> = 0x30..=0x38;
+const USAGE_HAT_SWITCH: u16 = 0x39;
+// Simulation Controls (油門、煞車、方向盤等)
+const PAGE_SIMULATION: u16 = 0x02;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HatDirection {
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+#[derive(Serialize, Clone, PartialEq)]
+pub struct GamepadAxis {
+    pub name: String,
+    // 依 logical 範圍正規化，中心為 0
+    pub value: f64,
+    pub raw: i64,
+}
+
+#[derive(Serialize, Clone, PartialEq)]
+pub struct GamepadState {
+    pub axes: Vec<GamepadAxis>,
+    // 每個 Hat Switch 一個，None 代表置中 (null state)
+    pub hats: Vec<Option<HatDirection>>,
+    // 第 i 個元素為 Button i+1
+    pub buttons: Vec<bool>,
+}
+
+pub struct GamepadDecoder {
+    desc: ReportDescriptor,
+    // 上一次的狀態，只有變化時才送出
+    last: Option<GamepadState>,
+}
+
+// logical 範圍；descriptor 把無號最大值編成負數時 (例: 0..65535) 改用位元寬度推算
+fn logical_range(f: &ReportField) -> (i64, i64) {
+    let (min, max) = (f.logical_min as i64, f.logical_max as i64);
+    if max > min { (min, max) } else { (0, (1i64 << f.bit_size.min(32)) - 1) }
+}
+
+fn normalize(f: &ReportField, raw: i64) -> f64 {
+    let (min, max) = logical_range(f);
+    let v = (raw - min) as f64 / (max - min) as f64 * 2.0 - 1.0;
+    v.clamp(-1.0, 1.0)
+}
+
+fn hat_direction(f: &ReportField, raw: i64) -> Option<HatDirection> {
+    use HatDirection::*;
+    let (min, max) = logical_range(f);
+    if raw < min || raw > max { return None; }
+    let step = (raw - min) as usize;
+    // 4 方向的 hat 只有上右下左；8 方向從上開始順時針
+    match max - min + 1 {
+        4 => [Up, Right, Down, Left].get(step).copied(),
+        _ => [Up, UpRight, Right, DownRight, Down, DownLeft, Left, UpLeft].get(step).copied(),
+    }
+}
+
+impl GamepadDecoder {
+    // 只處理頂層 usage 為 Joystick / Gamepad / Multi-axis Controller 的設備
+    pub fn new(identity: &DeviceIdentity, desc: Option<&ReportDescriptor>) -> Option<Self> {
+        if identity.usage_page != PAGE_GENERIC_DESKTOP
+            || !matches!(identity.usage, USAGE_JOYSTICK | USAGE_GAMEPAD | USAGE_MULTI_AXIS) {
+            return None;
+        }
+        Some(Self { desc: desc?.clone(), last: None })
+    }
+
+    // 狀態與上一筆相同時回傳 None
+    pub fn feed(&mut self, report: &[u8]) -> Option<GamepadState> {
+        let report_id = if self.desc.uses_report_ids { *report.first()? } else { 0 };
+        let body = self.desc.report_body(report, report_id)?;
+        let fields: Vec<&ReportField> = self.desc.fields.iter()
+            .filter(|f| f.kind == ReportKind::Input && f.report_id == report_id && !f.is_constant())
+            .collect();
+
+        let mut state = GamepadState { axes: Vec::new(), hats: Vec::new(), buttons: Vec::new() };
+        for f in fields.iter().filter(|f| f.is_variable()) {
+            for i in 0..f.count {
+                let Some(usage) = f.usages.get(i).or(f.usages.last()).copied() else { continue };
+                let Some(raw) = f.element_value(body, i) else { continue };
+                match (f.usage_page, usage) {
+                    (PAGE_GENERIC_DESKTOP, USAGE_HAT_SWITCH) => state.hats.push(hat_direction(f, raw)),
+                    (PAGE_GENERIC_DESKTOP, u) if AXIS_USAGES.contains(&u) && !f.is_relative() => {
+                        state.axes.push(GamepadAxis { name: usage_name(f.usage_page, u), value: normalize(f, raw), raw });
+                    }
+                    (PAGE_SIMULATION, u) if !f.is_relative() => {
+                        state.axes.push(GamepadAxis { name: usage_name(f.usage_page, u), value: normalize(f, raw), raw });
+                    }
+                    (PAGE_BUTTON, u) if u > 0 => {
+                        let index = u as usize - 1;
+                        if state.buttons.len() <= index { state.buttons.resize(index + 1, false); }
+                        state.buttons[index] = raw != 0;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        // Array 形式的按鍵欄位：每個元素是目前按下的按鍵編號
+        for f in fields.iter().filter(|f| !f.is_variable() && f.usage_page == PAGE_BUTTON) {
+            let max = f.usages.iter().copied().max().unwrap_or(0) as usize;
+            if state.buttons.len() < max { state.buttons.resize(max, false); }
+            for i in 0..f.count {
+                let Some(raw) = f.element_value(body, i) else { continue };
+                let index = (raw - f.logical_min as i64) as usize;
+                if let Some(button) = f.usages.get(index).filter(|u| **u > 0) {
+                    state.buttons[*button as usize - 1] = true;
+                }
+            }
+        }
+
+        // 這個 Report ID 沒有搖桿相關欄位
+        if state.axes.is_empty() && state.hats.is_empty() && state.buttons.is_empty() {
+            return None;
+        }
+        if self.last.as_ref() == Some(&state) {
+            return None;
+        }
+        self.last = Some(state.clone());
+        Some(state)
+    }
+}
@@ -0,0 +1,144 @@
+// --- 常用設備與啟動健康檢查 ---
+// 常用設備 (favorites) 以 VID / PID 加上選用的序號、介面辨識 (路徑在重新插拔後可能改變)，
+// 設定存於 app config 目錄的 favorites.json。check_on_launch 開啟時，啟動後對每個常用設備
+// 找到 -> 開啟 -> 經由協定插件查詢韌體版本與狀態 -> 關閉，結果以 health-check 事件送出：
+//   green  開啟成功，版本與狀態查詢正常
+//   yellow 開啟成功但查詢失敗，或電量偏低
+//   red    找不到設備或無法開啟
+//
+// { "check_on_launch": true,
+//   "devices": [ { "name": "scanner-A", "vendor_id": 1234, "product_id": 5678, "serial_number": "SN01" } ] }
+
+use crate::protocols::HeadsetStatus;
+use crate::webhook::DeviceIdentityReport;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// 電量低於此值時為 yellow
+const LOW_BATTERY: u8 = 20;
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Favorite {
+    pub name: String,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    // 同型號有多台時以序號區分
+    #[serde(default)]
+    pub serial_number: Option<String>,
+    // 複合設備指定要檢查的介面
+    #[serde(default)]
+    pub interface_number: Option<i32>,
+}
+
+impl Favorite {
+    pub fn matches(&self, dev: &DeviceIdentityReport) -> bool {
+        dev.vendor_id == self.vendor_id
+            && dev.product_id == self.product_id
+            && self.serial_number.as_ref().is_none_or(|s| dev.serial_number.as_ref() == Some(s))
+            && self.interface_number.is_none_or(|i| dev.interface_number == i)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct FavoritesConfig {
+    pub check_on_launch: bool,
+    pub devices: Vec<Favorite>,
+}
+
+// 順序即嚴重程度，整體結果取最嚴重者
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Health {
+    Green,
+    Yellow,
+    Red,
+}
+
+#[derive(Serialize, Clone)]
+pub struct HealthResult {
+    pub name: String,
+    pub health: Health,
+    pub path: Option<String>,
+    pub firmware_version: Option<String>,
+    // 協定支援耳機狀態時的查詢結果
+    pub status: Option<HeadsetStatus>,
+    pub message: Option<String>,
+}
+
+impl HealthResult {
+    pub fn missing(fav: &Favorite) -> Self {
+        Self { name: fav.name.clone(), health: Health::Red, path: None, firmware_version: None, status: None, message: Some("找不到設備".into()) }
+    }
+
+    pub fn unopened(fav: &Favorite, path: String, error: String) -> Self {
+        Self { name: fav.name.clone(), health: Health::Red, path: Some(path), firmware_version: None, status: None, message: Some(format!("無法開啟: {}", error)) }
+    }
+
+    // 開啟成功後依查詢結果評定 green / yellow
+    pub fn probed(fav: &Favorite, path: String, query: Result<(String, Option<HeadsetStatus>), String>) -> Self {
+        let (health, firmware_version, status, message) = match query {
+            Err(e) => (Health::Yellow, None, None, Some(format!("查詢失敗: {}", e))),
+            Ok((version, status)) => match status.as_ref().and_then(|s| s.battery).filter(|b| *b < LOW_BATTERY) {
+                Some(battery) => (Health::Yellow, Some(version), status, Some(format!("電量偏低 ({}%)", battery))),
+                None => (Health::Green, Some(version), status, None),
+            },
+        };
+        Self { name: fav.name.clone(), health, path: Some(path), firmware_version, status, message }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct HealthReport {
+    pub overall: Health,
+    pub results: Vec<HealthResult>,
+    pub checked_ms: i64,
+}
+
+impl HealthReport {
+    pub fn new(results: Vec<HealthResult>, checked_ms: i64) -> Self {
+        let overall = results.iter().map(|r| r.health).max().unwrap_or(Health::Green);
+        Self { overall, results, checked_ms }
+    }
+}
+
+pub struct Favorites {
+    pub config: FavoritesConfig,
+    // 設定檔位置 (app config 目錄取不到時為 None，只保存在記憶體)
+    file: Option<PathBuf>,
+    // 最近一次的檢查結果
+    pub last_report: Option<HealthReport>,
+}
+
+impl Default for Favorites {
+    fn default() -> Self { Self::new() }
+}
+
+impl Favorites {
+    pub fn new() -> Self {
+        Self { config: FavoritesConfig::default(), file: None, last_report: None }
+    }
+
+    // 載入設定檔；檔案不存在時視為沒有常用設備
+    pub fn load(&mut self, file: &Path) -> Result<(), String> {
+        self.file = Some(file.to_path_buf());
+        if !file.exists() { return Ok(()); }
+        let text = std::fs::read_to_string(file).map_err(|e| format!("讀取 {} 失敗: {}", file.display(), e))?;
+        self.config = serde_json::from_str(&text).map_err(|e| format!("常用設備設定格式錯誤: {}", e))?;
+        Ok(())
+    }
+
+    pub fn set(&mut self, config: FavoritesConfig) -> Result<(), String> {
+        if let Some(dup) = config.devices.iter().enumerate()
+            .find(|(i, f)| config.devices[..*i].iter().any(|g| g.name == f.name)) {
+            return Err(format!("常用設備名稱重複: {}", dup.1.name));
+        }
+        self.config = config;
+        let Some(file) = &self.file else { return Ok(()) };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("建立目錄失敗: {}", e))?;
+        }
+        let text = serde_json::to_string_pretty(&self.config).map_err(|e| e.to_string())?;
+        std::fs::write(file, text).map_err(|e| format!("寫入 {} 失敗: {}", file.display(), e))
+    }
+}
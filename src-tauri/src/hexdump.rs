@@ -0,0 +1,247 @@
+// --- 十六進位傾印匯入 ---
+// 從文字檔讀出要送往設備的 payload，格式依內容自動判斷 (同一個檔案只能使用一種格式)：
+//   xxd          "00000000: 0102 0a0b 4142  ....AB"
+//   hexdump -C   "00000000  01 02 0a 0b 41 42  |....AB|"
+//                位移連續的行合併成一個 payload，空白行或位移不連續時開始下一個；'*' (重複的行) 會展開
+//   C 陣列       "uint8_t cmd[] = { 0x01, 0x02, 10 };"，每組最內層的大括號是一個 payload (可跨行)，
+//                沒有 0x 前綴的數字依 C 的規則視為十進位
+//   純十六進位   其他情況每一行一個 payload："01 02 0A"、"01020A"、"0x01, 0x02"、"01:02:0A"
+// '#' 與 '//' 之後為註解 (C 陣列另外支援 /* */)。
+//
+// [{ "line": 3, "data": [1, 2, 10] }, { "line": 4, "data": [1, 3] }]
+
+use serde::Serialize;
+
+#[derive(Serialize, Clone)]
+pub struct HexPayload {
+    // payload 開始的行號 (從 1 開始)
+    pub line: usize,
+    pub data: Vec<u8>,
+}
+
+pub fn read(file: &str) -> Result<Vec<HexPayload>, String> {
+    let text = std::fs::read_to_string(file).map_err(|e| format!("讀取 {} 失敗: {}", file, e))?;
+    let payloads = parse(&text)?;
+    if payloads.is_empty() { return Err(format!("{} 中沒有找到任何 payload", file)); }
+    Ok(payloads)
+}
+
+pub fn parse(text: &str) -> Result<Vec<HexPayload>, String> {
+    if text.lines().any(|line| matches!(dump_line(line), Some(DumpLine::Data { .. }))) {
+        parse_dump(text)
+    } else if text.contains('{') {
+        parse_c_arrays(text)
+    } else {
+        parse_plain(text)
+    }
+}
+
+// --- xxd / hexdump -C ---
+
+enum DumpLine {
+    Data { offset: u64, bytes: Vec<u8> },
+    // hexdump -C 最後一行只有總長度
+    End,
+    // 與上一行相同的行被省略
+    Repeat,
+}
+
+fn dump_line(line: &str) -> Option<DumpLine> {
+    if line.trim() == "*" { return Some(DumpLine::Repeat); }
+    let digits = line.bytes().take_while(u8::is_ascii_hexdigit).count();
+    if digits < 4 { return None; }
+    let offset = u64::from_str_radix(&line[..digits], 16).ok()?;
+    let rest = &line[digits..];
+    let hex = if let Some(rest) = rest.strip_prefix(": ") {
+        // xxd：十六進位與 ASCII 欄之間至少兩個空白
+        rest.split("  ").next().unwrap_or("")
+    } else if rest.starts_with("  ") && rest.contains('|') {
+        // hexdump -C：十六進位中間也有兩個空白，以 '|' 分隔 ASCII 欄
+        rest.split('|').next().unwrap_or("")
+    } else if rest.trim().is_empty() && digits >= 7 {
+        return Some(DumpLine::End);
+    } else {
+        return None;
+    };
+    let mut bytes = Vec::new();
+    for group in hex.split_whitespace() {
+        bytes.extend(hex_digits(group)?);
+    }
+    Some(DumpLine::Data { offset, bytes })
+}
+
+fn parse_dump(text: &str) -> Result<Vec<HexPayload>, String> {
+    let mut payloads: Vec<HexPayload> = Vec::new();
+    // 目前的 payload 與下一行應有的位移
+    let mut current: Option<(HexPayload, u64)> = None;
+    let mut last_row: Vec<u8> = Vec::new();
+    let mut repeat = false;
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            payloads.extend(current.take().map(|(p, _)| p));
+            repeat = false;
+            continue;
+        }
+        if trimmed.starts_with('#') || trimmed.starts_with("//") { continue; }
+        let parsed = dump_line(line).ok_or_else(|| format!("第 {} 行不是 xxd / hexdump 格式: {}", i + 1, trimmed))?;
+        let (offset, bytes) = match parsed {
+            DumpLine::Repeat => {
+                repeat = true;
+                continue;
+            }
+            DumpLine::End => (None, Vec::new()),
+            DumpLine::Data { offset, bytes } => (Some(offset), bytes),
+        };
+        if let Some((payload, next)) = current.as_mut() {
+            let target = offset.unwrap_or(*next);
+            if repeat && !last_row.is_empty() && target > *next && (target - *next).is_multiple_of(last_row.len() as u64) {
+                for _ in 0..(target - *next) / last_row.len() as u64 {
+                    payload.data.extend_from_slice(&last_row);
+                }
+                *next = target;
+            }
+            if target != *next {
+                payloads.extend(current.take().map(|(p, _)| p));
+            }
+        }
+        repeat = false;
+        let Some(offset) = offset else {
+            payloads.extend(current.take().map(|(p, _)| p));
+            continue;
+        };
+        let (payload, next) = current.get_or_insert_with(|| (HexPayload { line: i + 1, data: Vec::new() }, offset));
+        payload.data.extend_from_slice(&bytes);
+        *next = offset + bytes.len() as u64;
+        last_row = bytes;
+    }
+    payloads.extend(current.map(|(p, _)| p));
+    payloads.retain(|p| !p.data.is_empty());
+    Ok(payloads)
+}
+
+// --- C 陣列 ---
+
+// 註解換成空白 (保留換行，行號才不會偏移)；'#' 開頭的前置處理指令整行略過
+fn strip_c_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut line_start = true;
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|&c| c != '\n') { chars.next(); }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' { out.push('\n'); }
+                    if prev == '*' && c == '/' { break; }
+                    prev = c;
+                }
+            }
+            '#' if line_start => {
+                while chars.peek().is_some_and(|&c| c != '\n') { chars.next(); }
+            }
+            _ => out.push(c),
+        }
+        if c == '\n' {
+            line_start = true;
+        } else if !c.is_whitespace() {
+            line_start = false;
+        }
+    }
+    out
+}
+
+fn parse_c_arrays(text: &str) -> Result<Vec<HexPayload>, String> {
+    let text = strip_c_comments(text);
+    let mut payloads = Vec::new();
+    // 尚未關閉的大括號：(開始位置, 行號, 是否包含內層大括號)
+    let mut open: Vec<(usize, usize, bool)> = Vec::new();
+    let mut line = 1;
+    for (pos, c) in text.char_indices() {
+        match c {
+            '\n' => line += 1,
+            '{' => {
+                if let Some(outer) = open.last_mut() { outer.2 = true; }
+                open.push((pos + 1, line, false));
+            }
+            '}' => {
+                let (start, start_line, nested) = open.pop().ok_or_else(|| format!("第 {} 行多了一個 '}}'", line))?;
+                if nested { continue; }
+                let data = c_values(&text[start..pos], start_line)?;
+                if !data.is_empty() { payloads.push(HexPayload { line: start_line, data }); }
+            }
+            _ => {}
+        }
+    }
+    if let Some((_, start_line, _)) = open.last() {
+        return Err(format!("第 {} 行的 '{{' 沒有對應的 '}}'", start_line));
+    }
+    Ok(payloads)
+}
+
+fn c_values(body: &str, start_line: usize) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    let mut line = start_line;
+    for token in body.split_inclusive([',', '\n']) {
+        let value = token.trim_end_matches([',', '\n']).trim();
+        if !value.is_empty() {
+            data.push(c_value(value).ok_or_else(|| format!("第 {} 行的數值無法解析: {}", line, value))?);
+        }
+        if token.ends_with('\n') { line += 1; }
+    }
+    Ok(data)
+}
+
+// 0x1F、31、'A'，可帶 u / U 後綴與 (uint8_t) 之類的轉型
+fn c_value(token: &str) -> Option<u8> {
+    let token = match token.strip_prefix('(') {
+        Some(rest) => rest.split_once(')')?.1.trim(),
+        None => token,
+    };
+    if let Some(ch) = token.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+        let mut chars = ch.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii() => Some(c as u8),
+            _ => None,
+        };
+    }
+    let token = token.trim_end_matches(['u', 'U']);
+    let value = match token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None => token.parse::<u32>().ok()?,
+    };
+    u8::try_from(value).ok()
+}
+
+// --- 純十六進位 ---
+
+fn parse_plain(text: &str) -> Result<Vec<HexPayload>, String> {
+    let mut payloads = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let content = line.split('#').next().unwrap_or("");
+        let content = content.split("//").next().unwrap_or("");
+        let mut data = Vec::new();
+        for token in content.split(|c: char| c.is_whitespace() || matches!(c, ',' | ':' | '-' | ';')) {
+            if token.is_empty() { continue; }
+            let bytes = match token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")) {
+                Some(hex) if (1..=2).contains(&hex.len()) => u8::from_str_radix(hex, 16).ok().map(|b| vec![b]),
+                Some(hex) => hex_digits(hex),
+                None if token.len() == 1 => u8::from_str_radix(token, 16).ok().map(|b| vec![b]),
+                None => hex_digits(token),
+            };
+            data.extend(bytes.ok_or_else(|| format!("第 {} 行的內容無法解析: {}", i + 1, token))?);
+        }
+        if !data.is_empty() { payloads.push(HexPayload { line: i + 1, data }); }
+    }
+    Ok(payloads)
+}
+
+// 偶數個十六進位數字，每兩個一個位元組
+fn hex_digits(text: &str) -> Option<Vec<u8>> {
+    if text.is_empty() || !text.len().is_multiple_of(2) || !text.bytes().all(|b| b.is_ascii_hexdigit()) { return None; }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok()).collect()
+}
@@ -0,0 +1,74 @@
+// --- 設備 I/O 抽象 ---
+// 實體設備 (hidapi) 與模擬設備 (demo 模式) 共用同一組介面。
+
+use crate::descriptor::{ReportDescriptor, ReportKind};
+use hidapi::HidDevice;
+
+pub trait HidIo: Send {
+    fn write(&self, data: &[u8]) -> Result<usize, String>;
+    // 以 control transfer (SET_REPORT) 送出 output report，而不是 interrupt OUT
+    fn send_output_report(&self, _data: &[u8]) -> Result<(), String> {
+        Err("此設備不支援以 control transfer 寫入 output report".into())
+    }
+    // 逾時回傳 Ok(0)
+    fn read_timeout(&self, buf: &mut [u8], timeout_ms: i32) -> Result<usize, String>;
+    fn send_feature_report(&self, data: &[u8]) -> Result<(), String>;
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, String>;
+    fn report_descriptor(&self) -> Result<Vec<u8>, String>;
+}
+
+// HID 規範上限 4096 bytes
+const MAX_DESCRIPTOR_SIZE: usize = 4096;
+
+impl HidIo for HidDevice {
+    fn write(&self, data: &[u8]) -> Result<usize, String> {
+        HidDevice::write(self, data).map_err(|e| e.to_string())
+    }
+
+    // Windows / Linux hidraw / libusb 後端支援；其他平台由 hidapi 回傳錯誤
+    fn send_output_report(&self, data: &[u8]) -> Result<(), String> {
+        HidDevice::send_output_report(self, data)
+            .map_err(|e| format!("control transfer 寫入失敗 (平台或 hidapi 後端可能不支援): {}", e))
+    }
+
+    fn read_timeout(&self, buf: &mut [u8], timeout_ms: i32) -> Result<usize, String> {
+        HidDevice::read_timeout(self, buf, timeout_ms).map_err(|e| e.to_string())
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> Result<(), String> {
+        HidDevice::send_feature_report(self, data).map_err(|e| e.to_string())
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, String> {
+        HidDevice::get_feature_report(self, buf).map_err(|e| e.to_string())
+    }
+
+    fn report_descriptor(&self) -> Result<Vec<u8>, String> {
+        let mut buf = vec![0u8; MAX_DESCRIPTOR_SIZE];
+        let n = self.get_report_descriptor(&mut buf).map_err(|e| e.to_string())?;
+        buf.truncate(n);
+        Ok(buf)
+    }
+}
+
+// 依 Report ID 順序讀取 feature report (重複的 ID 只讀一次)，長度取自 report descriptor
+// 回傳 (Report ID, 不含 Report ID 的資料)
+pub fn read_feature_reports(
+    dev: &dyn HidIo,
+    desc: &ReportDescriptor,
+    ids: impl IntoIterator<Item = u8>,
+) -> Result<Vec<(u8, Vec<u8>)>, String> {
+    let mut ids: Vec<u8> = ids.into_iter().collect();
+    ids.sort_unstable();
+    ids.dedup();
+    let mut out = Vec::with_capacity(ids.len());
+    for id in ids {
+        let mut buf = vec![0u8; desc.report_len(ReportKind::Feature, id) + 1];
+        buf[0] = id;
+        let n = dev.get_feature_report(&mut buf)
+            .map_err(|e| format!("讀取 feature report {:#04x} 失敗: {}", id, e))?;
+        // 回傳資料第 0 個 byte 固定是 Report ID (沒有 Report ID 時為 0)
+        out.push((id, buf.get(1..n).unwrap_or(&[]).to_vec()));
+    }
+    Ok(out)
+}
@@ -0,0 +1,80 @@
+// --- 擷取 / 匯出完成後的處理 ---
+// 執行外部程式 (例如上傳到實驗室資料管線)，或把檔案複製 / 移動到網路磁碟。
+// 擷取檔旁的註記檔 (見 annotations.rs) 會一起複製 / 移動。
+
+use crate::annotations;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PostCaptureHook {
+    // args 中的 {file} 會替換為擷取檔的完整路徑；沒有 {file} 時附加在最後
+    Run { program: String, #[serde(default)] args: Vec<String> },
+    Copy { destination: String },
+    Move { destination: String },
+}
+
+#[derive(Serialize, Clone)]
+pub struct HookResult {
+    pub file: String,
+    pub ok: bool,
+    // 執行結果說明 (輸出位置或錯誤訊息)
+    pub message: String,
+}
+
+// 目的地是目錄時保留原檔名
+fn target_path(file: &Path, destination: &str) -> Result<PathBuf, String> {
+    let dest = PathBuf::from(destination);
+    if !dest.is_dir() { return Ok(dest); }
+    let name = file.file_name().ok_or(format!("無效的檔案路徑: {}", file.display()))?;
+    Ok(dest.join(name))
+}
+
+// 移動時的原檔在複製後刪除
+fn transfer(file: &Path, target: &Path, remove: bool) -> Result<(), String> {
+    // 跨磁碟 (例如網路磁碟) 無法 rename，改為複製後刪除
+    if remove && std::fs::rename(file, target).is_ok() { return Ok(()); }
+    std::fs::copy(file, target).map_err(|e| format!("{}到 {} 失敗: {}", if remove { "移動" } else { "複製" }, target.display(), e))?;
+    if remove { std::fs::remove_file(file).map_err(|e| format!("已複製但無法刪除原檔: {}", e))?; }
+    Ok(())
+}
+
+fn transfer_with_notes(file: &Path, destination: &str, remove: bool) -> Result<String, String> {
+    let target = target_path(file, destination)?;
+    transfer(file, &target, remove)?;
+    let notes = annotations::notes_file(file);
+    if notes.exists() {
+        transfer(&notes, &annotations::notes_file(&target), remove).map_err(|e| format!("擷取檔已處理，註記檔失敗: {}", e))?;
+    }
+    Ok(target.to_string_lossy().to_string())
+}
+
+fn execute(hook: &PostCaptureHook, file: &Path) -> Result<String, String> {
+    match hook {
+        PostCaptureHook::Run { program, args } => {
+            let file_str = file.to_string_lossy();
+            let has_placeholder = args.iter().any(|a| a.contains("{file}"));
+            let mut args: Vec<String> = args.iter().map(|a| a.replace("{file}", &file_str)).collect();
+            if !has_placeholder { args.push(file_str.to_string()); }
+
+            let output = Command::new(program).args(&args).output()
+                .map_err(|e| format!("無法執行 {}: {}", program, e))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(format!("{} 結束代碼 {}: {}", program, output.status, stderr.trim()));
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        }
+        PostCaptureHook::Copy { destination } => transfer_with_notes(file, destination, false),
+        PostCaptureHook::Move { destination } => transfer_with_notes(file, destination, true),
+    }
+}
+
+pub fn run(hook: &PostCaptureHook, file: &str) -> HookResult {
+    match execute(hook, Path::new(file)) {
+        Ok(message) => HookResult { file: file.to_string(), ok: true, message },
+        Err(message) => HookResult { file: file.to_string(), ok: false, message },
+    }
+}
@@ -0,0 +1,135 @@
+// --- 異常快照 (incident) ---
+// 發生非預期的錯誤時 (寫入失敗、連續失敗進入錯誤狀態、解析 report descriptor 時 panic)，把最近的 report、
+// 當時正在送出的指令與設備資訊存成資料目錄 incidents/ 下帶時間戳記的 JSON，並發送 incident-captured
+// 事件指出檔案位置，方便直接附在問題回報中。同一設備的同一類錯誤在 60 秒內只記錄一次。
+
+use crate::descriptor::ReportSizes;
+use crate::logging::{self, Category, Severity};
+use crate::protocols::DeviceIdentity;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+// 每個設備保留的最近 report 數
+pub const RECENT_REPORTS: usize = 50;
+const MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum IncidentKind {
+    WriteFailed,
+    Fault,
+    DescriptorPanic,
+}
+
+#[derive(Serialize)]
+pub struct RecordedReport {
+    pub timestamp_ms: i64,
+    pub data: Vec<u8>,
+}
+
+// 最近收到的 report (含指令回覆)，超過上限時丟掉最舊的
+#[derive(Serialize, Default)]
+pub struct RecentReports(VecDeque<RecordedReport>);
+
+impl RecentReports {
+    pub fn push(&mut self, data: &[u8]) {
+        if self.0.len() >= RECENT_REPORTS { self.0.pop_front(); }
+        self.0.push_back(RecordedReport { timestamp_ms: chrono::Local::now().timestamp_millis(), data: data.to_vec() });
+    }
+}
+
+#[derive(Serialize)]
+pub struct Incident<'a> {
+    pub kind: IncidentKind,
+    pub error: &'a str,
+    pub path: &'a str,
+    pub device: DeviceIdentity,
+    pub report_sizes: &'a ReportSizes,
+    // 發生錯誤時正在送出的 report
+    pub command: Option<&'a [u8]>,
+    pub recent_reports: Option<&'a RecentReports>,
+}
+
+// 實際寫入檔案的內容
+#[derive(Serialize)]
+struct IncidentFile<'a> {
+    timestamp_ms: i64,
+    app_version: &'static str,
+    os: &'static str,
+    #[serde(flatten)]
+    incident: &'a Incident<'a>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct IncidentEvent {
+    pub path: String,
+    pub kind: IncidentKind,
+    pub error: String,
+    pub file: String,
+}
+
+pub struct IncidentRecorder {
+    // incidents 目錄 (資料目錄取不到時為 None，不記錄)
+    dir: Option<PathBuf>,
+    last: HashMap<(String, IncidentKind), Instant>,
+}
+
+impl Default for IncidentRecorder {
+    fn default() -> Self { Self::new() }
+}
+
+impl IncidentRecorder {
+    pub fn new() -> Self {
+        Self { dir: None, last: HashMap::new() }
+    }
+
+    pub fn set_dir(&mut self, dir: &Path) {
+        self.dir = Some(dir.to_path_buf());
+    }
+
+    // 未設定目錄或距離上一次同類記錄太近時回傳 None
+    fn write(&mut self, incident: &Incident) -> Result<Option<PathBuf>, String> {
+        let Some(dir) = &self.dir else { return Ok(None) };
+        let key = (incident.path.to_string(), incident.kind);
+        if self.last.get(&key).is_some_and(|t| t.elapsed() < MIN_INTERVAL) { return Ok(None); }
+        self.last.insert(key, Instant::now());
+
+        let now = chrono::Local::now();
+        let content = IncidentFile {
+            timestamp_ms: now.timestamp_millis(),
+            app_version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            incident,
+        };
+        std::fs::create_dir_all(dir).map_err(|e| format!("建立目錄 {} 失敗: {}", dir.display(), e))?;
+        let kind = serde_json::to_value(incident.kind).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+        let file = dir.join(format!("incident-{}-{}.json", now.format("%Y%m%d-%H%M%S-%3f"), kind));
+        let text = serde_json::to_string_pretty(&content).map_err(|e| e.to_string())?;
+        std::fs::write(&file, text).map_err(|e| format!("寫入 {} 失敗: {}", file.display(), e))?;
+        Ok(Some(file))
+    }
+}
+
+pub struct Incidents(pub Mutex<IncidentRecorder>);
+
+pub fn capture(app: &AppHandle, incident: Incident) {
+    let written = app.state::<Incidents>().0.lock().unwrap().write(&incident);
+    match written {
+        Ok(Some(file)) => {
+            let file = file.to_string_lossy().to_string();
+            logging::log(app, Severity::Error, Category::Device, Some(incident.path), format!("已記錄異常快照: {}", file));
+            let _ = app.emit("incident-captured", IncidentEvent {
+                path: incident.path.to_string(),
+                kind: incident.kind,
+                error: incident.error.to_string(),
+                file,
+            });
+        }
+        Ok(None) => {}
+        Err(e) => logging::log(app, Severity::Warning, Category::Device, Some(incident.path), format!("無法記錄異常快照: {}", e)),
+    }
+}
@@ -0,0 +1,294 @@
+// --- Schema 推測 ---
+// 由擷取到的 input report 推測欄位邊界、型別與序號欄位，產生 schema 草稿供使用者修改。
+// 對每個 byte 位置觀察整段樣本的變化：
+//   不變的 byte 視為保留 / 常數，不產生欄位
+//   每筆遞增 1 的是序號 (counter)
+//   相鄰兩個 byte 在低位 byte 溢位時高位 byte 跟著變動 (或合併後才是平滑的訊號)，合併成 16 位元數值
+//   只有少數位元在切換、且每次只變一個位元的是旗標 (按鍵)
+// 樣本標記了動作 (例: "左鍵"、"靜止") 時，用來替旗標命名或為數值欄位加上 labels。
+
+use crate::format::ValueFormat;
+use crate::regmap::Encoding;
+use crate::schema::{ReportSchema, SchemaField, SchemaReport};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+// 判定為序號 / 旗標 / 進位所需的比例
+const COUNTER_RATIO: f64 = 0.9;
+const FLAG_RATIO: f64 = 0.8;
+const CARRY_RATIO: f64 = 0.7;
+// 平均變化量 / 範圍：低於 SMOOTH 視為平滑的訊號，高於 NOISY 視為雜亂
+const SMOOTH: f64 = 0.1;
+const NOISY: f64 = 0.2;
+const MAX_FLAG_BITS: u32 = 4;
+// 自動判斷 Report ID 時，第 0 個 byte 最多幾種值
+const MAX_REPORT_IDS: usize = 8;
+
+#[derive(Deserialize, Clone)]
+pub struct InferSample {
+    pub data: Vec<u8>,
+    // 擷取這筆資料時使用者正在做的動作
+    #[serde(default)]
+    pub action: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct InferenceNote {
+    pub report_id: Option<u8>,
+    pub field: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SchemaDraft {
+    pub schema: ReportSchema,
+    pub notes: Vec<InferenceNote>,
+}
+
+// 一筆樣本的資料與動作標記
+type Sample<'a> = (&'a [u8], Option<&'a str>);
+
+fn field(name: String, offset: usize, encoding: Encoding) -> SchemaField {
+    SchemaField {
+        name,
+        offset,
+        encoding,
+        bit_offset: None,
+        bit_size: None,
+        scale: 1.0,
+        bias: 0.0,
+        unit: None,
+        format: ValueFormat::default(),
+        labels: BTreeMap::new(),
+        counter: false,
+        reserved: false,
+    }
+}
+
+// 相鄰樣本中符合條件的比例
+fn ratio(series: &[i64], pred: impl Fn(i64, i64) -> bool) -> f64 {
+    if series.len() < 2 { return 0.0; }
+    let hits = series.windows(2).filter(|w| pred(w[0], w[1])).count();
+    hits as f64 / (series.len() - 1) as f64
+}
+
+fn is_counter(series: &[i64], modulus: i64) -> bool {
+    series.len() >= 3 && ratio(series, |a, b| (b - a).rem_euclid(modulus) == 1) >= COUNTER_RATIO
+}
+
+fn range(series: &[i64]) -> i64 {
+    series.iter().max().unwrap_or(&0) - series.iter().min().unwrap_or(&0)
+}
+
+fn to_signed(v: i64, bits: u32) -> i64 {
+    let half = 1i64 << (bits - 1);
+    if v >= half { v - (half << 1) } else { v }
+}
+
+// 以有號數解讀時範圍明顯較小 (數值在 0 附近來回) 就視為有號
+fn prefers_signed(series: &[i64], bits: u32) -> bool {
+    let signed: Vec<i64> = series.iter().map(|v| to_signed(*v, bits)).collect();
+    range(&signed) < range(series)
+}
+
+fn roughness(series: &[i64]) -> f64 {
+    let span = range(series);
+    if span == 0 || series.len() < 2 { return 0.0; }
+    let total: i64 = series.windows(2).map(|w| (w[1] - w[0]).abs()).sum();
+    total as f64 / (series.len() - 1) as f64 / span as f64
+}
+
+// 高位 byte 的變動大多發生在低位 byte 溢位時
+fn carries(lo: &[i64], hi: &[i64]) -> bool {
+    let mut changes = 0;
+    let mut wraps = 0;
+    for i in 1..lo.len() {
+        if hi[i] != hi[i - 1] {
+            changes += 1;
+            if (lo[i] - lo[i - 1]).abs() > 0x80 { wraps += 1; }
+        }
+    }
+    changes > 0 && wraps as f64 >= changes as f64 * CARRY_RATIO
+}
+
+// 兩個 byte 屬於同一個 16 位元數值：低位溢位時才進位，或單看低位很雜亂、合併後卻很平滑 (變化快的訊號)
+fn linked(lo: &[i64], hi: &[i64], combined: &[i64]) -> bool {
+    carries(lo, hi) || (roughness(combined) < SMOOTH && roughness(lo) > NOISY)
+}
+
+fn plausible_float(samples: &[&[u8]], offset: usize, encoding: Encoding) -> bool {
+    samples.iter().all(|s| {
+        encoding.decode(&s[offset..]).is_some_and(|v| v.is_finite() && (v == 0.0 || (1e-6..1e7).contains(&v.abs())))
+    })
+}
+
+// 推測一個 report 的欄位；samples 都至少有 len bytes
+fn infer_report(report_id: Option<u8>, samples: &[Sample], notes: &mut Vec<InferenceNote>) -> SchemaReport {
+    let len = samples.iter().map(|(d, _)| d.len()).min().unwrap_or(0);
+    let column = |offset: usize| -> Vec<i64> { samples.iter().map(|(d, _)| d[offset] as i64).collect() };
+    let varying = |offset: usize| offset < len && column(offset).windows(2).any(|w| w[0] != w[1]);
+    let combine = |lo: usize, hi: usize| -> Vec<i64> {
+        samples.iter().map(|(d, _)| d[lo] as i64 | (d[hi] as i64) << 8).collect()
+    };
+    let data: Vec<&[u8]> = samples.iter().map(|(d, _)| *d).collect();
+
+    let mut fields = Vec::new();
+    let mut note = |field: &str, reason: String| {
+        notes.push(InferenceNote { report_id, field: field.to_string(), reason });
+    };
+    let mut offset = if report_id.is_some() { 1 } else { 0 };
+    while offset < len {
+        if !varying(offset) {
+            offset += 1;
+            continue;
+        }
+        let next = varying(offset + 1);
+
+        if offset + 4 <= len && (0..4).filter(|i| varying(offset + i)).count() >= 3 {
+            if let Some(enc) = [Encoding::F32le, Encoding::F32be].into_iter().find(|e| plausible_float(&data, offset, *e)) {
+                let name = format!("float{}", offset);
+                note(&name, "4 bytes 皆可解讀為合理範圍內的浮點數".into());
+                fields.push(field(name, offset, enc));
+                offset += 4;
+                continue;
+            }
+        }
+
+        if next {
+            let le = combine(offset, offset + 1);
+            let be = combine(offset + 1, offset);
+            let lo = column(offset);
+            let hi = column(offset + 1);
+            let counter = if is_counter(&le, 0x10000) && carries(&lo, &hi) {
+                Some(Encoding::U16le)
+            } else if is_counter(&be, 0x10000) && carries(&hi, &lo) {
+                Some(Encoding::U16be)
+            } else {
+                None
+            };
+            if let Some(enc) = counter {
+                let name = format!("counter{}", offset);
+                note(&name, "每筆 report 遞增 1 的 16 位元序號".into());
+                fields.push(SchemaField { counter: true, ..field(name, offset, enc) });
+                offset += 2;
+                continue;
+            }
+            let pair = if linked(&lo, &hi, &le) {
+                Some((le, Encoding::U16le, Encoding::I16le))
+            } else if linked(&hi, &lo, &be) {
+                Some((be, Encoding::U16be, Encoding::I16be))
+            } else {
+                None
+            };
+            if let Some((series, unsigned, signed)) = pair {
+                let is_signed = prefers_signed(&series, 16);
+                let name = format!("value{}", offset);
+                note(&name, format!("相鄰兩個 byte 的變化相連，合併為{}號 16 位元數值", if is_signed { "有" } else { "無" }));
+                fields.push(field(name, offset, if is_signed { signed } else { unsigned }));
+                offset += 2;
+                continue;
+            }
+        }
+
+        let series = column(offset);
+        if is_counter(&series, 0x100) {
+            let name = format!("counter{}", offset);
+            note(&name, "每筆 report 遞增 1 的 8 位元序號".into());
+            fields.push(SchemaField { counter: true, ..field(name, offset, Encoding::U8) });
+            offset += 1;
+            continue;
+        }
+
+        let mask = series.iter().fold(0, |m, v| m | (v ^ series[0])) as u8;
+        let changes: Vec<i64> = series.windows(2).filter(|w| w[0] != w[1]).map(|w| w[0] ^ w[1]).collect();
+        let single_flips = changes.iter().filter(|c| c.count_ones() == 1).count() as f64 / changes.len() as f64;
+        if mask.count_ones() <= MAX_FLAG_BITS && single_flips >= FLAG_RATIO {
+            for bit in (0..8).filter(|b| mask & (1 << b) != 0) {
+                let base = flag_name(samples, offset, bit).unwrap_or(format!("flag{}_{}", offset, bit));
+                // 多個位元以同一個動作命名時加上編號
+                let mut name = base.clone();
+                let mut n = 1;
+                while fields.iter().any(|f: &SchemaField| f.name == name) {
+                    n += 1;
+                    name = format!("{}_{}", base, n);
+                }
+                note(&name, format!("byte {} 的位元 {} 獨立切換", offset, bit));
+                fields.push(SchemaField { bit_offset: Some(bit), bit_size: Some(1), ..field(name, offset, Encoding::U8) });
+            }
+            offset += 1;
+            continue;
+        }
+
+        let is_signed = prefers_signed(&series, 8);
+        let name = format!("value{}", offset);
+        let labels = action_labels(samples, offset, is_signed);
+        note(&name, if labels.is_empty() { "數值".into() } else { "各動作的值固定且互不相同，加上 labels".into() });
+        fields.push(SchemaField { labels, ..field(name, offset, if is_signed { Encoding::I8 } else { Encoding::U8 }) });
+        offset += 1;
+    }
+    SchemaReport { report_id, fields }
+}
+
+// 只在某個動作時設定的位元以該動作命名
+fn flag_name(samples: &[Sample], offset: usize, bit: u32) -> Option<String> {
+    let mut set = BTreeSet::new();
+    let mut clear = BTreeSet::new();
+    for (data, action) in samples {
+        let Some(action) = action else { continue };
+        if data[offset] >> bit & 1 == 1 { set.insert(*action); } else { clear.insert(*action); }
+    }
+    match set.into_iter().collect::<Vec<_>>()[..] {
+        [action] if !clear.contains(action) && !clear.is_empty() => Some(action.to_string()),
+        _ => None,
+    }
+}
+
+// 每個動作對應唯一的值時產生 labels
+fn action_labels(samples: &[Sample], offset: usize, signed: bool) -> BTreeMap<i64, String> {
+    let mut values: BTreeMap<&str, BTreeSet<i64>> = BTreeMap::new();
+    for (data, action) in samples {
+        let Some(action) = action else { continue };
+        let v = data[offset] as i64;
+        values.entry(*action).or_default().insert(if signed { to_signed(v, 8) } else { v });
+    }
+    let mut labels = BTreeMap::new();
+    if values.len() < 2 { return labels; }
+    for (action, set) in values {
+        let [value] = set.into_iter().collect::<Vec<_>>()[..] else { return BTreeMap::new() };
+        if labels.insert(value, action.to_string()).is_some() { return BTreeMap::new(); }
+    }
+    labels
+}
+
+// report_ids 未指定時：第 0 個 byte 全部相同，或不同值的資料長度各自固定且互不相同，就視為 Report ID
+fn uses_report_ids(samples: &[InferSample]) -> bool {
+    let mut lengths: BTreeMap<u8, BTreeSet<usize>> = BTreeMap::new();
+    for s in samples {
+        if let Some(first) = s.data.first() { lengths.entry(*first).or_default().insert(s.data.len()); }
+    }
+    if lengths.len() == 1 { return true; }
+    let distinct: BTreeSet<usize> = lengths.values().flatten().copied().collect();
+    lengths.len() <= MAX_REPORT_IDS && lengths.values().all(|l| l.len() == 1) && distinct.len() == lengths.len()
+}
+
+pub fn infer(name: &str, samples: &[InferSample], report_ids: Option<bool>) -> Result<SchemaDraft, String> {
+    let samples: Vec<InferSample> = samples.iter().filter(|s| !s.data.is_empty()).cloned().collect();
+    if samples.len() < 2 { return Err("至少需要 2 筆樣本".into()); }
+    let report_ids = report_ids.unwrap_or_else(|| uses_report_ids(&samples));
+
+    let mut groups: BTreeMap<Option<u8>, Vec<Sample>> = BTreeMap::new();
+    for s in &samples {
+        let id = if report_ids { Some(s.data[0]) } else { None };
+        groups.entry(id).or_default().push((&s.data, s.action.as_deref()));
+    }
+    let mut notes = Vec::new();
+    let reports = groups.into_iter()
+        .map(|(id, group)| {
+            if group.len() < 2 {
+                notes.push(InferenceNote { report_id: id, field: String::new(), reason: "只有 1 筆樣本，無法判斷欄位".into() });
+            }
+            infer_report(id, &group, &mut notes)
+        })
+        .collect();
+    Ok(SchemaDraft { schema: ReportSchema { name: name.to_string(), reports }, notes })
+}
@@ -0,0 +1,99 @@
+// --- 鍵盤 report 解碼 (keycode 轉按鍵名稱) ---
+// 有 descriptor 時依其中的 Keyboard page 欄位解析 (支援 NKRO 位元圖)，
+// 否則以 boot protocol 格式 ([modifier, reserved, key1..key6]) 解析。
+// 新按下的按鍵另依設備的鍵盤配置 (keymap.rs) 還原成輸入的文字，條碼槍等 keyboard-wedge 設備可直接取得內容。
+
+use crate::descriptor::{ReportDescriptor, ReportKind};
+use crate::keymap::Keymap;
+use crate::usages::{self, PAGE_KEYBOARD};
+use serde::Serialize;
+
+const MODIFIER_FIRST: u16 = 0xE0;
+const MODIFIER_LAST: u16 = 0xE7;
+const LEFT_SHIFT: u16 = 0xE1;
+const RIGHT_SHIFT: u16 = 0xE5;
+const RIGHT_ALT: u16 = 0xE6;
+const BOOT_REPORT_LEN: usize = 8;
+
+#[derive(Serialize, Clone, PartialEq)]
+pub struct KeyboardState {
+    pub modifiers: Vec<String>,
+    // 目前按住的一般按鍵
+    pub keys: Vec<String>,
+    // 與上一筆相比新按下 / 放開的按鍵 (含 modifier)
+    pub pressed: Vec<String>,
+    pub released: Vec<String>,
+    // 新按下的按鍵依鍵盤配置對應的字元 (沒有對應字元的按鍵略過)
+    pub text: String,
+}
+
+pub struct KeyboardDecoder {
+    desc: Option<ReportDescriptor>,
+    held: Vec<u16>,
+}
+
+impl KeyboardDecoder {
+    // descriptor 中沒有 Keyboard page 的 input 欄位時回傳 None
+    pub fn new(desc: Option<&ReportDescriptor>) -> Option<Self> {
+        let desc = match desc {
+            Some(d) if d.fields.iter().any(|f| f.kind == ReportKind::Input && f.usage_page == PAGE_KEYBOARD) => Some(d.clone()),
+            Some(_) => return None,
+            None => None,
+        };
+        Some(Self { desc, held: Vec::new() })
+    }
+
+    // 取出 report 中所有被按下的 usage
+    fn active_usages(&self, report: &[u8]) -> Option<Vec<u16>> {
+        let Some(desc) = &self.desc else {
+            if report.len() != BOOT_REPORT_LEN { return None; }
+            let modifiers = (0..8).filter(|b| report[0] & (1 << b) != 0).map(|b| MODIFIER_FIRST + b);
+            let keys = report[2..].iter().filter(|k| **k != 0).map(|k| *k as u16);
+            return Some(modifiers.chain(keys).collect());
+        };
+
+        let report_id = if desc.uses_report_ids { *report.first()? } else { 0 };
+        let body = desc.report_body(report, report_id)?;
+        let fields: Vec<_> = desc.fields.iter()
+            .filter(|f| f.kind == ReportKind::Input && f.report_id == report_id)
+            .filter(|f| f.usage_page == PAGE_KEYBOARD && !f.is_constant())
+            .collect();
+        if fields.is_empty() { return None; }
+
+        let mut active = Vec::new();
+        for f in fields {
+            for i in 0..f.count {
+                let Some(value) = f.element_value(body, i) else { continue };
+                if f.is_variable() {
+                    // 位元圖：每個元素對應一個 usage
+                    if value != 0 { active.extend(f.usages.get(i)); }
+                } else if value >= f.logical_min as i64 && value <= f.logical_max as i64 {
+                    active.extend(f.usages.get((value - f.logical_min as i64) as usize).filter(|u| **u != 0));
+                }
+            }
+        }
+        Some(active)
+    }
+
+    pub fn feed(&mut self, report: &[u8], keymap: &Keymap) -> Option<KeyboardState> {
+        let active = self.active_usages(report)?;
+        let name = |u: &u16| usages::usage_name(PAGE_KEYBOARD, *u);
+        let is_modifier = |u: &&u16| (MODIFIER_FIRST..=MODIFIER_LAST).contains(*u);
+        let shift = active.contains(&LEFT_SHIFT) || active.contains(&RIGHT_SHIFT);
+        let altgr = active.contains(&RIGHT_ALT);
+
+        let state = KeyboardState {
+            modifiers: active.iter().filter(is_modifier).map(name).collect(),
+            keys: active.iter().filter(|u| !is_modifier(u)).map(name).collect(),
+            pressed: active.iter().filter(|u| !self.held.contains(u)).map(name).collect(),
+            released: self.held.iter().filter(|u| !active.contains(u)).map(name).collect(),
+            text: active.iter()
+                .filter(|u| !is_modifier(u) && !self.held.contains(u))
+                .filter_map(|u| u8::try_from(*u).ok())
+                .filter_map(|u| keymap.usage_to_char(u, shift, altgr))
+                .collect(),
+        };
+        self.held = active;
+        Some(state)
+    }
+}
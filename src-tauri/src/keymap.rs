@@ -0,0 +1,179 @@
+// --- HID 鍵盤 usage (page 0x07) 轉字元 ---
+// 用於 keyboard-wedge 類設備：磁條卡 (msr.rs) 與鍵盤 report 的文字還原 (keyboard.rs，條碼槍等)。
+// 各語系配置以 JSON 描述，內建 layouts/*.json，使用者可另外放在設定目錄的 layouts/ 下或以 load_layout 載入。
+//
+// {
+//   "name": "de",
+//   "description": "Deutsch (QWERTZ)",
+//   "extends": "us",
+//   "keys": { "1C": ["z", "Z"], "1F": ["2", "\"", "²"] }
+// }
+//
+// keys 以 usage (hex) 為索引，依序為 [一般, Shift, AltGr]
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+const MOD_LEFT_SHIFT: u8 = 0x02;
+const MOD_RIGHT_SHIFT: u8 = 0x20;
+const MOD_RIGHT_ALT: u8 = 0x40;
+
+pub const DEFAULT_LAYOUT: &str = "us";
+
+const BUILTIN_LAYOUTS: [&str; 4] = [
+    include_str!("../layouts/us.json"),
+    include_str!("../layouts/uk.json"),
+    include_str!("../layouts/de.json"),
+    include_str!("../layouts/fr.json"),
+];
+
+#[derive(Deserialize)]
+struct LayoutFile {
+    name: String,
+    #[serde(default)]
+    description: String,
+    extends: Option<String>,
+    keys: HashMap<String, Vec<String>>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct LayoutInfo {
+    pub name: String,
+    pub description: String,
+    pub builtin: bool,
+}
+
+pub struct Keymap {
+    pub info: LayoutInfo,
+    // usage -> [一般, Shift, AltGr]
+    keys: HashMap<u8, [Option<char>; 3]>,
+}
+
+impl Keymap {
+    fn parse(text: &str, builtin: bool, base: impl Fn(&str) -> Option<Arc<Keymap>>) -> Result<Self, String> {
+        let file: LayoutFile = serde_json::from_str(text).map_err(|e| format!("鍵盤配置格式錯誤: {}", e))?;
+        let mut keys = match &file.extends {
+            Some(parent) => base(parent).ok_or(format!("{}: 找不到基底配置 {}", file.name, parent))?.keys.clone(),
+            None => HashMap::new(),
+        };
+        for (usage, chars) in &file.keys {
+            let usage = u8::from_str_radix(usage.trim_start_matches("0x"), 16)
+                .map_err(|_| format!("{}: 無效的 usage {}", file.name, usage))?;
+            if chars.len() > 3 {
+                return Err(format!("{}: usage {:#04x} 最多只能有 3 個字元", file.name, usage));
+            }
+            let mut entry = [None; 3];
+            for (slot, s) in entry.iter_mut().zip(chars) {
+                let mut it = s.chars();
+                *slot = match (it.next(), it.next()) {
+                    (c, None) => c,
+                    _ => return Err(format!("{}: usage {:#04x} 的 \"{}\" 不是單一字元", file.name, usage, s)),
+                };
+            }
+            keys.insert(usage, entry);
+        }
+        Ok(Self { info: LayoutInfo { name: file.name, description: file.description, builtin }, keys })
+    }
+
+    pub fn usage_to_char(&self, usage: u8, shift: bool, altgr: bool) -> Option<char> {
+        let entry = self.keys.get(&usage)?;
+        match (altgr, shift) {
+            (true, _) => entry[2],
+            // 沒有定義 Shift 字元的鍵 (Enter、Tab 等) 沿用一般字元
+            (false, true) => entry[1].or(entry[0]),
+            (false, false) => entry[0],
+        }
+    }
+}
+
+// 鍵盤配置 (usage 轉字元)，各設備開啟時取得目前的配置
+pub struct KeyLayouts(pub Mutex<LayoutRegistry>);
+
+// 已載入的配置，以名稱索引，並記錄各設備選用的配置
+pub struct LayoutRegistry {
+    layouts: HashMap<String, Arc<Keymap>>,
+    per_device: HashMap<String, String>,
+}
+
+impl LayoutRegistry {
+    pub fn builtin() -> Self {
+        let mut reg = Self { layouts: HashMap::new(), per_device: HashMap::new() };
+        for text in BUILTIN_LAYOUTS {
+            reg.add(text, true).expect("內建鍵盤配置格式錯誤");
+        }
+        reg
+    }
+
+    fn add(&mut self, text: &str, builtin: bool) -> Result<Arc<Keymap>, String> {
+        let map = Arc::new(Keymap::parse(text, builtin, |name| self.layouts.get(name).cloned())?);
+        self.layouts.insert(map.info.name.clone(), map.clone());
+        Ok(map)
+    }
+
+    pub fn load_file(&mut self, file: &Path) -> Result<LayoutInfo, String> {
+        let text = std::fs::read_to_string(file).map_err(|e| format!("讀取 {} 失敗: {}", file.display(), e))?;
+        Ok(self.add(&text, false)?.info.clone())
+    }
+
+    // 載入目錄下所有 .json，回傳失敗的檔案與原因 (其餘照常載入)
+    pub fn load_dir(&mut self, dir: &Path) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+        let mut files: Vec<_> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        // 依檔名排序，讓 extends 的載入順序可預期
+        files.sort();
+        files.iter().filter_map(|f| self.load_file(f).err()).collect()
+    }
+
+    pub fn list(&self) -> Vec<LayoutInfo> {
+        let mut list: Vec<LayoutInfo> = self.layouts.values().map(|m| m.info.clone()).collect();
+        list.sort_by(|a, b| (!a.builtin, &a.name).cmp(&(!b.builtin, &b.name)));
+        list
+    }
+
+    pub fn set_device_layout(&mut self, path: &str, layout: &str) -> Result<Arc<Keymap>, String> {
+        let map = self.layouts.get(layout).cloned().ok_or(format!("未知的鍵盤配置: {}", layout))?;
+        self.per_device.insert(path.to_string(), layout.to_string());
+        Ok(map)
+    }
+
+    // 沒有指定時使用 US 配置
+    pub fn for_device(&self, path: &str) -> Arc<Keymap> {
+        let name = self.per_device.get(path).map(String::as_str).unwrap_or(DEFAULT_LAYOUT);
+        self.layouts.get(name).or_else(|| self.layouts.get(DEFAULT_LAYOUT)).cloned().unwrap()
+    }
+}
+
+// 把 boot keyboard report ([modifier, reserved, key1..key6]) 轉成新按下的字元
+pub struct WedgeDecoder {
+    keymap: Arc<Keymap>,
+    pressed: Vec<u8>,
+}
+
+impl WedgeDecoder {
+    pub fn new(keymap: Arc<Keymap>) -> Self {
+        Self { keymap, pressed: Vec::new() }
+    }
+
+    pub fn set_keymap(&mut self, keymap: Arc<Keymap>) {
+        self.keymap = keymap;
+    }
+
+    pub fn feed(&mut self, report: &[u8]) -> Vec<char> {
+        if report.len() < 3 { return Vec::new(); }
+        let shift = report[0] & (MOD_LEFT_SHIFT | MOD_RIGHT_SHIFT) != 0;
+        let altgr = report[0] & MOD_RIGHT_ALT != 0;
+        let keys: Vec<u8> = report[2..].iter().copied().filter(|k| *k > 0x03).collect();
+
+        let out = keys.iter()
+            .filter(|k| !self.pressed.contains(k))
+            .filter_map(|k| self.keymap.usage_to_char(*k, shift, altgr))
+            .collect();
+        self.pressed = keys;
+        out
+    }
+}
@@ -0,0 +1,76 @@
+// --- 來回延遲量測 ---
+// 寫入到收到回覆的時間在設備的 I/O 執行緒中以單調時鐘 (Instant) 量測，整理成百分位數與直方圖，
+// 讓韌體工程師不需要外部工具就能比較不同版本。逾時的樣本不計入統計，只記錄次數。
+
+use serde::Serialize;
+
+pub const DEFAULT_BUCKETS: usize = 20;
+// 單次量測的樣本數上限
+pub const MAX_SAMPLES: u32 = 1_000_000;
+
+#[derive(Serialize, Clone)]
+pub struct HistogramBucket {
+    // [from_us, to_us)，最後一格包含 max
+    pub from_us: u64,
+    pub to_us: u64,
+    pub count: u32,
+}
+
+#[derive(Serialize, Clone, Default)]
+pub struct LatencyReport {
+    pub samples: u32,
+    pub timeouts: u32,
+    pub errors: u32,
+    pub min_us: Option<u64>,
+    pub max_us: Option<u64>,
+    pub mean_us: Option<f64>,
+    pub stddev_us: Option<f64>,
+    pub p50_us: Option<u64>,
+    pub p90_us: Option<u64>,
+    pub p95_us: Option<u64>,
+    pub p99_us: Option<u64>,
+    pub histogram: Vec<HistogramBucket>,
+    // 提早取消 (只統計已完成的樣本)
+    pub cancelled: bool,
+}
+
+// nearest-rank 百分位數；sorted 不可為空
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn histogram(sorted: &[u64], buckets: usize) -> Vec<HistogramBucket> {
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    // 寬度至少 1 µs，所有樣本相同時只有一格
+    let width = ((max - min) / buckets as u64 + 1).max(1);
+    let count = ((max - min) / width + 1) as usize;
+    let mut out: Vec<HistogramBucket> = (0..count)
+        .map(|i| HistogramBucket { from_us: min + i as u64 * width, to_us: min + (i as u64 + 1) * width, count: 0 })
+        .collect();
+    for &v in sorted {
+        out[((v - min) / width) as usize].count += 1;
+    }
+    out
+}
+
+impl LatencyReport {
+    pub fn new(mut latencies: Vec<u64>, timeouts: u32, errors: u32, buckets: usize) -> Self {
+        let mut report = Self { samples: latencies.len() as u32, timeouts, errors, ..Self::default() };
+        if latencies.is_empty() { return report; }
+        latencies.sort_unstable();
+        let n = latencies.len() as f64;
+        let mean = latencies.iter().map(|&v| v as f64).sum::<f64>() / n;
+        let variance = latencies.iter().map(|&v| (v as f64 - mean).powi(2)).sum::<f64>() / n;
+        report.min_us = latencies.first().copied();
+        report.max_us = latencies.last().copied();
+        report.mean_us = Some(mean);
+        report.stddev_us = Some(variance.sqrt());
+        report.p50_us = Some(percentile(&latencies, 50.0));
+        report.p90_us = Some(percentile(&latencies, 90.0));
+        report.p95_us = Some(percentile(&latencies, 95.0));
+        report.p99_us = Some(percentile(&latencies, 99.0));
+        report.histogram = histogram(&latencies, buckets.max(1));
+        report
+    }
+}
@@ -0,0 +1,124 @@
+// --- 統一格式的記錄 ---
+// 後端產生的記錄一律帶有嚴重程度、類別與設備路徑，讓前端與匯出能以相同的規則過濾與上色。
+// 輸出端各自設定最低嚴重程度，低於門檻的記錄在後端就不送出：
+//   console  app-log 事件 (預設 info)
+//   file     資料目錄的 app.log，JSON lines (預設關閉；服務模式下未開啟時以 info 開啟)
+// 門檻儲存在設定目錄的 log_levels.json，啟動時恢復。
+//
+// { "timestamp_ms": 0, "severity": "warning", "category": "device", "device": "...", "message": "..." }
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+// 順序即嚴重程度
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Device,
+    Command,
+    Script,
+    Sequence,
+    Health,
+    Service,
+    Schedule,
+    Capture,
+}
+
+#[derive(Serialize, Clone)]
+pub struct LogEvent {
+    pub timestamp_ms: i64,
+    pub severity: Severity,
+    pub category: Category,
+    // 與設備無關的記錄為 None
+    pub device: Option<String>,
+    pub message: String,
+}
+
+// 各輸出端的最低嚴重程度，None 代表關閉
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct LogLevels {
+    pub console: Option<Severity>,
+    pub file: Option<Severity>,
+}
+
+impl Default for LogLevels {
+    fn default() -> Self {
+        Self { console: Some(Severity::Info), file: None }
+    }
+}
+
+impl LogLevels {
+    pub fn load(file: &Path) -> Result<Self, String> {
+        if !file.exists() { return Ok(Self::default()); }
+        let text = std::fs::read_to_string(file).map_err(|e| format!("讀取 {} 失敗: {}", file.display(), e))?;
+        serde_json::from_str(&text).map_err(|e| format!("記錄層級設定格式錯誤: {}", e))
+    }
+
+    pub fn save(&self, file: &Path) -> Result<(), String> {
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("建立目錄失敗: {}", e))?;
+        }
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, text).map_err(|e| format!("寫入 {} 失敗: {}", file.display(), e))
+    }
+}
+
+pub struct Logger {
+    pub levels: LogLevels,
+    // 記錄檔位置 (資料目錄取不到時為 None，file 輸出端不動作)
+    file: Option<PathBuf>,
+}
+
+impl Default for Logger {
+    fn default() -> Self { Self::new() }
+}
+
+impl Logger {
+    pub fn new() -> Self {
+        Self { levels: LogLevels::default(), file: None }
+    }
+
+    pub fn set_file(&mut self, file: &Path) {
+        self.file = Some(file.to_path_buf());
+    }
+
+    // 寫入檔案 (失敗時略過)，回傳是否需要送往 console
+    fn dispatch(&self, event: &LogEvent) -> bool {
+        let passes = |min: Option<Severity>| min.is_some_and(|min| event.severity >= min);
+        if let Some(file) = self.file.as_ref().filter(|_| passes(self.levels.file)) {
+            if let Some(dir) = file.parent() { let _ = std::fs::create_dir_all(dir); }
+            if let (Ok(mut f), Ok(line)) = (std::fs::OpenOptions::new().create(true).append(true).open(file), serde_json::to_string(event)) {
+                let _ = writeln!(f, "{}", line);
+            }
+        }
+        passes(self.levels.console)
+    }
+}
+
+pub struct Logging(pub Mutex<Logger>);
+
+pub fn log(app: &AppHandle, severity: Severity, category: Category, device: Option<&str>, message: impl Into<String>) {
+    let event = LogEvent {
+        timestamp_ms: chrono::Local::now().timestamp_millis(),
+        severity,
+        category,
+        device: device.map(str::to_string),
+        message: message.into(),
+    };
+    if app.state::<Logging>().0.lock().unwrap().dispatch(&event) {
+        let _ = app.emit("app-log", event);
+    }
+}
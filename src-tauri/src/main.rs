@@ -1,12 +1,140 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use hidapi::{HidApi, HidDevice};
+mod annotations;
+mod auth;
+mod capabilities;
+mod capture;
+mod capture_csv;
+mod capture_diff;
+mod capture_filter;
+mod capture_history;
+mod checksum;
+mod clock;
+mod codegen;
+mod dashboard;
+mod demo;
+mod descriptor;
+mod device;
+mod export;
+mod firmware;
+mod format;
+mod framing;
+mod gamepad;
+mod health;
+mod hexdump;
+mod hid_io;
+mod hooks;
+mod incident;
+mod inference;
+mod keyboard;
+mod keymap;
+mod latency;
+mod logging;
+mod mouse;
+mod msr;
+mod naming;
+mod onboard;
+mod operations;
+mod paging;
+mod pcapng;
+mod portable;
+mod power;
+mod protocols;
+mod regmap;
+mod replay;
+mod scale;
+mod scheduler;
+mod schema;
+mod script;
+mod sensor;
+mod sequence;
+mod service;
+mod sony;
+mod station;
+mod stats;
+mod stream;
+mod stress;
+mod summary;
+mod telemetry;
+mod telephony;
+mod template;
+mod test_report;
+mod traffic_log;
+mod transfer;
+mod usages;
+mod watch;
+mod webhook;
+
+use hidapi::HidApi;
+use hid_io::HidIo;
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU64, Ordering}};
 use std::thread;
-use std::time::Duration;
-use tauri::{AppHandle, Emitter, State, Manager};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Listener, State, Manager, RunEvent};
+use onboard::ProfileProgress;
+use paging::{Page, PageRequest};
+use operations::{OperationEvent, OperationInfo, OperationKind, OperationProgress, OperationState, Operations};
+use protocols::ccid::{self, ApduResponse, Voltage};
+use protocols::ctaphid::{self, CtapResponse};
+use protocols::qmk_via::{self, Keycode, ViaInfo};
+use protocols::hidpp::{self, BatteryInfo, DpiInfo, Hidpp, HidppInfo};
+use protocols::{DeviceIdentity, HeadsetStatus, HidTransport, LightingControl, LightingEffect, LightingZone, ProtocolInfo, Rgb, Transport};
+use stats::{ActivityState, DeviceStatsSnapshot};
+use stream::{StreamInfo, UdpStream};
+use stress::{StressOutcome, StressResult, StressStats};
+use test_report::{TestReportFormat, TestResults, TestSuite};
+use template::{CounterValue, Payload, TemplateState, TemplateValue};
+use descriptor::{DecodedReport, DescriptorFormat, ReportDescriptor, ReportKind, ReportSizes};
+use export::{ChannelData, ExportFormat};
+use firmware::{BootloaderInfo, FirmwareImage, FwProgress, ImageInfo};
+use device::{CommandDeadlines, CommandError, ConnectionState, DeviceFault, DeviceManager, DeviceMeta, ExchangeReply, FaultEvent, ListenOptions, ManagedDevice, OutputMethod, PollConfig, PowerEvent, Quiesce, ResponseMatch};
+use framing::FramingProfile;
+use telephony::{TelephonyLayout, TelephonyLeds};
+use summary::{SummaryEvent, Summarizer};
+use telemetry::{Telemetry, TelemetryReport, TelemetryStatus};
+use scale::ScaleReading;
+use keymap::{KeyLayouts, LayoutInfo, LayoutRegistry};
+use latency::LatencyReport;
+use logging::{Category, LogLevels, Logger, Logging, Severity};
+use transfer::{ChunkFormat, TransferProgress};
+use checksum::{AppendChecksum, Checksum};
+use msr::{MsrCapture, MsrMode};
+use naming::{NamingConfig, NamingContext};
+use hooks::PostCaptureHook;
+use health::{Favorite, Favorites, FavoritesConfig, HealthReport, HealthResult};
+use scheduler::{ScheduledTask, Scheduler, TaskAction, TaskRun};
+use station::{AcquireRequest, ReleaseRequest, StationLock, StationLockInfo};
+use portable::StorageInfo;
+use power::{PowerLayout, PowerStatus};
+use regmap::{RegisterMap, RegisterValue};
+use schema::{ReportSchema, SchemaDecoded, SchemaVersionRule, VersionRules, ViolationCount};
+use incident::{IncidentRecorder, Incidents};
+use inference::{InferSample, SchemaDraft};
+use sensor::{SensorLayout, SensorProperties};
+use script::{ScriptHost, ScriptResult};
+use sequence::{BytePattern, Sequence, SequenceProgress, SequenceResult};
+use auth::Scope;
+use service::{HandoverRequest, Service, ServiceConfig, ServiceDevice, ServiceStatus};
+use sony::{Connection, SonyModel, SonyOutput};
+use capabilities::CapabilityManifest;
+use clock::{CaptureClock, ClockMetadata, TimestampSource};
+use capture::{CaptureInfo, CaptureRecorder, Captures};
+use capture_filter::CaptureFilter;
+use annotations::{Annotation, AnnotationStore};
+use capture_history::{CaptureCount, CaptureHistory, CapturePage};
+use replay::{Divergence, DivergenceKind, ReplayOptions, ReplayResult};
+use hexdump::HexPayload;
+use pcapng::PcapExport;
+use capture_csv::{CsvExport, CsvOptions, DeviceDecoder};
+use capture_diff::{DiffOptions, DiffResult};
+use traffic_log::{TrafficLogConfig, TrafficLogFile};
+use codegen::CodeLanguage;
+use dashboard::{DashboardConfig, DashboardEntry, DashboardRow};
+use watch::{Watch, WatchInfo};
+use webhook::{DeviceIdentityReport, DeviceWebhook, WebhookConfig, WebhookStatus, WebhookTarget};
 
 // --- 資料結構 ---
 
@@ -19,14 +147,280 @@ struct HidDeviceNotify {
     interface_number: i32,
 }
 
-struct ManagedDevice {
-    device: Arc<Mutex<HidDevice>>,
-    is_paused: Arc<AtomicBool>,
-    should_stop: Arc<AtomicBool>,
+// 寫入 output report 的格式選項
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+struct WriteOptions {
+    // 指定 Report ID；未指定時沿用 data[0] == 0x00 的判斷
+    report_id: Option<u8>,
+    // report 長度 (不含 Report ID)，未指定時依 descriptor
+    report_length: Option<usize>,
+    // 是否把 report_id 加在 data 前面；false 代表 data[0] 已經是 Report ID
+    prepend_id: Option<bool>,
+    // interrupt (預設) 或 control transfer
+    method: OutputMethod,
+    // 在 data 後面附加 checksum (範圍以 data 的索引計算)
+    checksum: Option<AppendChecksum>,
+}
+
+// exchange 的選項
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ExchangeOptions {
+    #[serde(flatten)]
+    write: WriteOptions,
+    timeout_ms: Option<i32>,
+    response_match: Option<ResponseMatch>,
+    #[serde(flatten)]
+    quiesce: Quiesce,
+}
+
+// run_stress_test 的選項
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct StressOptions {
+    #[serde(flatten)]
+    exchange: ExchangeOptions,
+    // 回覆需符合的樣式 (比對未去除 framing 的回覆)
+    expect: Option<BytePattern>,
+    operation_id: Option<String>,
+    // 工作站鎖定的 token (見 station.rs)
+    station_token: Option<String>,
+}
+
+// benchmark_latency 的選項
+#[derive(Deserialize, Default)]
+struct BenchmarkOptions {
+    #[serde(flatten)]
+    exchange: ExchangeOptions,
+    // 先送出但不計入統計的次數 (讓設備與驅動進入穩定狀態)
+    #[serde(default)]
+    warmup: u32,
+    // 兩次量測之間的間隔
+    #[serde(default)]
+    interval_ms: u64,
+    // 直方圖的格數
+    #[serde(default)]
+    buckets: Option<usize>,
+    #[serde(default)]
+    operation_id: Option<String>,
+}
+
+// send_hid_broadcast 中單一設備的結果
+#[derive(Serialize, Clone)]
+struct BroadcastResult {
+    // 有等待回覆時為回覆內容 (逾時為空)，否則為空
+    response: Vec<u8>,
+    error: Option<String>,
+}
+
+// send_from_file 的選項
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SendFileOptions {
+    #[serde(flatten)]
+    write: WriteOptions,
+    // 每個 payload 都等待回覆 (否則只寫入)
+    wait_response: bool,
+    timeout_ms: Option<i32>,
+    // 兩個 payload 之間的間隔
+    interval_ms: u64,
+    // 第一次失敗 (寫入錯誤或等待回覆逾時) 就停止
+    stop_on_error: bool,
+    operation_id: Option<String>,
+}
+
+// send_from_file 中單一 payload 的結果
+#[derive(Serialize, Clone)]
+struct SendFileEntry {
+    // 在檔案中的行號
+    line: usize,
+    sent: Vec<u8>,
+    response: Vec<u8>,
+    error: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct SendFileResult {
+    total: usize,
+    sent: usize,
+    failed: usize,
+    entries: Vec<SendFileEntry>,
+    elapsed_ms: u64,
+    cancelled: bool,
+}
+
+// 已開啟 (監聽中) 的設備
+#[derive(Serialize, Clone)]
+struct ActiveDevice {
+    path: String,
+    identity: DeviceIdentity,
+    paused: bool,
+    state: ConnectionState,
+    activity: ActivityState,
+    // 錯誤狀態 (連續寫入失敗)，需 clear_error 或重新連接
+    error: Option<DeviceFault>,
+}
+
+#[derive(Serialize, Clone)]
+struct ActivityEvent {
+    path: String,
+    previous: ActivityState,
+    state: ActivityState,
+}
+
+// 活動狀態的檢查間隔
+const ACTIVITY_CHECK_MS: u64 = 250;
+
+// 已載入的 register map，以名稱索引
+struct RegisterMaps(Mutex<HashMap<String, RegisterMap>>);
+
+// 已載入的 report schema (以名稱為 key)
+struct Schemas(Mutex<HashMap<String, Arc<ReportSchema>>>);
+
+// 各型號 (VID, PID) 依韌體版本選用的 schema (存在 schema_versions.json)
+struct SchemaVersions(Mutex<VersionRules>);
+
+#[derive(Serialize, Clone)]
+struct SchemaSelectedEvent {
+    path: String,
+    firmware_version: Option<String>,
+    // 沒有符合的規則時為 None
+    schema: Option<String>,
+    error: Option<String>,
+}
+
+// 擷取 / 匯出檔案的命名設定
+struct CaptureNaming(Mutex<NamingConfig>);
+
+// 擷取 / 匯出完成後執行的 hook (未設定為 None)
+struct CaptureHook(Mutex<Option<PostCaptureHook>>);
+
+// 排程工作與執行紀錄
+struct TaskScheduler(Mutex<Scheduler>);
+
+#[derive(Serialize, Clone)]
+struct ScheduledTaskEvent {
+    task: String,
+    payload: serde_json::Value,
+}
+
+// 排程檢查間隔
+const SCHEDULER_TICK_MS: u64 = 1000;
+
+// 共用工作站的使用者鎖定
+struct Station(Mutex<StationLock>);
+
+// 設備插拔通報 (未設定為 None)
+struct Webhook(Mutex<Option<DeviceWebhook>>);
+const WEBHOOK_POLL_MS: u64 = 1000;
+
+// 匿名使用統計 (預設關閉)
+struct Usage(Mutex<Telemetry>);
+const TELEMETRY_CHECK_MS: u64 = 60 * 60 * 1000;
+
+// 無視窗監控服務 (服務模式以外只提供狀態查詢)
+struct WatchdogService(Mutex<Service>);
+// 服務記錄的設備事件
+const SERVICE_EVENTS: [&str; 3] = ["watch-triggered", "device-fault", "device-stall"];
+const SERVICE_MIN_SCAN_MS: u64 = 500;
+// 交接時等待 I/O 執行緒關閉設備的上限
+const HANDOVER_WAIT_MS: u64 = 3000;
+
+// GUI 向服務借用中的設備路徑
+struct BorrowedDevices(Mutex<HashSet<String>>);
+
+// 可攜模式的資料根目錄 (一般模式為 None，使用 OS 的 app 目錄)
+struct Storage(Option<PathBuf>);
+
+// Demo 模式：以模擬設備取代實體硬體
+struct DemoMode(AtomicBool);
+
+// 定期讀取電源狀態的設備: 路徑 -> (間隔, 下次讀取時間)
+struct PowerMonitor(Mutex<HashMap<String, (Duration, Instant)>>);
+const POWER_MONITOR_TICK_MS: u64 = 250;
+const POWER_MONITOR_MIN_MS: u64 = 1000;
+
+// hid-stats 事件的發送間隔 (ms)，0 代表關閉
+struct StatsConfig(AtomicU64);
+
+// device-summary (無障礙摘要) 的設定與上一次送出的句子
+struct Summaries(Mutex<Summarizer>);
+
+struct Dashboard(Mutex<DashboardConfig>);
+
+// 韌體更新選項
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+struct FirmwareOptions {
+    // binary 檔的起始位址
+    base_address: u32,
+    // 不做回讀驗證
+    skip_verify: bool,
+    // bootloader 協定的設定
+    bootloader: Option<serde_json::Value>,
+    // 工作站鎖定的 token (見 station.rs)
+    station_token: Option<String>,
+}
+
+// 執行中的長時間操作 (韌體更新、序列、壓力測試、延遲量測、腳本) 與各自的取消旗標
+struct RunningOperations(Mutex<Operations>);
+
+// 常用設備與最近一次的健康檢查結果
+struct FavoriteDevices(Mutex<Favorites>);
+
+// 序列與壓力測試的結果，供 export_test_report 匯出
+struct TestRecords(Mutex<TestResults>);
+
+// payload 樣板的使用者變數與各設備的計數器
+struct Templates(Mutex<TemplateState>);
+
+// 常駐流量記錄的設定 (記錄本身在 Captures 中)
+struct TrafficLog(Mutex<TrafficLogConfig>);
+
+// 擷取檔的註記與書籤
+struct Annotations(Mutex<AnnotationStore>);
+
+// 擷取檔分頁查詢用的索引
+struct History(Mutex<CaptureHistory>);
+
+#[derive(Serialize)]
+struct TrafficLogStatus {
+    #[serde(flatten)]
+    config: TrafficLogConfig,
+    active: bool,
+    // 實際使用的目錄與其中的記錄檔 (由新到舊)
+    directory: String,
+    files: Vec<TrafficLogFile>,
+}
+
+#[derive(Serialize, Clone)]
+struct SequenceEvent {
+    path: String,
+    #[serde(flatten)]
+    progress: SequenceProgress,
+}
+
+#[derive(Serialize, Clone)]
+struct FwEvent {
+    path: String,
+    #[serde(flatten)]
+    progress: FwProgress,
 }
 
-// 管理所有開啟中的設備
-struct DeviceManager(Mutex<HashMap<String, ManagedDevice>>);
+// send_hid_command 等待回覆的預設時間
+const DEFAULT_RESPONSE_TIMEOUT_MS: i32 = 1000;
+
+// 各設備的指令等待時間設定: 路徑 -> 設定
+struct Deadlines(Mutex<HashMap<String, CommandDeadlines>>);
+
+// 指令連續逾時進入 / 離開停滯狀態
+#[derive(Serialize, Clone)]
+struct StallEvent {
+    path: String,
+    stalled: bool,
+    consecutive_timeouts: u32,
+}
 
 // --- Helpers ---
 
@@ -34,12 +428,99 @@ fn get_api() -> Result<HidApi, String> {
     HidApi::new().map_err(|e| e.to_string())
 }
 
+// 依選項組出完整的 output report (Report ID + 補零後的資料)
+fn build_output_report(sizes: &ReportSizes, data: &[u8], opts: &WriteOptions) -> Result<Vec<u8>, String> {
+    let with_checksum = opts.checksum.as_ref().map(|c| c.apply(data)).transpose()?;
+    let data = with_checksum.as_deref().unwrap_or(data);
+    let (report_id, body) = match (opts.report_id, opts.prepend_id) {
+        (Some(id), Some(false)) => match data.split_first() {
+            Some((first, body)) if *first == id => (id, body),
+            Some((first, _)) => return Err(format!("data[0] = {:#04x} 與 report_id {:#04x} 不符", first, id)),
+            None => return Err("資料為空".into()),
+        },
+        (Some(id), _) => (id, data),
+        (None, Some(true)) => return Err("prepend_id 需要同時指定 report_id".into()),
+        (None, Some(false)) => match data.split_first() {
+            Some((id, body)) => (*id, body),
+            None => return Err("資料為空".into()),
+        },
+        // 設備使用 Report ID 時 data[0] 即為 ID；否則 data[0] == 0x00 代表已包含 Report ID
+        (None, None) => match data.first() {
+            None => return Err("資料為空".into()),
+            Some(&first) if sizes.uses_report_ids || first == 0x00 => (first, &data[1..]),
+            Some(_) => (0x00, data),
+        },
+    };
+
+    let report_len = match opts.report_length {
+        Some(len) if body.len() > len => {
+            return Err(format!("資料長度 {} 超過 report 長度 {}", body.len(), len));
+        }
+        Some(len) => len,
+        None => sizes.check_payload(ReportKind::Output, report_id, body.len())?,
+    };
+    let mut buf = vec![0u8; report_len + 1];
+    buf[0] = report_id;
+    buf[1..body.len() + 1].copy_from_slice(body);
+    Ok(buf)
+}
+
+// 透過 I/O 執行緒取得設備獨佔權後執行 f，結束後自動歸還
+fn with_exclusive_device<T>(
+    manager_state: &DeviceManager,
+    path: &str,
+    f: impl FnOnce(&dyn HidIo, &DeviceMeta) -> Result<T, String>,
+) -> Result<T, String> {
+    let m_dev = manager_state.get(path)?;
+    let lease = m_dev.lease()?;
+    let dev = lease.device()?;
+    f(dev.as_ref(), &m_dev.meta)
+}
+
+// 設備套用 framing profile 時先把 data 包成 frame，再組成 output report
+fn build_device_report(m_dev: &ManagedDevice, data: &[u8], opts: &WriteOptions) -> Result<Vec<u8>, String> {
+    let sizes = &m_dev.meta.report_sizes;
+    let framing = m_dev.framing.lock().unwrap().clone();
+    let Some(profile) = framing else { return build_output_report(sizes, data, opts) };
+    let frame = profile.frame(data)?;
+    // frame 的第一個位元組是 header，不能當成 Report ID
+    let report_id = opts.report_id.or(profile.report_id).or((!sizes.uses_report_ids).then_some(0))
+        .ok_or("設備使用 Report ID，請在 framing profile 或寫入選項指定 report_id")?;
+    let opts = WriteOptions { report_id: Some(report_id), prepend_id: None, ..opts.clone() };
+    build_output_report(sizes, &frame, &opts)
+}
+
+// 套用 framing profile 時只回傳驗證後的 payload
+fn unframe_response(m_dev: &ManagedDevice, resp: Vec<u8>) -> Result<Vec<u8>, String> {
+    let framing = m_dev.framing.lock().unwrap().clone();
+    let Some(profile) = framing else { return Ok(resp) };
+    let frame = profile.locate(&resp, m_dev.meta.report_sizes.uses_report_ids)
+        .ok_or("回覆的 Report ID 與 framing profile 不符")?;
+    profile.unframe(frame).map_err(|e| format!("回覆格式錯誤: {}", e))
+}
+
+// 展開 payload 樣板 (見 template.rs)，計數器依設備分開遞增
+fn expand_payload(app: &AppHandle, path: &str, payload: &Payload) -> Result<Vec<u8>, String> {
+    app.state::<Templates>().0.lock().unwrap().expand(path, payload)
+}
+
 // --- Commands ---
 
 #[tauri::command]
-fn scan_hid_devices() -> Result<Vec<HidDeviceNotify>, String> {
+fn scan_hid_devices(demo: State<'_, DemoMode>) -> Result<Vec<HidDeviceNotify>, String> {
+    let mut list: Vec<HidDeviceNotify> = Vec::new();
+    if demo.0.load(Ordering::SeqCst) {
+        list.extend(demo::devices().iter().map(|d| HidDeviceNotify {
+            path: d.path.to_string(),
+            vendor_id: format!("{:#06x}", d.identity.vendor_id),
+            product_id: format!("{:#06x}", d.identity.product_id),
+            usage_page: d.identity.usage_page,
+            interface_number: d.identity.interface_number,
+        }));
+    }
+
     let api = get_api()?;
-    Ok(api.device_list()
+    list.extend(api.device_list()
         .filter(|d| {
             // macOS 核心過濾：只顯示非系統佔用介面
             if cfg!(target_os = "macos") { d.usage_page() != 0x0001 } else { true }
@@ -50,138 +531,3045 @@ fn scan_hid_devices() -> Result<Vec<HidDeviceNotify>, String> {
             product_id: format!("{:#06x}", d.product_id()),
             usage_page: d.usage_page(),
             interface_number: d.interface_number(),
-        })
-        .collect())
+        }));
+    Ok(list)
 }
 
 #[tauri::command]
 async fn start_listening(
     app: AppHandle, 
     path: String, 
+    options: Option<ListenOptions>,
     manager_state: State<'_, DeviceManager>
 ) -> Result<(), String> {
+    match listen_device(&app, &path, options.clone(), &manager_state) {
+        // 可能被同一台電腦上的服務佔用：向服務借用後重試
+        Err(e) if !service::requested() => {
+            if borrow_device(&app, &path, None).is_err() { return Err(e); }
+            listen_device(&app, &path, options, &manager_state)
+        }
+        result => result,
+    }
+}
+
+fn listen_device(app: &AppHandle, path: &str, options: Option<ListenOptions>, manager_state: &DeviceManager) -> Result<(), String> {
     let mut manager = manager_state.0.lock().unwrap();
 
     // 如果已經在監聽，就不重複開啟
-    if manager.contains_key(&path) { return Ok(()); }
+    if let Some(m_dev) = manager.get(path) {
+        if let Some(options) = options { *m_dev.options.lock().unwrap() = options; }
+        return Ok(());
+    }
 
-    let api = get_api()?;
-    let device_info = api.device_list()
-        .find(|d| d.path().to_string_lossy() == path)
-        .ok_or("找不到設備")?;
-
-    let device = device_info.open_device(&api).map_err(|e| e.to_string())?;
-    
-    let shared_device = Arc::new(Mutex::new(device));
-    let is_paused = Arc::new(AtomicBool::new(false));
-    let should_stop = Arc::new(AtomicBool::new(false));
-
-    // 儲存狀態
-    manager.insert(path.clone(), ManagedDevice {
-        device: shared_device.clone(),
-        is_paused: is_paused.clone(),
-        should_stop: should_stop.clone(),
-    });
+    // 開啟設備並啟動專屬的 I/O 執行緒
+    let options = options.unwrap_or_default();
+    let m_dev = if demo::is_demo_path(path) {
+        demo::open(app, path, options)?
+    } else {
+        device::open(app, &get_api()?, path, options)?
+    };
+    manager.insert(path.to_string(), m_dev);
+    drop(manager);
+    count_feature(app, "listen");
 
-    // 啟動監聽執行緒
-    let app_inner = app.clone();
-    let path_inner = path.clone();
-    thread::spawn(move || {
-        loop {
-            if should_stop.load(Ordering::SeqCst) { break; }
+    auto_select_schema(app, path, manager_state);
+    Ok(())
+}
 
-            // 如果被暫停（正在發送指令），則稍候再讀取
-            if is_paused.load(Ordering::SeqCst) {
-                thread::sleep(Duration::from_millis(50));
-                continue;
-            }
+// 有設定版本規則的型號，開啟時查詢韌體版本並套用對應的 schema (schema-selected 事件)
+fn auto_select_schema(app: &AppHandle, path: &str, manager_state: &DeviceManager) {
+    let Ok(m_dev) = manager_state.get(path) else { return };
+    let id = m_dev.meta.identity;
+    let Some(rules) = app.state::<SchemaVersions>().0.lock().unwrap().get(&(id.vendor_id, id.product_id)).cloned() else { return };
 
-            if let Ok(dev) = shared_device.lock() {
-                let mut buf = [0u8; 64];
-                // 使用短 timeout 確保能頻繁檢查 pause 狀態
-                if let Ok(n) = dev.read_timeout(&mut buf, 100) {
-                    if n > 0 {
-                        let _ = app_inner.emit("hid-data", buf[..n].to_vec());
-                    }
-                } else {
-                    // 讀取錯誤（可能是拔掉設備）
-                    break;
-                }
-            }
-        }
-        // 清理狀態
-        let state = app_inner.state::<DeviceManager>();
-        state.0.lock().unwrap().remove(&path_inner);
+    let result = with_exclusive_device(manager_state, path, query_firmware_version).and_then(|version| {
+        let name = schema::select_schema(&rules, &version).map(str::to_string);
+        let schema = name.as_deref().map(|n| get_schema(&app.state::<Schemas>(), n)).transpose()?;
+        *m_dev.schema.lock().unwrap() = schema;
+        Ok((version, name))
     });
+    let event = match result {
+        Ok((version, schema)) => SchemaSelectedEvent { path: path.to_string(), firmware_version: Some(version), schema, error: None },
+        Err(e) => SchemaSelectedEvent { path: path.to_string(), firmware_version: None, schema: None, error: Some(e) },
+    };
+    let _ = app.emit("schema-selected", event);
+}
 
-    Ok(())
+// 依序由協定插件、VIA 鍵盤、bootloader 查詢，都不支援時以 bcdDevice (例: 0x0123 -> "1.23") 表示
+fn query_firmware_version(dev: &dyn HidIo, meta: &DeviceMeta) -> Result<String, String> {
+    let len = meta.report_sizes.len_of(ReportKind::Output, 0).unwrap_or(64);
+    if let Ok(plugin) = protocols::resolve(None, &meta.identity) {
+        if let Some(version) = plugin.firmware_version(&HidTransport::new(dev, len))? {
+            return Ok(version);
+        }
+    }
+    if meta.identity.usage_page == qmk_via::USAGE_PAGE_RAW_HID {
+        if let Some(version) = qmk_via::firmware_version(&HidTransport::new(dev, qmk_via::REPORT_LEN))? {
+            return Ok(version);
+        }
+    }
+    if let Some(version) = firmware::firmware_version(&meta.identity, &HidTransport::new(dev, len))? {
+        return Ok(version);
+    }
+    let bcd = meta.identity.release_number;
+    Ok(format!("{:x}.{:02x}", bcd >> 8, bcd & 0xFF))
+}
+
+// 計算 data[start..end] 的 checksum；end 未指定代表到資料結尾
+#[tauri::command]
+fn compute_checksum(data: Vec<u8>, algorithm: Checksum, start: Option<usize>, end: Option<usize>) -> Result<u32, String> {
+    let (start, end) = (start.unwrap_or(0), end.unwrap_or(data.len()));
+    let range = data.get(start..end).ok_or(format!("範圍 {}..{} 超出資料長度 {}", start, end, data.len()))?;
+    Ok(algorithm.compute(range))
 }
 
+// 等待時間與重送次數：呼叫端指定的優先，其次為 set_command_deadlines 的設定
+// 沒有回覆時回傳 Timeout 錯誤，連續逾時記錄在設備統計中，進入 / 離開停滯狀態時發送 device-stall
 #[tauri::command]
 async fn send_hid_command(
+    app: AppHandle,
     path: String, 
-    data: Vec<u8>, 
-    manager_state: State<'_, DeviceManager>
-) -> Result<Vec<u8>, String> {
-    // 1. 取得現有的設備句柄，如果不存則自動開啟監聽（可選）
-    let (device_arc, pause_flag) = {
-        let manager = manager_state.0.lock().unwrap();
-        let m_dev = manager.get(&path).ok_or("設備未開啟監聽，請先啟動監聽")?;
-        (m_dev.device.clone(), m_dev.is_paused.clone())
-    };
-
-    // 2. 暫停監聽執行緒的讀取動作
-    pause_flag.store(true, Ordering::SeqCst);
-
-    // 3. 執行寫入與讀取回傳 (使用同一個 Mutex)
-    let result = {
-        let dev = device_arc.lock().map_err(|_| "鎖定設備失敗")?;
-        
-        // 格式化數據 (Report ID 0x00 + 64 bytes)
-        let mut write_buf = vec![0u8; 65];
-        if data[0] == 0x00 {
-            let len = std::cmp::min(data.len(), 65);
-            write_buf[..len].copy_from_slice(&data[..len]);
-        } else {
-            let len = std::cmp::min(data.len(), 64);
-            write_buf[1..len + 1].copy_from_slice(&data[..len]);
+    data: Payload, 
+    options: Option<WriteOptions>,
+    timeout_ms: Option<i32>,
+    retries: Option<u32>,
+    response_match: Option<ResponseMatch>,
+) -> Result<Vec<u8>, CommandError> {
+    let m_dev = app.state::<DeviceManager>().get(&path)?;
+    count_feature(&app, "command");
+    let data = expand_payload(&app, &path, &data)?;
+
+    // 格式化數據 (Report ID + 依 descriptor 的 output report 長度補零)
+    let options = options.unwrap_or_default();
+    let write_buf = build_device_report(&m_dev, &data, &options)?;
+    let (rule_timeout, rule_retries) = app.state::<Deadlines>().0.lock().unwrap().get(&path).map(|d| d.resolve(&data)).unwrap_or_default();
+    let timeout_ms = timeout_ms.or(rule_timeout).unwrap_or(DEFAULT_RESPONSE_TIMEOUT_MS);
+    let attempts = retries.or(rule_retries).unwrap_or(0) + 1;
+
+    // 寫入與讀取回覆都在 I/O 執行緒中依序執行；沒有回覆或失敗時重送
+    let mut result = Ok(Vec::new());
+    for _ in 0..attempts {
+        result = m_dev.exchange(write_buf.clone(), options.method, timeout_ms, response_match.clone());
+        match &result {
+            Ok(resp) if !resp.is_empty() => break,
+            Ok(_) => {}
+            Err(_) => { m_dev.stats.lock().unwrap().record_error(); }
+        }
+    }
+    let resp = result?;
+    if let Some(consecutive) = track_reply(&app, &m_dev, &path, !resp.is_empty()) {
+        return Err(CommandError::Timeout {
+            message: format!("{} ms 內沒有收到回覆 (共嘗試 {} 次)", timeout_ms, attempts),
+            timeout_ms,
+            attempts,
+            consecutive,
+        });
+    }
+    Ok(unframe_response(&m_dev, resp)?)
+}
+
+// 記錄指令是否收到回覆；逾時回傳連續逾時次數
+fn track_reply(app: &AppHandle, m_dev: &ManagedDevice, path: &str, replied: bool) -> Option<u32> {
+    let mut stats = m_dev.stats.lock().unwrap();
+    if replied {
+        if stats.record_reply() {
+            logging::log(app, Severity::Info, Category::Command, Some(path), "設備恢復回應");
+            let _ = app.emit("device-stall", StallEvent { path: path.to_string(), stalled: false, consecutive_timeouts: 0 });
         }
+        return None;
+    }
+    let (consecutive, stalled) = stats.record_timeout();
+    if stalled {
+        logging::log(app, Severity::Warning, Category::Command, Some(path), format!("連續 {} 次指令逾時，設備可能停滯", consecutive));
+        let _ = app.emit("device-stall", StallEvent { path: path.to_string(), stalled: true, consecutive_timeouts: consecutive });
+    }
+    Some(consecutive)
+}
+
+// 寫入並等待對應的回覆；整個過程在設備的 I/O 執行緒中完成，輪詢、排程指令與其他寫入都不會穿插其中
+// 寫入前先讀掉緩衝區中的舊 report，並可要求匯流排安靜 quiet_ms 後才寫入
+#[tauri::command]
+async fn exchange(app: AppHandle, path: String, data: Payload, options: Option<ExchangeOptions>) -> Result<ExchangeReply, CommandError> {
+    let m_dev = app.state::<DeviceManager>().get(&path)?;
+    count_feature(&app, "exchange");
+    let data = expand_payload(&app, &path, &data)?;
+    let options = options.unwrap_or_default();
+    let report = build_device_report(&m_dev, &data, &options.write)?;
+    let (rule_timeout, _) = app.state::<Deadlines>().0.lock().unwrap().get(&path).map(|d| d.resolve(&data)).unwrap_or_default();
+    let timeout_ms = options.timeout_ms.or(rule_timeout).unwrap_or(DEFAULT_RESPONSE_TIMEOUT_MS);
+
+    let result = m_dev.quiesced_exchange(report, options.write.method, timeout_ms, options.response_match, options.quiesce);
+    if result.is_err() { m_dev.stats.lock().unwrap().record_error(); }
+    let mut reply = result?;
+    if let Some(consecutive) = track_reply(&app, &m_dev, &path, !reply.response.is_empty()) {
+        return Err(CommandError::Timeout {
+            message: format!("{} ms 內沒有收到回覆", timeout_ms),
+            timeout_ms,
+            attempts: 1,
+            consecutive,
+        });
+    }
+    reply.response = unframe_response(&m_dev, reply.response)?;
+    Ok(reply)
+}
 
-        dev.write(&write_buf).map_err(|e| format!("寫入失敗: {}", e))?;
+// 不送出指令，等待設備主動送出符合 pattern 的 input report (例: "01 ?? A* ..")，逾時回傳 Timeout 錯誤
+// 等待期間不符合的 report 照常送往 hid-data；只比對開始等待之後收到的 report
+#[tauri::command]
+async fn expect(app: AppHandle, path: String, pattern: BytePattern, timeout_ms: Option<i32>) -> Result<Vec<u8>, CommandError> {
+    let m_dev = app.state::<DeviceManager>().get(&path)?;
+    count_feature(&app, "expect");
+    let timeout_ms = timeout_ms.unwrap_or(DEFAULT_RESPONSE_TIMEOUT_MS);
+    let resp = m_dev.expect(ResponseMatch::Pattern { pattern: pattern.clone() }, timeout_ms)?;
+    if resp.is_empty() {
+        return Err(CommandError::Timeout {
+            message: format!("{} ms 內沒有符合 {} 的 report", timeout_ms, pattern.text()),
+            timeout_ms,
+            attempts: 1,
+            consecutive: 0,
+        });
+    }
+    Ok(unframe_response(&m_dev, resp)?)
+}
 
-        // 讀取回覆
-        let mut read_buf = [0u8; 64];
-        match dev.read_timeout(&mut read_buf, 1000) {
-            Ok(n) if n > 0 => Ok(read_buf[..n].to_vec()),
-            Ok(_) => Ok(Vec::new()),
-            Err(e) => Err(format!("讀取異常: {}", e)),
+// 設定設備的指令等待時間 (None 清除)
+#[tauri::command]
+fn set_command_deadlines(path: String, deadlines: Option<CommandDeadlines>, state: State<'_, Deadlines>) -> Result<(), String> {
+    if let Some(d) = &deadlines {
+        if d.default_timeout_ms.is_some_and(|t| t < 0) || d.rules.iter().any(|r| r.timeout_ms < 0) {
+            return Err("等待時間不可為負數".into());
         }
-    };
+        if d.rules.iter().any(|r| r.prefix.is_empty()) {
+            return Err("規則的 prefix 不可為空".into());
+        }
+    }
+    let mut map = state.0.lock().unwrap();
+    match deadlines {
+        Some(d) => { map.insert(path, d); }
+        None => { map.remove(&path); }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_command_deadlines(path: String, state: State<'_, Deadlines>) -> Option<CommandDeadlines> {
+    state.0.lock().unwrap().get(&path).cloned()
+}
+
+#[derive(Serialize)]
+struct TemplateStateInfo {
+    variables: HashMap<String, TemplateValue>,
+    counters: Vec<CounterValue>,
+}
+
+// 設定樣板的使用者變數 (None 刪除)；數值以 {{NAME:u16le}} 等編碼插入，位元組陣列原樣插入
+#[tauri::command]
+fn set_template_variable(name: String, value: Option<TemplateValue>, state: State<'_, Templates>) -> Result<(), String> {
+    state.0.lock().unwrap().set_variable(&name, value)
+}
+
+#[tauri::command]
+fn get_template_state(state: State<'_, Templates>) -> TemplateStateInfo {
+    let templates = state.0.lock().unwrap();
+    TemplateStateInfo { variables: templates.variables(), counters: templates.counters() }
+}
+
+// 將計數器歸零 (path 為 None 時重設所有設備)
+#[tauri::command]
+fn reset_template_counters(path: Option<String>, state: State<'_, Templates>) {
+    state.0.lock().unwrap().reset_counters(path.as_deref());
+}
 
-    // 4. 恢復監聽
-    pause_flag.store(false, Ordering::SeqCst);
+// 預覽展開結果，不送出也不遞增計數器
+#[tauri::command]
+fn preview_payload(path: String, payload: Payload, state: State<'_, Templates>) -> Result<Vec<u8>, String> {
+    let mut preview = state.0.lock().unwrap().clone();
+    preview.expand(&path, &payload)
+}
 
+// 只寫入不等待回覆 (LED、震動等沒有回應的 output report)
+#[tauri::command]
+async fn write_hid(
+    app: AppHandle,
+    path: String,
+    data: Payload,
+    options: Option<WriteOptions>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<usize, String> {
+    let m_dev = manager_state.get(&path)?;
+    let data = expand_payload(&app, &path, &data)?;
+    let options = options.unwrap_or_default();
+    let write_buf = build_device_report(&m_dev, &data, &options)?;
+    let result = m_dev.write(write_buf, options.method);
+    if result.is_err() { m_dev.stats.lock().unwrap().record_error(); }
     result
 }
 
+// 同一筆資料同時送往多個設備，各設備在自己的 I/O 執行緒中並行處理；樣板依各設備的計數器分別展開
 #[tauri::command]
-fn stop_listening(path: String, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
-    let mut manager = manager_state.0.lock().unwrap();
-    if let Some(m_dev) = manager.get(&path) {
-        m_dev.should_stop.store(true, Ordering::SeqCst);
+async fn send_hid_broadcast(
+    app: AppHandle,
+    paths: Vec<String>,
+    data: Payload,
+    options: Option<WriteOptions>,
+    timeout_ms: Option<i32>,
+    wait_response: Option<bool>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<HashMap<String, BroadcastResult>, String> {
+    let options = options.unwrap_or_default();
+    let timeout_ms = timeout_ms.unwrap_or(DEFAULT_RESPONSE_TIMEOUT_MS);
+    let wait_response = wait_response.unwrap_or(true);
+
+    let send = |path: &str| -> Result<Vec<u8>, String> {
+        let m_dev = manager_state.get(path)?;
+        let data = expand_payload(&app, path, &data)?;
+        let write_buf = build_device_report(&m_dev, &data, &options)?;
+        let result = if wait_response {
+            m_dev.exchange(write_buf, options.method, timeout_ms, None).and_then(|resp| unframe_response(&m_dev, resp))
+        } else {
+            m_dev.write(write_buf, options.method).map(|_| Vec::new())
+        };
+        if result.is_err() { m_dev.stats.lock().unwrap().record_error(); }
+        result
+    };
+
+    let results = thread::scope(|scope| {
+        let handles: Vec<_> = paths.iter()
+            .map(|path| (path, scope.spawn(|| send(path))))
+            .collect();
+        handles.into_iter()
+            .map(|(path, handle)| {
+                let result = handle.join().unwrap_or_else(|_| Err("傳送執行緒異常結束".into()));
+                let entry = match result {
+                    Ok(response) => BroadcastResult { response, error: None },
+                    Err(e) => BroadcastResult { response: Vec::new(), error: Some(e) },
+                };
+                (path.clone(), entry)
+            })
+            .collect()
+    });
+    Ok(results)
+}
+
+// 由後端定期送出 request，回覆以 hid-poll 事件送出 (不受前端計時器節流影響)
+#[tauri::command]
+fn start_polling(
+    path: String,
+    data: Vec<u8>,
+    interval_ms: u64,
+    options: Option<WriteOptions>,
+    timeout_ms: Option<i32>,
+    response_match: Option<ResponseMatch>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    if interval_ms == 0 { return Err("interval_ms 必須大於 0".into()); }
+    let m_dev = manager_state.get(&path)?;
+    let options = options.unwrap_or_default();
+    let report = build_device_report(&m_dev, &data, &options)?;
+    // 回覆等待時間不超過輪詢間隔，避免拖慢下一輪
+    let timeout_ms = timeout_ms.unwrap_or(DEFAULT_RESPONSE_TIMEOUT_MS).min(i32::try_from(interval_ms).unwrap_or(i32::MAX));
+    m_dev.set_polling(Some(PollConfig {
+        report,
+        method: options.method,
+        interval: Duration::from_millis(interval_ms),
+        timeout_ms,
+        matcher: response_match,
+    }))
+}
+
+#[tauri::command]
+fn stop_polling(path: String, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    manager_state.get(&path)?.set_polling(None)
+}
+
+#[tauri::command]
+fn stop_listening(app: AppHandle, path: String, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    if let Ok(m_dev) = manager_state.get(&path) {
+        m_dev.stop();
+    }
+    // 向服務借來的設備在關閉後歸還
+    if app.state::<BorrowedDevices>().0.lock().unwrap().contains(&path) {
+        thread::spawn(move || { let _ = return_device(&app, &path); });
     }
     Ok(())
 }
 
-fn main() {
-    tauri::Builder::default()
-        .manage(DeviceManager(Mutex::new(HashMap::new())))
-        .invoke_handler(tauri::generate_handler![
-            scan_hid_devices, 
-            start_listening, 
-            stop_listening,
-            send_hid_command
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+// 回傳的資料第一個位元組為 Report ID (與 hidapi 相同)
+#[tauri::command]
+async fn get_feature_report(
+    path: String,
+    report_id: u8,
+    length: Option<usize>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<Vec<u8>, String> {
+    with_exclusive_device(&manager_state, &path, |dev, meta| {
+        let len = match length {
+            Some(len) => len,
+            None => meta.report_sizes.len_of(ReportKind::Feature, report_id)
+                .ok_or(format!("設備沒有 Report ID {:#04x} 的 feature report，請指定長度", report_id))?,
+        };
+        let mut buf = vec![0u8; len + 1];
+        buf[0] = report_id;
+        let n = dev.get_feature_report(&mut buf).map_err(|e| format!("讀取 feature report 失敗: {}", e))?;
+        buf.truncate(n);
+        Ok(buf)
+    })
+}
+
+// data 不含 Report ID；length 未指定時依 descriptor 補零
+#[tauri::command]
+async fn send_feature_report(
+    path: String,
+    report_id: u8,
+    data: Vec<u8>,
+    length: Option<usize>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    with_exclusive_device(&manager_state, &path, |dev, meta| {
+        let len = match length {
+            Some(len) if data.len() > len => {
+                return Err(format!("資料長度 {} 超過指定長度 {}", data.len(), len));
+            }
+            Some(len) => len,
+            None if meta.report_sizes.from_descriptor => {
+                meta.report_sizes.check_payload(ReportKind::Feature, report_id, data.len())?
+            }
+            None => data.len(),
+        };
+        let mut buf = vec![0u8; len + 1];
+        buf[0] = report_id;
+        buf[1..data.len() + 1].copy_from_slice(&data);
+        dev.send_feature_report(&buf).map_err(|e| format!("寫入 feature report 失敗: {}", e))
+    })
+}
+
+#[tauri::command]
+fn get_report_sizes(path: String, manager_state: State<'_, DeviceManager>) -> Result<ReportSizes, String> {
+    let m_dev = manager_state.get(&path)?;
+    Ok(m_dev.meta.report_sizes.clone())
+}
+
+#[tauri::command]
+fn get_device_capabilities(path: String, manager_state: State<'_, DeviceManager>) -> Result<CapabilityManifest, String> {
+    let meta = manager_state.get(&path)?.meta;
+    let plugin = protocols::resolve(None, &meta.identity).ok();
+    Ok(capabilities::compute(&meta.identity, meta.descriptor.as_ref(), plugin.as_deref()))
+}
+
+#[tauri::command]
+fn set_listen_options(path: String, options: ListenOptions, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    let m_dev = manager_state.get(&path)?;
+    *m_dev.options.lock().unwrap() = options;
+    Ok(())
+}
+
+// 新增或取代同名的監看運算式
+#[tauri::command]
+fn add_watch(path: String, name: String, expression: String, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    let m_dev = manager_state.get(&path)?;
+    if m_dev.meta.descriptor.is_none() {
+        return Err("無法取得 report descriptor，不能監看欄位".into());
+    }
+    let watch = Watch::parse(&name, &expression)?;
+    let mut watches = m_dev.watches.lock().unwrap();
+    watches.retain(|w| w.name != name);
+    watches.push(watch);
+    Ok(())
+}
+
+#[tauri::command]
+fn remove_watch(path: String, name: String, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    let m_dev = manager_state.get(&path)?;
+    let mut watches = m_dev.watches.lock().unwrap();
+    let before = watches.len();
+    watches.retain(|w| w.name != name);
+    if watches.len() == before { return Err(format!("找不到監看: {}", name)); }
+    Ok(())
+}
+
+#[tauri::command]
+fn list_watches(path: String, manager_state: State<'_, DeviceManager>) -> Result<Vec<WatchInfo>, String> {
+    let m_dev = manager_state.get(&path)?;
+    let watches = m_dev.watches.lock().unwrap();
+    Ok(watches.iter().map(Watch::info).collect())
+}
+
+#[tauri::command]
+fn pause_listening(path: String, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    manager_state.get(&path)?.pause();
+    Ok(())
+}
+
+#[tauri::command]
+fn resume_listening(path: String, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    manager_state.get(&path)?.resume();
+    Ok(())
+}
+
+// 解析後的 report descriptor 模型 (各欄位的 usage、範圍與位元位置)
+#[tauri::command]
+fn get_report_descriptor(path: String, manager_state: State<'_, DeviceManager>) -> Result<ReportDescriptor, String> {
+    let m_dev = manager_state.get(&path)?;
+    m_dev.meta.descriptor.clone().ok_or("無法取得此設備的 report descriptor".into())
+}
+
+// 把 report descriptor 存成 binary / 十六進位文字 / 反組譯清單
+#[tauri::command]
+async fn export_report_descriptor(
+    path: String,
+    file: String,
+    format: DescriptorFormat,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    let raw = with_exclusive_device(&manager_state, &path, |dev, _| dev.report_descriptor())?;
+    let bytes = descriptor::export(&raw, format)?;
+    std::fs::write(&file, bytes).map_err(|e| format!("寫入 {} 失敗: {}", file, e))
+}
+
+// 以設備的 descriptor 解碼一筆 report (含 Report ID)，kind 預設為 input
+#[tauri::command]
+fn decode_report(
+    path: String,
+    data: Vec<u8>,
+    kind: Option<ReportKind>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<DecodedReport, String> {
+    let m_dev = manager_state.get(&path)?;
+    let desc = m_dev.meta.descriptor.as_ref().ok_or("無法取得此設備的 report descriptor")?;
+    desc.decode(kind.unwrap_or(ReportKind::Input), &data).ok_or("descriptor 中沒有對應此 report 的欄位".into())
+}
+
+#[tauri::command]
+fn get_device_stats(path: String, manager_state: State<'_, DeviceManager>) -> Result<DeviceStatsSnapshot, String> {
+    let m_dev = manager_state.get(&path)?;
+    let snapshot = m_dev.stats.lock().unwrap().snapshot(&path);
+    Ok(snapshot)
+}
+
+#[tauri::command]
+fn set_stats_interval(interval_ms: u64, config: State<'_, StatsConfig>) -> Result<(), String> {
+    config.0.store(interval_ms, Ordering::SeqCst);
+    Ok(())
+}
+
+#[tauri::command]
+fn list_active_devices(manager_state: State<'_, DeviceManager>) -> Vec<ActiveDevice> {
+    let manager = manager_state.0.lock().unwrap();
+    manager.iter()
+        .map(|(path, m_dev)| ActiveDevice {
+            path: path.clone(),
+            identity: m_dev.meta.identity,
+            paused: m_dev.user_paused.load(Ordering::SeqCst),
+            state: m_dev.connection.get(),
+            activity: m_dev.stats.lock().unwrap().activity(),
+            error: m_dev.fault.lock().unwrap().clone(),
+        })
+        .collect()
+}
+
+// 清除設備的錯誤狀態，恢復寫入與輪詢
+#[tauri::command]
+fn clear_error(app: AppHandle, path: String, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    let m_dev = manager_state.get(&path)?;
+    if m_dev.fault.lock().unwrap().take().is_some() {
+        logging::log(&app, Severity::Info, Category::Device, Some(&path), "已清除錯誤狀態");
+        let _ = app.emit("device-fault", FaultEvent { path, fault: None });
+    }
+    Ok(())
+}
+
+// 定期檢查各設備是否變得安靜，狀態改變時發送 device-activity
+fn spawn_activity_monitor(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_millis(ACTIVITY_CHECK_MS));
+        let transitions: Vec<ActivityEvent> = {
+            let state = app.state::<DeviceManager>();
+            let manager = state.0.lock().unwrap();
+            manager.iter()
+                .filter_map(|(path, m_dev)| {
+                    let (previous, state) = m_dev.stats.lock().unwrap().update_activity()?;
+                    Some(ActivityEvent { path: path.clone(), previous, state })
+                })
+                .collect()
+        };
+        for event in transitions {
+            let _ = app.emit("device-activity", event);
+        }
+    });
+}
+
+// 定期發送所有設備的統計 (hid-stats)
+fn spawn_stats_emitter(app: AppHandle) {
+    thread::spawn(move || loop {
+        let interval = app.state::<StatsConfig>().0.load(Ordering::SeqCst);
+        if interval == 0 {
+            thread::sleep(Duration::from_millis(200));
+            continue;
+        }
+        thread::sleep(Duration::from_millis(interval));
+
+        let snapshots: Vec<DeviceStatsSnapshot> = {
+            let state = app.state::<DeviceManager>();
+            let manager = state.0.lock().unwrap();
+            manager.iter()
+                .map(|(path, m_dev)| m_dev.stats.lock().unwrap().snapshot(path))
+                .collect()
+        };
+        let _ = app.emit("hid-stats", snapshots);
+    });
+}
+
+// 每隔 interval_ms 以一句話描述各設備的狀態 (device-summary 事件，見 summary.rs)，0 停止
+#[tauri::command]
+fn set_summary_options(interval_ms: u64, changes_only: Option<bool>, summaries: State<'_, Summaries>) -> Result<(), String> {
+    summaries.0.lock().unwrap().configure(interval_ms, changes_only.unwrap_or(true))
+}
+
+fn spawn_summary_emitter(app: AppHandle) {
+    thread::spawn(move || loop {
+        let interval = app.state::<Summaries>().0.lock().unwrap().interval_ms;
+        if interval == 0 {
+            thread::sleep(Duration::from_millis(200));
+            continue;
+        }
+        thread::sleep(Duration::from_millis(interval));
+
+        let current: Vec<SummaryEvent> = {
+            let aliases = app.state::<CaptureNaming>().0.lock().unwrap().aliases.clone();
+            let state = app.state::<DeviceManager>();
+            let manager = state.0.lock().unwrap();
+            manager.iter()
+                .map(|(path, m_dev)| {
+                    let id = &m_dev.meta.identity;
+                    let name = aliases.get(path).cloned()
+                        .unwrap_or_else(|| format!("Device {:04X}:{:04X}", id.vendor_id, id.product_id));
+                    let stats = m_dev.stats.lock().unwrap().snapshot(path);
+                    let paused = m_dev.user_paused.load(Ordering::SeqCst);
+                    let fault = m_dev.fault.lock().unwrap().clone();
+                    SummaryEvent { path: path.clone(), text: summary::describe(&name, &stats, paused, fault.as_ref()) }
+                })
+                .collect()
+        };
+        let events = app.state::<Summaries>().0.lock().unwrap().update(current);
+        for event in events {
+            let _ = app.emit("device-summary", event);
+        }
+    });
+}
+
+// 設定總覽畫面要合併的設備與欄位，interval_ms 為 0 時停止送出
+#[tauri::command]
+fn set_dashboard(
+    entries: Vec<DashboardEntry>,
+    interval_ms: u64,
+    dashboard: State<'_, Dashboard>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    if interval_ms != 0 && interval_ms < dashboard::MIN_INTERVAL_MS {
+        return Err(format!("interval_ms 不能小於 {}", dashboard::MIN_INTERVAL_MS));
+    }
+    // 不再列出的設備停止記錄欄位，列出的設備由 emitter 重新開啟
+    for m_dev in manager_state.0.lock().unwrap().values() {
+        *m_dev.latest.lock().unwrap() = Default::default();
+    }
+    *dashboard.0.lock().unwrap() = DashboardConfig { interval_ms, entries };
+    Ok(())
+}
+
+// 立即取得一次總覽資料
+#[tauri::command]
+fn get_dashboard(dashboard: State<'_, Dashboard>, manager_state: State<'_, DeviceManager>) -> Vec<DashboardRow> {
+    let config = dashboard.0.lock().unwrap();
+    config.rows(&manager_state.0.lock().unwrap())
+}
+
+fn spawn_dashboard_emitter(app: AppHandle) {
+    thread::spawn(move || loop {
+        let (interval, empty) = {
+            let dashboard = app.state::<Dashboard>();
+            let config = dashboard.0.lock().unwrap();
+            (config.interval_ms, config.entries.is_empty())
+        };
+        if interval == 0 || empty {
+            thread::sleep(Duration::from_millis(200));
+            continue;
+        }
+        thread::sleep(Duration::from_millis(interval));
+
+        let rows = {
+            let dashboard = app.state::<Dashboard>();
+            let config = dashboard.0.lock().unwrap();
+            let manager_state = app.state::<DeviceManager>();
+            let manager = manager_state.0.lock().unwrap();
+            config.rows(&manager)
+        };
+        let _ = app.emit("dashboard", rows);
+    });
+}
+
+#[tauri::command]
+fn list_protocols() -> Vec<ProtocolInfo> {
+    protocols::list()
+}
+
+// 進行 onboard profile 操作，並以 profile-progress 事件回報進度
+fn run_profile_op<T>(
+    app: &AppHandle,
+    manager_state: &DeviceManager,
+    path: &str,
+    protocol: Option<String>,
+    slot: u8,
+    op: impl FnOnce(&dyn protocols::ProfileStorage, &HidTransport, onboard::ProgressFn) -> Result<T, String>,
+) -> Result<T, String> {
+    with_exclusive_device(manager_state, path, |dev, meta| {
+        let plugin = protocols::resolve(protocol.as_deref(), &meta.identity)?;
+        let storage = plugin.profile_storage().ok_or("此協定不支援 onboard profile")?;
+        if slot >= storage.slot_count() {
+            return Err(format!("slot {} 不存在 (共 {} 個)", slot, storage.slot_count()));
+        }
+
+        let len = meta.report_sizes.len_of(ReportKind::Output, 0).unwrap_or(64);
+        let transport = HidTransport::new(dev, len);
+        let mut progress = |phase: &'static str, current: usize, total: usize| {
+            let _ = app.emit("profile-progress", ProfileProgress {
+                path: path.to_string(), slot, phase, current, total,
+            });
+        };
+        op(storage, &transport, &mut progress)
+    })
+}
+
+#[tauri::command]
+async fn read_profile(
+    app: AppHandle,
+    path: String,
+    protocol: Option<String>,
+    slot: u8,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<Vec<u8>, String> {
+    run_profile_op(&app, &manager_state, &path, protocol, slot, |storage, t, progress| {
+        onboard::read_slot(storage, t, slot, progress)
+    })
+}
+
+#[tauri::command]
+async fn write_profile(
+    app: AppHandle,
+    path: String,
+    protocol: Option<String>,
+    slot: u8,
+    data: Vec<u8>,
+    verify: bool,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    run_profile_op(&app, &manager_state, &path, protocol, slot, |storage, t, progress| {
+        onboard::write_slot(storage, t, slot, &data, verify, progress)
+    })
+}
+
+#[tauri::command]
+async fn erase_profile(
+    app: AppHandle,
+    path: String,
+    protocol: Option<String>,
+    slot: u8,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    run_profile_op(&app, &manager_state, &path, protocol, slot, |storage, t, progress| {
+        progress("erase", 0, 1);
+        storage.erase(t, slot)?;
+        progress("erase", 1, 1);
+        Ok(())
+    })
+}
+
+// 分段傳輸，登記成 operation (可取消)，以 transfer-progress 與 operation-progress 事件回報進度
+fn run_transfer<T>(
+    app: &AppHandle,
+    path: &str,
+    operation_id: Option<String>,
+    op: impl FnOnce(&HidTransport, usize, &AtomicBool, onboard::ProgressFn) -> Result<T, String>,
+) -> Result<T, String> {
+    let guard = begin_operation(app, OperationKind::Transfer, Some(path), operation_id)?;
+    with_exclusive_device(&app.state::<DeviceManager>(), path, |dev, meta| {
+        let len = meta.report_sizes.len_of(ReportKind::Output, 0).unwrap_or(64);
+        let transport = HidTransport::new(dev, len);
+        let mut progress = |phase: &'static str, current: usize, total: usize| {
+            let _ = app.emit("transfer-progress", TransferProgress {
+                path: path.to_string(), phase, current, total,
+            });
+            guard.progress(phase, current as u64, (total > 0).then_some(total as u64), None);
+        };
+        op(&transport, len, &guard.cancel, &mut progress)
+    })
+}
+
+#[tauri::command]
+async fn send_chunked(
+    app: AppHandle,
+    path: String,
+    data: Vec<u8>,
+    format: Option<ChunkFormat>,
+    operation_id: Option<String>,
+) -> Result<(), String> {
+    let format = format.unwrap_or_default();
+    run_transfer(&app, &path, operation_id, |t, len, cancel, progress| {
+        transfer::send(t, &format, len, &data, cancel, progress)
+    })
+}
+
+// request 為觸發設備開始回傳的指令 (可省略)
+#[tauri::command]
+async fn receive_chunked(
+    app: AppHandle,
+    path: String,
+    request: Option<Vec<u8>>,
+    format: Option<ChunkFormat>,
+    operation_id: Option<String>,
+) -> Result<Vec<u8>, String> {
+    let format = format.unwrap_or_default();
+    run_transfer(&app, &path, operation_id, |t, len, cancel, progress| {
+        if let Some(request) = &request { t.write(request)?; }
+        transfer::receive(t, &format, len, cancel, progress)
+    })
+}
+
+#[tauri::command]
+fn list_bootloaders() -> Vec<BootloaderInfo> {
+    firmware::list()
+}
+
+// 解析韌體檔 (Intel HEX / binary) 並回傳位址範圍；base_address 只用於 binary
+#[tauri::command]
+fn load_firmware_file(path: String, base_address: Option<u32>) -> Result<ImageInfo, String> {
+    Ok(FirmwareImage::load(&path, base_address.unwrap_or(0))?.info())
+}
+
+// 把韌體檔寫入設備，以 fw-progress 事件回報進度；未指定協定時依 VID / PID 選擇 bootloader
+// 回傳寫入的資料是否已驗證，沒有驗證時另外記錄警告
+#[tauri::command]
+async fn firmware_update(
+    app: AppHandle,
+    path: String,
+    file: String,
+    protocol: Option<String>,
+    options: Option<FirmwareOptions>,
+    operation_id: Option<String>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<bool, String> {
+    let options = options.unwrap_or_default();
+    check_station_lock(&app, options.station_token.as_deref())?;
+    let image = FirmwareImage::load(&file, options.base_address)?;
+    let op = begin_operation(&app, OperationKind::Firmware, Some(&path), operation_id)?;
+    count_feature(&app, "firmware_update");
+    let result = with_exclusive_device(&manager_state, &path, |dev, meta| {
+        let mut loader = firmware::resolve(protocol.as_deref(), &meta.identity, options.bootloader)?;
+        let len = meta.report_sizes.len_of(ReportKind::Output, 0).unwrap_or(64);
+        let transport = HidTransport::new(dev, len);
+        let mut progress = |progress: FwProgress| {
+            op.progress(progress.phase.name(), progress.current as u64, Some(progress.total as u64), progress.error.clone());
+            let _ = app.emit("fw-progress", FwEvent { path: path.clone(), progress });
+        };
+        let verified = firmware::update(loader.as_mut(), &transport, &image, !options.skip_verify, &op.cancel, &mut progress)?;
+        if !verified {
+            let reason = match options.skip_verify {
+                true => "已略過驗證".to_string(),
+                false => format!("{} 不支援驗證", loader.name()),
+            };
+            logging::log(&app, Severity::Warning, Category::Device, Some(&path), format!("韌體已寫入但未驗證 ({})", reason));
+        }
+        Ok(verified)
+    });
+    result
+}
+
+// 在下一個區塊之前停止更新 (設備可能停在 bootloader 中)
+#[tauri::command]
+fn cancel_firmware_update(path: String, operations: State<'_, RunningOperations>) -> Result<(), String> {
+    operations.0.lock().unwrap().cancel_kind(OperationKind::Firmware, Some(&path))
+}
+
+// 在後端依序執行指令序列 (見 sequence.rs)，每一步發送 sequence-progress，回傳 pass / fail 摘要
+// 指令經由設備的 I/O 執行緒送出，執行期間監聽照常進行
+#[tauri::command]
+async fn run_sequence(
+    app: AppHandle,
+    path: String,
+    sequence: Sequence,
+    operation_id: Option<String>,
+    station_token: Option<String>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<SequenceResult, String> {
+    check_station_lock(&app, station_token.as_deref())?;
+    let m_dev = manager_state.get(&path)?;
+    let op = begin_operation(&app, OperationKind::Sequence, Some(&path), operation_id)?;
+    count_feature(&app, "sequence");
+    let exchange = |data: Option<&Payload>, expect: Option<(&BytePattern, i32)>| -> Result<Vec<u8>, String> {
+        let Some(data) = data else {
+            let (pattern, timeout_ms) = expect.ok_or("沒有要執行的動作")?;
+            return m_dev.expect(ResponseMatch::Pattern { pattern: pattern.clone() }, timeout_ms);
+        };
+        let data = expand_payload(&app, &path, data)?;
+        let report = build_device_report(&m_dev, &data, &WriteOptions::default())?;
+        match expect {
+            Some((pattern, timeout_ms)) => {
+                let matcher = ResponseMatch::Pattern { pattern: pattern.clone() };
+                let resp = m_dev.exchange(report, OutputMethod::Interrupt, timeout_ms, Some(matcher))?;
+                track_reply(&app, &m_dev, &path, !resp.is_empty());
+                Ok(resp)
+            }
+            None => m_dev.write(report, OutputMethod::Interrupt).map(|_| Vec::new()),
+        }
+    };
+    let mut progress = |progress: SequenceProgress| {
+        if let Some(error) = &progress.error {
+            let message = format!("序列第 {} 步 ({}) 失敗: {}", progress.step, progress.description, error);
+            logging::log(&app, Severity::Error, Category::Sequence, Some(&path), message);
+        }
+        let _ = app.emit("sequence-progress", SequenceEvent { path: path.clone(), progress });
+    };
+    let result = sequence::run(&sequence, &exchange, &op.cancel, &mut progress);
+    if let Ok(result) = &result {
+        app.state::<TestRecords>().0.lock().unwrap().record(TestSuite::from_sequence(&path, result));
+    }
+    result
+}
+
+// --- 自動化腳本 ---
+
+// 腳本中的設備操作，與對應的 command 使用相同的路徑
+struct AppScriptHost(AppHandle);
+
+impl ScriptHost for AppScriptHost {
+    fn open(&self, path: &str) -> Result<(), String> {
+        listen_device(&self.0, path, None, &self.0.state::<DeviceManager>())
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> Result<usize, String> {
+        let m_dev = self.0.state::<DeviceManager>().get(path)?;
+        let report = build_device_report(&m_dev, data, &WriteOptions::default())?;
+        m_dev.write(report, OutputMethod::Interrupt)
+    }
+
+    fn exchange(&self, path: &str, data: &[u8], timeout_ms: i32) -> Result<Vec<u8>, String> {
+        let m_dev = self.0.state::<DeviceManager>().get(path)?;
+        let report = build_device_report(&m_dev, data, &WriteOptions::default())?;
+        let resp = m_dev.exchange(report, OutputMethod::Interrupt, timeout_ms, None)?;
+        track_reply(&self.0, &m_dev, path, !resp.is_empty());
+        Ok(resp)
+    }
+
+    fn expect(&self, path: &str, pattern: &BytePattern, timeout_ms: i32) -> Result<Vec<u8>, String> {
+        let m_dev = self.0.state::<DeviceManager>().get(path)?;
+        m_dev.expect(ResponseMatch::Pattern { pattern: pattern.clone() }, timeout_ms)
+    }
+
+    // 以獨佔操作直接讀取，讀到的 report 不會送往 hid-data
+    fn read(&self, path: &str, timeout_ms: i32) -> Result<Vec<u8>, String> {
+        with_exclusive_device(&self.0.state::<DeviceManager>(), path, |dev, meta| {
+            let mut buf = vec![0u8; meta.report_sizes.input_buffer_len()];
+            let n = dev.read_timeout(&mut buf, timeout_ms)?;
+            buf.truncate(n);
+            Ok(buf)
+        })
+    }
+
+    fn log(&self, message: &str) {
+        logging::log(&self.0, Severity::Info, Category::Script, None, message);
+    }
+}
+
+// 執行 Rhai 腳本 (可用的函式見 script.rs)，結束或被停止後回傳結果；log / print 以 app-log 事件送出 (category 為 script)
+#[tauri::command]
+async fn run_script(app: AppHandle, source: String, operation_id: Option<String>) -> Result<ScriptResult, String> {
+    let op = begin_operation(&app, OperationKind::Script, None, operation_id)?;
+    count_feature(&app, "script");
+    let (host, stop) = (app.clone(), op.cancel.clone());
+    thread::spawn(move || script::run(&source, std::rc::Rc::new(AppScriptHost(host)), stop))
+        .join()
+        .map_err(|_| "腳本執行緒異常結束".to_string())
+}
+
+#[tauri::command]
+fn stop_script(operations: State<'_, RunningOperations>) -> Result<(), String> {
+    operations.0.lock().unwrap().cancel_kind(OperationKind::Script, None)
+}
+
+// 在目前步驟結束後停止序列
+#[tauri::command]
+fn cancel_sequence(path: String, operations: State<'_, RunningOperations>) -> Result<(), String> {
+    operations.0.lock().unwrap().cancel_kind(OperationKind::Sequence, Some(&path))
+}
+
+// 以 interval_ms 的固定間隔送出 payload iterations 次，統計成功、逾時、不符 expect 與錯誤的次數及來回時間
+// options 除了 exchange 的選項 (回覆辨識、等待時間、寫入前讓匯流排安靜) 之外可指定 expect
+#[tauri::command]
+async fn run_stress_test(
+    app: AppHandle,
+    path: String,
+    payload: Payload,
+    iterations: u32,
+    interval_ms: u64,
+    options: Option<StressOptions>,
+) -> Result<StressResult, String> {
+    let m_dev = app.state::<DeviceManager>().get(&path)?;
+    let StressOptions { exchange: options, expect, operation_id, station_token } = options.unwrap_or_default();
+    check_station_lock(&app, station_token.as_deref())?;
+    // 樣板每次送出時重新展開 (計數器、時間戳記)；第一次在開始前展開，順便檢查格式
+    let build = || expand_payload(&app, &path, &payload).and_then(|data| build_device_report(&m_dev, &data, &options.write));
+    let mut next_report = Some(build()?);
+    let timeout_ms = options.timeout_ms.unwrap_or(DEFAULT_RESPONSE_TIMEOUT_MS);
+    let op = begin_operation(&app, OperationKind::Stress, Some(&path), operation_id)?;
+    count_feature(&app, "stress");
+
+    let mut stats = StressStats::new(iterations);
+    let interval = Duration::from_millis(interval_ms);
+    let started = Instant::now();
+    let mut next_at = started;
+    for i in 0..iterations {
+        if op.cancelled() { break; }
+        if i > 0 {
+            // 以固定間隔排程，單次耗時超過間隔時立即送出下一次
+            next_at += interval;
+            if let Some(wait) = next_at.checked_duration_since(Instant::now()) { thread::sleep(wait); }
+        }
+        let report = match next_report.take().map_or_else(build, Ok) {
+            Ok(report) => report,
+            Err(e) => {
+                stats.record(StressOutcome::Error(e));
+                continue;
+            }
+        };
+        let result = m_dev.quiesced_exchange(report, options.write.method, timeout_ms, options.response_match.clone(), options.quiesce.clone());
+        let outcome = match result {
+            Ok(reply) if reply.response.is_empty() => StressOutcome::Timeout,
+            Ok(reply) if expect.as_ref().is_some_and(|p| !p.matches(&reply.response)) => StressOutcome::Mismatch(reply.latency_us, reply.response),
+            Ok(reply) => StressOutcome::Success(reply.latency_us),
+            Err(e) => {
+                m_dev.stats.lock().unwrap().record_error();
+                StressOutcome::Error(e)
+            }
+        };
+        if !matches!(outcome, StressOutcome::Error(_)) {
+            track_reply(&app, &m_dev, &path, !matches!(outcome, StressOutcome::Timeout));
+        }
+        stats.record(outcome);
+        op.progress("stress", i as u64 + 1, Some(iterations as u64), None);
+    }
+    let result = stats.finish(started.elapsed().as_millis() as u64, op.cancelled());
+    app.state::<TestRecords>().0.lock().unwrap().record(TestSuite::from_stress(&path, &payload.describe(), &result));
+    Ok(result)
+}
+
+// 在目前這次送出結束後停止壓力測試，回傳已完成部分的統計
+#[tauri::command]
+fn cancel_stress_test(path: String, operations: State<'_, RunningOperations>) -> Result<(), String> {
+    operations.0.lock().unwrap().cancel_kind(OperationKind::Stress, Some(&path))
+}
+
+// 量測 samples 次寫入到回覆的延遲 (I/O 執行緒中以單調時鐘量測)，回傳百分位數與直方圖
+// 每次寫入前預設先讀掉緩衝區中的舊 report，避免被當成回覆；逾時與錯誤分開計數
+#[tauri::command]
+async fn benchmark_latency(
+    app: AppHandle,
+    path: String,
+    payload: Payload,
+    samples: u32,
+    options: Option<BenchmarkOptions>,
+) -> Result<LatencyReport, String> {
+    if samples > latency::MAX_SAMPLES {
+        return Err(format!("樣本數不可超過 {}", latency::MAX_SAMPLES));
+    }
+    let m_dev = app.state::<DeviceManager>().get(&path)?;
+    let options = options.unwrap_or_default();
+    let exchange = &options.exchange;
+    let build = || expand_payload(&app, &path, &payload).and_then(|data| build_device_report(&m_dev, &data, &exchange.write));
+    let mut next_report = Some(build()?);
+    let timeout_ms = exchange.timeout_ms.unwrap_or(DEFAULT_RESPONSE_TIMEOUT_MS);
+    let op = begin_operation(&app, OperationKind::Benchmark, Some(&path), options.operation_id.clone())?;
+    count_feature(&app, "benchmark");
+
+    let (mut latencies, mut timeouts, mut errors) = (Vec::with_capacity(samples as usize), 0, 0);
+    for i in 0..options.warmup.saturating_add(samples) {
+        if op.cancelled() { break; }
+        if i > 0 && options.interval_ms > 0 { thread::sleep(Duration::from_millis(options.interval_ms)); }
+        let result = next_report.take().map_or_else(build, Ok)
+            .and_then(|report| m_dev.quiesced_exchange(report, exchange.write.method, timeout_ms, exchange.response_match.clone(), exchange.quiesce.clone()));
+        if i < options.warmup { continue; }
+        match result {
+            Ok(reply) if reply.response.is_empty() => timeouts += 1,
+            Ok(reply) => latencies.push(reply.latency_us),
+            Err(_) => errors += 1,
+        }
+    }
+    let mut report = LatencyReport::new(latencies, timeouts, errors, options.buckets.unwrap_or(latency::DEFAULT_BUCKETS));
+    report.cancelled = op.cancelled();
+    Ok(report)
+}
+
+// --- 長時間操作 ---
+
+// operation-progress 在同一階段內的最短間隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+// 結束時 (包含提早回傳錯誤) 自動移除登記並發送 operation 事件
+struct OperationGuard {
+    app: AppHandle,
+    id: String,
+    kind: OperationKind,
+    path: Option<String>,
+    cancel: Arc<AtomicBool>,
+    // 上一次發送進度的階段與時間
+    last_progress: Mutex<Option<(String, Instant)>>,
+}
+
+impl OperationGuard {
+    fn cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    // 發送 operation-progress；同一階段內節流，換階段、最後一步或帶有訊息時一定送出
+    fn progress(&self, phase: &str, current: u64, total: Option<u64>, message: Option<String>) {
+        let mut last = self.last_progress.lock().unwrap();
+        let due = match &*last {
+            Some((last_phase, at)) => last_phase != phase || total == Some(current) || message.is_some() || at.elapsed() >= PROGRESS_INTERVAL,
+            None => true,
+        };
+        if !due { return; }
+        *last = Some((phase.to_string(), Instant::now()));
+        let _ = self.app.emit("operation-progress", OperationProgress {
+            id: self.id.clone(),
+            kind: self.kind,
+            path: self.path.clone(),
+            phase: phase.to_string(),
+            current,
+            total,
+            message,
+        });
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        let finished = self.app.state::<RunningOperations>().0.lock().unwrap().finish(&self.id);
+        if let Some(operation) = finished {
+            let _ = self.app.emit("operation", OperationEvent { operation, state: OperationState::Finished });
+        }
+    }
+}
+
+// id 為指令的 operation_id 參數；要取消操作的呼叫端必須帶入 (指令回傳時操作已結束)
+fn begin_operation(app: &AppHandle, kind: OperationKind, path: Option<&str>, id: Option<String>) -> Result<OperationGuard, String> {
+    let (operation, cancel) = app.state::<RunningOperations>().0.lock().unwrap().begin(kind, path, id)?;
+    let guard = OperationGuard {
+        app: app.clone(),
+        id: operation.id.clone(),
+        kind,
+        path: operation.path.clone(),
+        cancel,
+        last_progress: Mutex::new(None),
+    };
+    let _ = app.emit("operation", OperationEvent { operation, state: OperationState::Started });
+    Ok(guard)
+}
+
+#[tauri::command]
+fn list_operations(operations: State<'_, RunningOperations>) -> Vec<OperationInfo> {
+    operations.0.lock().unwrap().list()
+}
+
+// 要求停止操作；操作在目前的步驟 / 區塊 / 迭代結束後停止並回傳已完成的部分 (或錯誤)
+#[tauri::command]
+fn cancel_operation(op_id: String, operations: State<'_, RunningOperations>) -> Result<(), String> {
+    operations.0.lock().unwrap().cancel(&op_id)
+}
+
+#[tauri::command]
+fn get_test_results(cursor: Option<u64>, limit: Option<usize>, records: State<'_, TestRecords>) -> Page<TestSuite> {
+    records.0.lock().unwrap().page(PageRequest { cursor, limit })
+}
+
+#[tauri::command]
+fn clear_test_results(records: State<'_, TestRecords>) {
+    records.0.lock().unwrap().clear();
+}
+
+// 把累積的序列與壓力測試結果輸出成 JUnit XML 或 HTML 摘要 (見 test_report.rs)
+#[tauri::command]
+async fn export_test_report(format: TestReportFormat, path: String, records: State<'_, TestRecords>) -> Result<(), String> {
+    let suites = records.0.lock().unwrap().suites();
+    test_report::write(&path, format, &suites)
+}
+
+#[tauri::command]
+async fn headset_get_status(
+    path: String,
+    protocol: Option<String>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<HeadsetStatus, String> {
+    with_exclusive_device(&manager_state, &path, |dev, meta| {
+        let plugin = protocols::resolve(protocol.as_deref(), &meta.identity)?;
+        let headset = plugin.headset().ok_or("此協定不支援耳機控制")?;
+        let len = meta.report_sizes.len_of(ReportKind::Output, 0).unwrap_or(64);
+        headset.query_status(&HidTransport::new(dev, len))
+    })
+}
+
+#[tauri::command]
+async fn headset_set_sidetone(
+    path: String,
+    protocol: Option<String>,
+    level: u8,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    with_exclusive_device(&manager_state, &path, |dev, meta| {
+        let plugin = protocols::resolve(protocol.as_deref(), &meta.identity)?;
+        let headset = plugin.headset().ok_or("此協定不支援耳機控制")?;
+        let len = meta.report_sizes.len_of(ReportKind::Output, 0).unwrap_or(64);
+        headset.set_sidetone(&HidTransport::new(dev, len), level)
+    })
+}
+
+// 以燈光控制插件操作設備；zone 必須是插件列出的區域
+fn with_lighting<T>(
+    manager_state: &DeviceManager,
+    path: &str,
+    protocol: Option<&str>,
+    zone: Option<u8>,
+    f: impl FnOnce(&dyn LightingControl, &dyn Transport) -> Result<T, String>,
+) -> Result<T, String> {
+    with_exclusive_device(manager_state, path, |dev, meta| {
+        let plugin = protocols::resolve(protocol, &meta.identity)?;
+        let lighting = plugin.lighting().ok_or("此協定不支援燈光控制")?;
+        if let Some(id) = zone.filter(|id| !lighting.zones().iter().any(|z| z.id == *id)) {
+            return Err(format!("此設備沒有燈光區域 {:#04x}", id));
+        }
+        let len = meta.report_sizes.len_of(ReportKind::Output, 0).unwrap_or(64);
+        f(lighting, &HidTransport::new(dev, len))
+    })
+}
+
+#[tauri::command]
+async fn lighting_get_zones(path: String, protocol: Option<String>, manager_state: State<'_, DeviceManager>) -> Result<Vec<LightingZone>, String> {
+    with_lighting(&manager_state, &path, protocol.as_deref(), None, |lighting, _| Ok(lighting.zones()))
+}
+
+#[tauri::command]
+async fn lighting_set_color(
+    path: String,
+    protocol: Option<String>,
+    zone: Option<u8>,
+    color: Rgb,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    with_lighting(&manager_state, &path, protocol.as_deref(), zone, |lighting, t| lighting.set_color(t, zone, color))
+}
+
+// brightness 為 0..=100
+#[tauri::command]
+async fn lighting_set_brightness(
+    path: String,
+    protocol: Option<String>,
+    zone: Option<u8>,
+    brightness: u8,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    if brightness > 100 { return Err("亮度需介於 0..=100".into()); }
+    with_lighting(&manager_state, &path, protocol.as_deref(), zone, |lighting, t| lighting.set_brightness(t, zone, brightness))
+}
+
+#[tauri::command]
+async fn lighting_set_effect(
+    path: String,
+    protocol: Option<String>,
+    zone: Option<u8>,
+    effect: LightingEffect,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    with_lighting(&manager_state, &path, protocol.as_deref(), zone, |lighting, t| lighting.set_effect(t, zone, &effect))
+}
+
+fn sony_model(meta: &DeviceMeta) -> Result<SonyModel, String> {
+    SonyModel::detect(&meta.identity).ok_or("此設備不是 DualShock 4 / DualSense".into())
+}
+
+// 設定 DualShock 4 / DualSense 的震動、燈條、玩家指示燈與自適應扳機 (見 sony.rs)
+#[tauri::command]
+fn sony_set_output(path: String, output: SonyOutput, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    let m_dev = manager_state.get(&path)?;
+    let model = sony_model(&m_dev.meta)?;
+    let report = sony::build_output(model, Connection::detect(model, &m_dev.meta.report_sizes), &output)?;
+    m_dev.write(report, OutputMethod::Interrupt).map(|_| ())
+}
+
+// 藍牙連線時讀取校正資料，讓設備改送完整的 input report；USB 連線不需要
+#[tauri::command]
+async fn sony_enable_full_reports(path: String, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    with_exclusive_device(&manager_state, &path, |dev, meta| {
+        let model = sony_model(meta)?;
+        if Connection::detect(model, &meta.report_sizes) == Connection::Usb { return Ok(()); }
+        let mut buf = vec![0u8; sony::BT_FULL_REPORT_FEATURE_LEN];
+        buf[0] = sony::BT_FULL_REPORT_FEATURE;
+        dev.get_feature_report(&mut buf).map_err(|e| format!("讀取 feature report 失敗: {}", e))?;
+        Ok(())
+    })
+}
+
+// 送出一個 CTAPHID 指令 (FIDO2 / U2F)，cmd 不含 0x80 旗標 (例: 0x01 PING, 0x10 CBOR)
+#[tauri::command]
+async fn ctap_send(
+    path: String,
+    cmd: u8,
+    payload: Vec<u8>,
+    timeout_ms: Option<u64>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<CtapResponse, String> {
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(3000));
+    with_exclusive_device(&manager_state, &path, |dev, _| {
+        ctaphid::send(&HidTransport::new(dev, ctaphid::FRAME_LEN), cmd, &payload, timeout)
+    })
+}
+
+// CCID over HID 共用：每個 report 的長度取 descriptor 中的 output report 長度
+fn with_ccid<T>(
+    manager_state: &DeviceManager,
+    path: &str,
+    f: impl FnOnce(&dyn Transport, usize) -> Result<T, String>,
+) -> Result<T, String> {
+    with_exclusive_device(manager_state, path, |dev, meta| {
+        let report_len = meta.report_sizes.len_of(ReportKind::Output, 0).unwrap_or(64);
+        f(&HidTransport::new(dev, report_len), report_len)
+    })
+}
+
+// 啟動讀卡機上的卡片並回傳 ATR
+#[tauri::command]
+async fn ccid_power_on(
+    path: String,
+    slot: Option<u8>,
+    voltage: Option<Voltage>,
+    timeout_ms: Option<u64>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<Vec<u8>, String> {
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(3000));
+    with_ccid(&manager_state, &path, |t, len| {
+        ccid::power_on(t, len, slot.unwrap_or(0), voltage.unwrap_or_default(), timeout)
+    })
+}
+
+// 傳送一個 APDU (需先 ccid_power_on)
+#[tauri::command]
+async fn ccid_transmit_apdu(
+    path: String,
+    apdu: Vec<u8>,
+    slot: Option<u8>,
+    timeout_ms: Option<u64>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<ApduResponse, String> {
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(3000));
+    with_ccid(&manager_state, &path, |t, len| ccid::transmit_apdu(t, len, slot.unwrap_or(0), &apdu, timeout))
+}
+
+// HID++ 指令共用：device_index 預設 0xFF (直接連接)，接收器上的設備為 1..=6
+fn with_hidpp<T>(
+    manager_state: &DeviceManager,
+    path: &str,
+    device_index: Option<u8>,
+    f: impl FnOnce(&Hidpp) -> Result<T, String>,
+) -> Result<T, String> {
+    with_exclusive_device(manager_state, path, |dev, meta| {
+        let short = meta.report_sizes.len_of(ReportKind::Output, 0x10).is_some();
+        let timeout = Duration::from_millis(DEFAULT_RESPONSE_TIMEOUT_MS as u64);
+        f(&Hidpp::new(dev, device_index.unwrap_or(hidpp::DEVICE_DIRECT), short, timeout))
+    })
+}
+
+#[tauri::command]
+async fn hidpp_get_info(path: String, device_index: Option<u8>, manager_state: State<'_, DeviceManager>) -> Result<HidppInfo, String> {
+    with_hidpp(&manager_state, &path, device_index, |h| h.info())
+}
+
+#[tauri::command]
+async fn hidpp_get_battery(path: String, device_index: Option<u8>, manager_state: State<'_, DeviceManager>) -> Result<BatteryInfo, String> {
+    with_hidpp(&manager_state, &path, device_index, |h| h.battery())
+}
+
+#[tauri::command]
+async fn hidpp_get_dpi(
+    path: String,
+    device_index: Option<u8>,
+    sensor: Option<u8>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<DpiInfo, String> {
+    with_hidpp(&manager_state, &path, device_index, |h| h.dpi(sensor.unwrap_or(0)))
+}
+
+// QMK / VIA 指令共用：必須是鍵盤的 raw HID 介面
+fn with_via<T>(
+    manager_state: &DeviceManager,
+    path: &str,
+    f: impl FnOnce(&HidTransport) -> Result<T, String>,
+) -> Result<T, String> {
+    with_exclusive_device(manager_state, path, |dev, meta| {
+        if meta.identity.usage_page != qmk_via::USAGE_PAGE_RAW_HID {
+            return Err("此介面不是 QMK raw HID (usage page 0xFF60)".into());
+        }
+        f(&HidTransport::new(dev, qmk_via::REPORT_LEN))
+    })
+}
+
+#[tauri::command]
+async fn qmk_get_info(path: String, manager_state: State<'_, DeviceManager>) -> Result<ViaInfo, String> {
+    with_via(&manager_state, &path, |t| qmk_via::info(t))
+}
+
+#[tauri::command]
+async fn qmk_get_keycode(path: String, layer: u8, row: u8, col: u8, manager_state: State<'_, DeviceManager>) -> Result<Keycode, String> {
+    with_via(&manager_state, &path, |t| qmk_via::get_keycode(t, layer, row, col))
+}
+
+#[tauri::command]
+async fn qmk_set_keycode(
+    path: String,
+    layer: u8,
+    row: u8,
+    col: u8,
+    keycode: u16,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    with_via(&manager_state, &path, |t| qmk_via::set_keycode(t, layer, row, col, keycode))
+}
+
+// 矩陣大小 (rows / cols) 需由鍵盤的 VIA 定義檔提供
+#[tauri::command]
+async fn qmk_get_layer(
+    path: String,
+    layer: u8,
+    rows: u8,
+    cols: u8,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<Vec<Vec<Keycode>>, String> {
+    with_via(&manager_state, &path, |t| qmk_via::get_layer(t, layer, rows, cols))
+}
+
+#[tauri::command]
+async fn telephony_set_leds(
+    path: String,
+    leds: TelephonyLeds,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    let meta = manager_state.get(&path)?.meta;
+    let layout = meta.descriptor.as_ref().and_then(TelephonyLayout::from_descriptor);
+    let report = layout.ok_or("此設備不是 telephony 裝置")?.build_led_report(&leds)?;
+
+    with_exclusive_device(&manager_state, &path, |dev, _| {
+        dev.write(&report).map(|_| ()).map_err(|e| format!("寫入失敗: {}", e))
+    })
+}
+
+// 讀取秤重，直到連續數筆穩定的相同讀值或逾時
+#[tauri::command]
+async fn read_weight(
+    path: String,
+    timeout_ms: Option<u64>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<ScaleReading, String> {
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms.unwrap_or(3000));
+    with_exclusive_device(&manager_state, &path, |dev, meta| {
+        if meta.identity.usage_page != scale::PAGE_SCALE {
+            return Err("此設備不是 HID 電子秤".into());
+        }
+        let mut detector = scale::StableDetector::new();
+        let mut last = None;
+        let mut buf = vec![0u8; meta.report_sizes.input_buffer_len()];
+        while Instant::now() < deadline {
+            let n = dev.read_timeout(&mut buf, 100).map_err(|e| format!("讀取異常: {}", e))?;
+            let Some(reading) = scale::decode(&buf[..n]) else { continue };
+            if let Some(stable) = detector.push(reading.clone()) {
+                return Ok(stable);
+            }
+            last = Some(reading);
+        }
+        match last {
+            Some(r) => Err(format!("重量未穩定 (最後讀值 {} {}, {})", r.weight, r.unit, r.status)),
+            None => Err("逾時：沒有收到秤重資料".into()),
+        }
+    })
+}
+
+// 以 feature report 讀取 UPS 的電量、剩餘時間與電壓
+#[tauri::command]
+async fn read_power_status(path: String, manager_state: State<'_, DeviceManager>) -> Result<PowerStatus, String> {
+    with_exclusive_device(&manager_state, &path, |dev, meta| read_power(dev, meta))
+}
+
+fn read_power(dev: &dyn HidIo, meta: &DeviceMeta) -> Result<PowerStatus, String> {
+    let desc = meta.descriptor.as_ref().ok_or("無法取得 report descriptor")?;
+    let layout = PowerLayout::from_descriptor(desc).ok_or("此設備不是 HID Power Device")?;
+    layout.read(dev, desc)
+}
+
+// 每隔 interval_ms 讀取一次電源狀態並送出 power-status 事件，None 停止
+#[tauri::command]
+fn set_power_monitor(path: String, interval_ms: Option<u64>, monitor: State<'_, PowerMonitor>) -> Result<(), String> {
+    let mut monitor = monitor.0.lock().unwrap();
+    match interval_ms {
+        Some(ms) if ms < POWER_MONITOR_MIN_MS => return Err(format!("interval_ms 不能小於 {}", POWER_MONITOR_MIN_MS)),
+        Some(ms) => { monitor.insert(path, (Duration::from_millis(ms), Instant::now())); }
+        None => { monitor.remove(&path); }
+    }
+    Ok(())
+}
+
+fn spawn_power_monitor(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_millis(POWER_MONITOR_TICK_MS));
+        let due: Vec<String> = {
+            let state = app.state::<PowerMonitor>();
+            let mut monitor = state.0.lock().unwrap();
+            let now = Instant::now();
+            monitor.iter_mut()
+                .filter(|(_, (_, next))| *next <= now)
+                .map(|(path, (interval, next))| {
+                    *next = now + *interval;
+                    path.clone()
+                })
+                .collect()
+        };
+        for path in due {
+            let manager_state = app.state::<DeviceManager>();
+            // 設備尚未開啟監聽時略過，等下一輪
+            let Ok(m_dev) = manager_state.get(&path) else { continue };
+            let (status, error) = match with_exclusive_device(&manager_state, &path, read_power) {
+                Ok(status) => (status, None),
+                Err(e) => (PowerStatus::default(), Some(e)),
+            };
+            if let Some(percent) = status.battery_percent {
+                m_dev.stats.lock().unwrap().record_battery(percent.round() as u8);
+            }
+            let _ = app.emit("power-status", PowerEvent { path, status, error });
+        }
+    });
+}
+
+// 以 UDP 即時送出解碼後的數值通道 (格式見 stream.rs)，target 例: "127.0.0.1:9000"
+// channels 未指定時送出所有數值欄位
+#[tauri::command]
+fn start_stream(path: String, target: String, channels: Option<Vec<String>>, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    let m_dev = manager_state.get(&path)?;
+    *m_dev.stream.lock().unwrap() = Some(UdpStream::open(&target, channels)?);
+    Ok(())
+}
+
+#[tauri::command]
+fn stop_stream(path: String, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    let m_dev = manager_state.get(&path)?;
+    m_dev.stream.lock().unwrap().take();
+    Ok(())
+}
+
+#[tauri::command]
+fn get_stream_info(path: String, manager_state: State<'_, DeviceManager>) -> Result<Option<StreamInfo>, String> {
+    let m_dev = manager_state.get(&path)?;
+    let info = m_dev.stream.lock().unwrap().as_ref().map(UdpStream::info);
+    Ok(info)
+}
+
+// 讀取 HID Sensor 的回報間隔、電源狀態等屬性
+#[tauri::command]
+async fn read_sensor_properties(path: String, manager_state: State<'_, DeviceManager>) -> Result<Vec<SensorProperties>, String> {
+    with_exclusive_device(&manager_state, &path, |dev, meta| {
+        let desc = meta.descriptor.as_ref().ok_or("無法取得 report descriptor")?;
+        let layout = SensorLayout::from_descriptor(desc).ok_or("此設備沒有可辨識的 HID 感測器")?;
+        layout.read_properties(dev, desc)
+    })
+}
+
+// unmask 為 true 時才會回傳完整卡號
+#[tauri::command]
+fn start_msr_capture(
+    path: String,
+    mode: MsrMode,
+    unmask: Option<bool>,
+    layouts: State<'_, KeyLayouts>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    let m_dev = manager_state.get(&path)?;
+    let keymap = layouts.0.lock().unwrap().for_device(&path);
+    *m_dev.msr.lock().unwrap() = Some(MsrCapture::new(mode, !unmask.unwrap_or(false), keymap));
+    Ok(())
+}
+
+#[tauri::command]
+fn stop_msr_capture(path: String, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    let m_dev = manager_state.get(&path)?;
+    *m_dev.msr.lock().unwrap() = None;
+    Ok(())
+}
+
+#[tauri::command]
+fn list_layouts(layouts: State<'_, KeyLayouts>) -> Vec<LayoutInfo> {
+    layouts.0.lock().unwrap().list()
+}
+
+#[tauri::command]
+fn load_layout(file: String, layouts: State<'_, KeyLayouts>) -> Result<LayoutInfo, String> {
+    layouts.0.lock().unwrap().load_file(std::path::Path::new(&file))
+}
+
+// 設備不需要已開啟；已開啟時立即套用到鍵盤文字還原與磁條卡擷取，之後開啟的也會套用此配置
+#[tauri::command]
+fn set_layout(
+    path: String,
+    layout: String,
+    layouts: State<'_, KeyLayouts>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    let keymap = layouts.0.lock().unwrap().set_device_layout(&path, &layout)?;
+    if let Ok(m_dev) = manager_state.get(&path) {
+        *m_dev.keymap.lock().unwrap() = keymap.clone();
+        if let Some(capture) = m_dev.msr.lock().unwrap().as_mut() { capture.set_keymap(keymap); }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn load_register_map(file: String, maps: State<'_, RegisterMaps>) -> Result<RegisterMap, String> {
+    let map = RegisterMap::load(&file)?;
+    maps.0.lock().unwrap().insert(map.name.clone(), map.clone());
+    Ok(map)
+}
+
+#[tauri::command]
+fn list_register_maps(maps: State<'_, RegisterMaps>) -> Vec<RegisterMap> {
+    maps.0.lock().unwrap().values().cloned().collect()
+}
+
+fn get_register_map(maps: &RegisterMaps, name: &str) -> Result<RegisterMap, String> {
+    maps.0.lock().unwrap().get(name).cloned().ok_or(format!("尚未載入 register map: {}", name))
+}
+
+#[tauri::command]
+async fn read_register(
+    path: String,
+    map: String,
+    register: String,
+    maps: State<'_, RegisterMaps>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<RegisterValue, String> {
+    let map = get_register_map(&maps, &map)?;
+    with_exclusive_device(&manager_state, &path, |dev, meta| {
+        let len = meta.report_sizes.len_of(ReportKind::Output, 0).unwrap_or(64);
+        map.read(&HidTransport::new(dev, len), &register)
+    })
+}
+
+#[tauri::command]
+async fn write_register(
+    path: String,
+    map: String,
+    register: String,
+    value: f64,
+    maps: State<'_, RegisterMaps>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    let map = get_register_map(&maps, &map)?;
+    with_exclusive_device(&manager_state, &path, |dev, meta| {
+        let len = meta.report_sizes.len_of(ReportKind::Output, 0).unwrap_or(64);
+        map.write(&HidTransport::new(dev, len), &register, value)
+    })
+}
+
+#[tauri::command]
+fn load_schema(file: String, schemas: State<'_, Schemas>) -> Result<ReportSchema, String> {
+    let schema = ReportSchema::load(&file)?;
+    schemas.0.lock().unwrap().insert(schema.name.clone(), Arc::new(schema.clone()));
+    Ok(schema)
+}
+
+#[tauri::command]
+fn list_schemas(schemas: State<'_, Schemas>) -> Vec<ReportSchema> {
+    schemas.0.lock().unwrap().values().map(|s| s.as_ref().clone()).collect()
+}
+
+fn get_schema(schemas: &Schemas, name: &str) -> Result<Arc<ReportSchema>, String> {
+    schemas.0.lock().unwrap().get(name).cloned().ok_or(format!("尚未載入 schema: {}", name))
+}
+
+// 設定設備的封包格式 (見 framing.rs)，None 取消；之後的指令自動加上 frame，收到的 report 以 hid-frame 事件回報 payload
+#[tauri::command]
+fn set_device_framing(path: String, profile: Option<FramingProfile>, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    if let Some(p) = &profile { p.validate()?; }
+    let m_dev = manager_state.get(&path)?;
+    *m_dev.framing.lock().unwrap() = profile.map(Arc::new);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_device_framing(path: String, manager_state: State<'_, DeviceManager>) -> Result<Option<FramingProfile>, String> {
+    let m_dev = manager_state.get(&path)?;
+    let profile = m_dev.framing.lock().unwrap().as_deref().cloned();
+    Ok(profile)
+}
+
+// 指定設備的 input report 以 schema 解碼 (schema-fields / report-loss 事件)，None 取消
+#[tauri::command]
+fn set_device_schema(
+    path: String,
+    schema: Option<String>,
+    schemas: State<'_, Schemas>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<(), String> {
+    let schema = schema.map(|name| get_schema(&schemas, &name)).transpose()?;
+    let m_dev = manager_state.get(&path)?;
+    *m_dev.schema.lock().unwrap() = schema;
+    Ok(())
+}
+
+// 設定某型號各韌體版本使用的 schema，下次開啟設備時自動套用；空清單代表移除
+#[tauri::command]
+fn set_schema_versions(
+    app: AppHandle,
+    vendor_id: u16,
+    product_id: u16,
+    rules: Vec<SchemaVersionRule>,
+    versions: State<'_, SchemaVersions>,
+    schemas: State<'_, Schemas>,
+) -> Result<(), String> {
+    for rule in &rules {
+        get_schema(&schemas, &rule.schema)?;
+    }
+    let mut versions = versions.0.lock().unwrap();
+    if rules.is_empty() {
+        versions.remove(&(vendor_id, product_id));
+    } else {
+        versions.insert((vendor_id, product_id), rules);
+    }
+    schema::save_version_rules(&config_dir(&app)?.join("schema_versions.json"), &versions)
+}
+
+#[tauri::command]
+fn get_firmware_version(path: String, manager_state: State<'_, DeviceManager>) -> Result<String, String> {
+    with_exclusive_device(&manager_state, &path, query_firmware_version)
+}
+
+// 驗證模式 (ListenOptions.validate) 累計的違規次數
+#[tauri::command]
+fn get_schema_violations(path: String, manager_state: State<'_, DeviceManager>) -> Result<Vec<ViolationCount>, String> {
+    let m_dev = manager_state.get(&path)?;
+    let summary = m_dev.violations.lock().unwrap().summary();
+    Ok(summary)
+}
+
+#[tauri::command]
+fn reset_schema_violations(path: String, manager_state: State<'_, DeviceManager>) -> Result<(), String> {
+    manager_state.get(&path)?.violations.lock().unwrap().reset();
+    Ok(())
+}
+
+// 以 schema 解碼一筆資料 (console / 匯出用)
+#[tauri::command]
+fn decode_with_schema(schema: String, data: Vec<u8>, schemas: State<'_, Schemas>) -> Result<SchemaDecoded, String> {
+    get_schema(&schemas, &schema)?.decode(&data).ok_or("schema 中沒有對應此 Report ID 的定義".into())
+}
+
+// 由擷取的樣本推測 schema 草稿；report_ids 未指定時自動判斷第 0 個 byte 是否為 Report ID
+#[tauri::command]
+fn infer_schema(name: String, samples: Vec<InferSample>, report_ids: Option<bool>) -> Result<SchemaDraft, String> {
+    inference::infer(&name, &samples, report_ids)
+}
+
+// 由已載入的 schema 產生 C 標頭檔或 Rust 模組的原始碼
+#[tauri::command]
+fn generate_schema_code(schema: String, language: CodeLanguage, schemas: State<'_, Schemas>) -> Result<String, String> {
+    let schema = get_schema(&schemas, &schema)?;
+    codegen::generate(&schema, language)
+}
+
+// 解碼事件時間戳的來源 (monotonic / wall / both)
+#[tauri::command]
+fn set_timestamp_source(source: TimestampSource, clock: State<'_, CaptureClock>) {
+    clock.set_source(source);
+}
+
+// 匯出檔標頭用的時鐘資訊
+#[tauri::command]
+fn get_clock_metadata(clock: State<'_, CaptureClock>) -> ClockMetadata {
+    clock.metadata()
+}
+
+#[tauri::command]
+fn set_naming_template(
+    template: String,
+    directory: Option<String>,
+    naming: State<'_, CaptureNaming>,
+) -> Result<(), String> {
+    naming::validate(&template)?;
+    let mut config = naming.0.lock().unwrap();
+    config.template = template;
+    config.directory = directory.map(std::path::PathBuf::from);
+    Ok(())
+}
+
+// alias 為 None 時清除
+#[tauri::command]
+fn set_device_alias(path: String, alias: Option<String>, naming: State<'_, CaptureNaming>) {
+    let mut config = naming.0.lock().unwrap();
+    match alias {
+        Some(alias) => config.aliases.insert(path, alias),
+        None => config.aliases.remove(&path),
+    };
+}
+
+// 依命名樣板產生下一個擷取 / 匯出檔案的完整路徑 (目錄不存在時自動建立)
+#[tauri::command]
+fn resolve_capture_name(
+    app: AppHandle,
+    path: Option<String>,
+    extension: String,
+    naming: State<'_, CaptureNaming>,
+    manager_state: State<'_, DeviceManager>,
+) -> Result<String, String> {
+    let config = naming.0.lock().unwrap();
+    let identity = path.as_deref().and_then(|p| manager_state.get(p).ok()).map(|m_dev| m_dev.meta.identity);
+    // 沒有別名時以 VID/PID 代替
+    let alias = path.as_ref()
+        .and_then(|p| config.aliases.get(p).cloned())
+        .or_else(|| identity.map(|id| format!("{:04x}-{:04x}", id.vendor_id, id.product_id)))
+        .unwrap_or_else(|| "capture".into());
+    let ctx = NamingContext {
+        alias,
+        vendor_id: identity.map(|id| id.vendor_id),
+        product_id: identity.map(|id| id.product_id),
+    };
+
+    // 相對路徑以資料目錄為基準，可攜模式下擷取檔因此跟著隨身碟移動
+    let dir = match &config.directory {
+        Some(dir) if dir.is_absolute() => dir.clone(),
+        Some(dir) => data_dir(&app)?.join(dir),
+        None => data_dir(&app)?.join("captures"),
+    };
+    std::fs::create_dir_all(&dir).map_err(|e| format!("建立目錄 {} 失敗: {}", dir.display(), e))?;
+    let file = naming::resolve(&config.template, &dir, &ctx, &extension)?;
+    Ok(file.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+fn set_post_capture_hook(hook: Option<PostCaptureHook>, state: State<'_, CaptureHook>) {
+    *state.0.lock().unwrap() = hook;
+}
+
+// 把各通道數值輸出成 .mat (MATLAB v5) 或 .npz (NumPy)
+#[tauri::command]
+async fn export_channels(app: AppHandle, file: String, format: ExportFormat, data: ChannelData) -> Result<(), String> {
+    export::write(&file, format, &data, &app.state::<CaptureClock>().metadata())
+}
+
+// 擷取 / 匯出完成時呼叫；hook 在背景執行，結果以 capture-hook 事件回報
+#[tauri::command]
+fn capture_finished(app: AppHandle, file: String) {
+    run_capture_hook(&app, file);
+}
+
+fn run_capture_hook(app: &AppHandle, file: String) {
+    let Some(hook) = app.state::<CaptureHook>().0.lock().unwrap().clone() else { return };
+    let app = app.clone();
+    thread::spawn(move || {
+        let result = hooks::run(&hook, &file);
+        // 移動後原路徑的註記已不存在
+        app.state::<Annotations>().0.lock().unwrap().forget(&file);
+        let _ = app.emit("capture-hook", result);
+    });
+}
+
+// --- 流量擷取 (見 capture.rs) ---
+
+// 開始把送出與收到的 report 寫入 file (不可已存在)；path_filter 為 None 時記錄所有設備
+// filter 為過濾運算式 (見 capture_filter.rs)，格式錯誤時不會開始擷取
+#[tauri::command]
+fn start_capture(
+    app: AppHandle,
+    path_filter: Option<String>,
+    filter: Option<String>,
+    file: String,
+    captures: State<'_, Captures>,
+) -> Result<CaptureInfo, String> {
+    let filter = filter.filter(|f| !f.trim().is_empty()).map(CaptureFilter::try_from).transpose()?;
+    let clock = app.state::<CaptureClock>().metadata();
+    let info = captures.0.lock().unwrap().start(path_filter, filter, &file, clock)?;
+    app.state::<Annotations>().0.lock().unwrap().forget(&info.file);
+    app.state::<History>().0.lock().unwrap().forget(&info.file);
+    logging::log(&app, Severity::Info, Category::Capture, info.path_filter.as_deref(), format!("開始擷取 {}: {}", info.id, info.file));
+    Ok(info)
+}
+
+// 寫入結尾並關閉檔案，完成後執行 post-capture hook；只有一個擷取進行中時可省略 session_id
+#[tauri::command]
+async fn stop_capture(app: AppHandle, session_id: Option<String>, captures: State<'_, Captures>) -> Result<CaptureInfo, String> {
+    let capture = captures.0.lock().unwrap().remove(session_id.as_deref())?;
+    let info = finish_capture(&app, capture);
+    match &info.error {
+        Some(e) => Err(format!("擷取 {} 已停止，但檔案不完整: {}", info.id, e)),
+        None => Ok(info),
+    }
+}
+
+fn finish_capture(app: &AppHandle, capture: capture::ActiveCapture) -> CaptureInfo {
+    let info = capture.finish();
+    let (severity, message) = match &info.error {
+        Some(e) => (Severity::Error, format!("擷取 {} 結束，寫檔失敗: {}", info.id, e)),
+        None => (Severity::Info, format!("擷取 {} 結束: {} 個 frame，丟棄 {} 個", info.id, info.frames, info.dropped)),
+    };
+    logging::log(app, severity, Category::Capture, info.path_filter.as_deref(), message);
+    run_capture_hook(app, info.file.clone());
+    info
+}
+
+#[tauri::command]
+fn list_captures(captures: State<'_, Captures>) -> Vec<CaptureInfo> {
+    captures.0.lock().unwrap().list()
+}
+
+// 依 offset 取出一頁 frame (見 capture_history.rs)；session 為 session ID 或擷取檔路徑，錄製中的擷取也可查詢
+// filter 為過濾運算式 (見 capture_filter.rs)，offset 與 total 都以符合條件的 frame 計算
+#[tauri::command]
+async fn get_capture_page(
+    session: String,
+    offset: usize,
+    limit: Option<usize>,
+    filter: Option<String>,
+    captures: State<'_, Captures>,
+    history: State<'_, History>,
+) -> Result<CapturePage, String> {
+    let file = captures.0.lock().unwrap().resolve(&session)?;
+    let filter = filter.filter(|f| !f.trim().is_empty()).map(CaptureFilter::try_from).transpose()?;
+    history.0.lock().unwrap().page(&file, filter, offset, limit.unwrap_or(paging::DEFAULT_PAGE_SIZE))
+}
+
+// 目前為止的 frame 總數 (虛擬捲動的高度)
+#[tauri::command]
+async fn get_capture_count(
+    session: String,
+    filter: Option<String>,
+    captures: State<'_, Captures>,
+    history: State<'_, History>,
+) -> Result<CaptureCount, String> {
+    let file = captures.0.lock().unwrap().resolve(&session)?;
+    let filter = filter.filter(|f| !f.trim().is_empty()).map(CaptureFilter::try_from).transpose()?;
+    history.0.lock().unwrap().count(&file, filter)
+}
+
+// 在擷取的某個 frame 加上註記或書籤 (見 annotations.rs)，取代原有的註記；session 為 session ID 或擷取檔路徑
+// text 為空且 bookmark 不是 true 時移除該 frame 的註記並回傳 None
+#[tauri::command]
+fn annotate_frame(
+    session: String,
+    index: u64,
+    text: String,
+    bookmark: Option<bool>,
+    captures: State<'_, Captures>,
+    annotations: State<'_, Annotations>,
+) -> Result<Option<Annotation>, String> {
+    let file = captures.0.lock().unwrap().resolve(&session)?;
+    annotations.0.lock().unwrap().annotate(&file, index, &text, bookmark.unwrap_or(false))
+}
+
+// 依 frame 序號排序
+#[tauri::command]
+fn list_annotations(
+    session: String,
+    bookmarks_only: Option<bool>,
+    captures: State<'_, Captures>,
+    annotations: State<'_, Annotations>,
+) -> Result<Vec<Annotation>, String> {
+    let file = captures.0.lock().unwrap().resolve(&session)?;
+    annotations.0.lock().unwrap().list(&file, bookmarks_only.unwrap_or(false))
+}
+
+// 設定常駐流量記錄 (見 traffic_log.rs) 並立即套用，設定會保存，下次啟動時自動繼續
+// max_size 為單一檔案的位元組上限，rotate_minutes 另外依時間輪替
+#[tauri::command]
+fn set_logging(
+    app: AppHandle,
+    enabled: bool,
+    dir: Option<String>,
+    max_files: Option<usize>,
+    max_size: Option<u64>,
+    rotate_minutes: Option<u64>,
+    filter: Option<String>,
+) -> Result<TrafficLogStatus, String> {
+    let config = TrafficLogConfig {
+        enabled,
+        dir: dir.filter(|d| !d.is_empty()).map(PathBuf::from),
+        max_files: max_files.unwrap_or(traffic_log::DEFAULT_MAX_FILES),
+        max_size: max_size.unwrap_or(traffic_log::DEFAULT_MAX_SIZE),
+        rotate_minutes,
+        filter: filter.filter(|f| !f.trim().is_empty()).map(CaptureFilter::try_from).transpose()?,
+    };
+    config.validate()?;
+    apply_traffic_log(&app, &config)?;
+    config.save(&config_dir(&app)?.join("traffic_log.json"))?;
+    *app.state::<TrafficLog>().0.lock().unwrap() = config;
+    get_logging(app)
+}
+
+#[tauri::command]
+fn get_logging(app: AppHandle) -> Result<TrafficLogStatus, String> {
+    let config = app.state::<TrafficLog>().0.lock().unwrap().clone();
+    let dir = config.resolve_dir(&data_dir(&app)?);
+    Ok(TrafficLogStatus {
+        active: app.state::<Captures>().0.lock().unwrap().traffic_log_active(),
+        directory: dir.to_string_lossy().to_string(),
+        files: traffic_log::list_files(&dir),
+        config,
+    })
+}
+
+// 停止目前的記錄，啟用時以新的設定重新開始
+fn apply_traffic_log(app: &AppHandle, config: &TrafficLogConfig) -> Result<(), String> {
+    let previous = app.state::<Captures>().0.lock().unwrap().take_traffic_log();
+    if let Some(info) = previous.map(capture::ActiveCapture::finish) {
+        if let Some(e) = info.error {
+            logging::log(app, Severity::Warning, Category::Capture, None, format!("流量記錄寫檔失敗: {}", e));
+        }
+    }
+    if !config.enabled { return Ok(()); }
+    let rotation = config.rotation(&data_dir(app)?);
+    let clock = app.state::<CaptureClock>().metadata();
+    app.state::<Captures>().0.lock().unwrap().start_traffic_log(rotation, config.filter.clone(), clock)?;
+    logging::log(app, Severity::Info, Category::Capture, None, "開始常駐流量記錄");
+    Ok(())
+}
+
+// 轉成 Wireshark 可開啟的 pcapng (見 pcapng.rs)；session 為 session ID 或擷取檔路徑
+#[tauri::command]
+async fn export_capture_pcapng(session: String, file: String, captures: State<'_, Captures>) -> Result<PcapExport, String> {
+    let capture_file = captures.0.lock().unwrap().resolve(&session)?;
+    pcapng::write(&capture_file, &file)
+}
+
+// 每個 report 一列輸出成 CSV (見 capture_csv.rs)；設備仍開啟時附上解碼欄位
+#[tauri::command]
+async fn export_capture_csv(app: AppHandle, session_id: String, file: String, options: Option<CsvOptions>) -> Result<CsvExport, String> {
+    let options = options.unwrap_or_default();
+    let capture_file = app.state::<Captures>().0.lock().unwrap().resolve(&session_id)?;
+    let named = options.schema.as_deref().map(|name| get_schema(&app.state::<Schemas>(), name)).transpose()?;
+    let manager = app.state::<DeviceManager>();
+    let decoder_for = |path: &str| {
+        let Ok(m_dev) = manager.get(path) else {
+            return DeviceDecoder { schema: named.clone(), ..Default::default() };
+        };
+        DeviceDecoder {
+            uses_report_ids: Some(m_dev.meta.report_sizes.uses_report_ids),
+            schema: named.clone().or_else(|| m_dev.schema.lock().unwrap().clone()),
+            descriptor: m_dev.meta.descriptor.clone(),
+        }
+    };
+    capture_csv::write(&capture_file, &file, &options, decoder_for)
+}
+
+// 比較兩個擷取檔 (見 capture_diff.rs)；a、b 為 session ID 或擷取檔路徑，a 為基準
+#[tauri::command]
+async fn diff_captures(a: String, b: String, options: Option<DiffOptions>, captures: State<'_, Captures>) -> Result<DiffResult, String> {
+    let (file_a, file_b) = {
+        let recorder = captures.0.lock().unwrap();
+        (recorder.resolve(&a)?, recorder.resolve(&b)?)
+    };
+    capture_diff::diff(&file_a, &file_b, &options.unwrap_or_default())
+}
+
+// 依擷取檔把送往設備的 report 重新送往 path 並比較回覆 (見 replay.rs)；speed_factor 預設 1 (原速)
+#[tauri::command]
+async fn replay_session(
+    app: AppHandle,
+    file: String,
+    path: String,
+    speed_factor: Option<f64>,
+    options: Option<ReplayOptions>,
+) -> Result<ReplayResult, String> {
+    let options = options.unwrap_or_default();
+    check_station_lock(&app, options.station_token.as_deref())?;
+    let speed_factor = speed_factor.unwrap_or(1.0);
+    if !speed_factor.is_finite() || speed_factor < 0.0 { return Err("speed_factor 需為 0 以上的數字".into()); }
+    let plan = replay::plan(&file, options.source_path.as_deref())?;
+    let m_dev = app.state::<DeviceManager>().get(&path)?;
+    let op = begin_operation(&app, OperationKind::Replay, Some(&path), options.operation_id.clone())?;
+    count_feature(&app, "replay");
+
+    let mut result = ReplayResult { session: plan.session, source_path: plan.source, total: plan.total, ..Default::default() };
+    let started = Instant::now();
+    let mut next_at = started;
+    for step in plan.steps {
+        let step = step?;
+        // 錄製時的間隔可能很長，分段等待以便取消
+        next_at += replay::scaled(step.delay, speed_factor);
+        while let Some(wait) = next_at.checked_duration_since(Instant::now()) {
+            if op.cancelled() { break; }
+            thread::sleep(wait.min(Duration::from_millis(100)));
+        }
+        if op.cancelled() { break; }
+        result.sent += 1;
+        op.progress("replay", result.sent as u64, Some(result.total as u64), None);
+        let divergence = |kind: DivergenceKind, actual: Option<Vec<u8>>, error: Option<String>| Divergence {
+            index: step.index,
+            kind,
+            sent: step.report.clone(),
+            expected: step.expected.clone(),
+            actual,
+            error,
+        };
+        let Some(expected) = &step.expected else {
+            if let Err(e) = m_dev.write(step.report.clone(), OutputMethod::Interrupt) {
+                m_dev.stats.lock().unwrap().record_error();
+                result.record(divergence(DivergenceKind::Error, None, Some(e)));
+            }
+            continue;
+        };
+        result.compared += 1;
+        let recorded_ms = i32::try_from(step.response_delay.as_millis()).unwrap_or(i32::MAX);
+        let timeout_ms = options.timeout_ms.unwrap_or(recorded_ms.saturating_add(DEFAULT_RESPONSE_TIMEOUT_MS));
+        match m_dev.exchange(step.report.clone(), OutputMethod::Interrupt, timeout_ms, None) {
+            Ok(actual) if actual.is_empty() => result.record(divergence(DivergenceKind::Missing, None, None)),
+            Ok(actual) if replay::same_response(expected, &actual, &options.ignore_offsets) => result.matched += 1,
+            Ok(actual) => result.record(divergence(DivergenceKind::Mismatch, Some(actual), None)),
+            Err(e) => {
+                m_dev.stats.lock().unwrap().record_error();
+                result.record(divergence(DivergenceKind::Error, None, Some(e)));
+            }
+        }
+    }
+    result.elapsed_ms = started.elapsed().as_millis() as u64;
+    result.cancelled = op.cancelled();
+    let message = format!("重播 {}: 送出 {} / {}，回覆相符 {} / {}，差異 {}", file, result.sent, result.total, result.matched, result.compared, result.divergence_count);
+    logging::log(&app, Severity::Info, Category::Capture, Some(&path), message);
+    Ok(result)
+}
+
+// 讀出十六進位傾印檔中的 payload (見 hexdump.rs)，不送出
+#[tauri::command]
+fn parse_hex_file(file: String) -> Result<Vec<HexPayload>, String> {
+    hexdump::read(&file)
+}
+
+// 依序送出十六進位傾印檔中的每個 payload；payload 依寫入選項組成 output report (與 send_hid_command 相同)
+#[tauri::command]
+async fn send_from_file(app: AppHandle, path: String, file: String, options: Option<SendFileOptions>) -> Result<SendFileResult, String> {
+    let options = options.unwrap_or_default();
+    let payloads = hexdump::read(&file)?;
+    let m_dev = app.state::<DeviceManager>().get(&path)?;
+    let op = begin_operation(&app, OperationKind::SendFile, Some(&path), options.operation_id.clone())?;
+    count_feature(&app, "send_from_file");
+    let timeout_ms = options.timeout_ms.unwrap_or(DEFAULT_RESPONSE_TIMEOUT_MS);
+
+    let started = Instant::now();
+    let mut result = SendFileResult { total: payloads.len(), sent: 0, failed: 0, entries: Vec::new(), elapsed_ms: 0, cancelled: false };
+    for (i, payload) in payloads.into_iter().enumerate() {
+        if op.cancelled() { break; }
+        if i > 0 && options.interval_ms > 0 { thread::sleep(Duration::from_millis(options.interval_ms)); }
+        let report = match build_device_report(&m_dev, &payload.data, &options.write) {
+            Ok(report) => report,
+            Err(e) => return Err(format!("第 {} 行: {}", payload.line, e)),
+        };
+        let outcome = if options.wait_response {
+            m_dev.exchange(report, options.write.method, timeout_ms, None).and_then(|resp| match resp.is_empty() {
+                true => Err(format!("{} ms 內沒有收到回覆", timeout_ms)),
+                false => unframe_response(&m_dev, resp),
+            })
+        } else {
+            m_dev.write(report, options.write.method).map(|_| Vec::new())
+        };
+        result.sent += 1;
+        let (response, error) = match outcome {
+            Ok(response) => (response, None),
+            Err(e) => {
+                m_dev.stats.lock().unwrap().record_error();
+                result.failed += 1;
+                (Vec::new(), Some(e))
+            }
+        };
+        op.progress("send", result.sent as u64, Some(result.total as u64), error.clone());
+        let stop = error.is_some() && options.stop_on_error;
+        result.entries.push(SendFileEntry { line: payload.line, sent: payload.data, response, error });
+        if stop { break; }
+    }
+    result.elapsed_ms = started.elapsed().as_millis() as u64;
+    result.cancelled = op.cancelled();
+    let message = format!("送出 {}: {} / {}，失敗 {}", file, result.sent, result.total, result.failed);
+    logging::log(&app, Severity::Info, Category::Command, Some(&path), message);
+    Ok(result)
+}
+
+#[tauri::command]
+fn list_scheduled_tasks(scheduler: State<'_, TaskScheduler>) -> Vec<ScheduledTask> {
+    scheduler.0.lock().unwrap().tasks()
+}
+
+// 新增或取代同名的排程工作，並寫入設定檔
+#[tauri::command]
+fn save_scheduled_task(task: ScheduledTask, scheduler: State<'_, TaskScheduler>) -> Result<(), String> {
+    scheduler.0.lock().unwrap().upsert(task)
+}
+
+#[tauri::command]
+fn remove_scheduled_task(name: String, scheduler: State<'_, TaskScheduler>) -> Result<(), String> {
+    scheduler.0.lock().unwrap().remove(&name)
+}
+
+#[tauri::command]
+fn get_task_history(task: Option<String>, cursor: Option<u64>, limit: Option<usize>, scheduler: State<'_, TaskScheduler>) -> Page<TaskRun> {
+    scheduler.0.lock().unwrap().history(task.as_deref(), PageRequest { cursor, limit })
+}
+
+// 依 path 或 device 找出設備路徑；尚未開啟時開始監聽
+fn resolve_task_device(app: &AppHandle, path: Option<&str>, device: Option<&Favorite>) -> Result<String, String> {
+    let path = match (path, device) {
+        (Some(path), _) => path.to_string(),
+        (None, Some(fav)) => list_device_identities()?.into_iter()
+            .find(|d| fav.matches(d))
+            .map(|d| d.path)
+            .ok_or_else(|| format!("找不到設備: {}", fav.name))?,
+        (None, None) => return Err("send 工作需要指定 path 或 device".into()),
+    };
+    listen_device(app, &path, None, &app.state::<DeviceManager>())?;
+    Ok(path)
+}
+
+fn run_scheduled_task(app: &AppHandle, task: &ScheduledTask, started_ms: i64) -> TaskRun {
+    let started = Instant::now();
+    let mut run = TaskRun { task: task.name.clone(), started_ms, ok: false, message: String::new(), path: None, response: None, duration_ms: 0 };
+    let result = match &task.action {
+        TaskAction::Send { path, device, data, timeout_ms } => resolve_task_device(app, path.as_deref(), device.as_ref())
+            .and_then(|path| {
+                run.path = Some(path.clone());
+                let m_dev = app.state::<DeviceManager>().get(&path)?;
+                let data = expand_payload(app, &path, data)?;
+                let report = build_output_report(&m_dev.meta.report_sizes, &data, &WriteOptions::default())?;
+                let resp = m_dev.exchange(report, OutputMethod::Interrupt, timeout_ms.unwrap_or(DEFAULT_RESPONSE_TIMEOUT_MS), None)?;
+                if resp.is_empty() { return Err("設備沒有回應".into()); }
+                let hex = resp.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+                run.response = Some(resp);
+                Ok(hex)
+            }),
+        TaskAction::Emit { payload } => app
+            .emit("scheduled-task", ScheduledTaskEvent { task: task.name.clone(), payload: payload.clone() })
+            .map(|_| String::new())
+            .map_err(|e| e.to_string()),
+    };
+    run.duration_ms = started.elapsed().as_millis() as u64;
+    run.ok = result.is_ok();
+    run.message = result.unwrap_or_else(|e| e);
+    let (severity, message) = match run.ok {
+        true => (Severity::Info, format!("排程工作 {} 完成: {}", run.task, run.message)),
+        false => (Severity::Warning, format!("排程工作 {} 失敗: {}", run.task, run.message)),
+    };
+    logging::log(app, severity, Category::Schedule, run.path.as_deref(), message);
+    run
+}
+
+// 每秒檢查一次排程，到期的工作各自在背景執行，結果寫入紀錄並發送 task-run
+fn spawn_scheduler(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_millis(SCHEDULER_TICK_MS));
+        let now = chrono::Local::now();
+        let due = app.state::<TaskScheduler>().0.lock().unwrap().due(&now);
+        for task in due {
+            let app = app.clone();
+            thread::spawn(move || {
+                let run = run_scheduled_task(&app, &task, now.timestamp_millis());
+                app.state::<TaskScheduler>().0.lock().unwrap().record(run.clone());
+                let _ = app.emit("task-run", run);
+            });
+        }
+    });
+}
+
+// 會改寫或長時間佔用設備的操作開始前呼叫：有人鎖定工作站時只有持有者 (token 相符) 可以執行
+fn check_station_lock(app: &AppHandle, token: Option<&str>) -> Result<(), String> {
+    app.state::<Station>().0.lock().unwrap().check(token)
+}
+
+#[tauri::command]
+fn get_station_lock(station: State<'_, Station>) -> Option<StationLockInfo> {
+    station.0.lock().unwrap().info()
+}
+
+// 取得或續約 (帶上原本的 token) 工作站鎖定，回傳續約與釋放時需要的 token；狀態改變時發送 station-lock
+// GUI 與服務管理介面 (POST /station/acquire) 共用
+fn acquire_station(app: &AppHandle, request: AcquireRequest) -> Result<String, String> {
+    let state = app.state::<Station>();
+    let mut lock = state.0.lock().unwrap();
+    let token = lock.acquire(&request.owner, request.note, request.ttl_secs, request.token.as_deref())?;
+    let _ = app.emit("station-lock", lock.info());
+    Ok(token)
+}
+
+fn release_station(app: &AppHandle, request: &ReleaseRequest) -> Result<(), String> {
+    let state = app.state::<Station>();
+    let mut lock = state.0.lock().unwrap();
+    lock.release(&request.token)?;
+    let _ = app.emit("station-lock", lock.info());
+    Ok(())
+}
+
+#[tauri::command]
+fn acquire_station_lock(
+    app: AppHandle,
+    owner: String,
+    note: Option<String>,
+    ttl_secs: Option<u64>,
+    token: Option<String>,
+) -> Result<String, String> {
+    acquire_station(&app, AcquireRequest { owner, note, ttl_secs, token })
+}
+
+#[tauri::command]
+fn release_station_lock(app: AppHandle, token: String) -> Result<(), String> {
+    release_station(&app, &ReleaseRequest { token })
+}
+
+// 本機管理者強制解除，回傳原本的使用者
+#[tauri::command]
+fn force_release_station_lock(app: AppHandle, station: State<'_, Station>) -> Option<String> {
+    let owner = station.0.lock().unwrap().force_release();
+    let _ = app.emit("station-lock", None::<StationLockInfo>);
+    owner
+}
+
+// 啟用時先通報目前已接著的設備 (initial = true)，之後每次插拔各送出一筆 POST；設定會儲存，下次啟動時恢復
+#[tauri::command]
+fn set_device_webhook(app: AppHandle, config: Option<WebhookConfig>, webhook: State<'_, Webhook>) -> Result<(), String> {
+    let next = config.clone().map(DeviceWebhook::new).transpose()?;
+    webhook::save_config(&config_dir(&app)?.join("webhook.json"), config.as_ref())?;
+    *webhook.0.lock().unwrap() = next;
+    Ok(())
+}
+
+#[tauri::command]
+fn get_device_webhook_status(webhook: State<'_, Webhook>) -> Option<WebhookStatus> {
+    webhook.0.lock().unwrap().as_ref().map(DeviceWebhook::status)
+}
+
+// 捨棄等待重送的事件，回傳捨棄的筆數；未啟用時為 0
+#[tauri::command]
+fn clear_device_webhook_queue(webhook: State<'_, Webhook>) -> usize {
+    webhook.0.lock().unwrap().as_mut().map(DeviceWebhook::clear_pending).unwrap_or(0)
+}
+
+fn list_device_identities() -> Result<Vec<DeviceIdentityReport>, String> {
+    Ok(get_api()?.device_list()
+        .map(|d| DeviceIdentityReport {
+            path: d.path().to_string_lossy().to_string(),
+            vendor_id: d.vendor_id(),
+            product_id: d.product_id(),
+            serial_number: d.serial_number().map(str::to_string),
+            manufacturer: d.manufacturer_string().map(str::to_string),
+            product: d.product_string().map(str::to_string),
+            release_number: d.release_number(),
+            usage_page: d.usage_page(),
+            usage: d.usage(),
+            interface_number: d.interface_number(),
+        })
+        .collect())
+}
+
+// 設定 webhook 時定期比對設備清單，依序送出佇列中的插拔事件；失敗時停在該事件，等重送間隔過後再送
+fn spawn_device_reporter(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_millis(WEBHOOK_POLL_MS));
+        if app.state::<Webhook>().0.lock().unwrap().is_none() { continue; }
+        // 列舉設備可能需要一段時間，不持有鎖
+        if let Ok(devices) = list_device_identities() {
+            if let Some(webhook) = app.state::<Webhook>().0.lock().unwrap().as_mut() {
+                webhook.diff(devices);
+            }
+        }
+        loop {
+            let next = app.state::<Webhook>().0.lock().unwrap().as_mut().and_then(DeviceWebhook::next);
+            let Some((event, target)) = next else { break };
+            let result = target.post(&event);
+            let state = app.state::<Webhook>();
+            let mut webhook = state.0.lock().unwrap();
+            let Some(webhook) = webhook.as_mut() else { break };
+            if !webhook.record(event, result) { break; }
+        }
+    });
+}
+
+// --- 無視窗監控服務 (見 service.rs) ---
+
+fn start_service(app: &AppHandle) -> Result<(), String> {
+    for window in app.webview_windows().values() {
+        let _ = window.hide();
+    }
+    // 沒有視窗時記錄檔是唯一的輸出；未開啟時以 info 開啟 (不寫回設定)
+    {
+        let state = app.state::<Logging>();
+        let mut logger = state.0.lock().unwrap();
+        if logger.levels.file.is_none() { logger.levels.file = Some(Severity::Info); }
+    }
+    let port = {
+        let state = app.state::<WatchdogService>();
+        let mut service = state.0.lock().unwrap();
+        let handle = app.clone();
+        let sink = move |entry: &service::LogEntry| {
+            logging::log(&handle, entry.severity(), Category::Service, entry.device.as_deref(), entry.message());
+        };
+        service.start(&config_dir(app)?.join("service.json"), Box::new(sink))?;
+        service.config.control_port
+    };
+    for event in SERVICE_EVENTS {
+        let handle = app.clone();
+        app.listen_any(event, move |e| record_service_event(&handle, event, e.payload()));
+    }
+    let handle = app.clone();
+    service::serve(port, move |request| handle_service_request(&handle, request))?;
+    spawn_service_monitor(app.clone());
+    Ok(())
+}
+
+// 只記錄服務管理中的設備
+fn record_service_event(app: &AppHandle, event: &str, payload: &str) {
+    let Ok(detail) = serde_json::from_str::<serde_json::Value>(payload) else { return };
+    let Some(path) = detail.get("path").and_then(|p| p.as_str()).map(str::to_string) else { return };
+    let state = app.state::<WatchdogService>();
+    let mut service = state.0.lock().unwrap();
+    let Some(name) = service.name_of(&path) else { return };
+    if event == "watch-triggered" { service.mark_triggered(&path); }
+    service.record(Some(&name), event, detail);
+}
+
+fn handle_service_request(app: &AppHandle, request: &service::Request) -> (u16, String) {
+    use service::{reply, reply_error};
+    let state = app.state::<WatchdogService>();
+    // 查詢只需要 read，其餘操作需要 control
+    let scope = if request.method == "GET" { Scope::Read } else { Scope::Control };
+    let allowed = state.0.lock().unwrap().config.auth.authenticate(&request.credentials)
+        .and_then(|principal| principal.require(scope));
+    if let Err((status, error)) = allowed {
+        let detail = serde_json::json!({ "method": request.method, "path": request.path, "error": error });
+        state.0.lock().unwrap().record(None, "request-denied", detail);
+        return reply_error(status, &error);
+    }
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/status") => reply(&state.0.lock().unwrap().status()),
+        ("GET", "/log") => reply(&state.0.lock().unwrap().recent(service::page_request(request))),
+        ("POST", "/reload") => match reload_service(app) {
+            Ok(status) => reply(&status),
+            Err(e) => reply_error(400, &e),
+        },
+        ("POST", "/borrow") => match request.json::<HandoverRequest>().and_then(|r| lend_device(app, &r)) {
+            Ok(()) => reply(&serde_json::json!({ "lent": true })),
+            Err(e) => reply_error(400, &e),
+        },
+        ("POST", "/return") => match request.json::<HandoverRequest>().and_then(|r| state.0.lock().unwrap().take_back(&r.path)) {
+            Ok(()) => reply(&serde_json::json!({ "returned": true })),
+            Err(e) => reply_error(400, &e),
+        },
+        ("POST", "/stop") => {
+            state.0.lock().unwrap().record(None, "service-stopped", serde_json::Value::Null);
+            // 先送出回應再結束
+            let handle = app.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(200));
+                handle.exit(0);
+            });
+            reply(&serde_json::json!({ "stopping": true }))
+        }
+        ("GET", "/station") => reply(&app.state::<Station>().0.lock().unwrap().info()),
+        ("POST", "/station/acquire") => match request.json::<AcquireRequest>().and_then(|r| acquire_station(app, r)) {
+            Ok(token) => reply(&serde_json::json!({ "token": token })),
+            Err(e) => reply_error(400, &e),
+        },
+        ("POST", "/station/release") => match request.json::<ReleaseRequest>().and_then(|r| release_station(app, &r)) {
+            Ok(()) => reply(&serde_json::json!({ "released": true })),
+            Err(e) => reply_error(400, &e),
+        },
+        (_, "/status" | "/log" | "/reload" | "/stop" | "/borrow" | "/return" | "/station" | "/station/acquire" | "/station/release") => {
+            reply_error(405, "不支援的方法")
+        }
+        _ => reply_error(404, "找不到路徑"),
+    }
+}
+
+fn reload_service(app: &AppHandle) -> Result<ServiceStatus, String> {
+    let state = app.state::<WatchdogService>();
+    let mut service = state.0.lock().unwrap();
+    let manager = app.state::<DeviceManager>();
+    for path in service.reload()? {
+        if let Ok(m_dev) = manager.get(&path) { m_dev.stop(); }
+    }
+    service.record(None, "reloaded", serde_json::Value::Null);
+    Ok(service.status())
+}
+
+// 停止監聽並等 I/O 執行緒關閉設備後才回應，GUI 收到回應即可開啟
+fn lend_device(app: &AppHandle, request: &HandoverRequest) -> Result<(), String> {
+    app.state::<WatchdogService>().0.lock().unwrap().lend(request)?;
+    release_device(app, &request.path)
+}
+
+// 停止監聽並等到設備從 manager 移除 (I/O 執行緒結束)
+fn release_device(app: &AppHandle, path: &str) -> Result<(), String> {
+    let manager = app.state::<DeviceManager>();
+    let Ok(m_dev) = manager.get(path) else { return Ok(()) };
+    m_dev.stop();
+    drop(m_dev);
+    let deadline = Instant::now() + Duration::from_millis(HANDOVER_WAIT_MS);
+    while manager.get(path).is_ok() {
+        if Instant::now() >= deadline { return Err("等待設備關閉逾時".into()); }
+        thread::sleep(Duration::from_millis(20));
+    }
+    Ok(())
+}
+
+// 對本機服務的管理介面送出 borrow / return；連接埠取自自己的 service.json，未設定時為預設值
+// 服務設定了 API key 時使用其中一把有 control 權限的
+fn service_handover(app: &AppHandle, endpoint: &str, request: &HandoverRequest) -> Result<(), String> {
+    let config = config_dir(app)
+        .and_then(|dir| ServiceConfig::load(&dir.join("service.json")))
+        .unwrap_or_default();
+    let url = format!("http://127.0.0.1:{}/{}", config.control_port, endpoint);
+    let mut target = WebhookTarget::from_url(&url, Duration::from_millis(HANDOVER_WAIT_MS + 1000))?;
+    if let Some(key) = config.auth.key_for(Scope::Control) {
+        target = target.with_header("X-Api-Key", &key.key);
+    }
+    target.post(request)
+}
+
+fn borrow_device(app: &AppHandle, path: &str, timeout_ms: Option<u64>) -> Result<(), String> {
+    service_handover(app, "borrow", &HandoverRequest { path: path.to_string(), timeout_ms })?;
+    app.state::<BorrowedDevices>().0.lock().unwrap().insert(path.to_string());
+    Ok(())
+}
+
+fn return_device(app: &AppHandle, path: &str) -> Result<(), String> {
+    release_device(app, path)?;
+    app.state::<BorrowedDevices>().0.lock().unwrap().remove(path);
+    service_handover(app, "return", &HandoverRequest { path: path.to_string(), timeout_ms: None })
+}
+
+// 向服務借用設備 (服務停止監聽，借用期間不會重新開啟)；timeout_ms 到期後服務自動收回，重複借用可延長
+#[tauri::command]
+async fn borrow_from_service(app: AppHandle, path: String, timeout_ms: Option<u64>) -> Result<(), String> {
+    borrow_device(&app, &path, timeout_ms)
+}
+
+// 停止監聽並把設備還給服務
+#[tauri::command]
+async fn return_to_service(app: AppHandle, path: String) -> Result<(), String> {
+    return_device(&app, &path)
+}
+
+// 開始監聽並套用設定的監看條件；失敗時停止監聽，下次掃描再試
+fn open_service_device(app: &AppHandle, path: &str, spec: &ServiceDevice) -> Result<(), String> {
+    let watches = spec.watches.iter().map(|w| Watch::parse(&w.name, &w.expression)).collect::<Result<Vec<_>, _>>()?;
+    let manager = app.state::<DeviceManager>();
+    listen_device(app, path, None, &manager)?;
+    let m_dev = manager.get(path)?;
+    if !watches.is_empty() && m_dev.meta.descriptor.is_none() {
+        m_dev.stop();
+        return Err("無法取得 report descriptor，不能監看欄位".into());
+    }
+    *m_dev.watches.lock().unwrap() = watches;
+    Ok(())
+}
+
+// 定期檢查已連線的設備是否還在，並開啟剛插入的設備
+fn spawn_service_monitor(app: AppHandle) {
+    thread::spawn(move || loop {
+        let interval = app.state::<WatchdogService>().0.lock().unwrap().config.scan_interval_ms.max(SERVICE_MIN_SCAN_MS);
+        thread::sleep(Duration::from_millis(interval));
+        let state = app.state::<WatchdogService>();
+        let pending = {
+            let mut service = state.0.lock().unwrap();
+            // 拔除或讀取錯誤時 I/O 執行緒結束，設備會從 manager 移除
+            let manager = app.state::<DeviceManager>();
+            for path in service.connected() {
+                if manager.get(&path).is_err() { service.mark_disconnected(&path); }
+            }
+            service.expire_loans();
+            service.pending()
+        };
+        if pending.is_empty() { continue; }
+        // 列舉設備可能需要一段時間，不持有鎖
+        let Ok(devices) = list_device_identities() else { continue };
+        for spec in pending {
+            let claimed = |path: &str| state.0.lock().unwrap().is_claimed(path);
+            let Some(found) = devices.iter().find(|d| spec.device.matches(d) && !claimed(&d.path)) else { continue };
+            let result = open_service_device(&app, &found.path, &spec);
+            let mut service = state.0.lock().unwrap();
+            match result {
+                Ok(()) => service.mark_connected(&spec.device.name, &found.path),
+                Err(e) => service.mark_error(&spec.device.name, e),
+            }
+        }
+    });
+}
+
+#[tauri::command]
+fn get_service_status(state: State<'_, WatchdogService>) -> ServiceStatus {
+    state.0.lock().unwrap().status()
+}
+
+// --- 記錄 (見 logging.rs) ---
+
+#[tauri::command]
+fn get_log_levels(logging: State<'_, Logging>) -> LogLevels {
+    logging.0.lock().unwrap().levels.clone()
+}
+
+// 設定各輸出端的最低嚴重程度 (None 關閉該輸出端)；設定會儲存，下次啟動時恢復
+#[tauri::command]
+fn set_log_levels(app: AppHandle, levels: LogLevels, logging: State<'_, Logging>) -> Result<(), String> {
+    levels.save(&config_dir(&app)?.join("log_levels.json"))?;
+    logging.0.lock().unwrap().levels = levels;
+    Ok(())
+}
+
+// --- 資料目錄 (可攜模式見 portable.rs) ---
+
+fn config_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    match &app.state::<Storage>().0 {
+        Some(root) => Ok(root.join("config")),
+        None => app.path().app_config_dir().map_err(|e| e.to_string()),
+    }
+}
+
+fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    match &app.state::<Storage>().0 {
+        Some(root) => Ok(root.clone()),
+        None => app.path().app_data_dir().map_err(|e| e.to_string()),
+    }
+}
+
+#[tauri::command]
+fn get_storage_info(app: AppHandle) -> Result<StorageInfo, String> {
+    Ok(StorageInfo::new(app.state::<Storage>().0.is_some(), &config_dir(&app)?, &data_dir(&app)?))
+}
+
+// --- 匿名使用統計 ---
+
+fn count_feature(app: &AppHandle, feature: &str) {
+    app.state::<Usage>().0.lock().unwrap().record(feature);
+}
+
+#[tauri::command]
+fn get_telemetry_status(usage: State<'_, Usage>) -> TelemetryStatus {
+    usage.0.lock().unwrap().status()
+}
+
+// 開啟或關閉使用統計；關閉時清除已累積的資料
+#[tauri::command]
+fn set_telemetry(enabled: bool, endpoint: Option<String>, usage: State<'_, Usage>) -> Result<(), String> {
+    usage.0.lock().unwrap().configure(enabled, endpoint)
+}
+
+// 下一次會送出的完整內容
+#[tauri::command]
+fn preview_telemetry(usage: State<'_, Usage>) -> TelemetryReport {
+    usage.0.lock().unwrap().report()
+}
+
+fn spawn_telemetry_sender(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_millis(TELEMETRY_CHECK_MS));
+        let Some((target, report)) = app.state::<Usage>().0.lock().unwrap().due() else { continue };
+        let result = target.post(&report);
+        app.state::<Usage>().0.lock().unwrap().record_sent(&report, result);
+    });
+}
+
+// --- 常用設備健康檢查 ---
+
+#[tauri::command]
+fn get_favorites(favorites: State<'_, FavoriteDevices>) -> FavoritesConfig {
+    favorites.0.lock().unwrap().config.clone()
+}
+
+// 取代常用設備清單與 check_on_launch，並寫入設定檔
+#[tauri::command]
+fn set_favorites(config: FavoritesConfig, favorites: State<'_, FavoriteDevices>) -> Result<(), String> {
+    favorites.0.lock().unwrap().set(config)
+}
+
+// 立即檢查所有常用設備 (green / yellow / red，見 health.rs)，結果同時以 health-check 事件送出
+#[tauri::command]
+async fn run_health_check(app: AppHandle) -> Result<HealthReport, String> {
+    check_favorites(&app)
+}
+
+#[tauri::command]
+fn get_last_health_check(favorites: State<'_, FavoriteDevices>) -> Option<HealthReport> {
+    favorites.0.lock().unwrap().last_report.clone()
+}
+
+// 查詢韌體版本，協定支援耳機控制時一併查詢狀態
+fn query_health(dev: &dyn HidIo, meta: &DeviceMeta) -> Result<(String, Option<HeadsetStatus>), String> {
+    let version = query_firmware_version(dev, meta)?;
+    let status = match protocols::resolve(None, &meta.identity).ok() {
+        Some(plugin) => {
+            let len = meta.report_sizes.len_of(ReportKind::Output, 0).unwrap_or(64);
+            plugin.headset().map(|h| h.query_status(&HidTransport::new(dev, len))).transpose()?
+        }
+        None => None,
+    };
+    Ok((version, status))
+}
+
+fn check_favorites(app: &AppHandle) -> Result<HealthReport, String> {
+    let favorites = app.state::<FavoriteDevices>().0.lock().unwrap().config.devices.clone();
+    let devices = list_device_identities()?;
+    let api = get_api()?;
+    let manager_state = app.state::<DeviceManager>();
+    let results = favorites.iter()
+        .map(|fav| {
+            let Some(found) = devices.iter().find(|d| fav.matches(d)) else { return HealthResult::missing(fav) };
+            let path = found.path.clone();
+            // 已在監聽中的設備以獨佔操作查詢，不重新開啟
+            if manager_state.get(&path).is_ok() {
+                let query = with_exclusive_device(&manager_state, &path, query_health);
+                return HealthResult::probed(fav, path, query);
+            }
+            match device::open_device(&api, &path) {
+                Ok((dev, identity)) => {
+                    let meta = DeviceMeta::read(dev.as_ref(), identity);
+                    let query = query_health(dev.as_ref(), &meta);
+                    HealthResult::probed(fav, path, query)
+                }
+                Err(e) => HealthResult::unopened(fav, path, e),
+            }
+        })
+        .collect();
+    let report = HealthReport::new(results, chrono::Local::now().timestamp_millis());
+    for r in &report.results {
+        let severity = match r.health {
+            health::Health::Green => continue,
+            health::Health::Yellow => Severity::Warning,
+            health::Health::Red => Severity::Error,
+        };
+        let message = format!("{}: {}", r.name, r.message.as_deref().unwrap_or(""));
+        logging::log(app, severity, Category::Health, r.path.as_deref(), message);
+    }
+    app.state::<FavoriteDevices>().0.lock().unwrap().last_report = Some(report.clone());
+    let _ = app.emit("health-check", report.clone());
+    Ok(report)
+}
+
+// 開啟時把所有模擬設備直接放進 manager；關閉時停止它們
+fn apply_demo_mode(app: &AppHandle, enabled: bool) -> Result<(), String> {
+    app.state::<DemoMode>().0.store(enabled, Ordering::SeqCst);
+    let state = app.state::<DeviceManager>();
+    let mut manager = state.0.lock().unwrap();
+    if enabled {
+        for d in demo::devices() {
+            if manager.contains_key(d.path) { continue; }
+            let m_dev = demo::open(app, d.path, ListenOptions::default())?;
+            manager.insert(d.path.to_string(), m_dev);
+        }
+    } else {
+        manager.iter()
+            .filter(|(path, _)| demo::is_demo_path(path))
+            .for_each(|(_, m_dev)| m_dev.stop());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn set_demo_mode(app: AppHandle, enabled: bool) -> Result<(), String> {
+    apply_demo_mode(&app, enabled)
+}
+
+fn main() {
+    // 輸出安裝成系統服務的設定後結束 (見 service.rs)
+    if std::env::args().skip(1).any(|a| a == service::PRINT_UNIT_FLAG) {
+        let exe = std::env::current_exe().expect("無法取得執行檔路徑");
+        print!("{}", service::install_instructions(&exe));
+        return;
+    }
+    tauri::Builder::default()
+        .manage(DeviceManager::default())
+        .manage(StatsConfig(AtomicU64::new(0)))
+        .manage(Summaries(Mutex::new(Summarizer::new())))
+        .manage(Dashboard(Mutex::new(DashboardConfig::default())))
+        .manage(DemoMode(AtomicBool::new(false)))
+        .manage(RegisterMaps(Mutex::new(HashMap::new())))
+        .manage(Schemas(Mutex::new(HashMap::new())))
+        .manage(SchemaVersions(Mutex::new(VersionRules::new())))
+        .manage(KeyLayouts(Mutex::new(LayoutRegistry::builtin())))
+        .manage(CaptureNaming(Mutex::new(NamingConfig::default())))
+        .manage(CaptureClock::new())
+        .manage(RunningOperations(Mutex::new(Operations::default())))
+        .manage(FavoriteDevices(Mutex::new(Favorites::new())))
+        .manage(PowerMonitor(Mutex::new(HashMap::new())))
+        .manage(CaptureHook(Mutex::new(None)))
+        .manage(TaskScheduler(Mutex::new(Scheduler::new())))
+        .manage(Station(Mutex::new(StationLock::default())))
+        .manage(Webhook(Mutex::new(None)))
+        .manage(Deadlines(Mutex::new(HashMap::new())))
+        .manage(Usage(Mutex::new(Telemetry::new())))
+        .manage(Storage(portable::detect()))
+        .manage(WatchdogService(Mutex::new(Service::new())))
+        .manage(BorrowedDevices(Mutex::new(HashSet::new())))
+        .manage(Logging(Mutex::new(Logger::new())))
+        .manage(Incidents(Mutex::new(IncidentRecorder::new())))
+        .manage(Captures(Mutex::new(CaptureRecorder::default())))
+        .manage(TestRecords(Mutex::new(TestResults::default())))
+        .manage(Templates(Mutex::new(TemplateState::default())))
+        .manage(TrafficLog(Mutex::new(TrafficLogConfig::default())))
+        .manage(Annotations(Mutex::new(AnnotationStore::default())))
+        .manage(History(Mutex::new(CaptureHistory::default())))
+        .setup(|app| {
+            if let Ok(dir) = data_dir(app.handle()) {
+                app.state::<Logging>().0.lock().unwrap().set_file(&dir.join("app.log"));
+                app.state::<Incidents>().0.lock().unwrap().set_dir(&dir.join("incidents"));
+            }
+            // 設定檔損毀時使用預設層級
+            if let Ok(dir) = config_dir(app.handle()) {
+                app.state::<Logging>().0.lock().unwrap().levels = LogLevels::load(&dir.join("log_levels.json")).unwrap_or_default();
+            }
+            // 使用者自訂的鍵盤配置；個別檔案格式錯誤不影響啟動
+            if let Ok(dir) = config_dir(app.handle()) {
+                let errors = app.state::<KeyLayouts>().0.lock().unwrap().load_dir(&dir.join("layouts"));
+                for e in errors {
+                    logging::log(app.handle(), Severity::Warning, Category::Device, None, format!("無法載入鍵盤配置: {}", e));
+                }
+            }
+            spawn_stats_emitter(app.handle().clone());
+            spawn_summary_emitter(app.handle().clone());
+            spawn_dashboard_emitter(app.handle().clone());
+            spawn_power_monitor(app.handle().clone());
+            spawn_activity_monitor(app.handle().clone());
+            // 設定檔損毀時維持關閉
+            if let Ok(dir) = config_dir(app.handle()) {
+                match webhook::load_config(&dir.join("webhook.json")).and_then(|c| c.map(DeviceWebhook::new).transpose()) {
+                    Ok(webhook) => *app.state::<Webhook>().0.lock().unwrap() = webhook,
+                    Err(e) => logging::log(app.handle(), Severity::Warning, Category::Device, None, format!("無法載入 webhook 設定: {}", e)),
+                }
+            }
+            spawn_device_reporter(app.handle().clone());
+            // 規則檔損毀時不自動選擇 schema
+            if let Ok(dir) = config_dir(app.handle()) {
+                match schema::load_version_rules(&dir.join("schema_versions.json")) {
+                    Ok(rules) => *app.state::<SchemaVersions>().0.lock().unwrap() = rules,
+                    Err(e) => logging::log(app.handle(), Severity::Warning, Category::Device, None, format!("無法載入 schema 版本規則: {}", e)),
+                }
+            }
+            // 排程檔損毀時以空的排程啟動
+            if let Ok(dir) = config_dir(app.handle()) {
+                let result = app.state::<TaskScheduler>().0.lock().unwrap().load(&dir.join("schedule.json"));
+                if let Err(e) = result {
+                    logging::log(app.handle(), Severity::Warning, Category::Schedule, None, format!("無法載入排程: {}", e));
+                }
+            }
+            spawn_scheduler(app.handle().clone());
+            // 設定檔損毀時維持關閉，不影響啟動
+            if let Ok(dir) = config_dir(app.handle()) {
+                let _ = app.state::<Usage>().0.lock().unwrap().load(&dir.join("telemetry.json"));
+            }
+            spawn_telemetry_sender(app.handle().clone());
+            // 設定檔損毀時以沒有常用設備啟動
+            if let Ok(dir) = config_dir(app.handle()) {
+                let result = app.state::<FavoriteDevices>().0.lock().unwrap().load(&dir.join("favorites.json"));
+                if let Err(e) = result {
+                    logging::log(app.handle(), Severity::Warning, Category::Health, None, format!("無法載入常用設備: {}", e));
+                }
+            }
+            // 記錄目錄無法寫入時只留下警告，不影響啟動
+            if let Ok(dir) = config_dir(app.handle()) {
+                let config = TrafficLogConfig::load(&dir.join("traffic_log.json")).unwrap_or_default();
+                if let Err(e) = apply_traffic_log(app.handle(), &config) {
+                    logging::log(app.handle(), Severity::Warning, Category::Capture, None, format!("無法開始流量記錄: {}", e));
+                }
+                *app.state::<TrafficLog>().0.lock().unwrap() = config;
+            }
+            // 不阻擋視窗開啟，結果以 health-check 事件送出
+            if app.state::<FavoriteDevices>().0.lock().unwrap().config.check_on_launch {
+                let handle = app.handle().clone();
+                thread::spawn(move || { let _ = check_favorites(&handle); });
+            }
+            if service::requested() {
+                start_service(app.handle())?;
+            }
+            // KEYSTONE_DEMO=1 啟動時直接進入 demo 模式
+            if std::env::var("KEYSTONE_DEMO").is_ok_and(|v| v == "1") {
+                apply_demo_mode(app.handle(), true)?;
+            }
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            scan_hid_devices, 
+            start_listening, 
+            stop_listening,
+            set_listen_options,
+            add_watch,
+            remove_watch,
+            list_watches,
+            pause_listening,
+            resume_listening,
+            clear_error,
+            send_hid_command,
+            exchange,
+            expect,
+            compute_checksum,
+            set_command_deadlines,
+            get_command_deadlines,
+            set_template_variable,
+            get_template_state,
+            reset_template_counters,
+            preview_payload,
+            write_hid,
+            send_hid_broadcast,
+            start_polling,
+            stop_polling,
+            get_feature_report,
+            send_feature_report,
+            get_report_sizes,
+            get_device_capabilities,
+            get_report_descriptor,
+            export_report_descriptor,
+            decode_report,
+            get_device_stats,
+            list_active_devices,
+            set_stats_interval,
+            set_summary_options,
+            set_dashboard,
+            get_dashboard,
+            list_protocols,
+            read_profile,
+            write_profile,
+            erase_profile,
+            send_chunked,
+            receive_chunked,
+            list_bootloaders,
+            load_firmware_file,
+            firmware_update,
+            cancel_firmware_update,
+            run_sequence,
+            cancel_sequence,
+            run_stress_test,
+            cancel_stress_test,
+            list_operations,
+            cancel_operation,
+            get_test_results,
+            clear_test_results,
+            export_test_report,
+            benchmark_latency,
+            run_script,
+            stop_script,
+            headset_get_status,
+            headset_set_sidetone,
+            lighting_get_zones,
+            lighting_set_color,
+            lighting_set_brightness,
+            lighting_set_effect,
+            sony_set_output,
+            sony_enable_full_reports,
+            ctap_send,
+            ccid_power_on,
+            ccid_transmit_apdu,
+            hidpp_get_info,
+            hidpp_get_battery,
+            hidpp_get_dpi,
+            qmk_get_info,
+            qmk_get_keycode,
+            qmk_set_keycode,
+            qmk_get_layer,
+            telephony_set_leds,
+            read_weight,
+            read_power_status,
+            set_power_monitor,
+            read_sensor_properties,
+            start_stream,
+            stop_stream,
+            get_stream_info,
+            start_msr_capture,
+            stop_msr_capture,
+            list_layouts,
+            load_layout,
+            set_layout,
+            load_register_map,
+            list_register_maps,
+            read_register,
+            write_register,
+            load_schema,
+            list_schemas,
+            set_device_framing,
+            get_device_framing,
+            set_device_schema,
+            decode_with_schema,
+            set_schema_versions,
+            get_firmware_version,
+            get_schema_violations,
+            reset_schema_violations,
+            generate_schema_code,
+            infer_schema,
+            set_demo_mode,
+            set_timestamp_source,
+            get_clock_metadata,
+            set_naming_template,
+            set_device_alias,
+            resolve_capture_name,
+            set_post_capture_hook,
+            capture_finished,
+            start_capture,
+            annotate_frame,
+            list_annotations,
+            get_capture_page,
+            get_capture_count,
+            stop_capture,
+            list_captures,
+            replay_session,
+            diff_captures,
+            parse_hex_file,
+            send_from_file,
+            export_capture_pcapng,
+            export_capture_csv,
+            set_logging,
+            get_logging,
+            export_channels,
+            list_scheduled_tasks,
+            save_scheduled_task,
+            remove_scheduled_task,
+            get_task_history,
+            get_station_lock,
+            acquire_station_lock,
+            release_station_lock,
+            force_release_station_lock,
+            set_device_webhook,
+            get_device_webhook_status,
+            clear_device_webhook_queue,
+            get_favorites,
+            set_favorites,
+            run_health_check,
+            get_last_health_check,
+            get_telemetry_status,
+            get_storage_info,
+            get_service_status,
+            get_log_levels,
+            set_log_levels,
+            borrow_from_service,
+            return_to_service,
+            set_telemetry,
+            preview_telemetry
+        ])
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            // 正常結束的工作階段 (沒有收到代表當機)
+            if let RunEvent::Exit = event {
+                let _ = app.state::<Usage>().0.lock().unwrap().end_session();
+                // 擷取檔補上結尾
+                let captures = app.state::<Captures>().0.lock().unwrap().remove_all();
+                for capture in captures {
+                    finish_capture(app, capture);
+                }
+                let traffic_log = app.state::<Captures>().0.lock().unwrap().take_traffic_log();
+                if let Some(capture) = traffic_log { capture.finish(); }
+                // 歸還向服務借用的設備，不必等借用逾時
+                let borrowed: Vec<String> = app.state::<BorrowedDevices>().0.lock().unwrap().iter().cloned().collect();
+                for path in borrowed {
+                    let _ = return_device(app, &path);
+                }
+            }
+        });
 }
\ No newline at end of file
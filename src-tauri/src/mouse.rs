@@ -0,0 +1,84 @@
+// --- 滑鼠 report 解碼 ---
+// 依 descriptor 取出按鍵、X/Y、滾輪與水平滾輪 (AC Pan)，並處理相對/絕對座標與有號數。
+
+use crate::descriptor::{ReportDescriptor, ReportField, ReportKind};
+use crate::protocols::DeviceIdentity;
+use crate::usages::{PAGE_BUTTON, PAGE_CONSUMER, PAGE_GENERIC_DESKTOP};
+use serde::Serialize;
+
+const USAGE_POINTER: u16 = 0x01;
+const USAGE_MOUSE: u16 = 0x02;
+const USAGE_X: u16 = 0x30;
+const USAGE_Y: u16 = 0x31;
+const USAGE_WHEEL: u16 = 0x38;
+const USAGE_RESOLUTION_MULTIPLIER: u16 = 0x48;
+const USAGE_AC_PAN: u16 = 0x238;
+
+#[derive(Serialize, Clone, PartialEq)]
+pub struct MouseState {
+    pub buttons: Vec<bool>,
+    // relative 為 true 時是位移量 (dX/dY)，否則為絕對座標
+    pub x: i64,
+    pub y: i64,
+    pub relative: bool,
+    pub wheel: i64,
+    // 水平滾輪
+    pub pan: i64,
+    // 支援高解析度滾輪時，每一格對應的 wheel 數值 (需由主機啟用)
+    pub wheel_multiplier: Option<i32>,
+}
+
+pub struct MouseDecoder {
+    desc: ReportDescriptor,
+    wheel_multiplier: Option<i32>,
+}
+
+impl MouseDecoder {
+    // 只處理頂層 usage 為 Mouse / Pointer 且有 X/Y 欄位的設備
+    pub fn new(identity: &DeviceIdentity, desc: Option<&ReportDescriptor>) -> Option<Self> {
+        if identity.usage_page != PAGE_GENERIC_DESKTOP || !matches!(identity.usage, USAGE_POINTER | USAGE_MOUSE) {
+            return None;
+        }
+        let desc = desc?;
+        desc.find_usage(ReportKind::Input, PAGE_GENERIC_DESKTOP, USAGE_X)?;
+        let wheel_multiplier = desc.fields.iter()
+            .find(|f| f.kind == ReportKind::Feature && f.usage_page == PAGE_GENERIC_DESKTOP
+                && f.usages.contains(&USAGE_RESOLUTION_MULTIPLIER))
+            .map(|f| f.physical_max)
+            .filter(|m| *m > 1);
+        Some(Self { desc: desc.clone(), wheel_multiplier })
+    }
+
+    pub fn feed(&self, report: &[u8]) -> Option<MouseState> {
+        let report_id = if self.desc.uses_report_ids { *report.first()? } else { 0 };
+        let body = self.desc.report_body(report, report_id)?;
+        let fields: Vec<&ReportField> = self.desc.fields.iter()
+            .filter(|f| f.kind == ReportKind::Input && f.report_id == report_id && !f.is_constant() && f.is_variable())
+            .collect();
+
+        let find = |page: u16, usage: u16| {
+            fields.iter().find_map(|f| {
+                let i = f.usages.iter().position(|u| *u == usage).filter(|_| f.usage_page == page)?;
+                Some((*f, std::cmp::min(i, f.count.saturating_sub(1))))
+            })
+        };
+        // 這個 Report ID 不是滑鼠移動的 report
+        let (x_field, x_index) = find(PAGE_GENERIC_DESKTOP, USAGE_X)?;
+        let value = |page: u16, usage: u16| find(page, usage).and_then(|(f, i)| f.element_value(body, i)).unwrap_or(0);
+
+        let buttons = fields.iter()
+            .filter(|f| f.usage_page == PAGE_BUTTON)
+            .flat_map(|f| (0..f.count).map(|i| f.element_value(body, i).unwrap_or(0) != 0))
+            .collect();
+
+        Some(MouseState {
+            buttons,
+            x: x_field.element_value(body, x_index).unwrap_or(0),
+            y: value(PAGE_GENERIC_DESKTOP, USAGE_Y),
+            relative: x_field.is_relative(),
+            wheel: value(PAGE_GENERIC_DESKTOP, USAGE_WHEEL),
+            pan: value(PAGE_CONSUMER, USAGE_AC_PAN),
+            wheel_multiplier: self.wheel_multiplier,
+        })
+    }
+}
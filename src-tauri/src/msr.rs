@@ -0,0 +1,181 @@
+// --- 磁條讀卡機 (MSR) ---
+// 支援 keyboard-wedge 與 MagTek 式 vendor report，解析 track 1/2 並預設遮蔽卡號
+
+use crate::keymap::{Keymap, WedgeDecoder};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MsrMode {
+    // 模擬鍵盤輸入，以 Enter 結束一次刷卡
+    Wedge,
+    // MagTek HID: 3 bytes 狀態、3 bytes 長度、1 byte 編碼，之後每軌 110 bytes
+    Magtek,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Track1 {
+    pub pan: String,
+    pub name: String,
+    pub expiry: String,
+    pub service_code: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Track2 {
+    pub pan: String,
+    pub expiry: String,
+    pub service_code: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct MsrSwipe {
+    pub track1: Option<Track1>,
+    pub track2: Option<Track2>,
+    pub track3_len: usize,
+    // 原始軌道資料；遮蔽模式下卡號與 discretionary data 會被替換
+    pub raw: Vec<String>,
+    pub masked: bool,
+}
+
+const MAGTEK_TRACK_LEN: usize = 110;
+const MAGTEK_HEADER_LEN: usize = 7;
+
+// 以字元計算，讀卡機送來非 ASCII 的資料也不會切在字元中間
+pub fn mask_pan(pan: &str) -> String {
+    let chars: Vec<char> = pan.chars().collect();
+    if chars.len() <= 10 { return "*".repeat(chars.len()); }
+    let head: String = chars[..6].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}{}{}", head, "*".repeat(chars.len() - 10), tail)
+}
+
+// %B<PAN>^<NAME>^<YYMM><SVC><discretionary>?
+fn parse_track1(raw: &str) -> Option<Track1> {
+    let body = raw.trim().strip_prefix("%B")?.trim_end_matches('?');
+    let mut parts = body.splitn(3, '^');
+    let pan = parts.next()?.to_string();
+    let name = parts.next()?.trim().to_string();
+    let rest = parts.next()?;
+    Some(Track1 {
+        pan,
+        name,
+        expiry: rest.get(0..4)?.to_string(),
+        service_code: rest.get(4..7).unwrap_or("").to_string(),
+    })
+}
+
+// ;<PAN>=<YYMM><SVC><discretionary>?
+fn parse_track2(raw: &str) -> Option<Track2> {
+    let body = raw.trim().strip_prefix(';')?.trim_end_matches('?');
+    let (pan, rest) = body.split_once('=')?;
+    Some(Track2 {
+        pan: pan.to_string(),
+        expiry: rest.get(0..4)?.to_string(),
+        service_code: rest.get(4..7).unwrap_or("").to_string(),
+    })
+}
+
+fn build_swipe(tracks: Vec<String>, mask: bool) -> MsrSwipe {
+    let mut track1 = tracks.iter().find_map(|t| parse_track1(t));
+    let mut track2 = tracks.iter().find_map(|t| parse_track2(t));
+    let track3_len = tracks.get(2).map(|t| t.len()).unwrap_or(0);
+
+    let raw = if mask {
+        // discretionary data 可能含有驗證碼，遮蔽模式下不保留原文
+        tracks.iter().map(|t| if t.is_empty() { String::new() } else { format!("<{} chars masked>", t.len()) }).collect()
+    } else {
+        tracks
+    };
+    if mask {
+        if let Some(t) = track1.as_mut() { t.pan = mask_pan(&t.pan); }
+        if let Some(t) = track2.as_mut() { t.pan = mask_pan(&t.pan); }
+    }
+    MsrSwipe { track1, track2, track3_len, raw, masked: mask }
+}
+
+pub struct MsrCapture {
+    mode: MsrMode,
+    mask: bool,
+    wedge: WedgeDecoder,
+    buffer: String,
+}
+
+impl MsrCapture {
+    pub fn new(mode: MsrMode, mask: bool, keymap: Arc<Keymap>) -> Self {
+        Self { mode, mask, wedge: WedgeDecoder::new(keymap), buffer: String::new() }
+    }
+
+    pub fn set_keymap(&mut self, keymap: Arc<Keymap>) {
+        self.wedge.set_keymap(keymap);
+    }
+
+    // 餵入一筆 input report，刷卡完成時回傳解析結果
+    pub fn feed(&mut self, report: &[u8]) -> Option<MsrSwipe> {
+        match self.mode {
+            MsrMode::Wedge => {
+                for c in self.wedge.feed(report) {
+                    if c != '\n' {
+                        self.buffer.push(c);
+                        continue;
+                    }
+                    if self.buffer.is_empty() { continue; }
+                    let text = std::mem::take(&mut self.buffer);
+                    // 各軌以 end sentinel '?' 結尾
+                    let tracks: Vec<String> = text.split_inclusive('?').map(|t| t.to_string()).collect();
+                    return Some(build_swipe(tracks, self.mask));
+                }
+                None
+            }
+            MsrMode::Magtek => {
+                if report.len() < MAGTEK_HEADER_LEN { return None; }
+                let tracks: Vec<String> = (0..3)
+                    .map(|i| {
+                        let len = std::cmp::min(report[3 + i] as usize, MAGTEK_TRACK_LEN);
+                        let start = MAGTEK_HEADER_LEN + i * MAGTEK_TRACK_LEN;
+                        report.get(start..start + len)
+                            .map(|b| String::from_utf8_lossy(b).to_string())
+                            .unwrap_or_default()
+                    })
+                    .collect();
+                if tracks.iter().all(|t| t.is_empty()) { return None; }
+                Some(build_swipe(tracks, self.mask))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_all_but_first_six_and_last_four() {
+        assert_eq!(mask_pan("4111111111111111"), "411111******1111");
+        assert_eq!(mask_pan("1234567890"), "**********");
+        assert_eq!(mask_pan(""), "");
+    }
+
+    #[test]
+    fn masks_multibyte_pan_by_chars() {
+        // 位元組切片會在全形字元中間 panic
+        assert_eq!(mask_pan("４１１１１１１１１１１１"), "４１１１１１**１１１１");
+        assert_eq!(mask_pan("é1234567890ü"), "é12345**890ü");
+    }
+
+    #[test]
+    fn parses_and_masks_tracks() {
+        let tracks = vec!["%B4111111111111111^DOE/JOHN^2512101123?".to_string(), ";4111111111111111=2512101123?".to_string()];
+        let swipe = build_swipe(tracks.clone(), false);
+        let t1 = swipe.track1.unwrap();
+        assert_eq!((t1.pan.as_str(), t1.name.as_str(), t1.expiry.as_str(), t1.service_code.as_str()),
+            ("4111111111111111", "DOE/JOHN", "2512", "101"));
+        assert_eq!(swipe.track2.unwrap().expiry, "2512");
+        assert_eq!(swipe.raw, tracks);
+
+        let masked = build_swipe(tracks, true);
+        assert_eq!(masked.track2.unwrap().pan, "411111******1111");
+        assert!(masked.raw.iter().all(|t| !t.contains("4111")));
+    }
+}
@@ -0,0 +1,119 @@
+// --- 擷取 / 匯出檔案的命名樣板 ---
+// 例: "{device_alias}_{date}_{counter}" -> "scanner-A_20260315_3.csv"
+//
+// 欄位: {device_alias} {vid} {pid} {date} (YYYYMMDD) {time} (HHMMSS) {counter}
+// {counter:N} 會補零到 N 位數。樣板沒有 {counter} 時，檔名重複會自動加上 _2、_3...
+
+use chrono::Local;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_TEMPLATE: &str = "{device_alias}_{date}_{counter}";
+
+// 避免樣板寫錯時無限遞增
+const MAX_COUNTER: u32 = 100_000;
+
+pub struct NamingConfig {
+    pub template: String,
+    // 未設定時使用資料目錄下的 captures/，相對路徑以資料目錄為基準
+    pub directory: Option<PathBuf>,
+    // 設備路徑 -> 使用者取的別名
+    pub aliases: HashMap<String, String>,
+}
+
+impl Default for NamingConfig {
+    fn default() -> Self {
+        Self { template: DEFAULT_TEMPLATE.to_string(), directory: None, aliases: HashMap::new() }
+    }
+}
+
+// 套用樣板時需要的設備資訊
+pub struct NamingContext {
+    pub alias: String,
+    pub vendor_id: Option<u16>,
+    pub product_id: Option<u16>,
+}
+
+enum Token {
+    Text(String),
+    Alias,
+    Vid,
+    Pid,
+    Date,
+    Time,
+    Counter { width: usize },
+}
+
+fn parse(template: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        if start > 0 { tokens.push(Token::Text(rest[..start].to_string())); }
+        let end = rest[start..].find('}').ok_or("樣板的 { 沒有對應的 }")? + start;
+        let field = &rest[start + 1..end];
+        tokens.push(match field.split_once(':') {
+            Some(("counter", width)) => Token::Counter {
+                width: width.parse().map_err(|_| format!("無效的補零位數: {}", width))?,
+            },
+            None if field == "counter" => Token::Counter { width: 0 },
+            None if field == "device_alias" => Token::Alias,
+            None if field == "vid" => Token::Vid,
+            None if field == "pid" => Token::Pid,
+            None if field == "date" => Token::Date,
+            None if field == "time" => Token::Time,
+            _ => return Err(format!("未知的樣板欄位: {{{}}}", field)),
+        });
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() { tokens.push(Token::Text(rest.to_string())); }
+    if tokens.is_empty() { return Err("樣板不可為空".into()); }
+    Ok(tokens)
+}
+
+pub fn validate(template: &str) -> Result<(), String> {
+    parse(template).map(|_| ())
+}
+
+// 檔名不可含路徑分隔符號與 Windows 保留字元
+fn sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '_' } else { c })
+        .collect()
+}
+
+fn render(tokens: &[Token], ctx: &NamingContext, date: &str, time: &str, counter: u32) -> String {
+    let hex = |v: Option<u16>| v.map(|v| format!("{:04x}", v)).unwrap_or_else(|| "0000".into());
+    let name: String = tokens.iter()
+        .map(|t| match t {
+            Token::Text(s) => s.clone(),
+            Token::Alias => ctx.alias.clone(),
+            Token::Vid => hex(ctx.vendor_id),
+            Token::Pid => hex(ctx.product_id),
+            Token::Date => date.to_string(),
+            Token::Time => time.to_string(),
+            Token::Counter { width } => format!("{:0width$}", counter, width = *width),
+        })
+        .collect();
+    sanitize(&name)
+}
+
+// 產生 dir 下第一個不存在的檔名
+pub fn resolve(template: &str, dir: &Path, ctx: &NamingContext, extension: &str) -> Result<PathBuf, String> {
+    let tokens = parse(template)?;
+    let has_counter = tokens.iter().any(|t| matches!(t, Token::Counter { .. }));
+    let now = Local::now();
+    let (date, time) = (now.format("%Y%m%d").to_string(), now.format("%H%M%S").to_string());
+    let extension = extension.trim_start_matches('.');
+    let file_name = |stem: String| if extension.is_empty() { stem } else { format!("{}.{}", stem, extension) };
+
+    for counter in 1..=MAX_COUNTER {
+        let stem = match (has_counter, counter) {
+            (true, _) => render(&tokens, ctx, &date, &time, counter),
+            (false, 1) => render(&tokens, ctx, &date, &time, 0),
+            (false, _) => format!("{}_{}", render(&tokens, ctx, &date, &time, 0), counter),
+        };
+        let candidate = dir.join(file_name(stem));
+        if !candidate.exists() { return Ok(candidate); }
+    }
+    Err("找不到可用的檔名".into())
+}
@@ -0,0 +1,68 @@
+use crate::protocols::{ProfileStorage, Transport};
+use serde::Serialize;
+
+// --- Onboard profile 讀寫流程 ---
+
+#[derive(Serialize, Clone)]
+pub struct ProfileProgress {
+    pub path: String,
+    pub slot: u8,
+    pub phase: &'static str,
+    pub current: usize,
+    pub total: usize,
+}
+
+pub type ProgressFn<'a> = &'a mut dyn FnMut(&'static str, usize, usize);
+
+pub fn read_slot(storage: &dyn ProfileStorage, t: &dyn Transport, slot: u8, progress: ProgressFn) -> Result<Vec<u8>, String> {
+    read_range(storage, t, slot, storage.slot_size(), "read", progress)
+}
+
+pub fn write_slot(
+    storage: &dyn ProfileStorage,
+    t: &dyn Transport,
+    slot: u8,
+    data: &[u8],
+    verify: bool,
+    progress: ProgressFn,
+) -> Result<(), String> {
+    if data.len() > storage.slot_size() {
+        return Err(format!("資料長度 {} 超過 slot 容量 {}", data.len(), storage.slot_size()));
+    }
+
+    progress("erase", 0, 1);
+    storage.erase(t, slot)?;
+    progress("erase", 1, 1);
+
+    let mut offset = 0;
+    for chunk in data.chunks(storage.chunk_size()) {
+        storage.write_chunk(t, slot, offset, chunk)?;
+        offset += chunk.len();
+        progress("write", offset, data.len());
+    }
+
+    if verify {
+        let read_back = read_range(storage, t, slot, data.len(), "verify", progress)?;
+        if let Some(pos) = read_back.iter().zip(data).position(|(a, b)| a != b) {
+            return Err(format!("驗證失敗: offset {} 資料不符", pos));
+        }
+    }
+    Ok(())
+}
+
+fn read_range(
+    storage: &dyn ProfileStorage,
+    t: &dyn Transport,
+    slot: u8,
+    total: usize,
+    phase: &'static str,
+    progress: ProgressFn,
+) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(total);
+    while out.len() < total {
+        let len = std::cmp::min(storage.chunk_size(), total - out.len());
+        out.extend(storage.read_chunk(t, slot, out.len(), len)?);
+        progress(phase, out.len(), total);
+    }
+    Ok(out)
+}
@@ -0,0 +1,157 @@
+// --- 長時間操作與取消 ---
+// 韌體更新、分段傳輸、指令序列、壓力測試、延遲量測、擷取檔重播、檔案傳送與腳本執行時登記成一個 operation，各自持有取消旗標，
+// 在每個步驟 / 區塊 / 迭代之間檢查，因此取消後會在目前這一步完成時停止，不需要結束整個程式。
+// 這些指令在操作結束後才回傳，無法從回傳值取得 operation ID；需要取消時呼叫端必須自行帶入
+// operation_id 參數 (呼叫前就知道要取消哪一個)。未帶入時自動產生 ID，只在開始與結束時的 operation 事件
+// 與 list_operations 中出現，供顯示用 (韌體更新、序列與壓力測試另有依設備取消的指令)。
+//
+// { "id": "op-3", "kind": "stress", "path": "...", "started_ms": 0, "state": "started" }
+//
+// 執行中以 operation-progress 事件回報進度 (韌體更新、分段傳輸、重播、壓力測試、檔案傳送)，格式統一，
+// 前端用同一個進度元件顯示；同一階段內最多每 100 ms 一次，換階段與最後一步一定會送出。
+// total 未知時 (例如接收時還沒收到總長度) 為 None。
+//
+// { "id": "op-3", "kind": "firmware", "path": "...", "phase": "write", "current": 4096, "total": 65536, "message": null }
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Firmware,
+    Transfer,
+    Sequence,
+    Stress,
+    Benchmark,
+    Replay,
+    SendFile,
+    Script,
+}
+
+impl OperationKind {
+    fn label(self) -> &'static str {
+        match self {
+            OperationKind::Firmware => "韌體更新",
+            OperationKind::Transfer => "分段傳輸",
+            OperationKind::Sequence => "序列",
+            OperationKind::Stress => "壓力測試",
+            OperationKind::Benchmark => "延遲量測",
+            OperationKind::Replay => "重播",
+            OperationKind::SendFile => "檔案傳送",
+            OperationKind::Script => "腳本",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationState {
+    Started,
+    Finished,
+}
+
+#[derive(Serialize, Clone)]
+pub struct OperationInfo {
+    pub id: String,
+    pub kind: OperationKind,
+    // 與設備無關的操作 (腳本) 為 None
+    pub path: Option<String>,
+    pub started_ms: i64,
+    // 已要求取消、尚未停止
+    pub cancelling: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct OperationProgress {
+    pub id: String,
+    pub kind: OperationKind,
+    pub path: Option<String>,
+    // 目前階段 (例: erase、write、verify、send、receive、replay、stress)
+    pub phase: String,
+    pub current: u64,
+    pub total: Option<u64>,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct OperationEvent {
+    #[serde(flatten)]
+    pub operation: OperationInfo,
+    pub state: OperationState,
+}
+
+struct Operation {
+    info: OperationInfo,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Operation {
+    fn info(&self) -> OperationInfo {
+        OperationInfo { cancelling: self.cancel.load(Ordering::Relaxed), ..self.info.clone() }
+    }
+}
+
+#[derive(Default)]
+pub struct Operations {
+    next_id: u64,
+    running: Vec<Operation>,
+}
+
+impl Operations {
+    // 同一設備 (或沒有設備時全域) 同一種操作同時只能有一個
+    pub fn begin(&mut self, kind: OperationKind, path: Option<&str>, id: Option<String>) -> Result<(OperationInfo, Arc<AtomicBool>), String> {
+        if self.running.iter().any(|op| op.info.kind == kind && op.info.path.as_deref() == path) {
+            return Err(match path {
+                Some(_) => format!("此設備已有執行中的{}", kind.label()),
+                None => format!("已有執行中的{}", kind.label()),
+            });
+        }
+        let id = match id.filter(|id| !id.is_empty()) {
+            Some(id) if self.running.iter().any(|op| op.info.id == id) => return Err(format!("operation ID 重複: {}", id)),
+            Some(id) => id,
+            None => {
+                self.next_id += 1;
+                format!("op-{}", self.next_id)
+            }
+        };
+        let info = OperationInfo {
+            id,
+            kind,
+            path: path.map(str::to_string),
+            started_ms: chrono::Local::now().timestamp_millis(),
+            cancelling: false,
+        };
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.running.push(Operation { info: info.clone(), cancel: cancel.clone() });
+        Ok((info, cancel))
+    }
+
+    pub fn finish(&mut self, id: &str) -> Option<OperationInfo> {
+        let index = self.running.iter().position(|op| op.info.id == id)?;
+        Some(self.running.remove(index).info())
+    }
+
+    pub fn cancel(&self, id: &str) -> Result<(), String> {
+        let op = self.running.iter().find(|op| op.info.id == id).ok_or(format!("找不到執行中的操作: {}", id))?;
+        op.cancel.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    // 依種類與設備取消 (cancel_sequence 等舊的取消指令)
+    pub fn cancel_kind(&self, kind: OperationKind, path: Option<&str>) -> Result<(), String> {
+        let op = self.running.iter()
+            .find(|op| op.info.kind == kind && op.info.path.as_deref() == path)
+            .ok_or(match path {
+                Some(_) => format!("此設備沒有執行中的{}", kind.label()),
+                None => format!("沒有執行中的{}", kind.label()),
+            })?;
+        op.cancel.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<OperationInfo> {
+        self.running.iter().map(Operation::info).collect()
+    }
+}
@@ -0,0 +1,73 @@
+// --- 分頁查詢 ---
+// 保存在記憶體中的紀錄 (排程執行紀錄、服務記錄、測試結果) 以遞增的序號保存，查詢由新到舊分頁回傳。
+// cursor 是上一頁最後一筆的序號，下一頁從比它更舊的紀錄開始，因此查詢期間有新紀錄加入或舊紀錄被
+// 淘汰時，已取得的頁面不會重複或錯位。next_cursor 為 None 代表已經沒有更舊的紀錄。
+//
+// { "items": [...], "next_cursor": 1234 }
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+pub struct PageRequest {
+    pub cursor: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+impl PageRequest {
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<u64>,
+}
+
+// 有容量上限的紀錄，超過時淘汰最舊的
+pub struct SeqLog<T> {
+    entries: VecDeque<(u64, T)>,
+    next_seq: u64,
+    capacity: usize,
+}
+
+impl<T: Clone> SeqLog<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: VecDeque::new(), next_seq: 1, capacity }
+    }
+
+    pub fn push(&mut self, item: T) {
+        if self.entries.len() >= self.capacity { self.entries.pop_front(); }
+        self.entries.push_back((self.next_seq, item));
+        self.next_seq += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // 由舊到新
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+        self.entries.iter().map(|(_, item)| item)
+    }
+
+    // 由新到舊，只包含符合 filter 的紀錄
+    pub fn page(&self, request: PageRequest, filter: impl Fn(&T) -> bool) -> Page<T> {
+        let limit = request.limit();
+        let mut matching = self.entries.iter().rev()
+            .filter(|(seq, _)| request.cursor.is_none_or(|cursor| *seq < cursor))
+            .filter(|(_, item)| filter(item));
+        let items: Vec<&(u64, T)> = matching.by_ref().take(limit).collect();
+        let next_cursor = match matching.next() {
+            Some(_) => items.last().map(|(seq, _)| *seq),
+            None => None,
+        };
+        Page { items: items.into_iter().map(|(_, item)| item.clone()).collect(), next_cursor }
+    }
+}
@@ -0,0 +1,154 @@
+// --- 擷取檔轉 pcapng (Wireshark) ---
+// 以 LINKTYPE_USB_LINUX_MMAPPED (220) 輸出，每個 frame 包成一個 usbmon 的 64 位元組封包標頭加上 report：
+//   tx  URB_SUBMIT ('S')，interrupt OUT endpoint 0x01
+//   rx  URB_COMPLETE ('C')，interrupt IN endpoint 0x81
+// 擷取檔中的每個設備路徑依出現順序分配一個 device 編號 (bus 1)，對照表與擷取時的時鐘資訊寫在 section header 的註解中，
+// 每個封包的註解也帶有設備路徑與 frame 序號，以及該 frame 的註記 (見 annotations.rs)。Wireshark 可用 usb.device_address、usb.endpoint_address.direction、
+// usb.capdata 過濾；因為沒有錄到列舉過程，report 內容顯示為 capdata 而不會依 report descriptor 解析。
+// 真實的 endpoint 編號與 feature report (control transfer) 沒有記錄在擷取檔中，一律當成 interrupt 傳輸。
+// 所有欄位以 little-endian 寫入 (section header 的 byte-order magic 讓 Wireshark 以此解讀 usbmon 標頭)。
+
+use crate::annotations;
+use crate::capture::{self, Direction, Frame};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+const LINKTYPE_USB_LINUX_MMAPPED: u16 = 220;
+const USBMON_HEADER_LEN: usize = 64;
+
+const BLOCK_SHB: u32 = 0x0A0D_0D0A;
+const BLOCK_IDB: u32 = 0x0000_0001;
+const BLOCK_EPB: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+const OPT_END: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const OPT_SHB_USERAPPL: u16 = 4;
+const OPT_IF_NAME: u16 = 2;
+
+#[derive(Serialize, Clone)]
+pub struct PcapExport {
+    pub frames: u64,
+    // 設備路徑與對應的 USB device 編號
+    pub devices: Vec<(String, u8)>,
+}
+
+fn push_option(out: &mut Vec<u8>, code: u16, value: &[u8]) {
+    out.extend_from_slice(&code.to_le_bytes());
+    out.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    out.extend_from_slice(value);
+    out.resize(out.len().next_multiple_of(4), 0);
+}
+
+// option 長度欄位只有 16 位元；過長的註解在字元邊界截斷
+fn push_comment(out: &mut Vec<u8>, text: &str) {
+    let mut end = text.len().min(u16::MAX as usize);
+    while !text.is_char_boundary(end) { end -= 1; }
+    push_option(out, OPT_COMMENT, &text.as_bytes()[..end]);
+}
+
+// 區塊的前後各有一次總長度
+fn write_block(out: &mut impl Write, kind: u32, body: &[u8]) -> std::io::Result<()> {
+    let total = (12 + body.len()) as u32;
+    out.write_all(&kind.to_le_bytes())?;
+    out.write_all(&total.to_le_bytes())?;
+    out.write_all(body)?;
+    out.write_all(&total.to_le_bytes())
+}
+
+fn usbmon_header(frame: &Frame, devnum: u8) -> [u8; USBMON_HEADER_LEN] {
+    let mut h = [0u8; USBMON_HEADER_LEN];
+    let (urb_type, endpoint) = match frame.dir {
+        Direction::Tx => (b'S', 0x01),
+        Direction::Rx => (b'C', 0x81),
+    };
+    h[0..8].copy_from_slice(&frame.index.to_le_bytes());
+    h[8] = urb_type;
+    // interrupt
+    h[9] = 1;
+    h[10] = endpoint;
+    h[11] = devnum;
+    h[12..14].copy_from_slice(&1u16.to_le_bytes());
+    // 沒有 setup packet；flag_data 為 0 代表帶有資料
+    h[14] = b'-';
+    h[15] = 0;
+    h[16..24].copy_from_slice(&frame.wall_us.div_euclid(1_000_000).to_le_bytes());
+    h[24..28].copy_from_slice(&(frame.wall_us.rem_euclid(1_000_000) as i32).to_le_bytes());
+    // status 0、urb length 與 captured length 都是 report 長度
+    h[32..36].copy_from_slice(&(frame.data.len() as u32).to_le_bytes());
+    h[36..40].copy_from_slice(&(frame.data.len() as u32).to_le_bytes());
+    h
+}
+
+fn io_error(e: std::io::Error) -> String {
+    format!("寫入 pcapng 失敗: {}", e)
+}
+
+// 擷取檔讀兩次：第一次只取得設備清單 (section header 的對照表需要寫在最前面)，第二次逐筆轉換，不需要整份放進記憶體
+pub fn write(capture_file: &str, file: &str) -> Result<PcapExport, String> {
+    let mut devices: Vec<(String, u8)> = Vec::new();
+    let reader = capture::open(capture_file)?;
+    let clock = reader.header.clock.summary();
+    for frame in reader {
+        let frame = frame?;
+        if devices.iter().any(|(p, _)| *p == frame.path) { continue; }
+        if devices.len() >= 127 { return Err("擷取檔中的設備超過 127 個，無法對應 USB device 編號".into()); }
+        devices.push((frame.path.clone(), devices.len() as u8 + 1));
+    }
+
+    let handle = File::create(file).map_err(|e| format!("建立 {} 失敗: {}", file, e))?;
+    let mut out = BufWriter::new(handle);
+
+    let mut shb = Vec::new();
+    shb.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+    shb.extend_from_slice(&1u16.to_le_bytes());
+    shb.extend_from_slice(&0u16.to_le_bytes());
+    // section 長度未知
+    shb.extend_from_slice(&(-1i64).to_le_bytes());
+    let mapping = devices.iter().map(|(path, n)| format!("device {}: {}", n, path)).collect::<Vec<_>>().join("\n");
+    push_comment(&mut shb, &mapping);
+    push_comment(&mut shb, &clock);
+    push_option(&mut shb, OPT_SHB_USERAPPL, concat!("hid-master ", env!("CARGO_PKG_VERSION")).as_bytes());
+    push_option(&mut shb, OPT_END, &[]);
+    write_block(&mut out, BLOCK_SHB, &shb).map_err(io_error)?;
+
+    let mut idb = Vec::new();
+    idb.extend_from_slice(&LINKTYPE_USB_LINUX_MMAPPED.to_le_bytes());
+    idb.extend_from_slice(&0u16.to_le_bytes());
+    // snaplen 0 代表不限
+    idb.extend_from_slice(&0u32.to_le_bytes());
+    push_option(&mut idb, OPT_IF_NAME, b"usbmon1");
+    push_option(&mut idb, OPT_END, &[]);
+    write_block(&mut out, BLOCK_IDB, &idb).map_err(io_error)?;
+
+    let notes = annotations::load(capture_file)?;
+    let mut count = 0;
+    for frame in capture::open(capture_file)? {
+        let frame = frame?;
+        let devnum = devices.iter().find(|(p, _)| *p == frame.path).map_or(0, |(_, n)| *n);
+        let mut packet = usbmon_header(&frame, devnum).to_vec();
+        packet.extend_from_slice(&frame.data);
+        // 預設的時間解析度為微秒
+        let ts = frame.wall_us.max(0) as u64;
+        let mut epb = Vec::with_capacity(packet.len() + 64);
+        epb.extend_from_slice(&0u32.to_le_bytes());
+        epb.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+        epb.extend_from_slice(&(ts as u32).to_le_bytes());
+        epb.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&packet);
+        epb.resize(epb.len().next_multiple_of(4), 0);
+        let mut comment = format!("#{} {}", frame.index, frame.path);
+        if let Ok(i) = notes.binary_search_by_key(&frame.index, |n| n.index) {
+            let note = &notes[i];
+            comment.push_str(&format!("\n{}{}", if note.bookmark { "[書籤] " } else { "" }, note.text));
+        }
+        push_comment(&mut epb, &comment);
+        push_option(&mut epb, OPT_END, &[]);
+        write_block(&mut out, BLOCK_EPB, &epb).map_err(io_error)?;
+        count += 1;
+    }
+    out.flush().map_err(io_error)?;
+    Ok(PcapExport { frames: count, devices })
+}
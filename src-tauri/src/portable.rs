@@ -0,0 +1,43 @@
+// --- 可攜模式 ---
+// 供 USB 隨身碟部署到鎖定的工廠電腦：所有設定 (鍵盤配置、排程、常用設備、使用統計) 與
+// 擷取檔都放在執行檔旁的 data 目錄，不寫入 OS 的 app data 目錄。以下任一條件成立即啟用：
+//   - 執行檔旁有 portable 標記檔 (內容不拘)
+//   - 啟動參數 --portable
+//   - 環境變數 KEYSTONE_PORTABLE=1
+// 目錄結構：
+//   <執行檔目錄>/data/config    對應 app config 目錄
+//   <執行檔目錄>/data/captures  預設擷取目錄 (擷取目錄設為相對路徑時同樣以 data 為基準)
+// WebView 本身的快取與 localStorage 仍由系統管理。
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+const MARKER_FILE: &str = "portable";
+const FLAG: &str = "--portable";
+const ENV_VAR: &str = "KEYSTONE_PORTABLE";
+
+// 可攜模式時回傳資料根目錄 (<執行檔目錄>/data)
+pub fn detect() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+    let requested = exe_dir.join(MARKER_FILE).is_file()
+        || std::env::args().skip(1).any(|a| a == FLAG)
+        || std::env::var(ENV_VAR).is_ok_and(|v| v == "1");
+    requested.then(|| exe_dir.join("data"))
+}
+
+#[derive(Serialize, Clone)]
+pub struct StorageInfo {
+    pub portable: bool,
+    pub config_dir: String,
+    pub data_dir: String,
+}
+
+impl StorageInfo {
+    pub fn new(portable: bool, config_dir: &Path, data_dir: &Path) -> Self {
+        Self {
+            portable,
+            config_dir: config_dir.to_string_lossy().to_string(),
+            data_dir: data_dir.to_string_lossy().to_string(),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+// --- HID Power Device (UPS) 解析 ---
+// Power Device (0x84) 與 Battery System (0x85) usage page。
+// UPS 多半只在狀態改變時送 input report，電量、剩餘時間等數值需以 feature report 讀取。
+
+use crate::descriptor::{ReportDescriptor, ReportField, ReportKind};
+use crate::hid_io::{self, HidIo};
+use serde::Serialize;
+
+pub const PAGE_POWER_DEVICE: u16 = 0x84;
+pub const PAGE_BATTERY_SYSTEM: u16 = 0x85;
+
+const USAGE_VOLTAGE: u16 = 0x30;
+const USAGE_PERCENT_LOAD: u16 = 0x35;
+const USAGE_CHARGING: u16 = 0x44;
+const USAGE_DISCHARGING: u16 = 0x45;
+const USAGE_NEED_REPLACEMENT: u16 = 0x4B;
+const USAGE_REMAINING_CAPACITY: u16 = 0x66;
+const USAGE_RUNTIME_TO_EMPTY: u16 = 0x68;
+const USAGE_AC_PRESENT: u16 = 0xD0;
+
+// SI Linear 的電壓單位 (g·cm²/(s³·A))，1 V = 10^7 個基本單位
+const UNIT_VOLT: u32 = 0x00F0_D121;
+
+#[derive(Serialize, Clone, Default, PartialEq, Debug)]
+pub struct PowerStatus {
+    // 剩餘電量 (%)
+    pub battery_percent: Option<f64>,
+    // 預估剩餘供電時間 (秒)
+    pub runtime_s: Option<f64>,
+    // 市電輸入電壓 (V)
+    pub line_voltage: Option<f64>,
+    pub output_voltage: Option<f64>,
+    pub load_percent: Option<f64>,
+    pub ac_present: Option<bool>,
+    pub charging: Option<bool>,
+    pub discharging: Option<bool>,
+    pub need_replacement: Option<bool>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Quantity {
+    BatteryPercent,
+    Runtime,
+    LineVoltage,
+    OutputVoltage,
+    LoadPercent,
+    AcPresent,
+    Charging,
+    Discharging,
+    NeedReplacement,
+}
+
+#[derive(Clone)]
+struct Entry {
+    quantity: Quantity,
+    field: ReportField,
+    element: usize,
+}
+
+pub struct PowerLayout {
+    uses_report_ids: bool,
+    entries: Vec<Entry>,
+}
+
+// 依 unit exponent 換算成 SI 單位 (電壓另外除以 10^7)
+fn physical(field: &ReportField, raw: i64) -> f64 {
+    let exponent = field.unit_exponent - if field.unit == UNIT_VOLT { 7 } else { 0 };
+    raw as f64 * 10f64.powi(exponent)
+}
+
+impl PowerLayout {
+    // 沒有任何電源相關 usage 的設備回傳 None
+    pub fn from_descriptor(desc: &ReportDescriptor) -> Option<Self> {
+        let mut entries: Vec<Entry> = Vec::new();
+        for field in desc.fields.iter().filter(|f| f.kind != ReportKind::Output && !f.is_constant() && f.is_variable()) {
+            for (element, usage) in field.usages.iter().enumerate().take(field.count) {
+                let quantity = match (field.usage_page, *usage) {
+                    (PAGE_BATTERY_SYSTEM, USAGE_REMAINING_CAPACITY) => Quantity::BatteryPercent,
+                    (PAGE_BATTERY_SYSTEM, USAGE_RUNTIME_TO_EMPTY) => Quantity::Runtime,
+                    (PAGE_BATTERY_SYSTEM, USAGE_AC_PRESENT) => Quantity::AcPresent,
+                    (PAGE_BATTERY_SYSTEM, USAGE_CHARGING) => Quantity::Charging,
+                    (PAGE_BATTERY_SYSTEM, USAGE_DISCHARGING) => Quantity::Discharging,
+                    (PAGE_BATTERY_SYSTEM, USAGE_NEED_REPLACEMENT) => Quantity::NeedReplacement,
+                    (PAGE_POWER_DEVICE, USAGE_PERCENT_LOAD) => Quantity::LoadPercent,
+                    // descriptor 通常先宣告 Input collection 再宣告 Output collection
+                    (PAGE_POWER_DEVICE, USAGE_VOLTAGE) => {
+                        let seen = entries.iter()
+                            .filter(|e| e.field.kind == field.kind)
+                            .filter(|e| matches!(e.quantity, Quantity::LineVoltage | Quantity::OutputVoltage))
+                            .count();
+                        match seen {
+                            0 => Quantity::LineVoltage,
+                            1 => Quantity::OutputVoltage,
+                            _ => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                // 同一個量可能同時出現在 input 與 feature report，兩者都保留
+                entries.push(Entry { quantity, field: field.clone(), element });
+            }
+        }
+        if entries.is_empty() { return None; }
+        Some(Self { uses_report_ids: desc.uses_report_ids, entries })
+    }
+
+    // 把一筆 report (body 不含 Report ID) 中有出現的量填入 status，回傳是否有任何欄位
+    fn apply(&self, kind: ReportKind, report_id: u8, body: &[u8], status: &mut PowerStatus) -> bool {
+        let mut found = false;
+        for e in self.entries.iter().filter(|e| e.field.kind == kind && e.field.report_id == report_id) {
+            let Some(raw) = e.field.element_value(body, e.element) else { continue };
+            found = true;
+            let value = physical(&e.field, raw);
+            match e.quantity {
+                Quantity::BatteryPercent => status.battery_percent = Some(value),
+                Quantity::Runtime => status.runtime_s = Some(value),
+                Quantity::LineVoltage => status.line_voltage = Some(value),
+                Quantity::OutputVoltage => status.output_voltage = Some(value),
+                Quantity::LoadPercent => status.load_percent = Some(value),
+                Quantity::AcPresent => status.ac_present = Some(raw != 0),
+                Quantity::Charging => status.charging = Some(raw != 0),
+                Quantity::Discharging => status.discharging = Some(raw != 0),
+                Quantity::NeedReplacement => status.need_replacement = Some(raw != 0),
+            }
+        }
+        found
+    }
+
+    // 解析設備主動送出的 input report，只填入有出現的欄位
+    pub fn decode_input(&self, report: &[u8]) -> Option<PowerStatus> {
+        let (report_id, body) = if self.uses_report_ids { report.split_first().map(|(id, b)| (*id, b))? } else { (0, report) };
+        let mut status = PowerStatus::default();
+        self.apply(ReportKind::Input, report_id, body, &mut status).then_some(status)
+    }
+
+    // 讀取所有相關的 feature report
+    pub fn read(&self, dev: &dyn HidIo, desc: &ReportDescriptor) -> Result<PowerStatus, String> {
+        let ids = self.entries.iter()
+            .filter(|e| e.field.kind == ReportKind::Feature)
+            .map(|e| e.field.report_id);
+        let reports = hid_io::read_feature_reports(dev, desc, ids)?;
+        if reports.is_empty() { return Err("此設備沒有可讀取的電源 feature report".into()); }
+
+        let mut status = PowerStatus::default();
+        for (id, body) in reports {
+            self.apply(ReportKind::Feature, id, &body, &mut status);
+        }
+        Ok(status)
+    }
+}
+
+impl PowerStatus {
+    // 把新值併入目前狀態，有變化時回傳 true
+    pub fn merge(&mut self, update: &PowerStatus) -> bool {
+        fn take<T: Copy>(dst: &mut Option<T>, src: Option<T>) {
+            if src.is_some() { *dst = src; }
+        }
+        let before = self.clone();
+        take(&mut self.battery_percent, update.battery_percent);
+        take(&mut self.runtime_s, update.runtime_s);
+        take(&mut self.line_voltage, update.line_voltage);
+        take(&mut self.output_voltage, update.output_voltage);
+        take(&mut self.load_percent, update.load_percent);
+        take(&mut self.ac_present, update.ac_present);
+        take(&mut self.charging, update.charging);
+        take(&mut self.discharging, update.discharging);
+        take(&mut self.need_replacement, update.need_replacement);
+        *self != before
+    }
+}
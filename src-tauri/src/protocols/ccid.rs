@@ -0,0 +1,148 @@
+// --- CCID over HID (智慧卡讀卡機) ---
+// 部分讀卡機以 HID 介面傳送 CCID bulk 訊息：訊息依序切成固定長度的 report，
+// 回覆同樣以多個 report 拼回，長度由 10 bytes 標頭的 dwLength 決定。
+//   標頭: [bMessageType][dwLength 4 LE][bSlot][bSeq][3 bytes 指令參數 / bStatus bError bChainParameter]
+
+use super::Transport;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+
+const HEADER_LEN: usize = 10;
+// 與 CCID 規範的 dwMaxCCIDMessageLength 常見值相同
+const MAX_MESSAGE_LEN: usize = 271 + HEADER_LEN;
+
+const PC_TO_RDR_ICC_POWER_ON: u8 = 0x62;
+const PC_TO_RDR_XFR_BLOCK: u8 = 0x6F;
+const RDR_TO_PC_DATA_BLOCK: u8 = 0x80;
+
+// bStatus bit 6-7
+const STATUS_FAILED: u8 = 1;
+const STATUS_TIME_EXTENSION: u8 = 2;
+
+const INS_GET_RESPONSE: u8 = 0xC0;
+
+// 每個指令遞增，回覆的 bSeq 需相同
+static SEQ: AtomicU8 = AtomicU8::new(0);
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Voltage {
+    #[default]
+    Auto,
+    #[serde(rename = "5v")]
+    V5,
+    #[serde(rename = "3v")]
+    V3,
+    #[serde(rename = "1.8v")]
+    V1_8,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ApduResponse {
+    // 不含狀態字
+    pub data: Vec<u8>,
+    pub sw: u16,
+}
+
+fn error_name(code: u8) -> &'static str {
+    match code {
+        0xFF => "CMD_ABORTED",
+        0xFE => "ICC_MUTE",
+        0xFD => "XFR_PARITY_ERROR",
+        0xFC => "XFR_OVERRUN",
+        0xFB => "HW_ERROR",
+        0xF8 => "BAD_ATR_TS",
+        0xF7 => "BAD_ATR_TCK",
+        0xF6 => "ICC_PROTOCOL_NOT_SUPPORTED",
+        0xF5 => "ICC_CLASS_NOT_SUPPORTED",
+        0xF4 => "PROCEDURE_BYTE_CONFLICT",
+        0xF3 => "DEACTIVATED_PROTOCOL",
+        0xF2 => "BUSY_WITH_AUTO_SEQUENCE",
+        0xE0 => "CMD_SLOT_BUSY",
+        _ => "OTHER",
+    }
+}
+
+fn message(msg_type: u8, slot: u8, seq: u8, params: [u8; 3], data: &[u8]) -> Vec<u8> {
+    let mut out = vec![msg_type];
+    out.extend((data.len() as u32).to_le_bytes());
+    out.push(slot);
+    out.push(seq);
+    out.extend(params);
+    out.extend_from_slice(data);
+    out
+}
+
+// 讀取一則完整的回覆訊息
+fn receive(t: &dyn Transport, deadline: Instant) -> Result<Vec<u8>, String> {
+    let mut msg: Vec<u8> = Vec::new();
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() { return Err("等待讀卡機回覆逾時".into()); }
+        msg.extend(t.read(left.as_millis() as i32)?);
+        if msg.len() < HEADER_LEN { continue; }
+        let total = HEADER_LEN + u32::from_le_bytes([msg[1], msg[2], msg[3], msg[4]]) as usize;
+        if total > MAX_MESSAGE_LEN { return Err(format!("回覆長度 {} 超過上限", total)); }
+        if msg.len() >= total {
+            msg.truncate(total);
+            return Ok(msg);
+        }
+    }
+}
+
+// 送出一則訊息並回傳 RDR_to_PC_DataBlock 的資料；讀卡機要求延長時間時繼續等待
+fn transact(t: &dyn Transport, report_len: usize, msg_type: u8, slot: u8, params: [u8; 3], data: &[u8], timeout: Duration) -> Result<Vec<u8>, String> {
+    if HEADER_LEN + data.len() > MAX_MESSAGE_LEN {
+        return Err(format!("資料長度 {} 超過 CCID 訊息上限 {}", data.len(), MAX_MESSAGE_LEN - HEADER_LEN));
+    }
+    let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+    for chunk in message(msg_type, slot, seq, params, data).chunks(report_len) {
+        t.write(chunk)?;
+    }
+    let mut deadline = Instant::now() + timeout;
+    loop {
+        let resp = receive(t, deadline)?;
+        // 前一個逾時指令遲到的回覆
+        if resp[6] != seq { continue; }
+        if resp[0] != RDR_TO_PC_DATA_BLOCK {
+            return Err(format!("非預期的回覆類型 0x{:02X}", resp[0]));
+        }
+        match resp[7] >> 6 {
+            STATUS_TIME_EXTENSION => deadline = Instant::now() + timeout,
+            STATUS_FAILED => return Err(format!("CCID 錯誤 0x{:02X} ({})", resp[8], error_name(resp[8]))),
+            _ => return Ok(resp[HEADER_LEN..].to_vec()),
+        }
+    }
+}
+
+// 啟動卡片並回傳 ATR
+pub fn power_on(t: &dyn Transport, report_len: usize, slot: u8, voltage: Voltage, timeout: Duration) -> Result<Vec<u8>, String> {
+    let select = match voltage {
+        Voltage::Auto => 0,
+        Voltage::V5 => 1,
+        Voltage::V3 => 2,
+        Voltage::V1_8 => 3,
+    };
+    let atr = transact(t, report_len, PC_TO_RDR_ICC_POWER_ON, slot, [select, 0, 0], &[], timeout)?;
+    if atr.is_empty() { return Err("卡片沒有回傳 ATR".into()); }
+    Ok(atr)
+}
+
+// 傳送 APDU；SW1 為 0x61 (還有資料) 時自動以 GET RESPONSE 取回其餘資料
+pub fn transmit_apdu(t: &dyn Transport, report_len: usize, slot: u8, apdu: &[u8], timeout: Duration) -> Result<ApduResponse, String> {
+    if apdu.len() < 4 { return Err("APDU 至少需要 CLA INS P1 P2".into()); }
+    let mut data = Vec::new();
+    let mut command = apdu.to_vec();
+    loop {
+        let resp = transact(t, report_len, PC_TO_RDR_XFR_BLOCK, slot, [0, 0, 0], &command, timeout)?;
+        let Some((body, status)) = resp.split_last_chunk::<2>() else {
+            return Err(format!("回覆長度不足: {:02X?}", resp));
+        };
+        data.extend_from_slice(body);
+        if status[0] != 0x61 {
+            return Ok(ApduResponse { data, sw: u16::from_be_bytes(*status) });
+        }
+        command = vec![apdu[0], INS_GET_RESPONSE, 0, 0, status[1]];
+    }
+}
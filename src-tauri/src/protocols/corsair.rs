@@ -0,0 +1,70 @@
+// Corsair 滑鼠 / 鍵盤的燈光控制 (vendor 介面，舊版 CUE 協定)
+// 指令格式參考 ckb-next 專案的公開實作，每個指令為 64 bytes 的 output report：
+//   切換到軟體控制: 07 04 02       (設備預設為內建燈效，需先切換才會接受顏色)
+//   設定顏色:       07 22 <數量> 01 [區域 R G B]...
+//   整體亮度:       07 05 02 00 <0..3>
+// 動態燈效 (呼吸、波浪等) 由原廠軟體逐格送出，設備本身不支援，這裡只提供固定顏色。
+
+use super::{DeviceIdentity, LightingControl, LightingEffect, LightingZone, ProtocolPlugin, Rgb, Transport};
+
+const VENDOR_CORSAIR: u16 = 0x1B1C;
+
+const CMD_SET: u8 = 0x07;
+const FIELD_SPECIAL: u8 = 0x04;
+const FIELD_LIGHTING: u8 = 0x05;
+const FIELD_MOUSE_RGB: u8 = 0x22;
+const MODE_SOFTWARE: u8 = 0x02;
+
+const ZONES: [LightingZone; 6] = [
+    LightingZone { id: 0x01, name: "front" },
+    LightingZone { id: 0x02, name: "logo" },
+    LightingZone { id: 0x03, name: "dpi" },
+    LightingZone { id: 0x04, name: "wheel" },
+    LightingZone { id: 0x05, name: "thumb" },
+    LightingZone { id: 0x06, name: "side" },
+];
+
+pub struct CorsairPlugin;
+
+impl ProtocolPlugin for CorsairPlugin {
+    fn name(&self) -> &'static str { "corsair" }
+
+    fn description(&self) -> &'static str { "Corsair lighting, legacy CUE protocol (static color, brightness)" }
+
+    fn matches(&self, id: &DeviceIdentity) -> bool {
+        id.vendor_id == VENDOR_CORSAIR && id.usage_page >= 0xFF00
+    }
+
+    fn lighting(&self) -> Option<&dyn LightingControl> { Some(self) }
+}
+
+impl LightingControl for CorsairPlugin {
+    fn zones(&self) -> Vec<LightingZone> {
+        ZONES.to_vec()
+    }
+
+    fn set_effect(&self, t: &dyn Transport, zone: Option<u8>, effect: &LightingEffect) -> Result<(), String> {
+        let color = match effect {
+            LightingEffect::Off => Rgb { r: 0, g: 0, b: 0 },
+            LightingEffect::Static { color } => *color,
+            _ => return Err("Corsair 舊版協定只支援固定顏色，動態燈效需由軟體逐格送出".into()),
+        };
+        let zones: Vec<u8> = match zone {
+            Some(id) => vec![id],
+            None => ZONES.iter().map(|z| z.id).collect(),
+        };
+        t.write(&[CMD_SET, FIELD_SPECIAL, MODE_SOFTWARE])?;
+        let mut report = vec![CMD_SET, FIELD_MOUSE_RGB, zones.len() as u8, 0x01];
+        for id in zones {
+            report.extend([id, color.r, color.g, color.b]);
+        }
+        t.write(&report).map(|_| ())
+    }
+
+    // 只有整體亮度 (4 段)
+    fn set_brightness(&self, t: &dyn Transport, zone: Option<u8>, percent: u8) -> Result<(), String> {
+        if zone.is_some() { return Err("Corsair 只支援整體亮度".into()); }
+        let level = (percent.min(100) as u16 * 3).div_ceil(100) as u8;
+        t.write(&[CMD_SET, FIELD_LIGHTING, 0x02, 0x00, level]).map(|_| ())
+    }
+}
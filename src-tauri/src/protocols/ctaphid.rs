@@ -0,0 +1,235 @@
+// --- CTAPHID (FIDO2 / U2F 安全金鑰) ---
+// 訊息切成 64 位元組的 frame：
+//   初始 frame: [CID 4][CMD | 0x80][長度 H][長度 L][資料 57]
+//   後續 frame: [CID 4][SEQ 0..=0x7F][資料 59]
+// 每次送出前先在廣播通道上 INIT 取得新的通道 ID (CID)。
+
+use super::Transport;
+use serde::Serialize;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const FRAME_LEN: usize = 64;
+const INIT_DATA_LEN: usize = FRAME_LEN - 7;
+const CONT_DATA_LEN: usize = FRAME_LEN - 5;
+// 128 個後續 frame 的上限
+pub const MAX_PAYLOAD: usize = INIT_DATA_LEN + 128 * CONT_DATA_LEN;
+
+const BROADCAST_CID: u32 = 0xFFFF_FFFF;
+const CMD_INIT: u8 = 0x06;
+const CMD_KEEPALIVE: u8 = 0x3B;
+const CMD_ERROR: u8 = 0x3F;
+
+#[derive(Serialize, Clone)]
+pub struct CtapResponse {
+    pub cid: u32,
+    // 不含 0x80 旗標
+    pub cmd: u8,
+    pub payload: Vec<u8>,
+}
+
+fn error_name(code: u8) -> &'static str {
+    match code {
+        0x01 => "INVALID_CMD",
+        0x02 => "INVALID_PAR",
+        0x03 => "INVALID_LEN",
+        0x04 => "INVALID_SEQ",
+        0x05 => "MSG_TIMEOUT",
+        0x06 => "CHANNEL_BUSY",
+        0x0A => "LOCK_REQUIRED",
+        0x0B => "INVALID_CHANNEL",
+        _ => "OTHER",
+    }
+}
+
+fn frames(cid: u32, cmd: u8, payload: &[u8]) -> Vec<Vec<u8>> {
+    let mut out = Vec::new();
+    let (first, rest) = payload.split_at(payload.len().min(INIT_DATA_LEN));
+    let mut init = cid.to_be_bytes().to_vec();
+    init.push(cmd | 0x80);
+    init.extend((payload.len() as u16).to_be_bytes());
+    init.extend_from_slice(first);
+    out.push(init);
+    for (seq, chunk) in rest.chunks(CONT_DATA_LEN).enumerate() {
+        let mut cont = cid.to_be_bytes().to_vec();
+        cont.push(seq as u8);
+        cont.extend_from_slice(chunk);
+        out.push(cont);
+    }
+    out
+}
+
+// 讀取下一個屬於 cid 的 frame；其他通道的 frame 直接略過
+fn read_frame(t: &dyn Transport, cid: u32, deadline: Instant) -> Result<Vec<u8>, String> {
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() { return Err("等待 CTAPHID 回覆逾時".into()); }
+        let frame = t.read(left.as_millis() as i32)?;
+        if frame.len() >= 5 && frame[..4] == cid.to_be_bytes() {
+            return Ok(frame);
+        }
+    }
+}
+
+// 收完整個回覆；KEEPALIVE 會延後逾時 (使用者尚未觸碰金鑰)
+fn receive(t: &dyn Transport, cid: u32, timeout: Duration) -> Result<CtapResponse, String> {
+    let mut deadline = Instant::now() + timeout;
+    let init = loop {
+        let frame = read_frame(t, cid, deadline)?;
+        if frame[4] & 0x80 == 0 { return Err(format!("預期初始 frame，收到 SEQ {}", frame[4])); }
+        if frame[4] & 0x7F == CMD_KEEPALIVE {
+            deadline = Instant::now() + timeout;
+            continue;
+        }
+        break frame;
+    };
+    if init.len() < 7 { return Err("初始 frame 長度不足".into()); }
+
+    let cmd = init[4] & 0x7F;
+    let total = u16::from_be_bytes([init[5], init[6]]) as usize;
+    if total > MAX_PAYLOAD {
+        return Err(format!("回覆長度 {} 超過 CTAPHID 上限 {}", total, MAX_PAYLOAD));
+    }
+    let mut payload: Vec<u8> = init[7..].iter().take(total).copied().collect();
+    let mut seq = 0u8;
+    while payload.len() < total {
+        if seq > 0x7F { return Err("後續 frame 超過 128 個".into()); }
+        let frame = read_frame(t, cid, deadline)?;
+        if frame[4] != seq { return Err(format!("SEQ 錯誤: 預期 {}，收到 {}", seq, frame[4])); }
+        let need = total - payload.len();
+        payload.extend(frame[5..].iter().take(need));
+        seq += 1;
+    }
+
+    if cmd == CMD_ERROR {
+        let code = payload.first().copied().unwrap_or(0);
+        return Err(format!("CTAPHID 錯誤 0x{:02X} ({})", code, error_name(code)));
+    }
+    Ok(CtapResponse { cid, cmd, payload })
+}
+
+fn transact(t: &dyn Transport, cid: u32, cmd: u8, payload: &[u8], timeout: Duration) -> Result<CtapResponse, String> {
+    if payload.len() > MAX_PAYLOAD {
+        return Err(format!("資料長度 {} 超過 CTAPHID 上限 {}", payload.len(), MAX_PAYLOAD));
+    }
+    for frame in frames(cid, cmd & 0x7F, payload) {
+        t.write(&frame)?;
+    }
+    let resp = receive(t, cid, timeout)?;
+    if resp.cmd != cmd & 0x7F {
+        return Err(format!("回覆指令 0x{:02X} 與送出的 0x{:02X} 不符", resp.cmd, cmd & 0x7F));
+    }
+    Ok(resp)
+}
+
+fn nonce() -> [u8; 8] {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    (nanos as u64 ^ ((std::process::id() as u64) << 32)).to_le_bytes()
+}
+
+// 在廣播通道上配置新的 CID
+pub fn allocate_channel(t: &dyn Transport, timeout: Duration) -> Result<u32, String> {
+    let nonce = nonce();
+    let deadline = Instant::now() + timeout;
+    let mut resp = transact(t, BROADCAST_CID, CMD_INIT, &nonce, timeout)?;
+    loop {
+        // 其他程式同時在 INIT 時，回覆的 nonce 會不同
+        if resp.payload.get(..8) != Some(&nonce[..]) {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() { return Err("等待 CTAPHID INIT 回覆逾時".into()); }
+            resp = receive(t, BROADCAST_CID, left)?;
+            continue;
+        }
+        let cid = resp.payload.get(8..12).ok_or("INIT 回覆長度不足")?;
+        return Ok(u32::from_be_bytes([cid[0], cid[1], cid[2], cid[3]]));
+    }
+}
+
+// 送出一個 CTAPHID 指令並等待完整回覆；INIT 直接在廣播通道上送出
+pub fn send(t: &dyn Transport, cmd: u8, payload: &[u8], timeout: Duration) -> Result<CtapResponse, String> {
+    if cmd & 0x7F == CMD_INIT {
+        return transact(t, BROADCAST_CID, cmd, payload, timeout);
+    }
+    let cid = allocate_channel(t, timeout)?;
+    transact(t, cid, cmd, payload, timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+
+    // 依序回放預先排好的 frame，寫入的內容只記錄下來
+    struct Script {
+        reads: RefCell<VecDeque<Vec<u8>>>,
+        writes: RefCell<Vec<Vec<u8>>>,
+    }
+
+    impl Script {
+        fn new(reads: Vec<Vec<u8>>) -> Self {
+            Script { reads: RefCell::new(reads.into()), writes: RefCell::new(Vec::new()) }
+        }
+    }
+
+    impl Transport for Script {
+        fn write(&self, data: &[u8]) -> Result<usize, String> {
+            self.writes.borrow_mut().push(data.to_vec());
+            Ok(data.len())
+        }
+        fn read(&self, _timeout_ms: i32) -> Result<Vec<u8>, String> {
+            self.reads.borrow_mut().pop_front().ok_or_else(|| "沒有更多 frame".to_string())
+        }
+    }
+
+    const CID: u32 = 0x0102_0304;
+    const TIMEOUT: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn frames_round_trip_through_receive() {
+        let payload: Vec<u8> = (0..200u16).map(|i| i as u8).collect();
+        let out = frames(CID, 0x10, &payload);
+        // 57 + 59 + 59 + 25
+        assert_eq!(out.len(), 4);
+        assert_eq!(out[0][4], 0x90);
+        assert_eq!(out[2][4], 1);
+        let resp = receive(&Script::new(out), CID, TIMEOUT).unwrap();
+        assert_eq!((resp.cid, resp.cmd), (CID, 0x10));
+        assert_eq!(resp.payload, payload);
+    }
+
+    #[test]
+    fn max_payload_fills_all_continuation_frames() {
+        let out = frames(CID, 0x10, &vec![0xAB; MAX_PAYLOAD]);
+        assert_eq!(out.len(), 129);
+        assert_eq!(out[128][4], 0x7F);
+        assert_eq!(receive(&Script::new(out), CID, TIMEOUT).unwrap().payload.len(), MAX_PAYLOAD);
+    }
+
+    #[test]
+    fn rejects_oversized_lengths() {
+        let t = Script::new(Vec::new());
+        assert!(transact(&t, CID, 0x10, &vec![0; MAX_PAYLOAD + 1], TIMEOUT).is_err());
+        assert!(t.writes.borrow().is_empty());
+
+        let mut init = CID.to_be_bytes().to_vec();
+        init.push(0x90);
+        init.extend(((MAX_PAYLOAD + 1) as u16).to_be_bytes());
+        assert!(receive(&Script::new(vec![init]), CID, TIMEOUT).is_err_and(|e| e.contains("上限")));
+    }
+
+    #[test]
+    fn skips_other_channels_and_keepalive() {
+        let mut keepalive = CID.to_be_bytes().to_vec();
+        keepalive.extend([0x80 | CMD_KEEPALIVE, 0, 1, 2]);
+        let mut reads = frames(0x0A0B_0C0D, 0x10, &[9; 4]);
+        reads.push(keepalive);
+        reads.extend(frames(CID, 0x10, &[1, 2, 3]));
+        assert_eq!(receive(&Script::new(reads), CID, TIMEOUT).unwrap().payload, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn reports_error_frames() {
+        let reads = frames(CID, CMD_ERROR, &[0x06]);
+        assert!(receive(&Script::new(reads), CID, TIMEOUT).is_err_and(|e| e.contains("CHANNEL_BUSY")));
+    }
+}
@@ -0,0 +1,264 @@
+// --- Logitech HID++ 2.0 ---
+// 短 report: [0x10][設備][feature index][function << 4 | sw id][參數 3]
+// 長 report: [0x11][設備][feature index][function << 4 | sw id][參數 16]
+// feature index 由 Root feature (0x0000) 依 feature ID 查詢，每台設備不同。
+// 設備編號 0xFF 為直接連接 (USB / 藍牙)，接收器上的設備為 1..=6。
+
+use crate::hid_io::HidIo;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+pub const DEVICE_DIRECT: u8 = 0xFF;
+
+const REPORT_SHORT: u8 = 0x10;
+const REPORT_LONG: u8 = 0x11;
+const SHORT_LEN: usize = 7;
+const LONG_LEN: usize = 20;
+// 回覆會帶回同樣的 sw id，用來辨認是不是我們送出的請求
+const SW_ID: u8 = 0x0A;
+const ERROR_20: u8 = 0xFF;
+const ERROR_10: u8 = 0x8F;
+
+pub const FEATURE_ROOT: u16 = 0x0000;
+pub const FEATURE_SET: u16 = 0x0001;
+pub const FEATURE_BATTERY_STATUS: u16 = 0x1000;
+pub const FEATURE_BATTERY_VOLTAGE: u16 = 0x1001;
+pub const FEATURE_UNIFIED_BATTERY: u16 = 0x1004;
+pub const FEATURE_ADJUSTABLE_DPI: u16 = 0x2201;
+
+pub fn feature_name(id: u16) -> &'static str {
+    match id {
+        0x0000 => "Root",
+        0x0001 => "Feature Set",
+        0x0003 => "Device FW Version",
+        0x0005 => "Device Name",
+        0x0007 => "Device Friendly Name",
+        0x0020 => "Config Change",
+        0x1000 => "Battery Status",
+        0x1001 => "Battery Voltage",
+        0x1004 => "Unified Battery",
+        0x1300 => "LED Control",
+        0x1814 => "Change Host",
+        0x1815 => "Hosts Info",
+        0x1b04 => "Reprogrammable Keys v4",
+        0x1d4b => "Wireless Device Status",
+        0x2100 => "Vertical Scrolling",
+        0x2110 => "Smart Shift",
+        0x2121 => "Hi-Res Wheel",
+        0x2201 => "Adjustable DPI",
+        0x2205 => "Pointer Speed",
+        0x40a3 => "Fn Inversion",
+        0x4520 => "Keyboard Layout",
+        0x8060 => "Report Rate",
+        0x8070 => "Color LED Effects",
+        0x8100 => "Onboard Profiles",
+        _ => "Unknown",
+    }
+}
+
+fn error_name(code: u8) -> &'static str {
+    match code {
+        0x01 => "Unknown",
+        0x02 => "InvalidArgument",
+        0x03 => "OutOfRange",
+        0x04 => "HWError",
+        0x05 => "LogitechInternal",
+        0x06 => "InvalidFeatureIndex",
+        0x07 => "InvalidFunctionID",
+        0x08 => "Busy",
+        0x09 => "Unsupported",
+        _ => "Other",
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct FeatureInfo {
+    pub index: u8,
+    pub id: u16,
+    pub name: &'static str,
+    pub version: u8,
+    // feature type 旗標
+    pub obsolete: bool,
+    pub hidden: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct HidppInfo {
+    pub protocol_major: u8,
+    pub protocol_minor: u8,
+    pub features: Vec<FeatureInfo>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct BatteryInfo {
+    // 使用的 feature (0x1004 / 0x1000 / 0x1001)
+    pub feature: u16,
+    // 電量百分比 (只回報電壓的設備為 None)
+    pub level: Option<u8>,
+    pub voltage_mv: Option<u16>,
+    pub charging: bool,
+    pub status: &'static str,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DpiInfo {
+    pub sensor: u8,
+    pub sensor_count: u8,
+    pub dpi: u16,
+    pub default_dpi: u16,
+}
+
+pub struct Hidpp<'a> {
+    dev: &'a dyn HidIo,
+    device_index: u8,
+    // 介面有短 report (0x10) 時才使用，藍牙直連的設備通常只有長 report
+    short: bool,
+    timeout: Duration,
+}
+
+impl<'a> Hidpp<'a> {
+    pub fn new(dev: &'a dyn HidIo, device_index: u8, short: bool, timeout: Duration) -> Self {
+        Self { dev, device_index, short, timeout }
+    }
+
+    // 參數不超過 3 bytes 時用短 report，其餘用長 report；
+    // 回傳回覆的參數部分 (補零到 16 bytes，短 report 的回覆也能以相同位置讀取)
+    pub fn request(&self, feature_index: u8, function: u8, params: &[u8]) -> Result<Vec<u8>, String> {
+        if params.len() > LONG_LEN - 4 {
+            return Err(format!("HID++ 參數長度 {} 超過 {}", params.len(), LONG_LEN - 4));
+        }
+        let (report_id, len) = if self.short && params.len() <= SHORT_LEN - 4 { (REPORT_SHORT, SHORT_LEN) } else { (REPORT_LONG, LONG_LEN) };
+        let func = (function << 4) | SW_ID;
+        let mut buf = vec![0u8; len];
+        buf[..4].copy_from_slice(&[report_id, self.device_index, feature_index, func]);
+        buf[4..4 + params.len()].copy_from_slice(params);
+        self.dev.write(&buf).map_err(|e| format!("寫入失敗: {}", e))?;
+
+        let deadline = Instant::now() + self.timeout;
+        let mut resp = [0u8; LONG_LEN];
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() { return Err("等待 HID++ 回覆逾時".into()); }
+            let n = self.dev.read_timeout(&mut resp, left.as_millis() as i32).map_err(|e| format!("讀取異常: {}", e))?;
+            if n < SHORT_LEN || !matches!(resp[0], REPORT_SHORT | REPORT_LONG) || resp[1] != self.device_index {
+                continue;
+            }
+            match (resp[2], resp[3], resp[4]) {
+                (ERROR_20, fi, f) if fi == feature_index && f == func => {
+                    return Err(format!("HID++ 錯誤 0x{:02X} ({})", resp[5], error_name(resp[5])));
+                }
+                (ERROR_10, fi, f) if fi == feature_index && f == func => {
+                    return Err(format!("設備不支援 HID++ 2.0 (HID++ 1.0 錯誤 0x{:02X})", resp[5]));
+                }
+                (fi, f, _) if fi == feature_index && f == func => {
+                    let mut params = resp[4..n].to_vec();
+                    params.resize(LONG_LEN - 4, 0);
+                    return Ok(params);
+                }
+                // 設備主動送出的通知或其他程式的回覆
+                _ => continue,
+            }
+        }
+    }
+
+    // (major, minor)
+    pub fn protocol_version(&self) -> Result<(u8, u8), String> {
+        let resp = self.request(0, 1, &[0, 0, 0x5A])?;
+        Ok((resp[0], resp[1]))
+    }
+
+    // 設備不支援此 feature 時回傳 None
+    pub fn feature_index(&self, id: u16) -> Result<Option<u8>, String> {
+        let resp = self.request(0, 0, &id.to_be_bytes())?;
+        Ok(Some(resp[0]).filter(|i| *i != 0 || id == FEATURE_ROOT))
+    }
+
+    fn require(&self, id: u16) -> Result<u8, String> {
+        self.feature_index(id)?.ok_or(format!("設備不支援 feature 0x{:04X} ({})", id, feature_name(id)))
+    }
+
+    pub fn info(&self) -> Result<HidppInfo, String> {
+        let (protocol_major, protocol_minor) = self.protocol_version()?;
+        let set = self.require(FEATURE_SET)?;
+        let count = self.request(set, 0, &[])?[0];
+        let mut features = vec![FeatureInfo { index: 0, id: FEATURE_ROOT, name: feature_name(FEATURE_ROOT), version: 0, obsolete: false, hidden: false }];
+        for index in 1..=count {
+            let resp = self.request(set, 1, &[index])?;
+            let id = u16::from_be_bytes([resp[0], resp[1]]);
+            features.push(FeatureInfo {
+                index,
+                id,
+                name: feature_name(id),
+                version: resp[3],
+                obsolete: resp[2] & 0x80 != 0,
+                hidden: resp[2] & 0x40 != 0,
+            });
+        }
+        Ok(HidppInfo { protocol_major, protocol_minor, features })
+    }
+
+    // 依序嘗試 Unified Battery、Battery Status、Battery Voltage
+    pub fn battery(&self) -> Result<BatteryInfo, String> {
+        if let Some(index) = self.feature_index(FEATURE_UNIFIED_BATTERY)? {
+            let resp = self.request(index, 1, &[])?;
+            let status = match resp[2] {
+                0 => "discharging",
+                1 => "charging",
+                2 => "charging_slow",
+                3 => "full",
+                _ => "error",
+            };
+            return Ok(BatteryInfo {
+                feature: FEATURE_UNIFIED_BATTERY,
+                level: Some(resp[0]),
+                voltage_mv: None,
+                charging: matches!(resp[2], 1 | 2),
+                status,
+            });
+        }
+        if let Some(index) = self.feature_index(FEATURE_BATTERY_STATUS)? {
+            let resp = self.request(index, 0, &[])?;
+            let status = match resp[2] {
+                0 => "discharging",
+                1 => "charging",
+                2 => "almost_full",
+                3 => "full",
+                4 => "charging_slow",
+                5 => "invalid_battery",
+                _ => "error",
+            };
+            return Ok(BatteryInfo {
+                feature: FEATURE_BATTERY_STATUS,
+                level: Some(resp[0]),
+                voltage_mv: None,
+                charging: matches!(resp[2], 1 | 2 | 4),
+                status,
+            });
+        }
+        let index = self.require(FEATURE_BATTERY_VOLTAGE)?;
+        let resp = self.request(index, 0, &[])?;
+        let charging = resp[2] & 0x80 != 0;
+        Ok(BatteryInfo {
+            feature: FEATURE_BATTERY_VOLTAGE,
+            level: None,
+            voltage_mv: Some(u16::from_be_bytes([resp[0], resp[1]])),
+            charging,
+            status: if charging { "charging" } else { "discharging" },
+        })
+    }
+
+    pub fn dpi(&self, sensor: u8) -> Result<DpiInfo, String> {
+        let index = self.require(FEATURE_ADJUSTABLE_DPI)?;
+        let sensor_count = self.request(index, 0, &[])?[0];
+        if sensor >= sensor_count {
+            return Err(format!("sensor {} 不存在 (共 {} 個)", sensor, sensor_count));
+        }
+        let resp = self.request(index, 2, &[sensor])?;
+        Ok(DpiInfo {
+            sensor,
+            sensor_count,
+            dpi: u16::from_be_bytes([resp[1], resp[2]]),
+            default_dpi: u16::from_be_bytes([resp[3], resp[4]]),
+        })
+    }
+}
@@ -0,0 +1,232 @@
+// --- 協定插件 ---
+// 每個插件描述一種廠商協定，並透過 Transport 與設備溝通。
+
+pub mod ccid;
+pub mod corsair;
+pub mod ctaphid;
+pub mod hidpp;
+pub mod onboard_memory;
+pub mod qmk_via;
+pub mod razer;
+pub mod steelseries_arctis;
+
+use crate::hid_io::HidIo;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Clone, Copy)]
+pub struct DeviceIdentity {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub usage_page: u16,
+    pub usage: u16,
+    pub interface_number: i32,
+    // USB bcdDevice (常作為韌體版本)
+    pub release_number: u16,
+}
+
+// 插件與設備之間的 I/O 抽象
+pub trait Transport {
+    // 寫入一個 output report (不含 Report ID)，回傳實際寫入的位元組數
+    fn write(&self, data: &[u8]) -> Result<usize, String>;
+    // 讀取一個 input report，逾時回傳空 Vec
+    fn read(&self, timeout_ms: i32) -> Result<Vec<u8>, String>;
+
+    fn exchange(&self, data: &[u8], timeout_ms: i32) -> Result<Vec<u8>, String> {
+        self.write(data)?;
+        let resp = self.read(timeout_ms)?;
+        if resp.is_empty() { return Err("設備沒有回應".into()); }
+        Ok(resp)
+    }
+
+    // 送出 feature report (data[0] 為 Report ID)
+    fn send_feature(&self, _data: &[u8]) -> Result<(), String> {
+        Err("此傳輸不支援 feature report".into())
+    }
+    // 讀取 feature report，回傳內容包含 Report ID
+    fn get_feature(&self, _report_id: u8, _len: usize) -> Result<Vec<u8>, String> {
+        Err("此傳輸不支援 feature report".into())
+    }
+}
+
+// 以 Report ID 0x00 + 固定長度送出的 HID 傳輸
+pub struct HidTransport<'a> {
+    dev: &'a dyn HidIo,
+    report_len: usize,
+}
+
+impl<'a> HidTransport<'a> {
+    pub fn new(dev: &'a dyn HidIo, report_len: usize) -> Self {
+        Self { dev, report_len }
+    }
+}
+
+impl Transport for HidTransport<'_> {
+    fn write(&self, data: &[u8]) -> Result<usize, String> {
+        if data.len() > self.report_len {
+            return Err(format!("資料長度 {} 超過 report 長度 {}", data.len(), self.report_len));
+        }
+        let mut buf = vec![0u8; self.report_len + 1];
+        buf[1..data.len() + 1].copy_from_slice(data);
+        self.dev.write(&buf).map_err(|e| format!("寫入失敗: {}", e))
+    }
+
+    fn read(&self, timeout_ms: i32) -> Result<Vec<u8>, String> {
+        let mut buf = vec![0u8; self.report_len];
+        let n = self.dev.read_timeout(&mut buf, timeout_ms).map_err(|e| format!("讀取異常: {}", e))?;
+        buf.truncate(n);
+        Ok(buf)
+    }
+
+    fn send_feature(&self, data: &[u8]) -> Result<(), String> {
+        self.dev.send_feature_report(data).map_err(|e| format!("送出 feature report 失敗: {}", e))
+    }
+
+    fn get_feature(&self, report_id: u8, len: usize) -> Result<Vec<u8>, String> {
+        let mut buf = vec![0u8; len];
+        buf[0] = report_id;
+        let n = self.dev.get_feature_report(&mut buf).map_err(|e| format!("讀取 feature report 失敗: {}", e))?;
+        buf.truncate(n);
+        Ok(buf)
+    }
+}
+
+// 設備內建記憶體 (onboard profile) 的存取介面
+pub trait ProfileStorage {
+    fn slot_count(&self) -> u8;
+    fn slot_size(&self) -> usize;
+    // 單次傳輸可讀寫的最大位元組數
+    fn chunk_size(&self) -> usize;
+    fn read_chunk(&self, t: &dyn Transport, slot: u8, offset: usize, len: usize) -> Result<Vec<u8>, String>;
+    fn write_chunk(&self, t: &dyn Transport, slot: u8, offset: usize, data: &[u8]) -> Result<(), String>;
+    fn erase(&self, t: &dyn Transport, slot: u8) -> Result<(), String>;
+}
+
+// 耳機 / 音訊 dongle 控制
+#[derive(Serialize, Clone, Default, PartialEq)]
+pub struct HeadsetStatus {
+    pub battery: Option<u8>,
+    // 0 = 全遊戲, 64 = 平衡, 128 = 全語音
+    pub chatmix: Option<u8>,
+    pub mic_muted: Option<bool>,
+}
+
+impl HeadsetStatus {
+    // 把新值併入目前狀態，回傳實際有變化的欄位
+    pub fn merge(&mut self, update: &HeadsetStatus) -> Option<HeadsetStatus> {
+        let mut changed = HeadsetStatus::default();
+        if update.battery.is_some() && update.battery != self.battery {
+            self.battery = update.battery;
+            changed.battery = update.battery;
+        }
+        if update.chatmix.is_some() && update.chatmix != self.chatmix {
+            self.chatmix = update.chatmix;
+            changed.chatmix = update.chatmix;
+        }
+        if update.mic_muted.is_some() && update.mic_muted != self.mic_muted {
+            self.mic_muted = update.mic_muted;
+            changed.mic_muted = update.mic_muted;
+        }
+        if changed == HeadsetStatus::default() { None } else { Some(changed) }
+    }
+}
+
+pub trait HeadsetControl {
+    fn set_sidetone(&self, t: &dyn Transport, level: u8) -> Result<(), String>;
+    fn query_status(&self, t: &dyn Transport) -> Result<HeadsetStatus, String>;
+    // 解析設備主動送出的 report (按鍵/旋鈕變化)，只填入有出現的欄位
+    fn decode_report(&self, data: &[u8]) -> Option<HeadsetStatus>;
+}
+
+// RGB 燈光控制
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+// 可個別控制的燈光區域 (例: logo、滾輪)
+#[derive(Serialize, Clone)]
+pub struct LightingZone {
+    pub id: u8,
+    pub name: &'static str,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LightingEffect {
+    Off,
+    Static { color: Rgb },
+    // color 未指定時由設備隨機變換顏色
+    Breathing { #[serde(default)] color: Option<Rgb> },
+    // 色彩循環
+    Spectrum,
+    Wave { #[serde(default)] reverse: bool },
+    // 按下按鍵時亮起；speed 1 (快) ..= 4 (慢)
+    Reactive { color: Rgb, #[serde(default = "default_reactive_speed")] speed: u8 },
+}
+
+fn default_reactive_speed() -> u8 { 2 }
+
+pub trait LightingControl {
+    fn zones(&self) -> Vec<LightingZone>;
+    // zone 為 None 時套用到整個設備
+    fn set_effect(&self, t: &dyn Transport, zone: Option<u8>, effect: &LightingEffect) -> Result<(), String>;
+    fn set_color(&self, t: &dyn Transport, zone: Option<u8>, color: Rgb) -> Result<(), String> {
+        self.set_effect(t, zone, &LightingEffect::Static { color })
+    }
+    // 0..=100
+    fn set_brightness(&self, t: &dyn Transport, zone: Option<u8>, percent: u8) -> Result<(), String>;
+}
+
+pub trait ProtocolPlugin: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    // 是否能自動套用在此設備上
+    fn matches(&self, id: &DeviceIdentity) -> bool;
+
+    fn profile_storage(&self) -> Option<&dyn ProfileStorage> { None }
+    fn headset(&self) -> Option<&dyn HeadsetControl> { None }
+    fn lighting(&self) -> Option<&dyn LightingControl> { None }
+    // 向設備查詢韌體版本；不支援時回傳 None (改用 bcdDevice)
+    fn firmware_version(&self, _t: &dyn Transport) -> Result<Option<String>, String> { Ok(None) }
+}
+
+#[derive(Serialize, Clone)]
+pub struct ProtocolInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub profile_storage: bool,
+    pub headset: bool,
+    pub lighting: bool,
+}
+
+pub fn all_plugins() -> Vec<Box<dyn ProtocolPlugin>> {
+    vec![
+        Box::new(onboard_memory::OnboardMemoryPlugin),
+        Box::new(steelseries_arctis::ArctisPlugin),
+        Box::new(razer::RazerPlugin),
+        Box::new(corsair::CorsairPlugin),
+    ]
+}
+
+// 指定名稱時直接查找，否則依設備資訊自動偵測
+pub fn resolve(name: Option<&str>, id: &DeviceIdentity) -> Result<Box<dyn ProtocolPlugin>, String> {
+    let mut plugins = all_plugins().into_iter();
+    match name {
+        Some(n) => plugins.find(|p| p.name() == n).ok_or(format!("未知的協定: {}", n)),
+        None => plugins.find(|p| p.matches(id)).ok_or("找不到適用此設備的協定".into()),
+    }
+}
+
+pub fn list() -> Vec<ProtocolInfo> {
+    all_plugins().iter()
+        .map(|p| ProtocolInfo {
+            name: p.name(),
+            description: p.description(),
+            profile_storage: p.profile_storage().is_some(),
+            headset: p.headset().is_some(),
+            lighting: p.lighting().is_some(),
+        })
+        .collect()
+}
@@ -0,0 +1,71 @@
+// 通用 onboard memory 協定
+// 請求: [cmd, slot, offset_hi, offset_lo, len, data...]
+// 回覆: [cmd, status, len, data...]，status 0x00 代表成功
+
+use super::{DeviceIdentity, ProfileStorage, ProtocolPlugin, Transport};
+
+const CMD_READ: u8 = 0x10;
+const CMD_WRITE: u8 = 0x11;
+const CMD_ERASE: u8 = 0x12;
+
+const SLOT_COUNT: u8 = 4;
+const SLOT_SIZE: usize = 1024;
+const CHUNK_SIZE: usize = 56;
+const TIMEOUT_MS: i32 = 500;
+
+pub struct OnboardMemoryPlugin;
+
+impl OnboardMemoryPlugin {
+    fn request(&self, t: &dyn Transport, cmd: u8, slot: u8, offset: usize, len: usize, data: &[u8]) -> Result<Vec<u8>, String> {
+        if slot >= SLOT_COUNT { return Err(format!("slot {} 不存在", slot)); }
+        if offset + len > SLOT_SIZE { return Err("超出 slot 範圍".into()); }
+
+        let mut req = vec![cmd, slot, (offset >> 8) as u8, offset as u8, len as u8];
+        req.extend_from_slice(data);
+        let resp = t.exchange(&req, TIMEOUT_MS)?;
+
+        if resp.len() < 3 || resp[0] != cmd {
+            return Err(format!("非預期的回覆: {:02X?}", resp));
+        }
+        if resp[1] != 0x00 {
+            return Err(format!("設備回報錯誤 status={:#04x}", resp[1]));
+        }
+        let n = std::cmp::min(resp[2] as usize, resp.len() - 3);
+        Ok(resp[3..3 + n].to_vec())
+    }
+}
+
+impl ProtocolPlugin for OnboardMemoryPlugin {
+    fn name(&self) -> &'static str { "onboard-memory" }
+
+    fn description(&self) -> &'static str { "Generic onboard profile memory (read/write/erase)" }
+
+    // 沒有可靠的識別方式，只能手動指定
+    fn matches(&self, _id: &DeviceIdentity) -> bool { false }
+
+    fn profile_storage(&self) -> Option<&dyn ProfileStorage> { Some(self) }
+}
+
+impl ProfileStorage for OnboardMemoryPlugin {
+    fn slot_count(&self) -> u8 { SLOT_COUNT }
+
+    fn slot_size(&self) -> usize { SLOT_SIZE }
+
+    fn chunk_size(&self) -> usize { CHUNK_SIZE }
+
+    fn read_chunk(&self, t: &dyn Transport, slot: u8, offset: usize, len: usize) -> Result<Vec<u8>, String> {
+        let data = self.request(t, CMD_READ, slot, offset, len, &[])?;
+        if data.len() != len {
+            return Err(format!("讀取長度不符: 預期 {}，實際 {}", len, data.len()));
+        }
+        Ok(data)
+    }
+
+    fn write_chunk(&self, t: &dyn Transport, slot: u8, offset: usize, data: &[u8]) -> Result<(), String> {
+        self.request(t, CMD_WRITE, slot, offset, data.len(), data).map(|_| ())
+    }
+
+    fn erase(&self, t: &dyn Transport, slot: u8) -> Result<(), String> {
+        self.request(t, CMD_ERASE, slot, 0, 0, &[]).map(|_| ())
+    }
+}
@@ -0,0 +1,157 @@
+// --- QMK raw HID / VIA ---
+// 鍵盤的 raw HID 介面 (usage page 0xFF60, usage 0x61)，每個 report 固定 32 bytes，
+// 第一個 byte 為指令 ID，設備以相同 ID 回覆 (不支援時回 0xFF)。
+// keymap 為 [layer][row][col] 的 16-bit keycode (big-endian)，矩陣大小需由鍵盤定義檔得知。
+
+use super::Transport;
+use crate::usages::key_name;
+use serde::Serialize;
+
+pub const USAGE_PAGE_RAW_HID: u16 = 0xFF60;
+pub const REPORT_LEN: usize = 32;
+
+const CMD_GET_PROTOCOL_VERSION: u8 = 0x01;
+const CMD_GET_KEYBOARD_VALUE: u8 = 0x02;
+const CMD_GET_KEYCODE: u8 = 0x04;
+const CMD_SET_KEYCODE: u8 = 0x05;
+const CMD_GET_LAYER_COUNT: u8 = 0x11;
+const CMD_GET_BUFFER: u8 = 0x12;
+const CMD_UNHANDLED: u8 = 0xFF;
+
+const VALUE_UPTIME: u8 = 0x01;
+const VALUE_FIRMWARE_VERSION: u8 = 0x04;
+// 單次 get_buffer 可讀取的最大位元組數 (32 - 4 bytes 標頭)
+const BUFFER_CHUNK: usize = REPORT_LEN - 4;
+const TIMEOUT_MS: i32 = 500;
+
+// 此版本之後 keycode 使用 QMK 0.19 的編號
+const PROTOCOL_V12: u16 = 0x000C;
+
+#[derive(Serialize, Clone)]
+pub struct ViaInfo {
+    pub protocol_version: u16,
+    pub layer_count: u8,
+    pub uptime_ms: u32,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Keycode {
+    pub code: u16,
+    // 例: "A"、"MO(1)"、"LCtrl+C"；無法辨識時為十六進位
+    pub name: String,
+}
+
+fn mods_name(mods: u16) -> String {
+    const NAMES: [&str; 4] = ["Ctrl", "Shift", "Alt", "GUI"];
+    let side = if mods & 0x10 != 0 { "R" } else { "L" };
+    NAMES.iter().enumerate()
+        .filter(|(i, _)| mods & (1 << i) != 0)
+        .map(|(_, n)| format!("{}{}", side, n))
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+fn basic_name(code: u16) -> String {
+    match code {
+        0x0000 => "KC_NO".into(),
+        0x0001 => "KC_TRNS".into(),
+        _ => key_name(code).unwrap_or_else(|| format!("0x{:04X}", code)),
+    }
+}
+
+pub fn keycode_name(code: u16, protocol_version: u16) -> String {
+    if code <= 0x00FF {
+        return basic_name(code);
+    }
+    if protocol_version < PROTOCOL_V12 {
+        return format!("0x{:04X}", code);
+    }
+    let layer = code & 0x1F;
+    match code {
+        0x0100..=0x1FFF => format!("{}+{}", mods_name(code >> 8), basic_name(code & 0xFF)),
+        0x2000..=0x3FFF => format!("MT({}, {})", mods_name((code >> 8) & 0x1F), basic_name(code & 0xFF)),
+        0x4000..=0x4FFF => format!("LT({}, {})", (code >> 8) & 0x0F, basic_name(code & 0xFF)),
+        0x5200..=0x521F => format!("TO({})", layer),
+        0x5220..=0x523F => format!("MO({})", layer),
+        0x5240..=0x525F => format!("DF({})", layer),
+        0x5260..=0x527F => format!("TG({})", layer),
+        0x5280..=0x529F => format!("OSL({})", layer),
+        0x52C0..=0x52DF => format!("TT({})", layer),
+        _ => format!("0x{:04X}", code),
+    }
+}
+
+fn command(t: &dyn Transport, request: &[u8]) -> Result<Vec<u8>, String> {
+    let resp = t.exchange(request, TIMEOUT_MS)?;
+    match resp.first() {
+        Some(&CMD_UNHANDLED) => Err(format!("鍵盤不支援指令 0x{:02X}", request[0])),
+        Some(id) if *id == request[0] => Ok(resp),
+        _ => Err(format!("非預期的回覆: {:02X?}", resp)),
+    }
+}
+
+pub fn protocol_version(t: &dyn Transport) -> Result<u16, String> {
+    let resp = command(t, &[CMD_GET_PROTOCOL_VERSION])?;
+    let b = resp.get(1..3).ok_or("回覆長度不足")?;
+    Ok(u16::from_be_bytes([b[0], b[1]]))
+}
+
+pub fn info(t: &dyn Transport) -> Result<ViaInfo, String> {
+    let protocol_version = protocol_version(t)?;
+    let layer_count = *command(t, &[CMD_GET_LAYER_COUNT])?.get(1).ok_or("回覆長度不足")?;
+    let resp = command(t, &[CMD_GET_KEYBOARD_VALUE, VALUE_UPTIME])?;
+    let b = resp.get(2..6).ok_or("回覆長度不足")?;
+    Ok(ViaInfo { protocol_version, layer_count, uptime_ms: u32::from_be_bytes([b[0], b[1], b[2], b[3]]) })
+}
+
+// 韌體以 VIA_FIRMWARE_VERSION 設定的 32 位元版本號；未設定 (0) 時回傳 None
+pub fn firmware_version(t: &dyn Transport) -> Result<Option<String>, String> {
+    let resp = command(t, &[CMD_GET_KEYBOARD_VALUE, VALUE_FIRMWARE_VERSION])?;
+    let b = resp.get(2..6).ok_or("回覆長度不足")?;
+    let version = u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+    Ok((version != 0).then(|| version.to_string()))
+}
+
+pub fn get_keycode(t: &dyn Transport, layer: u8, row: u8, col: u8) -> Result<Keycode, String> {
+    let version = protocol_version(t)?;
+    let resp = command(t, &[CMD_GET_KEYCODE, layer, row, col])?;
+    let b = resp.get(4..6).ok_or("回覆長度不足")?;
+    let code = u16::from_be_bytes([b[0], b[1]]);
+    Ok(Keycode { code, name: keycode_name(code, version) })
+}
+
+pub fn set_keycode(t: &dyn Transport, layer: u8, row: u8, col: u8, code: u16) -> Result<(), String> {
+    let [hi, lo] = code.to_be_bytes();
+    command(t, &[CMD_SET_KEYCODE, layer, row, col, hi, lo])?;
+    Ok(())
+}
+
+// 以 dynamic keymap buffer 一次讀出整層，回傳 [row][col]
+pub fn get_layer(t: &dyn Transport, layer: u8, rows: u8, cols: u8) -> Result<Vec<Vec<Keycode>>, String> {
+    if rows == 0 || cols == 0 { return Err("矩陣大小不能為 0".into()); }
+    let layer_count = *command(t, &[CMD_GET_LAYER_COUNT])?.get(1).ok_or("回覆長度不足")?;
+    if layer >= layer_count {
+        return Err(format!("layer {} 不存在 (共 {} 層)", layer, layer_count));
+    }
+    let version = protocol_version(t)?;
+    let layer_size = rows as usize * cols as usize * 2;
+    let start = layer as usize * layer_size;
+    let mut data = Vec::with_capacity(layer_size);
+    while data.len() < layer_size {
+        let offset = start + data.len();
+        let size = (layer_size - data.len()).min(BUFFER_CHUNK);
+        // 動態 keymap 緩衝區以 16 位元位址存取
+        let offset = u16::try_from(offset).map_err(|_| format!("layer {} 超出 keymap 緩衝區的位址範圍", layer))?;
+        let [hi, lo] = offset.to_be_bytes();
+        let resp = command(t, &[CMD_GET_BUFFER, hi, lo, size as u8])?;
+        data.extend_from_slice(resp.get(4..4 + size).ok_or("回覆長度不足")?);
+    }
+    Ok(data.chunks(cols as usize * 2)
+        .map(|row| row.chunks(2)
+            .map(|b| {
+                let code = u16::from_be_bytes([b[0], b[1]]);
+                Keycode { code, name: keycode_name(code, version) }
+            })
+            .collect())
+        .collect())
+}
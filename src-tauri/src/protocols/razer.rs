@@ -0,0 +1,129 @@
+// Razer 滑鼠 / 鍵盤的燈光控制 (control 介面的 feature report)
+// 指令格式參考 OpenRazer 專案的公開實作，使用 extended matrix 指令 (較新的 Chroma 設備)：
+//   0 狀態  1 transaction ID  2-3 剩餘封包數  4 protocol type  5 參數長度  6 command class  7 command ID
+//   8-87 參數  88 CRC (byte 2..88 的 XOR)  89 保留
+// 送出後讀回同樣格式的 report，狀態 0x02 代表成功。
+
+use super::{DeviceIdentity, LightingControl, LightingEffect, LightingZone, ProtocolPlugin, Rgb, Transport};
+use std::thread;
+use std::time::Duration;
+
+const VENDOR_RAZER: u16 = 0x1532;
+
+const REPORT_LEN: usize = 90;
+const TRANSACTION_ID: u8 = 0x1F;
+
+const STATUS_BUSY: u8 = 0x01;
+const STATUS_SUCCESS: u8 = 0x02;
+const STATUS_FAILURE: u8 = 0x03;
+const STATUS_TIMEOUT: u8 = 0x04;
+const STATUS_NOT_SUPPORTED: u8 = 0x05;
+
+const CLASS_MATRIX: u8 = 0x0F;
+const CMD_EFFECT: u8 = 0x02;
+const CMD_BRIGHTNESS: u8 = 0x04;
+
+// 設定存入設備 (重新插拔後保留)
+const VARSTORE: u8 = 0x01;
+// 整個設備
+const LED_ALL: u8 = 0x00;
+
+const EFFECT_NONE: u8 = 0x00;
+const EFFECT_STATIC: u8 = 0x01;
+const EFFECT_BREATHING: u8 = 0x02;
+const EFFECT_SPECTRUM: u8 = 0x03;
+const EFFECT_WAVE: u8 = 0x04;
+const EFFECT_REACTIVE: u8 = 0x05;
+
+// 設備處理指令的時間
+const RESPONSE_DELAY_MS: u64 = 10;
+const BUSY_RETRIES: usize = 10;
+
+pub struct RazerPlugin;
+
+fn build_report(class: u8, command: u8, args: &[u8]) -> Vec<u8> {
+    let mut report = vec![0u8; REPORT_LEN];
+    report[1] = TRANSACTION_ID;
+    report[5] = args.len() as u8;
+    report[6] = class;
+    report[7] = command;
+    report[8..8 + args.len()].copy_from_slice(args);
+    report[88] = report[2..88].iter().fold(0, |a, b| a ^ b);
+    report
+}
+
+fn send(t: &dyn Transport, class: u8, command: u8, args: &[u8]) -> Result<(), String> {
+    let mut data = vec![0x00];
+    data.extend(build_report(class, command, args));
+    t.send_feature(&data)?;
+    for _ in 0..BUSY_RETRIES {
+        thread::sleep(Duration::from_millis(RESPONSE_DELAY_MS));
+        let resp = t.get_feature(0x00, REPORT_LEN + 1)?;
+        // 部分平台回傳的內容不含 Report ID
+        let resp = if resp.len() > REPORT_LEN { &resp[1..] } else { &resp[..] };
+        if resp.len() < 8 { return Err("回覆長度不足".into()); }
+        match resp[0] {
+            STATUS_SUCCESS => return Ok(()),
+            STATUS_BUSY => continue,
+            STATUS_FAILURE => return Err("設備回報指令失敗".into()),
+            STATUS_TIMEOUT => return Err("設備回報指令逾時".into()),
+            STATUS_NOT_SUPPORTED => return Err("此設備不支援這個指令".into()),
+            status => return Err(format!("未知的狀態 {:#04x}", status)),
+        }
+    }
+    Err("設備持續忙碌".into())
+}
+
+impl ProtocolPlugin for RazerPlugin {
+    fn name(&self) -> &'static str { "razer" }
+
+    fn description(&self) -> &'static str { "Razer Chroma lighting (static, breathing, spectrum, wave, reactive, brightness)" }
+
+    // 指令送往第一個介面 (鍵盤 / 滑鼠本身)
+    fn matches(&self, id: &DeviceIdentity) -> bool {
+        id.vendor_id == VENDOR_RAZER && id.interface_number <= 0
+    }
+
+    fn lighting(&self) -> Option<&dyn LightingControl> { Some(self) }
+}
+
+impl LightingControl for RazerPlugin {
+    fn zones(&self) -> Vec<LightingZone> {
+        vec![
+            LightingZone { id: 0x01, name: "scroll_wheel" },
+            LightingZone { id: 0x04, name: "logo" },
+            LightingZone { id: 0x05, name: "backlight" },
+        ]
+    }
+
+    fn set_effect(&self, t: &dyn Transport, zone: Option<u8>, effect: &LightingEffect) -> Result<(), String> {
+        let led = zone.unwrap_or(LED_ALL);
+        let rgb = |c: &Rgb| [c.r, c.g, c.b];
+        let mut args = vec![VARSTORE, led];
+        match effect {
+            LightingEffect::Off => args.extend([EFFECT_NONE, 0, 0, 0]),
+            LightingEffect::Static { color } => {
+                args.extend([EFFECT_STATIC, 0, 0, 0x01]);
+                args.extend(rgb(color));
+            }
+            LightingEffect::Breathing { color: Some(color) } => {
+                args.extend([EFFECT_BREATHING, 0x01, 0, 0x01]);
+                args.extend(rgb(color));
+            }
+            LightingEffect::Breathing { color: None } => args.extend([EFFECT_BREATHING, 0, 0, 0]),
+            LightingEffect::Spectrum => args.extend([EFFECT_SPECTRUM, 0, 0, 0]),
+            LightingEffect::Wave { reverse } => args.extend([EFFECT_WAVE, if *reverse { 0x02 } else { 0x01 }, 0x28, 0]),
+            LightingEffect::Reactive { color, speed } => {
+                if !(1..=4).contains(speed) { return Err("reactive 速度需介於 1..=4".into()); }
+                args.extend([EFFECT_REACTIVE, 0, *speed, 0x01]);
+                args.extend(rgb(color));
+            }
+        }
+        send(t, CLASS_MATRIX, CMD_EFFECT, &args)
+    }
+
+    fn set_brightness(&self, t: &dyn Transport, zone: Option<u8>, percent: u8) -> Result<(), String> {
+        let level = (percent.min(100) as u16 * 255 / 100) as u8;
+        send(t, CLASS_MATRIX, CMD_BRIGHTNESS, &[VARSTORE, zone.unwrap_or(LED_ALL), level])
+    }
+}
@@ -0,0 +1,75 @@
+// SteelSeries Arctis 無線耳機 dongle (vendor 介面)
+// 指令格式參考 HeadsetControl 專案的公開實作
+
+use super::{DeviceIdentity, HeadsetControl, HeadsetStatus, ProtocolPlugin, Transport};
+
+const VENDOR_STEELSERIES: u16 = 0x1038;
+// Arctis 7 / 7 2019 / Pro Wireless 等 dongle
+const PRODUCT_IDS: [u16; 4] = [0x1260, 0x12ad, 0x1252, 0x12b3];
+
+const CMD_PREFIX: u8 = 0x06;
+const CMD_SIDETONE: u8 = 0x35;
+const CMD_BATTERY: u8 = 0x18;
+const CMD_CHATMIX: u8 = 0x24;
+const CMD_MIC_MUTE: u8 = 0x30;
+
+const MAX_SIDETONE: u8 = 0x12;
+const TIMEOUT_MS: i32 = 500;
+
+pub struct ArctisPlugin;
+
+// 設備分別回報遊戲 / 語音的衰減量 (各 0..64)，合併回單一滑桿值 (0..128)
+fn combine_chatmix(game: u8, chat: u8) -> u8 {
+    let game = std::cmp::min(game, 64) as i16;
+    let chat = std::cmp::min(chat, 64) as i16;
+    (64 + game - chat) as u8
+}
+
+impl ProtocolPlugin for ArctisPlugin {
+    fn name(&self) -> &'static str { "steelseries-arctis" }
+
+    fn description(&self) -> &'static str { "SteelSeries Arctis wireless headset (sidetone, battery, chatmix, mic mute)" }
+
+    fn matches(&self, id: &DeviceIdentity) -> bool {
+        id.vendor_id == VENDOR_STEELSERIES && PRODUCT_IDS.contains(&id.product_id) && id.usage_page >= 0xFF00
+    }
+
+    fn headset(&self) -> Option<&dyn HeadsetControl> { Some(self) }
+}
+
+impl HeadsetControl for ArctisPlugin {
+    fn set_sidetone(&self, t: &dyn Transport, level: u8) -> Result<(), String> {
+        if level > MAX_SIDETONE {
+            return Err(format!("側音等級需介於 0..={}", MAX_SIDETONE));
+        }
+        let cmd = if level == 0 {
+            vec![CMD_PREFIX, CMD_SIDETONE, 0x00]
+        } else {
+            vec![CMD_PREFIX, CMD_SIDETONE, 0x01, 0x00, level]
+        };
+        t.write(&cmd).map(|_| ())
+    }
+
+    fn query_status(&self, t: &dyn Transport) -> Result<HeadsetStatus, String> {
+        let mut status = HeadsetStatus::default();
+        for cmd in [CMD_BATTERY, CMD_CHATMIX, CMD_MIC_MUTE] {
+            let resp = t.exchange(&[CMD_PREFIX, cmd], TIMEOUT_MS)?;
+            if let Some(partial) = self.decode_report(&resp) {
+                status.merge(&partial);
+            }
+        }
+        Ok(status)
+    }
+
+    fn decode_report(&self, data: &[u8]) -> Option<HeadsetStatus> {
+        if data.len() < 3 || data[0] != CMD_PREFIX { return None; }
+        let mut status = HeadsetStatus::default();
+        match data[1] {
+            CMD_BATTERY => status.battery = Some(std::cmp::min(data[2], 100)),
+            CMD_CHATMIX if data.len() >= 4 => status.chatmix = Some(combine_chatmix(data[2], data[3])),
+            CMD_MIC_MUTE => status.mic_muted = Some(data[2] != 0),
+            _ => return None,
+        }
+        Some(status)
+    }
+}
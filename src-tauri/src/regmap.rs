@@ -0,0 +1,253 @@
+// --- Register map 設備模型 ---
+// 以 JSON 宣告暫存器 (位址、寬度、存取權限、編碼)，並依 request 樣板產生讀寫指令。
+//
+// {
+//   "name": "tc08",
+//   "read":  { "template": [1, "{addr:u16le}", "{width}"], "response_prefix": [1], "response_offset": 3 },
+//   "write": { "template": [2, "{addr:u16le}", "{width}", "{value}"], "response_prefix": [2] },
+//   "registers": [
+//     { "name": "temperature", "address": 16, "encoding": "i16le", "access": "r", "scale": 0.1, "unit": "C",
+//       "format": { "style": "fixed", "decimals": 1 } },
+//     { "name": "state", "address": 18, "encoding": "u8", "access": "r",
+//       "labels": { "0": "Idle", "1": "Charging", "2": "Fault" } }
+//   ]
+// }
+
+use crate::format::{check_labels, ValueFormat};
+use crate::protocols::Transport;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    U8, I8,
+    U16le, U16be, I16le, I16be,
+    U32le, U32be, I32le, I32be,
+    F32le, F32be,
+}
+
+impl Encoding {
+    pub fn width(&self) -> usize {
+        match self {
+            Encoding::U8 | Encoding::I8 => 1,
+            Encoding::U16le | Encoding::U16be | Encoding::I16le | Encoding::I16be => 2,
+            _ => 4,
+        }
+    }
+
+    // 整數編碼可表示的範圍，浮點數回傳 None
+    pub fn range(&self) -> Option<(i64, i64)> {
+        Some(match self {
+            Encoding::U8 => (0, u8::MAX as i64),
+            Encoding::I8 => (i8::MIN as i64, i8::MAX as i64),
+            Encoding::U16le | Encoding::U16be => (0, u16::MAX as i64),
+            Encoding::I16le | Encoding::I16be => (i16::MIN as i64, i16::MAX as i64),
+            Encoding::U32le | Encoding::U32be => (0, u32::MAX as i64),
+            Encoding::I32le | Encoding::I32be => (i32::MIN as i64, i32::MAX as i64),
+            Encoding::F32le | Encoding::F32be => return None,
+        })
+    }
+
+    pub fn decode(&self, b: &[u8]) -> Option<f64> {
+        let b2 = || b.get(..2).map(|s| [s[0], s[1]]);
+        let b4 = || b.get(..4).map(|s| [s[0], s[1], s[2], s[3]]);
+        Some(match self {
+            Encoding::U8 => *b.first()? as f64,
+            Encoding::I8 => *b.first()? as i8 as f64,
+            Encoding::U16le => u16::from_le_bytes(b2()?) as f64,
+            Encoding::U16be => u16::from_be_bytes(b2()?) as f64,
+            Encoding::I16le => i16::from_le_bytes(b2()?) as f64,
+            Encoding::I16be => i16::from_be_bytes(b2()?) as f64,
+            Encoding::U32le => u32::from_le_bytes(b4()?) as f64,
+            Encoding::U32be => u32::from_be_bytes(b4()?) as f64,
+            Encoding::I32le => i32::from_le_bytes(b4()?) as f64,
+            Encoding::I32be => i32::from_be_bytes(b4()?) as f64,
+            Encoding::F32le => f32::from_le_bytes(b4()?) as f64,
+            Encoding::F32be => f32::from_be_bytes(b4()?) as f64,
+        })
+    }
+
+    pub fn encode(&self, v: f64) -> Vec<u8> {
+        let v = if matches!(self, Encoding::F32le | Encoding::F32be) { v } else { v.round() };
+        match self {
+            Encoding::U8 => vec![v as u8],
+            Encoding::I8 => vec![v as i8 as u8],
+            Encoding::U16le => (v as u16).to_le_bytes().to_vec(),
+            Encoding::U16be => (v as u16).to_be_bytes().to_vec(),
+            Encoding::I16le => (v as i16).to_le_bytes().to_vec(),
+            Encoding::I16be => (v as i16).to_be_bytes().to_vec(),
+            Encoding::U32le => (v as u32).to_le_bytes().to_vec(),
+            Encoding::U32be => (v as u32).to_be_bytes().to_vec(),
+            Encoding::I32le => (v as i32).to_le_bytes().to_vec(),
+            Encoding::I32be => (v as i32).to_be_bytes().to_vec(),
+            Encoding::F32le => (v as f32).to_le_bytes().to_vec(),
+            Encoding::F32be => (v as f32).to_be_bytes().to_vec(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    R,
+    W,
+    Rw,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Register {
+    pub name: String,
+    pub address: u32,
+    pub encoding: Encoding,
+    #[serde(default = "default_access")]
+    pub access: Access,
+    // 實際值 = 原始值 * scale + offset
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    #[serde(default)]
+    pub offset: f64,
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub format: ValueFormat,
+    // 原始值 -> 狀態名稱 (例: 0 = Idle, 1 = Charging)
+    #[serde(default)]
+    pub labels: BTreeMap<i64, String>,
+}
+
+fn default_access() -> Access { Access::Rw }
+fn default_scale() -> f64 { 1.0 }
+
+// 樣板元素：固定位元組或 "{addr:u16le}" / "{width}" / "{value}" 佔位字
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum TemplateItem {
+    Byte(u8),
+    Placeholder(String),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RequestFormat {
+    pub template: Vec<TemplateItem>,
+    // 回覆必須以這些位元組開頭
+    #[serde(default)]
+    pub response_prefix: Vec<u8>,
+    // 回覆中資料開始的位置 (讀取用)
+    #[serde(default)]
+    pub response_offset: usize,
+    #[serde(default = "default_timeout")]
+    pub timeout_ms: i32,
+}
+
+fn default_timeout() -> i32 { 500 }
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RegisterMap {
+    pub name: String,
+    pub read: RequestFormat,
+    pub write: Option<RequestFormat>,
+    pub registers: Vec<Register>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct RegisterValue {
+    pub name: String,
+    pub raw: Vec<u8>,
+    pub value: f64,
+    // 原始值對應的狀態名稱
+    pub label: Option<String>,
+    // 有 label 時為 label，否則依 format 產生
+    pub display: String,
+    pub unit: Option<String>,
+}
+
+fn expand(template: &[TemplateItem], reg: &Register, value: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    for item in template {
+        match item {
+            TemplateItem::Byte(b) => out.push(*b),
+            TemplateItem::Placeholder(p) => {
+                let inner = p.trim_start_matches('{').trim_end_matches('}');
+                let (key, fmt) = inner.split_once(':').unwrap_or((inner, "u8"));
+                match key {
+                    "addr" => {
+                        let enc: Encoding = serde_json::from_value(serde_json::Value::String(fmt.into()))
+                            .map_err(|_| format!("未知的位址格式: {}", fmt))?;
+                        out.extend(enc.encode(reg.address as f64));
+                    }
+                    "width" => out.push(reg.encoding.width() as u8),
+                    "value" => out.extend_from_slice(value),
+                    _ => return Err(format!("未知的樣板欄位: {}", p)),
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+impl RegisterMap {
+    pub fn load(file: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(file).map_err(|e| format!("讀取 {} 失敗: {}", file, e))?;
+        let map: RegisterMap = serde_json::from_str(&text).map_err(|e| format!("register map 格式錯誤: {}", e))?;
+        map.validate()?;
+        Ok(map)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let mut names: Vec<&str> = self.registers.iter().map(|r| r.name.as_str()).collect();
+        names.sort_unstable();
+        if let Some(w) = names.windows(2).find(|w| w[0] == w[1]) {
+            return Err(format!("暫存器名稱重複: {}", w[0]));
+        }
+        if self.write.is_none() && self.registers.iter().any(|r| r.access != Access::R) {
+            return Err("有可寫入的暫存器但沒有定義 write 格式".into());
+        }
+        for reg in &self.registers {
+            check_labels(&reg.name, reg.encoding, &reg.labels)?;
+        }
+        Ok(())
+    }
+
+    fn register(&self, name: &str) -> Result<&Register, String> {
+        self.registers.iter().find(|r| r.name == name).ok_or(format!("找不到暫存器: {}", name))
+    }
+
+    pub fn read(&self, t: &dyn Transport, name: &str) -> Result<RegisterValue, String> {
+        let reg = self.register(name)?;
+        if reg.access == Access::W { return Err(format!("{} 為唯寫暫存器", name)); }
+
+        let req = expand(&self.read.template, reg, &[])?;
+        let resp = t.exchange(&req, self.read.timeout_ms)?;
+        if !resp.starts_with(&self.read.response_prefix) {
+            return Err(format!("非預期的回覆: {:02X?}", resp));
+        }
+        let width = reg.encoding.width();
+        let raw = resp.get(self.read.response_offset..self.read.response_offset + width)
+            .ok_or("回覆長度不足")?
+            .to_vec();
+        let decoded = reg.encoding.decode(&raw).ok_or("回覆長度不足")?;
+        let value = decoded * reg.scale + reg.offset;
+        let label = reg.labels.get(&(decoded as i64)).cloned();
+        let display = label.clone().unwrap_or_else(|| reg.format.apply(decoded, width as u32 * 8, value));
+        Ok(RegisterValue { name: reg.name.clone(), raw, value, label, display, unit: reg.unit.clone() })
+    }
+
+    pub fn write(&self, t: &dyn Transport, name: &str, value: f64) -> Result<(), String> {
+        let reg = self.register(name)?;
+        if reg.access == Access::R { return Err(format!("{} 為唯讀暫存器", name)); }
+        let fmt = self.write.as_ref().ok_or("沒有定義 write 格式")?;
+
+        let raw = reg.encoding.encode((value - reg.offset) / reg.scale);
+        let req = expand(&fmt.template, reg, &raw)?;
+        if fmt.response_prefix.is_empty() {
+            t.write(&req)?;
+            return Ok(());
+        }
+        let resp = t.exchange(&req, fmt.timeout_ms)?;
+        if !resp.starts_with(&fmt.response_prefix) {
+            return Err(format!("寫入失敗，回覆: {:02X?}", resp));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+// --- 擷取檔重播 ---
+// 把擷取檔中送往設備的 report (tx) 依原本的間隔重新送出 (speed_factor 可加快或放慢，0 為不等待)，
+// 並把收到的回覆與錄製時的回覆比較。錄製時某個 tx 之後、下一個 tx 之前收到的第一個 rx 視為它的回覆；
+// 沒有回覆的 tx 只寫入不等待。回覆內容不同、錄製時有回覆但重播時逾時、送出失敗都記為差異 (divergence)。
+// report 依錄製時的原始位元組以 interrupt 方式送出，設備主動送出的 report 可能被當成回覆而產生差異，
+// 這類欄位 (計數器、時間戳記) 可用 ignore_offsets 略過。
+// 擷取檔讀兩次：第一次只確認來源設備並計算 tx 數量，第二次邊讀邊重播，不需要把整份擷取檔放進記憶體。
+
+use crate::capture::{self, CaptureReader, Direction};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// 最多保留的差異筆數 (超過時只計數)
+const MAX_DIVERGENCES: usize = 1000;
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ReplayOptions {
+    // 擷取檔中有多個設備時，指定要重播哪一個設備的流量
+    pub source_path: Option<String>,
+    // 等待回覆的時間，預設為錄製時回覆的延遲加上 1000 ms
+    pub timeout_ms: Option<i32>,
+    // 比較回覆時略過的位元組位置
+    pub ignore_offsets: Vec<usize>,
+    pub operation_id: Option<String>,
+    // 工作站鎖定的 token (見 station.rs)
+    pub station_token: Option<String>,
+}
+
+pub struct ReplayStep {
+    // 擷取檔中的 frame 序號
+    pub index: u64,
+    // 與上一個 step 的間隔 (未縮放)
+    pub delay: Duration,
+    pub report: Vec<u8>,
+    pub expected: Option<Vec<u8>>,
+    // 錄製時從送出到回覆的時間
+    pub response_delay: Duration,
+}
+
+pub struct ReplayPlan {
+    // 錄製時的 session ID
+    pub session: String,
+    // 要重播的來源設備路徑
+    pub source: String,
+    // tx 的數量
+    pub total: usize,
+    pub steps: ReplaySteps,
+}
+
+// 確認要重播的設備與 tx 數量，回傳依序讀出 step 的 ReplayPlan
+// 未指定 source_path 時擷取檔只能包含一個設備
+pub fn plan(file: &str, source_path: Option<&str>) -> Result<ReplayPlan, String> {
+    let reader = capture::open(file)?;
+    let session = reader.header.session.clone();
+    let mut source = source_path.map(str::to_string);
+    let mut total = 0;
+    for frame in reader {
+        let frame = frame?;
+        match &source {
+            Some(path) if *path == frame.path => {}
+            Some(_) if source_path.is_some() => continue,
+            Some(path) => return Err(format!("擷取檔包含多個設備 ({}、{})，請以 source_path 指定", path, frame.path)),
+            None => source = Some(frame.path.clone()),
+        }
+        if frame.dir == Direction::Tx { total += 1; }
+    }
+    let source = match source {
+        Some(source) if total > 0 => source,
+        _ => return Err("擷取檔中沒有送往設備的 report".into()),
+    };
+    let steps = ReplaySteps { reader: capture::open(file)?, source: source.clone(), pending: None, last_tx_us: None };
+    Ok(ReplayPlan { session, source, total, steps })
+}
+
+// 依序讀出要重播的 tx 與各自的回覆；收到回覆或下一個 tx 時才確定一個 step
+pub struct ReplaySteps {
+    reader: CaptureReader,
+    source: String,
+    // 還在等待回覆的 tx
+    pending: Option<ReplayStep>,
+    last_tx_us: Option<u64>,
+}
+
+impl Iterator for ReplaySteps {
+    type Item = Result<ReplayStep, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let frame = match self.reader.next() {
+                None => return self.pending.take().map(Ok),
+                Some(Err(e)) => return Some(Err(e)),
+                Some(Ok(frame)) => frame,
+            };
+            if frame.path != self.source { continue; }
+            match frame.dir {
+                Direction::Tx => {
+                    let delay = self.last_tx_us.map_or(0, |last| frame.mono_us.saturating_sub(last));
+                    self.last_tx_us = Some(frame.mono_us);
+                    let step = ReplayStep {
+                        index: frame.index,
+                        delay: Duration::from_micros(delay),
+                        report: frame.data,
+                        expected: None,
+                        response_delay: Duration::ZERO,
+                    };
+                    if let Some(done) = self.pending.replace(step) { return Some(Ok(done)); }
+                }
+                Direction::Rx => {
+                    let Some(mut step) = self.pending.take() else { continue };
+                    step.response_delay = Duration::from_micros(frame.mono_us.saturating_sub(self.last_tx_us.unwrap_or(frame.mono_us)));
+                    step.expected = Some(frame.data);
+                    return Some(Ok(step));
+                }
+            }
+        }
+    }
+}
+
+pub fn same_response(expected: &[u8], actual: &[u8], ignore: &[usize]) -> bool {
+    expected.len() == actual.len()
+        && expected.iter().zip(actual).enumerate().all(|(i, (e, a))| e == a || ignore.contains(&i))
+}
+
+// speed_factor 為 2 時以兩倍速重播；0 不等待
+pub fn scaled(delay: Duration, speed_factor: f64) -> Duration {
+    if speed_factor <= 0.0 { return Duration::ZERO; }
+    Duration::from_secs_f64(delay.as_secs_f64() / speed_factor)
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DivergenceKind {
+    // 回覆內容不同
+    Mismatch,
+    // 錄製時有回覆，重播時逾時
+    Missing,
+    // 送出或讀取失敗
+    Error,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Divergence {
+    pub index: u64,
+    pub kind: DivergenceKind,
+    pub sent: Vec<u8>,
+    pub expected: Option<Vec<u8>>,
+    pub actual: Option<Vec<u8>>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Default)]
+pub struct ReplayResult {
+    // 錄製時的 session ID 與設備路徑
+    pub session: String,
+    pub source_path: String,
+    pub total: usize,
+    pub sent: usize,
+    // 有錄製回覆可比較的次數
+    pub compared: usize,
+    pub matched: usize,
+    pub divergence_count: usize,
+    pub divergences: Vec<Divergence>,
+    pub elapsed_ms: u64,
+    pub cancelled: bool,
+}
+
+impl ReplayResult {
+    pub fn record(&mut self, divergence: Divergence) {
+        self.divergence_count += 1;
+        if self.divergences.len() < MAX_DIVERGENCES { self.divergences.push(divergence); }
+    }
+}
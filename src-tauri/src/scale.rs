@@ -0,0 +1,93 @@
+// --- HID POS 電子秤 (usage page 0x8D) ---
+// 常見的 Scale Data Report (Report ID 0x03):
+// [0x03, status, unit, exponent(i8), weight_lo, weight_hi]
+
+use serde::Serialize;
+
+pub const PAGE_SCALE: u16 = 0x8D;
+const SCALE_DATA_REPORT_ID: u8 = 0x03;
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct ScaleReading {
+    pub status: &'static str,
+    pub unit: &'static str,
+    pub raw_value: u16,
+    pub exponent: i8,
+    // raw_value * 10^exponent
+    pub weight: f64,
+    pub stable: bool,
+}
+
+fn status_name(code: u8) -> &'static str {
+    match code {
+        0x01 => "fault",
+        0x02 => "stable_at_zero",
+        0x03 => "in_motion",
+        0x04 => "stable",
+        0x05 => "under_zero",
+        0x06 => "over_weight",
+        0x07 => "requires_calibration",
+        0x08 => "requires_rezeroing",
+        _ => "unknown",
+    }
+}
+
+fn unit_name(code: u8) -> &'static str {
+    match code {
+        0x01 => "mg",
+        0x02 => "g",
+        0x03 => "kg",
+        0x04 => "ct",
+        0x05 => "taels",
+        0x06 => "gr",
+        0x07 => "dwt",
+        0x08 => "t",
+        0x09 => "ton",
+        0x0A => "ozt",
+        0x0B => "oz",
+        0x0C => "lb",
+        _ => "unknown",
+    }
+}
+
+pub fn decode(report: &[u8]) -> Option<ScaleReading> {
+    if report.len() < 6 || report[0] != SCALE_DATA_REPORT_ID { return None; }
+    let status = report[1];
+    let exponent = report[3] as i8;
+    let raw_value = u16::from_le_bytes([report[4], report[5]]);
+    let weight = match status {
+        // 低於零時設備仍回報絕對值
+        0x05 => -(raw_value as f64) * 10f64.powi(exponent as i32),
+        _ => raw_value as f64 * 10f64.powi(exponent as i32),
+    };
+    Some(ScaleReading {
+        status: status_name(status),
+        unit: unit_name(report[2]),
+        raw_value,
+        exponent,
+        weight,
+        stable: matches!(status, 0x02 | 0x04),
+    })
+}
+
+// 連續幾筆相同的穩定讀值才視為真正穩定
+pub const STABLE_SAMPLES: usize = 3;
+
+pub struct StableDetector {
+    last: Option<ScaleReading>,
+    count: usize,
+}
+
+impl StableDetector {
+    pub fn new() -> Self {
+        Self { last: None, count: 0 }
+    }
+
+    // 回傳目前已確認穩定的讀值
+    pub fn push(&mut self, reading: ScaleReading) -> Option<ScaleReading> {
+        let same = self.last.as_ref().is_some_and(|l| l.raw_value == reading.raw_value && l.unit == reading.unit);
+        self.count = if reading.stable && same { self.count + 1 } else if reading.stable { 1 } else { 0 };
+        self.last = Some(reading);
+        if self.count >= STABLE_SAMPLES { self.last.clone() } else { None }
+    }
+}
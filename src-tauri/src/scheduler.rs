@@ -0,0 +1,287 @@
+// --- 排程工作 (類 cron) ---
+// 依 5 欄位 cron 語法 (分 時 日 月 週) 或固定間隔定期執行工作，設定存於 app config 目錄，
+// 重新啟動後仍會繼續排程。
+//   "0 2 * * *"     cron
+//   "@every 5m"     固定間隔 (s / m / h)，啟動或儲存後立即執行一次
+//   "@hourly"       同 "0 * * * *"；另有 @daily
+//
+// send 以 path 或 device (VID/PID/序號，路徑在重新插拔後可能改變) 指定設備，
+// 設備尚未開啟時會自動開始監聽。
+//
+// [
+//   { "name": "nightly-check", "schedule": "0 2 * * *", "enabled": true,
+//     "action": { "type": "send", "path": "...", "data": [1, 2] } },
+//   { "name": "status", "schedule": "@every 5m",
+//     "action": { "type": "send", "device": { "name": "rig-1", "vendor_id": 1234, "product_id": 5678 },
+//                 "data": [6], "timeout_ms": 500 } }
+// ]
+
+use crate::health::Favorite;
+use crate::paging::{Page, PageRequest, SeqLog};
+use crate::template::Payload;
+use chrono::{DateTime, Datelike, Local, Timelike};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// 保留的執行紀錄筆數
+const HISTORY_LIMIT: usize = 500;
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TaskAction {
+    // 對設備送出指令並等待回覆；path 與 device 擇一
+    Send {
+        path: Option<String>,
+        device: Option<Favorite>,
+        // 位元組陣列或樣板字串 (見 template.rs)
+        data: Payload,
+        timeout_ms: Option<i32>,
+    },
+    // 發送 scheduled-task 事件，由前端執行巨集、擷取或匯出
+    Emit { payload: serde_json::Value },
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ScheduledTask {
+    pub name: String,
+    pub schedule: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub action: TaskAction,
+}
+
+fn default_enabled() -> bool { true }
+
+#[derive(Serialize, Clone)]
+pub struct TaskRun {
+    pub task: String,
+    // 開始時間 (Unix ms)
+    pub started_ms: i64,
+    pub ok: bool,
+    pub message: String,
+    // send 工作實際使用的設備與回覆
+    pub path: Option<String>,
+    pub response: Option<Vec<u8>>,
+    pub duration_ms: u64,
+}
+
+// --- cron 語法 ---
+
+struct CronField {
+    allowed: Vec<bool>,
+}
+
+impl CronField {
+    fn parse(spec: &str, min: u32, max: u32) -> Result<Self, String> {
+        let mut allowed = vec![false; (max + 1) as usize];
+        for part in spec.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((r, s)) => (r, s.parse::<u32>().map_err(|_| format!("無效的間隔: {}", part))?),
+                None => (part, 1),
+            };
+            if step == 0 { return Err(format!("間隔不可為 0: {}", part)); }
+            let (start, end) = match range {
+                "*" => (min, max),
+                r => match r.split_once('-') {
+                    Some((a, b)) => (parse_num(a)?, parse_num(b)?),
+                    // "5/10" 代表從 5 開始每 10 一次
+                    None if part.contains('/') => (parse_num(r)?, max),
+                    None => { let v = parse_num(r)?; (v, v) }
+                },
+            };
+            if start < min || end > max || start > end {
+                return Err(format!("{} 超出範圍 {}-{}", part, min, max));
+            }
+            (start..=end).step_by(step as usize).for_each(|v| allowed[v as usize] = true);
+        }
+        Ok(Self { allowed })
+    }
+
+    fn matches(&self, v: u32) -> bool {
+        self.allowed.get(v as usize).copied().unwrap_or(false)
+    }
+}
+
+fn parse_num(s: &str) -> Result<u32, String> {
+    s.parse().map_err(|_| format!("無效的數值: {}", s))
+}
+
+pub struct CronSchedule {
+    minute: CronField,
+    hour: CronField,
+    day: CronField,
+    month: CronField,
+    // 0 與 7 都代表星期日
+    weekday: CronField,
+    day_any: bool,
+    weekday_any: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = spec.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("排程需要 5 個欄位 (分 時 日 月 週): {}", spec));
+        };
+        Ok(Self {
+            minute: CronField::parse(minute, 0, 59)?,
+            hour: CronField::parse(hour, 0, 23)?,
+            day: CronField::parse(day, 1, 31)?,
+            month: CronField::parse(month, 1, 12)?,
+            weekday: CronField::parse(weekday, 0, 7)?,
+            day_any: day == "*",
+            weekday_any: weekday == "*",
+        })
+    }
+
+    pub fn matches(&self, t: &DateTime<Local>) -> bool {
+        let wd = t.weekday().num_days_from_sunday();
+        let weekday_ok = self.weekday.matches(wd) || (wd == 0 && self.weekday.matches(7));
+        // 與標準 cron 相同：日與週都有指定時，任一符合即可
+        let date_ok = match (self.day_any, self.weekday_any) {
+            (false, false) => self.day.matches(t.day()) || weekday_ok,
+            _ => self.day.matches(t.day()) && weekday_ok,
+        };
+        date_ok && self.minute.matches(t.minute()) && self.hour.matches(t.hour()) && self.month.matches(t.month())
+    }
+}
+
+// --- 排程時間 ---
+
+pub enum Timing {
+    Cron(CronSchedule),
+    Every(Duration),
+}
+
+impl Timing {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        match spec.trim() {
+            "@hourly" => CronSchedule::parse("0 * * * *").map(Timing::Cron),
+            "@daily" => CronSchedule::parse("0 0 * * *").map(Timing::Cron),
+            s => match s.strip_prefix("@every") {
+                Some(interval) => parse_interval(interval.trim()).map(Timing::Every),
+                None => CronSchedule::parse(s).map(Timing::Cron),
+            },
+        }
+    }
+}
+
+fn parse_interval(s: &str) -> Result<Duration, String> {
+    let (num, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let n: u64 = num.parse().map_err(|_| format!("無效的間隔: {}", s))?;
+    let secs = match unit {
+        "s" => n,
+        "m" => n * 60,
+        "h" => n * 3600,
+        _ => return Err(format!("間隔單位需為 s、m 或 h: {}", s)),
+    };
+    if secs == 0 { return Err(format!("間隔不可為 0: {}", s)); }
+    Ok(Duration::from_secs(secs))
+}
+
+// --- 排程狀態 ---
+
+struct Entry {
+    task: ScheduledTask,
+    timing: Timing,
+    // 固定間隔工作的下一次執行時間
+    next: Instant,
+}
+
+impl Entry {
+    fn new(task: ScheduledTask) -> Result<Self, String> {
+        let timing = Timing::parse(&task.schedule)?;
+        Ok(Self { task, timing, next: Instant::now() })
+    }
+}
+
+pub struct Scheduler {
+    tasks: Vec<Entry>,
+    history: SeqLog<TaskRun>,
+    // 設定檔位置 (app config 目錄取不到時為 None，只保存在記憶體)
+    file: Option<PathBuf>,
+    // 上一次檢查的分鐘，避免同一分鐘重複執行
+    last_minute: Option<i64>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self { Self::new() }
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self { tasks: Vec::new(), history: SeqLog::new(HISTORY_LIMIT), file: None, last_minute: None }
+    }
+
+    // 載入設定檔；檔案不存在時視為沒有排程
+    pub fn load(&mut self, file: &Path) -> Result<(), String> {
+        self.file = Some(file.to_path_buf());
+        if !file.exists() { return Ok(()); }
+        let text = std::fs::read_to_string(file).map_err(|e| format!("讀取 {} 失敗: {}", file.display(), e))?;
+        let tasks: Vec<ScheduledTask> = serde_json::from_str(&text).map_err(|e| format!("排程設定格式錯誤: {}", e))?;
+        self.tasks = tasks.into_iter().map(Entry::new).collect::<Result<_, _>>()?;
+        Ok(())
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(file) = &self.file else { return Ok(()) };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("建立目錄失敗: {}", e))?;
+        }
+        let tasks: Vec<&ScheduledTask> = self.tasks.iter().map(|e| &e.task).collect();
+        let text = serde_json::to_string_pretty(&tasks).map_err(|e| e.to_string())?;
+        std::fs::write(file, text).map_err(|e| format!("寫入 {} 失敗: {}", file.display(), e))
+    }
+
+    pub fn tasks(&self) -> Vec<ScheduledTask> {
+        self.tasks.iter().map(|e| e.task.clone()).collect()
+    }
+
+    // 同名工作直接取代
+    pub fn upsert(&mut self, task: ScheduledTask) -> Result<(), String> {
+        let entry = Entry::new(task)?;
+        self.tasks.retain(|e| e.task.name != entry.task.name);
+        self.tasks.push(entry);
+        self.save()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), String> {
+        let before = self.tasks.len();
+        self.tasks.retain(|e| e.task.name != name);
+        if self.tasks.len() == before { return Err(format!("找不到排程工作: {}", name)); }
+        self.save()
+    }
+
+    // 回傳現在該執行的工作 (cron 工作每分鐘只回傳一次)
+    pub fn due(&mut self, now: &DateTime<Local>) -> Vec<ScheduledTask> {
+        let minute = now.timestamp() / 60;
+        let new_minute = self.last_minute != Some(minute);
+        self.last_minute = Some(minute);
+        let instant = Instant::now();
+        let mut due = Vec::new();
+        for entry in self.tasks.iter_mut().filter(|e| e.task.enabled) {
+            let run = match &entry.timing {
+                Timing::Cron(cron) => new_minute && cron.matches(now),
+                Timing::Every(interval) => {
+                    if instant < entry.next { continue; }
+                    // 落後太多時跳過錯過的輪次
+                    entry.next += *interval;
+                    if entry.next < instant { entry.next = instant + *interval; }
+                    true
+                }
+            };
+            if run { due.push(entry.task.clone()); }
+        }
+        due
+    }
+
+    pub fn record(&mut self, run: TaskRun) {
+        self.history.push(run);
+    }
+
+    // 由新到舊
+    pub fn history(&self, task: Option<&str>, page: PageRequest) -> Page<TaskRun> {
+        self.history.page(page, |r| task.is_none_or(|name| r.task == name))
+    }
+}
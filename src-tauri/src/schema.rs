@@ -0,0 +1,416 @@
+// --- Report schema ---
+// 以 JSON 描述廠商自訂 input report 的欄位 (沒有 descriptor 可用或 descriptor 不夠詳細時)。
+// offset 以收到的原始資料計算 (有 Report ID 時第 0 個 byte 就是 Report ID)。
+//
+// {
+//   "name": "sensor-v1",
+//   "reports": [
+//     { "report_id": 1, "fields": [
+//         { "name": "seq", "offset": 1, "encoding": "u8", "counter": true },
+//         { "name": "state", "offset": 2, "labels": { "0": "Idle", "1": "Charging", "2": "Fault" } },
+//         { "name": "charging", "offset": 3, "bit_offset": 0, "bit_size": 1 },
+//         { "name": "reserved", "offset": 3, "bit_offset": 1, "bit_size": 7, "reserved": true },
+//         { "name": "temperature", "offset": 4, "encoding": "i16le", "scale": 0.01, "unit": "C",
+//           "format": { "style": "fixed", "decimals": 2 } }
+//     ] }
+//   ]
+// }
+
+use crate::format::{check_labels, ValueFormat};
+use crate::regmap::Encoding;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SchemaField {
+    pub name: String,
+    pub offset: usize,
+    #[serde(default = "default_encoding")]
+    pub encoding: Encoding,
+    // 只取編碼值中的部分位元 (旗標、packed 欄位)
+    #[serde(default)]
+    pub bit_offset: Option<u32>,
+    #[serde(default)]
+    pub bit_size: Option<u32>,
+    // 實際值 = 原始值 * scale + bias
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    #[serde(default)]
+    pub bias: f64,
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub format: ValueFormat,
+    #[serde(default)]
+    pub labels: BTreeMap<i64, String>,
+    // 每筆 report 遞增 1 並循環的序號，用來偵測遺失的 report
+    #[serde(default)]
+    pub counter: bool,
+    // 保留位元，驗證模式下值必須為 0
+    #[serde(default)]
+    pub reserved: bool,
+}
+
+fn default_encoding() -> Encoding { Encoding::U8 }
+fn default_scale() -> f64 { 1.0 }
+
+impl SchemaField {
+    // 原始值的位元數
+    fn bits(&self) -> u32 {
+        self.bit_size.unwrap_or(self.encoding.width() as u32 * 8)
+    }
+
+    // 未經 scale 的原始值；資料長度不足時回傳 None
+    fn raw(&self, data: &[u8]) -> Option<f64> {
+        let bytes = data.get(self.offset..self.offset + self.encoding.width())?;
+        let decoded = self.encoding.decode(bytes)?;
+        Some(match self.bit_size {
+            Some(size) => {
+                let mask = (1u64 << size) - 1;
+                ((decoded as i64 as u64 >> self.bit_offset.unwrap_or(0)) & mask) as f64
+            }
+            None => decoded,
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SchemaReport {
+    // 未設定代表設備不使用 Report ID
+    #[serde(default)]
+    pub report_id: Option<u8>,
+    pub fields: Vec<SchemaField>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ReportSchema {
+    pub name: String,
+    pub reports: Vec<SchemaReport>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SchemaValue {
+    pub name: String,
+    pub raw: f64,
+    pub value: f64,
+    pub label: Option<String>,
+    pub display: String,
+    pub unit: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SchemaDecoded {
+    pub schema: String,
+    pub report_id: Option<u8>,
+    pub values: Vec<SchemaValue>,
+}
+
+impl ReportSchema {
+    pub fn load(file: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(file).map_err(|e| format!("讀取 {} 失敗: {}", file, e))?;
+        let schema: ReportSchema = serde_json::from_str(&text).map_err(|e| format!("schema 格式錯誤: {}", e))?;
+        schema.validate()?;
+        Ok(schema)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let mut ids: Vec<Option<u8>> = self.reports.iter().map(|r| r.report_id).collect();
+        ids.sort_unstable();
+        if let Some(w) = ids.windows(2).find(|w| w[0] == w[1]) {
+            return Err(format!("Report ID 重複: {:?}", w[0]));
+        }
+        for report in &self.reports {
+            let mut names: Vec<&str> = report.fields.iter().map(|f| f.name.as_str()).collect();
+            names.sort_unstable();
+            if let Some(w) = names.windows(2).find(|w| w[0] == w[1]) {
+                return Err(format!("欄位名稱重複: {}", w[0]));
+            }
+            for field in &report.fields {
+                let width = field.encoding.width() as u32 * 8;
+                if field.bit_offset.unwrap_or(0) + field.bits() > width || field.bit_size == Some(0) {
+                    return Err(format!("{}: 位元範圍超出 {:?}", field.name, field.encoding));
+                }
+                if field.bit_size.is_none() {
+                    check_labels(&field.name, field.encoding, &field.labels)?;
+                }
+                if field.counter && field.encoding.range().is_none() {
+                    return Err(format!("{}: 序號欄位必須是整數編碼", field.name));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn report_for(&self, data: &[u8]) -> Option<&SchemaReport> {
+        self.reports.iter().find(|r| r.report_id.is_none_or(|id| data.first() == Some(&id)))
+    }
+
+    // 依 Report ID 找到對應的定義並解碼；沒有對應的定義時回傳 None
+    pub fn decode(&self, data: &[u8]) -> Option<SchemaDecoded> {
+        let report = self.report_for(data)?;
+        let values = report.fields.iter()
+            .filter_map(|f| {
+                let raw = f.raw(data)?;
+                let value = raw * f.scale + f.bias;
+                let label = f.labels.get(&(raw as i64)).cloned();
+                let display = label.clone().unwrap_or_else(|| f.format.apply(raw, f.bits(), value));
+                Some(SchemaValue { name: f.name.clone(), raw, value, label, display, unit: f.unit.clone() })
+            })
+            .collect();
+        Some(SchemaDecoded { schema: self.name.clone(), report_id: report.report_id, values })
+    }
+
+    // 驗證模式：檢查 report 是否符合 schema
+    pub fn check(&self, data: &[u8]) -> Vec<SchemaViolation> {
+        let Some(report) = self.report_for(data) else {
+            let report_id = if self.reports.iter().any(|r| r.report_id.is_some()) { data.first().copied() } else { None };
+            return vec![SchemaViolation { kind: ViolationKind::UnknownReport, report_id, field: None, value: None }];
+        };
+        let violation = |kind, field: &SchemaField, value| SchemaViolation {
+            kind,
+            report_id: report.report_id,
+            field: Some(field.name.clone()),
+            value,
+        };
+        let mut out = Vec::new();
+        for field in &report.fields {
+            let Some(raw) = field.raw(data) else {
+                out.push(violation(ViolationKind::ShortReport, field, Some(data.len() as f64)));
+                break;
+            };
+            if field.reserved && raw != 0.0 {
+                out.push(violation(ViolationKind::ReservedBits, field, Some(raw)));
+            } else if !field.labels.is_empty() && !field.labels.contains_key(&(raw as i64)) {
+                out.push(violation(ViolationKind::OutOfRange, field, Some(raw)));
+            }
+        }
+        out
+    }
+
+    fn counters(&self, data: &[u8]) -> Vec<(Option<u8>, &SchemaField, i64)> {
+        let Some(report) = self.report_for(data) else { return Vec::new() };
+        report.fields.iter()
+            .filter(|f| f.counter)
+            .filter_map(|f| Some((report.report_id, f, f.raw(data)? as i64)))
+            .collect()
+    }
+}
+
+// --- 依韌體版本選擇 schema ---
+// 同一款設備的不同韌體版本 report 格式可能不同，開啟時依版本自動套用對應的 schema。
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SchemaVersionRule {
+    // 包含此版本；未設定代表不限
+    #[serde(default)]
+    pub min_version: Option<String>,
+    // 不包含此版本；未設定代表不限
+    #[serde(default)]
+    pub max_version: Option<String>,
+    pub schema: String,
+}
+
+// 以 "." 分隔逐段比較，數字段依數值 (1.10 > 1.9)，其他依字串
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a = a.trim().trim_start_matches(['v', 'V']).split('.');
+    let mut b = b.trim().trim_start_matches(['v', 'V']).split('.');
+    loop {
+        let ord = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (x, y) => {
+                let (x, y) = (x.unwrap_or("0"), y.unwrap_or("0"));
+                match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    _ => x.cmp(y),
+                }
+            }
+        };
+        if ord != Ordering::Equal { return ord; }
+    }
+}
+
+// 符合版本範圍的規則中取 min_version 最高者 (範圍最精確)
+pub fn select_schema<'a>(rules: &'a [SchemaVersionRule], version: &str) -> Option<&'a str> {
+    rules.iter()
+        .filter(|r| r.min_version.as_deref().is_none_or(|min| compare_versions(version, min) != Ordering::Less))
+        .filter(|r| r.max_version.as_deref().is_none_or(|max| compare_versions(version, max) == Ordering::Less))
+        .max_by(|a, b| match (&a.min_version, &b.min_version) {
+            (Some(x), Some(y)) => compare_versions(x, y),
+            (x, y) => x.is_some().cmp(&y.is_some()),
+        })
+        .map(|r| r.schema.as_str())
+}
+
+// 各型號 (VID, PID) 的版本規則
+pub type VersionRules = HashMap<(u16, u16), Vec<SchemaVersionRule>>;
+
+// schema_versions.json 中的一個型號 (JSON 物件的 key 只能是字串，因此存成清單)
+#[derive(Deserialize, Serialize)]
+struct ModelRules {
+    vendor_id: u16,
+    product_id: u16,
+    rules: Vec<SchemaVersionRule>,
+}
+
+// 檔案不存在時沒有任何規則
+pub fn load_version_rules(file: &Path) -> Result<VersionRules, String> {
+    if !file.exists() { return Ok(VersionRules::new()); }
+    let text = std::fs::read_to_string(file).map_err(|e| format!("讀取 {} 失敗: {}", file.display(), e))?;
+    let models: Vec<ModelRules> = serde_json::from_str(&text).map_err(|e| format!("schema 版本規則格式錯誤: {}", e))?;
+    Ok(models.into_iter().map(|m| ((m.vendor_id, m.product_id), m.rules)).collect())
+}
+
+pub fn save_version_rules(file: &Path, rules: &VersionRules) -> Result<(), String> {
+    let mut models: Vec<ModelRules> = rules.iter()
+        .map(|(&(vendor_id, product_id), rules)| ModelRules { vendor_id, product_id, rules: rules.clone() })
+        .collect();
+    models.sort_by_key(|m| (m.vendor_id, m.product_id));
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("建立目錄失敗: {}", e))?;
+    }
+    let text = serde_json::to_string_pretty(&models).map_err(|e| e.to_string())?;
+    std::fs::write(file, text).map_err(|e| format!("寫入 {} 失敗: {}", file.display(), e))
+}
+
+// --- 驗證模式 ---
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    // schema 中沒有此 Report ID 的定義
+    UnknownReport,
+    // 資料長度不足以容納欄位 (value 為實際長度)
+    ShortReport,
+    // 有定義 labels 的欄位出現未定義的值
+    OutOfRange,
+    // 保留欄位不為 0
+    ReservedBits,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SchemaViolation {
+    pub kind: ViolationKind,
+    pub report_id: Option<u8>,
+    pub field: Option<String>,
+    pub value: Option<f64>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ViolationCount {
+    pub kind: ViolationKind,
+    pub report_id: Option<u8>,
+    pub field: Option<String>,
+    pub count: u64,
+    // 最近一次違規的值
+    pub last_value: Option<f64>,
+}
+
+// (類型, Report ID, 欄位)
+type ViolationKey = (ViolationKind, Option<u8>, Option<String>);
+
+// 同一種違規持續發生時，每個間隔最多發送一次事件 (帶累計次數)
+pub const VIOLATION_EMIT_INTERVAL: Duration = Duration::from_secs(1);
+
+struct ViolationEntry {
+    count: u64,
+    last_value: Option<f64>,
+    // 最近一次發送事件的時間
+    emitted_at: Instant,
+}
+
+// 各種違規的累計次數與最近一次的值
+#[derive(Default)]
+pub struct ViolationCounter {
+    counts: BTreeMap<ViolationKey, ViolationEntry>,
+}
+
+impl ViolationCounter {
+    // 累計此違規；第一次發生或距離上次發送超過 VIOLATION_EMIT_INTERVAL 時回傳累計次數，其餘回傳 None
+    pub fn record(&mut self, v: &SchemaViolation) -> Option<u64> {
+        let now = Instant::now();
+        match self.counts.get_mut(&(v.kind, v.report_id, v.field.clone())) {
+            Some(entry) => {
+                entry.count += 1;
+                entry.last_value = v.value;
+                if now.duration_since(entry.emitted_at) < VIOLATION_EMIT_INTERVAL { return None; }
+                entry.emitted_at = now;
+                Some(entry.count)
+            }
+            None => {
+                let entry = ViolationEntry { count: 1, last_value: v.value, emitted_at: now };
+                self.counts.insert((v.kind, v.report_id, v.field.clone()), entry);
+                Some(1)
+            }
+        }
+    }
+
+    pub fn summary(&self) -> Vec<ViolationCount> {
+        self.counts.iter()
+            .map(|((kind, report_id, field), entry)| ViolationCount {
+                kind: *kind,
+                report_id: *report_id,
+                field: field.clone(),
+                count: entry.count,
+                last_value: entry.last_value,
+            })
+            .collect()
+    }
+
+    pub fn reset(&mut self) {
+        self.counts.clear();
+    }
+}
+
+// 造成序號跳號的原因
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum LossCause {
+    // 程式持續在讀取，report 在設備 / USB / OS 緩衝區就遺失了
+    Usb,
+    // 期間程式暫停或被獨佔操作佔用而沒有讀取，report 被其他讀取者取走或緩衝區溢出
+    App,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ReportLoss {
+    pub report_id: Option<u8>,
+    pub field: String,
+    pub expected: i64,
+    pub actual: i64,
+    pub missed: u64,
+    pub cause: LossCause,
+}
+
+// 追蹤各序號欄位 (Report ID, 欄位名稱) 上一次的值
+#[derive(Default)]
+pub struct CounterTracker {
+    last: HashMap<(Option<u8>, String), i64>,
+}
+
+impl CounterTracker {
+    // interrupted: 從上一筆 report 到現在，讀取是否曾經中斷
+    pub fn check(&mut self, schema: &ReportSchema, data: &[u8], interrupted: bool) -> Vec<ReportLoss> {
+        let mut losses = Vec::new();
+        for (report_id, field, value) in schema.counters(data) {
+            let modulus = 1i64 << field.bits();
+            let value = value.rem_euclid(modulus);
+            if let Some(last) = self.last.insert((report_id, field.name.clone()), value) {
+                let expected = (last + 1).rem_euclid(modulus);
+                let missed = (value - expected).rem_euclid(modulus) as u64;
+                // 序號沒變代表重送或設備重置，不視為遺失
+                if missed > 0 && value != last {
+                    let cause = if interrupted { LossCause::App } else { LossCause::Usb };
+                    losses.push(ReportLoss { report_id, field: field.name.clone(), expected, actual: value, missed, cause });
+                }
+            }
+        }
+        losses
+    }
+
+    pub fn reset(&mut self) {
+        self.last.clear();
+    }
+}
@@ -0,0 +1,145 @@
+// --- 自動化腳本 (Rhai) ---
+// 無法以固定指令清單表達的流程 (讀版本 -> 分支 -> 設定) 以 Rhai 腳本撰寫，在背景執行緒執行。
+// 位元組以整數陣列表示，例: [0x01, 0x02]。可用的函式：
+//   open(path)                       開始監聽設備 (已開啟時不動作)
+//   write(path, bytes)               送出 output report (格式與 send_hid_command 相同)，回傳寫入的位元組數
+//   exchange(path, bytes)            送出並等待下一筆回覆，逾時回傳空陣列；可加上第 3 個參數 timeout_ms
+//   read(path, timeout_ms)           讀取下一筆 input report，逾時回傳空陣列
+//   expect(path, pattern, timeout_ms) 等待符合 pattern 的 report (例: "01 ?? A* ..")，逾時以錯誤結束腳本
+//   sleep(ms)
+//   log(message)                     以 app-log 事件送出 (print 亦同)
+//   assert(condition, message)       不成立時以錯誤結束腳本
+// stop_script 會在下一個運算或 sleep 中止腳本。
+//
+// let version = exchange(dev, [0x01]);
+// assert(version.len() > 0, "沒有回覆");
+// if version[1] >= 2 { write(dev, [0x10, 0x01]); } else { log("舊版韌體，略過設定"); }
+
+use crate::sequence::BytePattern;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Position};
+use serde::Serialize;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_TIMEOUT_MS: i64 = 1000;
+// sleep 時檢查停止旗標的間隔
+const SLEEP_SLICE_MS: u64 = 20;
+
+// 腳本與設備之間的介面，由主程式實作
+pub trait ScriptHost {
+    fn open(&self, path: &str) -> Result<(), String>;
+    fn write(&self, path: &str, data: &[u8]) -> Result<usize, String>;
+    fn exchange(&self, path: &str, data: &[u8], timeout_ms: i32) -> Result<Vec<u8>, String>;
+    fn read(&self, path: &str, timeout_ms: i32) -> Result<Vec<u8>, String>;
+    fn expect(&self, path: &str, pattern: &BytePattern, timeout_ms: i32) -> Result<Vec<u8>, String>;
+    fn log(&self, message: &str);
+}
+
+#[derive(Serialize, Clone)]
+pub struct ScriptResult {
+    pub ok: bool,
+    // 腳本最後一個運算式的值
+    pub value: Option<String>,
+    pub error: Option<String>,
+    pub stopped: bool,
+    pub elapsed_ms: u64,
+}
+
+type RhaiResult<T> = Result<T, Box<EvalAltResult>>;
+
+fn to_bytes(array: &Array) -> RhaiResult<Vec<u8>> {
+    array.iter()
+        .map(|v| {
+            let n = v.as_int().map_err(|t| format!("位元組陣列只能包含整數，而不是 {}", t))?;
+            u8::try_from(n).map_err(|_| format!("位元組超出範圍: {}", n).into())
+        })
+        .collect()
+}
+
+fn to_array(bytes: Vec<u8>) -> Array {
+    bytes.into_iter().map(|b| Dynamic::from_int(b as i64)).collect()
+}
+
+fn to_timeout(ms: i64) -> RhaiResult<i32> {
+    i32::try_from(ms).ok().filter(|ms| *ms >= 0).ok_or_else(|| format!("無效的 timeout_ms: {}", ms).into())
+}
+
+fn build_engine(host: Rc<dyn ScriptHost>, stop: Arc<AtomicBool>) -> Engine {
+    let mut engine = Engine::new();
+
+    let stop_flag = stop.clone();
+    engine.on_progress(move |_| stop_flag.load(Ordering::Relaxed).then(|| Dynamic::from("stopped")));
+    let h = host.clone();
+    engine.on_print(move |s| h.log(s));
+
+    let h = host.clone();
+    engine.register_fn("open", move |path: &str| -> RhaiResult<()> { Ok(h.open(path)?) });
+    let h = host.clone();
+    engine.register_fn("write", move |path: &str, data: Array| -> RhaiResult<i64> {
+        Ok(h.write(path, &to_bytes(&data)?)? as i64)
+    });
+    let h = host.clone();
+    engine.register_fn("exchange", move |path: &str, data: Array| -> RhaiResult<Array> {
+        Ok(to_array(h.exchange(path, &to_bytes(&data)?, DEFAULT_TIMEOUT_MS as i32)?))
+    });
+    let h = host.clone();
+    engine.register_fn("exchange", move |path: &str, data: Array, timeout_ms: i64| -> RhaiResult<Array> {
+        Ok(to_array(h.exchange(path, &to_bytes(&data)?, to_timeout(timeout_ms)?)?))
+    });
+    let h = host.clone();
+    engine.register_fn("read", move |path: &str, timeout_ms: i64| -> RhaiResult<Array> {
+        Ok(to_array(h.read(path, to_timeout(timeout_ms)?)?))
+    });
+    let h = host.clone();
+    engine.register_fn("expect", move |path: &str, pattern: &str, timeout_ms: i64| -> RhaiResult<Array> {
+        let pattern = BytePattern::try_from(pattern.to_string())?;
+        let resp = h.expect(path, &pattern, to_timeout(timeout_ms)?)?;
+        if resp.is_empty() {
+            return Err(format!("{} ms 內沒有符合 {} 的 report", timeout_ms, pattern.text()).into());
+        }
+        Ok(to_array(resp))
+    });
+    let h = host.clone();
+    engine.register_fn("log", move |message: &str| h.log(message));
+    engine.register_fn("sleep", move |ms: i64| -> RhaiResult<()> {
+        let deadline = Instant::now() + Duration::from_millis(ms.max(0) as u64);
+        while let Some(left) = deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()) {
+            if stop.load(Ordering::Relaxed) {
+                return Err(EvalAltResult::ErrorTerminated(Dynamic::from("stopped"), Position::NONE).into());
+            }
+            thread::sleep(left.min(Duration::from_millis(SLEEP_SLICE_MS)));
+        }
+        Ok(())
+    });
+    engine.register_fn("assert", |condition: bool, message: &str| -> RhaiResult<()> {
+        if condition { Ok(()) } else { Err(format!("assert 失敗: {}", message).into()) }
+    });
+    engine.register_fn("assert", |condition: bool| -> RhaiResult<()> {
+        if condition { Ok(()) } else { Err("assert 失敗".into()) }
+    });
+    engine
+}
+
+// 在目前執行緒執行腳本直到結束或被停止
+pub fn run(source: &str, host: Rc<dyn ScriptHost>, stop: Arc<AtomicBool>) -> ScriptResult {
+    let started = Instant::now();
+    let engine = build_engine(host, stop);
+    let outcome = engine.eval::<Dynamic>(source);
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    match outcome {
+        Ok(value) => ScriptResult {
+            ok: true,
+            value: (!value.is_unit()).then(|| value.to_string()),
+            error: None,
+            stopped: false,
+            elapsed_ms,
+        },
+        Err(e) => {
+            let stopped = matches!(*e, EvalAltResult::ErrorTerminated(..));
+            ScriptResult { ok: false, value: None, error: (!stopped).then(|| e.to_string()), stopped, elapsed_ms }
+        }
+    }
+}
@@ -0,0 +1,227 @@
+// --- HID Sensor 解析 ---
+// Sensor usage page (0x20)：加速度計、陀螺儀、溫濕度、環境光等。
+// 感測器種類由資料欄位的 usage 判斷，同一個 Report ID 視為同一個感測器 (sensor hub 以 Report ID 區分)。
+// 數值依 descriptor 的 unit exponent 換算，單位採 HID Sensor Usages 規範的預設單位。
+// 回報間隔、電源狀態等屬性放在 feature report，需主動讀取。
+
+use crate::descriptor::{ReportDescriptor, ReportField, ReportKind};
+use crate::hid_io::{self, HidIo};
+use serde::Serialize;
+
+pub const PAGE_SENSOR: u16 = 0x20;
+
+const USAGE_SENSOR_STATE: [&str; 7] = [
+    "Undefined", "Ready", "Not Available", "No Data", "Initializing", "Access Denied", "Error",
+];
+const USAGE_SENSOR_EVENT: [&str; 5] = [
+    "Unknown", "State Changed", "Property Changed", "Data Updated", "Poll Response",
+];
+const USAGE_REPORTING_STATE: [&str; 6] = [
+    "No Events", "All Events", "Threshold Events", "Wake No Events", "Wake All Events", "Wake Threshold Events",
+];
+const USAGE_POWER_STATE: [&str; 6] = [
+    "Undefined", "D0 Full Power", "D1 Low Power", "D2 Standby With Wake", "D3 Sleep With Wake", "D4 Power Off",
+];
+
+// selector 的起始 usage
+const SEL_SENSOR_STATE: u16 = 0x0800;
+const SEL_SENSOR_EVENT: u16 = 0x0810;
+const SEL_REPORTING_STATE: u16 = 0x0840;
+const SEL_POWER_STATE: u16 = 0x0850;
+
+const USAGE_REPORT_INTERVAL: u16 = 0x030E;
+// SI Linear 的秒
+const UNIT_SECOND: u32 = 0x1001;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorKind {
+    Accelerometer,
+    Gyrometer,
+    Compass,
+    Temperature,
+    Humidity,
+    Pressure,
+    AmbientLight,
+    Presence,
+}
+
+// (usage, 感測器種類, 數值名稱, 單位)
+const DATA_FIELDS: [(u16, SensorKind, &str, &str); 16] = [
+    (0x0453, SensorKind::Accelerometer, "x", "g"),
+    (0x0454, SensorKind::Accelerometer, "y", "g"),
+    (0x0455, SensorKind::Accelerometer, "z", "g"),
+    (0x0457, SensorKind::Gyrometer, "x", "deg/s"),
+    (0x0458, SensorKind::Gyrometer, "y", "deg/s"),
+    (0x0459, SensorKind::Gyrometer, "z", "deg/s"),
+    (0x0485, SensorKind::Compass, "x", "mG"),
+    (0x0486, SensorKind::Compass, "y", "mG"),
+    (0x0487, SensorKind::Compass, "z", "mG"),
+    (0x0434, SensorKind::Temperature, "temperature", "C"),
+    (0x0433, SensorKind::Humidity, "humidity", "%"),
+    (0x0431, SensorKind::Pressure, "pressure", "bar"),
+    (0x04D1, SensorKind::AmbientLight, "illuminance", "lux"),
+    (0x04D2, SensorKind::AmbientLight, "color_temperature", "K"),
+    (0x04B1, SensorKind::Presence, "present", ""),
+    (0x04B2, SensorKind::Presence, "distance", "m"),
+];
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct SensorValue {
+    pub name: String,
+    pub value: f64,
+    pub unit: String,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct SensorReading {
+    pub report_id: u8,
+    pub sensor: SensorKind,
+    // 例: "Ready"、"Initializing"；descriptor 沒有此欄位時為 None
+    pub state: Option<String>,
+    // 送出此 report 的原因，例: "Data Updated"、"Poll Response"
+    pub event: Option<String>,
+    pub values: Vec<SensorValue>,
+}
+
+#[derive(Serialize, Clone, Default, PartialEq, Debug)]
+pub struct SensorProperties {
+    pub report_id: u8,
+    pub sensor: Option<SensorKind>,
+    pub report_interval_ms: Option<f64>,
+    pub reporting_state: Option<String>,
+    pub power_state: Option<String>,
+    pub state: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum Role {
+    Data { kind: SensorKind, name: &'static str, unit: &'static str },
+    // Array 欄位，值對應到 selector 名稱表
+    Selector { base: u16, names: &'static [&'static str] },
+    ReportInterval,
+}
+
+#[derive(Clone)]
+struct Entry {
+    role: Role,
+    field: ReportField,
+    element: usize,
+}
+
+pub struct SensorLayout {
+    uses_report_ids: bool,
+    entries: Vec<Entry>,
+}
+
+fn selector_table(usage: u16) -> Option<(u16, &'static [&'static str])> {
+    let tables: [(u16, &'static [&'static str]); 4] = [
+        (SEL_SENSOR_STATE, &USAGE_SENSOR_STATE),
+        (SEL_SENSOR_EVENT, &USAGE_SENSOR_EVENT),
+        (SEL_REPORTING_STATE, &USAGE_REPORTING_STATE),
+        (SEL_POWER_STATE, &USAGE_POWER_STATE),
+    ];
+    tables.into_iter().find(|(base, names)| (*base..*base + names.len() as u16).contains(&usage))
+}
+
+// 沒有宣告單位時依規範為 ms，宣告為秒時依 unit exponent 換算
+fn interval_ms(field: &ReportField, raw: i64) -> f64 {
+    match field.unit {
+        UNIT_SECOND => raw as f64 * 10f64.powi(field.unit_exponent) * 1000.0,
+        _ => raw as f64,
+    }
+}
+
+// Array 欄位的值是 selector 清單的索引
+fn selector_name(entry: &Entry, base: u16, names: &[&str], raw: i64) -> Option<String> {
+    let usage = *entry.field.usages.get(usize::try_from(raw - entry.field.logical_min as i64).ok()?)?;
+    names.get(usage.checked_sub(base)? as usize).map(|n| n.to_string())
+}
+
+impl SensorLayout {
+    // 沒有任何可辨識的感測器欄位時回傳 None
+    pub fn from_descriptor(desc: &ReportDescriptor) -> Option<Self> {
+        let mut entries = Vec::new();
+        for field in desc.fields.iter().filter(|f| f.usage_page == PAGE_SENSOR && !f.is_constant()) {
+            if !field.is_variable() {
+                // selector 以 Logical collection 包住，整個欄位的 usage 都屬於同一張表
+                let Some((base, names)) = field.usages.iter().find_map(|u| selector_table(*u)) else { continue };
+                entries.push(Entry { role: Role::Selector { base, names }, field: field.clone(), element: 0 });
+                continue;
+            }
+            for (element, usage) in field.usages.iter().enumerate().take(field.count) {
+                // 高 4 位元是 modifier (靈敏度、最大值等)，只取原始資料欄位
+                let role = match DATA_FIELDS.iter().find(|(u, ..)| u == usage) {
+                    Some((_, kind, name, unit)) => Role::Data { kind: *kind, name, unit },
+                    None if *usage == USAGE_REPORT_INTERVAL => Role::ReportInterval,
+                    None => continue,
+                };
+                entries.push(Entry { role, field: field.clone(), element });
+            }
+        }
+        if !entries.iter().any(|e| matches!(e.role, Role::Data { .. })) { return None; }
+        Some(Self { uses_report_ids: desc.uses_report_ids, entries })
+    }
+
+    fn sensor_of(&self, report_id: u8) -> Option<SensorKind> {
+        self.entries.iter()
+            .filter(|e| e.field.report_id == report_id)
+            .find_map(|e| match e.role { Role::Data { kind, .. } => Some(kind), _ => None })
+    }
+
+    fn entries_of(&self, kind: ReportKind, report_id: u8) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(move |e| e.field.kind == kind && e.field.report_id == report_id)
+    }
+
+    // 解析 input report (含 Report ID)；不是感測器資料時回傳 None
+    pub fn decode_input(&self, report: &[u8]) -> Option<SensorReading> {
+        let (report_id, body) = if self.uses_report_ids { report.split_first().map(|(id, b)| (*id, b))? } else { (0, report) };
+        let mut reading = SensorReading { report_id, sensor: self.sensor_of(report_id)?, state: None, event: None, values: Vec::new() };
+        for e in self.entries_of(ReportKind::Input, report_id) {
+            let Some(raw) = e.field.element_value(body, e.element) else { continue };
+            match e.role {
+                Role::Data { name, unit, .. } => reading.values.push(SensorValue {
+                    name: name.to_string(),
+                    value: raw as f64 * 10f64.powi(e.field.unit_exponent),
+                    unit: unit.to_string(),
+                }),
+                Role::Selector { base, names } if base == SEL_SENSOR_STATE => reading.state = selector_name(e, base, names, raw),
+                Role::Selector { base, names } if base == SEL_SENSOR_EVENT => reading.event = selector_name(e, base, names, raw),
+                _ => {}
+            }
+        }
+        (!reading.values.is_empty()).then_some(reading)
+    }
+
+    // 讀取各感測器的屬性 feature report
+    pub fn read_properties(&self, dev: &dyn HidIo, desc: &ReportDescriptor) -> Result<Vec<SensorProperties>, String> {
+        let ids = self.entries.iter()
+            .filter(|e| e.field.kind == ReportKind::Feature)
+            .map(|e| e.field.report_id);
+        let reports = hid_io::read_feature_reports(dev, desc, ids)?;
+        if reports.is_empty() { return Err("此設備沒有可讀取的感測器屬性".into()); }
+
+        let mut out = Vec::new();
+        for (id, body) in reports {
+            let mut props = SensorProperties { report_id: id, sensor: self.sensor_of(id), ..Default::default() };
+            for e in self.entries_of(ReportKind::Feature, id) {
+                let Some(raw) = e.field.element_value(&body, e.element) else { continue };
+                match e.role {
+                    Role::ReportInterval => props.report_interval_ms = Some(interval_ms(&e.field, raw)),
+                    Role::Selector { base, names } => {
+                        let name = selector_name(e, base, names, raw);
+                        match base {
+                            SEL_REPORTING_STATE => props.reporting_state = name,
+                            SEL_POWER_STATE => props.power_state = name,
+                            SEL_SENSOR_STATE => props.state = name,
+                            _ => {}
+                        }
+                    }
+                    Role::Data { .. } => {}
+                }
+            }
+            out.push(props);
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,546 @@
+// --- 指令序列 (工廠測試腳本) ---
+// 以 JSON 描述的步驟在後端依序執行，每一步以 sequence-progress 事件回報，最後回傳 pass / fail 摘要。
+// 緊接在 send 之後的 expect 與其合併為一次寫入並等待回覆，避免回覆在開始等待前就到達；
+// 單獨的 expect (例如 wait 之後) 只等待設備主動送出的 report。
+//
+// { "name": "self-test",
+//   "steps": [
+//     { "type": "send", "data": [1, 2] },
+//     { "type": "expect", "pattern": "01 ?? 00", "timeout_ms": 500 },
+//     { "type": "wait", "ms": 100 },
+//     { "type": "repeat", "times": 3, "steps": [ { "type": "send", "data": [3] } ] }
+//   ] }
+//
+// 分支：if 依最近一次 expect 收到的回覆判斷，then / else 為 "next" (下一步)、"fail" (判定失敗)、
+// "end" (結束序列) 或 label 名稱。label 標記跳躍目標，不算一個步驟，也不能放在 repeat 內。
+//     { "type": "if", "condition": "byte[2] == 0x01", "then": "rev-b", "else": "fail" },
+//     { "type": "label", "name": "rev-b" }
+// 條件語法 (op 為 == != < <= > >=，數值可為十進位或 0x 開頭的十六進位)：
+//   byte[N] <op> <值>            例: "byte[2] == 0x01"
+//   byte[N] & <遮罩> <op> <值>   例: "byte[3] & 0xF0 == 0x20"
+//   len <op> <值>                回覆長度
+//   matches <pattern>            格式同 expect 的 pattern，例: "matches 01 ?? A*"
+//
+// send 的 data 也可以是樣板字串 (見 template.rs)，每次執行到該步驟時展開，例如 repeat 中的計數器：
+//     { "type": "send", "data": "10 {{counter:u16le}} {{random:4}}" }
+
+use crate::template::Payload;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_EXPECT_TIMEOUT_MS: i32 = 1000;
+// 展開 repeat 後的步驟上限
+const MAX_ACTIONS: usize = 100_000;
+// 實際執行的步驟上限 (分支形成迴圈時)
+const MAX_EXECUTED: usize = 1_000_000;
+// 結果中保留的步驟紀錄上限 (測試報告用)
+const MAX_RECORDED_STEPS: usize = 10_000;
+// wait 每隔這段時間檢查一次是否已取消
+const WAIT_SLICE: Duration = Duration::from_millis(50);
+
+// 回覆的比對樣式；report 以此開頭即符合。每個位元組兩個字元，每個字元為十六進位數字
+// 或萬用字元 (? * .)，以空白分隔或連續書寫皆可，例: "01 ?? A* .."、"01??A*"
+// 也可寫成 值/遮罩，只比對遮罩為 1 的位元，例: "80/C0" (最高兩個 bit 為 10)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(try_from = "String")]
+pub struct BytePattern {
+    text: String,
+    // (值, 遮罩)
+    bytes: Vec<(u8, u8)>,
+}
+
+fn parse_nibble(c: char) -> Option<(u8, u8)> {
+    match c {
+        '?' | '*' | '.' => Some((0, 0)),
+        c => c.to_digit(16).map(|d| (d as u8, 0x0F)),
+    }
+}
+
+impl TryFrom<String> for BytePattern {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        let invalid = |t: &str| format!("pattern 中無效的位元組: {}", t);
+        let mut bytes = Vec::new();
+        for token in text.split_whitespace() {
+            if let Some((value, mask)) = token.split_once('/') {
+                let value = u8::from_str_radix(value, 16).map_err(|_| invalid(token))?;
+                let mask = u8::from_str_radix(mask, 16).map_err(|_| invalid(token))?;
+                bytes.push((value & mask, mask));
+                continue;
+            }
+            let chars: Vec<char> = token.chars().collect();
+            if !chars.len().is_multiple_of(2) { return Err(invalid(token)); }
+            for pair in chars.chunks(2) {
+                let (Some((hi, hi_mask)), Some((lo, lo_mask))) = (parse_nibble(pair[0]), parse_nibble(pair[1])) else {
+                    return Err(invalid(token));
+                };
+                bytes.push((hi << 4 | lo, hi_mask << 4 | lo_mask));
+            }
+        }
+        if bytes.is_empty() { return Err("pattern 是空的".into()); }
+        Ok(Self { text, bytes })
+    }
+}
+
+impl BytePattern {
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.len() >= self.bytes.len() && self.bytes.iter().zip(data).all(|((value, mask), b)| b & mask == *value)
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Op { Lt, Le, Gt, Ge, Eq, Ne }
+
+impl Op {
+    fn apply(self, lhs: i64, rhs: i64) -> bool {
+        match self {
+            Op::Lt => lhs < rhs,
+            Op::Le => lhs <= rhs,
+            Op::Gt => lhs > rhs,
+            Op::Ge => lhs >= rhs,
+            Op::Eq => lhs == rhs,
+            Op::Ne => lhs != rhs,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Test {
+    Byte { index: usize, mask: Option<i64>, op: Op, value: i64 },
+    Len { op: Op, value: i64 },
+    Matches(BytePattern),
+}
+
+// 對回覆內容的判斷，格式見檔案開頭
+#[derive(Deserialize, Clone, Debug)]
+#[serde(try_from = "String")]
+pub struct Condition {
+    text: String,
+    test: Test,
+}
+
+fn parse_number(text: &str) -> Result<i64, String> {
+    let text = text.trim();
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("無效的數值: {}", text))
+}
+
+// 拆成 (左邊, 運算子, 右邊)；兩個字元的運算子優先
+fn split_comparison(text: &str) -> Result<(&str, Op, &str), String> {
+    const OPS: [(&str, Op); 6] = [("<=", Op::Le), (">=", Op::Ge), ("==", Op::Eq), ("!=", Op::Ne), ("<", Op::Lt), (">", Op::Gt)];
+    let (pos, sym, op) = OPS.iter()
+        .filter_map(|(sym, op)| text.find(sym).map(|pos| (pos, *sym, *op)))
+        .min_by_key(|(pos, sym, _)| (*pos, std::cmp::Reverse(sym.len())))
+        .ok_or(format!("缺少比較運算子: {}", text))?;
+    Ok((text[..pos].trim(), op, text[pos + sym.len()..].trim()))
+}
+
+impl TryFrom<String> for Condition {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        let trimmed = text.trim();
+        let test = if let Some(pattern) = trimmed.strip_prefix("matches ") {
+            Test::Matches(BytePattern::try_from(pattern.trim().to_string())?)
+        } else {
+            let (lhs, op, rhs) = split_comparison(trimmed)?;
+            let value = parse_number(rhs)?;
+            if lhs == "len" {
+                Test::Len { op, value }
+            } else {
+                let (target, mask) = match lhs.split_once('&') {
+                    Some((target, mask)) => (target.trim(), Some(parse_number(mask)?)),
+                    None => (lhs, None),
+                };
+                let index = target.strip_prefix("byte[").and_then(|t| t.strip_suffix(']'))
+                    .and_then(|i| i.trim().parse::<usize>().ok())
+                    .ok_or(format!("條件的左邊必須是 byte[N] 或 len: {}", lhs))?;
+                Test::Byte { index, mask, op, value }
+            }
+        };
+        Ok(Self { text, test })
+    }
+}
+
+impl Condition {
+    // 超出回覆長度的位元組視為不成立
+    pub fn eval(&self, response: &[u8]) -> bool {
+        match &self.test {
+            Test::Byte { index, mask, op, value } => response.get(*index)
+                .is_some_and(|b| op.apply(mask.map_or(*b as i64, |m| *b as i64 & m), *value)),
+            Test::Len { op, value } => op.apply(response.len() as i64, *value),
+            Test::Matches(pattern) => pattern.matches(response),
+        }
+    }
+}
+
+// 分支的去向
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(from = "String")]
+pub enum Jump {
+    Next,
+    Fail,
+    End,
+    Label(String),
+}
+
+impl From<String> for Jump {
+    fn from(text: String) -> Self {
+        match text.as_str() {
+            "next" => Jump::Next,
+            "fail" => Jump::Fail,
+            "end" => Jump::End,
+            _ => Jump::Label(text),
+        }
+    }
+}
+
+fn default_next() -> Jump { Jump::Next }
+
+#[derive(Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Step {
+    // data 的格式與 send_hid_command 相同
+    Send { data: Payload },
+    Wait { ms: u64 },
+    Expect { pattern: BytePattern, #[serde(default)] timeout_ms: Option<i32> },
+    Repeat { times: u32, steps: Vec<Step> },
+    If {
+        condition: Condition,
+        #[serde(default = "default_next")]
+        then: Jump,
+        #[serde(rename = "else", default = "default_next")]
+        otherwise: Jump,
+    },
+    Label { name: String },
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Sequence {
+    #[serde(default)]
+    pub name: String,
+    pub steps: Vec<Step>,
+    // 失敗後繼續執行其餘步驟 (預設在第一個失敗處停止)
+    #[serde(default)]
+    pub continue_on_failure: bool,
+}
+
+// 展開後的單一動作
+enum Action {
+    Send { data: Payload, expect: Option<(BytePattern, i32)> },
+    Expect(BytePattern, i32),
+    Wait(u64),
+    If { condition: Condition, then: Jump, otherwise: Jump },
+}
+
+impl Action {
+    fn describe(&self) -> String {
+        match self {
+            Action::Send { data, expect: None } => format!("send {}", data.describe()),
+            Action::Send { data, expect: Some((pattern, _)) } => format!("send {}, expect {}", data.describe(), pattern.text),
+            Action::Expect(pattern, _) => format!("expect {}", pattern.text),
+            Action::Wait(ms) => format!("wait {} ms", ms),
+            Action::If { condition, .. } => format!("if {}", condition.text),
+        }
+    }
+}
+
+// label 名稱 -> 其後第一個動作的位置
+type Labels = std::collections::HashMap<String, usize>;
+
+fn flatten(steps: &[Step], out: &mut Vec<Action>, mut labels: Option<&mut Labels>) -> Result<(), String> {
+    for step in steps {
+        match step {
+            Step::Send { data } => out.push(Action::Send { data: data.clone(), expect: None }),
+            Step::Wait { ms } => out.push(Action::Wait(*ms)),
+            Step::Expect { pattern, timeout_ms } => {
+                let expect = (pattern.clone(), timeout_ms.unwrap_or(DEFAULT_EXPECT_TIMEOUT_MS));
+                match out.last_mut() {
+                    Some(Action::Send { expect: slot @ None, .. }) => *slot = Some(expect),
+                    _ => out.push(Action::Expect(expect.0, expect.1)),
+                }
+            }
+            Step::Repeat { times, steps } => {
+                for _ in 0..*times {
+                    // 每一輪各自展開，repeat 內的 expect 不會接到外面的 send
+                    let mut body = Vec::new();
+                    flatten(steps, &mut body, None)?;
+                    // 沒有動作時 out 不會增加，次數上限檢查不到
+                    if body.is_empty() { return Err("repeat 內沒有可執行的步驟".into()); }
+                    out.extend(body);
+                    if out.len() > MAX_ACTIONS { return Err(format!("展開 repeat 後超過 {} 個步驟", MAX_ACTIONS)); }
+                }
+            }
+            Step::If { condition, then, otherwise } => {
+                out.push(Action::If { condition: condition.clone(), then: then.clone(), otherwise: otherwise.clone() });
+            }
+            Step::Label { name } => {
+                // repeat 展開後同一個 label 會出現多次，無法決定跳到哪一輪
+                let labels = labels.as_deref_mut().ok_or(format!("label \"{}\" 不能放在 repeat 內", name))?;
+                if matches!(Jump::from(name.clone()), Jump::Next | Jump::Fail | Jump::End) {
+                    return Err(format!("label 不能命名為 {}", name));
+                }
+                if labels.insert(name.clone(), out.len()).is_some() {
+                    return Err(format!("label 重複: {}", name));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Clone)]
+pub struct SequenceProgress {
+    // 從 1 開始，repeat 已展開 (分支跳躍時不連續)
+    pub step: usize,
+    pub total: usize,
+    pub description: String,
+    pub ok: bool,
+    pub error: Option<String>,
+    pub response: Option<Vec<u8>>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct StepFailure {
+    pub step: usize,
+    pub description: String,
+    pub error: String,
+}
+
+// 每個執行過的步驟，依執行順序
+#[derive(Serialize, Clone)]
+pub struct StepRecord {
+    pub step: usize,
+    pub description: String,
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SequenceResult {
+    pub name: String,
+    pub passed: bool,
+    pub steps_run: usize,
+    pub total: usize,
+    pub failures: Vec<StepFailure>,
+    pub cancelled: bool,
+    // 開始時間 (Unix ms)
+    pub started_ms: i64,
+    pub elapsed_ms: u64,
+    pub steps: Vec<StepRecord>,
+}
+
+// 寫入 data (樣板由呼叫端展開；None 時不寫入)；有 expect 時等待符合的回覆 (逾時回傳空 Vec)
+pub type ExchangeFn<'a> = &'a dyn Fn(Option<&Payload>, Option<(&BytePattern, i32)>) -> Result<Vec<u8>, String>;
+
+fn expect_reply(response: Vec<u8>, expect: Option<(&BytePattern, i32)>) -> Result<Option<Vec<u8>>, String> {
+    match expect {
+        Some((pattern, timeout_ms)) if response.is_empty() => {
+            Err(format!("{} ms 內沒有符合 {} 的回覆", timeout_ms, pattern.text))
+        }
+        Some(_) => Ok(Some(response)),
+        None => Ok(None),
+    }
+}
+
+// 只有序列格式錯誤時回傳 Err，步驟失敗記錄在結果中
+pub fn run(
+    sequence: &Sequence,
+    exchange: ExchangeFn,
+    cancel: &AtomicBool,
+    progress: &mut dyn FnMut(SequenceProgress),
+) -> Result<SequenceResult, String> {
+    let mut actions = Vec::new();
+    let mut labels = Labels::new();
+    flatten(&sequence.steps, &mut actions, Some(&mut labels))?;
+    for action in &actions {
+        let Action::If { then, otherwise, .. } = action else { continue };
+        for jump in [then, otherwise] {
+            if let Jump::Label(name) = jump {
+                if !labels.contains_key(name) { return Err(format!("找不到 label: {}", name)); }
+            }
+        }
+    }
+    let total = actions.len();
+    let started = Instant::now();
+    let mut result = SequenceResult {
+        name: sequence.name.clone(),
+        passed: true,
+        steps_run: 0,
+        total,
+        failures: Vec::new(),
+        cancelled: false,
+        started_ms: chrono::Local::now().timestamp_millis(),
+        elapsed_ms: 0,
+        steps: Vec::new(),
+    };
+
+    // 最近一次 expect 收到的回覆，供 if 判斷
+    let mut last_response: Option<Vec<u8>> = None;
+    let mut i = 0;
+    while let Some(action) = actions.get(i) {
+        if cancel.load(Ordering::Relaxed) {
+            result.cancelled = true;
+            result.passed = false;
+            break;
+        }
+        if result.steps_run >= MAX_EXECUTED {
+            result.passed = false;
+            let error = format!("執行超過 {} 個步驟，分支可能形成無窮迴圈", MAX_EXECUTED);
+            result.failures.push(StepFailure { step: i + 1, description: action.describe(), error });
+            break;
+        }
+        let mut next = i + 1;
+        let step_started = Instant::now();
+        let outcome = match action {
+            Action::Wait(ms) => {
+                // 分段等待，取消時在下一個步驟前停止
+                let deadline = step_started + Duration::from_millis(*ms);
+                while !cancel.load(Ordering::Relaxed) {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() { break; }
+                    thread::sleep(left.min(WAIT_SLICE));
+                }
+                Ok(None)
+            }
+            Action::Send { data, expect } => {
+                let expect = expect.as_ref().map(|(p, t)| (p, *t));
+                exchange(Some(data), expect).and_then(|resp| expect_reply(resp, expect))
+            }
+            Action::Expect(pattern, timeout_ms) => {
+                let expect = Some((pattern, *timeout_ms));
+                exchange(None, expect).and_then(|resp| expect_reply(resp, expect))
+            }
+            Action::If { condition, then, otherwise } => match &last_response {
+                None => Err("沒有可判斷的回覆 (if 之前需要有 expect)".to_string()),
+                Some(response) => {
+                    let holds = condition.eval(response);
+                    match if holds { then } else { otherwise } {
+                        Jump::Next => Ok(None),
+                        Jump::Fail => Err(format!("條件{}成立，判定失敗", if holds { "" } else { "不" })),
+                        Jump::End => {
+                            next = actions.len();
+                            Ok(None)
+                        }
+                        Jump::Label(name) => match labels.get(name) {
+                            Some(&target) => {
+                                next = target;
+                                Ok(None)
+                            }
+                            None => Err(format!("找不到 label: {}", name)),
+                        },
+                    }
+                }
+            },
+        };
+        if let Ok(Some(response)) = &outcome {
+            last_response = Some(response.clone());
+        }
+        result.steps_run += 1;
+        let description = action.describe();
+        let (response, error) = match outcome {
+            Ok(response) => (response, None),
+            Err(e) => (None, Some(e)),
+        };
+        if result.steps.len() < MAX_RECORDED_STEPS {
+            let elapsed_ms = step_started.elapsed().as_millis() as u64;
+            result.steps.push(StepRecord { step: i + 1, description: description.clone(), error: error.clone(), elapsed_ms });
+        }
+        progress(SequenceProgress {
+            step: i + 1,
+            total,
+            description: description.clone(),
+            ok: error.is_none(),
+            error: error.clone(),
+            response,
+        });
+        if let Some(error) = error {
+            result.passed = false;
+            result.failures.push(StepFailure { step: i + 1, description, error });
+            if !sequence.continue_on_failure { break; }
+        }
+        i = next;
+    }
+    result.elapsed_ms = started.elapsed().as_millis() as u64;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(text: &str) -> Result<BytePattern, String> {
+        BytePattern::try_from(text.to_string())
+    }
+
+    fn sequence(json: &str) -> Sequence {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn byte_patterns_match_prefix_with_wildcards_and_masks() {
+        let p = pattern("01 ?? A* 80/C0").unwrap();
+        assert!(p.matches(&[0x01, 0xFF, 0xA7, 0xBF, 0x00]));
+        assert!(!p.matches(&[0x01, 0xFF, 0xB7, 0xBF]));
+        assert!(!p.matches(&[0x01, 0xFF, 0xA7, 0x40]));
+        assert!(!p.matches(&[0x01, 0xFF, 0xA7]));
+        assert!(pattern("01??A*").unwrap().matches(&[0x01, 0x02, 0xA3]));
+        assert_eq!(p.text(), "01 ?? A* 80/C0");
+        for text in ["", "   ", "0", "0G", "é1", "1/ZZ"] {
+            assert!(pattern(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn rejects_empty_repeat_bodies() {
+        let mut out = Vec::new();
+        let empty = sequence(r#"{ "steps": [ { "type": "repeat", "times": 1000000, "steps": [] } ] }"#);
+        assert!(flatten(&empty.steps, &mut out, None).is_err());
+        let nested = sequence(r#"{ "steps": [ { "type": "repeat", "times": 2, "steps": [
+            { "type": "repeat", "times": 5, "steps": [] } ] } ] }"#);
+        assert!(flatten(&nested.steps, &mut out, None).is_err());
+        // 次數為 0 的 repeat 不展開任何步驟
+        let zero = sequence(r#"{ "steps": [ { "type": "repeat", "times": 0, "steps": [] } ] }"#);
+        assert!(flatten(&zero.steps, &mut out, None).is_ok());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn runs_expanded_steps_and_branches() {
+        let seq = sequence(r#"{ "name": "t", "steps": [
+            { "type": "repeat", "times": 2, "steps": [ { "type": "send", "data": [1] } ] },
+            { "type": "send", "data": [2] },
+            { "type": "expect", "pattern": "02 ??" },
+            { "type": "if", "condition": "byte[1] == 0x05", "then": "done", "else": "fail" },
+            { "type": "send", "data": [3] },
+            { "type": "label", "name": "done" }
+        ] }"#);
+        let sent = std::cell::RefCell::new(Vec::new());
+        let exchange = |data: Option<&Payload>, expect: Option<(&BytePattern, i32)>| {
+            if let Some(data) = data { sent.borrow_mut().push(data.describe()); }
+            Ok(if expect.is_some() { vec![0x02, 0x05] } else { Vec::new() })
+        };
+        let result = run(&seq, &exchange, &AtomicBool::new(false), &mut |_| {}).unwrap();
+        assert!(result.passed);
+        assert_eq!(result.total, 5);
+        assert_eq!(result.steps_run, 4);
+        assert_eq!(*sent.borrow(), vec!["01", "01", "02"]);
+    }
+
+    #[test]
+    fn rejects_unknown_labels() {
+        let seq = sequence(r#"{ "steps": [ { "type": "if", "condition": "len > 0", "then": "nowhere" } ] }"#);
+        let exchange = |_: Option<&Payload>, _: Option<(&BytePattern, i32)>| Ok(Vec::new());
+        assert!(run(&seq, &exchange, &AtomicBool::new(false), &mut |_| {}).is_err());
+    }
+}
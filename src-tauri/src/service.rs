@@ -0,0 +1,483 @@
+// --- 無視窗監控服務 ---
+// 以 --service 啟動 (或環境變數 KEYSTONE_SERVICE=1) 時不顯示視窗，依 config 目錄的 service.json 持續監控：
+// 設定的設備一插入就開始監聽並套用監看條件，拔除後等待重新插入。連線、斷線、監看觸發、
+// 錯誤狀態與停滯經由 logging.rs 記錄 (service 類別，寫入 app.log)，並在記憶體保留最近的記錄供 /log 查詢。
+//
+// 管理介面是只接受本機連線的 HTTP (127.0.0.1:control_port)，回應皆為 JSON：
+//   GET  /status           服務與各設備的狀態
+//   GET  /log?limit=N&cursor=C  最近的記錄，由新到舊分頁 (預設 100 筆，見 paging.rs)
+//   POST /reload           重新載入 service.json
+//   POST /stop             結束服務
+//   POST /borrow           把設備暫時借給 GUI: { "path": "...", "timeout_ms": 1800000 }
+//   POST /return           GUI 用完後歸還: { "path": "..." }
+//   GET  /station          工作站鎖定狀態 (見 station.rs)，沒有鎖定時為 null
+//   POST /station/acquire  取得或續約鎖定: { "owner": "...", "note": null, "ttl_secs": 300, "token": null }，回傳 { "token": "..." }
+//   POST /station/release  釋放鎖定: { "token": "..." }
+// 帶有 Origin header 的請求一律拒絕，避免瀏覽器中的網頁對本機服務送出請求；Host header 也必須是
+// 127.0.0.1:<port> 或 localhost:<port>，防止 DNS rebinding 的網頁以同源 GET 讀取狀態與記錄。
+// 可另外設定 API key 與同使用者檢查 (見 auth.rs)；GET 需要 read，其餘需要 control 權限。
+//
+// 設備交接：GUI 與服務在同一台電腦時，GUI 開啟設備失敗會先向服務借用再重試。服務收到 borrow 後
+// 停止監聽並等 I/O 執行緒關閉設備才回應，借出期間不再自動開啟；歸還或借用逾時 (GUI 當掉) 後
+// 下一次掃描重新開啟。重複 borrow 同一設備會延長借用時間。
+//
+// 安裝：以 --print-service-unit 執行會輸出 systemd unit (Linux)，或建立開機時執行的工作排程指令
+// (Windows；程式沒有實作 SCM 服務介面，因此以工作排程代替 Windows 服務)。
+//
+// { "scan_interval_ms": 2000, "control_port": 47800,
+//   "devices": [ { "name": "line-1", "vendor_id": 1234, "product_id": 5678,
+//                  "watches": [ { "name": "low-battery", "expression": "battery < 20" } ] } ],
+//   "auth": { "same_user": true, "api_keys": [ { "name": "ci", "key": "...", "scope": "control" } ] } }
+
+use crate::auth::{self, AuthConfig, Credentials};
+use crate::health::Favorite;
+use crate::logging::Severity;
+use crate::paging::{Page, PageRequest, SeqLog};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const SERVICE_FLAG: &str = "--service";
+pub const PRINT_UNIT_FLAG: &str = "--print-service-unit";
+const ENV_VAR: &str = "KEYSTONE_SERVICE";
+// 記憶體中保留的記錄筆數
+const RECENT_LIMIT: usize = 1000;
+const REQUEST_TIMEOUT_MS: u64 = 5000;
+// 請求標頭與內容的長度上限
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 64 * 1024;
+// 未指定時的借用時間
+const DEFAULT_LOAN_MS: u64 = 30 * 60 * 1000;
+
+pub fn requested() -> bool {
+    std::env::args().skip(1).any(|a| a == SERVICE_FLAG) || std::env::var(ENV_VAR).is_ok_and(|v| v == "1")
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct WatchSpec {
+    pub name: String,
+    pub expression: String,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ServiceDevice {
+    // 辨識方式與常用設備相同 (VID / PID 加上選用的序號、介面)
+    #[serde(flatten)]
+    pub device: Favorite,
+    #[serde(default)]
+    pub watches: Vec<WatchSpec>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ServiceConfig {
+    // 尋找未連線設備的間隔
+    pub scan_interval_ms: u64,
+    pub control_port: u16,
+    pub devices: Vec<ServiceDevice>,
+    pub auth: AuthConfig,
+}
+
+impl Default for ServiceConfig {
+    fn default() -> Self {
+        Self { scan_interval_ms: 2000, control_port: 47800, devices: Vec::new(), auth: AuthConfig::default() }
+    }
+}
+
+impl ServiceConfig {
+    // 檔案不存在時為預設值 (沒有設備)
+    pub fn load(file: &Path) -> Result<Self, String> {
+        if !file.exists() { return Ok(Self::default()); }
+        let text = std::fs::read_to_string(file).map_err(|e| format!("讀取 {} 失敗: {}", file.display(), e))?;
+        let config: Self = serde_json::from_str(&text).map_err(|e| format!("服務設定格式錯誤: {}", e))?;
+        config.auth.validate()?;
+        Ok(config)
+    }
+}
+
+// borrow / return 的請求內容
+#[derive(Deserialize, Serialize, Clone)]
+pub struct HandoverRequest {
+    pub path: String,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Loan {
+    pub path: String,
+    // 借用到期時間 (Unix ms)，到期後自動收回
+    pub until_ms: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct LogEntry {
+    pub timestamp_ms: u64,
+    // 設定中的設備名稱 (與設備無關的記錄為 None)
+    pub device: Option<String>,
+    pub event: String,
+    pub detail: Value,
+}
+
+impl LogEntry {
+    pub fn severity(&self) -> Severity {
+        match self.event.as_str() {
+            "error" | "device-fault" => Severity::Error,
+            "request-denied" | "loan-expired" | "device-stall" | "watch-triggered" => Severity::Warning,
+            _ => Severity::Info,
+        }
+    }
+
+    // 記錄的文字內容 (事件名稱與細節)
+    pub fn message(&self) -> String {
+        match &self.detail {
+            Value::Null => self.event.clone(),
+            Value::String(s) => format!("{}: {}", self.event, s),
+            detail => format!("{}: {}", self.event, detail),
+        }
+    }
+}
+
+// 每筆記錄送往統一記錄的出口 (由呼叫端接到 logging::log)
+pub type LogSink = Box<dyn Fn(&LogEntry) + Send>;
+
+#[derive(Serialize, Clone)]
+pub struct DeviceSlot {
+    pub name: String,
+    // 目前監聽中的路徑，未連線為 None
+    pub path: Option<String>,
+    pub connected_since_ms: Option<u64>,
+    pub triggers: u64,
+    pub last_error: Option<String>,
+    // 借給 GUI 使用中
+    pub lent: Option<Loan>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ServiceStatus {
+    // 是否以服務模式執行
+    pub running: bool,
+    pub started_ms: u64,
+    pub config_file: Option<String>,
+    pub devices: Vec<DeviceSlot>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+pub struct Service {
+    pub config: ServiceConfig,
+    pub running: bool,
+    config_file: Option<PathBuf>,
+    sink: Option<LogSink>,
+    // 與 config.devices 一一對應
+    slots: Vec<DeviceSlot>,
+    recent: SeqLog<LogEntry>,
+    started_ms: u64,
+}
+
+impl Default for Service {
+    fn default() -> Self { Self::new() }
+}
+
+impl Service {
+    pub fn new() -> Self {
+        Self {
+            config: ServiceConfig::default(),
+            running: false,
+            config_file: None,
+            sink: None,
+            slots: Vec::new(),
+            recent: SeqLog::new(RECENT_LIMIT),
+            started_ms: now_ms(),
+        }
+    }
+
+    pub fn start(&mut self, config_file: &Path, sink: LogSink) -> Result<(), String> {
+        self.config_file = Some(config_file.to_path_buf());
+        self.sink = Some(sink);
+        self.running = true;
+        self.started_ms = now_ms();
+        self.reload()?;
+        self.record(None, "service-started", Value::Null);
+        Ok(())
+    }
+
+    // 重新讀取設定，回傳原本監聽中的路徑；呼叫端停止監聽後，下次掃描會以新設定重新開啟
+    pub fn reload(&mut self) -> Result<Vec<String>, String> {
+        let config = match &self.config_file {
+            Some(file) => ServiceConfig::load(file)?,
+            None => ServiceConfig::default(),
+        };
+        let old = std::mem::replace(&mut self.slots, config.devices.iter()
+            .map(|d| DeviceSlot { name: d.device.name.clone(), path: None, connected_since_ms: None, triggers: 0, last_error: None, lent: None })
+            .collect());
+        self.config = config;
+        Ok(old.into_iter().filter_map(|s| s.path).collect())
+    }
+
+    pub fn record(&mut self, device: Option<&str>, event: &str, detail: Value) {
+        let entry = LogEntry { timestamp_ms: now_ms(), device: device.map(str::to_string), event: event.to_string(), detail };
+        if let Some(sink) = &self.sink { sink(&entry); }
+        self.recent.push(entry);
+    }
+
+    pub fn recent(&self, page: PageRequest) -> Page<LogEntry> {
+        self.recent.page(page, |_| true)
+    }
+
+    // 尚未連線且沒有借出的設備
+    pub fn pending(&self) -> Vec<ServiceDevice> {
+        self.slots.iter().zip(&self.config.devices)
+            .filter(|(s, _)| s.path.is_none() && s.lent.is_none())
+            .map(|(_, d)| d.clone())
+            .collect()
+    }
+
+    // 已連線設備的路徑
+    pub fn connected(&self) -> Vec<String> {
+        self.slots.iter().filter_map(|s| s.path.clone()).collect()
+    }
+
+    pub fn is_claimed(&self, path: &str) -> bool {
+        self.slots.iter().any(|s| s.path.as_deref() == Some(path) || s.lent.as_ref().is_some_and(|l| l.path == path))
+    }
+
+    pub fn name_of(&self, path: &str) -> Option<String> {
+        self.slots.iter().find(|s| s.path.as_deref() == Some(path)).map(|s| s.name.clone())
+    }
+
+    // 以名稱對應，掃描期間重新載入設定時不會對錯設備
+    pub fn mark_connected(&mut self, name: &str, path: &str) {
+        let Some(slot) = self.slots.iter_mut().find(|s| s.name == name && s.path.is_none()) else { return };
+        slot.path = Some(path.to_string());
+        slot.connected_since_ms = Some(now_ms());
+        slot.last_error = None;
+        let name = slot.name.clone();
+        self.record(Some(&name), "connected", Value::String(path.to_string()));
+    }
+
+    pub fn mark_disconnected(&mut self, path: &str) {
+        let Some(slot) = self.slots.iter_mut().find(|s| s.path.as_deref() == Some(path)) else { return };
+        slot.path = None;
+        slot.connected_since_ms = None;
+        let name = slot.name.clone();
+        self.record(Some(&name), "disconnected", Value::String(path.to_string()));
+    }
+
+    // 同樣的錯誤只記錄一次，避免每次掃描都寫入
+    pub fn mark_error(&mut self, name: &str, error: String) {
+        let Some(slot) = self.slots.iter_mut().find(|s| s.name == name) else { return };
+        if slot.last_error.as_ref() == Some(&error) { return; }
+        slot.last_error = Some(error.clone());
+        let name = slot.name.clone();
+        self.record(Some(&name), "error", Value::String(error));
+    }
+
+    // 借出監聽中 (或已借出) 的設備；呼叫端負責停止監聽
+    pub fn lend(&mut self, request: &HandoverRequest) -> Result<(), String> {
+        let path = request.path.as_str();
+        let slot = self.slots.iter_mut()
+            .find(|s| s.path.as_deref() == Some(path) || s.lent.as_ref().is_some_and(|l| l.path == path))
+            .ok_or("服務沒有使用此設備")?;
+        let until_ms = now_ms() + request.timeout_ms.unwrap_or(DEFAULT_LOAN_MS);
+        slot.path = None;
+        slot.connected_since_ms = None;
+        slot.lent = Some(Loan { path: path.to_string(), until_ms });
+        let name = slot.name.clone();
+        self.record(Some(&name), "lent", serde_json::json!({ "path": path, "until_ms": until_ms }));
+        Ok(())
+    }
+
+    pub fn take_back(&mut self, path: &str) -> Result<(), String> {
+        let slot = self.slots.iter_mut()
+            .find(|s| s.lent.as_ref().is_some_and(|l| l.path == path))
+            .ok_or("此設備沒有借出")?;
+        slot.lent = None;
+        let name = slot.name.clone();
+        self.record(Some(&name), "returned", Value::String(path.to_string()));
+        Ok(())
+    }
+
+    // 收回已到期的借用
+    pub fn expire_loans(&mut self) {
+        let now = now_ms();
+        let expired: Vec<_> = self.slots.iter_mut()
+            .filter(|s| s.lent.as_ref().is_some_and(|l| l.until_ms <= now))
+            .filter_map(|s| s.lent.take().map(|l| (s.name.clone(), l.path)))
+            .collect();
+        for (name, path) in expired {
+            self.record(Some(&name), "loan-expired", Value::String(path));
+        }
+    }
+
+    pub fn mark_triggered(&mut self, path: &str) {
+        if let Some(slot) = self.slots.iter_mut().find(|s| s.path.as_deref() == Some(path)) {
+            slot.triggers += 1;
+        }
+    }
+
+    pub fn status(&self) -> ServiceStatus {
+        ServiceStatus {
+            running: self.running,
+            started_ms: self.started_ms,
+            config_file: self.config_file.as_ref().map(|f| f.to_string_lossy().to_string()),
+            devices: self.slots.clone(),
+        }
+    }
+}
+
+// --- 本機 HTTP 管理介面 ---
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    // 帶有 Origin header (來自瀏覽器)
+    pub from_browser: bool,
+    pub host: Option<String>,
+    pub credentials: Credentials,
+}
+
+impl Request {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T, String> {
+        serde_json::from_slice(&self.body).map_err(|e| format!("請求內容格式錯誤: {}", e))
+    }
+}
+
+pub fn reply<T: Serialize>(value: &T) -> (u16, String) {
+    (200, serde_json::to_string(value).unwrap_or_default())
+}
+
+pub fn reply_error(status: u16, error: &str) -> (u16, String) {
+    (status, serde_json::json!({ "error": error }).to_string())
+}
+
+pub fn page_request(request: &Request) -> PageRequest {
+    PageRequest {
+        cursor: request.param("cursor").and_then(|v| v.parse().ok()),
+        limit: request.param("limit").and_then(|v| v.parse().ok()),
+    }
+}
+
+fn read_request(stream: &TcpStream) -> Result<Request, String> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|e| e.to_string())?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else { return Err("無效的請求".into()) };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query.split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| p.split_once('=').map(|(k, v)| (k.to_string(), v.to_string())).unwrap_or((p.to_string(), String::new())))
+        .collect();
+    let credentials = Credentials { api_key: None, peer_uid: auth::peer_uid(stream) };
+    let mut request = Request { method: method.to_string(), path: path.to_string(), query, body: Vec::new(), from_browser: false, host: None, credentials };
+
+    let mut total = line.len();
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        let n = reader.read_line(&mut header).map_err(|e| e.to_string())?;
+        total += n;
+        if n == 0 || header.trim().is_empty() { break; }
+        if total > MAX_HEADER_BYTES { return Err("請求標頭過長".into()); }
+        let Some((name, value)) = header.split_once(':') else { continue };
+        let name = name.trim();
+        request.from_browser |= name.eq_ignore_ascii_case("origin");
+        if name.eq_ignore_ascii_case("host") {
+            request.host = Some(value.trim().to_ascii_lowercase());
+        }
+        if name.eq_ignore_ascii_case("x-api-key") {
+            request.credentials.api_key = Some(value.trim().to_string());
+        } else if name.eq_ignore_ascii_case("authorization") {
+            if let Some(key) = value.trim().strip_prefix("Bearer ") { request.credentials.api_key = Some(key.trim().to_string()); }
+        }
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.trim().parse::<usize>().map_err(|_| "無效的 Content-Length")?;
+        }
+    }
+    if content_length > MAX_BODY_BYTES { return Err("請求內容過長".into()); }
+    request.body.resize(content_length, 0);
+    reader.read_exact(&mut request.body).map_err(|e| e.to_string())?;
+    Ok(request)
+}
+
+// 只接受直接連到本機位址的請求 (DNS rebinding 時 Host 是攻擊者的網域)
+fn local_host(host: Option<&str>, port: u16) -> bool {
+    host.is_some_and(|h| h == format!("127.0.0.1:{}", port) || h == format!("localhost:{}", port))
+}
+
+fn respond(mut stream: &TcpStream, status: u16, body: &str) {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let _ = write!(stream, "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, reason, body.len(), body);
+}
+
+// 在背景執行緒依序處理請求；handler 回傳 (HTTP 狀態碼, JSON 內容)
+pub fn serve<F>(port: u16, handler: F) -> Result<(), String>
+where
+    F: Fn(&Request) -> (u16, String) + Send + 'static,
+{
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port)).map_err(|e| format!("無法在連接埠 {} 開啟管理介面: {}", port, e))?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(Duration::from_millis(REQUEST_TIMEOUT_MS)));
+            let (status, body) = match read_request(&stream) {
+                Ok(request) if request.from_browser => reply_error(403, "不接受來自瀏覽器的請求"),
+                Ok(request) if !local_host(request.host.as_deref(), port) => reply_error(403, "Host 必須是 127.0.0.1 或 localhost"),
+                Ok(request) => handler(&request),
+                Err(e) => reply_error(400, &e),
+            };
+            respond(&stream, status, &body);
+        }
+    });
+    Ok(())
+}
+
+// --- 安裝 ---
+
+pub fn install_instructions(exe: &Path) -> String {
+    let exe = exe.to_string_lossy();
+    if cfg!(windows) {
+        format!(
+            "REM 以系統管理員身分執行；開機時以 SYSTEM 帳號在背景啟動服務\r\n\
+             schtasks /Create /F /TN \"keystone-ws-app service\" /SC ONSTART /RU SYSTEM /TR \"\\\"{}\\\" {}\"\r\n",
+            exe, SERVICE_FLAG,
+        )
+    } else {
+        format!(
+            "# 存成 /etc/systemd/system/keystone-ws-app.service 後執行\n\
+             #   systemctl daemon-reload && systemctl enable --now keystone-ws-app\n\
+             # WebView 需要顯示環境；沒有螢幕的主機可改用 xvfb-run 啟動\n\
+             [Unit]\n\
+             Description=keystone-ws-app HID monitoring service\n\
+             After=network.target\n\n\
+             [Service]\n\
+             ExecStart=\"{}\" {}\n\
+             Restart=always\n\
+             RestartSec=5\n\n\
+             [Install]\n\
+             WantedBy=multi-user.target\n",
+            exe, SERVICE_FLAG,
+        )
+    }
+}
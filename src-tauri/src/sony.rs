@@ -0,0 +1,413 @@
+// --- Sony DualShock 4 / DualSense ---
+// 解碼完整的 input report (搖桿、扳機、按鍵、陀螺儀、觸控板、電量)，
+// 並組成控制震動、燈條、玩家指示燈與自適應扳機的 output report。
+// 格式參考 Linux hid-playstation 驅動，USB 與藍牙的 report 不同：
+//   DualShock 4  input  USB 0x01 (64 bytes)；藍牙 0x11 (78 bytes，資料從第 3 byte 開始)
+//                output USB 0x05 (32 bytes)；藍牙 0x11 (78 bytes)
+//   DualSense    input  USB 0x01 (64 bytes)；藍牙 0x31 (78 bytes，資料從第 2 byte 開始)
+//                output USB 0x02 (48 bytes)；藍牙 0x31 (78 bytes)
+// 藍牙 report 最後 4 bytes 為 CRC-32，計算時前面加上 0xA1 (input) / 0xA2 (output)。
+// 藍牙連線後設備先送精簡的 0x01 report (不解碼)，讀取校正用的 feature report 後才改送完整格式。
+
+use crate::checksum::crc32_update;
+use crate::descriptor::ReportSizes;
+use crate::gamepad::HatDirection;
+use crate::protocols::{DeviceIdentity, Rgb};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+const VENDOR_SONY: u16 = 0x054C;
+const PRODUCTS_DS4: [u16; 3] = [0x05C4, 0x09CC, 0x0BA0];
+const PRODUCTS_DUALSENSE: [u16; 2] = [0x0CE6, 0x0DF2];
+
+const USB_INPUT_ID: u8 = 0x01;
+const USB_INPUT_LEN: usize = 64;
+const BT_REPORT_LEN: usize = 78;
+const BT_CRC_INPUT: u8 = 0xA1;
+const BT_CRC_OUTPUT: u8 = 0xA2;
+
+const DS4_BT_ID: u8 = 0x11;
+const DS4_USB_OUTPUT_ID: u8 = 0x05;
+const DS4_USB_OUTPUT_LEN: usize = 32;
+// hw_control: 以 HID 傳送並附 CRC
+const DS4_BT_HW_CONTROL: u8 = 0xC0;
+const DS4_FLAG_MOTOR: u8 = 0x01;
+const DS4_FLAG_LIGHTBAR: u8 = 0x02;
+
+const DS_BT_ID: u8 = 0x31;
+const DS_USB_OUTPUT_ID: u8 = 0x02;
+const DS_USB_OUTPUT_LEN: usize = 48;
+const DS_BT_TAG: u8 = 0x10;
+const DS_COMMON_LEN: usize = 47;
+// valid_flag0
+const DS_FLAG_VIBRATION: u8 = 0x01 | 0x02;
+const DS_FLAG_RIGHT_TRIGGER: u8 = 0x04;
+const DS_FLAG_LEFT_TRIGGER: u8 = 0x08;
+// valid_flag1
+const DS_FLAG_LIGHTBAR: u8 = 0x04;
+const DS_FLAG_PLAYER_LEDS: u8 = 0x10;
+// valid_flag2 / lightbar_setup：從開機的藍色呼吸燈手動接管燈條
+const DS_FLAG_LIGHTBAR_SETUP: u8 = 0x02;
+const DS_LIGHTBAR_LIGHT_OUT: u8 = 0x02;
+
+// 讀取後藍牙改送完整 report 的 feature report (校正資料，兩種型號相同)
+pub const BT_FULL_REPORT_FEATURE: u8 = 0x05;
+pub const BT_FULL_REPORT_FEATURE_LEN: usize = 41;
+
+// DualSense 藍牙 output 的序號 (0..15)，每送一筆加一
+static OUTPUT_SEQ: AtomicU8 = AtomicU8::new(0);
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SonyModel {
+    DualShock4,
+    DualSense,
+}
+
+impl SonyModel {
+    pub fn detect(identity: &DeviceIdentity) -> Option<Self> {
+        if identity.vendor_id != VENDOR_SONY { return None; }
+        if PRODUCTS_DS4.contains(&identity.product_id) { return Some(SonyModel::DualShock4); }
+        if PRODUCTS_DUALSENSE.contains(&identity.product_id) { return Some(SonyModel::DualSense); }
+        None
+    }
+
+    fn bt_report_id(self) -> u8 {
+        match self {
+            SonyModel::DualShock4 => DS4_BT_ID,
+            SonyModel::DualSense => DS_BT_ID,
+        }
+    }
+
+    // 完整 input report 中資料的起點
+    fn bt_data_offset(self) -> usize {
+        match self {
+            SonyModel::DualShock4 => 3,
+            SonyModel::DualSense => 2,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Connection {
+    Usb,
+    Bluetooth,
+}
+
+impl Connection {
+    // descriptor 中有藍牙的 output report 即為藍牙連線；取不到 descriptor 時視為 USB
+    pub fn detect(model: SonyModel, sizes: &ReportSizes) -> Self {
+        if sizes.output.iter().any(|s| s.report_id == model.bt_report_id()) { Connection::Bluetooth } else { Connection::Usb }
+    }
+}
+
+// 0..255，中心約為 128 (y 軸向下為正)
+#[derive(Serialize, Clone, Copy, PartialEq)]
+pub struct Stick {
+    pub x: u8,
+    pub y: u8,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Default)]
+pub struct SonyButtons {
+    pub cross: bool,
+    pub circle: bool,
+    pub square: bool,
+    pub triangle: bool,
+    pub l1: bool,
+    pub r1: bool,
+    pub l2: bool,
+    pub r2: bool,
+    pub l3: bool,
+    pub r3: bool,
+    // DualSense 上為 Create 鍵
+    pub share: bool,
+    pub options: bool,
+    pub ps: bool,
+    pub touchpad: bool,
+    // 只有 DualSense
+    pub mute: bool,
+}
+
+// 觸控板上的手指；座標原點在左上角 (DualShock 4 為 1920x942，DualSense 為 1920x1080)
+#[derive(Serialize, Clone, Copy, PartialEq)]
+pub struct TouchPoint {
+    pub id: u8,
+    pub x: u16,
+    pub y: u16,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+pub struct SonyBattery {
+    pub percent: u8,
+    pub charging: bool,
+}
+
+#[derive(Serialize, Clone, PartialEq)]
+pub struct SonyState {
+    pub model: SonyModel,
+    pub connection: Connection,
+    pub left_stick: Stick,
+    pub right_stick: Stick,
+    // 類比扳機 0..255
+    pub l2: u8,
+    pub r2: u8,
+    pub dpad: Option<HatDirection>,
+    pub buttons: SonyButtons,
+    // 原始讀值 (未套用校正)，順序為 x, y, z
+    pub gyro: [i16; 3],
+    pub accel: [i16; 3],
+    pub touches: Vec<TouchPoint>,
+    pub battery: SonyBattery,
+}
+
+// 各欄位在資料中的位置 (不含 Report ID 與藍牙的前置位元組)
+struct Layout {
+    triggers: usize,
+    buttons: usize,
+    gyro: usize,
+    accel: usize,
+    touch: usize,
+    status: usize,
+}
+
+const DS4_LAYOUT: Layout = Layout { triggers: 7, buttons: 4, gyro: 12, accel: 18, touch: 34, status: 29 };
+const DS_LAYOUT: Layout = Layout { triggers: 4, buttons: 7, gyro: 15, accel: 21, touch: 32, status: 52 };
+
+fn bt_crc(seed: u8, data: &[u8]) -> u32 {
+    crc32_update(crc32_update(0, &[seed]), data)
+}
+
+fn i16_at(data: &[u8], i: usize) -> i16 {
+    i16::from_le_bytes([data[i], data[i + 1]])
+}
+
+fn touch_point(p: &[u8]) -> Option<TouchPoint> {
+    // bit 7 為 1 代表沒有觸碰
+    if p[0] & 0x80 != 0 { return None; }
+    Some(TouchPoint {
+        id: p[0] & 0x7F,
+        x: p[1] as u16 | ((p[2] & 0x0F) as u16) << 8,
+        y: (p[2] >> 4) as u16 | (p[3] as u16) << 4,
+    })
+}
+
+fn battery(model: SonyModel, status: u8) -> SonyBattery {
+    let level = status & 0x0F;
+    let percent = (level * 10 + 5).min(100);
+    match model {
+        // bit 4: 接上電源；level 11 代表已充滿
+        SonyModel::DualShock4 => {
+            let cable = status & 0x10 != 0;
+            if cable && level > 10 { SonyBattery { percent: 100, charging: false } } else { SonyBattery { percent, charging: cable } }
+        }
+        // 高 4 bits: 0 放電中、1 充電中、2 已充滿
+        SonyModel::DualSense => match status >> 4 {
+            1 => SonyBattery { percent, charging: true },
+            2 => SonyBattery { percent: 100, charging: false },
+            _ => SonyBattery { percent, charging: false },
+        },
+    }
+}
+
+pub struct SonyDecoder {
+    model: SonyModel,
+}
+
+impl SonyDecoder {
+    pub fn new(identity: &DeviceIdentity) -> Option<Self> {
+        SonyModel::detect(identity).map(|model| Self { model })
+    }
+
+    // 取出完整 report 的資料；精簡 report、其他 Report ID 與 CRC 錯誤的藍牙 report 回傳 None
+    fn payload<'a>(&self, report: &'a [u8]) -> Option<(Connection, &'a [u8])> {
+        let id = *report.first()?;
+        if id == USB_INPUT_ID && report.len() >= USB_INPUT_LEN {
+            return Some((Connection::Usb, &report[1..]));
+        }
+        if id != self.model.bt_report_id() || report.len() < BT_REPORT_LEN { return None; }
+        let (body, crc) = report[..BT_REPORT_LEN].split_at(BT_REPORT_LEN - 4);
+        if bt_crc(BT_CRC_INPUT, body) != u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]) { return None; }
+        Some((Connection::Bluetooth, &body[self.model.bt_data_offset()..]))
+    }
+
+    pub fn feed(&self, report: &[u8]) -> Option<SonyState> {
+        let (connection, d) = self.payload(report)?;
+        let layout = match self.model {
+            SonyModel::DualShock4 => &DS4_LAYOUT,
+            SonyModel::DualSense => &DS_LAYOUT,
+        };
+        let b = &d[layout.buttons..layout.buttons + 3];
+        let bit = |i: usize, mask: u8| b[i] & mask != 0;
+        use HatDirection::*;
+        let dpad = [Up, UpRight, Right, DownRight, Down, DownLeft, Left, UpLeft].get((b[0] & 0x0F) as usize).copied();
+        let buttons = SonyButtons {
+            square: bit(0, 0x10),
+            cross: bit(0, 0x20),
+            circle: bit(0, 0x40),
+            triangle: bit(0, 0x80),
+            l1: bit(1, 0x01),
+            r1: bit(1, 0x02),
+            l2: bit(1, 0x04),
+            r2: bit(1, 0x08),
+            share: bit(1, 0x10),
+            options: bit(1, 0x20),
+            l3: bit(1, 0x40),
+            r3: bit(1, 0x80),
+            ps: bit(2, 0x01),
+            touchpad: bit(2, 0x02),
+            mute: self.model == SonyModel::DualSense && bit(2, 0x04),
+        };
+        let axes = |at: usize| [i16_at(d, at), i16_at(d, at + 2), i16_at(d, at + 4)];
+        Some(SonyState {
+            model: self.model,
+            connection,
+            left_stick: Stick { x: d[0], y: d[1] },
+            right_stick: Stick { x: d[2], y: d[3] },
+            l2: d[layout.triggers],
+            r2: d[layout.triggers + 1],
+            dpad,
+            buttons,
+            gyro: axes(layout.gyro),
+            accel: axes(layout.accel),
+            touches: [layout.touch, layout.touch + 4].iter().filter_map(|&at| touch_point(&d[at..at + 4])).collect(),
+            battery: battery(self.model, d[layout.status]),
+        })
+    }
+}
+
+// --- output report ---
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct Rumble {
+    // 左側低頻馬達
+    pub strong: u8,
+    // 右側高頻馬達
+    pub weak: u8,
+}
+
+// DualSense 自適應扳機；位置 0..255 對應扳機行程
+#[derive(Deserialize, Clone, Copy)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TriggerEffect {
+    Off,
+    // 從 start 開始持續的阻力
+    Resistance { start: u8, force: u8 },
+    // start..end 之間的阻力，過了 end 即放開 (模擬扳機擊發)
+    Section { start: u8, end: u8, force: u8 },
+    // 從 start 開始震動
+    Vibration { start: u8, amplitude: u8, frequency: u8 },
+}
+
+impl TriggerEffect {
+    fn bytes(self) -> [u8; 11] {
+        let mut out = [0u8; 11];
+        let params: &[u8] = match &self {
+            TriggerEffect::Off => &[0x05],
+            TriggerEffect::Resistance { start, force } => &[0x01, *start, *force],
+            TriggerEffect::Section { start, end, force } => &[0x02, *start, *end, *force],
+            TriggerEffect::Vibration { start, amplitude, frequency } => &[0x06, *frequency, *amplitude, *start],
+        };
+        out[..params.len()].copy_from_slice(params);
+        out
+    }
+}
+
+// 只送出有指定的項目，其餘維持設備目前的狀態
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SonyOutput {
+    pub rumble: Option<Rumble>,
+    pub lightbar: Option<Rgb>,
+    // DualSense 玩家指示燈，bit 0..4 對應 5 顆 LED
+    pub player_leds: Option<u8>,
+    pub left_trigger: Option<TriggerEffect>,
+    pub right_trigger: Option<TriggerEffect>,
+}
+
+// 藍牙 report 補到 78 bytes 並附上 CRC
+fn seal_bt(mut report: Vec<u8>) -> Vec<u8> {
+    report.resize(BT_REPORT_LEN - 4, 0);
+    let crc = bt_crc(BT_CRC_OUTPUT, &report);
+    report.extend(crc.to_le_bytes());
+    report
+}
+
+fn ds4_output(connection: Connection, output: &SonyOutput) -> Result<Vec<u8>, String> {
+    if output.player_leds.is_some() || output.left_trigger.is_some() || output.right_trigger.is_some() {
+        return Err("DualShock 4 沒有玩家指示燈與自適應扳機".into());
+    }
+    let mut common = [0u8; 10];
+    if let Some(rumble) = output.rumble {
+        common[0] |= DS4_FLAG_MOTOR;
+        common[3] = rumble.weak;
+        common[4] = rumble.strong;
+    }
+    if let Some(color) = output.lightbar {
+        common[0] |= DS4_FLAG_LIGHTBAR;
+        common[5..8].copy_from_slice(&[color.r, color.g, color.b]);
+    }
+    Ok(match connection {
+        Connection::Usb => {
+            let mut report = vec![DS4_USB_OUTPUT_ID];
+            report.extend(common);
+            report.resize(DS4_USB_OUTPUT_LEN, 0);
+            report
+        }
+        Connection::Bluetooth => {
+            let mut report = vec![DS4_BT_ID, DS4_BT_HW_CONTROL, 0x00];
+            report.extend(common);
+            seal_bt(report)
+        }
+    })
+}
+
+fn dualsense_output(connection: Connection, output: &SonyOutput) -> Vec<u8> {
+    let mut common = [0u8; DS_COMMON_LEN];
+    if let Some(rumble) = output.rumble {
+        common[0] |= DS_FLAG_VIBRATION;
+        common[2] = rumble.weak;
+        common[3] = rumble.strong;
+    }
+    if let Some(effect) = output.right_trigger {
+        common[0] |= DS_FLAG_RIGHT_TRIGGER;
+        common[10..21].copy_from_slice(&effect.bytes());
+    }
+    if let Some(effect) = output.left_trigger {
+        common[0] |= DS_FLAG_LEFT_TRIGGER;
+        common[21..32].copy_from_slice(&effect.bytes());
+    }
+    if let Some(leds) = output.player_leds {
+        common[1] |= DS_FLAG_PLAYER_LEDS;
+        common[43] = leds & 0x1F;
+    }
+    if let Some(color) = output.lightbar {
+        common[1] |= DS_FLAG_LIGHTBAR;
+        common[38] |= DS_FLAG_LIGHTBAR_SETUP;
+        common[41] = DS_LIGHTBAR_LIGHT_OUT;
+        common[44..47].copy_from_slice(&[color.r, color.g, color.b]);
+    }
+    match connection {
+        Connection::Usb => {
+            let mut report = vec![DS_USB_OUTPUT_ID];
+            report.extend(common);
+            report.resize(DS_USB_OUTPUT_LEN, 0);
+            report
+        }
+        Connection::Bluetooth => {
+            let seq = OUTPUT_SEQ.fetch_add(1, Ordering::Relaxed) & 0x0F;
+            let mut report = vec![DS_BT_ID, seq << 4, DS_BT_TAG];
+            report.extend(common);
+            seal_bt(report)
+        }
+    }
+}
+
+// 組成完整的 output report (含 Report ID)，可直接寫入設備
+pub fn build_output(model: SonyModel, connection: Connection, output: &SonyOutput) -> Result<Vec<u8>, String> {
+    match model {
+        SonyModel::DualShock4 => ds4_output(connection, output),
+        SonyModel::DualSense => Ok(dualsense_output(connection, output)),
+    }
+}
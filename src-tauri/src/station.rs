@@ -0,0 +1,134 @@
+// --- 工作站鎖定 ---
+// 共用實驗室電腦上標示目前由誰使用，避免兩位遠端使用者同時對設備進行衝突的操作。
+// GUI 以 Tauri 指令、遠端使用者經由服務管理介面 (/station，見 service.rs) 查詢、取得與釋放。
+// 鎖定需要定期續約 (帶著原本的 token 再次 acquire)；超過 ttl 沒有續約即自動失效，避免使用者斷線後卡住工作站。
+// 鎖定期間，會改寫設備或長時間佔用設備的操作 (韌體更新、指令序列、重播、壓力測試) 必須帶上持有者的 token
+// (station_token)，其他人的請求一律拒絕；沒有鎖定時不檢查。
+// owner 只是自行填寫的名稱，因此續約必須出示 token，其他人填同樣的名稱也拿不到 token。
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_TTL_SECS: u64 = 300;
+
+#[derive(Serialize, Clone)]
+pub struct StationLockInfo {
+    pub owner: String,
+    pub note: Option<String>,
+    // 取得鎖定的時間 (Unix ms)
+    pub since_ms: u64,
+    // 距離自動失效的剩餘時間
+    pub expires_in_ms: u64,
+}
+
+// 取得或續約 (服務管理介面 POST /station/acquire 的內容)
+#[derive(Deserialize)]
+pub struct AcquireRequest {
+    pub owner: String,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    // 續約時帶上原本的 token
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ReleaseRequest {
+    pub token: String,
+}
+
+struct Lock {
+    owner: String,
+    note: Option<String>,
+    token: String,
+    since_ms: u64,
+    renewed_at: Instant,
+    ttl: Duration,
+}
+
+#[derive(Default)]
+pub struct StationLock {
+    current: Option<Lock>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+// 續約與釋放只憑 token，因此取自作業系統的 CSPRNG，無法由 owner 或時間推得
+fn new_token() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| format!("無法產生鎖定 token: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+impl StationLock {
+    fn active(&mut self) -> Option<&Lock> {
+        if self.current.as_ref().is_some_and(|l| l.renewed_at.elapsed() >= l.ttl) {
+            self.current = None;
+        }
+        self.current.as_ref()
+    }
+
+    pub fn info(&mut self) -> Option<StationLockInfo> {
+        self.active().map(|l| StationLockInfo {
+            owner: l.owner.clone(),
+            note: l.note.clone(),
+            since_ms: l.since_ms,
+            expires_in_ms: l.ttl.saturating_sub(l.renewed_at.elapsed()).as_millis() as u64,
+        })
+    }
+
+    // 取得或續約；續約時 token 必須與原本的相同，沿用原本的開始時間
+    pub fn acquire(&mut self, owner: &str, note: Option<String>, ttl_secs: Option<u64>, token: Option<&str>) -> Result<String, String> {
+        let ttl = Duration::from_secs(ttl_secs.unwrap_or(DEFAULT_TTL_SECS));
+        if let Some(lock) = self.active() {
+            if lock.owner != owner {
+                return Err(format!("工作站正由 {} 使用中", lock.owner));
+            }
+            if token != Some(lock.token.as_str()) {
+                return Err("工作站已鎖定，續約需要原本的 token".into());
+            }
+        }
+        if self.current.is_none() {
+            self.current = Some(Lock {
+                owner: owner.to_string(),
+                note: None,
+                token: new_token()?,
+                since_ms: now_ms(),
+                renewed_at: Instant::now(),
+                ttl,
+            });
+        }
+        let lock = self.current.as_mut().unwrap();
+        lock.note = note;
+        lock.renewed_at = Instant::now();
+        lock.ttl = ttl;
+        Ok(lock.token.clone())
+    }
+
+    // 有人持有鎖定而 token 不符時拒絕
+    pub fn check(&mut self, token: Option<&str>) -> Result<(), String> {
+        match self.active() {
+            Some(lock) if token != Some(lock.token.as_str()) => Err(format!("工作站正由 {} 鎖定使用中", lock.owner)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn release(&mut self, token: &str) -> Result<(), String> {
+        match self.active() {
+            Some(lock) if lock.token != token => Err("鎖定 token 不符".into()),
+            _ => {
+                self.current = None;
+                Ok(())
+            }
+        }
+    }
+
+    // 強制解除 (管理者在本機操作)，回傳原本的使用者
+    pub fn force_release(&mut self) -> Option<String> {
+        self.current.take().map(|l| l.owner)
+    }
+}
@@ -0,0 +1,218 @@
+use crate::schema::LossCause;
+use serde::Serialize;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// 速率計算的視窗長度
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+// 活動狀態判斷：安靜超過平均回報間隔的倍數即視為 idle / asleep，並設有下限
+// 避免高回報率設備稍有停頓就被判定
+const IDLE_FACTOR: f64 = 5.0;
+const IDLE_MIN_SECS: f64 = 2.0;
+const ASLEEP_FACTOR: f64 = 50.0;
+const ASLEEP_MIN_SECS: f64 = 30.0;
+// 平均間隔的平滑係數
+const INTERVAL_ALPHA: f64 = 0.1;
+// 指令連續逾時達到此次數即視為設備停滯 (stalled)
+const STALL_THRESHOLD: u32 = 3;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityState {
+    Active,
+    Idle,
+    Asleep,
+}
+
+// --- 每個設備的即時統計 ---
+
+pub struct DeviceStats {
+    total_reports: u64,
+    total_bytes: u64,
+    error_count: u64,
+    lost_usb: u64,
+    lost_app: u64,
+    // send_hid_command 等不到回覆的次數
+    timeouts: u64,
+    consecutive_timeouts: u32,
+    last_activity: Option<SystemTime>,
+    last_activity_at: Option<Instant>,
+    window_start: Instant,
+    window_reports: u64,
+    window_bytes: u64,
+    reports_per_sec: f64,
+    bytes_per_sec: f64,
+    // 回報間隔的移動平均 (秒)
+    avg_interval: Option<f64>,
+    activity: ActivityState,
+    // 最近一次由 report 或電源狀態得知的電量 (%)
+    battery: Option<u8>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DeviceStatsSnapshot {
+    pub path: String,
+    pub total_reports: u64,
+    pub total_bytes: u64,
+    pub reports_per_sec: f64,
+    pub bytes_per_sec: f64,
+    pub error_count: u64,
+    // 依序號欄位偵測到的遺失 report 數 (見 schema::LossCause)
+    pub lost_usb: u64,
+    pub lost_app: u64,
+    pub timeouts: u64,
+    pub consecutive_timeouts: u32,
+    // 連續逾時達到門檻，設備可能已停止回應指令
+    pub stalled: bool,
+    // 最後一次收到資料的時間 (Unix ms)
+    pub last_activity_ms: Option<u64>,
+    // 距離最後一次收到資料經過的時間
+    pub idle_ms: Option<u64>,
+    pub activity: ActivityState,
+    pub battery_percent: Option<u8>,
+}
+
+impl Default for DeviceStats {
+    fn default() -> Self { Self::new() }
+}
+
+impl DeviceStats {
+    pub fn new() -> Self {
+        Self {
+            total_reports: 0,
+            total_bytes: 0,
+            error_count: 0,
+            lost_usb: 0,
+            lost_app: 0,
+            timeouts: 0,
+            consecutive_timeouts: 0,
+            last_activity: None,
+            last_activity_at: None,
+            window_start: Instant::now(),
+            window_reports: 0,
+            window_bytes: 0,
+            reports_per_sec: 0.0,
+            bytes_per_sec: 0.0,
+            avg_interval: None,
+            // 開啟後還沒收到資料前視為 idle
+            activity: ActivityState::Idle,
+            battery: None,
+        }
+    }
+
+    pub fn record_report(&mut self, len: usize) {
+        self.roll_window();
+        self.total_reports += 1;
+        self.total_bytes += len as u64;
+        self.window_reports += 1;
+        self.window_bytes += len as u64;
+        if let Some(last) = self.last_activity_at {
+            let interval = last.elapsed().as_secs_f64();
+            self.avg_interval = Some(match self.avg_interval {
+                Some(avg) => avg + INTERVAL_ALPHA * (interval - avg),
+                None => interval,
+            });
+        }
+        self.last_activity = Some(SystemTime::now());
+        self.last_activity_at = Some(Instant::now());
+    }
+
+    pub fn record_error(&mut self) {
+        self.error_count += 1;
+    }
+
+    pub fn record_battery(&mut self, percent: u8) {
+        self.battery = Some(percent.min(100));
+    }
+
+    // 回傳連續逾時次數；剛達到門檻時 stalled 為 true
+    pub fn record_timeout(&mut self) -> (u32, bool) {
+        self.timeouts += 1;
+        self.consecutive_timeouts += 1;
+        (self.consecutive_timeouts, self.consecutive_timeouts == STALL_THRESHOLD)
+    }
+
+    // 指令收到回覆；先前處於停滯狀態時回傳 true
+    pub fn record_reply(&mut self) -> bool {
+        let was_stalled = self.is_stalled();
+        self.consecutive_timeouts = 0;
+        was_stalled
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.consecutive_timeouts >= STALL_THRESHOLD
+    }
+
+    pub fn record_loss(&mut self, cause: LossCause, missed: u64) {
+        match cause {
+            LossCause::Usb => self.lost_usb += missed,
+            LossCause::App => self.lost_app += missed,
+        }
+    }
+
+    // 視窗結束時結算速率；若設備已安靜超過一個視窗，速率歸零
+    fn roll_window(&mut self) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed < RATE_WINDOW { return; }
+
+        if elapsed < RATE_WINDOW * 2 {
+            let secs = elapsed.as_secs_f64();
+            self.reports_per_sec = self.window_reports as f64 / secs;
+            self.bytes_per_sec = self.window_bytes as f64 / secs;
+        } else {
+            self.reports_per_sec = 0.0;
+            self.bytes_per_sec = 0.0;
+        }
+        self.window_start = Instant::now();
+        self.window_reports = 0;
+        self.window_bytes = 0;
+    }
+
+    fn classify(&self) -> ActivityState {
+        let Some(last) = self.last_activity_at else { return ActivityState::Idle };
+        let quiet = last.elapsed().as_secs_f64();
+        let avg = self.avg_interval.unwrap_or(0.0);
+        if quiet >= (avg * ASLEEP_FACTOR).max(ASLEEP_MIN_SECS) {
+            ActivityState::Asleep
+        } else if quiet >= (avg * IDLE_FACTOR).max(IDLE_MIN_SECS) {
+            ActivityState::Idle
+        } else {
+            ActivityState::Active
+        }
+    }
+
+    // 重新判斷活動狀態，有變化時回傳 (舊狀態, 新狀態)
+    pub fn update_activity(&mut self) -> Option<(ActivityState, ActivityState)> {
+        let state = self.classify();
+        if state == self.activity { return None; }
+        let previous = std::mem::replace(&mut self.activity, state);
+        Some((previous, state))
+    }
+
+    pub fn activity(&self) -> ActivityState {
+        self.activity
+    }
+
+    pub fn snapshot(&mut self, path: &str) -> DeviceStatsSnapshot {
+        self.roll_window();
+        DeviceStatsSnapshot {
+            path: path.to_string(),
+            total_reports: self.total_reports,
+            total_bytes: self.total_bytes,
+            reports_per_sec: self.reports_per_sec,
+            bytes_per_sec: self.bytes_per_sec,
+            error_count: self.error_count,
+            lost_usb: self.lost_usb,
+            lost_app: self.lost_app,
+            timeouts: self.timeouts,
+            consecutive_timeouts: self.consecutive_timeouts,
+            stalled: self.is_stalled(),
+            last_activity_ms: self.last_activity
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as u64),
+            idle_ms: self.last_activity_at.map(|t| t.elapsed().as_millis() as u64),
+            activity: self.classify(),
+            battery_percent: self.battery,
+        }
+    }
+}
@@ -0,0 +1,138 @@
+// --- 即時資料串流 (UDP) ---
+// 把解碼後的數值通道以 UDP 即時送出，供實驗 / 生理訊號擷取系統接收。
+// 未內建 LSL (需要 liblsl 原生函式庫)，接收端可再轉成 LSL outlet。
+//
+// 封包格式 (little-endian)：
+//   0   "HIDS"
+//   4   版本 (1)
+//   5   類型：1 = 通道清單, 2 = 樣本
+//   6   旗標：bit0 有 monotonic 時間, bit1 有 wall clock 時間
+//   7   保留 (0)
+//   8   序號 u32 (每個封包遞增，可用來偵測遺失)
+//   12  monotonic 時間 u64 (µs，見 get_clock_metadata)
+//   20  wall clock 時間 i64 (Unix µs)
+//   28  內容
+// 通道清單：UTF-8 JSON {"path": "...", "channels": ["X", "Y", ...]}，通道改變時與每秒送出一次
+// 樣本：通道數 u16 + 每個通道一個 f64 (缺值為 NaN)，順序與最近一次的通道清單相同
+
+use crate::clock::Timestamp;
+use serde::Serialize;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+const MAGIC: &[u8; 4] = b"HIDS";
+const VERSION: u8 = 1;
+const TYPE_CHANNELS: u8 = 1;
+const TYPE_SAMPLE: u8 = 2;
+const FLAG_MONO: u8 = 0x01;
+const FLAG_WALL: u8 = 0x02;
+
+const CHANNEL_LIST_INTERVAL: Duration = Duration::from_secs(1);
+// 單一 UDP 封包可放的通道數上限 (避免 IP 分片)
+pub const MAX_CHANNELS: usize = 128;
+
+#[derive(Serialize, Clone)]
+pub struct StreamInfo {
+    pub target: String,
+    pub channels: Vec<String>,
+    pub packets: u64,
+    // 最近一次送出失敗的原因
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ChannelList<'a> {
+    path: &'a str,
+    channels: &'a [String],
+}
+
+pub struct UdpStream {
+    socket: UdpSocket,
+    target: SocketAddr,
+    // 使用者指定的通道 (未指定時送出所有數值欄位)
+    fixed: Option<Vec<String>>,
+    channels: Vec<String>,
+    seq: u32,
+    packets: u64,
+    last_list: Option<Instant>,
+    error: Option<String>,
+}
+
+impl UdpStream {
+    pub fn open(target: &str, channels: Option<Vec<String>>) -> Result<Self, String> {
+        let addr = target.to_socket_addrs()
+            .map_err(|e| format!("無法解析位址 {}: {}", target, e))?
+            .next()
+            .ok_or(format!("無法解析位址 {}", target))?;
+        if channels.as_ref().is_some_and(|c| c.is_empty() || c.len() > MAX_CHANNELS) {
+            return Err(format!("通道數需為 1..={}", MAX_CHANNELS));
+        }
+        let bind = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = UdpSocket::bind(bind).map_err(|e| format!("建立 UDP socket 失敗: {}", e))?;
+        Ok(Self {
+            socket,
+            target: addr,
+            channels: channels.clone().unwrap_or_default(),
+            fixed: channels,
+            seq: 0,
+            packets: 0,
+            last_list: None,
+            error: None,
+        })
+    }
+
+    pub fn info(&self) -> StreamInfo {
+        StreamInfo {
+            target: self.target.to_string(),
+            channels: self.channels.clone(),
+            packets: self.packets,
+            error: self.error.clone(),
+        }
+    }
+
+    fn packet(&mut self, kind: u8, timestamp: Timestamp, body: &[u8]) -> Vec<u8> {
+        let flags = if timestamp.mono_us.is_some() { FLAG_MONO } else { 0 }
+            | if timestamp.wall_us.is_some() { FLAG_WALL } else { 0 };
+        let mut out = MAGIC.to_vec();
+        out.extend([VERSION, kind, flags, 0]);
+        out.extend(self.seq.to_le_bytes());
+        out.extend(timestamp.mono_us.unwrap_or(0).to_le_bytes());
+        out.extend(timestamp.wall_us.unwrap_or(0).to_le_bytes());
+        out.extend_from_slice(body);
+        self.seq = self.seq.wrapping_add(1);
+        out
+    }
+
+    fn transmit(&mut self, packet: &[u8]) {
+        match self.socket.send_to(packet, self.target) {
+            Ok(_) => self.packets += 1,
+            Err(e) => self.error = Some(format!("送出失敗: {}", e)),
+        }
+    }
+
+    // 送出一筆樣本；沒有任何數值時不送
+    pub fn send(&mut self, path: &str, values: &[(String, f64)], timestamp: Timestamp) {
+        if values.is_empty() { return; }
+        if self.fixed.is_none() {
+            let names: Vec<String> = values.iter().take(MAX_CHANNELS).map(|(n, _)| n.clone()).collect();
+            if names != self.channels {
+                self.channels = names;
+                self.last_list = None;
+            }
+        }
+        if self.last_list.is_none_or(|t| t.elapsed() >= CHANNEL_LIST_INTERVAL) {
+            let list = serde_json::to_vec(&ChannelList { path, channels: &self.channels }).unwrap_or_default();
+            let packet = self.packet(TYPE_CHANNELS, timestamp, &list);
+            self.transmit(&packet);
+            self.last_list = Some(Instant::now());
+        }
+
+        let mut body = (self.channels.len() as u16).to_le_bytes().to_vec();
+        for name in &self.channels {
+            let value = values.iter().find(|(n, _)| n == name).map_or(f64::NAN, |(_, v)| *v);
+            body.extend(value.to_le_bytes());
+        }
+        let packet = self.packet(TYPE_SAMPLE, timestamp, &body);
+        self.transmit(&packet);
+    }
+}
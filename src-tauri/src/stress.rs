@@ -0,0 +1,112 @@
+// --- 壓力測試 ---
+// 在後端以固定間隔重複送出同一個指令並檢查回覆，統計成功、逾時、內容不符與錯誤的次數及來回時間。
+// 來回時間在設備的 I/O 執行緒中量測 (寫入到收到回覆)，不含前端迴圈的 IPC 延遲。
+
+use serde::Serialize;
+
+// 結果中保留的失敗明細上限
+const MAX_FAILURES: usize = 20;
+
+pub enum StressOutcome {
+    // 來回時間 (µs)
+    Success(u64),
+    Timeout,
+    // 有回覆但不符合 expect
+    Mismatch(u64, Vec<u8>),
+    Error(String),
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureKind {
+    Timeout,
+    Mismatch,
+    Error,
+}
+
+#[derive(Serialize, Clone)]
+pub struct StressFailure {
+    // 從 1 開始
+    pub iteration: u32,
+    pub kind: FailureKind,
+    pub response: Option<Vec<u8>>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Default)]
+pub struct StressResult {
+    pub iterations: u32,
+    pub completed: u32,
+    pub successes: u32,
+    pub timeouts: u32,
+    pub mismatches: u32,
+    pub errors: u32,
+    // 有回覆的次數 (成功與內容不符) 的來回時間
+    pub min_rtt_us: Option<u64>,
+    pub avg_rtt_us: Option<u64>,
+    pub max_rtt_us: Option<u64>,
+    // 開始時間 (Unix ms)
+    pub started_ms: i64,
+    pub elapsed_ms: u64,
+    pub cancelled: bool,
+    // 最早的幾筆失敗
+    pub failures: Vec<StressFailure>,
+}
+
+pub struct StressStats {
+    result: StressResult,
+    rtt_total_us: u64,
+    rtt_count: u64,
+}
+
+impl StressStats {
+    pub fn new(iterations: u32) -> Self {
+        let started_ms = chrono::Local::now().timestamp_millis();
+        Self { result: StressResult { iterations, started_ms, ..StressResult::default() }, rtt_total_us: 0, rtt_count: 0 }
+    }
+
+    fn record_rtt(&mut self, us: u64) {
+        let r = &mut self.result;
+        r.min_rtt_us = Some(r.min_rtt_us.map_or(us, |m| m.min(us)));
+        r.max_rtt_us = Some(r.max_rtt_us.map_or(us, |m| m.max(us)));
+        self.rtt_total_us += us;
+        self.rtt_count += 1;
+    }
+
+    fn record_failure(&mut self, kind: FailureKind, response: Option<Vec<u8>>, error: Option<String>) {
+        if self.result.failures.len() < MAX_FAILURES {
+            let iteration = self.result.completed;
+            self.result.failures.push(StressFailure { iteration, kind, response, error });
+        }
+    }
+
+    pub fn record(&mut self, outcome: StressOutcome) {
+        self.result.completed += 1;
+        match outcome {
+            StressOutcome::Success(us) => {
+                self.result.successes += 1;
+                self.record_rtt(us);
+            }
+            StressOutcome::Timeout => {
+                self.result.timeouts += 1;
+                self.record_failure(FailureKind::Timeout, None, None);
+            }
+            StressOutcome::Mismatch(us, response) => {
+                self.result.mismatches += 1;
+                self.record_rtt(us);
+                self.record_failure(FailureKind::Mismatch, Some(response), None);
+            }
+            StressOutcome::Error(e) => {
+                self.result.errors += 1;
+                self.record_failure(FailureKind::Error, None, Some(e));
+            }
+        }
+    }
+
+    pub fn finish(mut self, elapsed_ms: u64, cancelled: bool) -> StressResult {
+        self.result.avg_rtt_us = (self.rtt_count > 0).then(|| self.rtt_total_us / self.rtt_count);
+        self.result.elapsed_ms = elapsed_ms;
+        self.result.cancelled = cancelled;
+        self.result
+    }
+}
@@ -0,0 +1,97 @@
+// --- 無障礙狀態摘要 ---
+// 把高頻的統計與狀態轉成每隔幾秒一句的英文描述 (device-summary 事件)，讓使用螢幕閱讀器的使用者
+// 不必逐筆聆聽資料也能掌握設備狀況，例: "Scanner A: 120 reports/s, battery 80%"
+// 速率取 2 位有效數字、閒置不報秒數，讓句子只在狀態真的改變時才不同；預設只送出改變的句子。
+
+use crate::device::DeviceFault;
+use crate::stats::{ActivityState, DeviceStatsSnapshot};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// 摘要間隔下限，太頻繁時螢幕閱讀器來不及唸完
+pub const MIN_INTERVAL_MS: u64 = 1000;
+
+#[derive(Serialize, Clone)]
+pub struct SummaryEvent {
+    pub path: String,
+    pub text: String,
+}
+
+// 2 位有效數字 (118 -> 120, 1234 -> 1200)，10 以下保留 1 位小數
+fn round_rate(rate: f64) -> String {
+    if rate < 10.0 {
+        let r = (rate * 10.0).round() / 10.0;
+        return if r.fract() == 0.0 { format!("{}", r as u64) } else { format!("{:.1}", r) };
+    }
+    let scale = 10f64.powi(rate.log10().floor() as i32 - 1);
+    format!("{}", ((rate / scale).round() * scale) as u64)
+}
+
+pub fn describe(name: &str, stats: &DeviceStatsSnapshot, paused: bool, fault: Option<&DeviceFault>) -> String {
+    let mut parts = Vec::new();
+    if let Some(fault) = fault {
+        parts.push(format!("error after {} failed writes, needs to be cleared", fault.failures));
+    }
+    if paused {
+        parts.push("paused".to_string());
+    } else {
+        parts.push(match stats.activity {
+            ActivityState::Active => format!("{} reports/s", round_rate(stats.reports_per_sec)),
+            ActivityState::Idle if stats.last_activity_ms.is_none() => "no data yet".to_string(),
+            ActivityState::Idle => "idle".to_string(),
+            ActivityState::Asleep => "asleep".to_string(),
+        });
+    }
+    if stats.stalled {
+        parts.push("not responding to commands".to_string());
+    }
+    let lost = stats.lost_usb + stats.lost_app;
+    if lost > 0 {
+        parts.push(format!("{} reports lost", lost));
+    }
+    if let Some(percent) = stats.battery_percent {
+        parts.push(format!("battery {}%", percent));
+    }
+    format!("{}: {}", name, parts.join(", "))
+}
+
+pub struct Summarizer {
+    // 0 代表關閉
+    pub interval_ms: u64,
+    // 只送出與上一次不同的句子
+    pub changes_only: bool,
+    last: HashMap<String, String>,
+}
+
+impl Default for Summarizer {
+    fn default() -> Self { Self::new() }
+}
+
+impl Summarizer {
+    pub fn new() -> Self {
+        Self { interval_ms: 0, changes_only: true, last: HashMap::new() }
+    }
+
+    pub fn configure(&mut self, interval_ms: u64, changes_only: bool) -> Result<(), String> {
+        if interval_ms != 0 && interval_ms < MIN_INTERVAL_MS {
+            return Err(format!("interval_ms 不能小於 {}", MIN_INTERVAL_MS));
+        }
+        self.interval_ms = interval_ms;
+        self.changes_only = changes_only;
+        // 重新設定後每個設備都先完整唸一次
+        self.last.clear();
+        Ok(())
+    }
+
+    // 回傳需要送出的事件；已關閉的設備從記錄中移除
+    pub fn update(&mut self, current: Vec<SummaryEvent>) -> Vec<SummaryEvent> {
+        self.last.retain(|path, _| current.iter().any(|e| &e.path == path));
+        current.into_iter()
+            .filter(|e| {
+                let changed = self.last.get(&e.path) != Some(&e.text);
+                self.last.insert(e.path.clone(), e.text.clone());
+                changed || !self.changes_only
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,179 @@
+// --- 匿名使用統計 (telemetry) ---
+// 預設關閉，使用者在設定中明確開啟並指定上傳網址後才會記錄與送出。
+// 送出的內容只有：程式版本、作業系統、各功能的使用次數、結束的工作階段數與其中沒有當機的數量；
+// 不含設備資訊、路徑、report 內容或任何使用者輸入。上傳只支援未加密的 http，因此不帶任何可
+// 識別這台電腦的 ID (每份統計彼此無法關聯)。
+// preview 回傳的即為下一次實際送出的 JSON，每天最多送出一次，成功後計數歸零。
+// 關閉時立即清除已累積的計數。
+//
+// 工作階段：啟動時標記為進行中，正常結束時清除；下次啟動時標記仍在代表上次當機。
+
+use crate::webhook::WebhookTarget;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// 兩次送出的最短間隔
+const SEND_INTERVAL_MS: u64 = 24 * 60 * 60 * 1000;
+const SEND_TIMEOUT_MS: u64 = 5000;
+
+// 存於 app config 目錄的 telemetry.json
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+struct Stored {
+    enabled: bool,
+    endpoint: Option<String>,
+    features: BTreeMap<String, u64>,
+    sessions: u64,
+    crashed_sessions: u64,
+    session_open: bool,
+    last_sent_ms: Option<u64>,
+}
+
+// 實際送出的內容
+#[derive(Serialize, Clone)]
+pub struct TelemetryReport {
+    pub app_version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    // 上次送出後結束的工作階段
+    pub sessions: u64,
+    pub crash_free_sessions: u64,
+    // 功能名稱 -> 使用次數
+    pub features: BTreeMap<String, u64>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct TelemetryStatus {
+    pub enabled: bool,
+    pub endpoint: Option<String>,
+    pub last_sent_ms: Option<u64>,
+    // 最近一次送出失敗的原因
+    pub last_error: Option<String>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+pub struct Telemetry {
+    stored: Stored,
+    // 設定檔位置 (app config 目錄取不到時為 None，只保存在記憶體)
+    file: Option<PathBuf>,
+    last_error: Option<String>,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self { Self::new() }
+}
+
+impl Telemetry {
+    pub fn new() -> Self {
+        Self { stored: Stored::default(), file: None, last_error: None }
+    }
+
+    // 載入設定並開始新的工作階段；上次沒有正常結束時記為當機
+    pub fn load(&mut self, file: &Path) -> Result<(), String> {
+        self.file = Some(file.to_path_buf());
+        if file.exists() {
+            let text = std::fs::read_to_string(file).map_err(|e| format!("讀取 {} 失敗: {}", file.display(), e))?;
+            self.stored = serde_json::from_str(&text).map_err(|e| format!("使用統計設定格式錯誤: {}", e))?;
+        }
+        if !self.stored.enabled { return Ok(()); }
+        if self.stored.session_open {
+            self.stored.sessions += 1;
+            self.stored.crashed_sessions += 1;
+        }
+        self.stored.session_open = true;
+        self.save()
+    }
+
+    // 程式正常結束時呼叫
+    pub fn end_session(&mut self) -> Result<(), String> {
+        if !self.stored.enabled || !self.stored.session_open { return Ok(()); }
+        self.stored.sessions += 1;
+        self.stored.session_open = false;
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(file) = &self.file else { return Ok(()) };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("建立目錄失敗: {}", e))?;
+        }
+        let text = serde_json::to_string_pretty(&self.stored).map_err(|e| e.to_string())?;
+        std::fs::write(file, text).map_err(|e| format!("寫入 {} 失敗: {}", file.display(), e))
+    }
+
+    pub fn status(&self) -> TelemetryStatus {
+        TelemetryStatus {
+            enabled: self.stored.enabled,
+            endpoint: self.stored.endpoint.clone(),
+            last_sent_ms: self.stored.last_sent_ms,
+            last_error: self.last_error.clone(),
+        }
+    }
+
+    // 開啟時從這次工作階段開始計算；關閉時清除所有累積的資料
+    pub fn configure(&mut self, enabled: bool, endpoint: Option<String>) -> Result<(), String> {
+        let endpoint = endpoint.filter(|e| !e.is_empty());
+        if let Some(url) = &endpoint {
+            WebhookTarget::from_url(url, Duration::from_millis(SEND_TIMEOUT_MS))?;
+        }
+        if enabled && !self.stored.enabled {
+            self.stored = Stored { enabled, session_open: true, ..Stored::default() };
+        } else if !enabled {
+            self.stored = Stored::default();
+        }
+        self.stored.endpoint = endpoint;
+        self.last_error = None;
+        self.save()
+    }
+
+    pub fn record(&mut self, feature: &str) {
+        if !self.stored.enabled { return; }
+        *self.stored.features.entry(feature.to_string()).or_default() += 1;
+    }
+
+    pub fn report(&self) -> TelemetryReport {
+        TelemetryReport {
+            app_version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            sessions: self.stored.sessions,
+            crash_free_sessions: self.stored.sessions.saturating_sub(self.stored.crashed_sessions),
+            features: self.stored.features.clone(),
+        }
+    }
+
+    // 到了送出時間時回傳目標與內容，讓呼叫端在不持有鎖的情況下送出
+    pub fn due(&self) -> Option<(WebhookTarget, TelemetryReport)> {
+        if !self.stored.enabled || (self.stored.sessions == 0 && self.stored.features.is_empty()) { return None; }
+        let endpoint = self.stored.endpoint.as_deref()?;
+        if self.stored.last_sent_ms.is_some_and(|t| now_ms().saturating_sub(t) < SEND_INTERVAL_MS) { return None; }
+        let target = WebhookTarget::from_url(endpoint, Duration::from_millis(SEND_TIMEOUT_MS)).ok()?;
+        Some((target, self.report()))
+    }
+
+    // 送出成功後扣除已送出的計數 (送出期間新增的保留到下一次)
+    pub fn record_sent(&mut self, sent: &TelemetryReport, result: Result<(), String>) {
+        if !self.stored.enabled { return; }
+        if let Err(e) = result {
+            self.last_error = Some(e);
+            return;
+        }
+        for (name, count) in &sent.features {
+            if let Some(current) = self.stored.features.get_mut(name) {
+                *current -= (*count).min(*current);
+            }
+        }
+        self.stored.features.retain(|_, count| *count > 0);
+        let crashed = sent.sessions - sent.crash_free_sessions;
+        self.stored.sessions -= sent.sessions.min(self.stored.sessions);
+        self.stored.crashed_sessions -= crashed.min(self.stored.crashed_sessions);
+        self.stored.last_sent_ms = Some(now_ms());
+        self.last_error = None;
+        let _ = self.save();
+    }
+}
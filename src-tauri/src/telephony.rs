@@ -0,0 +1,97 @@
+// --- Telephony usage page (0x0B) 解析與 LED 輸出 ---
+
+use crate::descriptor::{set_bits, ReportDescriptor, ReportKind, UsageLocation};
+use serde::{Deserialize, Serialize};
+
+const PAGE_LED: u16 = 0x08;
+pub const PAGE_TELEPHONY: u16 = 0x0B;
+
+const USAGE_HOOK_SWITCH: u16 = 0x20;
+const USAGE_FLASH: u16 = 0x21;
+const USAGE_PHONE_MUTE: u16 = 0x2F;
+
+const LED_MUTE: u16 = 0x09;
+const LED_OFF_HOOK: u16 = 0x17;
+const LED_RING: u16 = 0x18;
+const LED_HOLD: u16 = 0x20;
+const LED_MICROPHONE: u16 = 0x21;
+
+#[derive(Serialize, Clone, Default, PartialEq)]
+pub struct TelephonyState {
+    pub off_hook: Option<bool>,
+    pub mute: Option<bool>,
+    pub flash: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct TelephonyLeds {
+    pub off_hook: bool,
+    pub mute: bool,
+    pub ring: bool,
+    pub hold: bool,
+    pub microphone: bool,
+}
+
+pub struct TelephonyLayout {
+    desc: ReportDescriptor,
+    hook: Option<UsageLocation>,
+    flash: Option<UsageLocation>,
+    mute: Option<UsageLocation>,
+}
+
+impl TelephonyLayout {
+    // 沒有任何 telephony input usage 的設備回傳 None
+    pub fn from_descriptor(desc: &ReportDescriptor) -> Option<Self> {
+        let find = |usage| desc.find_usage(ReportKind::Input, PAGE_TELEPHONY, usage);
+        let layout = Self {
+            desc: desc.clone(),
+            hook: find(USAGE_HOOK_SWITCH),
+            flash: find(USAGE_FLASH),
+            mute: find(USAGE_PHONE_MUTE),
+        };
+        if layout.hook.is_none() && layout.flash.is_none() && layout.mute.is_none() { return None; }
+        Some(layout)
+    }
+
+    pub fn decode(&self, report: &[u8]) -> Option<TelephonyState> {
+        let read = |loc: &Option<UsageLocation>| {
+            loc.as_ref().and_then(|l| self.desc.usage_value(l, report)).map(|v| v != 0)
+        };
+        let state = TelephonyState {
+            off_hook: read(&self.hook),
+            mute: read(&self.mute),
+            flash: read(&self.flash),
+        };
+        if state == TelephonyState::default() { None } else { Some(state) }
+    }
+
+    // 組出 LED output report (含 Report ID)
+    pub fn build_led_report(&self, leds: &TelephonyLeds) -> Result<Vec<u8>, String> {
+        let wanted = [
+            (LED_OFF_HOOK, leds.off_hook),
+            (LED_MUTE, leds.mute),
+            (LED_RING, leds.ring),
+            (LED_HOLD, leds.hold),
+            (LED_MICROPHONE, leds.microphone),
+        ];
+
+        let mut report: Option<(u8, Vec<u8>)> = None;
+        for (usage, on) in wanted {
+            let Some(UsageLocation::Variable { report_id, bit_offset, bit_size }) =
+                self.desc.find_usage(ReportKind::Output, PAGE_LED, usage) else { continue };
+
+            let (id, body) = report.get_or_insert_with(|| {
+                (report_id, vec![0u8; self.desc.report_len(ReportKind::Output, report_id)])
+            });
+            // 不同 Report ID 的 LED 無法放在同一個 report
+            if *id != report_id { continue; }
+            set_bits(body, bit_offset, bit_size, on as u32);
+        }
+
+        let (report_id, body) = report.ok_or("此設備沒有 telephony LED 輸出")?;
+        let mut out = vec![report_id];
+        out.extend(body);
+        Ok(out)
+    }
+}
@@ -0,0 +1,304 @@
+// --- payload 樣板 ---
+// 指令與序列的 data 除了位元組陣列之外，也可以是在送出時才展開的樣板字串：
+//   "AA 01 {{counter:u16le}} {{timestamp:u32be}} {{random:4}} {{session}}"
+// 十六進位位元組以空白分隔 (或兩兩相連)，{{...}} 為變數：
+//   counter:ENC          每個設備各自的計數器，每送出一次加 1 (從 0 開始，超過寬度時回到 0)
+//   counter.NAME:ENC     另一個獨立的計數器
+//   timestamp:ENC        Unix 時間 (秒)；timestamp_ms 為毫秒
+//   random:N             N 個隨機位元組 (作業系統的 CSPRNG)
+//   NAME / NAME:ENC      使用者變數 (set_template_variable)；位元組變數原樣插入，數值變數依 ENC 編碼
+// ENC 為 u8、u16le、u16be、u32le、u32be、u64le、u64be。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// 隨機位元組的上限
+const MAX_RANDOM: usize = 1024;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct IntEncoding {
+    width: usize,
+    big_endian: bool,
+}
+
+impl IntEncoding {
+    fn parse(s: &str) -> Result<Self, String> {
+        let (width, big_endian) = match s {
+            "u8" => (1, false),
+            "u16le" => (2, false),
+            "u16be" => (2, true),
+            "u32le" => (4, false),
+            "u32be" => (4, true),
+            "u64le" => (8, false),
+            "u64be" => (8, true),
+            _ => return Err(format!("無效的編碼: {} (可用 u8、u16le、u16be、u32le、u32be、u64le、u64be)", s)),
+        };
+        Ok(Self { width, big_endian })
+    }
+
+    // 超過寬度的高位元捨去
+    fn encode(self, value: u64, out: &mut Vec<u8>) {
+        let bytes = value.to_le_bytes();
+        let bytes = &bytes[..self.width];
+        if self.big_endian { out.extend(bytes.iter().rev()); } else { out.extend_from_slice(bytes); }
+    }
+
+    fn max(self) -> u64 {
+        if self.width == 8 { u64::MAX } else { (1u64 << (self.width * 8)) - 1 }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Bytes(Vec<u8>),
+    Counter { name: String, encoding: IntEncoding },
+    Timestamp { millis: bool, encoding: IntEncoding },
+    Random(usize),
+    Variable { name: String, encoding: Option<IntEncoding> },
+}
+
+impl Part {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim();
+        let (name, arg) = match spec.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(arg.trim())),
+            None => (spec, None),
+        };
+        let encoding = |arg: Option<&str>| IntEncoding::parse(arg.ok_or(format!("{{{{{}}}}} 需要指定編碼", spec))?);
+        match name {
+            "" => Err("樣板變數名稱是空的".into()),
+            "counter" => Ok(Part::Counter { name: String::new(), encoding: encoding(arg)? }),
+            n if n.starts_with("counter.") => Ok(Part::Counter { name: n["counter.".len()..].to_string(), encoding: encoding(arg)? }),
+            "timestamp" | "timestamp_ms" => Ok(Part::Timestamp { millis: name == "timestamp_ms", encoding: encoding(arg)? }),
+            "random" => {
+                let n: usize = arg.ok_or("random 需要指定位元組數")?.parse().map_err(|_| format!("無效的位元組數: {}", spec))?;
+                if n == 0 || n > MAX_RANDOM { return Err(format!("random 的位元組數需在 1-{} 之間", MAX_RANDOM)); }
+                Ok(Part::Random(n))
+            }
+            n if n.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') => {
+                Ok(Part::Variable { name: n.to_string(), encoding: arg.map(IntEncoding::parse).transpose()? })
+            }
+            _ => Err(format!("無效的樣板變數: {{{{{}}}}}", spec)),
+        }
+    }
+}
+
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    for token in text.split_whitespace() {
+        let token = token.trim_start_matches("0x");
+        if !token.len().is_multiple_of(2) || !token.is_ascii() { return Err(format!("樣板中無效的位元組: {}", token)); }
+        for i in (0..token.len()).step_by(2) {
+            out.push(u8::from_str_radix(&token[i..i + 2], 16).map_err(|_| format!("樣板中無效的位元組: {}", token))?);
+        }
+    }
+    Ok(out)
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub struct PayloadTemplate {
+    text: String,
+    parts: Vec<Part>,
+}
+
+impl TryFrom<String> for PayloadTemplate {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find("{{") {
+            parts.push(Part::Bytes(parse_hex(&rest[..start])?));
+            let end = rest[start..].find("}}").ok_or("樣板中的 {{ 沒有對應的 }}")? + start;
+            parts.push(Part::parse(&rest[start + 2..end])?);
+            rest = &rest[end + 2..];
+        }
+        parts.push(Part::Bytes(parse_hex(rest)?));
+        parts.retain(|p| !matches!(p, Part::Bytes(b) if b.is_empty()));
+        if parts.is_empty() { return Err("樣板是空的".into()); }
+        Ok(Self { text, parts })
+    }
+}
+
+impl From<PayloadTemplate> for String {
+    fn from(template: PayloadTemplate) -> Self {
+        template.text
+    }
+}
+
+// 位元組陣列 (原本的格式) 或樣板字串
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Payload {
+    Bytes(Vec<u8>),
+    Template(PayloadTemplate),
+}
+
+impl Payload {
+    // 用於記錄與測試報告
+    pub fn describe(&self) -> String {
+        match self {
+            Payload::Bytes(data) => data.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" "),
+            Payload::Template(t) => t.text.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum TemplateValue {
+    Number(u64),
+    Bytes(Vec<u8>),
+}
+
+#[derive(Serialize, Clone)]
+pub struct CounterValue {
+    pub path: String,
+    // 預設計數器為空字串
+    pub name: String,
+    // 下一次送出的值
+    pub next: u64,
+}
+
+#[derive(Default, Clone)]
+pub struct TemplateState {
+    variables: HashMap<String, TemplateValue>,
+    // (設備路徑, 計數器名稱) -> 下一次的值
+    counters: HashMap<(String, String), u64>,
+}
+
+// 取自作業系統的 CSPRNG，可用於 nonce / challenge
+fn random_bytes(n: usize, out: &mut Vec<u8>) -> Result<(), String> {
+    let start = out.len();
+    out.resize(start + n, 0);
+    getrandom::fill(&mut out[start..]).map_err(|e| format!("無法取得隨機位元組: {}", e))
+}
+
+impl TemplateState {
+    pub fn set_variable(&mut self, name: &str, value: Option<TemplateValue>) -> Result<(), String> {
+        if matches!(Part::parse(name), Ok(Part::Variable { encoding: None, .. })) {
+            match value {
+                Some(value) => { self.variables.insert(name.to_string(), value); }
+                None => { self.variables.remove(name); }
+            }
+            return Ok(());
+        }
+        Err(format!("無效的變數名稱: {}", name))
+    }
+
+    pub fn variables(&self) -> HashMap<String, TemplateValue> {
+        self.variables.clone()
+    }
+
+    pub fn counters(&self) -> Vec<CounterValue> {
+        let mut out: Vec<CounterValue> = self.counters.iter()
+            .map(|((path, name), next)| CounterValue { path: path.clone(), name: name.clone(), next: *next })
+            .collect();
+        out.sort_by(|a, b| (&a.path, &a.name).cmp(&(&b.path, &b.name)));
+        out
+    }
+
+    // path 為 None 時重設所有設備
+    pub fn reset_counters(&mut self, path: Option<&str>) {
+        self.counters.retain(|(p, _), _| path.is_some_and(|path| p != path));
+    }
+
+    // 展開並遞增用到的計數器 (同一個計數器在一個 payload 中出現多次時使用相同的值)
+    pub fn expand(&mut self, path: &str, payload: &Payload) -> Result<Vec<u8>, String> {
+        let template = match payload {
+            Payload::Bytes(data) => return Ok(data.clone()),
+            Payload::Template(t) => t,
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut out = Vec::new();
+        let mut used: Vec<(String, u64)> = Vec::new();
+        for part in &template.parts {
+            match part {
+                Part::Bytes(bytes) => out.extend_from_slice(bytes),
+                Part::Counter { name, encoding } => {
+                    let value = self.counters.get(&(path.to_string(), name.clone())).copied().unwrap_or(0);
+                    encoding.encode(value, &mut out);
+                    let next = if value >= encoding.max() { 0 } else { value + 1 };
+                    if !used.iter().any(|(n, _)| n == name) { used.push((name.clone(), next)); }
+                }
+                Part::Timestamp { millis, encoding } => {
+                    let value = if *millis { now.as_millis() as u64 } else { now.as_secs() };
+                    encoding.encode(value, &mut out);
+                }
+                Part::Random(n) => random_bytes(*n, &mut out)?,
+                Part::Variable { name, encoding } => match self.variables.get(name) {
+                    None => return Err(format!("未定義的樣板變數: {}", name)),
+                    Some(TemplateValue::Bytes(bytes)) => {
+                        if encoding.is_some() { return Err(format!("變數 {} 是位元組，不能指定編碼", name)); }
+                        out.extend_from_slice(bytes);
+                    }
+                    Some(TemplateValue::Number(value)) => {
+                        let encoding = encoding.ok_or(format!("數值變數 {} 需要指定編碼，例如 {{{{{}:u16le}}}}", name, name))?;
+                        encoding.encode(*value, &mut out);
+                    }
+                },
+            }
+        }
+        for (name, next) in used {
+            self.counters.insert((path.to_string(), name), next);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(text: &str) -> Payload {
+        Payload::Template(PayloadTemplate::try_from(text.to_string()).unwrap())
+    }
+
+    #[test]
+    fn serializes_back_to_the_original_text() {
+        let text = "AA 01 {{counter:u16le}} {{random:4}}";
+        let payload: Payload = serde_json::from_str(&serde_json::to_string(text).unwrap()).unwrap();
+        assert_eq!(payload.describe(), text);
+        assert_eq!(serde_json::to_string(&payload).unwrap(), serde_json::to_string(text).unwrap());
+        let bytes: Payload = serde_json::from_str("[1, 2, 255]").unwrap();
+        assert_eq!(bytes.describe(), "01 02 FF");
+    }
+
+    #[test]
+    fn counters_are_per_device_and_wrap() {
+        let mut state = TemplateState::default();
+        let payload = template("AA{{counter:u8}} {{counter:u8}} {{counter.b:u16be}}");
+        assert_eq!(state.expand("dev1", &payload).unwrap(), vec![0xAA, 0, 0, 0, 0]);
+        assert_eq!(state.expand("dev1", &payload).unwrap(), vec![0xAA, 1, 1, 0, 1]);
+        assert_eq!(state.expand("dev2", &payload).unwrap(), vec![0xAA, 0, 0, 0, 0]);
+
+        state.counters.insert(("dev1".into(), String::new()), 0xFF);
+        assert_eq!(state.expand("dev1", &payload).unwrap()[1], 0xFF);
+        assert_eq!(state.expand("dev1", &payload).unwrap()[1], 0);
+
+        state.reset_counters(Some("dev1"));
+        assert!(state.counters().iter().all(|c| c.path == "dev2"));
+    }
+
+    #[test]
+    fn expands_variables_and_random() {
+        let mut state = TemplateState::default();
+        let payload = template("{{key}} {{n:u32be}} {{random:16}}");
+        assert!(state.expand("d", &payload).is_err());
+        state.set_variable("key", Some(TemplateValue::Bytes(vec![1, 2]))).unwrap();
+        state.set_variable("n", Some(TemplateValue::Number(0x0102_0304))).unwrap();
+        let out = state.expand("d", &payload).unwrap();
+        assert_eq!(out.len(), 2 + 4 + 16);
+        assert_eq!(out[..6], [1, 2, 1, 2, 3, 4]);
+        assert!(state.set_variable("counter:u8", None).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        for text in ["", "AA {{counter:u8", "ABC", "é1", "{{counter}}", "{{random:0}}", "{{random:1025}}", "{{a b}}", "{{x:u24}}"] {
+            assert!(PayloadTemplate::try_from(text.to_string()).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,266 @@
+// --- 自動化測試報告 (JUnit XML / HTML) ---
+// 指令序列與壓力測試的結果各記成一個 test suite，累積在記憶體中，匯出時一次輸出：
+//   junit  CI 系統可直接讀取的 JUnit XML (序列每一步為一個 testcase，壓力測試為單一 testcase)
+//   html   單一檔案的摘要頁面，可直接附在 CI artifact
+// 匯出後不會清除，需要時以 clear_test_results 重新開始。查詢以 paging.rs 的方式由新到舊分頁。
+
+use crate::paging::{Page, PageRequest, SeqLog};
+use crate::sequence::SequenceResult;
+use crate::stress::{FailureKind, StressResult};
+use chrono::TimeZone;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+// 保留的 suite 數量
+const MAX_SUITES: usize = 500;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TestReportFormat {
+    Junit,
+    Html,
+}
+
+#[derive(Serialize, Clone)]
+pub struct TestCase {
+    pub name: String,
+    pub elapsed_ms: u64,
+    // 失敗原因 (通過時為 None)
+    pub failure: Option<String>,
+    // 附加的明細，JUnit 中輸出為 system-out
+    pub output: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct TestSuite {
+    // "sequence" 或 "stress"
+    pub kind: &'static str,
+    pub name: String,
+    pub path: String,
+    pub started_ms: i64,
+    pub elapsed_ms: u64,
+    pub cancelled: bool,
+    pub cases: Vec<TestCase>,
+}
+
+impl TestSuite {
+    pub fn from_sequence(path: &str, result: &SequenceResult) -> Self {
+        let mut cases: Vec<TestCase> = result.steps.iter()
+            .map(|s| TestCase {
+                name: format!("{:04} {}", s.step, s.description),
+                elapsed_ms: s.elapsed_ms,
+                failure: s.error.clone(),
+                output: None,
+            })
+            .collect();
+        if result.steps.len() < result.steps_run {
+            let output = format!("共執行 {} 個步驟，只記錄前 {} 個", result.steps_run, result.steps.len());
+            cases.push(TestCase { name: "(truncated)".into(), elapsed_ms: 0, failure: None, output: Some(output) });
+        }
+        if result.cancelled {
+            cases.push(TestCase { name: "(cancelled)".into(), elapsed_ms: 0, failure: Some("序列被取消".into()), output: None });
+        }
+        Self {
+            kind: "sequence",
+            name: result.name.clone(),
+            path: path.to_string(),
+            started_ms: result.started_ms,
+            elapsed_ms: result.elapsed_ms,
+            cancelled: result.cancelled,
+            cases,
+        }
+    }
+
+    pub fn from_stress(path: &str, payload: &str, result: &StressResult) -> Self {
+        let r = result;
+        let failed = r.timeouts + r.mismatches + r.errors;
+        let mut failure = (failed > 0).then(|| {
+            format!("{} 次中 {} 次失敗 (逾時 {}、內容不符 {}、錯誤 {})", r.completed, failed, r.timeouts, r.mismatches, r.errors)
+        });
+        if r.cancelled && failure.is_none() {
+            failure = Some(format!("在 {} / {} 次時被取消", r.completed, r.iterations));
+        }
+        let us = |v: Option<u64>| v.map_or("-".to_string(), |v| v.to_string());
+        let mut output = format!(
+            "iterations={} completed={} successes={} rtt_us(min/avg/max)={}/{}/{}",
+            r.iterations, r.completed, r.successes, us(r.min_rtt_us), us(r.avg_rtt_us), us(r.max_rtt_us),
+        );
+        for f in &r.failures {
+            let kind = match f.kind {
+                FailureKind::Timeout => "timeout",
+                FailureKind::Mismatch => "mismatch",
+                FailureKind::Error => "error",
+            };
+            let _ = write!(output, "\n#{} {}", f.iteration, kind);
+            if let Some(response) = &f.response { let _ = write!(output, " {}", hex(response)); }
+            if let Some(error) = &f.error { let _ = write!(output, " {}", error); }
+        }
+        let name = format!("send {}", payload);
+        Self {
+            kind: "stress",
+            name: format!("stress {}", name),
+            path: path.to_string(),
+            started_ms: r.started_ms,
+            elapsed_ms: r.elapsed_ms,
+            cancelled: r.cancelled,
+            cases: vec![TestCase { name, elapsed_ms: r.elapsed_ms, failure, output: Some(output) }],
+        }
+    }
+
+    fn failures(&self) -> usize {
+        self.cases.iter().filter(|c| c.failure.is_some()).count()
+    }
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ")
+}
+
+pub struct TestResults {
+    suites: SeqLog<TestSuite>,
+}
+
+impl Default for TestResults {
+    fn default() -> Self {
+        Self { suites: SeqLog::new(MAX_SUITES) }
+    }
+}
+
+impl TestResults {
+    pub fn record(&mut self, suite: TestSuite) {
+        self.suites.push(suite);
+    }
+
+    // 由舊到新 (匯出用)
+    pub fn suites(&self) -> Vec<TestSuite> {
+        self.suites.iter().cloned().collect()
+    }
+
+    pub fn page(&self, page: PageRequest) -> Page<TestSuite> {
+        self.suites.page(page, |_| true)
+    }
+
+    pub fn clear(&mut self) {
+        self.suites.clear();
+    }
+}
+
+// --- 輸出 ---
+
+// XML 與 HTML 共用的跳脫
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            // XML 1.0 不允許的控制字元
+            c if (c as u32) < 0x20 && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn seconds(ms: u64) -> String {
+    format!("{:.3}", ms as f64 / 1000.0)
+}
+
+fn local_time(ms: i64, format: &str) -> String {
+    chrono::Local.timestamp_millis_opt(ms).single().map(|t| t.format(format).to_string()).unwrap_or_default()
+}
+
+pub fn to_junit(suites: &[TestSuite]) -> String {
+    let tests: usize = suites.iter().map(|s| s.cases.len()).sum();
+    let failures: usize = suites.iter().map(TestSuite::failures).sum();
+    let elapsed: u64 = suites.iter().map(|s| s.elapsed_ms).sum();
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(out, "<testsuites tests=\"{}\" failures=\"{}\" time=\"{}\">", tests, failures, seconds(elapsed));
+    for (id, suite) in suites.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "  <testsuite id=\"{}\" name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"0\" time=\"{}\" timestamp=\"{}\">",
+            id, escape(&suite.name), suite.cases.len(), suite.failures(), seconds(suite.elapsed_ms),
+            local_time(suite.started_ms, "%Y-%m-%dT%H:%M:%S"),
+        );
+        let _ = writeln!(out, "    <properties>");
+        let _ = writeln!(out, "      <property name=\"kind\" value=\"{}\"/>", suite.kind);
+        let _ = writeln!(out, "      <property name=\"path\" value=\"{}\"/>", escape(&suite.path));
+        let _ = writeln!(out, "      <property name=\"cancelled\" value=\"{}\"/>", suite.cancelled);
+        let _ = writeln!(out, "    </properties>");
+        let classname = format!("{}.{}", suite.kind, escape(&suite.name));
+        for case in &suite.cases {
+            let _ = write!(out, "    <testcase classname=\"{}\" name=\"{}\" time=\"{}\"", classname, escape(&case.name), seconds(case.elapsed_ms));
+            if case.failure.is_none() && case.output.is_none() {
+                out.push_str("/>\n");
+                continue;
+            }
+            out.push_str(">\n");
+            if let Some(failure) = &case.failure {
+                let _ = writeln!(out, "      <failure message=\"{}\">{}</failure>", escape(failure), escape(failure));
+            }
+            if let Some(output) = &case.output {
+                let _ = writeln!(out, "      <system-out>{}</system-out>", escape(output));
+            }
+            out.push_str("    </testcase>\n");
+        }
+        out.push_str("  </testsuite>\n");
+    }
+    out.push_str("</testsuites>\n");
+    out
+}
+
+pub fn to_html(suites: &[TestSuite]) -> String {
+    let tests: usize = suites.iter().map(|s| s.cases.len()).sum();
+    let failures: usize = suites.iter().map(TestSuite::failures).sum();
+    let mut out = String::from(concat!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Test report</title>\n<style>\n",
+        "body { font-family: sans-serif; margin: 2em; }\n",
+        "table { border-collapse: collapse; margin-bottom: 2em; width: 100%; }\n",
+        "th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; }\n",
+        ".pass { color: #1a7f37; } .fail { color: #cf222e; }\n",
+        "pre { margin: 0; white-space: pre-wrap; }\n",
+        "</style>\n</head>\n<body>\n",
+    ));
+    let _ = writeln!(
+        out,
+        "<h1>Test report</h1>\n<p>Generated {} &middot; {} suites &middot; {} tests &middot; <span class=\"{}\">{} failed</span></p>",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), suites.len(), tests,
+        if failures == 0 { "pass" } else { "fail" }, failures,
+    );
+    for suite in suites {
+        let (class, status) = if suite.failures() == 0 { ("pass", "PASS") } else { ("fail", "FAIL") };
+        let _ = writeln!(
+            out,
+            "<h2><span class=\"{}\">{}</span> {} <small>({})</small></h2>\n<p>{} &middot; {} &middot; {} ms{}</p>",
+            class, status, escape(&suite.name), suite.kind, escape(&suite.path),
+            local_time(suite.started_ms, "%Y-%m-%d %H:%M:%S"), suite.elapsed_ms,
+            if suite.cancelled { " &middot; cancelled" } else { "" },
+        );
+        out.push_str("<table>\n<tr><th>Case</th><th>Result</th><th>Time (ms)</th><th>Details</th></tr>\n");
+        for case in &suite.cases {
+            let (class, status) = if case.failure.is_none() { ("pass", "pass") } else { ("fail", "fail") };
+            let details = [case.failure.as_deref(), case.output.as_deref()].into_iter().flatten().collect::<Vec<_>>().join("\n");
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td><pre>{}</pre></td></tr>",
+                escape(&case.name), class, status, case.elapsed_ms, escape(&details),
+            );
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+pub fn write(file: &str, format: TestReportFormat, suites: &[TestSuite]) -> Result<(), String> {
+    if suites.is_empty() { return Err("沒有可匯出的測試結果".into()); }
+    let text = match format {
+        TestReportFormat::Junit => to_junit(suites),
+        TestReportFormat::Html => to_html(suites),
+    };
+    std::fs::write(file, text).map_err(|e| format!("寫入 {} 失敗: {}", file, e))
+}
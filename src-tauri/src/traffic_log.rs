@@ -0,0 +1,101 @@
+// --- 常駐流量記錄 ---
+// 啟用後不需要手動開始擷取，所有設備送出與收到的 report 持續寫入記錄目錄，檔案依大小或時間輪替，
+// 只保留最新的 max_files 個 (最多約 max_files x max_size 的磁碟空間)。每個記錄檔都是一般的擷取檔
+// (見 capture.rs)，可直接用 replay_session、export_capture_pcapng / export_capture_csv 處理。
+// 設定存在設定目錄的 traffic_log.json，重新啟動後自動繼續記錄，現場發生問題後再回頭取出記錄檔。
+//
+// 可設定過濾條件 (見 capture_filter.rs)，只記錄需要的 frame。
+//
+// { "enabled": true, "dir": null, "max_files": 20, "max_size": 10485760, "rotate_minutes": 60, "filter": "rx and id == 3" }
+
+use crate::capture::{self, Rotation};
+use crate::capture_filter::CaptureFilter;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+pub const DEFAULT_MAX_FILES: usize = 20;
+pub const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+const MIN_MAX_SIZE: u64 = 64 * 1024;
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct TrafficLogConfig {
+    pub enabled: bool,
+    // 未設定時為資料目錄下的 traffic/，相對路徑以資料目錄為基準
+    pub dir: Option<PathBuf>,
+    pub max_files: usize,
+    // 單一檔案的大小上限 (位元組)
+    pub max_size: u64,
+    // 檔案開啟超過此時間也換新檔 (None 只依大小輪替)
+    pub rotate_minutes: Option<u64>,
+    // None 記錄所有 frame
+    pub filter: Option<CaptureFilter>,
+}
+
+impl Default for TrafficLogConfig {
+    fn default() -> Self {
+        Self { enabled: false, dir: None, max_files: DEFAULT_MAX_FILES, max_size: DEFAULT_MAX_SIZE, rotate_minutes: None, filter: None }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct TrafficLogFile {
+    pub file: String,
+    pub size: u64,
+    pub modified_ms: i64,
+}
+
+impl TrafficLogConfig {
+    // 檔案不存在時為預設值 (關閉)
+    pub fn load(file: &Path) -> Result<Self, String> {
+        if !file.exists() { return Ok(Self::default()); }
+        let text = std::fs::read_to_string(file).map_err(|e| format!("讀取 {} 失敗: {}", file.display(), e))?;
+        let config: Self = serde_json::from_str(&text).map_err(|e| format!("流量記錄設定格式錯誤: {}", e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn save(&self, file: &Path) -> Result<(), String> {
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("建立目錄失敗: {}", e))?;
+        }
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(file, text).map_err(|e| format!("寫入 {} 失敗: {}", file.display(), e))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_files == 0 { return Err("max_files 至少為 1".into()); }
+        if self.max_size < MIN_MAX_SIZE { return Err(format!("max_size 至少為 {} 位元組", MIN_MAX_SIZE)); }
+        if self.rotate_minutes == Some(0) { return Err("rotate_minutes 至少為 1".into()); }
+        Ok(())
+    }
+
+    pub fn resolve_dir(&self, data_dir: &Path) -> PathBuf {
+        match &self.dir {
+            Some(dir) if dir.is_absolute() => dir.clone(),
+            Some(dir) => data_dir.join(dir),
+            None => data_dir.join("traffic"),
+        }
+    }
+
+    pub fn rotation(&self, data_dir: &Path) -> Rotation {
+        Rotation {
+            dir: self.resolve_dir(data_dir),
+            max_size: self.max_size,
+            max_age: self.rotate_minutes.map(|m| Duration::from_secs(m.saturating_mul(60))),
+            max_files: self.max_files,
+        }
+    }
+}
+
+// 由新到舊
+pub fn list_files(dir: &Path) -> Vec<TrafficLogFile> {
+    capture::traffic_files(dir).into_iter().rev()
+        .filter_map(|path| {
+            let meta = std::fs::metadata(&path).ok()?;
+            let modified_ms = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_millis() as i64;
+            Some(TrafficLogFile { file: path.to_string_lossy().to_string(), size: meta.len(), modified_ms })
+        })
+        .collect()
+}
@@ -0,0 +1,203 @@
+// --- 分段傳輸 (大於一個 report 的資料) ---
+// 把大筆資料切成帶序號的 chunk 依序送出，並把設備回傳的 chunk 重新組合。
+// 每個 chunk 的格式: [prefix][seq][total_len][chunk_len][data][checksum]，
+// 各欄位是否存在與寬度由 ChunkFormat 決定，checksum 涵蓋它之前的所有位元組。
+
+use crate::checksum::Checksum;
+use crate::onboard::ProgressFn;
+use crate::protocols::Transport;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+#[derive(Serialize, Clone)]
+pub struct TransferProgress {
+    pub path: String,
+    pub phase: &'static str,
+    pub current: usize,
+    // 接收時在收到總長度前為 0
+    pub total: usize,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct ChunkFormat {
+    // 每個 chunk 開頭的固定位元組 (例如指令碼)
+    pub prefix: Vec<u8>,
+    // 序號欄位寬度 (0 = 不含, 1 或 2)
+    pub seq_bytes: usize,
+    // 總長度欄位寬度 (0 = 不含, 2 或 4)
+    pub total_len_bytes: usize,
+    // 是否含本 chunk 的資料長度 (1 byte)
+    pub chunk_len: bool,
+    pub checksum: Checksum,
+    pub big_endian: bool,
+    // 送出每個 chunk 後等待設備確認 (回覆以這些位元組開頭)
+    pub ack: Option<Vec<u8>>,
+    // 等待確認或下一個 chunk 的時間
+    pub timeout_ms: u64,
+}
+
+impl Default for ChunkFormat {
+    fn default() -> Self {
+        Self {
+            prefix: Vec::new(),
+            seq_bytes: 1,
+            total_len_bytes: 2,
+            chunk_len: true,
+            checksum: Checksum::None,
+            big_endian: false,
+            ack: None,
+            timeout_ms: 1000,
+        }
+    }
+}
+
+struct Chunk<'a> {
+    seq: u32,
+    total: Option<usize>,
+    data: &'a [u8],
+}
+
+impl ChunkFormat {
+    fn header_len(&self) -> usize {
+        self.prefix.len() + self.seq_bytes + self.total_len_bytes + self.chunk_len as usize
+    }
+
+    fn validate(&self, report_len: usize) -> Result<usize, String> {
+        if !matches!(self.seq_bytes, 0..=2) { return Err("seq_bytes 只能是 0、1 或 2".into()); }
+        if !matches!(self.total_len_bytes, 0 | 2 | 4) { return Err("total_len_bytes 只能是 0、2 或 4".into()); }
+        let overhead = self.header_len() + self.checksum.width();
+        if overhead >= report_len {
+            return Err(format!("chunk 標頭 ({} bytes) 超過 report 長度 {}", overhead, report_len));
+        }
+        let per_chunk = report_len - overhead;
+        if self.chunk_len && per_chunk > 0xFF { return Ok(0xFF); }
+        Ok(per_chunk)
+    }
+
+    fn put(&self, out: &mut Vec<u8>, value: u32, width: usize) {
+        let bytes = if self.big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+        match (width, self.big_endian) {
+            (0, _) => {}
+            (_, true) => out.extend(&bytes[4 - width..]),
+            (_, false) => out.extend(&bytes[..width]),
+        }
+    }
+
+    fn get(&self, data: &[u8], width: usize) -> u32 {
+        let fold = |acc: u32, b: &u8| (acc << 8) | *b as u32;
+        if self.big_endian { data[..width].iter().fold(0, fold) } else { data[..width].iter().rev().fold(0, fold) }
+    }
+
+    pub fn encode(&self, payload: &[u8], report_len: usize) -> Result<Vec<Vec<u8>>, String> {
+        let per_chunk = self.validate(report_len)?;
+        let count = payload.len().div_ceil(per_chunk).max(1);
+        let seq_limit = if self.seq_bytes == 0 { usize::MAX } else { 1usize << (8 * self.seq_bytes) };
+        if count > seq_limit { return Err(format!("資料需要 {} 個 chunk，超過序號上限 {}", count, seq_limit)); }
+        if self.total_len_bytes == 2 && payload.len() > 0xFFFF {
+            return Err(format!("資料長度 {} 超過 total_len 欄位上限", payload.len()));
+        }
+
+        let chunks = (0..count).map(|seq| {
+            let data = &payload[std::cmp::min(seq * per_chunk, payload.len())..std::cmp::min((seq + 1) * per_chunk, payload.len())];
+            let mut chunk = self.prefix.clone();
+            self.put(&mut chunk, seq as u32, self.seq_bytes);
+            self.put(&mut chunk, payload.len() as u32, self.total_len_bytes);
+            if self.chunk_len { chunk.push(data.len() as u8); }
+            chunk.extend(data);
+            let crc = self.checksum.compute(&chunk);
+            self.put(&mut chunk, crc, self.checksum.width());
+            chunk
+        });
+        Ok(chunks.collect())
+    }
+
+    // 解析一個 chunk；prefix 不符時回傳 None
+    fn decode<'a>(&self, chunk: &'a [u8], per_chunk: usize) -> Result<Option<Chunk<'a>>, String> {
+        if !chunk.starts_with(&self.prefix) { return Ok(None); }
+        let header_len = self.header_len();
+        if chunk.len() < header_len + self.checksum.width() { return Err("chunk 長度不足".into()); }
+        let mut pos = self.prefix.len();
+        let seq = self.get(&chunk[pos..], self.seq_bytes);
+        pos += self.seq_bytes;
+        let total = (self.total_len_bytes > 0).then(|| self.get(&chunk[pos..], self.total_len_bytes) as usize);
+        pos += self.total_len_bytes;
+        let len = if self.chunk_len { chunk[pos] as usize } else { per_chunk };
+        if len > per_chunk || header_len + len + self.checksum.width() > chunk.len() {
+            return Err(format!("chunk {} 長度欄位錯誤", seq));
+        }
+
+        let end = header_len + len;
+        if self.checksum != Checksum::None {
+            let expected = self.checksum.compute(&chunk[..end]);
+            if self.get(&chunk[end..], self.checksum.width()) != expected {
+                return Err(format!("chunk {} checksum 錯誤", seq));
+            }
+        }
+        Ok(Some(Chunk { seq, total, data: &chunk[header_len..end] }))
+    }
+}
+
+// 在期限內讀取下一個封包
+fn next_packet(t: &dyn Transport, deadline: Instant) -> Result<Vec<u8>, String> {
+    let remaining = deadline.saturating_duration_since(Instant::now()).as_millis() as i32;
+    let resp = t.read(remaining)?;
+    if resp.is_empty() { return Err("等待 chunk 逾時".into()); }
+    Ok(resp)
+}
+
+// cancel 在每個 chunk 之前檢查，取消時回傳錯誤 (設備端只收到部分資料)
+pub fn send(t: &dyn Transport, format: &ChunkFormat, report_len: usize, payload: &[u8], cancel: &AtomicBool, progress: ProgressFn) -> Result<(), String> {
+    let chunks = format.encode(payload, report_len)?;
+    let mut sent = 0;
+    for (seq, chunk) in chunks.iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) { return Err(format!("已取消 (已送出 {} / {} 位元組)", sent, payload.len())); }
+        t.write(chunk)?;
+        if let Some(ack) = &format.ack {
+            // 確認之前收到的其他封包直接略過
+            let deadline = Instant::now() + Duration::from_millis(format.timeout_ms);
+            loop {
+                let resp = next_packet(t, deadline).map_err(|_| format!("chunk {} 沒有收到確認", seq))?;
+                if resp.starts_with(ack) { break; }
+            }
+        }
+        sent += chunk.len() - format.header_len() - format.checksum.width();
+        progress("send", sent, payload.len());
+    }
+    Ok(())
+}
+
+// 接收設備送來的 chunk 直到收齊總長度 (或收到未滿的最後一個 chunk)
+pub fn receive(t: &dyn Transport, format: &ChunkFormat, report_len: usize, cancel: &AtomicBool, progress: ProgressFn) -> Result<Vec<u8>, String> {
+    let per_chunk = format.validate(report_len)?;
+    if format.total_len_bytes == 0 && !format.chunk_len {
+        return Err("接收時需要 total_len 或 chunk_len 欄位才能判斷結尾".into());
+    }
+
+    let mut out = Vec::new();
+    let mut expected_seq = 0u32;
+    let mut total = None;
+    loop {
+        if cancel.load(Ordering::Relaxed) { return Err(format!("已取消 (已收到 {} 位元組)", out.len())); }
+        let deadline = Instant::now() + Duration::from_millis(format.timeout_ms);
+        let chunk = next_packet(t, deadline)?;
+        // prefix 不符的封包不是傳輸的一部分
+        let Some(Chunk { seq, total: chunk_total, data }) = format.decode(&chunk, per_chunk)? else { continue };
+        if seq != expected_seq {
+            return Err(format!("chunk 序號錯誤: 預期 {}，收到 {}", expected_seq, seq));
+        }
+        expected_seq = expected_seq.wrapping_add(1);
+        total = total.or(chunk_total);
+        out.extend(data);
+        progress("receive", out.len(), total.unwrap_or(0));
+
+        let done = match total {
+            Some(total) => out.len() >= total,
+            None => data.len() < per_chunk,
+        };
+        if done { break; }
+    }
+    if let Some(total) = total { out.truncate(total); }
+    Ok(out)
+}
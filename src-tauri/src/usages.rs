@@ -0,0 +1,166 @@
+// --- HID usage 名稱 ---
+// 常見 usage page 的名稱對照，用於把解碼後的欄位以可讀的名稱呈現。
+
+pub const PAGE_GENERIC_DESKTOP: u16 = 0x01;
+pub const PAGE_KEYBOARD: u16 = 0x07;
+pub const PAGE_LED: u16 = 0x08;
+pub const PAGE_BUTTON: u16 = 0x09;
+pub const PAGE_TELEPHONY: u16 = 0x0B;
+pub const PAGE_CONSUMER: u16 = 0x0C;
+
+pub fn page_name(page: u16) -> String {
+    match page {
+        PAGE_GENERIC_DESKTOP => "Generic Desktop".into(),
+        0x02 => "Simulation".into(),
+        0x05 => "Game Controls".into(),
+        0x06 => "Generic Device".into(),
+        PAGE_KEYBOARD => "Keyboard".into(),
+        PAGE_LED => "LED".into(),
+        PAGE_BUTTON => "Buttons".into(),
+        PAGE_TELEPHONY => "Telephony".into(),
+        PAGE_CONSUMER => "Consumer".into(),
+        0x0D => "Digitizer".into(),
+        0x20 => "Sensor".into(),
+        0x84 => "Power".into(),
+        0x85 => "Battery".into(),
+        0x8D => "Scale".into(),
+        0xFF00..=0xFFFF => format!("Vendor {:#06x}", page),
+        _ => format!("Page {:#06x}", page),
+    }
+}
+
+// Keyboard/Keypad page (0x07) 的按鍵名稱 (以 US 鍵盤印字為準)
+pub fn key_name(usage: u16) -> Option<String> {
+    const SYMBOLS: [&str; 19] = [
+        "Enter", "Escape", "Backspace", "Tab", "Space", "-", "=", "[", "]", "\\",
+        "Non-US #", ";", "'", "`", ",", ".", "/", "Caps Lock", "F1",
+    ];
+    const NAV: [&str; 13] = [
+        "Print Screen", "Scroll Lock", "Pause", "Insert", "Home", "Page Up", "Delete", "End", "Page Down",
+        "Right", "Left", "Down", "Up",
+    ];
+    const KEYPAD: [&str; 17] = [
+        "Num Lock", "Keypad /", "Keypad *", "Keypad -", "Keypad +", "Keypad Enter", "Keypad 1", "Keypad 2",
+        "Keypad 3", "Keypad 4", "Keypad 5", "Keypad 6", "Keypad 7", "Keypad 8", "Keypad 9", "Keypad 0", "Keypad .",
+    ];
+    const MODIFIERS: [&str; 8] = [
+        "Left Ctrl", "Left Shift", "Left Alt", "Left GUI", "Right Ctrl", "Right Shift", "Right Alt", "Right GUI",
+    ];
+    let name = match usage {
+        0x01 => "ErrorRollOver".into(),
+        0x02 => "POSTFail".into(),
+        0x03 => "ErrorUndefined".into(),
+        0x04..=0x1D => ((b'A' + (usage - 0x04) as u8) as char).to_string(),
+        0x1E..=0x26 => ((b'1' + (usage - 0x1E) as u8) as char).to_string(),
+        0x27 => "0".into(),
+        0x28..=0x3A => SYMBOLS[(usage - 0x28) as usize].into(),
+        0x3B..=0x45 => format!("F{}", usage - 0x3A + 1),
+        0x46..=0x52 => NAV[(usage - 0x46) as usize].into(),
+        0x53..=0x63 => KEYPAD[(usage - 0x53) as usize].into(),
+        0x64 => "Non-US \\".into(),
+        0x65 => "Application".into(),
+        0x66 => "Power".into(),
+        0x67 => "Keypad =".into(),
+        0x68..=0x73 => format!("F{}", usage - 0x68 + 13),
+        0x7F => "Mute".into(),
+        0x80 => "Volume Up".into(),
+        0x81 => "Volume Down".into(),
+        0x87..=0x8F => format!("International{}", usage - 0x87 + 1),
+        0x90..=0x98 => format!("LANG{}", usage - 0x90 + 1),
+        0xE0..=0xE7 => MODIFIERS[(usage - 0xE0) as usize].into(),
+        _ => return None,
+    };
+    Some(name)
+}
+
+pub fn usage_name(page: u16, usage: u16) -> String {
+    if page == PAGE_KEYBOARD {
+        if let Some(name) = key_name(usage) { return name; }
+    }
+    let known = match (page, usage) {
+        (PAGE_GENERIC_DESKTOP, 0x01) => "Pointer",
+        (PAGE_GENERIC_DESKTOP, 0x02) => "Mouse",
+        (PAGE_GENERIC_DESKTOP, 0x04) => "Joystick",
+        (PAGE_GENERIC_DESKTOP, 0x05) => "Gamepad",
+        (PAGE_GENERIC_DESKTOP, 0x06) => "Keyboard",
+        (PAGE_GENERIC_DESKTOP, 0x07) => "Keypad",
+        (PAGE_GENERIC_DESKTOP, 0x30) => "X",
+        (PAGE_GENERIC_DESKTOP, 0x31) => "Y",
+        (PAGE_GENERIC_DESKTOP, 0x32) => "Z",
+        (PAGE_GENERIC_DESKTOP, 0x33) => "Rx",
+        (PAGE_GENERIC_DESKTOP, 0x34) => "Ry",
+        (PAGE_GENERIC_DESKTOP, 0x35) => "Rz",
+        (PAGE_GENERIC_DESKTOP, 0x36) => "Slider",
+        (PAGE_GENERIC_DESKTOP, 0x37) => "Dial",
+        (PAGE_GENERIC_DESKTOP, 0x38) => "Wheel",
+        (PAGE_GENERIC_DESKTOP, 0x39) => "Hat Switch",
+        (PAGE_GENERIC_DESKTOP, 0x3D) => "Start",
+        (PAGE_GENERIC_DESKTOP, 0x3E) => "Select",
+        (PAGE_GENERIC_DESKTOP, 0x40) => "Vx",
+        (PAGE_GENERIC_DESKTOP, 0x41) => "Vy",
+        (PAGE_GENERIC_DESKTOP, 0x42) => "Vz",
+        (PAGE_GENERIC_DESKTOP, 0x48) => "Resolution Multiplier",
+        (PAGE_GENERIC_DESKTOP, 0x80) => "System Control",
+        (PAGE_GENERIC_DESKTOP, 0x81) => "System Power Down",
+        (PAGE_GENERIC_DESKTOP, 0x82) => "System Sleep",
+        (PAGE_GENERIC_DESKTOP, 0x83) => "System Wake Up",
+        (PAGE_GENERIC_DESKTOP, 0x90) => "D-pad Up",
+        (PAGE_GENERIC_DESKTOP, 0x91) => "D-pad Down",
+        (PAGE_GENERIC_DESKTOP, 0x92) => "D-pad Right",
+        (PAGE_GENERIC_DESKTOP, 0x93) => "D-pad Left",
+        (PAGE_LED, 0x01) => "Num Lock",
+        (PAGE_LED, 0x02) => "Caps Lock",
+        (PAGE_LED, 0x03) => "Scroll Lock",
+        (PAGE_LED, 0x04) => "Compose",
+        (PAGE_LED, 0x05) => "Kana",
+        (PAGE_LED, 0x09) => "Mute",
+        (PAGE_LED, 0x17) => "Off-Hook",
+        (PAGE_LED, 0x18) => "Ring",
+        (PAGE_LED, 0x20) => "Hold",
+        (PAGE_LED, 0x21) => "Microphone",
+        (PAGE_TELEPHONY, 0x20) => "Hook Switch",
+        (PAGE_TELEPHONY, 0x21) => "Flash",
+        (PAGE_TELEPHONY, 0x2F) => "Phone Mute",
+        (PAGE_CONSUMER, 0x01) => "Consumer Control",
+        (PAGE_CONSUMER, 0x30) => "Power",
+        (PAGE_CONSUMER, 0x40) => "Menu",
+        (PAGE_CONSUMER, 0x6F) => "Brightness Up",
+        (PAGE_CONSUMER, 0x70) => "Brightness Down",
+        (PAGE_CONSUMER, 0xB0) => "Play",
+        (PAGE_CONSUMER, 0xB1) => "Pause",
+        (PAGE_CONSUMER, 0xB5) => "Next Track",
+        (PAGE_CONSUMER, 0xB6) => "Previous Track",
+        (PAGE_CONSUMER, 0xB7) => "Stop",
+        (PAGE_CONSUMER, 0xCD) => "Play/Pause",
+        (PAGE_CONSUMER, 0xE2) => "Mute",
+        (PAGE_CONSUMER, 0xE9) => "Volume Up",
+        (PAGE_CONSUMER, 0xEA) => "Volume Down",
+        (PAGE_CONSUMER, 0x238) => "AC Pan",
+        (0x20, 0x0431) => "Atmospheric Pressure",
+        (0x20, 0x0433) => "Relative Humidity",
+        (0x20, 0x0434) => "Temperature",
+        (0x20, 0x0453) => "Acceleration X",
+        (0x20, 0x0454) => "Acceleration Y",
+        (0x20, 0x0455) => "Acceleration Z",
+        (0x20, 0x0457) => "Angular Velocity X",
+        (0x20, 0x0458) => "Angular Velocity Y",
+        (0x20, 0x0459) => "Angular Velocity Z",
+        (0x20, 0x0485) => "Magnetic Flux X",
+        (0x20, 0x0486) => "Magnetic Flux Y",
+        (0x20, 0x0487) => "Magnetic Flux Z",
+        (0x20, 0x04B1) => "Human Presence",
+        (0x20, 0x04B2) => "Human Proximity Range",
+        (0x20, 0x04D1) => "Illuminance",
+        (0x20, 0x04D2) => "Color Temperature",
+        (0x85, 0x65) => "Absolute State Of Charge",
+        (0x85, 0x66) => "Remaining Capacity",
+        (0x85, 0x44) => "Charging",
+        (0x85, 0x45) => "Discharging",
+        _ => "",
+    };
+    if !known.is_empty() { return known.to_string(); }
+    match page {
+        PAGE_BUTTON => format!("Button {}", usage),
+        _ => format!("{} {:#06x}", page_name(page), usage),
+    }
+}
@@ -0,0 +1,113 @@
+// --- 監看運算式 ---
+// 對解碼後的欄位設定條件 (例: "battery < 20"、"status != 0 && Buttons[0] == 1")，
+// 每筆 report 都重新計算，只有從不成立變成成立時才通知。
+//
+// 語法: <欄位>[索引] <運算子> <數值>，運算子為 < <= > >= == !=，
+// 數值可為十進位或 0x 開頭的十六進位，條件之間可用 && / || 連接 (&& 優先)。
+
+use crate::descriptor::{DecodedReport, FieldValue};
+use serde::Serialize;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Op { Lt, Le, Gt, Ge, Eq, Ne }
+
+#[derive(Clone, Debug)]
+struct Comparison {
+    field: String,
+    index: Option<usize>,
+    op: Op,
+    value: i64,
+}
+
+impl Comparison {
+    fn parse(text: &str) -> Result<Self, String> {
+        // 兩個字元的運算子要先比對
+        const OPS: [(&str, Op); 6] = [("<=", Op::Le), (">=", Op::Ge), ("==", Op::Eq), ("!=", Op::Ne), ("<", Op::Lt), (">", Op::Gt)];
+        let (pos, sym, op) = OPS.iter()
+            .filter_map(|(sym, op)| text.find(sym).map(|pos| (pos, *sym, *op)))
+            .min_by_key(|(pos, sym, _)| (*pos, std::cmp::Reverse(sym.len())))
+            .ok_or(format!("缺少比較運算子: {}", text))?;
+        let lhs = text[..pos].trim();
+        let rhs = text[pos + sym.len()..].trim();
+
+        let (field, index) = match lhs.strip_suffix(']').and_then(|l| l.split_once('[')) {
+            Some((name, i)) => (name.trim(), Some(i.trim().parse::<usize>().map_err(|_| format!("索引格式錯誤: {}", lhs))?)),
+            None => (lhs, None),
+        };
+        if field.is_empty() { return Err(format!("缺少欄位名稱: {}", text)); }
+        let value = match rhs.strip_prefix("0x").or(rhs.strip_prefix("0X")) {
+            Some(hex) => i64::from_str_radix(hex, 16),
+            None => rhs.parse::<i64>(),
+        }.map_err(|_| format!("數值格式錯誤: {}", rhs))?;
+        Ok(Self { field: field.to_string(), index, op, value })
+    }
+
+    // 欄位不在這筆 report 中時回傳 None
+    fn eval(&self, report: &DecodedReport) -> Option<bool> {
+        let actual = match (report.fields.get(&self.field)?, self.index) {
+            (FieldValue::Number(v), None | Some(0)) => *v,
+            (FieldValue::List(list), i) => *list.get(i.unwrap_or(0))?,
+            // Usages 欄位以觸發中的 usage 數量比較
+            (FieldValue::Usages(list), None) => list.len() as i64,
+            _ => return None,
+        };
+        Some(match self.op {
+            Op::Lt => actual < self.value,
+            Op::Le => actual <= self.value,
+            Op::Gt => actual > self.value,
+            Op::Ge => actual >= self.value,
+            Op::Eq => actual == self.value,
+            Op::Ne => actual != self.value,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Watch {
+    pub name: String,
+    pub expression: String,
+    // OR 連接的各組 AND 條件
+    clauses: Vec<Vec<Comparison>>,
+    active: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct WatchInfo {
+    pub name: String,
+    pub expression: String,
+    pub active: bool,
+}
+
+impl Watch {
+    pub fn parse(name: &str, expression: &str) -> Result<Self, String> {
+        let clauses = expression.split("||")
+            .map(|clause| clause.split("&&").map(Comparison::parse).collect::<Result<Vec<_>, _>>())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { name: name.to_string(), expression: expression.to_string(), clauses, active: false })
+    }
+
+    // 任一條件用到的欄位不在這筆 report 中就不計算 (維持原狀態)
+    fn eval(&self, report: &DecodedReport) -> Option<bool> {
+        let mut any = false;
+        for clause in &self.clauses {
+            let mut all = true;
+            for cmp in clause {
+                all &= cmp.eval(report)?;
+            }
+            any |= all;
+        }
+        Some(any)
+    }
+
+    // 從不成立變成成立時回傳 true
+    pub fn update(&mut self, report: &DecodedReport) -> bool {
+        let Some(now) = self.eval(report) else { return false };
+        let triggered = now && !self.active;
+        self.active = now;
+        triggered
+    }
+
+    pub fn info(&self) -> WatchInfo {
+        WatchInfo { name: self.name.clone(), expression: self.expression.clone(), active: self.active }
+    }
+}
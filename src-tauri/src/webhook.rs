@@ -0,0 +1,345 @@
+// --- 設備插拔通報 (webhook) ---
+// 定期比對 HID 設備清單，把插入 / 拔除事件連同設備識別資訊以 JSON POST 到指定網址，
+// 讓實驗室的資產管理系統即時知道哪些待測設備接在哪一台工作站。
+// 只支援 http:// (不含 TLS)，https:// 網址直接拒絕；需要 https 時請在本機或區網架設轉送服務。
+// 送出失敗的事件留在佇列中，之後依序重送；連續失敗時重送間隔加倍 (最長 5 分鐘)，成功後恢復。
+// 佇列已滿時捨棄最舊的事件，也可以手動清空 (clear_pending)。
+// 設定儲存在設定目錄的 webhook.json，啟動時自動恢復。
+//
+// POST 內容：
+//   { "event": "attached" | "detached", "station": "...", "timestamp_ms": 0, "initial": false,
+//     "path": "...", "vendor_id": 0, "product_id": 0, "serial_number": "...", ... }
+// initial 為 true 代表啟用通報時已經接著的設備 (不是剛插入)
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// 等待重送的事件上限
+pub const MAX_PENDING: usize = 1000;
+// 第一次失敗後的重送間隔，之後每次失敗加倍
+const RETRY_BASE: Duration = Duration::from_secs(2);
+const RETRY_MAX: Duration = Duration::from_secs(300);
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct WebhookConfig {
+    pub url: String,
+    // 工作站名稱，未指定時使用電腦名稱
+    #[serde(default)]
+    pub station: Option<String>,
+    // 額外的 HTTP header (例如 Authorization)
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_timeout_ms() -> u64 { 3000 }
+
+// 沒有設定檔時為 None (未啟用)
+pub fn load_config(file: &Path) -> Result<Option<WebhookConfig>, String> {
+    if !file.exists() { return Ok(None); }
+    let text = std::fs::read_to_string(file).map_err(|e| format!("讀取 {} 失敗: {}", file.display(), e))?;
+    serde_json::from_str(&text).map(Some).map_err(|e| format!("webhook 設定格式錯誤: {}", e))
+}
+
+// None 時刪除設定檔
+pub fn save_config(file: &Path, config: Option<&WebhookConfig>) -> Result<(), String> {
+    let Some(config) = config else {
+        if file.exists() {
+            std::fs::remove_file(file).map_err(|e| format!("刪除 {} 失敗: {}", file.display(), e))?;
+        }
+        return Ok(());
+    };
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("建立目錄失敗: {}", e))?;
+    }
+    let text = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    std::fs::write(file, text).map_err(|e| format!("寫入 {} 失敗: {}", file.display(), e))
+}
+
+#[derive(Serialize, Clone, PartialEq)]
+pub struct DeviceIdentityReport {
+    pub path: String,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub serial_number: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub release_number: u16,
+    pub usage_page: u16,
+    pub usage: u16,
+    pub interface_number: i32,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceEventKind {
+    Attached,
+    Detached,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DeviceEventReport {
+    pub event: DeviceEventKind,
+    pub station: String,
+    pub timestamp_ms: u64,
+    pub initial: bool,
+    #[serde(flatten)]
+    pub device: DeviceIdentityReport,
+}
+
+#[derive(Serialize, Clone)]
+pub struct WebhookStatus {
+    pub url: String,
+    pub station: String,
+    pub sent: u64,
+    pub failed: u64,
+    // 最近一次失敗的原因
+    pub last_error: Option<String>,
+    // 等待重送的事件數
+    pub pending: usize,
+    // 佇列已滿而捨棄的事件數
+    pub dropped: u64,
+    // 連續失敗次數
+    pub consecutive_failures: u32,
+    // 距離下一次重送的時間；沒有在等待時為 None
+    pub retry_in_ms: Option<u64>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn default_station() -> String {
+    ["COMPUTERNAME", "HOSTNAME"].iter()
+        .find_map(|k| std::env::var(k).ok().filter(|v| !v.is_empty()))
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok().map(|s| s.trim().to_string()))
+        .filter(|s| !s.is_empty())
+        .unwrap_or("unknown".into())
+}
+
+// http://host[:port][/path] 拆成 (host, port, path)
+fn parse_url(url: &str) -> Result<(String, u16, String), String> {
+    if url.get(..8).is_some_and(|s| s.eq_ignore_ascii_case("https://")) {
+        return Err(format!("不支援 https (沒有 TLS)，請改用 http:// 或經由本機轉送服務: {}", url));
+    }
+    let rest = url.strip_prefix("http://").ok_or(format!("只支援 http:// 網址: {}", url))?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() { return Err(format!("網址缺少主機名稱: {}", url)); }
+    let (host, port) = match authority.rsplit_once(':') {
+        // IPv6 位址 [::1] 本身含有 ':'
+        Some((host, port)) if !port.contains(']') => {
+            (host, port.parse::<u16>().map_err(|_| format!("無效的連接埠: {}", port))?)
+        }
+        _ => (authority, 80),
+    };
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+pub struct DeviceWebhook {
+    config: WebhookConfig,
+    station: String,
+    host: String,
+    port: u16,
+    path: String,
+    // 上一次輪詢時的設備；None 代表尚未取得第一份清單
+    known: Option<HashMap<String, DeviceIdentityReport>>,
+    // 等待送出 (或重送) 的事件，依發生順序
+    pending: VecDeque<DeviceEventReport>,
+    sent: u64,
+    failed: u64,
+    dropped: u64,
+    last_error: Option<String>,
+    consecutive_failures: u32,
+    // 連續失敗時，在這之前不送出
+    retry_at: Option<Instant>,
+}
+
+impl DeviceWebhook {
+    pub fn new(config: WebhookConfig) -> Result<Self, String> {
+        let (host, port, path) = parse_url(&config.url)?;
+        if config.headers.iter().any(|(k, v)| k.contains([':', '\r', '\n']) || v.contains(['\r', '\n'])) {
+            return Err("header 名稱或內容含有無效字元".into());
+        }
+        let station = config.station.clone().filter(|s| !s.is_empty()).unwrap_or_else(default_station);
+        Ok(Self {
+            config,
+            station,
+            host,
+            port,
+            path,
+            known: None,
+            pending: VecDeque::new(),
+            sent: 0,
+            failed: 0,
+            dropped: 0,
+            last_error: None,
+            consecutive_failures: 0,
+            retry_at: None,
+        })
+    }
+
+    pub fn status(&self) -> WebhookStatus {
+        WebhookStatus {
+            url: self.config.url.clone(),
+            station: self.station.clone(),
+            sent: self.sent,
+            failed: self.failed,
+            last_error: self.last_error.clone(),
+            pending: self.pending.len(),
+            dropped: self.dropped,
+            consecutive_failures: self.consecutive_failures,
+            retry_in_ms: self.retry_at
+                .map(|at| at.saturating_duration_since(Instant::now()).as_millis() as u64)
+                .filter(|ms| *ms > 0),
+        }
+    }
+
+    // 捨棄所有等待送出的事件 (例如接收端長時間無法使用)，回傳捨棄的筆數；重送間隔一併恢復
+    pub fn clear_pending(&mut self) -> usize {
+        let count = self.pending.len();
+        self.pending.clear();
+        self.dropped += count as u64;
+        self.consecutive_failures = 0;
+        self.retry_at = None;
+        count
+    }
+
+    // 與上一份清單比對，需要通報的事件加入佇列；同一設備屬性改變 (例如重新列舉) 視為拔除後再插入
+    pub fn diff(&mut self, devices: Vec<DeviceIdentityReport>) {
+        let current: HashMap<String, DeviceIdentityReport> = devices.into_iter().map(|d| (d.path.clone(), d)).collect();
+        let timestamp_ms = now_ms();
+        let event = |event, initial, device: &DeviceIdentityReport| DeviceEventReport {
+            event,
+            station: self.station.clone(),
+            timestamp_ms,
+            initial,
+            device: device.clone(),
+        };
+        let mut events = Vec::new();
+        match &self.known {
+            None => events.extend(current.values().map(|d| event(DeviceEventKind::Attached, true, d))),
+            Some(known) => {
+                for (path, old) in known {
+                    if current.get(path) != Some(old) { events.push(event(DeviceEventKind::Detached, false, old)); }
+                }
+                for (path, new) in &current {
+                    if known.get(path) != Some(new) { events.push(event(DeviceEventKind::Attached, false, new)); }
+                }
+            }
+        }
+        self.known = Some(current);
+        for event in events {
+            if self.pending.len() >= MAX_PENDING {
+                self.pending.pop_front();
+                self.dropped += 1;
+            }
+            self.pending.push_back(event);
+        }
+    }
+
+    // 取出下一個要送出的事件，以及送出所需的資料，讓呼叫端可以在不持有鎖的情況下送出；
+    // 失敗後的重送間隔未到時回傳 None
+    pub fn next(&mut self) -> Option<(DeviceEventReport, WebhookTarget)> {
+        if self.retry_at.is_some_and(|at| Instant::now() < at) { return None; }
+        let event = self.pending.pop_front()?;
+        Some((event, self.target()))
+    }
+
+    fn target(&self) -> WebhookTarget {
+        WebhookTarget {
+            host: self.host.clone(),
+            port: self.port,
+            path: self.path.clone(),
+            headers: self.config.headers.clone(),
+            timeout: Duration::from_millis(self.config.timeout_ms.max(1)),
+        }
+    }
+
+    // 送出失敗時事件放回佇列最前面，回傳 false 代表這一輪應停止送出
+    pub fn record(&mut self, event: DeviceEventReport, result: Result<(), String>) -> bool {
+        match result {
+            Ok(()) => {
+                self.sent += 1;
+                self.consecutive_failures = 0;
+                self.retry_at = None;
+                true
+            }
+            Err(e) => {
+                self.failed += 1;
+                self.last_error = Some(e);
+                self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+                let backoff = RETRY_BASE.saturating_mul(1 << (self.consecutive_failures - 1).min(16)).min(RETRY_MAX);
+                self.retry_at = Some(Instant::now() + backoff);
+                if self.pending.len() < MAX_PENDING {
+                    self.pending.push_front(event);
+                } else {
+                    self.dropped += 1;
+                }
+                false
+            }
+        }
+    }
+}
+
+pub struct WebhookTarget {
+    host: String,
+    port: u16,
+    path: String,
+    headers: Vec<(String, String)>,
+    timeout: Duration,
+}
+
+impl WebhookTarget {
+    // 不帶額外 header 的目標 (例如使用統計的上傳網址)
+    pub fn from_url(url: &str, timeout: Duration) -> Result<Self, String> {
+        let (host, port, path) = parse_url(url)?;
+        Ok(Self { host, port, path, headers: Vec::new(), timeout })
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn post(&self, body: &impl Serialize) -> Result<(), String> {
+        let body = serde_json::to_vec(body).map_err(|e| e.to_string())?;
+        let addr = (self.host.trim_start_matches('[').trim_end_matches(']'), self.port).to_socket_addrs()
+            .map_err(|e| format!("無法解析 {}: {}", self.host, e))?
+            .next()
+            .ok_or(format!("無法解析 {}", self.host))?;
+        let mut stream = TcpStream::connect_timeout(&addr, self.timeout).map_err(|e| format!("連線到 {} 失敗: {}", addr, e))?;
+        stream.set_read_timeout(Some(self.timeout)).map_err(|e| e.to_string())?;
+        stream.set_write_timeout(Some(self.timeout)).map_err(|e| e.to_string())?;
+
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.path, self.host, self.port, body.len(),
+        );
+        for (name, value) in &self.headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        let mut data = request.into_bytes();
+        data.extend(body);
+        stream.write_all(&data).map_err(|e| format!("送出失敗: {}", e))?;
+
+        // 只需要狀態列 "HTTP/1.1 200 OK"
+        let mut head = [0u8; 64];
+        let n = stream.read(&mut head).map_err(|e| format!("讀取回應失敗: {}", e))?;
+        let line = String::from_utf8_lossy(&head[..n]);
+        let status = line.split_whitespace().nth(1).and_then(|s| s.parse::<u16>().ok())
+            .ok_or("無效的 HTTP 回應")?;
+        if !(200..300).contains(&status) {
+            return Err(format!("伺服器回應 HTTP {}", status));
+        }
+        Ok(())
+    }
+}
@@ -21,7 +21,9 @@ let unlistenHid: UnlistenFn | null = null;
 const activeListeners = new Set<string>();
 
 // --- 2. 跨平台相容的時間日誌 ---
-function addLog(message: string, type: 'info' | 'incoming' | 'outgoing' | 'error' = 'info') {
+type LogType = 'debug' | 'info' | 'incoming' | 'outgoing' | 'warning' | 'error';
+
+function addLog(message: string, type: LogType = 'info') {
   const logDiv = document.querySelector('#log') as HTMLElement;
   if (!logDiv) return;
 
@@ -30,9 +32,11 @@ function addLog(message: string, type: 'info' | 'incoming' | 'outgoing' | 'error
   const time = `${d.getHours().toString().padStart(2, '0')}:${d.getMinutes().toString().padStart(2, '0')}:${d.getSeconds().toString().padStart(2, '0')}.${ms}`;
 
   const colors = { 
+    debug: '#555',
     info: '#888', 
     incoming: '#00ff00', 
     outgoing: '#00bfff', 
+    warning: '#faad14',
     error: '#ff4d4f' 
   };
 
@@ -46,14 +50,117 @@ function addLog(message: string, type: 'info' | 'incoming' | 'outgoing' | 'error
 }
 
 // --- 3. 初始化監聽 (Async Event) ---
+// 設備使用 Report ID 時 report_id 已從 data 中移除；start_listening 帶 legacy_payload 時仍是 number[]
+interface HidDataEvent {
+  path: string;
+  report_id: number | null;
+  seq: number;
+  timestamp: { mono_us?: number; wall_us?: number };
+  data: number[];
+}
+
 async function initEventListener() {
   if (unlistenHid) unlistenHid();
-  unlistenHid = await listen<number[]>("hid-data", (event) => {
-    const hex = event.payload.map(b => b.toString(16).toUpperCase().padStart(2, '0')).join(' ');
-    addLog(`[ASYNC IN] ${hex}`, 'incoming');
+  unlistenHid = await listen<HidDataEvent | number[]>("hid-data", (event) => {
+    const toHex = (bytes: number[]) => bytes.map(b => b.toString(16).toUpperCase().padStart(2, '0')).join(' ');
+    if (Array.isArray(event.payload)) {
+      addLog(`[ASYNC IN] ${toHex(event.payload)}`, 'incoming');
+      return;
+    }
+    const { path, report_id, seq, data } = event.payload;
+    const id = report_id === null ? '' : ` ID ${report_id.toString(16).toUpperCase().padStart(2, '0')}`;
+    addLog(`[ASYNC IN #${seq}] ${path}${id}: ${toHex(data)}`, 'incoming');
+  });
+}
+
+// --- 設備連線狀態 (device-state) ---
+// 後端在每次狀態轉換時送出，監聽標記以此為準，不需要從有沒有收到資料推測
+type ConnectionState = 'opened' | 'listening' | 'paused' | 'closed' | 'disconnected';
+
+interface DeviceStateEvent {
+  path: string;
+  state: ConnectionState;
+  previous: ConnectionState | null;
+  timestamp_ms: number;
+}
+
+async function initDeviceState() {
+  await listen<DeviceStateEvent>("device-state", (event) => {
+    const { path, state } = event.payload;
+    if (state === 'closed' || state === 'disconnected') {
+      activeListeners.delete(path);
+    } else {
+      activeListeners.add(path);
+    }
+    addLog(`[STATE] ${path}: ${state.toUpperCase()}`, state === 'disconnected' ? 'warning' : 'debug');
+  });
+}
+
+// --- 監聽異常結束 (device-error) ---
+// 讀取失敗或拔除設備時後端已移除該設備，清除監聽標記，下次發送時重新 start_listening
+interface DeviceErrorEvent {
+  path: string;
+  code: 'disconnected' | 'access_denied' | 'read_failed' | 'listener_panic';
+  os_error: string;
+  os_code: number | null;
+  timestamp_ms: number;
+}
+
+async function initDeviceErrors() {
+  await listen<DeviceErrorEvent>("device-error", (event) => {
+    const { path, code, os_error, os_code } = event.payload;
+    activeListeners.delete(path);
+    const detail = os_code !== null ? `${os_error} (code ${os_code})` : os_error;
+    addLog(`[DEVICE ERROR] ${code.toUpperCase()} ${path}: ${detail}`, 'error');
   });
 }
 
+// --- 後端記錄 (app-log) ---
+// 嚴重程度決定顏色，類別與設備顯示在訊息前；最低嚴重程度由後端的 set_log_levels 控制
+interface AppLogEvent {
+  timestamp_ms: number;
+  severity: 'debug' | 'info' | 'warning' | 'error';
+  category: string;
+  device: string | null;
+  message: string;
+}
+
+async function initAppLog() {
+  await listen<AppLogEvent>("app-log", (event) => {
+    const { severity, category, device, message } = event.payload;
+    const source = device ? `${category.toUpperCase()} ${device}` : category.toUpperCase();
+    addLog(`[${source}] ${message}`, severity);
+  });
+}
+
+// --- 常用設備健康檢查 ---
+interface HealthResult {
+  name: string;
+  health: 'green' | 'yellow' | 'red';
+  firmware_version: string | null;
+  message: string | null;
+}
+
+interface HealthReport {
+  overall: 'green' | 'yellow' | 'red';
+  results: HealthResult[];
+}
+
+function showHealthReport(report: HealthReport) {
+  addLog(`[HEALTH] Bench: ${report.overall.toUpperCase()}`, report.overall === 'red' ? 'error' : 'info');
+  for (const r of report.results) {
+    const detail = r.message ?? (r.firmware_version ? `FW ${r.firmware_version}` : '');
+    addLog(`[HEALTH] ${r.health.toUpperCase()} ${r.name} ${detail}`, r.health === 'red' ? 'error' : 'info');
+  }
+}
+
+// 啟動時的檢查可能在監聽建立前就完成，先顯示最近一次的結果
+async function initHealthCheck() {
+  await listen<HealthReport>("health-check", (event) => showHealthReport(event.payload));
+  const last = await invoke<HealthReport | null>("get_last_health_check");
+  if (last) showHealthReport(last);
+}
+
 // --- 4. UI 與主邏輯 ---
 async function startApp() {
   const app = document.querySelector<HTMLDivElement>('#app');
@@ -104,6 +211,10 @@ async function startApp() {
   const clearLog = document.getElementById('clearLog') as HTMLElement;
 
   await initEventListener();
+  await initAppLog();
+  await initDeviceState();
+  await initDeviceErrors();
+  await initHealthCheck();
   clearLog.onclick = () => { document.getElementById('log')!.innerHTML = ''; };
 
   // --- 列表渲染 (優化跨平台顯示) ---
@@ -187,8 +298,13 @@ async function startApp() {
         addLog(`[RESULT] ${respHex}`, 'info');
       }
 
-    } catch (e) {
-      addLog(`Communication Failure: ${e}`, 'error');
+    } catch (e: any) {
+      // send_hid_command 的錯誤為 { kind, message }；逾時只代表設備沒有回覆，監聽仍然有效
+      if (e?.kind === 'timeout') {
+        addLog(`[TIMEOUT] ${e.message}`, 'error');
+        return;
+      }
+      addLog(`Communication Failure: ${e?.message ?? e}`, 'error');
       // 如果出錯，清除監聽標記以便重試
       activeListeners.delete(selectedDevicePath);
     }